@@ -1,3 +1,16 @@
 fn main() {
+    // Embed the commit hash for get_backend_info; source tarballs without .git
+    // just report "unknown".
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/index");
+    let hash = std::process::Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=CACHEPHOENIX_GIT_HASH={}", hash);
+
     tauri_build::build()
 }
@@ -0,0 +1,234 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{AesMode, CompressionMethod, ZipWriter};
+
+use crate::output_file::OutputFile;
+
+// ---------------------------------------------------------------------------
+// ZIP export
+// ---------------------------------------------------------------------------
+//
+// Bundles recovered files into one ZIP archive, optionally AES-256 encrypted
+// with a passphrase supplied by the caller (WinZip AE-2: opens in 7-Zip,
+// WinZip or Keka, but not in the extractors built into Windows and macOS).
+// The zip writer needs to seek, so the archive is built in the temp directory
+// and then streamed to the destination through `OutputFile`, which keeps
+// cloud-folder handling and the no-torn-files guarantee. A source that fails
+// to read part-way (a file on a drive that went away) is dropped from the
+// archive and reported like one that never opened. The passphrase is never
+// logged or stored.
+
+/// Already-compressed formats: stored as-is instead of deflated.
+const STORED_EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "m4a", "mov", "webm", "mkv", "avi", "ogv", "ogg", "opus", "mp3", "aac", "flac",
+    "jpg", "jpeg", "png", "gif", "webp", "avif", "heic", "zip",
+];
+/// Files larger than this need ZIP64 records.
+const ZIP64_THRESHOLD: u64 = 0xFFFF_0000;
+const COPY_CHUNK: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveExportResult {
+    pub output: String,
+    pub files: usize,
+    /// Total size of the source files
+    pub bytes_in: u64,
+    /// Size of the finished archive
+    pub bytes_written: u64,
+    /// "none" or "aes256"
+    pub encryption: &'static str,
+    /// Sources that could not be added, with the reason
+    pub errors: Vec<String>,
+}
+
+/// Entry name for `path`, made unique within the archive ("clip (2).mp4").
+fn entry_name(path: &Path, used: &mut HashSet<String>) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string());
+    if used.insert(name.to_lowercase()) {
+        return name;
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot..]),
+        _ => (name.as_str(), ""),
+    };
+    (2..)
+        .map(|n| format!("{} ({}){}", stem, n, ext))
+        .find(|candidate| used.insert(candidate.to_lowercase()))
+        .unwrap_or(name.clone())
+}
+
+fn compression_for(name: &str) -> CompressionMethod {
+    let ext = name.rsplit('.').next().unwrap_or("").to_lowercase();
+    if STORED_EXTENSIONS.contains(&ext.as_str()) {
+        CompressionMethod::Stored
+    } else {
+        CompressionMethod::Deflated
+    }
+}
+
+/// Write the archive to `zip_path`. Returns (files added, source bytes, errors).
+fn build_archive(
+    paths: &[String],
+    zip_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(usize, u64, Vec<String>), String> {
+    let file = File::create(zip_path)
+        .map_err(|e| format!("Failed to create {}: {}", zip_path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let mut used = HashSet::new();
+    let mut errors = Vec::new();
+    let mut files = 0usize;
+    let mut bytes_in = 0u64;
+
+    for src in paths {
+        let mut input = match File::open(src) {
+            Ok(f) => f,
+            Err(e) => {
+                errors.push(crate::format_read_error(src, &e));
+                continue;
+            }
+        };
+        let size = input.metadata().map(|m| m.len()).unwrap_or(0);
+        let name = entry_name(Path::new(src), &mut used);
+        let options = SimpleFileOptions::default()
+            .compression_method(compression_for(&name))
+            .large_file(size >= ZIP64_THRESHOLD);
+        let started = match passphrase {
+            Some(pass) => zip.start_file(name.as_str(), options.with_aes_encryption(AesMode::Aes256, pass)),
+            None => zip.start_file(name.as_str(), options),
+        };
+        started.map_err(|e| format!("Failed to add {} to archive: {}", name, e))?;
+
+        let mut buf = vec![0u8; COPY_CHUNK.min(size.max(1) as usize)];
+        let copied = loop {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(n) => n,
+                Err(e) => break Err(crate::format_read_error(src, &e)),
+            };
+            std::io::Write::write_all(&mut zip, &buf[..n])
+                .map_err(|e| format!("Failed to write archive entry {}: {}", name, e))?;
+        };
+        if let Err(e) = copied {
+            zip.abort_file().map_err(|e| format!("Failed to drop {} from archive: {}", name, e))?;
+            used.remove(&name.to_lowercase());
+            errors.push(e);
+            continue;
+        }
+        files += 1;
+        bytes_in += size;
+    }
+    zip.finish().map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok((files, bytes_in, errors))
+}
+
+/// Stream `src` into `dst` through `OutputFile`.
+fn publish(src: &Path, dst: &str) -> Result<u64, String> {
+    let mut input = File::open(src).map_err(|e| format!("Failed to open {}: {}", src.display(), e))?;
+    let mut out = OutputFile::create(dst)?;
+    let mut buf = vec![0u8; COPY_CHUNK];
+    loop {
+        let n = input
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read {}: {}", src.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        out.write_all(&buf[..n])?;
+    }
+    out.finish()
+}
+
+/// Pack `paths` into a ZIP archive at `output`. With a `passphrase`, every
+/// entry is AES-256 encrypted. Unreadable sources are reported, not fatal.
+/// Requires a `consent` token covering `output`.
+#[tauri::command(async)]
+pub fn export_zip(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    paths: Vec<String>,
+    output: String,
+    passphrase: Option<String>,
+    consent: String,
+) -> Result<ArchiveExportResult, String> {
+    let output = state.check(&consent, &output)?;
+    export_archive(paths, output, passphrase)
+}
+
+fn export_archive(
+    paths: Vec<String>,
+    output: String,
+    passphrase: Option<String>,
+) -> Result<ArchiveExportResult, String> {
+    let _job = crate::crash::job(format!("exporting ZIP {}", output));
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    if paths.is_empty() {
+        return Err("No files to export".to_string());
+    }
+    let tmp = std::env::temp_dir().join(format!(
+        "cachephoenix-export-{}-{}.zip",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+    ));
+    let result = build_archive(&paths, &tmp, passphrase.as_deref())
+        .and_then(|built| publish(&tmp, &output).map(|written| (built, written)));
+    let _ = std::fs::remove_file(&tmp);
+    let ((files, bytes_in, errors), bytes_written) = result?;
+
+    let encryption = if passphrase.is_some() { "aes256" } else { "none" };
+    println!(
+        "[export_zip] {} files ({} bytes) -> {} ({} bytes, encryption: {}), {} errors",
+        files, bytes_in, output, bytes_written, encryption, errors.len()
+    );
+    Ok(ArchiveExportResult { output, files, bytes_in, bytes_written, encryption, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_archive_needs_passphrase() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-zip-unit-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        let first = dir.join("a").join("clip.mp4");
+        let second = dir.join("b").join("clip.mp4");
+        std::fs::write(&first, b"first body").unwrap();
+        std::fs::write(&second, b"second body").unwrap();
+        let output = dir.join("out").join("recovered.zip");
+
+        // A directory opens but fails on the first read, like a file whose
+        // drive went away mid-copy.
+        let unreadable = dir.join("a").to_string_lossy().to_string();
+
+        let result = export_archive(
+            vec![first.to_string_lossy().to_string(), unreadable, second.to_string_lossy().to_string()],
+            output.to_string_lossy().to_string(),
+            Some("correct horse".to_string()),
+        )
+        .unwrap();
+        assert_eq!((result.files, result.errors.len(), result.encryption), (2, 1, "aes256"));
+
+        let mut archive = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert!(archive.by_name("clip.mp4").is_err());
+        let mut body = String::new();
+        archive
+            .by_name_decrypt("clip (2).mp4", b"correct horse")
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "second body");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
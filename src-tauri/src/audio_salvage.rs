@@ -0,0 +1,253 @@
+use serde::Serialize;
+
+use crate::moov_rebuild::{mp4_box, rebuild_children, table_box, timescale, with_duration};
+use crate::mp4_samples::{child, children, sample_deltas, track_samples};
+
+// ─── Audio-only salvage ─────────────────────────────────────────────────────
+//
+// When the cache lost enough of an mdat that the video track stops after a
+// few seconds, the audio is often still complete: its samples are a few
+// hundred bytes each and interleaved throughout the file, so a missing chunk
+// costs a fraction of a second of sound but whole GOPs of picture. The moov's
+// sample tables locate every audio sample; the present ones are copied into
+// a fresh M4A (ftyp, moov with only the audio trak, mdat) and missing ones are
+// left out, so the sound plays through with small skips where data was lost.
+
+/// Brands of the M4A written.
+const M4A_FTYP: &[u8] = b"M4A \0\0\0\0M4A mp42isom";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioSalvage {
+    /// Sample entry type, e.g. "mp4a" or "Opus"
+    pub codec: String,
+    /// Audio samples written
+    pub samples: usize,
+    /// Audio samples missing from the source, left out
+    pub dropped_samples: usize,
+    pub duration_secs: f64,
+    /// Playing time of the dropped samples
+    pub dropped_secs: f64,
+    pub bytes_written: u64,
+}
+
+/// Run-length (count, delta) pairs of `deltas`, flattened for an stts box.
+fn stts_entries(deltas: &[u32]) -> Vec<u32> {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &delta in deltas {
+        match runs.last_mut() {
+            Some((n, d)) if *d == delta => *n += 1,
+            _ => runs.push((1, delta)),
+        }
+    }
+    let mut entries = vec![runs.len() as u32];
+    entries.extend(runs.into_iter().flat_map(|(n, d)| [n, d]));
+    entries
+}
+
+/// Copy the present samples of the first audio track in `data` (a whole MP4)
+/// into a new M4A.
+pub fn salvage(data: &[u8]) -> Result<(Vec<u8>, AudioSalvage), String> {
+    let moov = crate::mp4_samples::find_moov(data).ok_or("No moov found; the sample tables are needed")?;
+    let (trak, samples) = children(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .find_map(|(_, trak)| {
+            let samples = track_samples(trak).filter(|t| t.handler == "soun" && !t.samples.is_empty())?;
+            Some((trak, samples.samples))
+        })
+        .ok_or("No audio track with samples (fragmented MP4s keep their samples in moof boxes)")?;
+    let mdia = child(trak, b"mdia").ok_or("The audio track has no mdia")?;
+    let mdhd = child(mdia, b"mdhd").ok_or("The audio track has no mdhd")?;
+    let minf = child(mdia, b"minf").ok_or("The audio track has no minf")?;
+    let stbl = child(minf, b"stbl").ok_or("The audio track has no stbl")?;
+    let stsd = child(stbl, b"stsd").ok_or("The audio track has no stsd")?;
+    let codec = stsd.get(12..16).map(|c| String::from_utf8_lossy(c).trim().to_string()).unwrap_or_default();
+    let deltas = sample_deltas(child(stbl, b"stts").unwrap_or_default(), samples.len());
+
+    // Present: inside the file and not a zero-filled gap.
+    let mut media = Vec::new();
+    let mut sizes = Vec::new();
+    let mut kept_deltas = Vec::new();
+    let mut dropped_time = 0u64;
+    for (&(offset, size), &delta) in samples.iter().zip(&deltas) {
+        let sample = (offset as usize).checked_add(size as usize).and_then(|end| data.get(offset as usize..end));
+        match sample.filter(|s| s.iter().any(|b| *b != 0)) {
+            Some(sample) => {
+                media.extend_from_slice(sample);
+                sizes.push(size);
+                kept_deltas.push(delta);
+            }
+            None => dropped_time += delta as u64,
+        }
+    }
+    if sizes.is_empty() {
+        return Err("None of the audio samples are present".to_string());
+    }
+
+    let media_timescale = timescale(mdhd).ok_or("The audio mdhd has no timescale")?;
+    let movie_timescale = child(moov, b"mvhd").and_then(timescale).unwrap_or(1000);
+    let media_duration: u64 = kept_deltas.iter().map(|&d| d as u64).sum();
+    let movie_duration = media_duration * movie_timescale as u64 / media_timescale as u64;
+    let u32s = |v: u32| v.to_be_bytes().to_vec();
+    let mut stsz = vec![0, sizes.len() as u32];
+    stsz.extend(&sizes);
+    let ftyp = mp4_box(b"ftyp", M4A_FTYP);
+    let (mdat_header, mdat_size) = match u32::try_from(media.len() + 8) {
+        Ok(size) => (8, size as u64),
+        Err(_) => (16, media.len() as u64 + 16),
+    };
+
+    // All samples in one chunk; its offset depends on the moov's size, which
+    // doesn't depend on the offset's value.
+    let build_moov = |chunk_offset: u32| {
+        let new_stbl = [
+            mp4_box(b"stsd", stsd),
+            table_box(b"stts", &stts_entries(&kept_deltas), u32s),
+            table_box(b"stsc", &[1, 1, sizes.len() as u32, 1], u32s),
+            table_box(b"stsz", &stsz, u32s),
+            table_box(b"stco", &[1, chunk_offset], u32s),
+        ]
+        .concat();
+        let new_minf = rebuild_children(minf, |kind, body| match kind {
+            b"stbl" => Some(mp4_box(kind, &new_stbl)),
+            _ => Some(mp4_box(kind, body)),
+        });
+        let new_mdia = rebuild_children(mdia, |kind, body| match kind {
+            b"mdhd" => Some(mp4_box(kind, &with_duration(body, 16, 24, media_duration))),
+            b"minf" => Some(mp4_box(kind, &new_minf)),
+            _ => Some(mp4_box(kind, body)),
+        });
+        let new_trak = rebuild_children(trak, |kind, body| match kind {
+            b"tkhd" => Some(mp4_box(kind, &with_duration(body, 20, 28, movie_duration))),
+            // The edit list maps the original timeline, which lost samples.
+            b"edts" => None,
+            b"mdia" => Some(mp4_box(kind, &new_mdia)),
+            _ => Some(mp4_box(kind, body)),
+        });
+        mp4_box(
+            b"moov",
+            &rebuild_children(moov, |kind, body| match kind {
+                b"mvhd" => Some(mp4_box(kind, &with_duration(body, 16, 24, movie_duration))),
+                b"trak" if std::ptr::eq(body, trak) => Some(mp4_box(kind, &new_trak)),
+                b"trak" | b"mvex" => None,
+                _ => Some(mp4_box(kind, body)),
+            }),
+        )
+    };
+    let moov_len = build_moov(0).len();
+    let chunk_offset = u32::try_from(ftyp.len() + moov_len + mdat_header).map_err(|_| "The moov is too large")?;
+
+    let mut out = Vec::with_capacity(ftyp.len() + moov_len + mdat_header + media.len());
+    out.extend(ftyp);
+    out.extend(build_moov(chunk_offset));
+    if mdat_header == 16 {
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(b"mdat");
+        out.extend_from_slice(&mdat_size.to_be_bytes());
+    } else {
+        out.extend_from_slice(&(mdat_size as u32).to_be_bytes());
+        out.extend_from_slice(b"mdat");
+    }
+    out.extend_from_slice(&media);
+
+    let report = AudioSalvage {
+        codec,
+        samples: sizes.len(),
+        dropped_samples: samples.len() - sizes.len(),
+        duration_secs: media_duration as f64 / media_timescale as f64,
+        dropped_secs: dropped_time as f64 / media_timescale as f64,
+        bytes_written: out.len() as u64,
+    };
+    Ok((out, report))
+}
+
+/// Save the audio track of the MP4 at `path` (typically a reconstruction
+/// whose video has gaps) as an M4A at `output`, skipping missing samples.
+/// Requires a `consent` token covering `output`.
+#[tauri::command(async)]
+pub fn salvage_audio(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    path: String,
+    output: String,
+    consent: String,
+) -> Result<AudioSalvage, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("salvaging audio of {}", path));
+    let data = std::fs::read(&path).map_err(|e| crate::format_read_error(&path, &e))?;
+    let (out, report) = salvage(&data)?;
+    crate::output_file::write_output(&output, &out)?;
+    println!(
+        "[audio_salvage] {} {} samples ({:.1}s, {} dropped) from {} to {}",
+        report.samples, report.codec, report.duration_secs, report.dropped_samples, path, output
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::full;
+
+    fn trak(handler: &[u8; 4], stbl: Vec<u8>) -> Vec<u8> {
+        let mut hdlr = full(&[0]);
+        hdlr.extend_from_slice(handler);
+        hdlr.extend([0u8; 13]);
+        let minf = mp4_box(b"minf", &mp4_box(b"stbl", &stbl));
+        let mdia = [mp4_box(b"mdhd", &full(&[0, 0, 48_000, 0, 0])), mp4_box(b"hdlr", &hdlr), minf].concat();
+        [mp4_box(b"tkhd", &full(&[0, 0, 1, 0, 0])), mp4_box(b"mdia", &mdia)].concat()
+    }
+
+    /// Video chunks at 100 and 400, audio chunks (two 50-byte samples) at 300 and 600.
+    fn source(video_lost: bool) -> Vec<u8> {
+        let mut stsd = full(&[1]);
+        stsd.extend(mp4_box(b"mp4a", &[0; 28]));
+        let audio = [
+            mp4_box(b"stsd", &stsd),
+            mp4_box(b"stts", &full(&[1, 4, 1024])),
+            mp4_box(b"stsc", &full(&[1, 1, 2, 1])),
+            mp4_box(b"stsz", &full(&[50, 4])),
+            mp4_box(b"stco", &full(&[2, 300, 600])),
+        ]
+        .concat();
+        let video = [
+            mp4_box(b"stsc", &full(&[1, 1, 1, 1])),
+            mp4_box(b"stsz", &full(&[200, 2])),
+            mp4_box(b"stco", &full(&[2, 100, 400])),
+        ]
+        .concat();
+        let moov = [
+            mp4_box(b"mvhd", &full(&[0, 0, 1000, 0])),
+            mp4_box(b"trak", &trak(b"vide", video)),
+            mp4_box(b"trak", &trak(b"soun", audio)),
+        ]
+        .concat();
+        let mut data = vec![0x11u8; 700];
+        if video_lost {
+            data[400..600].fill(0);
+        }
+        data[0..8].copy_from_slice(&[0, 0, 0, 8, b'f', b'r', b'e', b'e']);
+        data[8..16].copy_from_slice(&[0, 0, 2, 180, b'm', b'd', b'a', b't']);
+        data.extend(mp4_box(b"moov", &moov));
+        data
+    }
+
+    #[test]
+    fn keeps_only_the_audio_track() {
+        let (out, report) = salvage(&source(true)).unwrap();
+        assert_eq!((report.codec.as_str(), report.samples, report.dropped_samples), ("mp4a", 4, 0));
+        let coverage = crate::mp4_samples::coverage(&out).unwrap();
+        assert_eq!(coverage.tracks.len(), 1);
+        assert_eq!((coverage.tracks[0].handler.as_str(), coverage.tracks[0].present_samples), ("soun", 4));
+        assert!(out.ends_with(&[0x11; 200]));
+    }
+
+    #[test]
+    fn drops_missing_audio_samples() {
+        let mut data = source(false);
+        data[600..650].fill(0);
+        let (out, report) = salvage(&data).unwrap();
+        assert_eq!((report.samples, report.dropped_samples), (3, 1));
+        assert!((report.dropped_secs - 1024.0 / 48_000.0).abs() < 1e-9);
+        assert_eq!(crate::mp4_samples::coverage(&out).unwrap().missing_samples, 0);
+    }
+}
@@ -1,8 +1,12 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use crate::eviction_risk::{self, EvictionRisk, RiskInput};
+use crate::events::{self, Coalescer, IndexProgress};
+use crate::hot_items::HotItemNotifier;
+use crate::reconstruction::{GapPolicy, ReconstructionResult};
+
 // ---------------------------------------------------------------------------
 // CacheAddr constants
 // ---------------------------------------------------------------------------
@@ -22,6 +26,19 @@ const BLOCK_MAGIC: u32 = 0xC104CAC3;
 
 const INDEX_HEADER_SIZE: usize = 368; // 256 IndexHeader + 112 LruData
 const BLOCK_HEADER_SIZE: u64 = 8192;
+/// Block files are chained via `next_file`; Chromium caps the selector at 255.
+const MAX_BLOCK_FILES: usize = 256;
+
+// Sparse entries (net/disk_cache/blockfile/sparse_control.cc): a parent keeps
+// a SparseData record in stream 2 whose bitmap marks which 1 MiB children
+// exist; each child keeps the data in stream 1 and its own SparseData in
+// stream 2, with one bit per 1 KiB block actually written.
+const SPARSE_DATA_STREAM: usize = 1;
+const SPARSE_INDEX_STREAM: usize = 2;
+/// SparseHeader: signature, magic, parent_key_len, last_block, last_block_len, dummy[10]
+const SPARSE_HEADER_SIZE: usize = 64;
+const SPARSE_BLOCK_SIZE: u64 = 1024;
+const SPARSE_CHILD_SIZE: u64 = 1 << 20;
 
 // ---------------------------------------------------------------------------
 // CacheAddr
@@ -66,11 +83,11 @@ impl CacheAddr {
     }
 
     /// Resolve to a file path under `cache_dir`.
-    fn to_file_path(&self, cache_dir: &Path) -> PathBuf {
+    fn to_file_path(self, cache_dir: &Path) -> PathBuf {
         if self.is_external() {
-            cache_dir.join(format!("f_{:06x}", self.file_number()))
+            crate::cache::resolve_child(cache_dir, &format!("f_{:06x}", self.file_number()))
         } else {
-            cache_dir.join(format!("data_{}", self.file_selector()))
+            crate::cache::resolve_child(cache_dir, &format!("data_{}", self.file_selector()))
         }
     }
 
@@ -83,6 +100,25 @@ impl CacheAddr {
     fn data_length(&self, entry_size: u32) -> u64 {
         self.num_blocks() as u64 * entry_size as u64
     }
+
+    /// Describe where this address points, without reading anything.
+    fn slot(&self) -> BlockfileSlot {
+        if self.is_external() {
+            BlockfileSlot {
+                file: format!("f_{:06x}", self.file_number()),
+                is_external: true,
+                start_block: None,
+                num_blocks: None,
+            }
+        } else {
+            BlockfileSlot {
+                file: format!("data_{}", self.file_selector()),
+                is_external: false,
+                start_block: Some(self.start_block()),
+                num_blocks: Some(self.num_blocks()),
+            }
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -91,7 +127,12 @@ impl CacheAddr {
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct BlockfileCacheEntry {
+    /// Cache key as stored, possibly with an isolation prefix
     pub url: String,
+    /// The resource URL, isolation prefix stripped
+    pub effective_url: String,
+    /// Top frame and frame site of a double-keyed key; empty otherwise
+    pub isolation_sites: Vec<String>,
     pub content_type: Option<String>,
     pub content_length: Option<u64>,
     pub original_filename: Option<String>,
@@ -109,6 +150,30 @@ pub struct BlockfileCacheEntry {
     pub body_size: u64,
     pub is_sparse: bool,
     pub children: Vec<BlockfileSparseChild>,
+    /// Present byte ranges of a sparse body across all children, as [start, end)
+    pub sparse_ranges: Vec<[u64; 2]>,
+    /// Children the parent's bitmap lists that are gone from the index
+    pub missing_children: Vec<u64>,
+    /// Declared size of each of the four streams (0 = headers, 1 = body,
+    /// 2 = extra/side data, 3 = sparse/metadata); negative sizes read as 0
+    pub stream_sizes: [u64; 4],
+    /// Times the entry was reused; selects its LRU list for eviction
+    pub reuse_count: u32,
+    /// Last use and last write from the entry's rankings node (unix seconds)
+    pub last_used: Option<f64>,
+    pub last_modified: Option<f64>,
+    /// LRU list the entry is linked into: "no_use", "low_use" or "high_use"
+    pub lru_list: Option<&'static str>,
+    /// Position in the order eviction trims the lists (0 = first to go);
+    /// counts every index entry, sparse children included
+    pub eviction_order: Option<u32>,
+    /// Likelihood this entry is evicted soon (set by `parse_blockfile_index`)
+    pub eviction_risk: Option<EvictionRisk>,
+    /// Host/signature-independent identity for known Discord CDN URLs
+    /// (see `discord_cdn`); entries sharing a key are the same object.
+    pub group_key: Option<String>,
+    /// IDs, variant and signed-expiry fields of a Discord CDN URL
+    pub cdn: Option<crate::discord_cdn::CdnMatch>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -120,16 +185,83 @@ pub struct BlockfileDataRef {
     pub is_external: bool,
 }
 
+/// Header of one `data_N` block file.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BlockfileDataFile {
+    pub name: String,
+    pub version: u32,
+    /// Block size in bytes: 36 (rankings), 256, 1024 or 4096
+    pub entry_size: u32,
+    /// Blocks currently in use
+    pub num_entries: u32,
+    /// Blocks the file can hold before `next_file` is used
+    pub max_entries: u32,
+    /// Next file in the chain once this one is full (e.g. "data_4")
+    pub next_file: Option<String>,
+}
+
+/// A location inside the cache directory: a whole `f_` file, or a run of
+/// blocks inside a `data_N` file.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BlockfileSlot {
+    /// File name, e.g. "f_00630b" or "data_1"
+    pub file: String,
+    pub is_external: bool,
+    /// First block inside a `data_N` file (None for `f_` files)
+    pub start_block: Option<u32>,
+    /// Contiguous blocks used (1-4; None for `f_` files)
+    pub num_blocks: Option<u32>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BlockfileStreamSlot {
+    pub stream_index: u32,
+    pub size: u64,
+    pub slot: BlockfileSlot,
+}
+
+/// One index entry as listed by `list_blockfile_entries`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BlockfileEntryListing {
+    /// Cache key as stored, possibly with an isolation prefix
+    pub url: String,
+    /// The resource URL, isolation prefix stripped
+    pub effective_url: String,
+    pub creation_time: Option<f64>,
+    /// Raw CacheAddr of the EntryStore, e.g. "0xA0010003"
+    pub entry_address: String,
+    /// Where the EntryStore record itself lives
+    pub entry_slot: BlockfileSlot,
+    /// Non-empty streams and where their data lives
+    pub streams: Vec<BlockfileStreamSlot>,
+    pub state: u32,
+    pub flags: u32,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BlockfileEntryList {
+    pub entries: Vec<BlockfileEntryListing>,
+    pub entry_count: u32,
+    pub version: u32,
+    pub errors: Vec<String>,
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct BlockfileSparseChild {
     pub child_id: u64,
     pub offset_bytes: u64,
     pub data_ref: BlockfileDataRef,
+    /// Byte ranges of the parent body this child actually holds, as
+    /// [start, end), from its block bitmap (the whole stream without one)
+    pub ranges: Vec<[u64; 2]>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SparseLinkingStats {
     pub total_children_linked: usize,
+    /// Children whose signature differs from their parent's (left over from
+    /// an earlier download of the same URL); not linked
+    pub stale_children: usize,
     pub orphaned_groups: usize,
     pub orphaned_children_total: usize,
     pub unmatched_parents: usize,
@@ -148,6 +280,10 @@ pub struct BlockfileIndexResult {
     pub entries: Vec<BlockfileCacheEntry>,
     pub entry_count: u32,
     pub version: u32,
+    /// Total bytes stored in the cache according to the index header
+    pub num_bytes: u64,
+    /// Block files found, including chained `data_4+` files
+    pub block_files: Vec<BlockfileDataFile>,
     pub errors: Vec<String>,
     pub sparse_linking_stats: SparseLinkingStats,
 }
@@ -157,10 +293,11 @@ pub struct BlockfileIndexResult {
 // ---------------------------------------------------------------------------
 
 struct RawEntry {
-    _hash: u32,
+    /// SuperFastHash of the key
+    hash: u32,
     next: CacheAddr,
-    _rankings_node: CacheAddr,
-    _reuse_count: u32,
+    rankings_node: CacheAddr,
+    reuse_count: u32,
     _refetch_count: u32,
     state: u32,
     creation_time: u64,
@@ -177,8 +314,17 @@ struct RawEntry {
 // Block file header cache
 // ---------------------------------------------------------------------------
 
+/// A loaded `data_N` file. Header layout (net/disk_cache/blockfile/disk_format_base.h):
+///   0x00 magic, 0x04 version, 0x08 this_file (i16), 0x0A next_file (i16),
+///   0x0C entry_size, 0x10 num_entries, 0x14 max_entries, then allocation
+///   bookkeeping up to the 8 KiB header boundary.
 struct BlockFileInfo {
+    version: u32,
+    this_file: i16,
+    next_file: i16,
     entry_size: u32,
+    num_entries: i32,
+    max_entries: i32,
     data: Vec<u8>,
 }
 
@@ -214,9 +360,20 @@ impl BlockFileCache {
                         ));
                         return None;
                     }
-                    let entry_size = read_u32_le(&data, 0x0C);
-                    self.files
-                        .insert(path.to_path_buf(), BlockFileInfo { entry_size, data });
+                    let info = BlockFileInfo {
+                        version: read_u32_le(&data, 0x04),
+                        this_file: read_i16_le(&data, 0x08),
+                        next_file: read_i16_le(&data, 0x0A),
+                        entry_size: read_u32_le(&data, 0x0C),
+                        num_entries: read_i32_le(&data, 0x10),
+                        max_entries: read_i32_le(&data, 0x14),
+                        data,
+                    };
+                    if info.entry_size == 0 {
+                        errors.push(format!("Block file {} has entry_size 0", path.display()));
+                        return None;
+                    }
+                    self.files.insert(path.to_path_buf(), info);
                 }
                 Err(e) => {
                     errors.push(format!("Cannot read block file {}: {}", path.display(), e));
@@ -232,6 +389,13 @@ impl BlockFileCache {
 // Helpers
 // ---------------------------------------------------------------------------
 
+fn read_i16_le(buf: &[u8], offset: usize) -> i16 {
+    if offset + 2 > buf.len() {
+        return 0;
+    }
+    i16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
 fn read_u32_le(buf: &[u8], offset: usize) -> u32 {
     if offset + 4 > buf.len() {
         return 0;
@@ -366,14 +530,8 @@ fn parse_entry_store(buf: &[u8]) -> Option<RawEntry> {
     let key_len = read_u32_le(buf, 0x20);
     let long_key = CacheAddr(read_u32_le(buf, 0x24));
 
-    let mut data_size = [0i32; 4];
-    for i in 0..4 {
-        data_size[i] = read_i32_le(buf, 0x28 + i * 4);
-    }
-    let mut data_addr = [CacheAddr(0); 4];
-    for i in 0..4 {
-        data_addr[i] = CacheAddr(read_u32_le(buf, 0x38 + i * 4));
-    }
+    let data_size: [i32; 4] = std::array::from_fn(|i| read_i32_le(buf, 0x28 + i * 4));
+    let data_addr: [CacheAddr; 4] = std::array::from_fn(|i| CacheAddr(read_u32_le(buf, 0x38 + i * 4)));
 
     let flags = read_u32_le(buf, 0x48);
     let self_hash = read_u32_le(buf, 0x5C);
@@ -385,10 +543,10 @@ fn parse_entry_store(buf: &[u8]) -> Option<RawEntry> {
     let key_data = buf[key_start..].to_vec();
 
     Some(RawEntry {
-        _hash: hash,
+        hash,
         next,
-        _rankings_node: rankings_node,
-        _reuse_count: reuse_count,
+        rankings_node,
+        reuse_count,
         _refetch_count: refetch_count,
         state,
         creation_time,
@@ -480,127 +638,19 @@ impl ParsedHttpMeta {
     }
 }
 
-/// Convert Chromium microsecond timestamp (since 1601-01-01) to Unix seconds.
-fn chrome_time_to_unix(us: i64) -> f64 {
-    // Chromium stores time as microseconds since 1601-01-01 00:00:00 UTC
-    // Unix epoch starts at 1970-01-01 00:00:00 UTC
-    // Difference = 11644473600 seconds
-    (us as f64 / 1_000_000.0) - 11_644_473_600.0
-}
-
-/// Read a Pickle-encoded int32 at `pos`, returning the value and the new position.
-/// All Pickle values are 4-byte aligned.
-fn pickle_read_i32(data: &[u8], pos: usize) -> Option<(i32, usize)> {
-    if pos + 4 > data.len() {
-        return None;
-    }
-    let val = read_i32_le(data, pos);
-    Some((val, pos + 4))
-}
-
-/// Read a Pickle-encoded int64 at `pos`, returning the value and the new position.
-fn pickle_read_i64(data: &[u8], pos: usize) -> Option<(i64, usize)> {
-    if pos + 8 > data.len() {
-        return None;
-    }
-    let val = read_i64_le(data, pos);
-    Some((val, pos + 8))
-}
-
-/// Read a Pickle-encoded string at `pos`: 4-byte length prefix + data + padding.
-fn pickle_read_string(data: &[u8], pos: usize) -> Option<(Vec<u8>, usize)> {
-    if pos + 4 > data.len() {
-        return None;
-    }
-    let len = read_i32_le(data, pos) as usize;
-    let start = pos + 4;
-    if start + len > data.len() {
-        return None;
-    }
-    let bytes = data[start..start + len].to_vec();
-    // Align to next 4-byte boundary
-    let aligned = (start + len + 3) & !3;
-    Some((bytes, aligned))
-}
-
-/// Parse HTTP response metadata from stream 0 (Chromium Pickle format).
-///
-/// Stream 0 is a Pickle-serialized HttpResponseInfo. Layout:
-///   [4 bytes: pickle payload_size (u32 LE)]
-///   Payload:
-///     int32 flags (version in bits[7:0], HAS_EXTRA_FLAGS = bit 31)
-///     int32 extra_flags (only if HAS_EXTRA_FLAGS set)
-///     int64 request_time (Chrome microseconds since 1601-01-01)
-///     int64 response_time
-///     int64 original_response_time (only if extra_flags bit 2 set)
-///     string raw_headers_ (null-separated: "HTTP/1.1 200 OK\0header: value\0...\0")
-///     ...optional SSL/cert fields (ignored)...
+/// Parse HTTP response metadata from stream 0, a pickled HttpResponseInfo
+/// (layout in `http_info`). Only the timestamps and raw header block are
+/// used here.
 fn parse_http_headers(data: &[u8]) -> ParsedHttpMeta {
-    const HAS_EXTRA_FLAGS: i32 = 1 << 31; // flags bit 31
-    const HAS_ORIGINAL_RESPONSE_TIME: i32 = 1 << 2; // extra_flags bit 2
-
-    if data.len() < 8 {
-        return ParsedHttpMeta::empty();
-    }
-
-    // Skip pickle header (4-byte payload_size)
-    let mut pos: usize = 4;
-
-    // Read flags
-    let (flags, next) = match pickle_read_i32(data, pos) {
-        Some(v) => v,
-        None => return ParsedHttpMeta::empty(),
-    };
-    pos = next;
-
-    // Read extra_flags if HAS_EXTRA_FLAGS is set
-    let mut extra_flags: i32 = 0;
-    if flags & HAS_EXTRA_FLAGS != 0 {
-        let (ef, next) = match pickle_read_i32(data, pos) {
-            Some(v) => v,
-            None => return ParsedHttpMeta::empty(),
-        };
-        extra_flags = ef;
-        pos = next;
+    match crate::http_info::read_pickled(data) {
+        Some(pickled) => parse_raw_headers(pickled.raw_headers, pickled.request_time_us, pickled.response_time_us),
+        // Fallback: try to find HTTP/ signature in the remaining data
+        // This handles edge cases where flag bits are unexpected
+        None => match find_http_headers_fallback(data) {
+            Some(bytes) => parse_raw_headers(&bytes, 0, 0),
+            None => ParsedHttpMeta::empty(),
+        },
     }
-
-    // Read request_time (int64, Chrome microseconds)
-    let (request_time_us, next) = match pickle_read_i64(data, pos) {
-        Some(v) => v,
-        None => return ParsedHttpMeta::empty(),
-    };
-    pos = next;
-
-    // Read response_time (int64, Chrome microseconds)
-    let (response_time_us, next) = match pickle_read_i64(data, pos) {
-        Some(v) => v,
-        None => return ParsedHttpMeta::empty(),
-    };
-    pos = next;
-
-    // Read original_response_time if present
-    if extra_flags & HAS_ORIGINAL_RESPONSE_TIME != 0 {
-        let (_, next) = match pickle_read_i64(data, pos) {
-            Some(v) => v,
-            None => return ParsedHttpMeta::empty(),
-        };
-        pos = next;
-    }
-
-    // Read raw_headers_ string (Pickle string: 4-byte len + data + padding)
-    let raw_headers_bytes = match pickle_read_string(data, pos) {
-        Some((bytes, _)) => bytes,
-        None => {
-            // Fallback: try to find HTTP/ signature in the remaining data
-            // This handles edge cases where flag bits are unexpected
-            match find_http_headers_fallback(data) {
-                Some(bytes) => bytes,
-                None => return ParsedHttpMeta::empty(),
-            }
-        }
-    };
-
-    parse_raw_headers(&raw_headers_bytes, request_time_us, response_time_us)
 }
 
 /// Fallback: scan the data for "HTTP/" to find where the raw headers blob starts.
@@ -674,7 +724,7 @@ fn parse_raw_headers(raw: &[u8], request_time_us: i64, response_time_us: i64) ->
             } else if lower_name == "content-length" {
                 content_length = value.parse::<u64>().ok();
             } else if lower_name == "content-disposition" {
-                original_filename = parse_content_disposition_filename(&value);
+                original_filename = crate::content_disposition::filename(&value);
             }
 
             // Store all headers (lowercase key for consistency)
@@ -683,12 +733,12 @@ fn parse_raw_headers(raw: &[u8], request_time_us: i64, response_time_us: i64) ->
     }
 
     let request_time = if request_time_us > 0 {
-        Some(chrome_time_to_unix(request_time_us))
+        Some(crate::http_info::chrome_time_to_unix(request_time_us))
     } else {
         None
     };
     let response_time = if response_time_us > 0 {
-        Some(chrome_time_to_unix(response_time_us))
+        Some(crate::http_info::chrome_time_to_unix(response_time_us))
     } else {
         None
     };
@@ -708,43 +758,8 @@ fn parse_raw_headers(raw: &[u8], request_time_us: i64, response_time_us: i64) ->
     }
 }
 
-/// Extract filename from Content-Disposition header value.
-fn parse_content_disposition_filename(value: &str) -> Option<String> {
-    // Try filename*=UTF-8''... first (RFC 5987)
-    if let Some(pos) = value.to_lowercase().find("filename*=utf-8''") {
-        let start = pos + "filename*=utf-8''".len();
-        let rest = &value[start..];
-        let end = rest.find(';').unwrap_or(rest.len());
-        let encoded = rest[..end].trim();
-        // URL-decode
-        return Some(url_decode(encoded));
-    }
-
-    // Try filename="..."
-    if let Some(pos) = value.to_lowercase().find("filename=\"") {
-        let start = pos + "filename=\"".len();
-        let rest = &value[start..];
-        if let Some(end) = rest.find('"') {
-            return Some(rest[..end].to_string());
-        }
-    }
-
-    // Try filename=... (unquoted)
-    if let Some(pos) = value.to_lowercase().find("filename=") {
-        let start = pos + "filename=".len();
-        let rest = &value[start..];
-        let end = rest.find(';').unwrap_or(rest.len());
-        let name = rest[..end].trim();
-        if !name.is_empty() {
-            return Some(name.to_string());
-        }
-    }
-
-    None
-}
-
 /// Simple percent-decoding for URL-encoded filenames.
-fn url_decode(input: &str) -> String {
+pub(crate) fn url_decode(input: &str) -> String {
     let mut result = Vec::new();
     let bytes = input.as_bytes();
     let mut i = 0;
@@ -795,12 +810,230 @@ fn build_data_ref(
     })
 }
 
+/// A SparseData record from stream 2 of a sparse parent or child.
+struct SparseData {
+    signature: u64,
+    /// Block written last, and how much of it (its bit is only set once full)
+    last_block: i32,
+    last_block_len: i32,
+    /// Parent: one bit per child. Child: one bit per 1 KiB block.
+    bitmap: Vec<u32>,
+}
+
+impl SparseData {
+    fn parse(buf: &[u8]) -> Option<SparseData> {
+        if buf.len() < SPARSE_HEADER_SIZE || read_u32_le(buf, 8) != INDEX_MAGIC {
+            return None;
+        }
+        Some(SparseData {
+            signature: read_u64_le(buf, 0),
+            last_block: read_i32_le(buf, 16),
+            last_block_len: read_i32_le(buf, 20),
+            bitmap: buf[SPARSE_HEADER_SIZE..]
+                .chunks_exact(4)
+                .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+                .collect(),
+        })
+    }
+
+    fn read(
+        raw: &ParsedRawEntry,
+        cache_dir: &Path,
+        block_cache: &mut BlockFileCache,
+        errors: &mut Vec<String>,
+    ) -> Option<SparseData> {
+        let size = raw.data_size[SPARSE_INDEX_STREAM];
+        let buf = read_stream_data(
+            raw.data_addr[SPARSE_INDEX_STREAM],
+            size.max(0) as u32,
+            cache_dir,
+            block_cache,
+            errors,
+        )?;
+        SparseData::parse(&buf)
+    }
+
+    fn is_set(&self, bit: u64) -> bool {
+        self.bitmap
+            .get((bit / 32) as usize)
+            .is_some_and(|word| word >> (bit % 32) & 1 != 0)
+    }
+
+    /// Indexes of the set bits (children of a parent).
+    fn set_bits(&self) -> Vec<u64> {
+        (0..self.bitmap.len() as u64 * 32).filter(|&bit| self.is_set(bit)).collect()
+    }
+}
+
+/// Parent body ranges held by child `child_id` whose stream 1 is `data_len`
+/// bytes. Without a bitmap the whole stream counts as written.
+fn child_ranges(child_id: u64, sparse: Option<&SparseData>, data_len: u64) -> Vec<[u64; 2]> {
+    let base = child_id * SPARSE_CHILD_SIZE;
+    let Some(sparse) = sparse else {
+        return if data_len > 0 { vec![[base, base + data_len]] } else { Vec::new() };
+    };
+    let mut ranges = Vec::new();
+    for block in 0..SPARSE_CHILD_SIZE / SPARSE_BLOCK_SIZE {
+        let len = if sparse.is_set(block) {
+            SPARSE_BLOCK_SIZE
+        } else if sparse.last_block >= 0 && sparse.last_block as u64 == block {
+            (sparse.last_block_len.max(0) as u64).min(SPARSE_BLOCK_SIZE)
+        } else {
+            continue;
+        };
+        let start = block * SPARSE_BLOCK_SIZE;
+        let end = (start + len).min(data_len);
+        if end > start {
+            ranges.push([base + start, base + end]);
+        }
+    }
+    crate::compare::normalize(ranges)
+}
+
+// ---------------------------------------------------------------------------
+// Rankings (LRU lists)
+// ---------------------------------------------------------------------------
+//
+// Every entry has a RankingsNode in data_0 (36-byte blocks,
+// net/disk_cache/blockfile/disk_format.h):
+//   0x00 last_used (u64), 0x08 last_modified (u64), 0x10 next, 0x14 prev,
+//   0x18 contents (the EntryStore's CacheAddr), 0x1C dirty, 0x20 self_hash
+// Nodes are chained into the LRU lists whose heads and tails sit in the
+// LruData block after the 256-byte IndexHeader. Heads are the most recently
+// used entries; eviction trims from the tails, NO_USE list first, then
+// LOW_USE, then HIGH_USE.
+
+const LRU_DATA_OFFSET: usize = 256;
+const RANKINGS_NODE_SIZE: u32 = 36;
+/// NO_USE, LOW_USE and HIGH_USE; lists 3 (RESERVED) and 4 (DELETED) hold
+/// no live entries.
+const LRU_LISTS: [&str; 3] = ["no_use", "low_use", "high_use"];
+
+/// LruData: pad1[2], filled, sizes[5], heads[5], tails[5], transaction, ...
+struct LruLists {
+    sizes: [i32; 5],
+    tails: [CacheAddr; 5],
+}
+
+impl LruLists {
+    fn parse(index_data: &[u8]) -> Self {
+        let base = LRU_DATA_OFFSET;
+        let mut lists = LruLists { sizes: [0; 5], tails: [CacheAddr(0); 5] };
+        for i in 0..5 {
+            lists.sizes[i] = read_i32_le(index_data, base + 0x0C + i * 4);
+            lists.tails[i] = CacheAddr(read_u32_le(index_data, base + 0x34 + i * 4));
+        }
+        lists
+    }
+}
+
+struct RankingsNode {
+    last_used: u64,
+    last_modified: u64,
+    prev: CacheAddr,
+    contents: CacheAddr,
+}
+
+fn read_rankings_node(
+    addr: CacheAddr,
+    dir: &Path,
+    block_cache: &mut BlockFileCache,
+    errors: &mut Vec<String>,
+) -> Option<RankingsNode> {
+    if addr.is_external() || addr.file_type() != 1 {
+        return None;
+    }
+    let buf = read_stream_data(addr, RANKINGS_NODE_SIZE, dir, block_cache, errors)?;
+    (buf.len() == RANKINGS_NODE_SIZE as usize).then(|| RankingsNode {
+        last_used: read_u64_le(&buf, 0x00),
+        last_modified: read_u64_le(&buf, 0x08),
+        prev: CacheAddr(read_u32_le(&buf, 0x14)),
+        contents: CacheAddr(read_u32_le(&buf, 0x18)),
+    })
+}
+
+/// Where an entry stands in the LRU lists.
+#[derive(Debug, Clone, Copy)]
+struct Ranking {
+    list: &'static str,
+    /// Place in the overall trim order, 0 = evicted first
+    eviction_order: u32,
+}
+
+/// Walk each LRU list from its tail, in the order eviction trims them, and
+/// key the result by EntryStore address.
+fn read_rankings(
+    lru: &LruLists,
+    dir: &Path,
+    block_cache: &mut BlockFileCache,
+    errors: &mut Vec<String>,
+) -> HashMap<u32, Ranking> {
+    let mut rankings = HashMap::new();
+    let mut order = 0u32;
+    for (list, name) in LRU_LISTS.iter().enumerate() {
+        let mut visited: HashSet<u32> = HashSet::new();
+        let mut current = lru.tails[list];
+        let limit = lru.sizes[list].max(0) as usize;
+        while current.is_initialized() && visited.insert(current.0) {
+            if visited.len() > limit + 1 {
+                errors.push(format!("LRU list {} is longer than its recorded size {}", name, limit));
+                break;
+            }
+            let Some(node) = read_rankings_node(current, dir, block_cache, errors) else {
+                errors.push(format!("Unreadable rankings node 0x{:08X} in LRU list {}", current.0, name));
+                break;
+            };
+            rankings.entry(node.contents.0).or_insert(Ranking { list: name, eviction_order: order });
+            order += 1;
+            current = node.prev;
+        }
+    }
+    rankings
+}
+
 // ---------------------------------------------------------------------------
 // Core parser
 // ---------------------------------------------------------------------------
 
-fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
-    let index_path = dir.join("index");
+/// An entry as found in the index hash table, before stream data is read.
+struct ParsedRawEntry {
+    /// Where the EntryStore itself lives
+    addr: CacheAddr,
+    url: String,
+    state: u32,
+    flags: u32,
+    reuse_count: u32,
+    creation_time: u64,
+    rankings_node: CacheAddr,
+    data_size: [i32; 4],
+    data_addr: [CacheAddr; 4],
+}
+
+/// A sparse child as decoded from its key and streams, before linking.
+struct ParsedChild {
+    child_id: u64,
+    /// Signature from the child's key; must match the parent's
+    signature: Option<u64>,
+    data_ref: BlockfileDataRef,
+    ranges: Vec<[u64; 2]>,
+}
+
+/// Everything `walk_index` reads from the index header and hash table.
+struct IndexWalk {
+    version: u32,
+    entry_count: u32,
+    num_bytes: u64,
+    block_files: Vec<BlockfileDataFile>,
+    raw_entries: Vec<ParsedRawEntry>,
+    lru: LruLists,
+    errors: Vec<String>,
+    block_cache: BlockFileCache,
+}
+
+/// Validate the index header, load the block files and walk every hash
+/// bucket's collision chain, decoding each EntryStore and its key.
+fn walk_index(dir: &Path) -> Result<IndexWalk, String> {
+    let index_path = crate::cache::resolve_child(dir, "index");
     let index_data = fs::read(&index_path)
         .map_err(|e| format!("Cannot read index file {}: {}", index_path.display(), e))?;
 
@@ -823,11 +1056,26 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
 
     // Version check
     let version = read_u32_le(&index_data, 4);
-    if version != 0x20000 && version != 0x20001 && version != 0x30000 {
+    if version < 0x20000 || (version > 0x20001 && version < 0x30000) {
         return Err(format!("Unsupported index version: 0x{:X}", version));
     }
+    if version > 0x30000 {
+        // Newer than we know: read it as v3.
+        crate::compat::record(
+            "blockfile_index",
+            format!("version 0x{:X}", version),
+            index_data.len() as u64,
+            &[("version", version as u64), ("entry_count", read_u32_le(&index_data, 8) as u64)],
+        );
+    }
 
     let entry_count = read_u32_le(&index_data, 8);
+    // v3 keeps a 64-bit byte count at 0x30; v2 only has the 32-bit one at 0x0C.
+    let num_bytes = if version >= 0x30000 {
+        read_i64_le(&index_data, 0x30).max(0) as u64
+    } else {
+        read_i32_le(&index_data, 0x0C).max(0) as u64
+    };
     let mut table_len = read_u32_le(&index_data, 0x1C);
     if table_len == 0 {
         table_len = 0x10000;
@@ -836,11 +1084,43 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
     let mut errors: Vec<String> = Vec::new();
     let mut block_cache = BlockFileCache::new();
 
-    // Pre-load block files (data_0..data_3)
-    for i in 0..4 {
-        let bf_path = dir.join(format!("data_{}", i));
-        if bf_path.exists() {
-            block_cache.get_or_load(&bf_path, &mut errors);
+    // Pre-load block files: data_0..data_3, plus any data_4+ files they
+    // chain to via next_file once full.
+    let mut block_files: Vec<BlockfileDataFile> = Vec::new();
+    let mut loaded: HashSet<i16> = HashSet::new();
+    for first in 0..4i16 {
+        let mut selector = first;
+        while loaded.len() < MAX_BLOCK_FILES && loaded.insert(selector) {
+            let name = format!("data_{}", selector);
+            let bf_path = crate::cache::resolve_child(dir, &name);
+            if !bf_path.exists() {
+                if selector != first {
+                    errors.push(format!("Chained block file missing: {}", name));
+                }
+                break;
+            }
+            let Some(info) = block_cache.get_or_load(&bf_path, &mut errors) else {
+                break;
+            };
+            if info.this_file != selector {
+                errors.push(format!(
+                    "Block file {} claims to be data_{}",
+                    name, info.this_file
+                ));
+            }
+            let next_file = info.next_file;
+            block_files.push(BlockfileDataFile {
+                name,
+                version: info.version,
+                entry_size: info.entry_size,
+                num_entries: info.num_entries.max(0) as u32,
+                max_entries: info.max_entries.max(0) as u32,
+                next_file: (next_file > 0).then(|| format!("data_{}", next_file)),
+            });
+            if next_file <= 0 {
+                break;
+            }
+            selector = next_file;
         }
     }
 
@@ -856,16 +1136,6 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
     }
 
     // Collect all raw entries with their addresses
-    struct ParsedRawEntry {
-        _addr: CacheAddr,
-        url: String,
-        state: u32,
-        flags: u32,
-        creation_time: u64,
-        data_size: [i32; 4],
-        data_addr: [CacheAddr; 4],
-    }
-
     let mut raw_entries: Vec<ParsedRawEntry> = Vec::new();
     let mut visited: HashSet<u32> = HashSet::new();
 
@@ -936,11 +1206,13 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
                     let next = entry.next;
 
                     raw_entries.push(ParsedRawEntry {
-                        _addr: current,
+                        addr: current,
                         url,
                         state: entry.state,
                         flags: entry.flags,
+                        reuse_count: entry.reuse_count,
                         creation_time: entry.creation_time,
+                        rankings_node: entry.rankings_node,
                         data_size: entry.data_size,
                         data_addr: entry.data_addr,
                     });
@@ -955,11 +1227,45 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
         }
     }
 
+    Ok(IndexWalk {
+        version,
+        entry_count,
+        num_bytes,
+        block_files,
+        raw_entries,
+        lru: LruLists::parse(&index_data),
+        errors,
+        block_cache,
+    })
+}
+
+/// Parse the index and all entries. When `hot` is given, high-confidence
+/// videos are reported through it as soon as they are decoded; `progress`
+/// receives per-entry progress.
+pub(crate) fn parse_index_internal(
+    dir: &Path,
+    mut hot: Option<&mut HotItemNotifier>,
+    mut progress: Option<&mut Coalescer<IndexProgress>>,
+) -> Result<BlockfileIndexResult, String> {
+    let IndexWalk {
+        version,
+        entry_count,
+        num_bytes,
+        block_files,
+        raw_entries,
+        lru,
+        mut errors,
+        mut block_cache,
+    } = walk_index(dir)?;
+    let rankings = read_rankings(&lru, dir, &mut block_cache, &mut errors);
+
     // Build cache entries
     let mut entries: Vec<BlockfileCacheEntry> = Vec::new();
     // Track children by parent URL for sparse reconstruction
     // Children have key like "Range_<parent_key>:<signature_hex>:<child_id_hex>"
-    let mut children_map: HashMap<String, Vec<(u64, BlockfileDataRef)>> = HashMap::new();
+    let mut children_map: HashMap<String, Vec<ParsedChild>> = HashMap::new();
+    // Each sparse parent's own SparseData (signature + children bitmap), by URL
+    let mut parent_index: HashMap<String, SparseData> = HashMap::new();
     let mut child_count = 0usize;
     let mut parent_count = 0usize;
     let mut child_no_url_count = 0usize;
@@ -967,7 +1273,10 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
     let mut child_no_range_prefix_count = 0usize;
     let mut child_parse_fail_count = 0usize;
 
-    for raw in &raw_entries {
+    for (i, raw) in raw_entries.iter().enumerate() {
+        if let Some(progress) = progress.as_deref_mut() {
+            progress.push(IndexProgress { phase: "entries", current: i + 1, total: raw_entries.len() });
+        }
         let is_child = raw.flags & 2 != 0;
         let is_parent = raw.flags & 1 != 0;
 
@@ -1035,39 +1344,46 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
                     // Find the second-to-last ':' to split off the signature,
                     // leaving only the true parent key.
                     // The parent entry's URL does NOT include the signature.
-                    let parent_url = if let Some(sig_colon) = remainder.rfind(':') {
+                    let (parent_url, signature) = if let Some(sig_colon) = remainder.rfind(':') {
                         // Verify the segment after sig_colon looks like a hex signature
                         let sig_candidate = &remainder[sig_colon + 1..];
                         if sig_candidate.len() >= 4 && sig_candidate.chars().all(|c| c.is_ascii_hexdigit()) {
-                            &remainder[..sig_colon]
+                            (&remainder[..sig_colon], u64::from_str_radix(sig_candidate, 16).ok())
                         } else {
                             // Doesn't look like a signature — use full remainder as parent URL
-                            remainder
+                            (remainder, None)
                         }
                     } else {
-                        remainder
+                        (remainder, None)
                     };
 
-
-                    // Build data ref — try all streams, not just stream 1.
-                    // In Chromium sparse child entries, body data can be stored in
-                    // any stream (0-3). Stream 1 (data_1, 256-byte blocks) is most
-                    // common, but many children store body data in stream 2
-                    // (data_2, 1024-byte blocks). Try preferred order: 1, 2, 0, 3.
-                    let dr = build_data_ref(1, raw.data_addr[1], raw.data_size[1], dir, &mut block_cache, &mut errors)
-                        .or_else(|| build_data_ref(2, raw.data_addr[2], raw.data_size[2], dir, &mut block_cache, &mut errors))
-                        .or_else(|| build_data_ref(0, raw.data_addr[0], raw.data_size[0], dir, &mut block_cache, &mut errors))
-                        .or_else(|| build_data_ref(3, raw.data_addr[3], raw.data_size[3], dir, &mut block_cache, &mut errors));
+                    // Chromium writes child data to stream 1 and the child's
+                    // block bitmap to stream 2. Children without a readable
+                    // bitmap (older or damaged caches) fall back to trying
+                    // every stream, preferring 1, 2, 0, 3.
+                    let sparse = SparseData::read(raw, dir, &mut block_cache, &mut errors);
+                    let stream = |i: usize, block_cache: &mut BlockFileCache, errors: &mut Vec<String>| {
+                        build_data_ref(i as u32, raw.data_addr[i], raw.data_size[i], dir, block_cache, errors)
+                    };
+                    let dr = if sparse.is_some() {
+                        stream(SPARSE_DATA_STREAM, &mut block_cache, &mut errors)
+                    } else {
+                        stream(1, &mut block_cache, &mut errors)
+                            .or_else(|| stream(2, &mut block_cache, &mut errors))
+                            .or_else(|| stream(0, &mut block_cache, &mut errors))
+                            .or_else(|| stream(3, &mut block_cache, &mut errors))
+                    };
 
                     if let Some(dr) = dr {
+                        let ranges = child_ranges(child_id, sparse.as_ref(), dr.size);
                         println!(
-                            "[parse_blockfile_index] CHILD OK: child_id={}, stream={}, size={} bytes, is_external={}, parent_url={}",
-                            child_id, dr.stream_index, dr.size, dr.is_external, &parent_url[..parent_url.len().min(80)]
+                            "[parse_blockfile_index] CHILD OK: child_id={}, stream={}, size={} bytes, ranges={}, is_external={}, parent_url={}",
+                            child_id, dr.stream_index, dr.size, ranges.len(), dr.is_external, &parent_url[..parent_url.len().min(80)]
                         );
                         children_map
                             .entry(parent_url.to_string())
                             .or_default()
-                            .push((child_id, dr));
+                            .push(ParsedChild { child_id, signature, data_ref: dr, ranges });
                     } else {
                         child_no_data_ref_count += 1;
                         println!(
@@ -1095,13 +1411,30 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
 
         if is_parent {
             parent_count += 1;
+            if let Some(sparse) = SparseData::read(raw, dir, &mut block_cache, &mut errors) {
+                parent_index.insert(raw.url.clone(), sparse);
+            }
         }
 
+        // Known Discord CDN endpoints give a grouping key and a URL-derived
+        // filename when Content-Disposition didn't provide one.
+        let cdn = crate::discord_cdn::match_response(&raw.url, meta.headers.as_ref());
+        // A node that points at another entry is stale; its times aren't ours.
+        let node = read_rankings_node(raw.rankings_node, dir, &mut block_cache, &mut errors)
+            .filter(|n| n.contents == raw.addr);
+        let ranking = rankings.get(&raw.addr.0);
+        let node_time = |t: u64| (t > 0).then(|| filetime_to_unix(t));
+        let original_filename = meta
+            .original_filename
+            .or_else(|| cdn.as_ref().and_then(|m| m.original_filename.clone()));
+
         entries.push(BlockfileCacheEntry {
             url: raw.url.clone(),
+            effective_url: crate::cache_key::effective_url(&raw.url).to_string(),
+            isolation_sites: crate::cache_key::isolation_sites(&raw.url).into_iter().map(String::from).collect(),
             content_type: meta.content_type,
             content_length: meta.content_length,
-            original_filename: meta.original_filename,
+            original_filename,
             http_status: meta.http_status,
             creation_time: creation_ts,
             request_time: meta.request_time,
@@ -1113,7 +1446,24 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
             body_size,
             is_sparse: is_parent,
             children: Vec::new(),
+            sparse_ranges: Vec::new(),
+            missing_children: Vec::new(),
+            stream_sizes: raw.data_size.map(|s| s.max(0) as u64),
+            reuse_count: raw.reuse_count,
+            last_used: node.as_ref().and_then(|n| node_time(n.last_used)),
+            last_modified: node.as_ref().and_then(|n| node_time(n.last_modified)),
+            lru_list: ranking.map(|r| r.list),
+            eviction_order: ranking.map(|r| r.eviction_order),
+            eviction_risk: None,
+            group_key: cdn.as_ref().map(|m| m.group_key.clone()),
+            cdn,
         });
+        // Sparse parents only know their size once children are linked below.
+        if let (Some(hot), Some(entry)) = (hot.as_deref_mut(), entries.last()) {
+            if !entry.is_sparse {
+                hot.offer(entry);
+            }
+        }
     }
 
     println!(
@@ -1122,11 +1472,31 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
     );
     // Link children to parent entries
     let mut linked_count = 0usize;
+    let mut stale_children = 0usize;
     let mut unmatched_parent_urls: Vec<String> = Vec::new();
-    for entry in &mut entries {
+    let entry_total = entries.len();
+    for (i, entry) in entries.iter_mut().enumerate() {
+        if let Some(progress) = progress.as_deref_mut() {
+            progress.push(IndexProgress { phase: "linking", current: i + 1, total: entry_total });
+        }
         if entry.is_sparse {
+            let parent = parent_index.get(&entry.url);
             if let Some(mut child_list) = children_map.remove(&entry.url) {
-                child_list.sort_by_key(|(id, _)| *id);
+                // A child whose signature isn't the parent's belongs to an
+                // earlier download of this URL; Chromium would discard it.
+                if let Some(parent) = parent {
+                    let before = child_list.len();
+                    child_list.retain(|c| c.signature.is_none_or(|sig| sig == parent.signature));
+                    if child_list.len() < before {
+                        println!(
+                            "[parse_blockfile_index] Dropped {} stale children (signature mismatch) of: {}",
+                            before - child_list.len(),
+                            &entry.url[..entry.url.len().min(120)]
+                        );
+                        stale_children += before - child_list.len();
+                    }
+                }
+                child_list.sort_by_key(|c| c.child_id);
                 linked_count += child_list.len();
                 println!(
                     "[parse_blockfile_index] Linked {} children to sparse parent: {}",
@@ -1135,17 +1505,23 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
                 );
                 entry.children = child_list
                     .into_iter()
-                    .map(|(child_id, data_ref)| BlockfileSparseChild {
-                        child_id,
-                        offset_bytes: child_id << 20, // each child covers 1MB
-                        data_ref,
+                    .map(|c| BlockfileSparseChild {
+                        child_id: c.child_id,
+                        offset_bytes: c.child_id * SPARSE_CHILD_SIZE,
+                        data_ref: c.data_ref,
+                        ranges: c.ranges,
                     })
                     .collect();
+                entry.sparse_ranges =
+                    crate::compare::normalize(entry.children.iter().flat_map(|c| c.ranges.clone()).collect());
 
                 // Update body_size for sparse entries: parent's data_size[1] is 0
                 // because body data lives in children, not the parent entry.
                 if entry.body_size == 0 && !entry.children.is_empty() {
-                    entry.body_size = entry.children.iter().map(|c| c.data_ref.size).sum();
+                    entry.body_size = entry.sparse_ranges.iter().map(|r| r[1] - r[0]).sum();
+                }
+                if let Some(hot) = hot.as_deref_mut() {
+                    hot.offer(entry);
                 }
             } else {
                 println!(
@@ -1154,6 +1530,14 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
                 );
                 unmatched_parent_urls.push(entry.url[..entry.url.len().min(200)].to_string());
             }
+            // Children the parent knows about but the index no longer has were evicted.
+            if let Some(parent) = parent {
+                entry.missing_children = parent
+                    .set_bits()
+                    .into_iter()
+                    .filter(|id| !entry.children.iter().any(|c| c.child_id == *id))
+                    .collect();
+            }
         }
     }
 
@@ -1182,6 +1566,7 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
 
     let sparse_linking_stats = SparseLinkingStats {
         total_children_linked: linked_count,
+        stale_children,
         orphaned_groups: orphaned_details.len(),
         orphaned_children_total,
         unmatched_parents: unmatched_parent_urls.len(),
@@ -1196,31 +1581,291 @@ fn parse_index_internal(dir: &Path) -> Result<BlockfileIndexResult, String> {
         entries,
         entry_count,
         version,
+        num_bytes,
+        block_files,
         errors,
         sparse_linking_stats,
     })
 }
 
+// ---------------------------------------------------------------------------
+// Orphaned EntryStores
+// ---------------------------------------------------------------------------
+// Dooming or evicting an entry unlinks it from its hash bucket and frees its
+// blocks in the allocation bitmap, but nothing is zeroed: the EntryStore
+// stays in its 256-byte block (state ENTRY_DOOMED or ENTRY_EVICTED) and its
+// streams stay where they were until the blocks are handed out again.
+// Scanning the 256-byte block files for EntryStores that no bucket reaches
+// finds those entries.
+
+/// EntryStore.state: ENTRY_NORMAL, ENTRY_EVICTED, ENTRY_DOOMED.
+const ENTRY_STATES: [&str; 3] = ["normal", "evicted", "doomed"];
+const ENTRY_STORE_SIZE: usize = 256;
+/// Longest key an orphan may claim; more means the block is stream data.
+const MAX_ORPHAN_KEY_LEN: u32 = 8192;
+/// Creation times (µs since 1601) between 2000 and 2100.
+const PLAUSIBLE_FILETIMES: std::ops::Range<u64> = 12_591_158_400_000_000..15_746_918_400_000_000;
+
+/// An EntryStore left in a block file that no index bucket reaches.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OrphanedBlockEntry {
+    pub url: String,
+    /// CacheAddr the EntryStore had, e.g. "0xA0010003"
+    pub entry_address: String,
+    pub entry_slot: BlockfileSlot,
+    /// "normal", "evicted" or "doomed"
+    pub state: &'static str,
+    pub creation_time: Option<f64>,
+    pub streams: Vec<BlockfileStreamSlot>,
+    /// Some of the body's blocks (or its `f_` file) now belong to a live entry
+    pub body_reused: bool,
+}
+
+/// Blocks an address covers, as (file selector, block); an `f_` file is
+/// (u32::MAX, file number).
+fn addr_blocks(addr: CacheAddr) -> Vec<(u32, u32)> {
+    if !addr.is_initialized() {
+        return Vec::new();
+    }
+    if addr.is_external() {
+        return vec![(u32::MAX, addr.file_number())];
+    }
+    (addr.start_block()..addr.start_block() + addr.num_blocks()).map(|b| (addr.file_selector(), b)).collect()
+}
+
+/// Decode `buf` as an EntryStore if it looks like one: a known state, a
+/// creation time in range, stream sizes that point somewhere, and a
+/// printable inline key or a long-key pointer.
+fn plausible_entry_store(buf: &[u8]) -> Option<RawEntry> {
+    let entry = parse_entry_store(buf)?;
+    let plausible = (entry.state as usize) < ENTRY_STATES.len()
+        && PLAUSIBLE_FILETIMES.contains(&entry.creation_time)
+        && (1..=MAX_ORPHAN_KEY_LEN).contains(&entry.key_len)
+        && (0..4).all(|i| {
+            let (size, addr) = (entry.data_size[i], entry.data_addr[i]);
+            size >= 0 && (size == 0 || addr.is_initialized()) && (!addr.is_initialized() || addr.file_type() != 1)
+        });
+    if !plausible {
+        return None;
+    }
+    if entry.long_key.is_initialized() {
+        return Some(entry);
+    }
+    let key = entry.key_data.get(..entry.key_len as usize)?;
+    key.iter().all(|b| (0x20..0x7F).contains(b)).then_some(entry)
+}
+
+/// Blocks an EntryStore with this key takes: the key is inline after the
+/// 0x60-byte header unless it needed a long-key allocation.
+fn entry_store_blocks(entry: &RawEntry) -> usize {
+    if entry.long_key.is_initialized() {
+        return 1;
+    }
+    (0x60 + entry.key_len as usize + 1).div_ceil(ENTRY_STORE_SIZE).clamp(1, 4)
+}
+
+/// Find every EntryStore in the 256-byte block files of the blockfile cache
+/// in `dir` that the index no longer reaches. Returns them with the errors
+/// met along the way.
+pub(crate) fn find_orphaned_entries(dir: &Path) -> Result<(Vec<OrphanedBlockEntry>, Vec<String>), String> {
+    let mut walk = walk_index(dir)?;
+    let mut live: HashSet<(u32, u32)> = HashSet::new();
+    for raw in &walk.raw_entries {
+        live.extend(addr_blocks(raw.addr));
+        for addr in raw.data_addr {
+            live.extend(addr_blocks(addr));
+        }
+    }
+
+    let mut candidates: Vec<(CacheAddr, RawEntry)> = Vec::new();
+    for file in walk.block_files.iter().filter(|f| f.entry_size as usize == ENTRY_STORE_SIZE) {
+        let selector: u32 = file.name.trim_start_matches("data_").parse().unwrap_or(0);
+        let path = crate::cache::resolve_child(dir, &file.name);
+        let Some(info) = walk.block_cache.get_or_load(&path, &mut walk.errors) else {
+            continue;
+        };
+        let blocks = (info.data.len() - BLOCK_HEADER_SIZE as usize) / ENTRY_STORE_SIZE;
+        let mut block = 0;
+        while block < blocks {
+            let at = BLOCK_HEADER_SIZE as usize + block * ENTRY_STORE_SIZE;
+            let buf = &info.data[at..(at + 4 * ENTRY_STORE_SIZE).min(info.data.len())];
+            let found = if live.contains(&(selector, block as u32)) { None } else { plausible_entry_store(buf) };
+            let Some(entry) = found else {
+                block += 1;
+                continue;
+            };
+            let n = entry_store_blocks(&entry);
+            let addr = INITIALIZED_MASK
+                | (2 << FILE_TYPE_SHIFT)
+                | (((n as u32) - 1) << _NUM_BLOCKS_SHIFT)
+                | (selector << FILE_SELECTOR_SHIFT)
+                | block as u32;
+            candidates.push((CacheAddr(addr), entry));
+            block += n;
+        }
+    }
+
+    // The stored hash must be the key's: that rules out stale stream data
+    // that happens to pass the field checks.
+    let orphans = candidates
+        .into_iter()
+        .filter_map(|(addr, entry)| {
+            let url = read_entry_key(&entry, dir, &mut walk.block_cache, &mut walk.errors)?;
+            (crate::key_hash::super_fast_hash(url.as_bytes()) == entry.hash).then_some((addr, entry, url))
+        })
+        .map(|(addr, entry, url)| OrphanedBlockEntry {
+            url,
+            entry_address: format!("0x{:08X}", addr.0),
+            entry_slot: addr.slot(),
+            state: ENTRY_STATES[entry.state as usize],
+            creation_time: Some(filetime_to_unix(entry.creation_time)),
+            streams: (0..4)
+                .filter(|&i| entry.data_addr[i].is_initialized() && entry.data_size[i] > 0)
+                .map(|i| BlockfileStreamSlot {
+                    stream_index: i as u32,
+                    size: entry.data_size[i] as u64,
+                    slot: entry.data_addr[i].slot(),
+                })
+                .collect(),
+            body_reused: addr_blocks(entry.data_addr[1]).iter().any(|b| live.contains(b)),
+        })
+        .collect();
+    Ok((orphans, walk.errors))
+}
+
+/// The response headers (lowercase names) and body of the orphaned
+/// EntryStore at `entry_address`, read from wherever its streams still point.
+pub(crate) fn read_orphaned_entry(
+    dir: &Path,
+    entry_address: u32,
+) -> Result<(HashMap<String, String>, Vec<u8>), String> {
+    let addr = CacheAddr(entry_address);
+    if !addr.is_initialized() || addr.file_type() != 2 {
+        return Err(format!("0x{:08X} is not an EntryStore address", entry_address));
+    }
+    let (mut block_cache, mut errors) = (BlockFileCache::new(), Vec::new());
+    let size = addr.data_length(ENTRY_STORE_SIZE as u32) as u32;
+    let entry = read_stream_data(addr, size, dir, &mut block_cache, &mut errors)
+        .and_then(|buf| plausible_entry_store(&buf))
+        .ok_or_else(|| format!("No EntryStore at 0x{:08X} in {}", entry_address, dir.display()))?;
+    let headers = read_stream_data(entry.data_addr[0], entry.data_size[0] as u32, dir, &mut block_cache, &mut errors)
+        .and_then(|stream0| parse_http_headers(&stream0).headers)
+        .unwrap_or_default();
+    let body = read_stream_data(entry.data_addr[1], entry.data_size[1] as u32, dir, &mut block_cache, &mut errors)
+        .ok_or_else(|| match errors.first() {
+            Some(e) => format!("Body of 0x{:08X} is gone: {}", entry_address, e),
+            None => format!("Entry 0x{:08X} has no body", entry_address),
+        })?;
+    Ok((headers, body))
+}
+
 // ---------------------------------------------------------------------------
 // Tauri commands
 // ---------------------------------------------------------------------------
 
-#[tauri::command]
-pub fn parse_blockfile_index(dir: String) -> Result<BlockfileIndexResult, String> {
+/// Parse a blockfile cache index. Each entry gets an eviction risk estimate;
+/// `max_cache_bytes` overrides the assumed cache size limit used for it.
+/// High-confidence videos are announced early via the `scan-hot-items` event.
+#[tauri::command(async)]
+pub fn parse_blockfile_index(
+    app: tauri::AppHandle,
+    dir: String,
+    max_cache_bytes: Option<u64>,
+) -> Result<BlockfileIndexResult, String> {
+    let dir_path = Path::new(&dir);
+    if !dir_path.exists() {
+        return Err(format!("Directory does not exist: {}", dir));
+    }
+    let mut hot = HotItemNotifier::for_app(app.clone());
+    let mut progress = Coalescer::for_app(app, events::INDEX_PROGRESS_EVENT);
+    let mut result = parse_index_internal(dir_path, Some(&mut hot), Some(&mut progress))?;
+    hot.flush();
+    progress.flush();
+    println!(
+        "[parse_blockfile_index] Sent {} progress events ({} coalesced away)",
+        progress.emitted(),
+        progress.dropped()
+    );
+    if hot.total() > 0 {
+        println!("[parse_blockfile_index] Announced {} hot items early", hot.total());
+    }
+
+    let inputs: Vec<RiskInput> = result
+        .entries
+        .iter()
+        .map(|e| RiskInput {
+            last_used: e.last_used.or(e.response_time).or(e.creation_time),
+            size: e.body_size,
+            reuse_count: e.reuse_count,
+        })
+        .collect();
+    let max_bytes = max_cache_bytes.unwrap_or(eviction_risk::DEFAULT_MAX_CACHE_BYTES);
+    let risks = eviction_risk::assess(&inputs, result.num_bytes, max_bytes);
+    for (entry, risk) in result.entries.iter_mut().zip(risks) {
+        entry.eviction_risk = Some(risk);
+    }
+    Ok(result)
+}
+
+/// List every entry in the index hash table with its URL key, creation time
+/// and the `f_` file or block slots holding each stream. Much cheaper than
+/// `parse_blockfile_index`: stream data (headers, bodies) is never read.
+#[tauri::command(async)]
+pub fn list_blockfile_entries(dir: String) -> Result<BlockfileEntryList, String> {
     let dir_path = Path::new(&dir);
     if !dir_path.exists() {
         return Err(format!("Directory does not exist: {}", dir));
     }
-    parse_index_internal(dir_path)
+    let walk = walk_index(dir_path)?;
+    let entries = walk
+        .raw_entries
+        .iter()
+        .map(|raw| BlockfileEntryListing {
+            url: raw.url.clone(),
+            effective_url: crate::cache_key::effective_url(&raw.url).to_string(),
+            creation_time: (raw.creation_time > 0).then(|| filetime_to_unix(raw.creation_time)),
+            entry_address: format!("0x{:08X}", raw.addr.0),
+            entry_slot: raw.addr.slot(),
+            streams: (0..4)
+                .filter(|&i| raw.data_addr[i].is_initialized() && raw.data_size[i] > 0)
+                .map(|i| BlockfileStreamSlot {
+                    stream_index: i as u32,
+                    size: raw.data_size[i] as u64,
+                    slot: raw.data_addr[i].slot(),
+                })
+                .collect(),
+            state: raw.state,
+            flags: raw.flags,
+        })
+        .collect();
+    Ok(BlockfileEntryList {
+        entries,
+        entry_count: walk.entry_count,
+        version: walk.version,
+        errors: walk.errors,
+    })
 }
 
+/// Rebuild the body of `url` from the blockfile index in `dir` into `output`.
+/// Requires a `consent` token covering `output`.
 #[tauri::command]
-pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Result<u64, String> {
+pub fn reconstruct_from_index(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    dir: String,
+    url: String,
+    output: String,
+    gap_policy: Option<GapPolicy>,
+    consent: String,
+) -> Result<ReconstructionResult, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("reconstructing {} from index", url));
+    let gap_policy = gap_policy.unwrap_or_default();
+    let mut result = ReconstructionResult::new(gap_policy);
     let dir_path = Path::new(&dir);
-    let result = parse_index_internal(dir_path)?;
+    let index = parse_index_internal(dir_path, None, None)?;
 
     // Find matching entry
-    let entry = result
+    let entry = index
         .entries
         .iter()
         .find(|e| e.url == url)
@@ -1234,7 +1879,7 @@ pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Resul
         // Sparse: read each child's data and place at correct byte offset.
         // children are already sorted by child_id.
         // Each child covers 1MB (offset_bytes = child_id << 20).
-        // We MUST zero-fill gaps for missing children so that moov's
+        // We MUST zero-fill gaps for missing children and unwritten blocks so that moov's
         // stco/co64 absolute byte offsets remain valid.
 
         println!(
@@ -1243,19 +1888,20 @@ pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Resul
             entry.url
         );
 
-        // Step 1: Read all children into a position-aware buffer
-        let mut raw_data: Vec<u8> = Vec::new();
-        for child in &entry.children {
-            let target_offset = child.offset_bytes as usize;
+        // Step 1: Read each child and keep only the blocks its bitmap marks
+        // as written, as (offset, bytes) chunks in body order.
+        let mut chunks: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (i, child) in entry.children.iter().enumerate() {
             let dr = &child.data_ref;
             let addr_path = Path::new(&dr.file_path);
 
             println!(
-                "[reconstruct_from_index] Child #{}: child_id={}, offset={}, data_size={} bytes, is_external={}, file={}",
-                entry.children.iter().position(|c| c.child_id == child.child_id).unwrap_or(0),
+                "[reconstruct_from_index] Child #{}: child_id={}, offset={}, data_size={} bytes, ranges={}, is_external={}, file={}",
+                i,
                 child.child_id,
-                target_offset,
+                child.offset_bytes,
                 dr.size,
+                child.ranges.len(),
                 dr.is_external,
                 &dr.file_path[dr.file_path.len().saturating_sub(30)..]
             );
@@ -1295,28 +1941,66 @@ pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Resul
                 }
                 info.data[offset..end].to_vec()
             };
+            for range in &child.ranges {
+                let start = (range[0] - child.offset_bytes) as usize;
+                let end = ((range[1] - child.offset_bytes) as usize).min(child_data.len());
+                if start < end {
+                    chunks.push((range[0], child_data[start..end].to_vec()));
+                }
+            }
+        }
+
+        // Step 2: Place the chunks in a position-aware buffer.
+        // With GapPolicy::CloseGaps, skipped gaps shift later chunks back.
+        let mut raw_data: Vec<u8> = Vec::new();
+        let mut dropped_bytes = 0usize;
+        for (offset, chunk) in &chunks {
+            let mut target_offset = (*offset as usize).saturating_sub(dropped_bytes);
 
-            // Zero-fill gap if this child starts beyond current buffer length
+            // Handle a gap if this chunk starts beyond current buffer length
             if target_offset > raw_data.len() {
                 let gap = target_offset - raw_data.len();
-                println!(
-                    "[reconstruct_from_index] Gap before child {}: {} bytes zero-fill",
-                    child.child_id, gap
-                );
-                raw_data.resize(raw_data.len() + gap, 0u8);
+                // Children hold 1 MiB each; name the ones the gap falls in.
+                let start = *offset - gap as u64;
+                let children = (start >> 20..(*offset).div_ceil(1 << 20)).map(|id| format!("child {}", id)).collect();
+                result.record_gap_range(start, gap as u64, children);
+                match gap_policy {
+                    GapPolicy::PadZeros => {
+                        println!(
+                            "[reconstruct_from_index] Gap before offset {}: {} bytes zero-fill",
+                            offset, gap
+                        );
+                        raw_data.resize(raw_data.len() + gap, 0u8);
+                    }
+                    GapPolicy::TruncateAtGap => {
+                        println!(
+                            "[reconstruct_from_index] Gap before offset {}: {} bytes — truncating here",
+                            offset, gap
+                        );
+                        result.truncated = true;
+                        break;
+                    }
+                    GapPolicy::CloseGaps => {
+                        println!(
+                            "[reconstruct_from_index] Gap before offset {}: {} bytes — closed up, later moov offsets stale",
+                            offset, gap
+                        );
+                        dropped_bytes += gap;
+                        target_offset = raw_data.len();
+                    }
+                }
             }
 
-            // Write child data at target offset
+            // Write chunk data at target offset
             if target_offset == raw_data.len() {
-                raw_data.extend_from_slice(&child_data);
+                raw_data.extend_from_slice(chunk);
             } else if target_offset < raw_data.len() {
                 // Overlapping — overwrite existing zeros/data
-                let end = target_offset + child_data.len();
+                let end = target_offset + chunk.len();
                 if end > raw_data.len() {
                     raw_data.resize(end, 0u8);
                 }
-                raw_data[target_offset..target_offset + child_data.len()]
-                    .copy_from_slice(&child_data);
+                raw_data[target_offset..end].copy_from_slice(chunk);
             }
         }
 
@@ -1326,7 +2010,7 @@ pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Resul
             raw_data.len() as f64 / 1024.0 / 1024.0
         );
 
-        // Step 2: Try MP4-aware reconstruction (ftyp/mdat/moov handling)
+        // Step 3: Try MP4-aware reconstruction (ftyp/mdat/moov handling)
         let ftyp_result = crate::find_mp4_box(&raw_data, b"ftyp");
         let mdat_result = crate::find_mp4_box(&raw_data, b"mdat");
         let moov_result = crate::scan_for_moov(&raw_data);
@@ -1411,16 +2095,15 @@ pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Resul
             );
 
             // 6. Patch mdat header with actual size
+            let mut widened = false;
             if mdat_header_size == 16 {
                 reconstructed[mdat_start + 8..mdat_start + 16]
                     .copy_from_slice(&actual_mdat_size.to_be_bytes());
             } else if actual_mdat_size > u32::MAX as u64 {
-                // Need to upgrade to 64-bit mdat header
-                let mut new_header = Vec::with_capacity(16);
-                new_header.extend_from_slice(&1u32.to_be_bytes());
-                new_header.extend_from_slice(b"mdat");
-                new_header.extend_from_slice(&(actual_mdat_size + 8).to_be_bytes());
-                reconstructed.splice(mdat_start..mdat_start + 8, new_header);
+                // Need to upgrade to 64-bit mdat header; the media moves 8 bytes
+                // on, so the moov's chunk offsets move with it.
+                crate::mp4_samples::widen_mdat_header(&mut reconstructed, mdat_start, actual_mdat_size)?;
+                widened = true;
             } else {
                 reconstructed[mdat_start..mdat_start + 4]
                     .copy_from_slice(&(actual_mdat_size as u32).to_be_bytes());
@@ -1435,13 +2118,16 @@ pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Resul
                     // Streaming layout: moov after mdat — append it
                     let moov_end = (moov_off + moov_sz).min(raw_data.len());
                     if moov_off < raw_data.len() {
-                        let moov_data = &raw_data[moov_off..moov_end];
+                        let mut moov_data = raw_data[moov_off..moov_end].to_vec();
+                        if widened {
+                            crate::mp4_samples::shift_chunk_offsets(&mut moov_data, 8)?;
+                        }
                         println!(
                             "[reconstruct_from_index] Appending moov ({} bytes) at file offset {}",
                             moov_data.len(),
                             reconstructed.len()
                         );
-                        reconstructed.extend_from_slice(moov_data);
+                        reconstructed.extend_from_slice(&moov_data);
                     }
                 } else {
                     // Faststart layout: moov before mdat — already included in gap
@@ -1516,24 +2202,124 @@ pub fn reconstruct_from_index(dir: String, url: String, output: String) -> Resul
     }
 
     // Write output
-    let output_path = Path::new(&output);
-    if let Some(parent) = output_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Cannot create output directory: {}", e))?;
-    }
-
-    let mut file = fs::File::create(output_path)
-        .map_err(|e| format!("Cannot create output file {}: {}", output, e))?;
+    crate::output_file::write_output(&output, &output_data)?;
 
-    file.write_all(&output_data)
-        .map_err(|e| format!("Cannot write output file: {}", e))?;
-
-    Ok(output_data.len() as u64)
+    result.finish(&output_data);
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn child_bitmap_gives_written_ranges() {
+        // Child 3: blocks 0-1 full, block 5 written up to 100 bytes.
+        let mut buf = vec![0u8; SPARSE_HEADER_SIZE + 128];
+        buf[0..8].copy_from_slice(&0x1234_5678_9abc_def0u64.to_le_bytes());
+        buf[8..12].copy_from_slice(&INDEX_MAGIC.to_le_bytes());
+        buf[16..20].copy_from_slice(&5i32.to_le_bytes());
+        buf[20..24].copy_from_slice(&100i32.to_le_bytes());
+        buf[SPARSE_HEADER_SIZE] = 0b11;
+        let sparse = SparseData::parse(&buf).unwrap();
+        assert_eq!(sparse.signature, 0x1234_5678_9abc_def0);
+        assert_eq!(sparse.set_bits(), vec![0, 1]);
+
+        let base = 3 * SPARSE_CHILD_SIZE;
+        assert_eq!(
+            child_ranges(3, Some(&sparse), 8192),
+            vec![[base, base + 2048], [base + 5120, base + 5220]]
+        );
+        // Stream 1 shorter than the bitmap claims: clipped to what exists.
+        assert_eq!(child_ranges(3, Some(&sparse), 1500), vec![[base, base + 1500]]);
+        assert_eq!(child_ranges(3, None, 700), vec![[base, base + 700]]);
+        assert!(SparseData::parse(&buf[..40]).is_none());
+    }
+
+    #[test]
+    fn rankings_give_eviction_order_and_times() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-rankings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let node_addr = |block: u32| CacheAddr(INITIALIZED_MASK | (1 << FILE_TYPE_SHIFT) | block);
+        let entry_addr = |i: u32| CacheAddr(0xA001_0000 + i);
+        // NO_USE list, head → tail: block 0, 1, 2. The head's prev is itself.
+        let mut data_0 = vec![0u8; BLOCK_HEADER_SIZE as usize + 3 * RANKINGS_NODE_SIZE as usize];
+        data_0[0..4].copy_from_slice(&BLOCK_MAGIC.to_le_bytes());
+        data_0[0x0C..0x10].copy_from_slice(&RANKINGS_NODE_SIZE.to_le_bytes());
+        for (block, prev) in [(0u32, 0u32), (1, 0), (2, 1)] {
+            let at = BLOCK_HEADER_SIZE as usize + block as usize * RANKINGS_NODE_SIZE as usize;
+            let used = 13_300_000_000_000_000u64 + block as u64 * 1_000_000;
+            data_0[at..at + 8].copy_from_slice(&used.to_le_bytes());
+            data_0[at + 0x14..at + 0x18].copy_from_slice(&node_addr(prev).0.to_le_bytes());
+            data_0[at + 0x18..at + 0x1C].copy_from_slice(&entry_addr(block).0.to_le_bytes());
+        }
+        fs::write(dir.join("data_0"), &data_0).unwrap();
+        let mut index = vec![0u8; INDEX_HEADER_SIZE];
+        index[LRU_DATA_OFFSET + 0x0C..LRU_DATA_OFFSET + 0x10].copy_from_slice(&3i32.to_le_bytes());
+        index[LRU_DATA_OFFSET + 0x34..LRU_DATA_OFFSET + 0x38].copy_from_slice(&node_addr(2).0.to_le_bytes());
+
+        let (mut cache, mut errors) = (BlockFileCache::new(), Vec::new());
+        let rankings = read_rankings(&LruLists::parse(&index), &dir, &mut cache, &mut errors);
+        let node = read_rankings_node(node_addr(1), &dir, &mut cache, &mut errors).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        let order: Vec<u32> = (0..3).map(|i| rankings[&entry_addr(i).0].eviction_order).collect();
+        assert_eq!(order, vec![2, 1, 0]);
+        assert_eq!(rankings[&entry_addr(0).0].list, "no_use");
+        assert_eq!(node.contents, entry_addr(1));
+        assert_eq!(filetime_to_unix(node.last_used).round(), 1_655_526_401.0);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn orphaned_entry_store_is_found_and_read() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-orphans-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut index = vec![0u8; INDEX_HEADER_SIZE + 16 * 4];
+        index[0..4].copy_from_slice(&INDEX_MAGIC.to_le_bytes());
+        index[4..8].copy_from_slice(&0x30000u32.to_le_bytes());
+        index[0x1C..0x20].copy_from_slice(&16u32.to_le_bytes());
+        let live_addr = 0xA001_0000u32;
+        index[INDEX_HEADER_SIZE..INDEX_HEADER_SIZE + 4].copy_from_slice(&live_addr.to_le_bytes());
+        fs::write(dir.join("index"), &index).unwrap();
+
+        let mut data_1 = vec![0u8; BLOCK_HEADER_SIZE as usize + 4 * ENTRY_STORE_SIZE];
+        data_1[0..4].copy_from_slice(&BLOCK_MAGIC.to_le_bytes());
+        data_1[0x08..0x0A].copy_from_slice(&1i16.to_le_bytes());
+        data_1[0x0C..0x10].copy_from_slice(&(ENTRY_STORE_SIZE as u32).to_le_bytes());
+        let body = b"\x00\x00\x00\x18ftypmp42 doomed video";
+        let mut put_entry = |block: usize, key: &str, state: u32| {
+            let at = BLOCK_HEADER_SIZE as usize + block * ENTRY_STORE_SIZE;
+            let store = &mut data_1[at..at + ENTRY_STORE_SIZE];
+            store[0x00..0x04].copy_from_slice(&crate::key_hash::super_fast_hash(key.as_bytes()).to_le_bytes());
+            store[0x14..0x18].copy_from_slice(&state.to_le_bytes());
+            store[0x18..0x20].copy_from_slice(&13_300_000_000_000_000u64.to_le_bytes());
+            store[0x20..0x24].copy_from_slice(&(key.len() as u32).to_le_bytes());
+            store[0x2C..0x30].copy_from_slice(&(body.len() as i32).to_le_bytes());
+            store[0x3C..0x40].copy_from_slice(&0x8000_0001u32.to_le_bytes());
+            store[0x60..0x60 + key.len()].copy_from_slice(key.as_bytes());
+        };
+        put_entry(0, "https://example.com/live.mp4", 0);
+        put_entry(2, "https://example.com/doomed.mp4", 2);
+        fs::write(dir.join("data_1"), &data_1).unwrap();
+        fs::write(dir.join("f_000001"), body).unwrap();
+
+        let (orphans, _) = find_orphaned_entries(&dir).unwrap();
+        let read = orphans.first().map(|o| {
+            let addr = u32::from_str_radix(o.entry_address.trim_start_matches("0x"), 16).unwrap();
+            read_orphaned_entry(&dir, addr).unwrap()
+        });
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(orphans.len(), 1);
+        let orphan = &orphans[0];
+        assert_eq!((orphan.url.as_str(), orphan.state), ("https://example.com/doomed.mp4", "doomed"));
+        assert_eq!(orphan.entry_slot.start_block, Some(2));
+        // The live entry's body is the same f_ file.
+        assert!(orphan.body_reused);
+        assert_eq!(read.unwrap().1, body.to_vec());
+    }
+
     #[test]
     fn diagnose_discord_cache() {
         let cache_dir = std::env::var("APPDATA")
@@ -1545,11 +2331,15 @@ mod tests {
             return;
         }
         println!("Reading Discord cache at {:?}", dir);
-        match parse_index_internal(dir) {
+        match parse_index_internal(dir, None, None) {
             Ok(result) => {
                 println!("\n=== RESULT ===");
                 println!("Entries: {}, Entry count: {}", result.entries.len(), result.entry_count);
                 println!("Errors: {}", result.errors.len());
+                for bf in &result.block_files {
+                    println!("Block file {}: entry_size={} used={}/{} next={:?}",
+                        bf.name, bf.entry_size, bf.num_entries, bf.max_entries, bf.next_file);
+                }
                 println!("Sparse linking: linked={}, orphaned_groups={}, unmatched_parents={}",
                     result.sparse_linking_stats.total_children_linked,
                     result.sparse_linking_stats.orphaned_groups,
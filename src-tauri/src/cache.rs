@@ -1,13 +1,30 @@
 use serde::Serialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use crate::eviction_risk::{self, EvictionRisk, RiskInput};
+
 #[derive(Debug, Serialize)]
 pub struct CachePathInfo {
     pub path: String,
+    /// `path` with the home / AppData folder abbreviated (`~/...`, `%APPDATA%\...`)
+    pub display_path: String,
     pub exists: bool,
     pub file_count: usize,
     pub total_size: u64,
     pub client_name: String,
+    /// "http_cache", "profile_root", "unknown", "snapshot" (an archive not
+    /// extracted yet), or a non-media cache kind ("code_cache", "gpu_cache",
+    /// "dawn_cache", "shader_cache")
+    pub kind: &'static str,
+    /// Human-readable explanation for anything other than an HTTP cache
+    pub kind_label: Option<&'static str>,
+    /// For a profile root: the HTTP cache folder that will actually be scanned
+    pub resolved_path: Option<String>,
+    /// Which Discord build wrote the cache, for Discord folders
+    pub discord_build: Option<crate::discord_build::DiscordBuild>,
+    /// `path` is a ZIP or tar archive; `resolved_path` is its extracted cache folder
+    pub from_archive: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -16,6 +33,131 @@ pub struct CacheFileEntry {
     pub path: String,
     pub size: u64,
     pub modified_at: f64,
+    /// Last time the browser used the entry (unix seconds), from the-real-index
+    pub last_used: Option<f64>,
+    /// Size of the whole entry (all streams) according to the-real-index
+    pub index_size: Option<u64>,
+    /// Likelihood the entry is evicted soon; only set when the-real-index was readable
+    pub eviction_risk: Option<EvictionRisk>,
+}
+
+// ─── Simple Cache index (index-dir/the-real-index) ──────────────────────────
+//
+// The Simple Cache backend keeps one Pickle-serialized index per cache
+// (net/disk_cache/simple/simple_index_file.cc):
+//   [u32 payload_size] [u32 crc]
+//   IndexMetadata: u64 magic, u32 version, u64 entry_count, u64 cache_size,
+//                  u32 write_reason (version >= 7)
+//   entry_count x { u64 entry_hash, i64 last_used (µs since 1601),
+//                   u64 size — bytes before v8, (size / 256) << 8 | flags from v8 }
+//   i64 index_last_modified
+// Pickle only aligns to 4 bytes, so u64 fields are not 8-byte aligned.
+// `entry_hash` is the same value as the `{16 hex}` prefix of the entry files.
+
+const SIMPLE_INDEX_MAGIC: u64 = 0x656e74657220796f;
+/// Oldest index version we understand (Chromium ~2014).
+const SIMPLE_INDEX_MIN_VERSION: u32 = 6;
+const SIMPLE_INDEX_MAX_VERSION: u32 = 9;
+const SIMPLE_INDEX_ENTRY_SIZE: usize = 24;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimpleIndexEntry {
+    /// Unix seconds; None when the index stored no time
+    pub last_used: Option<f64>,
+    /// Bytes across all of the entry's streams (rounded up to 256 from v8)
+    pub size: u64,
+}
+
+#[derive(Debug)]
+pub struct SimpleIndex {
+    pub version: u32,
+    /// Total cache size recorded in the index
+    pub cache_size: u64,
+    /// Keyed by entry hash
+    pub entries: HashMap<u64, SimpleIndexEntry>,
+}
+
+fn read_u32(buf: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buf.get(off..off + 4)?.try_into().ok()?))
+}
+
+fn read_u64(buf: &[u8], off: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(buf.get(off..off + 8)?.try_into().ok()?))
+}
+
+/// Parse the bytes of a `the-real-index` file.
+pub fn parse_simple_index(data: &[u8]) -> Result<SimpleIndex, String> {
+    let magic = read_u64(data, 8).ok_or("Index file too small")?;
+    if magic != SIMPLE_INDEX_MAGIC {
+        return Err(format!("Bad index magic: 0x{:016x}", magic));
+    }
+    let version = read_u32(data, 16).ok_or("Index file too small")?;
+    if version < SIMPLE_INDEX_MIN_VERSION {
+        return Err(format!("Unsupported index version: {}", version));
+    }
+    let entry_count = read_u64(data, 20).ok_or("Index file too small")?;
+    let cache_size = read_u64(data, 28).ok_or("Index file too small")?;
+    if version > SIMPLE_INDEX_MAX_VERSION {
+        // Newer than we know: read it with the newest layout.
+        crate::compat::record(
+            "simple_index",
+            format!("version {}", version),
+            data.len() as u64,
+            &[
+                ("version", version as u64),
+                ("entry_count", entry_count),
+                ("payload_size", read_u32(data, 0).unwrap_or(0) as u64),
+            ],
+        );
+    }
+    let mut pos = if version >= 7 { 40 } else { 36 };
+
+    let mut entries = HashMap::new();
+    for _ in 0..entry_count {
+        let (Some(hash), Some(last_used), Some(size)) = (
+            read_u64(data, pos),
+            read_u64(data, pos + 8),
+            read_u64(data, pos + 16),
+        ) else {
+            // Truncated index: keep what was readable.
+            eprintln!(
+                "[cache] the-real-index truncated after {} of {} entries",
+                entries.len(),
+                entry_count
+            );
+            break;
+        };
+        let last_used = last_used as i64;
+        let size = if version >= 8 { (size >> 8) * 256 } else { size };
+        entries.insert(
+            hash,
+            SimpleIndexEntry {
+                last_used: (last_used > 0)
+                    .then(|| last_used as f64 / 1_000_000.0 - 11_644_473_600.0),
+                size,
+            },
+        );
+        pos += SIMPLE_INDEX_ENTRY_SIZE;
+    }
+
+    Ok(SimpleIndex { version, cache_size, entries })
+}
+
+/// Read `index-dir/the-real-index` from a Simple Cache directory.
+pub fn read_simple_index(cache_dir: &Path) -> Result<SimpleIndex, String> {
+    let path = resolve_child(&resolve_child(cache_dir, "index-dir"), "the-real-index");
+    let data = std::fs::read(&path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    parse_simple_index(&data)
+}
+
+/// Entry hash from a Simple Cache file name (`{16 hex}_{stream}`).
+pub(crate) fn simple_entry_hash(name: &str) -> Option<u64> {
+    let (hash, _) = name.split_once('_')?;
+    if hash.len() != 16 {
+        return None;
+    }
+    u64::from_str_radix(hash, 16).ok()
 }
 
 pub fn get_default_cache_paths() -> Vec<String> {
@@ -36,6 +178,14 @@ pub fn get_default_cache_paths() -> Vec<String> {
                     .join("Cache_Data");
                 paths.push(p.to_string_lossy().to_string());
             }
+            collect_slack_caches(&PathBuf::from(&appdata).join("Slack"), &mut paths);
+            // WhatsApp Desktop (Electron build)
+            let whatsapp = PathBuf::from(&appdata).join("WhatsApp");
+            paths.push(resolve_cache_dir(&whatsapp).to_string_lossy().to_string());
+            paths.push(resolve_cache_dir(&PathBuf::from(&appdata).join("Signal")).to_string_lossy().to_string());
+            // Classic (Electron) Teams; new Teams is a WebView2 app found below.
+            let teams = PathBuf::from(&appdata).join("Microsoft").join("Teams");
+            paths.push(resolve_cache_dir(&teams).to_string_lossy().to_string());
         }
         if let Ok(localappdata) = std::env::var("LOCALAPPDATA") {
             // Browsers with User Data/profile structure
@@ -43,6 +193,9 @@ pub fn get_default_cache_paths() -> Vec<String> {
                 "Google/Chrome",
                 "BraveSoftware/Brave-Browser",
                 "Microsoft/Edge",
+                "Vivaldi",
+                "Yandex/YandexBrowser",
+                "Chromium",
             ];
             for browser in browsers_with_profiles {
                 let user_data_dir = PathBuf::from(&localappdata).join(browser).join("User Data");
@@ -54,6 +207,57 @@ pub fn get_default_cache_paths() -> Vec<String> {
                 .join("Cache")
                 .join("Cache_Data");
             paths.push(opera_cache.to_string_lossy().to_string());
+            let opera_gx_cache = PathBuf::from(&localappdata)
+                .join("Opera Software/Opera GX Stable")
+                .join("Cache")
+                .join("Cache_Data");
+            paths.push(opera_gx_cache.to_string_lossy().to_string());
+
+            // WebView2 hosts: packaged apps (new Teams, Store apps) under
+            // Packages\<family>\..., unpackaged apps under their own folder.
+            let local = PathBuf::from(&localappdata);
+            if let Ok(packages) = std::fs::read_dir(local.join("Packages")) {
+                for package in packages.flatten() {
+                    collect_webview2_caches(&package.path(), WEBVIEW2_PACKAGE_DEPTH, &mut paths);
+                }
+            }
+            collect_webview2_caches(&local, WEBVIEW2_APP_DEPTH, &mut paths);
+            collect_spotify_caches(&local.join("Spotify"), &mut paths);
+
+            // Microsoft Store Slack, WhatsApp and Spotify redirect their data into the package.
+            if let Ok(packages) = std::fs::read_dir(local.join("Packages")) {
+                for package in packages.flatten() {
+                    let name = package.file_name().to_string_lossy().to_lowercase();
+                    if name.contains(".slack_") {
+                        let slack = package.path().join("LocalCache").join("Roaming").join("Slack");
+                        if slack.is_dir() {
+                            collect_slack_caches(&slack, &mut paths);
+                        }
+                    } else if name.contains(".whatsappdesktop_") {
+                        // Store build of the Electron app; the WebView2 build
+                        // (LocalState\EBWebView) is found by the scan above.
+                        let whatsapp = package.path().join("LocalCache").join("Roaming").join("WhatsApp");
+                        if whatsapp.is_dir() {
+                            paths.push(resolve_cache_dir(&whatsapp).to_string_lossy().to_string());
+                        }
+                    } else if name.starts_with("thebrowsercompany.arc_") {
+                        // Arc is a Store app; its User Data sits inside the package.
+                        let arc = package.path().join("LocalCache").join("Local").join("Arc").join("User Data");
+                        if arc.is_dir() {
+                            collect_chromium_profiles(&arc, &mut paths);
+                        }
+                    } else if name.starts_with("spotifyab.spotifymusic_") {
+                        let spotify = package.path().join("LocalCache").join("Spotify");
+                        if spotify.is_dir() {
+                            collect_spotify_caches(&spotify, &mut paths);
+                        }
+                    }
+                }
+            }
+        }
+        // Any other Electron app, once the known clients are in.
+        if let Ok(appdata) = std::env::var("APPDATA") {
+            collect_electron_caches(&PathBuf::from(&appdata), &mut paths);
         }
     }
     #[cfg(target_os = "macos")]
@@ -62,14 +266,36 @@ pub fn get_default_cache_paths() -> Vec<String> {
             let app_support = PathBuf::from(&home).join("Library/Application Support");
             let lib_caches = PathBuf::from(&home).join("Library/Caches");
             for client in &discord_clients {
-                let p = app_support.join(client).join("Cache/Cache_Data");
+                let p = resolve_cache_dir(&app_support.join(client));
                 paths.push(p.to_string_lossy().to_string());
             }
+            collect_slack_caches(&app_support.join("Slack"), &mut paths);
+            // Mac App Store build is sandboxed
+            let slack_mas = PathBuf::from(&home)
+                .join("Library/Containers/com.tinyspeck.slackmacgap/Data/Library/Application Support/Slack");
+            if slack_mas.is_dir() {
+                collect_slack_caches(&slack_mas, &mut paths);
+            }
+            // WhatsApp Desktop (Electron build; the native app has no Chromium cache)
+            paths.push(resolve_cache_dir(&app_support.join("WhatsApp")).to_string_lossy().to_string());
+            paths.push(resolve_cache_dir(&app_support.join("Signal")).to_string_lossy().to_string());
+            let teams_classic = app_support.join("Microsoft/Teams");
+            paths.push(resolve_cache_dir(&teams_classic).to_string_lossy().to_string());
+            // New Teams ships the WebView2 runtime on macOS too, inside its container.
+            let teams2 = PathBuf::from(&home)
+                .join("Library/Containers/com.microsoft.teams2/Data/Library/Application Support/Microsoft");
+            collect_webview2_caches(&teams2, WEBVIEW2_APP_DEPTH, &mut paths);
+            collect_spotify_caches(&lib_caches.join("com.spotify.client"), &mut paths);
 
             let browsers: &[(&str, &str)] = &[
                 ("Google/Chrome", "Google/Chrome"),
                 ("BraveSoftware/Brave-Browser", "BraveSoftware/Brave-Browser"),
                 ("Microsoft Edge", "Microsoft Edge"),
+                ("Vivaldi", "Vivaldi"),
+                ("Yandex/YandexBrowser", "Yandex/YandexBrowser"),
+                ("Chromium", "Chromium"),
+                // Arc nests its profiles under User Data, like Chrome on Windows
+                ("Arc/User Data", "Arc/User Data"),
             ];
             for (app_support_name, caches_name) in browsers {
                 collect_chromium_profiles(&app_support.join(app_support_name), &mut paths);
@@ -79,63 +305,396 @@ pub fn get_default_cache_paths() -> Vec<String> {
             // Opera now uses Default profile subfolder (Chromium layout)
             collect_chromium_profiles(&app_support.join("com.operasoftware.Opera"), &mut paths);
             collect_chromium_profiles(&lib_caches.join("com.operasoftware.Opera"), &mut paths);
+            collect_chromium_profiles(&app_support.join("com.operasoftware.OperaGX"), &mut paths);
+            collect_chromium_profiles(&lib_caches.join("com.operasoftware.OperaGX"), &mut paths);
+
+            // Any other Electron app
+            collect_electron_caches(&app_support, &mut paths);
         }
     }
     #[cfg(target_os = "linux")]
     {
         if let Ok(home) = std::env::var("HOME") {
-            let config_dir = PathBuf::from(&home).join(".config");
-            let cache_dir = PathBuf::from(&home).join(".cache");
-            for client in &discord_clients {
-                let p = config_dir.join(client).join("Cache/Cache_Data");
-                paths.push(p.to_string_lossy().to_string());
-            }
+            let home = PathBuf::from(&home);
+            collect_xdg_caches(&home.join(".config"), &home.join(".cache"), &discord_clients, &mut paths);
+            collect_sandboxed_caches(&home, &discord_clients, &mut paths);
+        }
+    }
 
-            // Chromium browsers store profile data in ~/.config/ but cache in ~/.cache/
-            let browsers_config: &[&str] = &[
-                "google-chrome",
-                "BraveSoftware/Brave-Browser",
-                "microsoft-edge",
-            ];
-            let browsers_cache: &[&str] = &[
-                "google-chrome",
-                "BraveSoftware/Brave-Browser",
-                "microsoft-edge",
-            ];
-            for browser in browsers_config {
-                collect_chromium_profiles(&config_dir.join(browser), &mut paths);
-            }
-            for browser in browsers_cache {
-                collect_chromium_profiles(&cache_dir.join(browser), &mut paths);
-            }
+    paths
+}
+
+/// Linux apps keep profile data under `config_dir` (`~/.config`) and, for
+/// Chromium browsers, the cache itself under `cache_dir` (`~/.cache`).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn collect_xdg_caches(config_dir: &Path, cache_dir: &Path, discord_clients: &[&str], paths: &mut Vec<String>) {
+    for client in discord_clients {
+        let p = resolve_cache_dir(&config_dir.join(client));
+        paths.push(p.to_string_lossy().to_string());
+    }
+    collect_slack_caches(&config_dir.join("Slack"), paths);
+    paths.push(resolve_cache_dir(&config_dir.join("Signal")).to_string_lossy().to_string());
+    let teams = config_dir.join("Microsoft").join("Microsoft Teams");
+    paths.push(resolve_cache_dir(&teams).to_string_lossy().to_string());
+    collect_spotify_caches(&cache_dir.join("spotify"), paths);
 
-            // Opera
-            collect_chromium_profiles(&config_dir.join("opera"), &mut paths);
-            collect_chromium_profiles(&cache_dir.join("opera"), &mut paths);
+    // Chromium browsers store profile data in ~/.config/ but cache in ~/.cache/
+    // (Arc and Opera GX have no Linux builds.)
+    let browsers: &[&str] = &[
+        "google-chrome",
+        "BraveSoftware/Brave-Browser",
+        "microsoft-edge",
+        "vivaldi",
+        "yandex-browser",
+        "chromium",
+        "opera",
+    ];
+    for browser in browsers {
+        collect_chromium_profiles(&config_dir.join(browser), paths);
+    }
+    for browser in browsers {
+        collect_chromium_profiles(&cache_dir.join(browser), paths);
+    }
+
+    // Any other Electron app
+    collect_electron_caches(config_dir, paths);
+}
+
+/// Flatpak and Snap apps get private XDG dirs: `~/.var/app/<id>/config` and
+/// `.../cache` for Flatpak; `~/snap/<name>/current/.config` and
+/// `~/snap/<name>/common/.cache` for Snap (the Chromium snap keeps its
+/// profile directly in `common/`). Each is searched like `~/.config`, but
+/// only caches that exist are added.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn collect_sandboxed_caches(home: &Path, discord_clients: &[&str], paths: &mut Vec<String>) {
+    let mut roots: Vec<(PathBuf, PathBuf)> = Vec::new();
+    if let Ok(apps) = std::fs::read_dir(home.join(".var").join("app")) {
+        for app in apps.flatten() {
+            let app = app.path();
+            roots.push((app.join("config"), app.join("cache")));
         }
     }
+    if let Ok(snaps) = std::fs::read_dir(home.join("snap")) {
+        for snap in snaps.flatten() {
+            let snap = snap.path();
+            let cache = snap.join("common").join(".cache");
+            roots.push((snap.join("current").join(".config"), cache.clone()));
+            roots.push((snap.join("common"), cache));
+        }
+    }
+    roots.sort();
 
-    paths
+    let mut found = Vec::new();
+    for (config, cache) in roots.iter().filter(|(config, cache)| config.is_dir() || cache.is_dir()) {
+        collect_xdg_caches(config, cache, discord_clients, &mut found);
+    }
+    for path in found {
+        if Path::new(&path).is_dir() && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+}
+
+/// `dir/name`, matching `name` case-insensitively when the exact spelling is
+/// absent. Case-sensitive volumes (Linux, case-sensitive APFS) don't fold
+/// "Cache_data" into "Cache_Data" or "F_00A1B2" into "f_00a1b2", so a miss
+/// there lists `dir` and takes the first case-insensitive match. Returns
+/// `dir/name` unchanged if nothing matches.
+pub fn resolve_child(dir: &Path, name: &str) -> PathBuf {
+    let exact = dir.join(name);
+    // Windows volumes are case-insensitive: a miss is a real miss.
+    if cfg!(target_os = "windows") || exact.exists() {
+        return exact;
+    }
+    std::fs::read_dir(dir)
+        .ok()
+        .and_then(|entries| {
+            entries
+                .flatten()
+                .find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+                .map(|e| e.path())
+        })
+        .unwrap_or(exact)
 }
 
-/// Resolve the cache directory for a given profile path.
-/// Checks `Cache/Cache_Data` first, then falls back to `Cache/`.
-/// Returns the path that exists, or `Cache/Cache_Data` as default.
+/// Where a profile's HTTP cache may live, in order of preference. Newer
+/// Chrome/Edge builds can keep it under `Network/` beside the network state,
+/// and some platforms use `Cache` without `Cache_Data`.
+const PROFILE_CACHE_DIRS: &[&[&str]] = &[
+    &["Cache", "Cache_Data"],
+    &["Network", "Cache", "Cache_Data"],
+    &["Network", "Cache"],
+    &["Cache"],
+];
+/// The media cache older Chrome builds kept beside the HTTP cache (blockfile).
+const MEDIA_CACHE_DIR: &str = "Media Cache";
+/// Storage partitions of extensions and Chrome apps: `Storage/ext/<id>/def`,
+/// each laid out like a small profile with its own HTTP cache.
+const EXTENSION_STORAGE_DIR: &[&str] = &["Storage", "ext"];
+
+/// `dir` joined with each of `parts`, resolved case-insensitively.
+fn resolve_path(dir: &Path, parts: &[&str]) -> PathBuf {
+    parts.iter().fold(dir.to_path_buf(), |path, part| resolve_child(&path, part))
+}
+
+/// Resolve the cache directory for a given profile path: the first of
+/// `PROFILE_CACHE_DIRS` holding cache files, else the first that exists,
+/// else `Cache/Cache_Data` as the canonical default.
 fn resolve_cache_dir(profile_dir: &Path) -> PathBuf {
-    let cache_data = profile_dir.join("Cache").join("Cache_Data");
-    if cache_data.is_dir() {
-        return cache_data;
+    let candidates: Vec<PathBuf> = PROFILE_CACHE_DIRS.iter().map(|parts| resolve_path(profile_dir, parts)).collect();
+    candidates
+        .iter()
+        .find(|c| has_cache_files(c))
+        .or_else(|| candidates.iter().find(|c| c.is_dir()))
+        .cloned()
+        .unwrap_or_else(|| candidates[0].clone())
+}
+
+/// Caches of a profile other than `main` (its `resolve_cache_dir`) that hold
+/// cache files: a second HTTP cache location left behind by a browser
+/// update, the legacy `Media Cache`, and extension storage partitions.
+fn profile_extra_caches(profile_dir: &Path, main: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = PROFILE_CACHE_DIRS.iter().map(|parts| resolve_path(profile_dir, parts)).collect();
+    found.push(resolve_child(profile_dir, MEDIA_CACHE_DIR));
+    if let Ok(extensions) = std::fs::read_dir(resolve_path(profile_dir, EXTENSION_STORAGE_DIR)) {
+        let mut partitions: Vec<PathBuf> = extensions.flatten().map(|e| resolve_child(&e.path(), "def")).collect();
+        partitions.sort();
+        found.extend(partitions.iter().map(|def| resolve_cache_dir(def)));
     }
-    let cache_only = profile_dir.join("Cache");
-    if cache_only.is_dir() {
-        return cache_only;
+    let mut extra: Vec<PathBuf> = Vec::new();
+    for dir in found {
+        if dir != main && !main.starts_with(&dir) && !extra.contains(&dir) && has_cache_files(&dir) {
+            extra.push(dir);
+        }
     }
-    // Neither exists yet — return Cache_Data as the canonical default
-    cache_data
+    extra
+}
+// ─── Directory classification ───────────────────────────────────────────────
+//
+// Chromium profiles hold several caches besides the HTTP cache. `Code Cache`
+// (V8 bytecode) uses the Simple Cache file layout and GPUCache uses blockfile
+// layout, so both look like media caches to `is_cache_file` but contain
+// nothing recoverable. They are recognized by folder name — on the folder
+// itself or any ancestor, so `Code Cache/js` is caught too — and excluded.
+
+/// Non-media cache folders: (folder name, kind, label).
+const NON_MEDIA_CACHE_DIRS: &[(&str, &str, &str)] = &[
+    ("Code Cache", "code_cache", "V8 code cache (compiled JavaScript, no media)"),
+    ("GPUCache", "gpu_cache", "GPU shader cache (no media)"),
+    ("GrShaderCache", "shader_cache", "Skia shader cache (no media)"),
+    ("GraphiteDawnCache", "dawn_cache", "Dawn/WebGPU shader cache (no media)"),
+    ("DawnCache", "dawn_cache", "Dawn/WebGPU shader cache (no media)"),
+    ("DawnGraphiteCache", "dawn_cache", "Dawn/WebGPU shader cache (no media)"),
+    ("DawnWebGPUCache", "dawn_cache", "Dawn/WebGPU shader cache (no media)"),
+    ("ShaderCache", "shader_cache", "GPU shader cache (no media)"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirClass {
+    pub kind: &'static str,
+    pub label: Option<&'static str>,
+    /// Folder whose files are listed: the dir itself, or a profile's HTTP
+    /// cache. `None` for non-media caches.
+    pub scan_dir: Option<PathBuf>,
+}
+
+/// Blockfile caches have an `index` file next to `data_0`.
+pub fn is_blockfile_dir(dir: &Path) -> bool {
+    resolve_child(dir, "index").is_file() && resolve_child(dir, "data_0").is_file()
 }
+
+fn has_cache_files(dir: &Path) -> bool {
+    if resolve_child(dir, "index-dir").is_dir() || is_blockfile_dir(dir) {
+        return true;
+    }
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| is_cache_file(&e.file_name().to_string_lossy()))
+        })
+        .unwrap_or(false)
+}
+
+/// Classify a folder the user pointed the app at.
+pub fn classify_dir(dir: &Path) -> DirClass {
+    for component in dir.components().rev() {
+        let name = component.as_os_str().to_string_lossy();
+        if let Some((_, kind, label)) = NON_MEDIA_CACHE_DIRS
+            .iter()
+            .find(|(n, _, _)| n.eq_ignore_ascii_case(&name))
+        {
+            return DirClass { kind, label: Some(label), scan_dir: None };
+        }
+    }
+    if has_cache_files(dir) {
+        return DirClass { kind: "http_cache", label: None, scan_dir: Some(dir.to_path_buf()) };
+    }
+    if is_spotify_storage(dir) {
+        return DirClass {
+            kind: "spotify_storage",
+            label: Some("Spotify audio cache (proprietary encrypted chunks, not supported)"),
+            scan_dir: None,
+        };
+    }
+    let spotify_browser = resolve_child(dir, "Browser");
+    if is_spotify_path(dir) && resolve_child(&spotify_browser, "Cache").is_dir() {
+        return DirClass {
+            kind: "profile_root",
+            label: Some("Spotify folder; its image cache is scanned (audio Storage is not supported)"),
+            scan_dir: Some(resolve_cache_dir(&spotify_browser)),
+        };
+    }
+    let profile_cache = resolve_cache_dir(dir);
+    if profile_cache.is_dir() {
+        return DirClass {
+            kind: "profile_root",
+            label: Some("Browser profile folder; its HTTP cache is scanned"),
+            scan_dir: Some(profile_cache),
+        };
+    }
+    DirClass { kind: "unknown", label: None, scan_dir: Some(dir.to_path_buf()) }
+}
+
+// ─── Spotify ────────────────────────────────────────────────────────────────
+//
+// The Spotify desktop client is CEF-based: cover art and UI assets live in a
+// normal Chromium cache under `Browser/`. Audio is kept separately in
+// `Storage/` (or `Data/` in newer and Store builds) as encrypted,
+// proprietary chunk files in two-hex-digit subfolders. Those are detected
+// and reported as unsupported instead of being parsed as cache entries.
+
+/// Folder names Spotify uses for its audio chunk store.
+const SPOTIFY_STORAGE_DIRS: &[&str] = &["Storage", "Data"];
+
+fn is_spotify_path(dir: &Path) -> bool {
+    dir.to_string_lossy().to_lowercase().contains("spotify")
+}
+
+/// `dir` is (inside) Spotify's audio chunk store.
+fn is_spotify_storage(dir: &Path) -> bool {
+    is_spotify_path(dir)
+        && dir.components().rev().take(2).any(|c| {
+            let name = c.as_os_str().to_string_lossy();
+            SPOTIFY_STORAGE_DIRS.iter().any(|d| d.eq_ignore_ascii_case(&name))
+        })
+}
+
+/// Add the Chromium (image) cache of a Spotify install rooted at `dir`.
+fn collect_spotify_caches(dir: &Path, paths: &mut Vec<String>) {
+    paths.push(resolve_cache_dir(&resolve_child(dir, "Browser")).to_string_lossy().to_string());
+}
+
+/// WebView2 keeps its browser data in a folder named `EBWebView`.
+const WEBVIEW2_DIR: &str = "EBWebView";
+/// How deep to look for EBWebView inside one package folder
+/// (e.g. LocalCache\Microsoft\MSTeams\EBWebView).
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+const WEBVIEW2_PACKAGE_DEPTH: usize = 5;
+/// How deep to look under %LOCALAPPDATA% itself (e.g. <App>\<exe>.WebView2\EBWebView).
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+const WEBVIEW2_APP_DEPTH: usize = 3;
+
+/// Find `EBWebView` folders under `dir` (up to `depth` levels down) and add
+/// the HTTP cache of every profile inside them: `Default`, `Profile N`, and
+/// host-named profiles such as new Teams' `WV2Profile_tfw`.
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn collect_webview2_caches(dir: &Path, depth: usize, paths: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        // file_type() doesn't follow junctions, so reparse-point loops are skipped.
+        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().eq_ignore_ascii_case(WEBVIEW2_DIR) {
+            let Ok(profiles) = std::fs::read_dir(&path) else {
+                continue;
+            };
+            let mut found: Vec<String> = profiles
+                .flatten()
+                .map(|p| p.path())
+                .filter(|p| resolve_child(p, "Cache").is_dir())
+                .map(|p| resolve_cache_dir(&p).to_string_lossy().to_string())
+                .collect();
+            found.sort();
+            paths.extend(found);
+        } else if depth > 1 {
+            collect_webview2_caches(&path, depth - 1, paths);
+        }
+    }
+}
+
+/// Add Slack's cache folders: the shared `Slack/Cache` plus one per
+/// workspace session under `Slack/Partitions/<name>/Cache` (older builds).
+fn collect_slack_caches(slack_dir: &Path, paths: &mut Vec<String>) {
+    paths.push(resolve_cache_dir(slack_dir).to_string_lossy().to_string());
+    let Ok(partitions) = std::fs::read_dir(slack_dir.join("Partitions")) else {
+        return;
+    };
+    let mut found: Vec<String> = partitions
+        .flatten()
+        .map(|p| p.path())
+        .filter(|p| resolve_child(p, "Cache").is_dir())
+        .map(|p| resolve_cache_dir(&p).to_string_lossy().to_string())
+        .collect();
+    found.sort();
+    paths.extend(found);
+}
+
+/// Simple Cache entries checked for the magic before a folder is rejected.
+const MAGIC_PROBE_FILES: usize = 16;
+
+/// True if `dir` holds a Chromium HTTP cache: a blockfile index, or Simple
+/// Cache entry files that start with the Simple Cache magic.
+pub fn holds_chromium_cache(dir: &Path) -> bool {
+    use std::io::Read;
+    if is_blockfile_dir(dir) {
+        return true;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries
+        .flatten()
+        .filter(|e| simple_entry_hash(&e.file_name().to_string_lossy()).is_some())
+        .take(MAGIC_PROBE_FILES)
+        .any(|e| {
+            let mut magic = [0u8; 8];
+            std::fs::File::open(e.path())
+                .and_then(|mut f| f.read_exact(&mut magic))
+                .map(|_| u64::from_le_bytes(magic) == crate::SIMPLE_CACHE_MAGIC)
+                .unwrap_or(false)
+        })
+}
+
+/// Add the HTTP cache of every Electron app directly under `root`
+/// (`<root>/<App>/Cache/Cache_Data`) that isn't already in `paths`. Covers
+/// Obsidian, Notion, Element and the like without per-app code; they are
+/// labelled "Custom (<App>)".
+fn collect_electron_caches(root: &Path, paths: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return;
+    };
+    let known: Vec<String> = paths.iter().map(|p| p.to_lowercase()).collect();
+    let mut found: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| resolve_child(&resolve_child(&e.path(), "Cache"), "Cache_Data"))
+        .filter(|cache| holds_chromium_cache(cache))
+        .map(|cache| cache.to_string_lossy().to_string())
+        .filter(|p| !known.contains(&p.to_lowercase()))
+        .collect();
+    found.sort();
+    paths.extend(found);
+}
+
 /// Scan a Chromium browser directory for all profile cache folders.
 /// Checks for "Default", "Profile 1", "Profile 2", etc.
-/// Uses `resolve_cache_dir` to handle both `Cache/Cache_Data` and `Cache/` layouts.
+/// Uses `resolve_cache_dir` to handle the `Cache/Cache_Data`, `Network/Cache`
+/// and `Cache/` layouts, and adds each profile's other media caches.
 fn collect_chromium_profiles(browser_dir: &Path, paths: &mut Vec<String>) {
     if !browser_dir.is_dir() {
         // Still add the Default path so it shows as "not found" rather than invisible
@@ -144,41 +703,99 @@ fn collect_chromium_profiles(browser_dir: &Path, paths: &mut Vec<String>) {
         return;
     }
     // Always check Default
-    let default_dir = browser_dir.join("Default");
+    let default_dir = resolve_child(browser_dir, "Default");
     let default_cache = resolve_cache_dir(&default_dir);
     paths.push(default_cache.to_string_lossy().to_string());
+    let extra = profile_extra_caches(&default_dir, &default_cache);
+    paths.extend(extra.iter().map(|p| p.to_string_lossy().to_string()));
     // Scan for "Profile N" directories
     if let Ok(entries) = std::fs::read_dir(browser_dir) {
         for entry in entries.flatten() {
             let name = entry.file_name().to_string_lossy().to_string();
-            if name.starts_with("Profile ") && entry.path().is_dir() {
+            if name.to_lowercase().starts_with("profile ") && entry.path().is_dir() {
                 let profile_cache = resolve_cache_dir(&entry.path());
                 if profile_cache.is_dir() {
                     paths.push(profile_cache.to_string_lossy().to_string());
                 }
+                let extra = profile_extra_caches(&entry.path(), &profile_cache);
+                paths.extend(extra.iter().map(|p| p.to_string_lossy().to_string()));
             }
         }
     }
 }
 
 pub fn validate_cache_path(path: &str) -> Result<CachePathInfo, String> {
-    let dir = Path::new(path);
-    let client_name = extract_client_name(path);
+    // Accept pasted `~/...` and `%APPDATA%\...` forms.
+    let expanded = crate::path_display::expand_path(path);
+    let path = expanded.as_str();
+    let from_archive = crate::snapshot::is_archive(Path::new(path));
+    let snapshot = from_archive.then(|| crate::snapshot::extracted(Path::new(path))).flatten();
+    let dir = snapshot.as_deref().unwrap_or(Path::new(path));
+    let client_name = extract_client_name(&dir.to_string_lossy());
+    let display_path = crate::path_display::display_path(path);
+
+    if from_archive && snapshot.is_none() {
+        // Extracting is left to the scan; it can take minutes.
+        return Ok(CachePathInfo {
+            path: path.to_string(),
+            display_path,
+            exists: true,
+            file_count: 0,
+            total_size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            client_name,
+            kind: "snapshot",
+            kind_label: Some("Archived cache snapshot; extracted when it is scanned"),
+            resolved_path: None,
+            discord_build: None,
+            from_archive,
+        });
+    }
 
     if !dir.exists() {
         return Ok(CachePathInfo {
             path: path.to_string(),
+            display_path,
             exists: false,
             file_count: 0,
             total_size: 0,
             client_name,
+            kind: "unknown",
+            kind_label: None,
+            resolved_path: None,
+            discord_build: None,
+            from_archive,
         });
     }
 
+    let mut class = classify_dir(dir);
+    if from_archive && class.label.is_none() {
+        class.label = Some("Archived cache snapshot; its extracted copy is scanned");
+    }
     let mut file_count = 0usize;
     let mut total_size = 0u64;
+    let resolved_path = match &class.scan_dir {
+        Some(scan_dir) if scan_dir != Path::new(path) => Some(scan_dir.to_string_lossy().to_string()),
+        _ => None,
+    };
 
-    let entries = std::fs::read_dir(dir).map_err(|e| format!("Cannot read directory: {}", e))?;
+    let entries = match &class.scan_dir {
+        Some(scan_dir) => std::fs::read_dir(scan_dir).map_err(|e| format!("Cannot read directory: {}", e))?,
+        None => {
+            return Ok(CachePathInfo {
+                path: path.to_string(),
+                display_path,
+                exists: true,
+                file_count,
+                total_size,
+                client_name,
+                kind: class.kind,
+                kind_label: class.label,
+                resolved_path,
+                discord_build: crate::discord_build::detect(dir),
+                from_archive,
+            });
+        }
+    };
     for entry in entries.flatten() {
         if let Ok(meta) = entry.metadata() {
             if meta.is_file() {
@@ -193,18 +810,41 @@ pub fn validate_cache_path(path: &str) -> Result<CachePathInfo, String> {
 
     Ok(CachePathInfo {
         path: path.to_string(),
+        display_path,
         exists: true,
         file_count,
         total_size,
         client_name,
+        kind: class.kind,
+        kind_label: class.label,
+        resolved_path,
+        discord_build: crate::discord_build::detect(dir),
+        from_archive,
     })
 }
 
 pub fn list_cache_files(dir: &str) -> Result<Vec<CacheFileEntry>, String> {
-    let path = Path::new(dir);
+    let snapshot;
+    let mut path = Path::new(dir);
+    if crate::snapshot::is_archive(path) {
+        snapshot = crate::snapshot::open(path)?;
+        path = snapshot.as_path();
+    }
     if !path.is_dir() {
         return Err(format!("Not a directory: {}", dir));
     }
+    let class = classify_dir(path);
+    let Some(scan_dir) = class.scan_dir else {
+        println!("[cache] Skipping {} ({}): {}", dir, class.kind, class.label.unwrap_or(""));
+        return Ok(Vec::new());
+    };
+    if scan_dir != path {
+        println!("[cache] {} is a profile folder; listing {}", dir, scan_dir.display());
+    }
+    let path = scan_dir.as_path();
+    if let Some(build) = crate::discord_build::detect(path) {
+        println!("[cache] {} was written by {}", dir, build.summary());
+    }
 
     let mut files = Vec::new();
     let entries = std::fs::read_dir(path).map_err(|e| e.to_string())?;
@@ -224,6 +864,9 @@ pub fn list_cache_files(dir: &str) -> Result<Vec<CacheFileEntry>, String> {
                         path: entry.path().to_string_lossy().to_string(),
                         size: meta.len(),
                         modified_at,
+                        last_used: None,
+                        index_size: None,
+                        eviction_risk: None,
                     });
                 }
             }
@@ -231,14 +874,67 @@ pub fn list_cache_files(dir: &str) -> Result<Vec<CacheFileEntry>, String> {
     }
 
     files.sort_by(|a, b| a.name.cmp(&b.name));
+    attach_simple_index(path, &mut files);
     Ok(files)
 }
 
+/// Fill in last-used times, entry sizes and eviction risk from the-real-index.
+/// Blockfile caches and caches without a readable index are left untouched.
+fn attach_simple_index(dir: &Path, files: &mut [CacheFileEntry]) {
+    if !resolve_child(dir, "index-dir").is_dir() {
+        return;
+    }
+    let index = match read_simple_index(dir) {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("[cache] Skipping the-real-index for {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    println!(
+        "[cache] the-real-index v{}: {} entries, {} bytes",
+        index.version,
+        index.entries.len(),
+        index.cache_size
+    );
+    for file in files.iter_mut() {
+        if let Some(entry) = simple_entry_hash(&file.name).and_then(|h| index.entries.get(&h)) {
+            file.last_used = entry.last_used;
+            file.index_size = Some(entry.size);
+        }
+    }
+
+    // Risk is ranked across all entries in the index, not just listed files,
+    // so it reflects the cache's actual eviction order.
+    let hashes: Vec<u64> = index.entries.keys().copied().collect();
+    let inputs: Vec<RiskInput> = hashes
+        .iter()
+        .map(|h| {
+            let entry = &index.entries[h];
+            RiskInput { last_used: entry.last_used, size: entry.size, reuse_count: 0 }
+        })
+        .collect();
+    let risks = eviction_risk::assess_simple(
+        &inputs,
+        index.cache_size,
+        eviction_risk::DEFAULT_MAX_CACHE_BYTES,
+    );
+    let by_hash: HashMap<u64, EvictionRisk> = hashes.into_iter().zip(risks).collect();
+    for file in files.iter_mut() {
+        if let Some(risk) = simple_entry_hash(&file.name).and_then(|h| by_hash.get(&h)) {
+            file.eviction_risk = Some(risk.clone());
+        }
+    }
+}
+
 /// Check if a filename matches a Chromium cache file pattern.
 /// Supports two formats:
 ///  - Blockfile backend (Windows): `f_XXXXXX` (8 chars: "f_" + 6 hex digits)
 ///  - Simple Cache backend (macOS/Linux): `{16 hex chars}_{stream}` (e.g. "170e8695a0c85bd4_0")
+///
+/// Matching ignores case: copies made on other filesystems may be upper-cased.
 fn is_cache_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
     // Blockfile format: f_XXXXXX
     if name.len() == 8 && name.starts_with("f_") {
         return name[2..].chars().all(|c| c.is_ascii_hexdigit());
@@ -263,6 +959,28 @@ fn extract_client_name(path: &str) -> String {
     let lower = path.to_lowercase();
     let profile = extract_profile_label(path);
 
+    if let Some(teams) = teams_client_name(&lower) {
+        return teams;
+    }
+    if lower.contains("whatsapp") {
+        return "WhatsApp".to_string();
+    }
+    if lower.split(['/', '\\']).any(|part| part == "signal") {
+        return "Signal".to_string();
+    }
+    if lower.contains("spotify") {
+        return "Spotify".to_string();
+    }
+    if lower.contains("slack") {
+        return match slack_partition_name(path) {
+            Some(workspace) => format!("Slack ({})", workspace),
+            None => "Slack".to_string(),
+        };
+    }
+    if let Some(host) = webview2_host_name(path) {
+        return format!("WebView2 ({})", host);
+    }
+
     let base = if lower.contains("discorddevelopment") {
         "Discord Development"
     } else if lower.contains("discordcanary") {
@@ -277,8 +995,20 @@ fn extract_client_name(path: &str) -> String {
         "Chrome"
     } else if lower.contains("edge") || lower.contains("microsoft-edge") {
         "Edge"
+    } else if lower.contains("vivaldi") {
+        "Vivaldi"
+    } else if lower.contains("yandex") {
+        "Yandex"
+    } else if lower.contains("chromium") {
+        "Chromium"
+    } else if lower.split(['/', '\\']).any(|part| part == "arc") || lower.contains("thebrowsercompany.arc_") {
+        "Arc"
+    } else if lower.contains("opera gx") || lower.contains("operagx") {
+        "Opera GX"
     } else if lower.contains("opera") {
         "Opera"
+    } else if let Some(app) = electron_app_name(path) {
+        return format!("Custom ({})", app);
     } else {
         "Custom"
     };
@@ -289,6 +1019,66 @@ fn extract_client_name(path: &str) -> String {
     }
 }
 
+/// App folder of an Electron-style cache path (`<App>/Cache/Cache_Data` or
+/// `<App>/Cache`).
+fn electron_app_name(path: &str) -> Option<String> {
+    let normalized = path.replace('\\', "/");
+    let mut parts: Vec<&str> = normalized.split('/').filter(|p| !p.is_empty()).collect();
+    if parts.last().is_some_and(|p| p.eq_ignore_ascii_case("Cache_Data")) {
+        parts.pop();
+    }
+    if !parts.pop()?.eq_ignore_ascii_case("Cache") {
+        return None;
+    }
+    parts.last().map(|app| app.to_string())
+}
+
+/// Name of the app hosting a WebView2 cache: the package name for packaged
+/// apps ("MSTeams" from "MSTeams_8wekyb3d8bbwe"), otherwise the folder the
+/// EBWebView directory sits in ("MyApp" from "MyApp.exe.WebView2").
+fn webview2_host_name(path: &str) -> Option<String> {
+    let normalized = path.replace('\\', "/");
+    let parts: Vec<&str> = normalized.split('/').collect();
+    let webview = parts.iter().position(|p| p.eq_ignore_ascii_case(WEBVIEW2_DIR))?;
+    if let Some(pkg) = parts[..webview].iter().position(|p| p.eq_ignore_ascii_case("Packages")) {
+        if let Some(family) = parts.get(pkg + 1) {
+            return Some(family.split('_').next().unwrap_or(family).to_string());
+        }
+    }
+    let parent = parts[..webview].last()?;
+    let name = parent.trim_end_matches(".WebView2").trim_end_matches(".exe");
+    Some(name.to_string())
+}
+
+/// Label for Teams caches: new Teams (WebView2, "MSTeams") names its
+/// profiles by account type, classic Teams is an Electron app.
+fn teams_client_name(lower: &str) -> Option<String> {
+    let lower = lower.replace('\\', "/");
+    if lower.contains("msteams") {
+        let account = if lower.contains("wv2profile_tfl") {
+            " (personal)"
+        } else if lower.contains("wv2profile_tfw") {
+            " (work or school)"
+        } else {
+            ""
+        };
+        return Some(format!("Teams{}", account));
+    }
+    if lower.contains("microsoft/teams/") || lower.contains("microsoft/microsoft teams/") {
+        return Some("Teams (classic)".to_string());
+    }
+    None
+}
+
+/// Workspace partition a Slack cache belongs to
+/// (".../Slack/Partitions/acme/Cache/Cache_Data" -> "acme").
+fn slack_partition_name(path: &str) -> Option<String> {
+    let normalized = path.replace('\\', "/");
+    let parts: Vec<&str> = normalized.split('/').collect();
+    let idx = parts.iter().position(|p| p.eq_ignore_ascii_case("Partitions"))?;
+    parts.get(idx + 1).filter(|p| !p.is_empty()).map(|p| p.to_string())
+}
+
 /// Extract a human-readable profile label from a cache path.
 /// e.g., ".../Profile 2/Cache/Cache_Data" -> Some("Profile 2")
 /// e.g., ".../Default/Cache/Cache_Data" -> None (Default is implied)
@@ -298,9 +1088,200 @@ fn extract_profile_label(path: &str) -> Option<String> {
     let parts: Vec<&str> = normalized.split('/').collect();
     // Look for "Profile N" segment (typically 2 segments before "Cache/Cache_Data")
     for part in &parts {
-        if part.starts_with("Profile ") {
+        if part.to_lowercase().starts_with("profile ") {
             return Some(part.to_string());
         }
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_v9_index() {
+        let data = crate::fixture::simple_index(9, &[(0x170e8695a0c85bd4, 13_350_000_000_000_000, 4096)]);
+        let index = parse_simple_index(&data).unwrap();
+        assert_eq!(index.version, 9);
+        let entry = index.entries[&0x170e8695a0c85bd4];
+        assert_eq!(entry.size, 4096);
+        assert!((entry.last_used.unwrap() - 1_705_526_400.0).abs() < 1.0);
+        assert_eq!(simple_entry_hash("170e8695a0c85bd4_s"), Some(0x170e8695a0c85bd4));
+
+        // A newer index is read with the v9 layout rather than rejected.
+        let mut newer = data.clone();
+        newer[16..20].copy_from_slice(&10u32.to_le_bytes());
+        let index = parse_simple_index(&newer).unwrap();
+        assert_eq!((index.version, index.entries[&0x170e8695a0c85bd4].size), (10, 4096));
+    }
+
+    #[test]
+    fn classifies_profile_subfolders() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-classify-{}", std::process::id()));
+        let profile = root.join("Default");
+        let http = profile.join("Cache").join("Cache_Data");
+        let code = profile.join("Code Cache").join("js");
+        for dir in [&http, &code] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("170e8695a0c85bd4_0"), b"x").unwrap();
+        }
+
+        assert_eq!(classify_dir(&http).kind, "http_cache");
+        assert_eq!(classify_dir(&code).kind, "code_cache");
+        assert_eq!(classify_dir(&code).scan_dir, None);
+        assert_eq!(classify_dir(&profile.join("GPUCache")).kind, "gpu_cache");
+        let class = classify_dir(&profile);
+        assert_eq!((class.kind, class.scan_dir.as_deref()), ("profile_root", Some(http.as_path())));
+        assert!(list_cache_files(&code.to_string_lossy()).unwrap().is_empty());
+        assert_eq!(list_cache_files(&profile.to_string_lossy()).unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn discovers_webview2_profiles() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-webview2-{}", std::process::id()));
+        let teams = root.join("Packages/MSTeams_8wekyb3d8bbwe/LocalCache/Microsoft/MSTeams/EBWebView");
+        std::fs::create_dir_all(teams.join("WV2Profile_tfw/Cache/Cache_Data")).unwrap();
+        std::fs::create_dir_all(teams.join("Crashpad")).unwrap();
+
+        let mut paths = Vec::new();
+        collect_webview2_caches(&root.join("Packages/MSTeams_8wekyb3d8bbwe"), WEBVIEW2_PACKAGE_DEPTH, &mut paths);
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].ends_with("Cache_Data"));
+        assert_eq!(extract_client_name(&paths[0]), "Teams (work or school)");
+        assert_eq!(
+            extract_client_name(r"C:\Users\u\AppData\Local\Packages\5319275A.WhatsAppDesktop_cv1g1gvanyjgm\LocalState\EBWebView\Default\Cache\Cache_Data"),
+            "WhatsApp"
+        );
+        assert_eq!(extract_client_name("/home/u/.config/Signal/Cache/Cache_Data"), "Signal");
+        assert_eq!(
+            extract_client_name(r"C:\Users\u\AppData\Local\Opera Software\Opera GX Stable\Cache\Cache_Data"),
+            "Opera GX"
+        );
+        assert_eq!(
+            extract_client_name("/Users/u/Library/Application Support/Arc/User Data/Profile 2/Cache/Cache_Data"),
+            "Arc (Profile 2)"
+        );
+        assert_eq!(
+            extract_client_name(r"C:\Users\u\AppData\Roaming\Microsoft\Teams\Cache"),
+            "Teams (classic)"
+        );
+        assert_eq!(
+            extract_client_name(r"C:\Users\u\AppData\Local\Foo\Foo.exe.WebView2\EBWebView\Default\Cache\Cache_Data"),
+            "WebView2 (Foo)"
+        );
+    }
+
+    #[test]
+    fn discovers_slack_workspaces() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-slack-{}", std::process::id()));
+        let slack = root.join("Slack");
+        std::fs::create_dir_all(slack.join("Cache/Cache_Data")).unwrap();
+        std::fs::create_dir_all(slack.join("Partitions/acme/Cache/Cache_Data")).unwrap();
+        std::fs::create_dir_all(slack.join("Partitions/empty")).unwrap();
+
+        let mut paths = Vec::new();
+        collect_slack_caches(&slack, &mut paths);
+        let _ = std::fs::remove_dir_all(&root);
+        let names: Vec<String> = paths.iter().map(|p| extract_client_name(p)).collect();
+        assert_eq!(names, ["Slack", "Slack (acme)"]);
+    }
+
+    #[test]
+    fn discovers_electron_apps() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-electron-{}", std::process::id()));
+        let obsidian = root.join("obsidian/Cache/Cache_Data");
+        std::fs::create_dir_all(&obsidian).unwrap();
+        let mut entry = crate::SIMPLE_CACHE_MAGIC.to_le_bytes().to_vec();
+        entry.extend_from_slice(&[0u8; 16]);
+        std::fs::write(obsidian.join("0123456789abcdef_0"), &entry).unwrap();
+        // Not a cache: a Simple Cache-looking name without the magic
+        std::fs::create_dir_all(root.join("Code/Cache/Cache_Data")).unwrap();
+        std::fs::write(root.join("Code/Cache/Cache_Data/0123456789abcdef_0"), b"not a cache entry").unwrap();
+
+        let mut paths = Vec::new();
+        collect_electron_caches(&root, &mut paths);
+        collect_electron_caches(&root, &mut paths);
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(paths, vec![obsidian.to_string_lossy().to_string()]);
+        assert_eq!(extract_client_name(&paths[0]), "Custom (obsidian)");
+    }
+
+    #[test]
+    fn discovers_flatpak_and_snap_caches() {
+        let home = std::env::temp_dir().join(format!("cachephoenix-sandboxed-{}", std::process::id()));
+        let flatpak = home.join(".var/app/com.discordapp.Discord/config/discord/Cache/Cache_Data");
+        let snap = home.join("snap/chromium/common/chromium/Default/Cache/Cache_Data");
+        std::fs::create_dir_all(&flatpak).unwrap();
+        std::fs::create_dir_all(&snap).unwrap();
+        std::fs::create_dir_all(home.join("snap/chromium/current/.config")).unwrap();
+
+        let mut paths = Vec::new();
+        collect_sandboxed_caches(&home, &["discord"], &mut paths);
+        let _ = std::fs::remove_dir_all(&home);
+        assert_eq!(paths, vec![flatpak.to_string_lossy().to_string(), snap.to_string_lossy().to_string()]);
+        assert_eq!(extract_client_name(&paths[0]), "Discord");
+        assert_eq!(extract_client_name(&paths[1]), "Chromium");
+    }
+
+    #[test]
+    fn resolves_mismatched_case() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-case-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("Default/cache/Cache_data")).unwrap();
+        std::fs::write(root.join("Default/cache/Cache_data/F_00A1B2"), b"x").unwrap();
+
+        let cache = resolve_cache_dir(&root.join("Default"));
+        let found = resolve_child(&cache, "f_00a1b2").is_file();
+        let _ = std::fs::remove_dir_all(&root);
+        assert!(cache.ends_with("cache/Cache_data"));
+        assert!(found);
+        assert!(is_cache_file("F_00A1B2") && is_cache_file("170E8695A0C85BD4_S"));
+    }
+
+    #[test]
+    fn finds_network_media_and_extension_caches() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-network-{}", std::process::id()));
+        let profile = root.join("Default");
+        let network = profile.join("Network/Cache/Cache_Data");
+        let media = profile.join("Media Cache");
+        let ext = profile.join("Storage/ext/abcdefghijklmnop/def/Cache/Cache_Data");
+        for dir in [&network, &ext] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("170e8695a0c85bd4_0"), b"x").unwrap();
+        }
+        std::fs::create_dir_all(&media).unwrap();
+        std::fs::write(media.join("f_000001"), b"x").unwrap();
+        // An empty Cache folder left by an older version doesn't win.
+        std::fs::create_dir_all(profile.join("Cache")).unwrap();
+
+        let main = resolve_cache_dir(&profile);
+        let extra = profile_extra_caches(&profile, &main);
+        let class = classify_dir(&profile);
+        let mut paths = Vec::new();
+        collect_chromium_profiles(&root, &mut paths);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(main, network);
+        assert_eq!(extra, vec![media.clone(), ext.clone()]);
+        assert_eq!(class.scan_dir.as_deref(), Some(network.as_path()));
+        assert_eq!(paths.len(), 3);
+    }
+
+    #[test]
+    fn flags_spotify_audio_storage() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-spotify-{}", std::process::id()));
+        let spotify = root.join("Spotify");
+        std::fs::create_dir_all(spotify.join("Browser/Cache/Cache_Data")).unwrap();
+        std::fs::create_dir_all(spotify.join("Storage/3f")).unwrap();
+        std::fs::write(spotify.join("Storage/3f/3fa2c1d0e9b8.file"), b"enc").unwrap();
+
+        let storage = classify_dir(&spotify.join("Storage/3f"));
+        let whole = classify_dir(&spotify);
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!((storage.kind, storage.scan_dir), ("spotify_storage", None));
+        assert_eq!(whole.scan_dir, Some(spotify.join("Browser/Cache/Cache_Data")));
+        assert_eq!(extract_client_name(&spotify.join("Browser/Cache").to_string_lossy()), "Spotify");
+    }
+}
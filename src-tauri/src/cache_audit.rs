@@ -0,0 +1,265 @@
+use serde::Serialize;
+use std::io::{Read, Seek};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::crc_check::read_up_to;
+use crate::{
+    SIMPLE_CACHE_EOF_MAGIC, SIMPLE_CACHE_EOF_SIZE, SIMPLE_CACHE_HEADER_SIZE, SPARSE_RANGE_HEADER_SIZE, SPARSE_RANGE_MAGIC,
+};
+
+// ─── Whole-cache integrity audit ────────────────────────────────────────────
+//
+// How much of a cache folder is intact? Each Simple Cache file is checked the
+// way the parsers will read it: the header magic and key hash, the EOF
+// records (`_0`, `_1`) or the chain of range headers (`_s`), and the stored
+// CRC32s. Files are spread over the same worker pool as `verify_crc32` and
+// read the same way: small reads of the structure, the CRCs streamed.
+//
+//   healthy    every check passed (or the file carries no CRCs to check)
+//   truncated  the file ends early: EOF record or last range missing
+//   corrupt    bad magic, key hash, range header or CRC
+//   locked     another process holds the file (Discord still running)
+//   unreadable any other read error
+//   skipped    not a Simple Cache file (blockfile `f_` data)
+//
+// The summary is cheap to show; per-file details are only returned when
+// asked for, since a large cache has tens of thousands of files.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditFile {
+    pub path: String,
+    /// "healthy", "truncated", "corrupt", "locked", "unreadable" or "skipped"
+    pub status: &'static str,
+    /// What failed, e.g. "EOF0 record missing", "CRC mismatch in stream 1"
+    pub problems: Vec<String>,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    pub dir: String,
+    pub total: usize,
+    pub healthy: usize,
+    pub truncated: usize,
+    pub corrupt: usize,
+    pub locked: usize,
+    pub unreadable: usize,
+    pub skipped: usize,
+    pub bytes_read: u64,
+    pub elapsed_ms: u64,
+    /// Every file's result, in listing order; empty unless details were requested
+    pub files: Vec<AuditFile>,
+}
+
+/// Read errors that mean another process has the file open or locked.
+fn is_lock_error(e: &std::io::Error) -> bool {
+    #[cfg(windows)]
+    {
+        // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+        matches!(e.raw_os_error(), Some(32) | Some(33))
+    }
+    #[cfg(not(windows))]
+    {
+        // EACCES from a byte-range lock (see read_with_lock_retry), EAGAIN
+        matches!(e.raw_os_error(), Some(13) | Some(11))
+    }
+}
+
+/// Whether the EOF record at `at` has the EOF magic.
+fn eof_at<R: Read + Seek>(reader: &mut R, at: u64) -> std::io::Result<bool> {
+    Ok(read_up_to(reader, at, 8)? == SIMPLE_CACHE_EOF_MAGIC.to_le_bytes())
+}
+
+/// Structural problems of a sparse file's range chain, as (truncated, corrupt).
+fn sparse_problems<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+    key_length: usize,
+) -> std::io::Result<(Vec<String>, Vec<String>)> {
+    let (mut truncated, mut corrupt) = (Vec::new(), Vec::new());
+    let mut pos = (SIMPLE_CACHE_HEADER_SIZE + key_length) as u64;
+    while pos < size {
+        if pos + SPARSE_RANGE_HEADER_SIZE as u64 > size {
+            truncated.push(format!("range header at {} cut off", pos));
+            break;
+        }
+        let header = read_up_to(reader, pos, SPARSE_RANGE_HEADER_SIZE)?;
+        if header[0..8] != SPARSE_RANGE_MAGIC.to_le_bytes() {
+            corrupt.push(format!("bad range header at {}", pos));
+            break;
+        }
+        let length = u64::from_le_bytes(header[16..24].try_into().unwrap_or_default());
+        let start = pos + SPARSE_RANGE_HEADER_SIZE as u64;
+        let end = start.saturating_add(length);
+        if end > size {
+            truncated.push(format!("range at {} has {} of {} bytes", pos, size - start, length));
+            break;
+        }
+        pos = end;
+    }
+    Ok((truncated, corrupt))
+}
+
+/// Audit the file in `reader` (`size` bytes) with small reads of its
+/// structure; the CRCs are streamed by `crc_check`. `path` decides which
+/// layout is expected.
+fn audit_reader<R: Read + Seek>(reader: &mut R, size: u64, path: &str) -> std::io::Result<AuditFile> {
+    let mut file = AuditFile { path: path.to_string(), status: "skipped", problems: Vec::new(), size };
+    let name = std::path::Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let is_entry = name.len() == 18 && name[..16].chars().all(|c| c.is_ascii_hexdigit());
+    if !is_entry {
+        return Ok(file);
+    }
+    let head = crate::crc_check::read_head(reader)?;
+    let (key_length, key_check) = crate::key_hash::check_key(&head);
+    let header_cut = size < SIMPLE_CACHE_HEADER_SIZE as u64;
+    let Some(key_length) = key_length else {
+        file.status = if header_cut { "truncated" } else { "corrupt" };
+        file.problems.push(match key_check.status {
+            "not_simple_cache" if header_cut => "header cut off".to_string(),
+            "not_simple_cache" => "bad header magic".to_string(),
+            _ => "key hash mismatch".to_string(),
+        });
+        return Ok(file);
+    };
+
+    let (mut truncated, mut corrupt) = (Vec::new(), Vec::new());
+    let body_start = (SIMPLE_CACHE_HEADER_SIZE + key_length) as u64;
+    let eof_size = SIMPLE_CACHE_EOF_SIZE as u64;
+    if crate::is_simple_cache_sparse(path) {
+        (truncated, corrupt) = sparse_problems(reader, size, key_length)?;
+    } else if size < body_start + eof_size || !eof_at(reader, size - eof_size)? {
+        let record = if crate::is_simple_cache_stream2(path) { "EOF record" } else { "EOF0 record" };
+        truncated.push(format!("{} missing", record));
+    } else if !crate::is_simple_cache_stream2(path)
+        && crate::crc_check::stream0_layout(reader, size, body_start)?.is_none()
+    {
+        corrupt.push("EOF1 record not where EOF0 says".to_string());
+    }
+    if key_check.status == "repaired" {
+        corrupt.push("key length field wrong".to_string());
+    }
+    let crc = crate::crc_check::check_reader(reader, size, path)?;
+    corrupt.extend(crc.failed.iter().map(|label| format!("CRC mismatch in {}", label)));
+
+    file.status = if !corrupt.is_empty() {
+        "corrupt"
+    } else if !truncated.is_empty() {
+        "truncated"
+    } else {
+        "healthy"
+    };
+    file.problems = corrupt.into_iter().chain(truncated).collect();
+    Ok(file)
+}
+
+fn audit_file(path: &str) -> AuditFile {
+    let _permit = crate::io_limit::acquire_for(path);
+    let result = crate::with_lock_retry(path, || {
+        let mut file = std::fs::File::open(path)?;
+        let size = file.metadata()?.len();
+        audit_reader(&mut file, size, path)
+    });
+    result.unwrap_or_else(|e| AuditFile {
+        path: path.to_string(),
+        status: if is_lock_error(&e) { "locked" } else { "unreadable" },
+        problems: vec![e.to_string()],
+        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+/// Check the header, EOF records and CRC32s of every file in the cache folder
+/// `dir`, in parallel. With `details`, every file's result is included.
+#[tauri::command(async)]
+pub fn audit_cache(dir: String, details: Option<bool>) -> Result<AuditReport, String> {
+    let dir = crate::path_display::expand_path(&dir);
+    let _job = crate::crash::job(format!("auditing {}", dir));
+    let started = std::time::Instant::now();
+    let paths: Vec<String> = crate::cache::list_cache_files(&dir)?.into_iter().map(|f| f.path).collect();
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<AuditFile>>> = paths.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..crate::crc_check::workers().min(paths.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+                *results[i].lock().unwrap_or_else(|e| e.into_inner()) = Some(audit_file(path));
+            });
+        }
+    });
+    let files: Vec<AuditFile> = results
+        .into_iter()
+        .filter_map(|slot| slot.into_inner().unwrap_or_else(|e| e.into_inner()))
+        .collect();
+    let count = |status: &str| files.iter().filter(|f| f.status == status).count();
+    let mut report = AuditReport {
+        total: files.len(),
+        healthy: count("healthy"),
+        truncated: count("truncated"),
+        corrupt: count("corrupt"),
+        locked: count("locked"),
+        unreadable: count("unreadable"),
+        skipped: count("skipped"),
+        bytes_read: files.iter().filter(|f| f.status != "locked" && f.status != "unreadable").map(|f| f.size).sum(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        files: Vec::new(),
+        dir,
+    };
+    println!(
+        "[audit] {}: {} files in {} ms — {} healthy, {} truncated, {} corrupt, {} locked, {} unreadable, {} skipped",
+        report.dir,
+        report.total,
+        report.elapsed_ms,
+        report.healthy,
+        report.truncated,
+        report.corrupt,
+        report.locked,
+        report.unreadable,
+        report.skipped
+    );
+    if details.unwrap_or(false) {
+        report.files = files;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit_data(data: &[u8], path: &str) -> AuditFile {
+        audit_reader(&mut std::io::Cursor::new(data), data.len() as u64, path).unwrap()
+    }
+
+    #[test]
+    fn classifies_healthy_truncated_and_corrupt() {
+        let key = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let entry = crate::fixture::simple_entry(key, &["HTTP/1.1 200 OK"], b"body bytes");
+        assert_eq!(audit_data(&entry, "0123456789abcdef_0").status, "healthy");
+
+        let cut = &entry[..entry.len() - 10];
+        let file = audit_data(cut, "0123456789abcdef_0");
+        assert_eq!((file.status, file.problems.clone()), ("truncated", vec!["EOF0 record missing".to_string()]));
+
+        let mut flipped = entry.clone();
+        flipped[SIMPLE_CACHE_HEADER_SIZE + key.len()] ^= 0xff;
+        let file = audit_data(&flipped, "0123456789abcdef_0");
+        assert_eq!((file.status, file.problems.clone()), ("corrupt", vec!["CRC mismatch in stream 1".to_string()]));
+
+        let sparse = crate::fixture::sparse_entry(key, &[(0, b"first"), (4096, b"second")]);
+        assert_eq!(audit_data(&sparse, "0123456789abcdef_s").status, "healthy");
+        assert_eq!(audit_data(&sparse[..sparse.len() - 3], "0123456789abcdef_s").status, "truncated");
+        assert_eq!(audit_data(b"blockfile data", "f_000001").status, "skipped");
+        assert_eq!(audit_data(b"garbage garbage garbage garbage", "0123456789abcdef_1").status, "corrupt");
+
+        let dir = std::env::temp_dir().join(format!("cachephoenix-audit-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir).unwrap();
+        cache.add_entry(key, &["HTTP/1.1 200 OK"], b"body").unwrap();
+        let report = audit_cache(dir.to_string_lossy().to_string(), None).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!((report.total, report.healthy, report.files.len()), (1, 1, 0));
+    }
+}
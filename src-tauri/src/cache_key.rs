@@ -0,0 +1,91 @@
+// ─── Cache key normalization ────────────────────────────────────────────────
+//
+// Chromium's HttpCache::GenerateCacheKey builds the key from more than the
+// URL:
+//   1/0/                           credentials flag / upload data identifier
+//   _dk_ | _dk_s_ | _dk_cn_        double-keying marker (+ subframe document,
+//                                  cross-site main frame navigation flags)
+//   https://a.com https://b.com    network isolation key: top frame site,
+//                                  then frame site
+//   https://cdn.example/file.mp4   the resource URL
+// Older caches have just the URL, or `1/0/` and the URL. Grouping, naming and
+// CDN matching want the resource URL (the "effective" URL); the raw key is
+// kept for anything that has to match the cache's own bookkeeping.
+
+/// Marker that starts the isolation part of a double-keyed cache key.
+const DOUBLE_KEY_MARKER: &str = "_dk_";
+
+/// Skip a `<digits>/<digits>/` prefix.
+fn strip_numeric_prefix(key: &str) -> &str {
+    let mut rest = key;
+    for _ in 0..2 {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        match rest[digits..].strip_prefix('/') {
+            Some(after) if digits > 0 => rest = after,
+            _ => return key,
+        }
+    }
+    rest
+}
+
+/// Start of the first `http://` or `https://` in `s`.
+fn scheme_start(s: &str) -> Option<usize> {
+    match (s.find("http://"), s.find("https://")) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// The resource URL of a cache key, without the `1/0/` prefix and network
+/// isolation sites. Keys that aren't recognisably double-keyed come back
+/// with only the numeric prefix removed.
+pub fn effective_url(key: &str) -> &str {
+    let rest = strip_numeric_prefix(key.trim());
+    if !rest.starts_with(DOUBLE_KEY_MARKER) {
+        return rest;
+    }
+    // The URL is the last space-separated token; it may carry its own
+    // `1/0/` when an isolation prefix was prepended to a full key.
+    let last = rest.rsplit(' ').next().unwrap_or(rest);
+    scheme_start(last).map_or(last, |at| &last[at..])
+}
+
+/// The network isolation sites of a double-keyed key (top frame site, then
+/// frame site); empty for single-keyed caches.
+pub fn isolation_sites(key: &str) -> Vec<&str> {
+    let rest = strip_numeric_prefix(key.trim());
+    let Some(isolation) = rest.strip_prefix(DOUBLE_KEY_MARKER) else {
+        return Vec::new();
+    };
+    let mut tokens: Vec<&str> = isolation.split(' ').filter(|t| !t.is_empty()).collect();
+    tokens.pop();
+    tokens
+        .into_iter()
+        .filter_map(|t| scheme_start(t).map(|at| &t[at..]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_double_keyed_and_legacy_keys() {
+        let url = "https://cdn.discordapp.com/attachments/1/2/clip.mp4?ex=1";
+        let double = format!("1/0/_dk_https://discord.com https://discord.com {}", url);
+        assert_eq!(effective_url(&double), url);
+        assert_eq!(isolation_sites(&double), ["https://discord.com", "https://discord.com"]);
+
+        let subframe = format!("1/0/_dk_s_https://a.com https://b.com {}", url);
+        assert_eq!(effective_url(&subframe), url);
+        assert_eq!(isolation_sites(&subframe), ["https://a.com", "https://b.com"]);
+        assert_eq!(effective_url(&format!("_dk_s_ 1/0/{}", url)), url);
+
+        assert_eq!(effective_url(&format!("1/0/{}", url)), url);
+        assert_eq!(effective_url(&format!("0/12/{}", url)), url);
+        assert_eq!(effective_url(url), url);
+        assert!(isolation_sites(url).is_empty());
+        // A URL containing spaces-encoded-as-spaces is left whole when single-keyed.
+        assert_eq!(effective_url("1/0/https://x.com/a b.png"), "https://x.com/a b.png");
+    }
+}
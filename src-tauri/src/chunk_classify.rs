@@ -0,0 +1,166 @@
+use crate::moov_rebuild::{handler_of, nal_format, stbl_of, Codec, NalScanner};
+use crate::mp4_samples::{child, children};
+
+// ─── Chunk membership from the video bitstream ──────────────────────────────
+//
+// Which cache files belong to a chunked MP4 used to be decided by size alone:
+// full-size chunks are media, undersized ones are media unless they start
+// like a file of their own. That keeps a full-size chunk of some other
+// download and can drop a real one whose first bytes happen to look like a
+// JPEG or a TS packet. The moov says what the video data must look like — an
+// H.264 or H.265 stream of NAL units behind length prefixes of the size
+// avcC/hvcC gives — so a chunk is scanned for runs of such units. Media of
+// this video is mostly made of them (interleaved audio breaks the runs, the
+// next picture picks them up again). A chunk that parses better as the other
+// codec, or carries Annex B start codes instead of length prefixes, belongs
+// to a different stream. Anything in between — a chunk inside one huge
+// picture, zeros, audio — is left to the size rules.
+
+/// Share of a chunk covered by NAL units for it to count as this video's media.
+const MIN_NAL_SHARE: f64 = 0.25;
+/// Chunks shorter than this say too little either way.
+const MIN_CLASSIFY_BYTES: usize = 4096;
+/// Annex B start codes per MiB that mark an elementary stream of another file.
+const MIN_START_CODES_PER_MIB: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ChunkClass {
+    /// Runs of NAL units in the header's codec and prefix size
+    Video,
+    /// A bitstream in another codec or framing
+    Foreign,
+    /// No signal either way
+    Unknown,
+}
+
+pub(crate) struct NalClassifier {
+    codec: Codec,
+    length_size: usize,
+}
+
+impl NalClassifier {
+    /// None unless `moov` (the box payload) has an H.264 or H.265 video track
+    /// with 4-byte NAL length prefixes. Shorter prefixes are rare, and random
+    /// bytes chain into them too easily to tell anything.
+    pub(crate) fn from_moov(moov: &[u8]) -> Option<Self> {
+        let trak = children(moov)
+            .into_iter()
+            .filter(|(kind, _)| kind == b"trak")
+            .map(|(_, trak)| trak)
+            .find(|trak| handler_of(trak) == "vide")?;
+        let (codec, length_size) = nal_format(child(stbl_of(trak)?, b"stsd")?).ok()?;
+        (length_size == 4).then_some(NalClassifier { codec, length_size })
+    }
+
+    /// Share of `data` covered by chains of at least two NAL units of
+    /// `codec`, each chain starting where an access unit can.
+    fn nal_share(&self, data: &[u8], codec: Codec) -> f64 {
+        let scanner = NalScanner { data, codec, length_size: self.length_size, max_nal: data.len() as u64 };
+        let mut covered = 0;
+        let mut pos = 0;
+        while let Some(start) = (pos..data.len()).find(|&p| scanner.resync_at(p)) {
+            let (mut end, mut units) = (start, 0);
+            while let Some(nal) = scanner.nal_at(end) {
+                end += nal.total;
+                units += 1;
+            }
+            if units >= 2 || end == data.len() {
+                covered += end - start;
+            }
+            pos = end.max(start + 1);
+        }
+        covered as f64 / data.len() as f64
+    }
+
+    /// Annex B start codes (00 00 01) followed by a NAL header either codec
+    /// could write. Length-prefixed streams only have them in the prefixes.
+    fn start_codes(data: &[u8]) -> usize {
+        data.windows(4).filter(|w| w[..3] == [0, 0, 1] && w[3] & 0x80 == 0 && w[3] != 0).count()
+    }
+
+    pub(crate) fn classify(&self, data: &[u8]) -> ChunkClass {
+        if data.len() < MIN_CLASSIFY_BYTES {
+            return ChunkClass::Unknown;
+        }
+        let other = match self.codec {
+            Codec::Avc => Codec::Hevc,
+            Codec::Hevc => Codec::Avc,
+        };
+        let own = self.nal_share(data, self.codec);
+        let theirs = self.nal_share(data, other);
+        if theirs >= MIN_NAL_SHARE && theirs > own {
+            return ChunkClass::Foreign;
+        }
+        if own >= MIN_NAL_SHARE {
+            return ChunkClass::Video;
+        }
+        let start_codes = Self::start_codes(data);
+        if start_codes * (1 << 20) >= MIN_START_CODES_PER_MIB * data.len() && start_codes >= 4 {
+            return ChunkClass::Foreign;
+        }
+        ChunkClass::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::boxed;
+
+    /// Pseudo-random bytes standing in for entropy-coded slice data and audio.
+    fn noise(seed: u32, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    /// `count` pictures of length-prefixed NAL units, `audio` bytes after each.
+    fn stream(headers: &[[u8; 2]], count: usize, audio: usize) -> Vec<u8> {
+        let mut out = noise(1, 700); // the end of a picture from the previous chunk
+        for i in 0..count {
+            for (j, header) in headers.iter().enumerate() {
+                let mut unit = header.to_vec();
+                unit.push(if j == headers.len() - 1 { 0x80 } else { 0 });
+                unit.extend(noise(i as u32 * 7 + j as u32, 400 + 37 * j));
+                out.extend((unit.len() as u32).to_be_bytes());
+                out.extend(unit);
+            }
+            out.extend(noise(i as u32 + 99, audio));
+        }
+        out
+    }
+
+    #[test]
+    fn tells_this_videos_media_from_other_streams() {
+        let mut avc1 = vec![0u8; 78];
+        avc1.extend(boxed(b"avcC", &[1, 0x64, 0, 0x1F, 0xFF, 0xE0, 0]));
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend(boxed(b"avc1", &avc1));
+        let stbl = boxed(b"stbl", &boxed(b"stsd", &stsd));
+        let hdlr = boxed(b"hdlr", &[0, 0, 0, 0, 0, 0, 0, 0, b'v', b'i', b'd', b'e']);
+        let moov = boxed(b"trak", &boxed(b"mdia", &[hdlr, boxed(b"minf", &stbl)].concat()));
+        let classifier = NalClassifier::from_moov(&moov).unwrap();
+
+        // H.264: AUD, SEI, slice; H.265: AUD, SEI, TRAIL_N slice.
+        let avc = stream(&[[0x09, 0x10], [0x06, 0x05], [0x41, 0x9A]], 12, 300);
+        assert_eq!(classifier.classify(&avc), ChunkClass::Video);
+        let hevc = stream(&[[0x46, 0x01], [0x4E, 0x01], [0x00, 0x01]], 12, 300);
+        assert_eq!(classifier.classify(&hevc), ChunkClass::Foreign);
+
+        // The same pictures with start codes instead of length prefixes.
+        let mut annex_b = Vec::new();
+        for i in 0..40u32 {
+            annex_b.extend([0, 0, 0, 1, 0x41, 0x9A]);
+            annex_b.extend(noise(i, 500).iter().map(|&b| b | 1));
+        }
+        assert_eq!(classifier.classify(&annex_b), ChunkClass::Foreign);
+
+        assert_eq!(classifier.classify(&noise(5, 64 * 1024)), ChunkClass::Unknown);
+        assert_eq!(classifier.classify(&vec![0u8; 64 * 1024]), ChunkClass::Unknown);
+        assert_eq!(classifier.classify(&avc[..1000]), ChunkClass::Unknown);
+    }
+}
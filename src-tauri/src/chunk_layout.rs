@@ -0,0 +1,298 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::moov_rebuild::{handler_of, nal_format, stbl_of, Codec, NalScanner};
+use crate::mp4_samples::{child, children, track_samples};
+
+// ─── Chunk placement from the sample tables ─────────────────────────────────
+//
+// The legacy reconstruction places middle chunks by their f_XXXXXX numbers:
+// consecutive numbers are neighbours, a skipped number is a missing chunk.
+// That holds while the cache allocates files strictly in download order, and
+// breaks when two resources download at once or a range was re-fetched. Once
+// the tail's moov is known there is something better to go on: stco/co64 and
+// stsz say where every video sample starts and how long it is, and an H.264 /
+// H.265 sample is a run of length-prefixed NAL units that fills it exactly.
+// Random media bytes at a wrong offset almost never do. Each chunk is checked
+// at the slot the hex numbers gave it; a chunk that fails there is tried in
+// the free slots, and moved if the samples line up somewhere else.
+
+/// Whole video samples checked per chunk and slot; plenty to tell right from wrong.
+const MAX_CHECKS: usize = 32;
+
+/// Where a middle chunk goes, counted in chunk-sized slots after the header file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Placement {
+    pub slot: u64,
+    /// The slot is known (hex number or sample table), so a jump from the
+    /// previous chunk is a real gap. Otherwise the chunk simply follows it.
+    pub anchored: bool,
+}
+
+/// Slots from the hex filenames, as the legacy gap detection assigned them.
+/// `tail_hex` occupies a number but is placed at the end, so it is no gap.
+pub(crate) fn hex_placements(paths: &[String], header_hex: Option<u64>, tail_hex: Option<u64>) -> Vec<Placement> {
+    let mut last_hex = header_hex;
+    let mut next_slot = 0u64;
+    paths
+        .iter()
+        .map(|path| {
+            let hex = crate::parse_cache_hex(path);
+            let placement = match (last_hex, hex) {
+                (Some(prev), Some(curr)) => {
+                    let mut gap = curr.saturating_sub(prev).saturating_sub(1);
+                    if tail_hex.is_some_and(|th| th > prev && th < curr) {
+                        gap = gap.saturating_sub(1);
+                    }
+                    Placement { slot: next_slot + gap, anchored: true }
+                }
+                _ => Placement { slot: next_slot, anchored: false },
+            };
+            last_hex = hex.or(last_hex);
+            next_slot = placement.slot + 1;
+            placement
+        })
+        .collect()
+}
+
+/// The cache file name ("f_00002a") that would hold `slot`: the inverse of
+/// `hex_placements`, skipping the tail's number. None without a header number.
+pub(crate) fn slot_file_name(slot: u64, header_hex: Option<u64>, tail_hex: Option<u64>) -> Option<String> {
+    let mut hex = header_hex? + 1 + slot;
+    if tail_hex.is_some_and(|th| th > header_hex.unwrap_or(0) && th <= hex) {
+        hex += 1;
+    }
+    Some(format!("f_{:06x}", hex))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LayoutCheck {
+    /// Chunks whose video samples line up where the hex numbers put them
+    pub confirmed: usize,
+    /// Chunks moved to the slot where their samples line up
+    pub moved: usize,
+    /// Chunks that line up neither where they are nor in any free slot
+    pub contradicted: usize,
+    /// Chunks with no whole video sample to check, or mixed results
+    pub unverifiable: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Verdict {
+    Confirmed,
+    Contradicted,
+    Unverifiable,
+}
+
+fn verdict((checked, valid): (usize, usize)) -> Verdict {
+    if checked == 0 {
+        Verdict::Unverifiable
+    } else if valid * 10 >= checked * 9 {
+        Verdict::Confirmed
+    } else if valid * 2 < checked {
+        Verdict::Contradicted
+    } else {
+        Verdict::Unverifiable
+    }
+}
+
+/// The video track's samples, in the reconstructed file's coordinates.
+pub(crate) struct SampleLayout {
+    /// (offset, size), sorted by offset
+    samples: Vec<(u64, u32)>,
+    codec: Codec,
+    length_size: usize,
+    /// File offset of slot 0: the end of the header file's data
+    base: u64,
+    chunk_size: u64,
+}
+
+impl SampleLayout {
+    /// None unless `moov` (the box payload) has an H.264 or H.265 video track
+    /// with sample tables.
+    pub(crate) fn from_moov(moov: &[u8], base: u64, chunk_size: u64) -> Option<Self> {
+        let trak = children(moov)
+            .into_iter()
+            .filter(|(kind, _)| kind == b"trak")
+            .map(|(_, trak)| trak)
+            .find(|trak| handler_of(trak) == "vide")?;
+        let (codec, length_size) = nal_format(child(stbl_of(trak)?, b"stsd")?).ok()?;
+        let mut samples = track_samples(trak)?.samples;
+        samples.retain(|&(_, size)| size > 0);
+        samples.sort_unstable();
+        let usable = !samples.is_empty() && chunk_size > 0;
+        usable.then_some(SampleLayout { samples, codec, length_size, base, chunk_size })
+    }
+
+    /// Slots up to the end of the last video sample.
+    fn slot_count(&self) -> u64 {
+        let end = self.samples.iter().map(|&(offset, size)| offset + size as u64).max().unwrap_or(0);
+        end.saturating_sub(self.base).div_ceil(self.chunk_size)
+    }
+
+    /// Whether `data` is exactly a run of NAL units.
+    fn is_sample(&self, data: &[u8]) -> bool {
+        let scanner =
+            NalScanner { data, codec: self.codec, length_size: self.length_size, max_nal: data.len() as u64 };
+        let mut pos = 0;
+        while pos < data.len() {
+            match scanner.nal_at(pos) {
+                Some(nal) => pos += nal.total,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// (checked, valid) for the whole video samples `chunk` would hold at `slot`.
+    fn score(&self, chunk: &[u8], slot: u64) -> (usize, usize) {
+        let start = self.base + slot * self.chunk_size;
+        let end = start + chunk.len() as u64;
+        let first = self.samples.partition_point(|&(offset, _)| offset < start);
+        self.samples[first..]
+            .iter()
+            .take_while(|&&(offset, _)| offset < end)
+            .filter(|&&(offset, size)| offset + size as u64 <= end)
+            .take(MAX_CHECKS)
+            .fold((0, 0), |(checked, valid), &(offset, size)| {
+                let at = (offset - start) as usize;
+                (checked + 1, valid + self.is_sample(&chunk[at..at + size as usize]) as usize)
+            })
+    }
+}
+
+/// Check every chunk against the sample table at its placement and move the
+/// ones whose samples line up in a different free slot. `paths` and
+/// `placements` come back sorted by slot.
+pub(crate) fn check_placements(
+    layout: &SampleLayout,
+    paths: &mut [String],
+    placements: &mut [Placement],
+    read: fn(&str) -> Result<Vec<u8>, String>,
+) -> LayoutCheck {
+    let mut check = LayoutCheck::default();
+    let mut claimed = HashSet::new();
+    let mut misplaced = Vec::new();
+    for (i, (_, chunk)) in crate::reconstruction::read_ahead(paths.to_vec(), read).enumerate() {
+        // Unreadable chunks and duplicate tails are left to the assembly loop.
+        let Ok(chunk) = chunk else {
+            claimed.insert(placements[i].slot);
+            continue;
+        };
+        if crate::scan_for_moov(&chunk).is_some() {
+            continue;
+        }
+        match verdict(layout.score(&chunk, placements[i].slot)) {
+            Verdict::Confirmed => check.confirmed += 1,
+            Verdict::Unverifiable => check.unverifiable += 1,
+            Verdict::Contradicted => {
+                misplaced.push(i);
+                continue;
+            }
+        }
+        claimed.insert(placements[i].slot);
+    }
+
+    for i in misplaced {
+        let Ok(chunk) = read(&paths[i]) else {
+            check.contradicted += 1;
+            continue;
+        };
+        let best = (0..layout.slot_count())
+            .filter(|slot| !claimed.contains(slot))
+            .map(|slot| (slot, layout.score(&chunk, slot)))
+            .filter(|&(_, score)| verdict(score) == Verdict::Confirmed)
+            .max_by_key(|&(slot, (_, valid))| (valid, std::cmp::Reverse(slot)));
+        let name = std::path::Path::new(&paths[i]).file_name().unwrap_or_default().to_string_lossy().to_string();
+        match best {
+            Some((slot, _)) => {
+                println!(
+                    "[chunk_layout] {} belongs in slot {} per the sample table, not slot {}",
+                    name, slot, placements[i].slot
+                );
+                placements[i] = Placement { slot, anchored: true };
+                claimed.insert(slot);
+                check.moved += 1;
+            }
+            None => {
+                println!(
+                    "[chunk_layout] {} doesn't match the sample table in slot {} or any free slot",
+                    name, placements[i].slot
+                );
+                claimed.insert(placements[i].slot);
+                check.contradicted += 1;
+            }
+        }
+    }
+
+    let mut order: Vec<(Placement, String)> = placements.iter().copied().zip(paths.iter().cloned()).collect();
+    order.sort_by_key(|(placement, _)| placement.slot);
+    for ((placement, path), (slot_placement, slot_path)) in order.into_iter().zip(placements.iter_mut().zip(paths)) {
+        *slot_placement = placement;
+        *slot_path = path;
+    }
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{boxed, full};
+
+    #[test]
+    fn moves_chunks_whose_samples_line_up_elsewhere() {
+        // Four 100-byte chunks after a 100-byte header; samples cross chunk
+        // edges so each chunk's sample starts sit at different positions.
+        let sizes = [30u32, 30, 30, 20, 50, 30, 30, 50, 50, 40, 40];
+        let mut media = Vec::new();
+        for (i, &size) in sizes.iter().enumerate() {
+            media.extend_from_slice(&(size - 4).to_be_bytes());
+            media.push(if i == 0 { 0x65 } else { 0x41 });
+            media.resize(media.len() + size as usize - 5, 0x5A);
+        }
+        let mut avc1 = vec![0u8; 78];
+        avc1.extend(boxed(b"avcC", &[1, 0x64, 0, 0x1F, 0xFF, 0xE0, 0]));
+        let mut stsz = vec![0, sizes.len() as u32];
+        stsz.extend_from_slice(&sizes);
+        let stbl = [
+            boxed(b"stsd", &[full(&[1]), boxed(b"avc1", &avc1)].concat()),
+            boxed(b"stsc", &full(&[1, 1, sizes.len() as u32, 1])),
+            boxed(b"stsz", &full(&stsz)),
+            boxed(b"stco", &full(&[1, 100])),
+        ]
+        .concat();
+        let hdlr = boxed(b"hdlr", &[0, 0, 0, 0, 0, 0, 0, 0, b'v', b'i', b'd', b'e']);
+        let moov = boxed(b"trak", &boxed(b"mdia", &[hdlr, boxed(b"minf", &boxed(b"stbl", &stbl))].concat()));
+        let layout = SampleLayout::from_moov(&moov, 100, 100).unwrap();
+        assert_eq!(layout.slot_count(), 4);
+
+        // f_000003 and f_000004 hold each other's data.
+        let dir = std::env::temp_dir().join(format!("cachephoenix-layout-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut paths = Vec::new();
+        for (hex, slot) in [(2, 0), (3, 2), (4, 1), (5, 3)] {
+            let path = dir.join(format!("f_{:06x}", hex));
+            std::fs::write(&path, &media[slot * 100..slot * 100 + 100]).unwrap();
+            paths.push(path.to_string_lossy().to_string());
+        }
+        let mut placements = hex_placements(&paths, Some(1), None);
+        assert_eq!(placements.iter().map(|p| p.slot).collect::<Vec<_>>(), [0, 1, 2, 3]);
+
+        let check = check_placements(&layout, &mut paths, &mut placements, |p| {
+            std::fs::read(p).map_err(|e| e.to_string())
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(check, LayoutCheck { confirmed: 2, moved: 2, contradicted: 0, unverifiable: 0 });
+        let names: Vec<&str> = paths.iter().map(|p| &p[p.len() - 8..]).collect();
+        assert_eq!(names, ["f_000002", "f_000004", "f_000003", "f_000005"]);
+        assert!(placements.iter().all(|p| p.anchored));
+
+        // A skipped number is a gap; the tail's number is not.
+        let gapped: Vec<String> = ["c/f_000003", "c/f_000006", "c/f_000008"].iter().map(|s| s.to_string()).collect();
+        let slots: Vec<u64> = hex_placements(&gapped, Some(1), Some(7)).iter().map(|p| p.slot).collect();
+        assert_eq!(slots, [1, 4, 5]);
+
+        // Eight bytes, but not an f_ name; the first char isn't one byte.
+        assert_eq!(crate::parse_cache_hex("c/€12345"), None);
+    }
+}
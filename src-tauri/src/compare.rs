@@ -0,0 +1,264 @@
+use serde::Serialize;
+
+// Entry body comparison.
+//
+// The same video often sits in a cache several times: a full `_0` body, a
+// partial `_s` sparse entry, a media-proxy variant. `compare_entries` tells
+// the user how two of them relate — identical, one a prefix of the other, or
+// overlapping byte ranges — and which one holds more of the resource.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryCoverage {
+    pub path: String,
+    /// Size of the reassembled body (including zero-filled gaps)
+    pub size: u64,
+    /// Bytes actually present in the cache
+    pub covered_bytes: u64,
+    /// Present byte ranges as [start, end)
+    pub ranges: Vec<[u64; 2]>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareResult {
+    pub a: EntryCoverage,
+    pub b: EntryCoverage,
+    /// "identical", "a_prefix_of_b", "b_prefix_of_a", "overlapping",
+    /// "diverged" (overlapping ranges disagree) or "disjoint"
+    pub relation: &'static str,
+    /// Length of the common prefix of both bodies
+    pub common_prefix: u64,
+    /// Bytes present in both and equal
+    pub matching_bytes: u64,
+    /// Bytes present in both but different
+    pub conflicting_bytes: u64,
+    pub only_a_bytes: u64,
+    pub only_b_bytes: u64,
+    /// "a", "b" or "same": which entry covers more of the resource
+    pub more_complete: &'static str,
+}
+
+/// A body plus the ranges of it that actually came from the cache.
+pub(crate) struct Body {
+    pub(crate) data: Vec<u8>,
+    pub(crate) ranges: Vec<[u64; 2]>,
+}
+
+/// Sort and merge overlapping/adjacent ranges.
+pub(crate) fn normalize(mut ranges: Vec<[u64; 2]>) -> Vec<[u64; 2]> {
+    ranges.retain(|r| r[1] > r[0]);
+    ranges.sort_by_key(|r| r[0]);
+    let mut out: Vec<[u64; 2]> = Vec::with_capacity(ranges.len());
+    for r in ranges {
+        match out.last_mut() {
+            Some(last) if r[0] <= last[1] => last[1] = last[1].max(r[1]),
+            _ => out.push(r),
+        }
+    }
+    out
+}
+
+fn intersect(a: &[[u64; 2]], b: &[[u64; 2]]) -> Vec<[u64; 2]> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        let start = a[i][0].max(b[j][0]);
+        let end = a[i][1].min(b[j][1]);
+        if start < end {
+            out.push([start, end]);
+        }
+        if a[i][1] < b[j][1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    out
+}
+
+fn total(ranges: &[[u64; 2]]) -> u64 {
+    ranges.iter().map(|r| r[1] - r[0]).sum()
+}
+
+pub(crate) fn load(path: &str) -> Result<Body, String> {
+    if crate::is_simple_cache_sparse(path) {
+        let raw = crate::read_with_lock_retry(path).map_err(|e| crate::format_read_error(path, &e))?;
+        let (_, chunks) = crate::parse_sparse_ranges(&raw, path)?;
+        let data = crate::reassemble_sparse_data(&raw, path)?;
+        let ranges = if chunks.is_empty() {
+            // Raw-body variant without range headers: all of it is present.
+            vec![[0, data.len() as u64]]
+        } else {
+            chunks
+                .iter()
+                .map(|(off, d)| [*off, off + d.len() as u64])
+                .collect()
+        };
+        Ok(Body { ranges: normalize(ranges), data })
+    } else {
+        let data = crate::read_cache_body(path)?;
+        Ok(Body { ranges: vec![[0, data.len() as u64]], data })
+    }
+}
+
+fn compare_bodies(a: &Body, b: &Body) -> (u64, u64, u64) {
+    let common_prefix = a
+        .data
+        .iter()
+        .zip(&b.data)
+        .take_while(|(x, y)| x == y)
+        .count() as u64;
+    let mut matching = 0u64;
+    let mut conflicting = 0u64;
+    for [start, end] in intersect(&a.ranges, &b.ranges) {
+        let (s, e) = (start as usize, end as usize);
+        let same = a.data[s..e]
+            .iter()
+            .zip(&b.data[s..e])
+            .filter(|(x, y)| x == y)
+            .count() as u64;
+        matching += same;
+        conflicting += (e - s) as u64 - same;
+    }
+    (common_prefix, matching, conflicting)
+}
+
+fn build_result(a_path: String, a: &Body, b_path: String, b: &Body) -> CompareResult {
+    let (common_prefix, matching_bytes, conflicting_bytes) = compare_bodies(a, b);
+    let a_covered = total(&a.ranges);
+    let b_covered = total(&b.ranges);
+    let shared = matching_bytes + conflicting_bytes;
+    let (a_len, b_len) = (a.data.len() as u64, b.data.len() as u64);
+
+    let relation = if a.data == b.data && a.ranges == b.ranges {
+        "identical"
+    } else if conflicting_bytes > 0 {
+        "diverged"
+    } else if shared == 0 {
+        "disjoint"
+    } else if common_prefix == a_len && a_covered == a_len && a_len < b_len {
+        "a_prefix_of_b"
+    } else if common_prefix == b_len && b_covered == b_len && b_len < a_len {
+        "b_prefix_of_a"
+    } else {
+        "overlapping"
+    };
+    let more_complete = match a_covered.cmp(&b_covered) {
+        std::cmp::Ordering::Greater => "a",
+        std::cmp::Ordering::Less => "b",
+        std::cmp::Ordering::Equal => "same",
+    };
+
+    CompareResult {
+        a: EntryCoverage { path: a_path, size: a_len, covered_bytes: a_covered, ranges: a.ranges.clone() },
+        b: EntryCoverage { path: b_path, size: b_len, covered_bytes: b_covered, ranges: b.ranges.clone() },
+        relation,
+        common_prefix,
+        matching_bytes,
+        conflicting_bytes,
+        only_a_bytes: a_covered - shared,
+        only_b_bytes: b_covered - shared,
+        more_complete,
+    }
+}
+
+/// Compare the bodies of two cache entry files (`_0`, `_1`, `_s` or `f_`).
+#[tauri::command(async)]
+pub fn compare_entries(a: String, b: String) -> Result<CompareResult, String> {
+    let body_a = load(&a)?;
+    let body_b = load(&b)?;
+    Ok(build_result(a, &body_a, b, &body_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full(data: &[u8]) -> Body {
+        Body { data: data.to_vec(), ranges: vec![[0, data.len() as u64]] }
+    }
+
+    #[test]
+    fn prefix_and_partial_sparse() {
+        let r = build_result("a".into(), &full(b"abcdef"), "b".into(), &full(b"abcdefghij"));
+        assert_eq!(r.relation, "a_prefix_of_b");
+        assert_eq!(r.more_complete, "b");
+        assert_eq!(r.only_b_bytes, 4);
+
+        // Sparse body with a zero-filled hole at [2, 6)
+        let sparse = Body { data: b"ab\0\0\0\0ghij".to_vec(), ranges: vec![[0, 2], [6, 10]] };
+        let r = build_result("a".into(), &full(b"abcdefghij"), "b".into(), &sparse);
+        assert_eq!(r.relation, "overlapping");
+        assert_eq!(r.matching_bytes, 6);
+        assert_eq!(r.conflicting_bytes, 0);
+        assert_eq!(r.more_complete, "a");
+    }
+
+    #[test]
+    fn merges_and_intersects_ranges() {
+        assert_eq!(normalize(vec![[5, 8], [0, 3], [3, 4], [7, 9]]), vec![[0, 4], [5, 9]]);
+        assert_eq!(intersect(&[[0, 4], [5, 9]], &[[2, 6]]), vec![[2, 4], [5, 6]]);
+    }
+
+    #[test]
+    fn later_sparse_range_wins_overlap() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-overlap-{}", std::process::id()));
+        // [4, 11) is written first; the re-fetched [0, 9) comes later in the file.
+        let path = crate::fixture::FixtureCache::create(&dir)
+            .unwrap()
+            .add_sparse_entry("https://cdn.example/v.mp4", &[(4, b"stale!!"), (0, b"freshdata")])
+            .unwrap();
+        let body = load(&path).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(body.data, b"freshdata!!");
+        assert_eq!(body.ranges, vec![[0, 11]]);
+    }
+
+    #[test]
+    fn resyncs_past_corrupt_range_header() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-resync-{}", std::process::id()));
+        let path = crate::fixture::FixtureCache::create(&dir)
+            .unwrap()
+            .add_sparse_entry("https://cdn.example/v.mp4", &[(0, b"aaaa"), (4, b"bbbb"), (8, b"cccc")])
+            .unwrap();
+        let mut raw = std::fs::read(&path).unwrap();
+        let magic = crate::SPARSE_RANGE_MAGIC.to_le_bytes();
+        let second = raw
+            .windows(magic.len())
+            .enumerate()
+            .filter(|(_, w)| *w == magic)
+            .nth(1)
+            .unwrap()
+            .0;
+        raw[second] ^= 0xff;
+        std::fs::write(&path, &raw).unwrap();
+
+        let body = load(&path).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(body.ranges, vec![[0, 4], [8, 12]]);
+        assert_eq!(&body.data[8..], b"cccc");
+    }
+
+    #[test]
+    fn keeps_the_cut_off_tail_behind_a_corrupt_header() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-cut-tail-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir).unwrap();
+        let path = cache
+            .add_sparse_entry("https://cdn.example/v.mp4", &[(0, b"aaaa"), (4, b"bbbb"), (8, b"cccc")])
+            .unwrap();
+        let far = cache.add_sparse_entry("https://cdn.example/w.mp4", &[(0, b"aaaa"), (1 << 40, b"zz")]).unwrap();
+        let mut raw = std::fs::read(&path).unwrap();
+        let magic = crate::SPARSE_RANGE_MAGIC.to_le_bytes();
+        let headers: Vec<usize> =
+            raw.windows(magic.len()).enumerate().filter(|(_, w)| *w == magic).map(|(i, _)| i).collect();
+        raw[headers[1]] ^= 0xff;
+        raw.truncate(headers[2] + crate::SPARSE_RANGE_HEADER_SIZE + 2);
+        std::fs::write(&path, &raw).unwrap();
+
+        let body = load(&path).unwrap();
+        let far = load(&far).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(body.ranges, vec![[0, 4], [8, 10]]);
+        assert_eq!(&body.data[8..], b"cc");
+        assert_eq!(far.data, b"aaaa");
+    }
+}
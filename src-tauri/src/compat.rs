@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::{
+    FLAG_HAS_CRC32, FLAG_HAS_KEY_SHA256, SIMPLE_CACHE_EOF_MAGIC, SIMPLE_CACHE_EOF_SIZE, SIMPLE_CACHE_HEADER_SIZE,
+};
+
+// ─── Forward compatibility ──────────────────────────────────────────────────
+//
+// Chromium bumps on-disk versions and adds flag bits now and then. Rejecting
+// anything newer than what we know turns one such release into "unsupported"
+// for the whole cache, even though the layouts rarely change much. Instead
+// the parsers carry on with the newest layout they understand and record a
+// structural fingerprint here: the format, what was unexpected, and numeric
+// fields such as sizes, versions and flags — never keys, URLs or body bytes.
+// Fingerprints are deduplicated, a few samples are kept per anomaly, and the
+// lot is written to the diagnostics folder for attaching to a bug report.
+
+/// Simple Cache entry versions we parse (kSimpleEntryVersionOnDisk).
+const KNOWN_ENTRY_VERSIONS: &[u32] = &[5];
+const KNOWN_EOF_FLAGS: u32 = FLAG_HAS_CRC32 | FLAG_HAS_KEY_SHA256;
+/// Samples kept per anomaly; later sightings are only counted.
+const MAX_SAMPLES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fingerprint {
+    pub file_size: u64,
+    /// Structural fields only: versions, flags, sizes, magics
+    pub fields: BTreeMap<&'static str, u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompatObservation {
+    /// "simple_entry", "simple_index" or "blockfile_index"
+    pub format: &'static str,
+    /// What was unexpected, e.g. "header version 6"
+    pub anomaly: String,
+    /// Times seen since startup
+    pub count: u64,
+    pub samples: Vec<Fingerprint>,
+}
+
+static OBSERVATIONS: Mutex<Option<HashMap<(&'static str, String), CompatObservation>>> = Mutex::new(None);
+
+fn with_observations<T>(f: impl FnOnce(&mut HashMap<(&'static str, String), CompatObservation>) -> T) -> T {
+    let mut observations = OBSERVATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    f(observations.get_or_insert_with(HashMap::new))
+}
+
+/// Record an unexpected structure the parser chose to carry on past.
+pub(crate) fn record(format: &'static str, anomaly: String, file_size: u64, fields: &[(&'static str, u64)]) {
+    let sample = Fingerprint { file_size, fields: fields.iter().copied().collect() };
+    let new_sample = with_observations(|observations| {
+        let observation = observations.entry((format, anomaly.clone())).or_insert_with(|| {
+            eprintln!("[compat] Unrecognised {} structure ({}), parsing best-effort", format, anomaly);
+            CompatObservation { format, anomaly, count: 0, samples: Vec::new() }
+        });
+        observation.count += 1;
+        let keep = observation.samples.len() < MAX_SAMPLES && !observation.samples.contains(&sample);
+        if keep {
+            observation.samples.push(sample);
+        }
+        keep
+    });
+    if new_sample {
+        if let Err(e) = write_fingerprints() {
+            eprintln!("[compat] {}", e);
+        }
+    }
+}
+
+/// Check a Simple Cache entry file's header version and trailing EOF flags.
+/// Called by the layout parsers once the magic has matched.
+pub(crate) fn note_simple_entry(data: &[u8]) {
+    let read_u32 = |off: usize| data.get(off..off + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    let Some(version) = read_u32(8) else {
+        return;
+    };
+    let key_length = read_u32(12).unwrap_or(0);
+    let eof = data.len().checked_sub(SIMPLE_CACHE_EOF_SIZE).filter(|&start| {
+        start >= SIMPLE_CACHE_HEADER_SIZE
+            && data.get(start..start + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+                == Some(SIMPLE_CACHE_EOF_MAGIC)
+    });
+    let eof_flags = eof.and_then(|start| read_u32(start + 8));
+    let eof_stream_size = eof.and_then(|start| read_u32(start + 16));
+
+    let mut anomalies = Vec::new();
+    if !KNOWN_ENTRY_VERSIONS.contains(&version) {
+        anomalies.push(format!("header version {}", version));
+    }
+    if let Some(flags) = eof_flags.filter(|flags| flags & !KNOWN_EOF_FLAGS != 0) {
+        anomalies.push(format!("EOF flags 0x{:x}", flags & !KNOWN_EOF_FLAGS));
+    }
+    let fields = [
+        ("header_version", version as u64),
+        ("key_length", key_length as u64),
+        ("trailing_eof", eof.is_some() as u64),
+        ("eof_flags", eof_flags.unwrap_or(0) as u64),
+        ("eof_stream_size", eof_stream_size.unwrap_or(0) as u64),
+    ];
+    for anomaly in anomalies {
+        record("simple_entry", anomaly, data.len() as u64, &fields);
+    }
+}
+
+/// Everything recorded since startup, most frequent first.
+pub fn observations() -> Vec<CompatObservation> {
+    let mut all: Vec<CompatObservation> = with_observations(|observations| observations.values().cloned().collect());
+    all.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.anomaly.cmp(&b.anomaly)));
+    all
+}
+
+fn write_fingerprints() -> Result<(), String> {
+    let dir = crate::crash::diagnostics_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create dir {}: {}", dir.display(), e))?;
+    let path = dir.join("compat-fingerprints.json");
+    let report = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "observations": observations(),
+    });
+    let text = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Unrecognised cache structures seen this session.
+#[tauri::command]
+pub fn compat_fingerprints() -> Vec<CompatObservation> {
+    observations()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::simple_entry;
+
+    #[test]
+    fn fingerprints_unknown_versions_and_flags_without_content() {
+        let key = "https://cdn.discordapp.com/attachments/1/2/secret.png";
+        let mut entry = simple_entry(key, &["HTTP/1.1 200 OK"], b"body");
+        entry[8..12].copy_from_slice(&77u32.to_le_bytes());
+        let eof = entry.len() - SIMPLE_CACHE_EOF_SIZE;
+        entry[eof + 8] |= 0x40;
+        note_simple_entry(&entry);
+        note_simple_entry(&entry);
+        note_simple_entry(&simple_entry("https://example.com/", &["HTTP/1.1 200 OK"], b"ok"));
+
+        let found = observations();
+        let version = found.iter().find(|o| o.anomaly == "header version 77").unwrap();
+        assert_eq!((version.format, version.count, version.samples.len()), ("simple_entry", 2, 1));
+        assert_eq!(version.samples[0].fields["header_version"], 77);
+        assert_eq!(version.samples[0].file_size, entry.len() as u64);
+        assert!(found.iter().any(|o| o.anomaly == "EOF flags 0x40"));
+        assert!(!serde_json::to_string(&found).unwrap().contains("discordapp"));
+    }
+}
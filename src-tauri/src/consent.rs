@@ -0,0 +1,256 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// ─── Write consent ──────────────────────────────────────────────────────────
+//
+// Every command that creates, rewrites or deletes files at a path the
+// frontend chose (recovery, export, repair, carving, job cleanup) only runs
+// with a consent token. The one exception is the scanner's scratch files in
+// the temp directory (`dccr_*`), which previews write unasked. A
+// token is minted by `request_write_consent`, which shows a native dialog
+// from Rust naming the folder; it is never shown or granted by the webview
+// itself. A compromised webview can therefore ask, but cannot write silently.
+// Each token covers one folder (or file) tree and expires after IDLE_TTL
+// without use, or MAX_LIFETIME in total.
+
+/// A token expires after this long without being used.
+const IDLE_TTL: Duration = Duration::from_secs(10 * 60);
+/// Hard limit on a token's lifetime, however often it is used.
+const MAX_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+struct Grant {
+    scope: PathBuf,
+    created: Instant,
+    last_used: Instant,
+}
+
+/// Live consent tokens; managed as Tauri state.
+#[derive(Default)]
+pub struct ConsentState {
+    grants: Mutex<HashMap<String, Grant>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WriteConsent {
+    pub token: String,
+    /// The folder (or file) the token allows writing to, normalized
+    pub scope: String,
+    pub idle_ttl_secs: u64,
+}
+
+/// Make `path` absolute-and-final for comparisons: `.` is dropped, `..` is
+/// refused (collapsing it lexically would undo a symlink the OS follows
+/// first, `<scope>/link/../x`), then the deepest existing ancestor is
+/// canonicalized so a symlink inside the scope can't point writes outside it.
+fn normalize(path: &Path) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("Path must be absolute: {}", path.display()));
+    }
+    let mut lexical = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => return Err(format!("Path must not contain '..': {}", path.display())),
+            Component::CurDir => {}
+            other => lexical.push(other),
+        }
+    }
+    let mut existing = lexical.clone();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match existing.file_name() {
+            Some(name) => {
+                missing.push(name.to_os_string());
+                existing.pop();
+            }
+            None => break,
+        }
+    }
+    let mut resolved = std::fs::canonicalize(&existing).unwrap_or(existing);
+    for name in missing.iter().rev() {
+        resolved.push(name);
+    }
+    Ok(resolved)
+}
+
+/// 128 bits from the OS CSPRNG.
+fn random_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| format!("No randomness for a consent token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Whether `path` is one of CachePhoenix's own scratch files in the temp
+/// directory, which scan previews write without asking.
+pub(crate) fn is_scratch(path: &str) -> bool {
+    let (Ok(temp), Ok(target)) = (normalize(&std::env::temp_dir()), normalize(Path::new(path))) else {
+        return false;
+    };
+    target
+        .strip_prefix(&temp)
+        .ok()
+        .and_then(|rest| rest.components().next())
+        .is_some_and(|first| crate::privacy::is_workspace_entry(&first.as_os_str().to_string_lossy()))
+}
+
+impl ConsentState {
+    fn mint(&self, scope: PathBuf) -> Result<String, String> {
+        let token = random_token()?;
+        let now = Instant::now();
+        let mut grants = self.grants.lock().unwrap_or_else(|e| e.into_inner());
+        grants.retain(|_, g| !g.expired(now));
+        grants.insert(token.clone(), Grant { scope, created: now, last_used: now });
+        Ok(token)
+    }
+
+    /// Allow a write to `path` only if `token` is live and covers it. Returns
+    /// the path as it was checked (see `resolve`); write there, not to `path`.
+    pub fn check(&self, token: &str, path: &str) -> Result<String, String> {
+        self.resolve(token, path).and_then(into_string)
+    }
+
+    /// `check` as a `PathBuf`: absolute, with symlinks resolved. Write there
+    /// rather than to `path` itself.
+    pub fn resolve(&self, token: &str, path: &str) -> Result<PathBuf, String> {
+        let target = normalize(Path::new(path))?;
+        let now = Instant::now();
+        let mut grants = self.grants.lock().unwrap_or_else(|e| e.into_inner());
+        let grant = match grants.get_mut(token) {
+            Some(grant) if !grant.expired(now) => grant,
+            Some(_) => {
+                grants.remove(token);
+                return Err("Write permission expired — please confirm again".to_string());
+            }
+            None => return Err("Write permission was not granted".to_string()),
+        };
+        if !target.starts_with(&grant.scope) {
+            eprintln!(
+                "[consent] Refused write to {} (token covers {})",
+                target.display(),
+                grant.scope.display()
+            );
+            return Err(format!("Write permission does not cover {}", path));
+        }
+        grant.last_used = now;
+        Ok(target)
+    }
+
+    /// `check` for commands the scanner also runs on its temp scratch files
+    /// (`dccr_*`, `cachephoenix*`): those need no token, anything else does.
+    pub fn check_output(&self, token: Option<&str>, path: &str) -> Result<String, String> {
+        if is_scratch(path) {
+            return normalize(Path::new(path)).and_then(into_string);
+        }
+        self.check(token.unwrap_or_default(), path)
+    }
+}
+
+fn into_string(path: PathBuf) -> Result<String, String> {
+    path.into_os_string()
+        .into_string()
+        .map_err(|p| format!("Path is not valid Unicode: {}", p.to_string_lossy()))
+}
+
+impl Grant {
+    fn expired(&self, now: Instant) -> bool {
+        now.duration_since(self.last_used) > IDLE_TTL || now.duration_since(self.created) > MAX_LIFETIME
+    }
+}
+
+/// Ask the user (native dialog) to allow file writes under `scope`. Returns
+/// a token to pass as `consent` to write commands, or an error if declined.
+#[tauri::command(async)]
+pub fn request_write_consent(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ConsentState>,
+    scope: String,
+    reason: String,
+) -> Result<WriteConsent, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let normalized = normalize(Path::new(&scope))?;
+    if normalized.parent().is_none() {
+        return Err(format!("Refusing write permission for a whole drive: {}", scope));
+    }
+    let allowed = app
+        .dialog()
+        .message(format!(
+            "{}\n\nCachePhoenix will be allowed to create and modify files in:\n{}",
+            reason,
+            normalized.display()
+        ))
+        .title("Allow file changes?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Cancel".to_string()))
+        .blocking_show();
+    if !allowed {
+        return Err("Write permission was declined".to_string());
+    }
+    println!("[consent] Granted writes under {}", normalized.display());
+    let token = state.mint(normalized.clone())?;
+    Ok(WriteConsent {
+        token,
+        scope: normalized.to_string_lossy().to_string(),
+        idle_ttl_secs: IDLE_TTL.as_secs(),
+    })
+}
+
+/// Drop a token before it expires (e.g. when recovery finishes).
+#[tauri::command]
+pub fn revoke_write_consent(state: tauri::State<'_, ConsentState>, token: String) {
+    let mut grants = state.grants.lock().unwrap_or_else(|e| e.into_inner());
+    grants.remove(&token);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_scoped_to_their_folder() {
+        let root = std::env::temp_dir().join(format!("consent-test-{}", std::process::id()));
+        let out = root.join("out");
+        std::fs::create_dir_all(&out).unwrap();
+        let state = ConsentState::default();
+        let token = state.mint(normalize(&out).unwrap()).unwrap();
+
+        let inside = out.join("videos").join("clip.mp4");
+        assert!(state.check(&token, &inside.to_string_lossy()).is_ok());
+        let escape = out.join("..").join("elsewhere.txt");
+        assert!(state.check(&token, &escape.to_string_lossy()).is_err());
+        assert!(state.check(&token, &out.join("videos/../clip.mp4").to_string_lossy()).is_err());
+        assert!(state.check("not-a-token", &inside.to_string_lossy()).is_err());
+        assert_ne!(random_token().unwrap(), random_token().unwrap());
+        assert_eq!(random_token().unwrap().len(), 32);
+
+        // Scratch files in the temp directory need no token; the rest do.
+        let scratch = std::env::temp_dir().join("dccr_thumb_1.png");
+        assert!(state.check_output(None, &scratch.to_string_lossy()).is_ok());
+        assert!(state.check_output(None, &inside.to_string_lossy()).is_err());
+        assert!(state.check_output(Some(&token), &inside.to_string_lossy()).is_ok());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_resolve_before_the_scope_check() {
+        let root = std::env::temp_dir().join(format!("consent-link-{}", std::process::id()));
+        let out = root.join("out");
+        let elsewhere = root.join("elsewhere").join("deep");
+        std::fs::create_dir_all(&out).unwrap();
+        std::fs::create_dir_all(&elsewhere).unwrap();
+        std::os::unix::fs::symlink(&elsewhere, out.join("link")).unwrap();
+        let state = ConsentState::default();
+        let token = state.mint(normalize(&out).unwrap()).unwrap();
+
+        // The OS would follow `link` first and land in `elsewhere`.
+        let via_link = out.join("link").join("..").join("x.txt");
+        assert!(state.check(&token, &via_link.to_string_lossy()).is_err());
+        assert!(state.check(&token, &out.join("link").join("x.txt").to_string_lossy()).is_err());
+        let checked = state.check(&token, &out.join("x.txt").to_string_lossy()).unwrap();
+        assert_eq!(PathBuf::from(checked), normalize(&out).unwrap().join("x.txt"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,149 @@
+// Content-Disposition filename extraction (RFC 6266 + RFC 5987).
+//
+// Discord and most CDNs send `content-disposition: attachment; filename="video.mp4"`,
+// sometimes alongside an extended `filename*=UTF-8''v%C3%ADdeo.mp4` parameter for
+// non-ASCII names. Per RFC 6266 §4.3 the extended form wins when both are present.
+
+/// Extract the original filename from a Content-Disposition header value.
+/// Prefers `filename*` (RFC 5987) over `filename`. The result is reduced to a
+/// bare file name (no directory components) and is never empty.
+pub fn filename(value: &str) -> Option<String> {
+    let mut plain: Option<String> = None;
+    let mut extended: Option<String> = None;
+
+    for param in split_params(value).into_iter().skip(1) {
+        let Some((name, raw)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_lowercase().as_str() {
+            "filename*" if extended.is_none() => extended = decode_ext_value(raw.trim()),
+            "filename" if plain.is_none() => plain = Some(unquote(raw.trim())),
+            _ => {}
+        }
+    }
+
+    extended
+        .and_then(|n| sanitize(&n))
+        .or_else(|| plain.and_then(|n| sanitize(&n)))
+}
+
+/// Split a header value on `;`, ignoring separators inside quoted strings.
+/// The first element is the disposition type (`attachment` / `inline`).
+fn split_params(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Remove surrounding quotes and backslash escapes from a quoted-string.
+/// Unquoted tokens are returned as-is.
+fn unquote(raw: &str) -> String {
+    let Some(inner) = raw.strip_prefix('"') else {
+        return raw.to_string();
+    };
+    let inner = inner.strip_suffix('"').unwrap_or(inner);
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                out.push(next);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Decode an RFC 5987 ext-value: `charset'[language]'percent-encoded-bytes`.
+fn decode_ext_value(raw: &str) -> Option<String> {
+    // Some servers wrap the ext-value in quotes even though the RFC forbids it.
+    let raw = raw.trim_matches('"');
+    let mut parts = raw.splitn(3, '\'');
+    let charset = parts.next()?.to_lowercase();
+    let _language = parts.next()?;
+    let encoded = parts.next()?;
+    let bytes = percent_decode(encoded);
+    match charset.as_str() {
+        // Latin-1 bytes map 1:1 onto the first 256 code points.
+        "iso-8859-1" | "latin1" | "latin-1" => Some(bytes.iter().map(|&b| b as char).collect()),
+        _ => Some(String::from_utf8_lossy(&bytes).to_string()),
+    }
+}
+
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Reduce a suggested filename to something safe to join onto an output folder:
+/// drop directory components and control characters, reject `.`/`..`/empty.
+pub fn sanitize(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let cleaned: String = base.chars().filter(|c| !c.is_control()).collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        None
+    } else {
+        Some(cleaned.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extended_filename_wins_over_plain() {
+        let v = "attachment; filename=\"fallback.mp4\"; filename*=UTF-8''v%C3%ADdeo%20final.mp4";
+        assert_eq!(filename(v).as_deref(), Some("vídeo final.mp4"));
+    }
+
+    #[test]
+    fn quoted_filename_with_semicolon_and_escapes() {
+        let v = r#"attachment; filename="a;b \"c\".png""#;
+        assert_eq!(filename(v).as_deref(), Some(r#"a;b "c".png"#));
+    }
+
+    #[test]
+    fn latin1_and_unquoted_and_traversal() {
+        assert_eq!(filename("inline; filename*=ISO-8859-1'en'caf%E9.jpg").as_deref(), Some("café.jpg"));
+        assert_eq!(filename("attachment; FILENAME=clip.webm").as_deref(), Some("clip.webm"));
+        assert_eq!(filename("attachment; filename=\"../../etc/passwd\"").as_deref(), Some("passwd"));
+        assert_eq!(filename("attachment; filename=\"..\"").as_deref(), None);
+        assert_eq!(filename("inline").as_deref(), None);
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::Read;
+
+// ─── Content-Encoding ───────────────────────────────────────────────────────
+//
+// Chromium caches response bodies exactly as they came off the wire, so a
+// response sent with `Content-Encoding: gzip` (or br, zstd, deflate) is stored
+// compressed and copies out as binary garbage. The encoding is read from the
+// entry's stream 0 — the file's own for `_0`, the sibling `_0` for `_1`/`_s`
+// — and the body decoded before it is written. A body that fails to decode
+// (typically a range download that doesn't start at byte 0) is kept as
+// stored; the raw bytes are still worth having.
+
+/// Decoded bodies larger than this are treated as decompression bombs.
+const MAX_DECODED_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+fn read_limited(mut reader: impl Read, coding: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_DECODED_BYTES + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("{} decoding failed: {}", coding, e))?;
+    if out.len() as u64 > MAX_DECODED_BYTES {
+        return Err(format!("{} body decodes to more than {} bytes", coding, MAX_DECODED_BYTES));
+    }
+    Ok(out)
+}
+
+fn decode_one(body: &[u8], coding: &str) -> Result<Vec<u8>, String> {
+    match coding {
+        "gzip" | "x-gzip" => read_limited(flate2::read::MultiGzDecoder::new(body), coding),
+        // "deflate" is meant to be zlib-wrapped, but some servers send raw deflate.
+        "deflate" => read_limited(flate2::read::ZlibDecoder::new(body), coding)
+            .or_else(|_| read_limited(flate2::read::DeflateDecoder::new(body), coding)),
+        "br" => read_limited(brotli::Decompressor::new(body, 64 * 1024), coding),
+        "zstd" => read_limited(zstd::stream::read::Decoder::new(body).map_err(|e| e.to_string())?, coding),
+        "identity" | "" => Ok(body.to_vec()),
+        other => Err(format!("Unsupported Content-Encoding \"{}\"", other)),
+    }
+}
+
+/// Undo a Content-Encoding header value. Codings listed as "gzip, br" were
+/// applied in that order, so they are removed last to first.
+pub fn decode(body: &[u8], encoding: &str) -> Result<Vec<u8>, String> {
+    let codings: Vec<String> = encoding.split(',').map(|c| c.trim().to_ascii_lowercase()).collect();
+    let mut out = body.to_vec();
+    for coding in codings.iter().rev() {
+        out = decode_one(&out, coding)?;
+    }
+    Ok(out)
+}
+
+/// The Content-Encoding of the entry `path` belongs to; `data` is the file's
+/// bytes. None for identity, plain files and entries without headers.
+pub fn encoding_of(path: &str, data: &[u8]) -> Option<String> {
+    let stream0 = if crate::is_simple_cache_stream2(path) || crate::is_simple_cache_sparse(path) {
+        let headers_file = crate::entry::sibling_file(path, "0")?;
+        let bytes = crate::read_with_lock_retry(&headers_file).ok()?;
+        crate::extract_simple_cache_headers(&bytes)?
+    } else {
+        crate::extract_simple_cache_headers(data)?
+    };
+    let info = crate::http_info::parse(&stream0)?;
+    let encoding = info.header("content-encoding")?.trim().to_ascii_lowercase();
+    (!encoding.is_empty() && encoding != "identity").then_some(encoding)
+}
+
+/// Decode `body` (read from `path`) according to `encoding`, as found by
+/// `encoding_of`. Returns the body unchanged when there is no encoding or it
+/// can't be undone.
+pub fn decode_body(path: &str, encoding: Option<&str>, body: Vec<u8>) -> Vec<u8> {
+    let Some(encoding) = encoding else {
+        return body;
+    };
+    match decode(&body, encoding) {
+        Ok(decoded) => {
+            println!("[encoding] {}: {} → {} bytes ({})", path, body.len(), decoded.len(), encoding);
+            decoded
+        }
+        Err(e) => {
+            eprintln!("[encoding] {}: keeping {} body as stored: {}", path, encoding, e);
+            body
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn decodes_each_coding_and_stacks() {
+        let text = b"<html>cached page</html>".repeat(50);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&text).unwrap();
+        let gz = gz.finish().unwrap();
+        let mut br = Vec::new();
+        brotli::CompressorWriter::new(&mut br, 4096, 5, 22).write_all(&gz).unwrap();
+        let zst = zstd::encode_all(&text[..], 3).unwrap();
+
+        assert_eq!(decode(&gz, "gzip").unwrap(), text);
+        assert_eq!(decode(&zst, "ZSTD").unwrap(), text);
+        assert_eq!(decode(&br, "gzip, br").unwrap(), text);
+        assert!(decode(&text, "gzip").is_err());
+        assert!(decode(&text, "compress").is_err());
+
+        let dir = std::env::temp_dir().join(format!("cachephoenix-encoding-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir).unwrap();
+        let url = "https://discord.com/assets/app.js";
+        let headers = ["HTTP/1.1 200", "content-type: text/javascript", "content-encoding: gzip"];
+        let entry = cache.add_entry(url, &headers, &gz).unwrap();
+        let data = std::fs::read(&entry).unwrap();
+        let encoding = encoding_of(&entry, &data);
+        let decoded = decode_body(&entry, encoding.as_deref(), crate::strip_simple_cache_wrapper(data, &entry));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn decodes_a_body_split_across_files_once() {
+        let text = b"<html>chunked page</html>".repeat(200);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&text).unwrap();
+        let gz = gz.finish().unwrap();
+        let (head, tail) = gz.split_at(gz.len() / 2);
+
+        let dir = std::env::temp_dir().join(format!("cachephoenix-encoding-split-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir).unwrap();
+        let headers = ["HTTP/1.1 200", "content-encoding: gzip"];
+        let parts = vec![
+            cache.add_entry("https://example.com/page?part=1", &headers, head).unwrap(),
+            cache.add_entry("https://example.com/page?part=2", &headers, tail).unwrap(),
+        ];
+        let output = dir.join("joined.html").to_string_lossy().to_string();
+        let decoded = crate::join_bodies(parts.clone(), &output, true).map(|_| std::fs::read(&output));
+        let stored = crate::join_bodies(parts, &output, false).map(|_| std::fs::read(&output));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(decoded.unwrap().unwrap(), text);
+        assert_eq!(stored.unwrap().unwrap(), gz);
+    }
+}
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock, TryLockError};
+
+use crate::events::Coalescer;
+
+// ─── Panic reporting ────────────────────────────────────────────────────────
+//
+// A panic inside a command kills the worker thread and the frontend's
+// `invoke` promise never settles: the UI just spins. The hook installed here
+// writes a crash report (message, location, backtrace and the job the thread
+// was running) to the diagnostics folder and emits `backend-crashed`, so the
+// UI can say what failed and where the report is (batched through a
+// `Coalescer`, so a panicking loop can't flood it). The default hook still runs
+// afterwards. Long-running commands name their job with `crash::job(...)`;
+// the name is per thread, so concurrent jobs don't mislabel each other.
+
+/// Event name carrying a `Vec<CrashReport>` payload.
+pub const CRASH_EVENT: &str = "backend-crashed";
+
+static EVENTS: OnceLock<Mutex<Coalescer<CrashReport>>> = OnceLock::new();
+
+thread_local! {
+    static JOB: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    pub message: String,
+    /// "file:line" of the panic
+    pub location: Option<String>,
+    /// The job the panicking thread was running, if it named one
+    pub job: Option<String>,
+    /// Where the full report (with backtrace) was written
+    pub report_path: Option<String>,
+}
+
+/// Named jobs in flight on any thread; nested jobs count once each.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Restores the thread's previous job name when dropped.
+pub struct JobGuard(Option<String>);
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        JOB.with(|job| *job.borrow_mut() = previous);
+        RUNNING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Name what this thread is doing until the returned guard is dropped.
+pub fn job(name: impl Into<String>) -> JobGuard {
+    RUNNING.fetch_add(1, Ordering::Relaxed);
+    JobGuard(JOB.with(|job| job.borrow_mut().replace(name.into())))
+}
+
+/// Whether any thread is inside a named job right now.
+pub fn jobs_running() -> bool {
+    RUNNING.load(Ordering::Relaxed) > 0
+}
+
+/// The job this thread is running, if it named one.
+pub fn current_job() -> Option<String> {
+    JOB.with(|job| job.borrow().clone())
+}
+
+/// Crash reports go next to the other temp workspace, so "forget
+/// everything" removes them too.
+pub(crate) fn diagnostics_dir() -> PathBuf {
+    std::env::temp_dir().join("cachephoenix-diagnostics")
+}
+
+fn write_report(report: &CrashReport, backtrace: &str) -> Result<PathBuf, String> {
+    let dir = diagnostics_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create dir {}: {}", dir.display(), e))?;
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = dir.join(format!("crash-{}.txt", stamp));
+    let text = format!(
+        "CachePhoenix {} crash report\nos: {} {}\nthread: {}\njob: {}\nlocation: {}\nmessage: {}\n\nbacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::thread::current().name().unwrap_or("unnamed"),
+        report.job.as_deref().unwrap_or("none"),
+        report.location.as_deref().unwrap_or("unknown"),
+        report.message,
+        backtrace
+    );
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Run `f` on the crash event coalescer, unless another thread is using it:
+/// the panic hook must never block.
+fn with_events(f: impl FnOnce(&mut Coalescer<CrashReport>)) {
+    let Some(events) = EVENTS.get() else {
+        return;
+    };
+    let mut events = match events.try_lock() {
+        Ok(events) => events,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+    f(&mut events);
+}
+
+/// Send crash reports the rate limit held back. The watchdog's loop calls
+/// this once a second.
+pub fn tick_events() {
+    with_events(Coalescer::tick);
+}
+
+/// Install the panic hook. Call once, from `setup`.
+pub fn install(app: tauri::AppHandle) {
+    let _ = EVENTS.set(Mutex::new(Coalescer::for_app(app, CRASH_EVENT)));
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        // The hook may run while the thread's JOB is mid-update; never panic here.
+        let job = JOB.try_with(|job| job.try_borrow().ok().and_then(|j| j.clone())).ok().flatten();
+        let mut report = CrashReport {
+            message,
+            location: info.location().map(|l| format!("{}:{}", l.file(), l.line())),
+            job,
+            report_path: None,
+        };
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        match write_report(&report, &backtrace) {
+            Ok(path) => report.report_path = Some(path.to_string_lossy().to_string()),
+            Err(e) => eprintln!("[crash] {}", e),
+        }
+        eprintln!(
+            "[crash] Backend panicked during {}: {} (report: {})",
+            report.job.as_deref().unwrap_or("an unnamed job"),
+            report.message,
+            report.report_path.as_deref().unwrap_or("not written")
+        );
+        // A panic while the coalescer is busy loses only this event.
+        with_events(|events| events.push(report));
+        default_hook(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_names_nest_per_thread() {
+        let current = || JOB.with(|job| job.borrow().clone());
+        {
+            let _outer = job("recovering clip.mp4");
+            {
+                let _inner = job("reading f_000123");
+                assert_eq!(current().as_deref(), Some("reading f_000123"));
+                std::thread::spawn(move || assert_eq!(JOB.with(|job| job.borrow().clone()), None))
+                    .join()
+                    .unwrap();
+            }
+            assert_eq!(current().as_deref(), Some("recovering clip.mp4"));
+        }
+        assert_eq!(current(), None);
+    }
+}
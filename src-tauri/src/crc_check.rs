@@ -0,0 +1,350 @@
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::{
+    FLAG_HAS_CRC32, FLAG_HAS_KEY_SHA256, SIMPLE_CACHE_EOF_MAGIC, SIMPLE_CACHE_EOF_SIZE, SIMPLE_CACHE_HEADER_SIZE,
+    SIMPLE_CACHE_MAGIC, SPARSE_RANGE_HEADER_SIZE, SPARSE_RANGE_MAGIC,
+};
+
+// ─── Stream CRC32 verification ──────────────────────────────────────────────
+//
+// Simple Cache stores a zlib CRC-32 of each stream in its EOF record (when
+// FLAG_HAS_CRC32 is set) and of each sparse range in its range header. A
+// mismatch means the body was torn or overwritten after Chromium closed it.
+//
+// Checking this reads every byte of every body, so it has to be cheap:
+//   - crc32fast picks the hardware path at runtime (PCLMULQDQ on x86_64,
+//     the CRC instructions on aarch64) and falls back to a table otherwise;
+//   - files are spread over a fixed pool of at most MAX_WORKERS threads, and
+//     each worker reads its file's layout with small reads and then streams
+//     the checksummed parts through the hasher a block at a time, so memory
+//     stays at one block per worker however large a `_1` body is.
+
+const MAX_WORKERS: usize = 8;
+/// Bytes hashed per read.
+const READ_BLOCK: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrcCheck {
+    pub path: String,
+    /// "ok", "mismatch", "unchecked" (no stored CRCs), "not_simple_cache"
+    /// or "unreadable"
+    pub status: &'static str,
+    pub streams_checked: usize,
+    /// Streams/ranges whose CRC didn't match, e.g. "stream 1", "range @ 1048576"
+    pub failed: Vec<String>,
+    pub bytes_checked: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrcReport {
+    /// The CPU offers the instructions crc32fast accelerates with
+    pub hardware: bool,
+    pub files: Vec<CrcCheck>,
+    pub bytes_checked: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Whether this CPU has the instructions crc32fast's fast path uses.
+pub fn hardware_crc() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        std::is_x86_feature_detected!("pclmulqdq") && std::is_x86_feature_detected!("sse4.1")
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        std::arch::is_aarch64_feature_detected!("crc")
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        false
+    }
+}
+
+pub(crate) fn workers() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4).min(MAX_WORKERS)
+}
+
+/// Up to `len` bytes at `at` (fewer at the end of the file).
+pub(crate) fn read_up_to<R: Read + Seek>(reader: &mut R, at: u64, len: usize) -> std::io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(at))?;
+    let mut buf = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_le(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn is_eof_record(eof: &[u8]) -> bool {
+    eof.len() == SIMPLE_CACHE_EOF_SIZE && u64_le(eof, 0) == Some(SIMPLE_CACHE_EOF_MAGIC)
+}
+
+/// The stored CRC of an EOF record, if it has one.
+fn eof_crc(eof: &[u8]) -> Option<u32> {
+    let flags = u32_le(eof, 8)?;
+    (is_eof_record(eof) && flags & FLAG_HAS_CRC32 != 0).then(|| u32_le(eof, 12)).flatten()
+}
+
+/// The Simple Cache header and key at the start of a file: enough for
+/// `check_key`, however long the key turns out to be.
+pub(crate) fn read_head<R: Read + Seek>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    read_up_to(reader, 0, SIMPLE_CACHE_HEADER_SIZE + crate::key_hash::MAX_KEY_LENGTH + 1)
+}
+
+/// Where EOF0 of a `_0` file of `len` bytes says EOF1 and stream 0 are, as
+/// (EOF1 offset, stream 0 start, stream 0 end), with EOF1 really there and
+/// after `body_start`. None when EOF0 is missing or disagrees with the file.
+pub(crate) fn stream0_layout<R: Read + Seek>(
+    reader: &mut R,
+    len: u64,
+    body_start: u64,
+) -> std::io::Result<Option<(u64, u64, u64)>> {
+    let eof_size = SIMPLE_CACHE_EOF_SIZE as u64;
+    let Some(eof0_at) = len.checked_sub(eof_size) else {
+        return Ok(None);
+    };
+    let eof0 = read_up_to(reader, eof0_at, SIMPLE_CACHE_EOF_SIZE)?;
+    let (Some(flags), Some(stream0_size)) = (u32_le(&eof0, 8), u32_le(&eof0, 16)) else {
+        return Ok(None);
+    };
+    // With FLAG_HAS_KEY_SHA256, 32 bytes of SHA-256 sit just before EOF0.
+    let sha = if flags & FLAG_HAS_KEY_SHA256 != 0 { 32 } else { 0 };
+    let layout = eof0_at
+        .checked_sub(sha)
+        .and_then(|end| Some((end.checked_sub(stream0_size as u64)?, end)))
+        .and_then(|(start, end)| Some((start.checked_sub(eof_size)?, start, end)))
+        .filter(|&(eof1_at, _, _)| is_eof_record(&eof0) && eof1_at >= body_start);
+    let Some((eof1_at, _, _)) = layout else {
+        return Ok(None);
+    };
+    Ok(is_eof_record(&read_up_to(reader, eof1_at, SIMPLE_CACHE_EOF_SIZE)?).then_some(layout).flatten())
+}
+
+/// Offset of the first EOF magic at or after `from`, searched a block at a time.
+fn find_eof_magic<R: Read + Seek>(reader: &mut R, from: u64, len: u64) -> std::io::Result<Option<u64>> {
+    let magic = SIMPLE_CACHE_EOF_MAGIC.to_le_bytes();
+    let mut pos = from;
+    while pos < len {
+        let block = read_up_to(reader, pos, READ_BLOCK)?;
+        if let Some(i) = block.windows(magic.len()).position(|w| w == magic) {
+            return Ok(Some(pos + i as u64));
+        }
+        if block.len() < READ_BLOCK {
+            break;
+        }
+        // A magic across the block boundary is found in the next block.
+        pos += (READ_BLOCK - magic.len() + 1) as u64;
+    }
+    Ok(None)
+}
+
+/// A checksummed stream or range: label, start, end and stored CRC.
+type Part = (String, u64, u64, u32);
+
+/// Every checksummed stream or range of the Simple Cache file in `reader`
+/// (`len` bytes). None if it isn't one.
+fn checksummed_parts<R: Read + Seek>(reader: &mut R, len: u64, path: &str) -> std::io::Result<Option<Vec<Part>>> {
+    let head = read_head(reader)?;
+    if u64_le(&head, 0) != Some(SIMPLE_CACHE_MAGIC) {
+        return Ok(None);
+    }
+    let Some(key_length) = crate::key_hash::check_key(&head).0 else {
+        return Ok(None);
+    };
+    let body_start = (SIMPLE_CACHE_HEADER_SIZE + key_length) as u64;
+    let eof_size = SIMPLE_CACHE_EOF_SIZE as u64;
+    let mut parts = Vec::new();
+    if crate::is_simple_cache_sparse(path) {
+        let mut pos = body_start;
+        while pos + SPARSE_RANGE_HEADER_SIZE as u64 <= len {
+            let hdr = read_up_to(reader, pos, SPARSE_RANGE_HEADER_SIZE)?;
+            if u64_le(&hdr, 0) != Some(SPARSE_RANGE_MAGIC) {
+                break;
+            }
+            let (Some(offset), Some(length), Some(stored)) = (u64_le(&hdr, 8), u64_le(&hdr, 16), u32_le(&hdr, 24))
+            else {
+                break;
+            };
+            let start = pos + SPARSE_RANGE_HEADER_SIZE as u64;
+            // A range cut short by the end of the file can't be checked.
+            let Some(end) = start.checked_add(length).filter(|&end| end <= len) else {
+                break;
+            };
+            parts.push((format!("range @ {}", offset), start, end, stored));
+            pos = end;
+        }
+    } else if crate::is_simple_cache_stream2(path) {
+        if len < body_start + eof_size {
+            return Ok(None);
+        }
+        let eof = read_up_to(reader, len - eof_size, SIMPLE_CACHE_EOF_SIZE)?;
+        if let Some(stored) = eof_crc(&eof) {
+            parts.push(("stream 2".to_string(), body_start, len - eof_size, stored));
+        }
+    } else {
+        if len < body_start + eof_size {
+            return Ok(None);
+        }
+        match stream0_layout(reader, len, body_start)? {
+            Some((eof1_at, stream0_start, stream0_end)) => {
+                let eof1 = read_up_to(reader, eof1_at, SIMPLE_CACHE_EOF_SIZE)?;
+                if let Some(stored) = eof_crc(&eof1) {
+                    parts.push(("stream 1".to_string(), body_start, eof1_at, stored));
+                }
+                let eof0 = read_up_to(reader, len - eof_size, SIMPLE_CACHE_EOF_SIZE)?;
+                if let (true, Some(stored)) = (stream0_end > stream0_start, eof_crc(&eof0)) {
+                    parts.push(("stream 0".to_string(), stream0_start, stream0_end, stored));
+                }
+            }
+            // EOF0 is damaged: stream 1 still ends at the first EOF record.
+            None => {
+                let Some(eof1_at) = find_eof_magic(reader, body_start, len)? else {
+                    return Ok(None);
+                };
+                if let Some(stored) = eof_crc(&read_up_to(reader, eof1_at, SIMPLE_CACHE_EOF_SIZE)?) {
+                    parts.push(("stream 1".to_string(), body_start, eof1_at, stored));
+                }
+            }
+        }
+    }
+    Ok(Some(parts))
+}
+
+fn unchecked(path: &str, status: &'static str) -> CrcCheck {
+    CrcCheck { path: path.to_string(), status, streams_checked: 0, failed: Vec::new(), bytes_checked: 0 }
+}
+
+/// Verify the stored CRCs of the file in `reader` (`len` bytes), one part
+/// after the other.
+pub(crate) fn check_reader<R: Read + Seek>(reader: &mut R, len: u64, path: &str) -> std::io::Result<CrcCheck> {
+    let mut check = unchecked(path, "not_simple_cache");
+    let Some(parts) = checksummed_parts(reader, len, path)? else {
+        return Ok(check);
+    };
+    let mut buf = vec![0u8; READ_BLOCK.min(len as usize)];
+    for (label, start, end, stored) in parts {
+        reader.seek(SeekFrom::Start(start))?;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut left = end - start;
+        while left > 0 {
+            let n = left.min(buf.len() as u64) as usize;
+            reader.read_exact(&mut buf[..n])?;
+            hasher.update(&buf[..n]);
+            left -= n as u64;
+        }
+        check.streams_checked += 1;
+        check.bytes_checked += end - start;
+        if hasher.finalize() != stored {
+            check.failed.push(label);
+        }
+    }
+    check.status = if check.streams_checked == 0 {
+        "unchecked"
+    } else if check.failed.is_empty() {
+        "ok"
+    } else {
+        "mismatch"
+    };
+    Ok(check)
+}
+
+/// Verify the stored CRCs of one file's bytes.
+pub fn check_data(data: &[u8], path: &str) -> CrcCheck {
+    check_reader(&mut std::io::Cursor::new(data), data.len() as u64, path)
+        .unwrap_or_else(|_| unchecked(path, "unreadable"))
+}
+
+fn check_file(path: &str) -> CrcCheck {
+    let _permit = crate::io_limit::acquire_for(path);
+    let result = crate::with_lock_retry(path, || {
+        let mut file = std::fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        check_reader(&mut file, len, path)
+    });
+    result.unwrap_or_else(|e| {
+        eprintln!("[crc] {}", crate::format_read_error(path, &e));
+        unchecked(path, "unreadable")
+    })
+}
+
+/// Verify the stream and sparse-range CRC32s of Simple Cache files. Results
+/// are in input order.
+#[tauri::command(async)]
+pub fn verify_crc32(paths: Vec<String>) -> CrcReport {
+    let _job = crate::crash::job(format!("verifying CRC32 of {} files", paths.len()));
+    let started = std::time::Instant::now();
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<CrcCheck>>> = paths.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..workers().min(paths.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+                *results[i].lock().unwrap_or_else(|e| e.into_inner()) = Some(check_file(path));
+            });
+        }
+    });
+    let files: Vec<CrcCheck> = results
+        .into_iter()
+        .filter_map(|slot| slot.into_inner().unwrap_or_else(|e| e.into_inner()))
+        .collect();
+    let report = CrcReport {
+        hardware: hardware_crc(),
+        bytes_checked: files.iter().map(|f| f.bytes_checked).sum(),
+        files,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    };
+    println!(
+        "[crc] {} files, {} bytes in {} ms ({}), {} mismatched",
+        report.files.len(),
+        report.bytes_checked,
+        report.elapsed_ms,
+        if report.hardware { "hardware CRC" } else { "table CRC" },
+        report.files.iter().filter(|f| f.status == "mismatch").count()
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_streams_and_ranges() {
+        let key = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let mut entry = crate::fixture::simple_entry(key, &["HTTP/1.1 200 OK"], b"body bytes");
+        let check = check_data(&entry, "0123456789abcdef_0");
+        assert_eq!((check.status, check.streams_checked), ("ok", 2));
+
+        let body_at = SIMPLE_CACHE_HEADER_SIZE + key.len();
+        entry[body_at] ^= 0xff;
+        let check = check_data(&entry, "0123456789abcdef_0");
+        assert_eq!((check.status, check.failed.clone()), ("mismatch", vec!["stream 1".to_string()]));
+
+        let mut sparse = crate::fixture::sparse_entry(key, &[(0, b"first"), (4096, b"second")]);
+        assert_eq!(check_data(&sparse, "0123456789abcdef_s").status, "ok");
+        let last = sparse.len() - 1;
+        sparse[last] ^= 1;
+        assert_eq!(check_data(&sparse, "0123456789abcdef_s").failed, vec!["range @ 4096".to_string()]);
+
+        // A body larger than one read block is streamed from disk.
+        let big: Vec<u8> = (0..READ_BLOCK * 2 + 12345).map(|i| (i * 31 % 251) as u8).collect();
+        let dir = std::env::temp_dir().join(format!("cachephoenix-crc-{}", std::process::id()));
+        let path = dir.join("0123456789abcdef_1");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, crate::fixture::stream2_entry(key, &big)).unwrap();
+        let report = verify_crc32(vec![path.to_string_lossy().to_string()]);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!((report.files[0].status, report.bytes_checked), ("ok", big.len() as u64));
+    }
+}
@@ -0,0 +1,138 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// ─── Custom cache path registry ─────────────────────────────────────────────
+//
+// Folders the user adds by hand are kept in `custom_cache_paths.json` in the
+// app config folder, so they survive restarts and a cleared webview storage.
+// Paths are stored expanded (see `path_display`) and deduplicated; on Windows
+// and macOS that comparison ignores case, like the filesystems do.
+
+const REGISTRY_FILE: &str = "custom_cache_paths.json";
+
+/// Serializes read-modify-write of the registry file.
+static REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Registry {
+    paths: Vec<String>,
+}
+
+/// `name` inside the app config folder.
+pub(crate) fn config_file(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    use tauri::Manager;
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(name))
+        .map_err(|e| format!("Failed to locate config dir: {}", e))
+}
+
+fn registry_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    config_file(app, REGISTRY_FILE)
+}
+
+/// A missing file is the default value; a corrupt one is reported, not
+/// silently replaced.
+pub(crate) fn read_json<T: Default + DeserializeOwned>(file: &Path) -> Result<T, String> {
+    match std::fs::read(file) {
+        Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Failed to parse {}: {}", file.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("Failed to read {}: {}", file.display(), e)),
+    }
+}
+
+/// Write via a temp file + rename so a crash can't leave half a file.
+pub(crate) fn write_json<T: Serialize>(file: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir {}: {}", parent.display(), e))?;
+    }
+    let data = serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize {}: {}", file.display(), e))?;
+    let tmp = file.with_extension("json.tmp");
+    std::fs::write(&tmp, data).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, file).map_err(|e| format!("Failed to replace {}: {}", file.display(), e))
+}
+
+fn load(file: &Path) -> Result<Registry, String> {
+    read_json(file)
+}
+
+fn save(file: &Path, registry: &Registry) -> Result<(), String> {
+    write_json(file, registry)
+}
+
+pub(crate) fn normalize(path: &str) -> String {
+    let expanded = crate::path_display::expand_path(path);
+    let trimmed = expanded.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() { expanded } else { trimmed.to_string() }
+}
+
+pub(crate) fn same_path(a: &str, b: &str) -> bool {
+    if cfg!(target_os = "linux") { a == b } else { a.eq_ignore_ascii_case(b) }
+}
+
+fn add(file: &Path, path: &str) -> Result<Vec<String>, String> {
+    let path = normalize(path);
+    if !Path::new(&path).is_absolute() {
+        return Err(format!("Path must be absolute: {}", path));
+    }
+    let _lock = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut registry = load(file)?;
+    if !registry.paths.iter().any(|p| same_path(p, &path)) {
+        registry.paths.push(path);
+        save(file, &registry)?;
+    }
+    Ok(registry.paths)
+}
+
+fn remove(file: &Path, path: &str) -> Result<Vec<String>, String> {
+    let path = normalize(path);
+    let _lock = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut registry = load(file)?;
+    let before = registry.paths.len();
+    registry.paths.retain(|p| !same_path(p, &path));
+    if registry.paths.len() != before {
+        save(file, &registry)?;
+    }
+    Ok(registry.paths)
+}
+
+/// Remember a user-added cache folder. Returns the updated list.
+#[tauri::command]
+pub fn add_custom_cache_path(app: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+    add(&registry_file(&app)?, &path)
+}
+
+/// Forget a user-added cache folder. Returns the updated list.
+#[tauri::command]
+pub fn remove_custom_cache_path(app: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+    remove(&registry_file(&app)?, &path)
+}
+
+/// All user-added cache folders, in the order they were added.
+#[tauri::command]
+pub fn list_custom_cache_paths(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let _lock = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    load(&registry_file(&app)?).map(|r| r.paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_added_paths() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-registry-{}", std::process::id()));
+        let file = dir.join(REGISTRY_FILE);
+        let cache = dir.join("portable-discord").join("Cache");
+        let cache = cache.to_string_lossy().to_string();
+
+        assert_eq!(add(&file, &format!("{}/", cache)).unwrap(), vec![cache.clone()]);
+        assert_eq!(add(&file, &cache).unwrap().len(), 1);
+        assert!(add(&file, "relative/Cache").is_err());
+        assert_eq!(load(&file).unwrap().paths, vec![cache.clone()]);
+        assert!(remove(&file, &cache).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fmp4::Fmp4Reconstruction;
+use crate::reconstruction::{GapPolicy, ReconstructionResult};
+
+// ─── DASH segment reassembly ────────────────────────────────────────────────
+//
+// DASH players fetch each representation (one resolution, or the audio) of a
+// video as fragmented MP4 in one of two ways. With segment templates every
+// request is a whole segment — init.mp4, then styp/sidx + moof/mdat files —
+// and the fMP4 reassembly can order them by moof sequence number. With a
+// single indexed file (YouTube's videoplayback?...&range=a-b, or plain Range
+// requests cached as separate entries) each request is an arbitrary byte
+// range of one file, and boxes straddle entries. Those are first laid out at
+// their byte offsets; where a range is missing, the data after it is resumed
+// at the next moof so the fragments can be handed to the same reassembly.
+
+/// Query parameters holding a request's byte range.
+const RANGE_PARAMS: &[&str] = &["range", "bytes"];
+/// Query parameters that change with every request for the same file
+/// (YouTube's request number and buffer state).
+const PER_REQUEST_PARAMS: &[&str] = &["rn", "rbuf"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DashReconstruction {
+    #[serde(flatten)]
+    pub fragmented: Fmp4Reconstruction,
+    /// Entries placed by byte range rather than read as whole segments
+    pub range_entries: usize,
+    /// Holes between byte ranges
+    pub range_gaps: usize,
+}
+
+/// A cache entry and, when the caller knows it, the URL it was fetched from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DashSource {
+    pub path: String,
+    pub url: Option<String>,
+}
+
+/// One cache entry given to the reassembly.
+pub struct DashFile {
+    pub path: String,
+    /// Start of the entry's data in the representation's file, from the URL's
+    /// range parameter or the Content-Range header
+    pub range_start: Option<u64>,
+    /// The URL without its range and per-request parameters: byte ranges
+    /// only line up if they are of the same file
+    pub file_url: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// Start of a `range=a-b` / `bytes=a-b` query parameter.
+fn url_range_start(url: &str) -> Option<u64> {
+    let query = url.split('#').next()?.split_once('?')?.1;
+    query.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        if !RANGE_PARAMS.contains(&name) {
+            return None;
+        }
+        value.split(['-', '%']).next()?.parse().ok()
+    })
+}
+
+/// `url` without the fragment and the query parameters that differ between
+/// ranges of one file.
+fn url_without_range(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or(url);
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let name = pair.split('=').next().unwrap_or(pair);
+            !RANGE_PARAMS.contains(&name) && !PER_REQUEST_PARAMS.contains(&name)
+        })
+        .collect();
+    if kept.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, kept.join("&"))
+    }
+}
+
+/// Whether `data` starts with a DASH media segment (styp, sidx or moof).
+pub fn is_media_segment(data: &[u8]) -> bool {
+    data.len() >= 8
+        && matches!(&data[4..8], b"styp" | b"sidx" | b"moof")
+        && crate::mp4_samples::be_u32(data, 0) >= Some(8)
+}
+
+/// Offset of the first plausible top-level moof in `data`.
+fn next_moof(data: &[u8]) -> Option<usize> {
+    (0..data.len().saturating_sub(16)).find(|&p| {
+        &data[p + 4..p + 8] == b"moof"
+            && crate::mp4_samples::be_u32(data, p).is_some_and(|size| size >= 16 && p + size as usize <= data.len())
+            && &data[p + 12..p + 16] == b"mfhd"
+    })
+}
+
+/// Length of the whole top-level boxes at the start of `data`.
+fn complete_boxes(data: &[u8]) -> usize {
+    crate::fmp4::top_level_boxes(data).0.iter().map(|(_, whole)| whole.len()).sum()
+}
+
+/// Lay byte-range entries out in file order. Overlaps are trimmed; after a
+/// missing range the partial box before it is dropped and the data resumes at
+/// the next moof. Returns the data and the number of holes.
+fn lay_out_ranges(files: &[DashFile], policy: GapPolicy) -> (Vec<u8>, usize) {
+    let mut ordered: Vec<(u64, &DashFile)> = files.iter().filter_map(|f| Some((f.range_start?, f))).collect();
+    ordered.sort_by_key(|(start, file)| (*start, std::cmp::Reverse(file.data.len())));
+    let mut out = Vec::with_capacity(ordered.iter().map(|(_, f)| f.data.len()).sum());
+    let mut covered = 0u64;
+    let mut gaps = 0;
+    for (start, file) in ordered {
+        let Some(end) = start.checked_add(file.data.len() as u64) else {
+            println!("[dash] {} claims a range past the largest offset; skipped", file.path);
+            continue;
+        };
+        if end <= covered {
+            continue;
+        }
+        if start <= covered {
+            out.extend_from_slice(&file.data[(covered - start) as usize..]);
+        } else {
+            gaps += 1;
+            println!("[dash] {} bytes missing before offset {} ({:?})", start - covered, start, policy);
+            if policy == GapPolicy::TruncateAtGap {
+                break;
+            }
+            let Some(resume) = next_moof(&file.data) else {
+                println!("[dash] {} has no fragment to resume at; skipped", file.path);
+                continue;
+            };
+            out.truncate(complete_boxes(&out));
+            out.extend_from_slice(&file.data[resume..]);
+        }
+        covered = end;
+    }
+    (out, gaps)
+}
+
+/// Whether the entries are byte ranges of one file: each has a range, and
+/// all share a URL once the range is taken out. Different URLs that each
+/// start at `bytes=0-` are separate segments, not one range laid over another.
+fn ranges_of_one_file(files: &[DashFile]) -> bool {
+    let Some(first) = files.first().and_then(|f| f.file_url.as_deref()) else {
+        return false;
+    };
+    files.iter().all(|f| f.range_start.is_some() && f.file_url.as_deref() == Some(first))
+}
+
+/// Join the DASH entries of one representation into one MP4. Byte ranges of
+/// one file are laid out by offset first; otherwise each entry is a whole
+/// init or media segment.
+pub fn reassemble(files: Vec<DashFile>, policy: GapPolicy) -> Result<(Vec<u8>, DashReconstruction), String> {
+    if !ranges_of_one_file(&files) {
+        let segments: Vec<(String, Vec<u8>)> = files.into_iter().map(|f| (f.path, f.data)).collect();
+        let (out, fragmented) = crate::fmp4::reassemble(&segments, policy)?;
+        return Ok((out, DashReconstruction { fragmented, range_entries: 0, range_gaps: 0 }));
+    }
+
+    let (joined, range_gaps) = lay_out_ranges(&files, policy);
+    if crate::fmp4::has_init_segment(&joined) {
+        let (out, mut fragmented) = crate::fmp4::reassemble(&[("byte ranges".to_string(), joined)], policy)?;
+        fragmented.result.truncated |= policy == GapPolicy::TruncateAtGap && range_gaps > 0;
+        return Ok((out, DashReconstruction { fragmented, range_entries: files.len(), range_gaps }));
+    }
+    // A progressive (non-fragmented) file fetched in ranges: the layout is the file.
+    if range_gaps > 0 && policy != GapPolicy::TruncateAtGap {
+        return Err("Byte ranges of a non-fragmented MP4 are missing; use truncation to keep the prefix".to_string());
+    }
+    let mut result = ReconstructionResult::new(policy);
+    result.truncated = range_gaps > 0;
+    result.finish(&joined);
+    let fragmented = Fmp4Reconstruction {
+        result,
+        fragments: 0,
+        missing_fragments: 0,
+        duplicate_fragments: 0,
+        foreign_fragments: 0,
+        cut_off_fragments: 0,
+        absolute_offsets: 0,
+    };
+    Ok((joined, DashReconstruction { fragmented, range_entries: files.len(), range_gaps }))
+}
+
+/// Reassemble one DASH representation from its init segment and media
+/// segments (or byte-range entries), in any order. URLs not given are read
+/// from Simple Cache keys; a byte range in the URL or the entry's
+/// Content-Range header places the entry in the file. Requires a `consent`
+/// token covering `output`.
+#[tauri::command(async)]
+pub fn reconstruct_dash(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    sources: Vec<DashSource>,
+    output: String,
+    gap_policy: Option<GapPolicy>,
+    consent: String,
+) -> Result<DashReconstruction, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("reconstructing {}", output));
+    let files = sources
+        .into_iter()
+        .map(|source| {
+            let data = crate::read_cache_body(&source.path)?;
+            let url = source.url.or_else(|| {
+                crate::read_simple_cache_key(&source.path).map(|key| crate::cache_key::effective_url(&key).to_string())
+            });
+            let range_start = url
+                .as_deref()
+                .and_then(url_range_start)
+                .or_else(|| crate::reconstruction::content_range_start(&source.path));
+            let file_url = url.as_deref().map(url_without_range);
+            Ok(DashFile { path: source.path, range_start, file_url, data })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let entries = files.len();
+    let (out, report) = reassemble(files, gap_policy.unwrap_or_default())?;
+    crate::output_file::write_output(&output, &out)?;
+    println!(
+        "[dash] Wrote {} fragments from {} entries ({} by byte range, {} range gaps) to {}",
+        report.fragmented.fragments,
+        entries,
+        report.range_entries,
+        report.range_gaps,
+        output
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{boxed, full};
+
+    fn init() -> Vec<u8> {
+        let trak = boxed(b"trak", &boxed(b"tkhd", &full(&[0, 0, 1, 0, 0])));
+        let moov = [boxed(b"mvhd", &[0; 100]), trak, boxed(b"mvex", &boxed(b"trex", &full(&[1, 1, 0, 0, 0])))];
+        [boxed(b"ftyp", b"dash\0\0\0\0"), boxed(b"moov", &moov.concat())].concat()
+    }
+
+    fn segment(sequence: u32) -> Vec<u8> {
+        let tfhd = [0x02_0000u32.to_be_bytes(), 1u32.to_be_bytes()].concat();
+        let traf = boxed(b"traf", &boxed(b"tfhd", &tfhd));
+        let moof = boxed(b"moof", &[boxed(b"mfhd", &full(&[sequence])), traf].concat());
+        [boxed(b"styp", b"msdh\0\0\0\0"), moof, boxed(b"mdat", &[sequence as u8; 300])].concat()
+    }
+
+    fn file(range_start: Option<u64>, data: &[u8]) -> DashFile {
+        let file_url = Some("https://cdn.example/v.mp4".to_string());
+        DashFile { path: format!("{:?}", range_start), range_start, file_url, data: data.to_vec() }
+    }
+
+    #[test]
+    fn reads_range_parameters() {
+        assert_eq!(url_range_start("https://r1.example/videoplayback?itag=137&range=4096-8191&rn=3"), Some(4096));
+        assert_eq!(url_range_start("https://cdn.example/v.mp4?bytes=0-99"), Some(0));
+        assert_eq!(url_range_start("https://cdn.example/seg-1.m4s"), None);
+        assert_eq!(
+            url_without_range("https://r1.example/videoplayback?itag=137&range=4096-8191&rn=3#t"),
+            "https://r1.example/videoplayback?itag=137"
+        );
+        assert_eq!(url_without_range("https://cdn.example/v.mp4?bytes=0-99"), "https://cdn.example/v.mp4");
+        assert!(is_media_segment(&segment(1)));
+        assert!(!is_media_segment(&init()));
+    }
+
+    #[test]
+    fn joins_whole_segments_in_sequence_order() {
+        let files = vec![file(None, &segment(2)), file(None, &init()), file(None, &segment(1))];
+        let (out, report) = reassemble(files, GapPolicy::default()).unwrap();
+        assert_eq!((report.fragmented.fragments, report.range_entries), (2, 0));
+        assert!(out.starts_with(&init()));
+    }
+
+    #[test]
+    fn keeps_segments_of_different_urls_apart() {
+        // Every segment fetched from its own URL with bytes=0-.
+        let mut files = vec![file(Some(0), &segment(2)), file(Some(0), &init()), file(Some(0), &segment(1))];
+        for (i, f) in files.iter_mut().enumerate() {
+            f.file_url = Some(format!("https://cdn.example/seg-{}.m4s", i));
+        }
+        let (out, report) = reassemble(files, GapPolicy::default()).unwrap();
+        assert_eq!((report.fragmented.fragments, report.range_entries), (2, 0));
+        assert!(out.starts_with(&init()));
+    }
+
+    #[test]
+    fn lays_out_byte_ranges_and_resumes_after_a_hole() {
+        let whole = [init(), segment(1), segment(2), segment(3)].concat();
+        let second = init().len() + segment(1).len();
+        let cut = |a: usize, b: usize| whole[a..b].to_vec();
+        // Overlapping ranges, then a hole covering the end of fragment 2.
+        let files = || vec![
+            file(Some(0), &cut(0, 200)),
+            file(Some(150), &cut(150, second + 40)),
+            file(Some((second + 200) as u64), &cut(second + 200, whole.len())),
+        ];
+        let (out, report) = reassemble(files(), GapPolicy::CloseGaps).unwrap();
+        assert_eq!((report.range_entries, report.range_gaps), (3, 1));
+        assert_eq!((report.fragmented.fragments, report.fragmented.missing_fragments), (2, 1));
+        assert!(out.ends_with(&segment(3)[segment(3).len() - 300..]));
+
+        let (_, report) = reassemble(files(), GapPolicy::TruncateAtGap).unwrap();
+        assert_eq!(report.fragmented.fragments, 1);
+        assert!(report.fragmented.result.truncated);
+    }
+}
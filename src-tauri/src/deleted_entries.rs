@@ -0,0 +1,325 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::{SIMPLE_CACHE_HEADER_SIZE, SPARSE_RANGE_MAGIC};
+
+// ─── Doomed and deleted entries ─────────────────────────────────────────────
+//
+// The files users most want back are the ones the browser just threw away,
+// and deletion leaves more behind than the normal scan looks at:
+//
+//   todelete_file   Simple Cache renames a doomed entry's files to
+//                   `todelete_{random}` before unlinking them; a crash or a
+//                   held handle leaves them there. The rename drops the
+//                   `_0`/`_1`/`_s` suffix, so the layout decides which it was.
+//   unindexed       Simple Cache files whose hash the-real-index no longer
+//                   lists: removed from the index, not yet from disk. Files
+//                   newer than the index are skipped, since the index is only
+//                   written every so often.
+//   orphaned_entry  Blockfile EntryStores no hash bucket reaches any more
+//                   (see `blockfile_index::find_orphaned_entries`); their
+//                   streams stay readable until the blocks are reused.
+
+const DOOMED_PREFIX: &str = "todelete_";
+/// Body bytes sniffed for the MIME type (a few MPEG-TS packets).
+const SNIFF_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedEntry {
+    /// "todelete_file", "unindexed" or "orphaned_entry"
+    pub source: &'static str,
+    /// The cache file; for an orphaned entry, the `data_N` file holding its EntryStore
+    pub path: String,
+    /// Orphaned entries only: the EntryStore address, e.g. "0xA0010003"
+    pub entry_address: Option<String>,
+    pub url: Option<String>,
+    /// Simple Cache files: the stream file it was, "_0", "_1" or "_s"
+    pub stream_file: Option<&'static str>,
+    /// Orphaned entries only: "normal", "evicted" or "doomed"
+    pub state: Option<&'static str>,
+    pub body_size: u64,
+    /// Sniffed from the body, else the stored Content-Type
+    pub mime_type: Option<String>,
+    /// Unix seconds: file modification time, or the EntryStore's creation time
+    pub time: Option<f64>,
+    /// Part of the body's storage now belongs to a live entry, so what is
+    /// read back may be another resource's data
+    pub body_reused: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedEntryScan {
+    pub dir: String,
+    pub entries: Vec<DeletedEntry>,
+    pub errors: Vec<String>,
+}
+
+/// Which stream file a Simple Cache file was, from its layout: range
+/// headers right after the key make it `_s`, an EOF0 record that places
+/// stream 0 makes it `_0`, anything else with a valid header `_1`.
+fn stream_file_of(data: &[u8]) -> Option<&'static str> {
+    let key_length = crate::key_hash::check_key(data).0?;
+    let body_start = SIMPLE_CACHE_HEADER_SIZE + key_length;
+    if data.get(body_start..body_start + 8).is_some_and(|m| m == SPARSE_RANGE_MAGIC.to_le_bytes()) {
+        return Some("_s");
+    }
+    match crate::parse_simple_cache_layout(data) {
+        Some(layout) if layout.stream0_end > 0 => Some("_0"),
+        _ => Some("_1"),
+    }
+}
+
+/// The body a Simple Cache file holds, as stored (not content-decoded).
+fn body_of(data: &[u8], stream_file: &str, path: &str) -> Result<Vec<u8>, String> {
+    let layout = match stream_file {
+        "_s" => return crate::reassemble_sparse_data(data, path),
+        "_0" => crate::parse_simple_cache_layout(data),
+        _ => crate::parse_simple_cache_stream2_layout(data),
+    };
+    let layout = layout.ok_or_else(|| format!("No body found in {}", path))?;
+    Ok(data[layout.stream1_start..layout.stream1_end].to_vec())
+}
+
+fn content_type(headers: &[u8]) -> Option<String> {
+    let info = crate::http_info::parse(headers)?;
+    info.header("content-type").map(crate::mime_category::essence)
+}
+
+fn sniffed(body: &[u8]) -> Option<String> {
+    crate::quick_scan::sniff(&body[..body.len().min(SNIFF_BYTES)]).map(|(mime, _)| mime.to_string())
+}
+
+fn modified_at(path: &Path) -> Option<f64> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    modified.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs_f64())
+}
+
+fn doomed_file(path: &Path) -> Result<Option<DeletedEntry>, String> {
+    let path_str = path.to_string_lossy().to_string();
+    let _permit = crate::io_limit::acquire_for(&path_str);
+    let data = crate::read_with_lock_retry(&path_str).map_err(|e| crate::format_read_error(&path_str, &e))?;
+    let Some(stream_file) = stream_file_of(&data) else {
+        return Ok(None);
+    };
+    let body = body_of(&data, stream_file, &path_str)?;
+    let declared = crate::extract_simple_cache_headers(&data).and_then(|h| content_type(&h));
+    Ok(Some(DeletedEntry {
+        source: "todelete_file",
+        url: crate::read_simple_cache_key(&path_str).map(|k| crate::cache_key::effective_url(&k).to_string()),
+        stream_file: Some(stream_file),
+        state: None,
+        body_size: body.len() as u64,
+        mime_type: sniffed(&body).or(declared),
+        time: modified_at(path),
+        body_reused: false,
+        entry_address: None,
+        path: path_str,
+    }))
+}
+
+/// Simple Cache files whose entry hash the-real-index doesn't list.
+fn unindexed_files(dir: &Path) -> Result<Vec<DeletedEntry>, String> {
+    if !crate::cache::resolve_child(dir, "index-dir").is_dir() {
+        return Ok(Vec::new());
+    }
+    let index = crate::cache::read_simple_index(dir)?;
+    let index_file = crate::cache::resolve_child(&crate::cache::resolve_child(dir, "index-dir"), "the-real-index");
+    let written = modified_at(&index_file).unwrap_or(f64::MAX);
+    let files = crate::cache::list_cache_files(&dir.to_string_lossy())?;
+    Ok(files
+        .into_iter()
+        .filter(|f| f.modified_at <= written)
+        .filter(|f| crate::cache::simple_entry_hash(&f.name).is_some_and(|h| !index.entries.contains_key(&h)))
+        .map(|f| DeletedEntry {
+            source: "unindexed",
+            url: crate::read_simple_cache_key(&f.path).map(|k| crate::cache_key::effective_url(&k).to_string()),
+            stream_file: f.name.get(16..).and_then(|s| ["_0", "_1", "_s"].into_iter().find(|x| *x == s)),
+            state: None,
+            body_size: f.size,
+            mime_type: None,
+            time: Some(f.modified_at),
+            body_reused: false,
+            entry_address: None,
+            path: f.path,
+        })
+        .collect())
+}
+
+fn orphaned_entries(dir: &Path) -> Result<(Vec<DeletedEntry>, Vec<String>), String> {
+    let (orphans, errors) = crate::blockfile_index::find_orphaned_entries(dir)?;
+    let entries = orphans
+        .into_iter()
+        .map(|o| {
+            let address = u32::from_str_radix(o.entry_address.trim_start_matches("0x"), 16).unwrap_or(0);
+            let (mime_type, body_size) = match crate::blockfile_index::read_orphaned_entry(dir, address) {
+                Ok((headers, body)) => {
+                    let declared = headers.get("content-type").map(|t| crate::mime_category::essence(t));
+                    (sniffed(&body).or(declared), body.len() as u64)
+                }
+                Err(_) => (None, 0),
+            };
+            DeletedEntry {
+                source: "orphaned_entry",
+                path: crate::cache::resolve_child(dir, &o.entry_slot.file).to_string_lossy().to_string(),
+                entry_address: Some(o.entry_address),
+                url: (!o.url.is_empty()).then_some(o.url),
+                stream_file: None,
+                state: Some(o.state),
+                body_size,
+                mime_type,
+                time: o.creation_time,
+                body_reused: o.body_reused,
+            }
+        })
+        .collect();
+    Ok((entries, errors))
+}
+
+/// Find the entries in the cache folder `dir` that the browser deleted or is
+/// about to: `todelete_*` files, Simple Cache files missing from the index,
+/// and blockfile EntryStores the index no longer reaches.
+#[tauri::command(async)]
+pub fn scan_deleted_entries(dir: String) -> Result<DeletedEntryScan, String> {
+    let dir = crate::path_display::expand_path(&dir);
+    let _job = crate::crash::job(format!("scanning {} for deleted entries", dir));
+    let scan_dir: PathBuf = crate::cache::classify_dir(Path::new(&dir))
+        .scan_dir
+        .ok_or_else(|| format!("{} is not an HTTP cache", dir))?;
+    let (mut entries, mut errors) = (Vec::new(), Vec::new());
+
+    let listing = std::fs::read_dir(&scan_dir).map_err(|e| format!("Cannot list {}: {}", scan_dir.display(), e))?;
+    let mut doomed: Vec<PathBuf> = listing
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(DOOMED_PREFIX))
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    doomed.sort();
+    for path in doomed {
+        match doomed_file(&path) {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if crate::cache::is_blockfile_dir(&scan_dir) {
+        match orphaned_entries(&scan_dir) {
+            Ok((found, walk_errors)) => {
+                entries.extend(found);
+                errors.extend(walk_errors);
+            }
+            Err(e) => errors.push(e),
+        }
+    } else {
+        match unindexed_files(&scan_dir) {
+            Ok(found) => entries.extend(found),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    let count = |source: &str| entries.iter().filter(|e| e.source == source).count();
+    println!(
+        "[deleted] {}: {} todelete files, {} unindexed, {} orphaned entries",
+        scan_dir.display(),
+        count("todelete_file"),
+        count("unindexed"),
+        count("orphaned_entry")
+    );
+    Ok(DeletedEntryScan { dir: scan_dir.to_string_lossy().to_string(), entries, errors })
+}
+
+/// Write the body of a deleted entry to `dst`. `path` and `entry_address`
+/// are as returned by `scan_deleted_entries`; `decode_content` (default
+/// true) undoes a Content-Encoding when the headers survived. Requires a
+/// `consent` token covering `dst`.
+#[tauri::command(async)]
+pub fn recover_deleted_entry(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    path: String,
+    entry_address: Option<String>,
+    dst: String,
+    decode_content: Option<bool>,
+    consent: String,
+) -> Result<u64, String> {
+    let dst = state.check(&consent, &dst)?;
+    recover_deleted(path, entry_address, dst, decode_content)
+}
+
+fn recover_deleted(
+    path: String,
+    entry_address: Option<String>,
+    dst: String,
+    decode_content: Option<bool>,
+) -> Result<u64, String> {
+    let _job = crate::crash::job(format!("recovering deleted entry {}", path));
+    let _permit = crate::io_limit::acquire_for(&path);
+    let decode = decode_content.unwrap_or(true);
+    let body = match entry_address {
+        Some(address) => {
+            let address = u32::from_str_radix(address.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("Bad entry address: {}", address))?;
+            let dir = Path::new(&path).parent().ok_or_else(|| format!("No cache folder for {}", path))?;
+            let (headers, body) = crate::blockfile_index::read_orphaned_entry(dir, address)?;
+            let encoding = headers
+                .get("content-encoding")
+                .map(|e| e.trim().to_ascii_lowercase())
+                .filter(|e| decode && !e.is_empty() && e != "identity");
+            crate::content_encoding::decode_body(&path, encoding.as_deref(), body)
+        }
+        None => {
+            let data = crate::read_with_lock_retry(&path).map_err(|e| crate::format_read_error(&path, &e))?;
+            let stream_file = stream_file_of(&data).ok_or_else(|| format!("Not a Simple Cache entry: {}", path))?;
+            let encoding = if decode { crate::content_encoding::encoding_of(&path, &data) } else { None };
+            let body = body_of(&data, stream_file, &path)?;
+            crate::content_encoding::decode_body(&path, encoding.as_deref(), body)
+        }
+    };
+    let bytes = crate::output_file::write_output(&dst, &body)?;
+    println!("[deleted] {} -> {} ({} bytes)", path, dst, bytes);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_todelete_files_by_layout_and_unindexed_entries() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-deleted-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir).unwrap();
+        let key = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let body = b"\x00\x00\x00\x18ftypisom rest of the video";
+        let headers = ["HTTP/1.1 200 OK", "Content-Type: video/mp4"];
+        std::fs::write(dir.join("todelete_0a1b2c3d4e5f6071"), crate::fixture::simple_entry(key, &headers, body)).unwrap();
+        std::fs::write(dir.join("todelete_1a1b2c3d4e5f6071"), crate::fixture::stream2_entry(key, body)).unwrap();
+        let sparse = crate::fixture::sparse_entry(key, &[(0, b"first"), (5, b"second")]);
+        std::fs::write(dir.join("todelete_2a1b2c3d4e5f6071"), sparse).unwrap();
+        let indexed = cache.add_entry("https://example.com/kept.png", &headers, b"png").unwrap();
+        let dropped = cache.add_entry("https://example.com/dropped.png", &headers, b"png").unwrap();
+        cache.add_index(&[("https://example.com/kept.png", 13_300_000_000_000_000, 256)]).unwrap();
+
+        let scan = scan_deleted_entries(dir.to_string_lossy().to_string()).unwrap();
+        let out = dir.join("out.bin").to_string_lossy().to_string();
+        let recovered = recover_deleted(scan.entries[0].path.clone(), None, out.clone(), None).unwrap();
+        let written = std::fs::read(&out).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let kinds: Vec<_> = scan.entries.iter().map(|e| (e.source, e.stream_file)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("todelete_file", Some("_0")),
+                ("todelete_file", Some("_1")),
+                ("todelete_file", Some("_s")),
+                ("unindexed", Some("_0"))
+            ]
+        );
+        assert_eq!(scan.entries[0].url.as_deref(), Some(key));
+        assert_eq!(scan.entries[0].mime_type.as_deref(), Some("video/mp4"));
+        assert_eq!(scan.entries[2].body_size, 11);
+        assert_eq!(scan.entries[3].path, dropped);
+        assert_ne!(scan.entries[3].path, indexed);
+        assert_eq!((recovered, written), (body.len() as u64, body.to_vec()));
+    }
+}
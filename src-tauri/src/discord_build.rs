@@ -0,0 +1,179 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// ─── Discord client build detection ─────────────────────────────────────────
+//
+// Cache layout quirks differ between Discord builds (Stable, PTB, Canary and
+// their Electron versions), so a recovery failure is much easier to triage
+// when it's known which build wrote the cache. The cache sits in the
+// client's user-data folder (`discord`, `discordptb`, `discordcanary`, ...),
+// which also holds:
+//   <version>/modules/       one folder per installed host version, with
+//                            `installed.json` listing module versions
+//   settings.json            the client's local settings
+// On Windows the host itself is installed by Squirrel under
+// %LOCALAPPDATA%\Discord*\app-<version>, with its modules beside it.
+
+/// User-data folder names, lowercase, and their release channel.
+const CHANNELS: &[(&str, &str)] = &[
+    ("discord", "stable"),
+    ("discordptb", "ptb"),
+    ("discordcanary", "canary"),
+    ("discorddevelopment", "development"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiscordBuild {
+    /// "stable", "ptb", "canary" or "development"
+    pub channel: &'static str,
+    /// Newest installed host version, e.g. "1.0.9163"
+    pub host_version: Option<String>,
+    /// Installed modules and their versions, e.g. discord_desktop_core → 1
+    pub modules: BTreeMap<String, u32>,
+    /// settings.json has SKIP_HOST_UPDATE set, so the host may be old
+    pub host_updates_skipped: bool,
+    pub user_data_dir: String,
+}
+
+impl DiscordBuild {
+    /// One line for logs, e.g. "Discord ptb 0.0.100, discord_desktop_core 3".
+    pub fn summary(&self) -> String {
+        let version = self.host_version.as_deref().unwrap_or("(unknown version)");
+        let mut line = format!("Discord {} {}", self.channel, version);
+        if let Some(core) = self.modules.get("discord_desktop_core") {
+            line.push_str(&format!(", discord_desktop_core {}", core));
+        }
+        if self.host_updates_skipped {
+            line.push_str(", host updates skipped");
+        }
+        line
+    }
+}
+
+/// "1.0.9163" → [1, 0, 9163]; None for anything that isn't a dotted version.
+fn parse_version(name: &str) -> Option<Vec<u32>> {
+    let parts: Option<Vec<u32>> = name.split('.').map(|p| p.parse().ok()).collect();
+    parts.filter(|p| p.len() >= 2)
+}
+
+/// Subfolders of `dir` named `{prefix}{version}`, newest first.
+fn version_dirs(dir: &Path, prefix: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(Vec<u32>, String, PathBuf)> = entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let version = name.strip_prefix(prefix)?.to_string();
+            Some((parse_version(&version)?, version, e.path()))
+        })
+        .collect();
+    found.sort_by(|a, b| b.0.cmp(&a.0));
+    found.into_iter().map(|(_, version, path)| (version, path)).collect()
+}
+
+/// Module versions from `modules/installed.json`, or failing that from the
+/// `{name}-{version}` folder names Squirrel installs use.
+fn read_modules(modules_dir: &Path) -> BTreeMap<String, u32> {
+    let installed = std::fs::read_to_string(modules_dir.join("installed.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok());
+    if let Some(serde_json::Value::Object(modules)) = installed {
+        return modules
+            .into_iter()
+            .filter_map(|(name, info)| Some((name, info.get("installedVersion")?.as_u64()? as u32)))
+            .collect();
+    }
+    let Ok(entries) = std::fs::read_dir(modules_dir) else {
+        return BTreeMap::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let (module, version) = name.rsplit_once('-')?;
+            Some((module.to_string(), version.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Squirrel's install folder for a channel: %LOCALAPPDATA%\Discord, DiscordPTB, ...
+fn squirrel_dir(user_data_name: &str) -> Option<PathBuf> {
+    let local = std::env::var_os("LOCALAPPDATA")?;
+    let name = match user_data_name {
+        "discordptb" => "DiscordPTB",
+        "discordcanary" => "DiscordCanary",
+        "discorddevelopment" => "DiscordDevelopment",
+        _ => "Discord",
+    };
+    Some(PathBuf::from(local).join(name))
+}
+
+/// Which Discord build wrote the cache at `cache_dir`. None when the folder
+/// isn't inside a Discord user-data folder.
+pub fn detect(cache_dir: &Path) -> Option<DiscordBuild> {
+    let (user_data, channel) = cache_dir.ancestors().find_map(|dir| {
+        let name = dir.file_name()?.to_string_lossy().to_lowercase();
+        CHANNELS.iter().find(|(n, _)| *n == name).map(|(_, channel)| (dir, *channel))
+    })?;
+    let name = user_data.file_name()?.to_string_lossy().to_lowercase();
+
+    let mut host = version_dirs(user_data, "").into_iter().next();
+    let mut modules_dir = host.as_ref().map(|(_, dir)| dir.join("modules"));
+    if let Some((version, dir)) = squirrel_dir(&name).and_then(|d| version_dirs(&d, "app-").into_iter().next()) {
+        let newer = host.as_ref().is_none_or(|(v, _)| parse_version(&version) > parse_version(v));
+        if newer {
+            modules_dir = Some(dir.join("modules"));
+            host = Some((version, dir));
+        }
+    }
+
+    let settings = std::fs::read_to_string(user_data.join("settings.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok());
+    let host_updates_skipped =
+        settings.as_ref().and_then(|s| s.get("SKIP_HOST_UPDATE")).and_then(|v| v.as_bool()).unwrap_or(false);
+
+    Some(DiscordBuild {
+        channel,
+        host_version: host.map(|(version, _)| version),
+        modules: modules_dir.as_deref().map(read_modules).unwrap_or_default(),
+        host_updates_skipped,
+        user_data_dir: user_data.to_string_lossy().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_channel_newest_host_and_modules() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-discord-{}", std::process::id()));
+        let user_data = root.join("discordptb");
+        let cache = user_data.join("Cache").join("Cache_Data");
+        std::fs::create_dir_all(&cache).unwrap();
+        std::fs::create_dir_all(user_data.join("0.0.98/modules")).unwrap();
+        std::fs::create_dir_all(user_data.join("0.0.100/modules/discord_voice-2")).unwrap();
+        std::fs::write(
+            user_data.join("0.0.100/modules/installed.json"),
+            r#"{"discord_desktop_core": {"installedVersion": 3}, "discord_voice": {"installedVersion": 2}}"#,
+        )
+        .unwrap();
+        std::fs::write(user_data.join("settings.json"), r#"{"SKIP_HOST_UPDATE": true}"#).unwrap();
+
+        let build = detect(&cache);
+        let other = detect(&root.join("Google/Chrome/Default/Cache"));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let build = build.unwrap();
+        assert_eq!((build.channel, build.host_version.as_deref()), ("ptb", Some("0.0.100")));
+        assert_eq!(build.modules.get("discord_desktop_core"), Some(&3));
+        assert!(build.host_updates_skipped);
+        assert_eq!(build.summary(), "Discord ptb 0.0.100, discord_desktop_core 3, host updates skipped");
+        assert!(other.is_none());
+    }
+}
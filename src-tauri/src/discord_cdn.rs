@@ -0,0 +1,499 @@
+// Known Discord CDN response shapes.
+//
+// Discord serves user content from a handful of CDN endpoints, each with a
+// stable URL layout and a recognizable set of response headers. Rather than
+// scattering string matching across the scanner, the layouts live here as a
+// small table that the rest of the crate can query for original filenames
+// and grouping keys.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+// ---------------------------------------------------------------------------
+// Knowledge table
+// ---------------------------------------------------------------------------
+
+/// Hosts that serve Discord CDN content.
+/// `media.discordapp.net` is the resizing media proxy in front of the CDN.
+const CDN_HOSTS: &[&str] = &["cdn.discordapp.com", "media.discordapp.net"];
+
+/// Media proxy host (served resized/transcoded variants of CDN objects).
+const MEDIA_PROXY_HOST: &str = "media.discordapp.net";
+
+/// Discord's snowflake epoch, 2015-01-01T00:00:00Z, in Unix milliseconds.
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+/// Snowflakes decoding past this (2030-01-01) are not real IDs.
+const MAX_SNOWFLAKE_MS: u64 = 1_893_456_000_000;
+
+/// One segment of an endpoint's URL path pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Seg {
+    /// Literal path segment, e.g. `attachments`.
+    Lit(&'static str),
+    /// Snowflake ID (decimal digits). Part of the grouping key.
+    Id,
+    /// Asset hash, optionally with an extension (`a_1b2c.gif`). Part of the grouping key.
+    Hash,
+    /// Uploader-supplied filename (percent-encoded). Part of the grouping key.
+    File,
+    /// Everything from here on, unparsed (external proxy targets).
+    Rest,
+}
+
+/// A response header this endpoint is known to send.
+#[derive(Debug, Clone, Copy)]
+struct HeaderShape {
+    /// Lowercase header name.
+    name: &'static str,
+    /// Substring expected in the lowercase header value ("" = presence only).
+    contains: &'static str,
+}
+
+/// A known Discord CDN endpoint.
+#[derive(Debug)]
+struct Endpoint {
+    kind: &'static str,
+    path: &'static [Seg],
+    /// URLs carry `ex`/`is`/`hm` signing parameters.
+    signed: bool,
+    headers: &'static [HeaderShape],
+}
+
+const CACHE_IMMUTABLE: HeaderShape = HeaderShape { name: "cache-control", contains: "max-age" };
+const DISPOSITION_FILENAME: HeaderShape = HeaderShape { name: "content-disposition", contains: "filename" };
+const CONTENT_TYPE: HeaderShape = HeaderShape { name: "content-type", contains: "" };
+
+/// Endpoint table. Order matters: the first matching pattern wins.
+const ENDPOINTS: &[Endpoint] = &[
+    // /attachments/{channel_id}/{attachment_id}/{filename}
+    Endpoint {
+        kind: "attachment",
+        path: &[Seg::Lit("attachments"), Seg::Id, Seg::Id, Seg::File],
+        signed: true,
+        headers: &[CACHE_IMMUTABLE, DISPOSITION_FILENAME, CONTENT_TYPE],
+    },
+    // /ephemeral-attachments/{application_id}/{attachment_id}/{filename}
+    Endpoint {
+        kind: "ephemeral_attachment",
+        path: &[Seg::Lit("ephemeral-attachments"), Seg::Id, Seg::Id, Seg::File],
+        signed: true,
+        headers: &[CACHE_IMMUTABLE, DISPOSITION_FILENAME, CONTENT_TYPE],
+    },
+    // /avatars/{user_id}/{hash}.{ext}
+    Endpoint {
+        kind: "avatar",
+        path: &[Seg::Lit("avatars"), Seg::Id, Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /guilds/{guild_id}/users/{user_id}/avatars/{hash}.{ext}
+    Endpoint {
+        kind: "guild_member_avatar",
+        path: &[Seg::Lit("guilds"), Seg::Id, Seg::Lit("users"), Seg::Id, Seg::Lit("avatars"), Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /guilds/{guild_id}/users/{user_id}/banners/{hash}.{ext}
+    Endpoint {
+        kind: "guild_member_banner",
+        path: &[Seg::Lit("guilds"), Seg::Id, Seg::Lit("users"), Seg::Id, Seg::Lit("banners"), Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /banners/{user_or_guild_id}/{hash}.{ext}
+    Endpoint {
+        kind: "banner",
+        path: &[Seg::Lit("banners"), Seg::Id, Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /avatar-decoration-presets/{hash}.{ext}
+    Endpoint {
+        kind: "avatar_decoration",
+        path: &[Seg::Lit("avatar-decoration-presets"), Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /icons/{guild_id}/{hash}.{ext}
+    Endpoint {
+        kind: "guild_icon",
+        path: &[Seg::Lit("icons"), Seg::Id, Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /splashes/{guild_id}/{hash}.{ext}
+    Endpoint {
+        kind: "guild_splash",
+        path: &[Seg::Lit("splashes"), Seg::Id, Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /role-icons/{role_id}/{hash}.{ext}
+    Endpoint {
+        kind: "role_icon",
+        path: &[Seg::Lit("role-icons"), Seg::Id, Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /app-icons/{application_id}/{hash}.{ext}
+    Endpoint {
+        kind: "app_icon",
+        path: &[Seg::Lit("app-icons"), Seg::Id, Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /emojis/{emoji_id}.{ext}
+    Endpoint {
+        kind: "emoji",
+        path: &[Seg::Lit("emojis"), Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /stickers/{sticker_id}.{ext}
+    Endpoint {
+        kind: "sticker",
+        path: &[Seg::Lit("stickers"), Seg::Hash],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+    // /external/{signature}/{scheme}/{host}/{path...} (media proxy for embeds)
+    Endpoint {
+        kind: "external_proxy",
+        path: &[Seg::Lit("external"), Seg::Rest],
+        signed: false,
+        headers: &[CACHE_IMMUTABLE, CONTENT_TYPE],
+    },
+];
+
+// ---------------------------------------------------------------------------
+// Output structs (JSON-serializable)
+// ---------------------------------------------------------------------------
+
+/// Signing parameters found on a CDN URL.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedUrlParams {
+    /// `ex`: expiry, unix seconds (hex in the URL)
+    pub expires_at: Option<u64>,
+    /// `is`: issue time, unix seconds (hex in the URL)
+    pub issued_at: Option<u64>,
+    /// `hm`: HMAC signature (hex)
+    pub hm: Option<String>,
+}
+
+/// Resize / transcode parameters of a media proxy or CDN image URL.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VariantParams {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// `size`: square edge for avatars, icons and emojis
+    pub size: Option<u32>,
+    /// `format`: requested output format, e.g. "webp"
+    pub format: Option<String>,
+    /// `quality`: "lossless" or a number
+    pub quality: Option<String>,
+}
+
+/// Result of matching a URL (and optionally its response headers) against the table.
+#[derive(Debug, Clone, Serialize)]
+pub struct CdnMatch {
+    /// Endpoint kind, e.g. "attachment", "avatar"
+    pub kind: &'static str,
+    pub host: String,
+    pub is_media_proxy: bool,
+    /// Stable identity of the underlying object: independent of host (CDN vs
+    /// media proxy), signing parameters and resize query params.
+    pub group_key: String,
+    /// Best available original filename (Content-Disposition first, then URL)
+    pub original_filename: Option<String>,
+    pub signed: Option<SignedUrlParams>,
+    /// Channel ID of an attachment (application ID for ephemeral attachments)
+    pub channel_id: Option<String>,
+    /// Attachment ID, or the user/guild/emoji/sticker ID the asset belongs to
+    pub resource_id: Option<String>,
+    /// Creation time encoded in `resource_id`, Unix seconds
+    pub created_at: Option<u64>,
+    /// Creation time encoded in `channel_id`, Unix seconds
+    pub channel_created_at: Option<u64>,
+    pub variant: Option<VariantParams>,
+    /// The URL without its isolation prefix and `hm` signature
+    pub clean_url: String,
+    /// Known headers for this endpoint that were present with the expected shape
+    pub header_hits: Vec<&'static str>,
+    /// Known headers for this endpoint that were missing or had an unexpected shape
+    pub header_misses: Vec<&'static str>,
+}
+
+// ---------------------------------------------------------------------------
+// Matching
+// ---------------------------------------------------------------------------
+
+/// Split a cache key into (host, path segments, query string).
+/// Cache keys may carry an isolation prefix (`1/0/`, `_dk_... `); it is skipped.
+fn split_url(key: &str) -> Option<(String, Vec<&str>, &str)> {
+    let url = crate::cache_key::effective_url(key);
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let (authority, path_and_query) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, ""),
+    };
+    let path_and_query = path_and_query.split('#').next().unwrap_or("");
+    let (path, query) = match path_and_query.find('?') {
+        Some(i) => (&path_and_query[..i], &path_and_query[i + 1..]),
+        None => (path_and_query, ""),
+    };
+    let host = authority.split(':').next().unwrap_or("").to_lowercase();
+    let segments = path.split('/').filter(|s| !s.is_empty()).collect();
+    Some((host, segments, query))
+}
+
+fn seg_matches(seg: Seg, value: &str) -> bool {
+    match seg {
+        Seg::Lit(lit) => value == lit,
+        Seg::Id => !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()),
+        Seg::Hash | Seg::File | Seg::Rest => !value.is_empty(),
+    }
+}
+
+fn match_path(endpoint: &Endpoint, segments: &[&str]) -> bool {
+    let has_rest = endpoint.path.last() == Some(&Seg::Rest);
+    if has_rest {
+        if segments.len() < endpoint.path.len() {
+            return false;
+        }
+    } else if segments.len() != endpoint.path.len() {
+        return false;
+    }
+    endpoint
+        .path
+        .iter()
+        .zip(segments)
+        .all(|(seg, value)| seg_matches(*seg, value))
+}
+
+fn parse_signed_params(query: &str) -> Option<SignedUrlParams> {
+    let mut params = SignedUrlParams { expires_at: None, issued_at: None, hm: None };
+    for pair in query.split('&') {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        match k {
+            "ex" => params.expires_at = u64::from_str_radix(v, 16).ok(),
+            "is" => params.issued_at = u64::from_str_radix(v, 16).ok(),
+            "hm" if !v.is_empty() => params.hm = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    if params.expires_at.is_none() && params.issued_at.is_none() && params.hm.is_none() {
+        None
+    } else {
+        Some(params)
+    }
+}
+
+fn parse_variant_params(query: &str) -> Option<VariantParams> {
+    let mut params = VariantParams::default();
+    for pair in query.split('&') {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        match k {
+            "width" => params.width = v.parse().ok(),
+            "height" => params.height = v.parse().ok(),
+            "size" => params.size = v.parse().ok(),
+            "format" if !v.is_empty() => params.format = Some(v.to_ascii_lowercase()),
+            "quality" if !v.is_empty() => params.quality = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    let any = params.width.is_some()
+        || params.height.is_some()
+        || params.size.is_some()
+        || params.format.is_some()
+        || params.quality.is_some();
+    any.then_some(params)
+}
+
+/// Creation time of a snowflake ID, Unix seconds; None for values that
+/// can't be a Discord ID.
+pub fn snowflake_time(id: &str) -> Option<u64> {
+    let ms = (id.parse::<u64>().ok()? >> 22) + DISCORD_EPOCH_MS;
+    (ms > DISCORD_EPOCH_MS && ms < MAX_SNOWFLAKE_MS).then_some(ms / 1000)
+}
+
+/// `url` (isolation prefix already removed) without the `hm` parameter.
+fn strip_signature(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or(url);
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let kept: Vec<&str> = query.split('&').filter(|p| !p.is_empty() && !p.starts_with("hm=")).collect();
+    if kept.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, kept.join("&"))
+    }
+}
+
+/// Match a URL and, when available, its parsed response headers (lowercase keys).
+pub fn match_response(url: &str, headers: Option<&HashMap<String, String>>) -> Option<CdnMatch> {
+    let (host, segments, query) = split_url(url)?;
+    if !CDN_HOSTS.contains(&host.as_str()) {
+        return None;
+    }
+    let endpoint = ENDPOINTS.iter().find(|e| match_path(e, &segments))?;
+
+    // Group key: kind + every variable segment, with hash extensions dropped so
+    // `a_abc.gif` and `a_abc.webp` (same asset, different format) group together.
+    let mut key_parts: Vec<String> = vec![endpoint.kind.to_string()];
+    let mut url_filename: Option<String> = None;
+    let mut ids: Vec<&str> = Vec::new();
+    let mut hash_id: Option<&str> = None;
+    for (seg, value) in endpoint.path.iter().zip(&segments) {
+        match seg {
+            Seg::Lit(_) => {}
+            Seg::Id => {
+                key_parts.push(value.to_string());
+                ids.push(value);
+            }
+            Seg::Hash => {
+                // Emojis and stickers are named by their snowflake ID.
+                let stem = value.split('.').next().unwrap_or(value);
+                if !stem.is_empty() && stem.chars().all(|c| c.is_ascii_digit()) {
+                    hash_id = Some(stem);
+                }
+                key_parts.push(value.split('.').next().unwrap_or(value).to_string());
+                url_filename = Some(crate::blockfile_index::url_decode(value));
+            }
+            Seg::File => {
+                let decoded = crate::blockfile_index::url_decode(value);
+                key_parts.push(decoded.clone());
+                url_filename = Some(decoded);
+            }
+            Seg::Rest => {
+                let rest = segments[endpoint.path.len() - 1..].join("/");
+                url_filename = segments.last().map(|s| crate::blockfile_index::url_decode(s));
+                key_parts.push(rest);
+            }
+        }
+    }
+
+    let mut header_hits = Vec::new();
+    let mut header_misses = Vec::new();
+    let mut disposition_filename: Option<String> = None;
+    if let Some(headers) = headers {
+        for shape in endpoint.headers {
+            match headers.get(shape.name) {
+                Some(value) if value.to_lowercase().contains(shape.contains) => {
+                    header_hits.push(shape.name);
+                }
+                _ => header_misses.push(shape.name),
+            }
+        }
+        if let Some(cd) = headers.get("content-disposition") {
+            disposition_filename = crate::content_disposition::filename(cd);
+        }
+    }
+
+    // Attachments are /{channel}/{attachment}/; other assets are named after
+    // their owner (user, guild, role, app) or, for emojis and stickers, themselves.
+    let has_file = endpoint.path.contains(&Seg::File);
+    let channel_id = if has_file { ids.first().map(|s| s.to_string()) } else { None };
+    let resource_id = if has_file { ids.get(1).copied() } else { hash_id.or(ids.last().copied()) };
+
+    Some(CdnMatch {
+        kind: endpoint.kind,
+        channel_created_at: channel_id.as_deref().and_then(snowflake_time),
+        channel_id,
+        created_at: resource_id.and_then(snowflake_time),
+        resource_id: resource_id.map(str::to_string),
+        variant: parse_variant_params(query),
+        clean_url: strip_signature(crate::cache_key::effective_url(url)),
+        is_media_proxy: host == MEDIA_PROXY_HOST,
+        host,
+        group_key: key_parts.join("/"),
+        original_filename: disposition_filename.or(url_filename),
+        signed: if endpoint.signed { parse_signed_params(query) } else { None },
+        header_hits,
+        header_misses,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Describe a Discord CDN URL using the known endpoint table.
+/// Returns null for URLs that are not recognized Discord CDN endpoints.
+#[tauri::command]
+pub fn describe_cdn_url(
+    url: String,
+    headers: Option<HashMap<String, String>>,
+) -> Option<CdnMatch> {
+    let headers = headers.map(|h| {
+        h.into_iter()
+            .map(|(k, v)| (k.to_lowercase(), v))
+            .collect::<HashMap<_, _>>()
+    });
+    match_response(&url, headers.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn match_url(url: &str) -> Option<CdnMatch> {
+        match_response(url, None)
+    }
+
+    #[test]
+    fn attachment_groups_across_hosts_and_signatures() {
+        let a = match_url("https://cdn.discordapp.com/attachments/111/222/clip%20one.mp4?ex=65f0a1b2&is=65ef5032&hm=abcd").unwrap();
+        let b = match_url("https://media.discordapp.net/attachments/111/222/clip%20one.mp4?width=400").unwrap();
+        assert_eq!(a.kind, "attachment");
+        assert_eq!(a.group_key, b.group_key);
+        assert_eq!(a.original_filename.as_deref(), Some("clip one.mp4"));
+        assert!(b.is_media_proxy);
+        let signed = a.signed.unwrap();
+        assert_eq!(signed.expires_at, Some(0x65f0a1b2));
+        assert_eq!(signed.hm.as_deref(), Some("abcd"));
+    }
+
+    #[test]
+    fn extracts_ids_variant_and_clean_url() {
+        let m = match_url(
+            "1/0/https://media.discordapp.net/attachments/1081612633416712222/1215368452891033600/clip.mp4?ex=65f0a1b2&is=65ef5032&hm=ff&format=webp&width=400&height=225",
+        )
+        .unwrap();
+        assert_eq!(m.channel_id.as_deref(), Some("1081612633416712222"));
+        assert_eq!(m.resource_id.as_deref(), Some("1215368452891033600"));
+        assert_eq!((m.channel_created_at, m.created_at), (Some(1_677_946_947), Some(1_709_836_819)));
+        let variant = m.variant.unwrap();
+        assert_eq!((variant.width, variant.height, variant.format.as_deref()), (Some(400), Some(225), Some("webp")));
+        assert_eq!(
+            m.clean_url,
+            "https://media.discordapp.net/attachments/1081612633416712222/1215368452891033600/clip.mp4?ex=65f0a1b2&is=65ef5032&format=webp&width=400&height=225"
+        );
+        let emoji = match_url("https://cdn.discordapp.com/emojis/1215368452891033600.webp?size=48").unwrap();
+        assert_eq!((emoji.resource_id.as_deref(), emoji.variant.unwrap().size), (Some("1215368452891033600"), Some(48)));
+        assert_eq!(snowflake_time("42"), None);
+    }
+
+    #[test]
+    fn content_disposition_beats_url_filename() {
+        let mut headers = HashMap::new();
+        headers.insert("content-disposition".to_string(), "attachment; filename=\"real.mp4\"".to_string());
+        headers.insert("cache-control".to_string(), "public, max-age=31536000".to_string());
+        let m = match_response("https://cdn.discordapp.com/attachments/1/2/renamed.mp4", Some(&headers)).unwrap();
+        assert_eq!(m.original_filename.as_deref(), Some("real.mp4"));
+        assert_eq!(m.header_hits, vec!["cache-control", "content-disposition"]);
+        assert_eq!(m.header_misses, vec!["content-type"]);
+    }
+
+    #[test]
+    fn avatar_formats_share_group_key() {
+        let gif = match_url("https://cdn.discordapp.com/avatars/42/a_deadbeef.gif?size=128").unwrap();
+        let webp = match_url("https://cdn.discordapp.com/avatars/42/a_deadbeef.webp").unwrap();
+        assert_eq!(gif.kind, "avatar");
+        assert_eq!(gif.group_key, webp.group_key);
+        assert!(match_url("https://example.com/avatars/42/a_deadbeef.gif").is_none());
+    }
+}
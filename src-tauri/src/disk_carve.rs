@@ -0,0 +1,409 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::events::Coalescer;
+use crate::output_file::OutputFile;
+use crate::{
+    FLAG_HAS_KEY_SHA256, SIMPLE_CACHE_EOF_MAGIC, SIMPLE_CACHE_EOF_SIZE, SIMPLE_CACHE_HEADER_SIZE, SIMPLE_CACHE_MAGIC,
+    SPARSE_RANGE_HEADER_SIZE, SPARSE_RANGE_MAGIC,
+};
+
+// ─── Raw disk-image carving ─────────────────────────────────────────────────
+//
+// Once a cache folder is wiped, its entries may still sit in unallocated
+// space. `carve_disk_image` reads a raw image (dd, a free-space dump from
+// blkls, a raw partition) from start to end looking for three signatures and
+// rebuilds whole files from them:
+//
+//   Simple Cache header  magic, then a key whose stored hash matches. What
+//                        follows decides the file: a sparse range header
+//                        (`_s`, ranges chained to the last one), or stream
+//                        data closed by an EOF record whose stream size
+//                        matches (`_1`), plus a second stream and EOF record
+//                        when present (`_0`).
+//   MP4 `ftyp` box       top-level boxes followed while their types and sizes
+//                        stay plausible; kept only if a `moov` or `mdat` is
+//                        among them.
+//
+// Carved entries are written under their own names into the output folder,
+// so it reads as a Simple Cache folder and the normal scan (sparse
+// reassembly, MP4 reconstruction) runs on it unchanged. Names come from a
+// hash of the key, which keeps an entry's `_0` and `_s` files together; it is
+// not Chromium's own entry hash, and there is no index. Standalone MP4s are
+// written as `carved_<offset>.mp4`. Signatures inside something already
+// carved are skipped. Files are assumed contiguous on disk: a fragmented
+// entry is cut at the first fragment or fails validation.
+
+/// Event name carrying a `CarveProgress` payload.
+pub const CARVE_PROGRESS_EVENT: &str = "carve-progress";
+
+const SCAN_CHUNK: usize = 16 * 1024 * 1024;
+/// Signatures straddling a chunk boundary are found in the earlier chunk.
+const SCAN_OVERLAP: usize = 16;
+/// Longest key read when validating a header.
+const MAX_CARVED_KEY: usize = 8 * 1024;
+/// Longest stream (`_0` stream 1, `_1` body) searched for its EOF record.
+const MAX_CARVED_STREAM: usize = 64 * 1024 * 1024;
+/// Longest stream 0 (HTTP headers) after EOF1.
+const MAX_CARVED_STREAM0: usize = 256 * 1024;
+/// Bytes read at a time while looking for an EOF record.
+const EOF_SEARCH_WINDOW: usize = 1024 * 1024;
+/// Largest sparse file or standalone MP4 followed.
+const MAX_CARVED_FILE: u64 = 4 * 1024 * 1024 * 1024;
+const MAX_FTYP_SIZE: u64 = 1024;
+const COPY_CHUNK: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CarvedItem {
+    /// "simple_entry" (`_0`), "stream2_entry" (`_1`), "sparse_entry" (`_s`) or "mp4"
+    pub kind: &'static str,
+    /// Byte offset in the image
+    pub offset: u64,
+    pub length: u64,
+    pub url: Option<String>,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CarveProgress {
+    pub image: String,
+    pub bytes_scanned: u64,
+    pub total_bytes: u64,
+    pub found: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CarveReport {
+    pub image: String,
+    pub output_dir: String,
+    pub bytes_scanned: u64,
+    pub items: Vec<CarvedItem>,
+    /// Entries whose key was already carved (another copy of the same file)
+    pub duplicates: usize,
+    pub errors: Vec<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Up to `len` bytes of `file` at `offset` (fewer at the end of the image).
+fn read_at(file: &File, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut handle = file;
+    handle.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::with_capacity(len);
+    handle.take(len as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Every position of `needle` in `haystack`.
+fn find_all(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+    let Some(&first) = needle.first() else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    let mut at = 0;
+    while let Some(i) = haystack.get(at..).and_then(|rest| rest.iter().position(|&b| b == first)) {
+        let start = at + i;
+        if haystack[start..].starts_with(needle) {
+            found.push(start);
+        }
+        at = start + 1;
+    }
+    found
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_le(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Length of the stream starting at image offset `at`: the bytes before the
+/// first EOF magic whose stream size matches them, after an optional key
+/// SHA-256 when `allow_sha` is set. Searched up to `max_len` bytes into the
+/// stream a window at a time, so a false signature costs one window's read.
+fn stream_end(file: &File, at: u64, max_len: usize, allow_sha: bool) -> Option<u64> {
+    let magic = SIMPLE_CACHE_EOF_MAGIC.to_le_bytes();
+    let mut scanned = 0;
+    while scanned < max_len {
+        let want = (max_len - scanned).min(EOF_SEARCH_WINDOW);
+        // Read past the window so a record starting inside it is whole.
+        let window = read_at(file, at + scanned as u64, want + SIMPLE_CACHE_EOF_SIZE).ok()?;
+        let found = find_all(&window[..want.min(window.len())], &magic).into_iter().find(|&eof| {
+            let (Some(flags), Some(size)) = (u32_le(&window, eof + 8), u32_le(&window, eof + 16)) else {
+                return false;
+            };
+            let sha = if allow_sha && flags & FLAG_HAS_KEY_SHA256 != 0 { 32 } else { 0 };
+            eof + SIMPLE_CACHE_EOF_SIZE <= window.len() && size as usize + sha == scanned + eof
+        });
+        if let Some(eof) = found {
+            return Some((scanned + eof) as u64);
+        }
+        if window.len() < want + SIMPLE_CACHE_EOF_SIZE {
+            return None; // end of the image
+        }
+        scanned += want;
+    }
+    None
+}
+
+/// End (relative to `offset`) of the chain of sparse ranges at `first_range`.
+fn sparse_end(file: &File, offset: u64, first_range: u64, image_len: u64) -> Option<u64> {
+    let mut pos = first_range;
+    let mut ranges = 0;
+    while offset + pos + SPARSE_RANGE_HEADER_SIZE as u64 <= image_len && pos < MAX_CARVED_FILE {
+        let header = read_at(file, offset + pos, SPARSE_RANGE_HEADER_SIZE).ok()?;
+        if u64_le(&header, 0) != Some(SPARSE_RANGE_MAGIC) {
+            break;
+        }
+        let length = u64_le(&header, 16)?;
+        let next = pos + SPARSE_RANGE_HEADER_SIZE as u64 + length;
+        if offset + next > image_len {
+            break;
+        }
+        pos = next;
+        ranges += 1;
+    }
+    (ranges > 0).then_some(pos)
+}
+
+/// Kind, length and key of the Simple Cache file starting at `offset`.
+fn carve_entry(file: &File, offset: u64, image_len: u64) -> Option<(&'static str, u64, String)> {
+    let head = read_at(file, offset, SIMPLE_CACHE_HEADER_SIZE + MAX_CARVED_KEY).ok()?;
+    let key_length = crate::key_hash::check_key(&head).0?;
+    let key = String::from_utf8_lossy(&head[SIMPLE_CACHE_HEADER_SIZE..SIMPLE_CACHE_HEADER_SIZE + key_length]);
+    let stream_start = SIMPLE_CACHE_HEADER_SIZE + key_length;
+    if u64_le(&head, stream_start) == Some(SPARSE_RANGE_MAGIC) {
+        let end = sparse_end(file, offset, stream_start as u64, image_len)?;
+        return Some(("sparse_entry", end, key.to_string()));
+    }
+
+    // EOF1 closes stream 1 and EOF0 stream 0 with sizes that match what is
+    // before them: the same boundaries the `_0` layout is read with later.
+    let stream1_len = stream_end(file, offset + stream_start as u64, MAX_CARVED_STREAM, false)?;
+    let stream0_start = stream_start as u64 + stream1_len + SIMPLE_CACHE_EOF_SIZE as u64;
+    match stream_end(file, offset + stream0_start, MAX_CARVED_STREAM0, true) {
+        Some(stream0_len) => {
+            Some(("simple_entry", stream0_start + stream0_len + SIMPLE_CACHE_EOF_SIZE as u64, key.to_string()))
+        }
+        None => Some(("stream2_entry", stream0_start, key.to_string())),
+    }
+}
+
+/// Length of the MP4 whose `ftyp` box starts at `offset`.
+fn carve_mp4(file: &File, offset: u64, image_len: u64) -> Option<u64> {
+    let mut pos = 0u64;
+    let mut has_media = false;
+    while offset + pos + 8 <= image_len && pos < MAX_CARVED_FILE {
+        let header = read_at(file, offset + pos, 16).ok()?;
+        let box_type = &header[4..8];
+        if !box_type.iter().all(|b| b.is_ascii_alphanumeric() || *b == b' ') {
+            break;
+        }
+        let size = match u32::from_be_bytes(header[0..4].try_into().ok()?) as u64 {
+            0 => image_len - offset - pos,
+            1 => u64::from_be_bytes(header.get(8..16)?.try_into().ok()?),
+            size => size,
+        };
+        let first = pos == 0;
+        if size < 8 || (first && (box_type != b"ftyp" || size > MAX_FTYP_SIZE)) {
+            break;
+        }
+        has_media |= box_type == b"moov" || box_type == b"mdat";
+        // A box cut off by the end of the image is kept up to there.
+        pos = (pos + size).min(image_len - offset);
+    }
+    (has_media && pos > 0).then_some(pos)
+}
+
+/// Output file name for a carved entry: `{hash of key}_{0|1|s}`.
+fn entry_name(kind: &str, key: &str) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    let hash = u64::from_le_bytes(digest[..8].try_into().unwrap_or_default());
+    let suffix = match kind {
+        "sparse_entry" => "s",
+        "stream2_entry" => "1",
+        _ => "0",
+    };
+    format!("{:016x}_{}", hash, suffix)
+}
+
+fn copy_out(file: &File, offset: u64, length: u64, dst: &Path) -> Result<(), String> {
+    let mut out = OutputFile::create(&dst.to_string_lossy())?;
+    let mut done = 0u64;
+    while done < length {
+        let n = (length - done).min(COPY_CHUNK as u64) as usize;
+        let buf = read_at(file, offset + done, n)
+            .map_err(|e| format!("Failed to read image at {}: {}", offset + done, e))?;
+        if buf.is_empty() {
+            break;
+        }
+        out.write_all(&buf)?;
+        done += buf.len() as u64;
+    }
+    out.finish().map(|_| ())
+}
+
+/// Scan `image` and write what it holds into `output_dir`.
+fn carve(image: &Path, output_dir: &Path, on_progress: &mut dyn FnMut(&CarveProgress)) -> Result<CarveReport, String> {
+    let started = std::time::Instant::now();
+    let file = File::open(image).map_err(|e| crate::format_read_error(&image.to_string_lossy(), &e))?;
+    let image_len = file.metadata().map(|m| m.len()).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(output_dir).map_err(|e| format!("Failed to create {}: {}", output_dir.display(), e))?;
+    let mut report = CarveReport {
+        image: image.to_string_lossy().to_string(),
+        output_dir: output_dir.to_string_lossy().to_string(),
+        bytes_scanned: 0,
+        items: Vec::new(),
+        duplicates: 0,
+        errors: Vec::new(),
+        elapsed_ms: 0,
+    };
+    let entry_magic = SIMPLE_CACHE_MAGIC.to_le_bytes();
+    let mut covered_until = 0u64;
+    let mut pos = 0u64;
+    while pos < image_len {
+        let chunk = read_at(&file, pos, SCAN_CHUNK + SCAN_OVERLAP)
+            .map_err(|e| format!("Failed to read {} at {}: {}", image.display(), pos, e))?;
+        let mut hits: Vec<(u64, bool)> =
+            find_all(&chunk, &entry_magic).into_iter().map(|i| (pos + i as u64, true)).collect();
+        hits.extend(find_all(&chunk, b"ftyp").into_iter().filter(|&i| i >= 4).map(|i| (pos + i as u64 - 4, false)));
+        hits.retain(|&(at, _)| at < pos + SCAN_CHUNK as u64);
+        hits.sort();
+
+        for (at, is_entry) in hits {
+            if at < covered_until {
+                continue;
+            }
+            let (kind, length, url, name) = if is_entry {
+                let Some((kind, length, key)) = carve_entry(&file, at, image_len) else {
+                    continue;
+                };
+                let url = crate::cache_key::effective_url(&key).to_string();
+                (kind, length, Some(url), entry_name(kind, &key))
+            } else {
+                let Some(length) = carve_mp4(&file, at, image_len) else {
+                    continue;
+                };
+                ("mp4", length, None, format!("carved_{:012x}.mp4", at))
+            };
+            covered_until = at + length;
+            let output = output_dir.join(&name);
+            if is_entry && output.exists() {
+                report.duplicates += 1;
+                continue;
+            }
+            match copy_out(&file, at, length, &output) {
+                Ok(()) => report.items.push(CarvedItem {
+                    kind,
+                    offset: at,
+                    length,
+                    url,
+                    output: output.to_string_lossy().to_string(),
+                }),
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        pos += SCAN_CHUNK as u64;
+        report.bytes_scanned = pos.min(image_len);
+        on_progress(&CarveProgress {
+            image: report.image.clone(),
+            bytes_scanned: report.bytes_scanned,
+            total_bytes: image_len,
+            found: report.items.len(),
+        });
+    }
+    report.elapsed_ms = started.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+/// Carve Simple Cache entries and MP4 files out of the raw image `image`
+/// into `output_dir`, emitting `carve-progress` as it goes. Requires a
+/// `consent` token covering `output_dir`.
+#[tauri::command(async)]
+pub fn carve_disk_image(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    image: String,
+    output_dir: String,
+    consent: String,
+) -> Result<CarveReport, String> {
+    let output_dir = state.check(&consent, &output_dir)?;
+    if !cfg!(feature = "carving") {
+        return Err("This build was compiled without disk-image carving (feature \"carving\")".to_string());
+    }
+    let image = PathBuf::from(crate::path_display::expand_path(&image));
+    let output_dir = PathBuf::from(crate::path_display::expand_path(&output_dir));
+    if !image.is_file() {
+        return Err(format!("Not a file: {}", image.display()));
+    }
+    let _job = crate::crash::job(format!("carving {}", image.display()));
+    let mut events = Coalescer::for_app(app, CARVE_PROGRESS_EVENT);
+    let report = carve(&image, &output_dir, &mut |progress| events.push(progress.clone()))?;
+    println!(
+        "[carve] {}: {} items ({} duplicates) from {} bytes in {} ms, {} errors",
+        report.image,
+        report.items.len(),
+        report.duplicates,
+        report.bytes_scanned,
+        report.elapsed_ms,
+        report.errors.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carves_entries_and_mp4_from_an_image() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-carve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = "1/0/_dk_https://discord.com https://discord.com https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let entry = crate::fixture::simple_entry(key, &["HTTP/1.1 200 OK", "Content-Type: video/mp4"], b"body bytes");
+        let sparse = crate::fixture::sparse_entry(key, &[(0, b"first"), (4096, b"second")]);
+        let stream2 = crate::fixture::stream2_entry(key, b"large body");
+        let mut mp4 = b"\x00\x00\x00\x18ftypisom\x00\x00\x02\x00isomiso2".to_vec();
+        mp4.extend_from_slice(b"\x00\x00\x00\x10mdat12345678");
+
+        let mut image = vec![0xa5u8; 1000];
+        let offsets: Vec<usize> = [&entry, &sparse, &stream2, &mp4, &entry]
+            .iter()
+            .map(|part| {
+                let at = image.len();
+                image.extend_from_slice(part);
+                image.extend_from_slice(&[0u8; 333]);
+                at
+            })
+            .collect();
+        let image_path = dir.join("disk.img");
+        std::fs::write(&image_path, &image).unwrap();
+
+        let out = dir.join("carved");
+        let report = carve(&image_path, &out, &mut |_| {}).unwrap();
+        let listed = crate::cache::list_cache_files(&out.to_string_lossy()).unwrap().len();
+        let carved_entry = std::fs::read(&report.items[0].output).unwrap();
+        // An EOF record across a search window boundary is still found.
+        let body_len = EOF_SEARCH_WINDOW - 10;
+        let big = crate::fixture::stream2_entry(key, &vec![7u8; body_len]);
+        std::fs::write(dir.join("big_1"), &big).unwrap();
+        let body_at = (big.len() - SIMPLE_CACHE_EOF_SIZE - body_len) as u64;
+        let big_end = stream_end(&File::open(dir.join("big_1")).unwrap(), body_at, MAX_CARVED_STREAM, false);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let kinds: Vec<&str> = report.items.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, vec!["simple_entry", "sparse_entry", "stream2_entry", "mp4"]);
+        assert_eq!(report.items[3].offset, offsets[3] as u64);
+        assert_eq!(report.items[3].length, mp4.len() as u64);
+        assert_eq!(report.items[1].length, sparse.len() as u64);
+        assert_eq!(report.items[0].url.as_deref(), Some("https://cdn.discordapp.com/attachments/1/2/clip.mp4"));
+        assert_eq!(carved_entry, entry);
+        assert_eq!((report.duplicates, listed), (1, 3));
+        assert_eq!(big_end, Some(body_len as u64));
+    }
+}
@@ -0,0 +1,159 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::cache::CachePathInfo;
+use crate::events::Coalescer;
+
+// ─── Drive-wide cache discovery ─────────────────────────────────────────────
+//
+// The default path list assumes the current user's home folder. A disk taken
+// from another machine (or an old system drive) has its caches under some
+// other user's profile, often with a different OS layout, so instead of
+// guessing paths `scan_drive_for_caches` walks the whole tree and tests every
+// folder for cache signatures: a blockfile `index` + `data_0`, or Simple Cache
+// entries starting with the Simple Cache magic. Only HTTP caches are
+// returned; Code Cache and friends share the format but hold no media.
+
+/// Event name carrying a `DriveScanProgress` payload.
+pub const DRIVE_SCAN_PROGRESS_EVENT: &str = "drive-scan-progress";
+
+/// Deep enough for `<drive>/Users/<name>/AppData/Local/<vendor>/<app>/User Data/<profile>/Cache/Cache_Data`
+/// with room for a backup folder or two on top.
+const MAX_DEPTH: usize = 16;
+/// OS folders skipped directly under the root (they hold no user caches).
+const ROOT_SKIP_DIRS: &[&str] = &["windows", "program files", "program files (x86)", "proc", "sys", "dev"];
+/// Folders never worth entering, at any depth.
+const SKIP_DIRS: &[&str] = &[
+    "$recycle.bin",
+    "system volume information",
+    "node_modules",
+    ".git",
+    ".trash",
+    ".trashes",
+    ".spotlight-v100",
+    ".fseventsd",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriveScanProgress {
+    pub root: String,
+    pub dirs_visited: usize,
+    pub caches_found: usize,
+    /// Folder being searched
+    pub current: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DriveScanResult {
+    pub root: String,
+    pub caches: Vec<CachePathInfo>,
+    pub dirs_visited: usize,
+    /// Folders that could not be read (permissions, I/O errors), capped
+    pub unreadable: Vec<String>,
+}
+
+const MAX_REPORTED_ERRORS: usize = 50;
+
+fn skip(name: &str, depth: usize) -> bool {
+    let lower = name.to_lowercase();
+    SKIP_DIRS.contains(&lower.as_str()) || (depth == 0 && ROOT_SKIP_DIRS.contains(&lower.as_str()))
+}
+
+/// Depth-first walk collecting cache folders. Symlinks are not followed, and
+/// a folder found to be a cache is not searched further.
+fn walk(root: &Path, on_progress: &mut dyn FnMut(&DriveScanProgress)) -> DriveScanResult {
+    let mut result = DriveScanResult {
+        root: root.to_string_lossy().to_string(),
+        caches: Vec::new(),
+        dirs_visited: 0,
+        unreadable: Vec::new(),
+    };
+    let mut stack: Vec<(PathBuf, usize)> = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = stack.pop() {
+        result.dirs_visited += 1;
+        if crate::cache::holds_chromium_cache(&dir) {
+            let path = dir.to_string_lossy().to_string();
+            match crate::cache::validate_cache_path(&path) {
+                Ok(info) if info.kind == "http_cache" => result.caches.push(info),
+                Ok(_) => {}
+                Err(e) => result.unreadable.push(e),
+            }
+            continue;
+        }
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                if result.unreadable.len() < MAX_REPORTED_ERRORS {
+                    result.unreadable.push(format!("{}: {}", dir.display(), e));
+                }
+                continue;
+            }
+        };
+        if depth < MAX_DEPTH {
+            let mut children: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+                .filter(|e| !skip(&e.file_name().to_string_lossy(), depth))
+                .map(|e| e.path())
+                .collect();
+            // Reverse so the stack visits children in name order.
+            children.sort_by(|a, b| b.cmp(a));
+            stack.extend(children.into_iter().map(|c| (c, depth + 1)));
+        }
+        on_progress(&DriveScanProgress {
+            root: result.root.clone(),
+            dirs_visited: result.dirs_visited,
+            caches_found: result.caches.len(),
+            current: dir.to_string_lossy().to_string(),
+        });
+    }
+    result
+}
+
+/// Walk `root` (a drive, mount point or any folder) for Chromium HTTP
+/// caches, emitting `drive-scan-progress` as it goes.
+#[tauri::command(async)]
+pub fn scan_drive_for_caches(app: tauri::AppHandle, root: String) -> Result<DriveScanResult, String> {
+    let root_path = PathBuf::from(crate::path_display::expand_path(&root));
+    if !root_path.is_dir() {
+        return Err(format!("Not a folder: {}", root_path.display()));
+    }
+    let _job = crate::crash::job(format!("searching {} for caches", root_path.display()));
+    let mut events = Coalescer::for_app(app, DRIVE_SCAN_PROGRESS_EVENT);
+    let result = walk(&root_path, &mut |progress| events.push(progress.clone()));
+    println!(
+        "[drive_scan] {}: {} caches in {} folders ({} unreadable)",
+        result.root,
+        result.caches.len(),
+        result.dirs_visited,
+        result.unreadable.len()
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_caches_under_another_users_profile() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-drive-{}", std::process::id()));
+        let profile = root.join("Users/old-pc/AppData/Roaming/discord");
+        let http = profile.join("Cache/Cache_Data");
+        let code = profile.join("Code Cache/js");
+        std::fs::create_dir_all(&http).unwrap();
+        std::fs::create_dir_all(&code).unwrap();
+        std::fs::create_dir_all(root.join("$Recycle.Bin/Cache")).unwrap();
+        for dir in [&http, &code, &root.join("$Recycle.Bin/Cache")] {
+            std::fs::write(dir.join("index"), b"").unwrap();
+            std::fs::write(dir.join("data_0"), b"").unwrap();
+        }
+
+        let mut events = 0;
+        let result = walk(&root, &mut |_| events += 1);
+        let _ = std::fs::remove_dir_all(&root);
+        let found: Vec<&str> = result.caches.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(found, vec![http.to_string_lossy().as_ref()]);
+        assert!(events > 0);
+    }
+}
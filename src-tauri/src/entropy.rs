@@ -0,0 +1,138 @@
+use serde::Serialize;
+
+// ─── Encrypted body detection ──────────────────────────────────────────────
+//
+// Some apps (Signal Desktop, notably) cache attachment blobs that are
+// encrypted before they reach the HTTP cache. Those bodies have no magic
+// bytes, so without a check they fall through to the unknown-chunk grouping
+// and come out as broken files. Ciphertext is the only thing in a cache that
+// is byte-for-byte uniform: compressed media (JPEG, H.264, deflate) gets close
+// on entropy but keeps enough structure to fail a chi-square test.
+
+/// Bytes sampled from the start of each body.
+const SAMPLE_SIZE: usize = 64 * 1024;
+/// Below this the statistics are too noisy to call.
+const MIN_SAMPLE: usize = 4 * 1024;
+/// Bits per byte; compressed media usually sits at 7.6–7.97.
+const MIN_ENTROPY: f64 = 7.98;
+/// Chi-square over 256 byte values (255 dof): uniform data lands near 255
+/// and stays below this at the 99.9% level; structured data is far above.
+const MAX_CHI_SQUARE: f64 = 330.0;
+
+#[derive(Debug, Serialize)]
+pub struct EncryptedCheck {
+    pub path: String,
+    /// Shannon entropy of the sample, bits per byte
+    pub entropy: f64,
+    pub chi_square: f64,
+    pub encrypted: bool,
+}
+
+fn histogram(data: &[u8]) -> [u64; 256] {
+    let mut counts = [0u64; 256];
+    for &b in data {
+        counts[b as usize] += 1;
+    }
+    counts
+}
+
+/// Shannon entropy in bits per byte (0.0 for empty input).
+pub fn shannon(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let len = data.len() as f64;
+    histogram(data)
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Chi-square statistic of the byte distribution against uniform.
+pub fn chi_square(data: &[u8]) -> f64 {
+    let expected = data.len() as f64 / 256.0;
+    if expected == 0.0 {
+        return 0.0;
+    }
+    histogram(data)
+        .iter()
+        .map(|&c| {
+            let d = c as f64 - expected;
+            d * d / expected
+        })
+        .sum()
+}
+
+/// True if `sample` is statistically indistinguishable from random bytes.
+pub fn looks_encrypted(sample: &[u8]) -> bool {
+    sample.len() >= MIN_SAMPLE && shannon(sample) >= MIN_ENTROPY && chi_square(sample) < MAX_CHI_SQUARE
+}
+
+fn check_one(path: &str) -> Result<EncryptedCheck, String> {
+    let sample = if crate::is_simple_cache_sparse(path) {
+        crate::read_sparse_cache_header(path.to_string(), SAMPLE_SIZE)?
+    } else {
+        crate::read_file_header(path.to_string(), SAMPLE_SIZE)?
+    };
+    Ok(EncryptedCheck {
+        path: path.to_string(),
+        entropy: shannon(&sample),
+        chi_square: chi_square(&sample),
+        encrypted: looks_encrypted(&sample),
+    })
+}
+
+/// Sample the body of each cache file and report which ones look encrypted.
+/// Unreadable files are skipped.
+#[tauri::command(async)]
+pub fn check_encrypted_bodies(paths: Vec<String>) -> Vec<EncryptedCheck> {
+    let results: Vec<EncryptedCheck> = paths
+        .iter()
+        .filter_map(|path| match check_one(path) {
+            Ok(check) => Some(check),
+            Err(e) => {
+                eprintln!("[entropy] {}", e);
+                None
+            }
+        })
+        .collect();
+    println!(
+        "[entropy] {} of {} bodies look encrypted",
+        results.iter().filter(|c| c.encrypted).count(),
+        paths.len()
+    );
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_random_from_structured() {
+        // xorshift64*: uniform enough to stand in for ciphertext
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let random: Vec<u8> = (0..SAMPLE_SIZE)
+            .map(|_| {
+                state ^= state >> 12;
+                state ^= state << 25;
+                state ^= state >> 27;
+                (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+            })
+            .collect();
+        assert!(looks_encrypted(&random));
+
+        // High entropy but skewed, like compressed media
+        let skewed: Vec<u8> = random.iter().map(|&b| if b < 16 { 0 } else { b }).collect();
+        assert!(shannon(&skewed) > 7.5);
+        assert!(!looks_encrypted(&skewed));
+
+        let text = b"GET /attachments/clip.mp4 HTTP/1.1\r\n".repeat(2000);
+        assert!(!looks_encrypted(&text));
+        assert!(!looks_encrypted(&random[..1024]));
+    }
+}
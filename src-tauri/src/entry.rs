@@ -0,0 +1,320 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::cache::CacheFileEntry;
+
+// ─── Whole-entry extraction ─────────────────────────────────────────────────
+//
+// A Simple Cache entry is up to three files sharing one 16-hex hash: `_0`
+// (headers in stream 0, small bodies in stream 1), `_1` (the body of large
+// resources) and `_s` (sparse ranges of a range download). Large resources
+// leave stream 1 empty and put the body in `_1`, so copying the `_0` alone
+// yields nothing. `read_entry` / `copy_entry` accept any of the files and
+// return the headers plus the body from wherever it actually lives.
+// `group_cache_entries` does the same for a whole folder: files are grouped
+// by the URL key stored in each of them, so one logical entry is listed once
+// with its combined size and the stream its body should be read from.
+
+#[derive(Debug, Serialize)]
+pub struct EntryBody {
+    /// Files of this entry that exist on disk
+    pub files: Vec<String>,
+    /// Status line and headers from stream 0; empty without a `_0`
+    pub headers: Vec<String>,
+    /// Lowercase MIME type, parameters stripped
+    pub content_type: Option<String>,
+    /// "stream1" (`_0`), "stream2" (`_1`) or "sparse" (`_s`); None if every
+    /// stream is empty
+    pub body_source: Option<&'static str>,
+    pub body: Vec<u8>,
+}
+
+/// One logical Simple Cache entry: the `_0`, `_1` and `_s` files of one key.
+#[derive(Debug, Serialize)]
+pub struct CacheEntryGroup {
+    /// Cache key as stored (may carry an isolation prefix); for files whose
+    /// key can't be read, "#" plus the file hash
+    pub key: String,
+    /// The key's URL, isolation prefix stripped
+    pub url: String,
+    /// Top frame and frame site of a double-keyed key; empty otherwise
+    pub isolation_sites: Vec<String>,
+    pub hash: String,
+    pub stream0_file: Option<String>,
+    pub stream2_file: Option<String>,
+    pub sparse_file: Option<String>,
+    /// Sum of the files' sizes
+    pub total_size: u64,
+    /// Latest modification time of the files, Unix seconds
+    pub modified_at: f64,
+    /// Lowercase MIME type from stream 0, parameters stripped
+    pub content_type: Option<String>,
+    /// HTTP status code and Content-Length from stream 0
+    pub http_status: Option<u16>,
+    pub content_length: Option<u64>,
+    /// Content-Disposition filename, if the response named one
+    pub original_filename: Option<String>,
+    /// Where the body is: "stream1" (`_0`), "stream2" (`_1`) or "sparse" (`_s`)
+    pub body_source: Option<&'static str>,
+    pub body_file: Option<String>,
+    /// Body bytes in that stream; for `_s` the file size less its header,
+    /// an upper bound on the ranges present
+    pub body_size: u64,
+}
+
+/// Bytes of a `_1` / `_s` file past its header and key.
+fn payload_size(file: &CacheFileEntry, key_len: usize) -> u64 {
+    file.size.saturating_sub((crate::SIMPLE_CACHE_HEADER_SIZE + key_len) as u64)
+}
+
+/// Group a folder listing into logical entries, in listing order of each
+/// entry's first file. Files that aren't Simple Cache entry files are left out.
+pub fn group_entries(files: &[CacheFileEntry]) -> Vec<CacheEntryGroup> {
+    let mut order: Vec<String> = Vec::new();
+    let mut members: HashMap<String, Vec<&CacheFileEntry>> = HashMap::new();
+    for file in files {
+        let Some((_, hash)) = entry_base(&file.path) else {
+            continue;
+        };
+        let key = crate::read_simple_cache_key(&file.path).unwrap_or_else(|| format!("#{}", hash));
+        if !members.contains_key(&key) {
+            order.push(key.clone());
+        }
+        members.entry(key).or_default().push(file);
+    }
+
+    order
+        .into_iter()
+        .map(|key| {
+            let files = members.remove(&key).unwrap_or_default();
+            let find = |suffix: &str| files.iter().find(|f| f.name.to_ascii_lowercase().ends_with(suffix)).copied();
+            let (stream0, stream2, sparse) = (find("_0"), find("_1"), find("_s"));
+            let hash = files[0].name[..16].to_string();
+            let key_len = if key.starts_with('#') { 0 } else { key.len() };
+            let mut group = CacheEntryGroup {
+                url: crate::cache_key::effective_url(&key).to_string(),
+                isolation_sites: crate::cache_key::isolation_sites(&key).into_iter().map(String::from).collect(),
+                hash,
+                stream0_file: stream0.map(|f| f.path.clone()),
+                stream2_file: stream2.map(|f| f.path.clone()),
+                sparse_file: sparse.map(|f| f.path.clone()),
+                total_size: files.iter().map(|f| f.size).sum(),
+                modified_at: files.iter().map(|f| f.modified_at).fold(0.0, f64::max),
+                content_type: None,
+                http_status: None,
+                content_length: None,
+                original_filename: None,
+                body_source: None,
+                body_file: None,
+                body_size: 0,
+                key,
+            };
+            // Same preference as `load_entry`: stream 1, then `_1`, then `_s`.
+            if let Some(file) = stream0 {
+                let _permit = crate::io_limit::acquire_for(&file.path);
+                if let Ok(data) = crate::read_with_lock_retry(&file.path) {
+                    let info = crate::extract_simple_cache_headers(&data).and_then(|s| crate::http_info::parse(&s));
+                    if let Some(info) = info {
+                        group.content_type = info.header("content-type").map(crate::mime_category::essence);
+                        group.http_status = info.status_code;
+                        group.content_length = info.header("content-length").and_then(|v| v.trim().parse().ok());
+                        group.original_filename =
+                            info.header("content-disposition").and_then(crate::content_disposition::filename);
+                    }
+                    if let Some(layout) = crate::parse_simple_cache_layout(&data) {
+                        group.body_size = (layout.stream1_end - layout.stream1_start) as u64;
+                        if group.body_size > 0 {
+                            group.body_source = Some("stream1");
+                            group.body_file = Some(file.path.clone());
+                        }
+                    }
+                }
+            }
+            // `_1` ends in an EOF record; `_s` range headers are counted as body.
+            let eof = crate::SIMPLE_CACHE_EOF_SIZE as u64;
+            for (file, source, trailer) in [(stream2, "stream2", eof), (sparse, "sparse", 0)] {
+                let Some(file) = file.filter(|_| group.body_source.is_none()) else {
+                    continue;
+                };
+                let size = payload_size(file, key_len).saturating_sub(trailer);
+                if size > 0 {
+                    group.body_source = Some(source);
+                    group.body_file = Some(file.path.clone());
+                    group.body_size = size;
+                }
+            }
+            group
+        })
+        .collect()
+}
+
+/// Directory and hash prefix of a `{16 hex}_{0,1,s}` entry file.
+fn entry_base(path: &str) -> Option<(PathBuf, String)> {
+    let p = Path::new(path);
+    let name = p.file_name()?.to_str()?;
+    let valid = name.len() == 18
+        && name[..16].chars().all(|c| c.is_ascii_hexdigit())
+        && matches!(&name[16..].to_ascii_lowercase()[..], "_0" | "_1" | "_s");
+    if !valid {
+        return None;
+    }
+    Some((p.parent().unwrap_or(Path::new("")).to_path_buf(), name[..16].to_string()))
+}
+
+/// The entry's file for `suffix` ("0", "1" or "s"), if it exists.
+fn sibling(dir: &Path, hash: &str, suffix: &str) -> Option<String> {
+    let path = dir.join(format!("{}_{}", hash, suffix));
+    path.is_file().then(|| path.to_string_lossy().to_string())
+}
+
+/// The file for `suffix` of the entry `path` belongs to, if it exists.
+pub(crate) fn sibling_file(path: &str, suffix: &str) -> Option<String> {
+    let (dir, hash) = entry_base(path)?;
+    sibling(&dir, &hash, suffix)
+}
+
+/// Collect headers and body of the entry `path` belongs to, undoing a
+/// Content-Encoding (gzip, br, ...) if `decode_content`.
+pub fn load_entry(path: &str, decode_content: bool) -> Result<EntryBody, String> {
+    let (dir, hash) = entry_base(path).ok_or_else(|| format!("Not a Simple Cache entry file: {}", path))?;
+    let stream0_file = sibling(&dir, &hash, "0");
+    let stream2_file = sibling(&dir, &hash, "1");
+    let sparse_file = sibling(&dir, &hash, "s");
+    let mut entry = EntryBody {
+        files: [&stream0_file, &stream2_file, &sparse_file].into_iter().flatten().cloned().collect(),
+        headers: Vec::new(),
+        content_type: None,
+        body_source: None,
+        body: Vec::new(),
+    };
+    if entry.files.is_empty() {
+        return Err(format!("No entry files found for {}", path));
+    }
+
+    if let Some(file) = &stream0_file {
+        let _permit = crate::io_limit::acquire_for(file);
+        let data = crate::read_with_lock_retry(file).map_err(|e| crate::format_read_error(file, &e))?;
+        if let Some(info) = crate::extract_simple_cache_headers(&data).and_then(|s| crate::http_info::parse(&s)) {
+            entry.content_type =
+                info.header("content-type").map(|ct| ct.split(';').next().unwrap_or("").trim().to_lowercase());
+            entry.headers = info
+                .status_line
+                .into_iter()
+                .chain(info.headers.iter().map(|h| format!("{}: {}", h.name, h.value)))
+                .collect();
+        }
+        if let Some(layout) = crate::parse_simple_cache_layout(&data) {
+            let encoding = if decode_content { crate::content_encoding::encoding_of(file, &data) } else { None };
+            let body = data[layout.stream1_start..layout.stream1_end].to_vec();
+            entry.body = crate::content_encoding::decode_body(file, encoding.as_deref(), body);
+            entry.body_source = Some("stream1");
+        }
+    }
+    // Empty stream 1: the body is in `_1`, or in `_s` for range downloads.
+    for (file, source) in [(&stream2_file, "stream2"), (&sparse_file, "sparse")] {
+        if !entry.body.is_empty() {
+            break;
+        }
+        if let Some(file) = file {
+            let _permit = crate::io_limit::acquire_for(file);
+            let body = crate::read_cache_body_with(file, decode_content)?;
+            if !body.is_empty() {
+                println!("[entry] {}: stream 1 empty, body from {} ({} bytes)", hash, file, body.len());
+                entry.body = body;
+                entry.body_source = Some(source);
+            }
+        }
+    }
+    if entry.body.is_empty() {
+        entry.body_source = None;
+    }
+    Ok(entry)
+}
+
+/// Headers and complete (decoded) body of the Simple Cache entry `path`
+/// belongs to (`_0`, `_1` or `_s`), combining the entry's files.
+#[tauri::command(async)]
+pub fn read_entry(path: String) -> Result<EntryBody, String> {
+    load_entry(&path, true)
+}
+
+/// Write the complete body of the entry `src` belongs to into `dst`, decoded
+/// unless `decode_content` is false. Returns the bytes written. Requires a
+/// `consent` token covering `dst`.
+#[tauri::command(async)]
+pub fn copy_entry(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    src: String,
+    dst: String,
+    decode_content: Option<bool>,
+    consent: String,
+) -> Result<u64, String> {
+    let dst = state.check(&consent, &dst)?;
+    let entry = load_entry(&src, decode_content.unwrap_or(true))?;
+    if entry.body_source.is_none() {
+        return Err(format!("Entry has no body in any of its files: {}", src));
+    }
+    crate::output_file::write_output(&dst, &entry.body)
+}
+
+/// List `dir` (minus files the saved ignore rules skip) as logical Simple
+/// Cache entries, one per key.
+#[tauri::command(async)]
+pub fn group_cache_entries(app: tauri::AppHandle, dir: String) -> Result<Vec<CacheEntryGroup>, String> {
+    let dir = crate::path_display::expand_path(&dir);
+    let _job = crate::crash::job(format!("grouping entries of {}", dir));
+    let mut files = crate::cache::list_cache_files(&dir).map_err(|e| e.to_string())?;
+    crate::ignore_rules::apply_saved(&app, &dir, &mut files);
+    let groups = group_entries(&files);
+    // The scan goes on to sniff and thumbnail every entry; likely videos
+    // are announced now.
+    let mut hot = crate::hot_items::HotItemNotifier::for_app(app);
+    groups.iter().for_each(|group| hot.offer_group(group));
+    hot.flush();
+    if hot.total() > 0 {
+        println!("[group_cache_entries] Announced {} hot items early", hot.total());
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureCache;
+
+    #[test]
+    fn body_comes_from_stream2_when_stream1_is_empty() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-entry-{}", std::process::id()));
+        let cache = FixtureCache::create(&dir).unwrap();
+        let big = "https://cdn.discordapp.com/attachments/1/2/big.mp4";
+        let headers_file =
+            cache.add_entry(big, &["HTTP/1.1 200 OK", "Content-Type: video/mp4; codecs=avc1"], b"").unwrap();
+        let body_file = cache.add_stream2_entry(big, b"the real body").unwrap();
+        let small = "https://cdn.discordapp.com/attachments/1/2/small.png";
+        let small_file = cache.add_entry(small, &["HTTP/1.1 200 OK"], b"inline").unwrap();
+
+        let from_headers = load_entry(&headers_file, true).unwrap();
+        let from_body = load_entry(&body_file, true).unwrap();
+        let inline = load_entry(&small_file, true).unwrap();
+        let groups = group_entries(&crate::cache::list_cache_files(&dir.to_string_lossy()).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let big_group = groups.iter().find(|g| g.url == big).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!((big_group.body_source, big_group.body_size), (Some("stream2"), 13));
+        assert_eq!(big_group.body_file.as_deref(), Some(body_file.as_str()));
+        assert_eq!(big_group.content_type.as_deref(), Some("video/mp4"));
+        assert_eq!((big_group.http_status, big_group.content_length), (Some(200), None));
+        // A 13-byte "video" is no early find.
+        assert!(crate::hot_items::classify_group(big_group).is_none());
+
+        assert_eq!(from_headers.body, b"the real body");
+        assert_eq!(from_headers.body_source, Some("stream2"));
+        assert_eq!(from_headers.content_type.as_deref(), Some("video/mp4"));
+        assert_eq!(from_headers.headers[0], "HTTP/1.1 200 OK");
+        assert_eq!((from_body.files.len(), from_body.body.len()), (2, 13));
+        assert_eq!((inline.body_source, inline.body.as_slice()), (Some("stream1"), &b"inline"[..]));
+        assert!(load_entry("/tmp/not-an-entry.bin", true).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::custom_paths::{read_json, write_json};
+
+// Backpressure-aware event emission.
+//
+// Long-running commands report to the frontend through Tauri events. Emitting
+// one event per parsed entry floods the webview's IPC channel and its render
+// loop, so every event goes through a `Coalescer`: at most `max_per_sec`
+// events per job (one coalescer per command invocation), with items queued in
+// between either replaced (latest state wins — progress) or batched (every
+// item matters — hot items). Each event type has a default policy in
+// `default_policy`; its rate and batch size can be overridden with
+// `set_event_policy` and are saved across restarts. Whether an event is
+// `Latest` or `Batch` is fixed, since that decides the payload's shape.
+
+/// Event name carrying an `IndexProgress` payload.
+pub const INDEX_PROGRESS_EVENT: &str = "index-parse-progress";
+const POLICIES_FILE: &str = "event_policies.json";
+/// Fastest rate an override may set.
+const MAX_RATE: f64 = 1000.0;
+
+/// Events whose policy can be overridden.
+const EVENTS: &[&str] = &[
+    INDEX_PROGRESS_EVENT,
+    crate::hot_items::HOT_ITEMS_EVENT,
+    crate::sparse_watch::SPARSE_COVERAGE_EVENT,
+    crate::watchdog::STALL_EVENT,
+    crate::crash::CRASH_EVENT,
+];
+
+/// What happens to items queued while the rate limit holds emission back.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Coalesce {
+    /// Keep only the newest item; the payload is that item.
+    Latest,
+    /// Keep all items; the payload is a `Vec`. Flushes early at `max_batch`.
+    Batch { max_batch: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EventPolicy {
+    pub max_per_sec: f64,
+    pub mode: Coalesce,
+}
+
+impl EventPolicy {
+    fn interval(&self) -> Duration {
+        if self.max_per_sec > 0.0 {
+            Duration::from_secs_f64(1.0 / self.max_per_sec)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// Built-in rate limit and coalescing mode for each event type.
+fn default_policy(event: &str) -> EventPolicy {
+    match event {
+        INDEX_PROGRESS_EVENT => EventPolicy { max_per_sec: 10.0, mode: Coalesce::Latest },
+        crate::hot_items::HOT_ITEMS_EVENT => EventPolicy {
+            max_per_sec: 4.0,
+            mode: Coalesce::Batch { max_batch: 50 },
+        },
+        crate::sparse_watch::SPARSE_COVERAGE_EVENT => EventPolicy { max_per_sec: 2.0, mode: Coalesce::Latest },
+        // The full stalled list each time; only the newest matters.
+        crate::watchdog::STALL_EVENT => EventPolicy { max_per_sec: 1.0, mode: Coalesce::Latest },
+        // Every report matters, and a panicking loop shouldn't bury the UI.
+        crate::crash::CRASH_EVENT => EventPolicy {
+            max_per_sec: 1.0,
+            mode: Coalesce::Batch { max_batch: 20 },
+        },
+        _ => EventPolicy { max_per_sec: 5.0, mode: Coalesce::Latest },
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PolicyFile {
+    policies: HashMap<String, EventPolicy>,
+}
+
+/// Overrides in effect; loaded at startup and changed by `set_event_policy`.
+static OVERRIDES: RwLock<Option<HashMap<String, EventPolicy>>> = RwLock::new(None);
+
+/// Rate limit and coalescing mode for each event type: the saved override,
+/// else the default.
+pub fn policy_for(event: &str) -> EventPolicy {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    overrides.as_ref().and_then(|o| o.get(event).copied()).unwrap_or_else(|| default_policy(event))
+}
+
+/// `policy` as an override for `event`: a known event, a sane rate, and the
+/// default's mode (so listeners keep getting the payload shape they expect).
+fn validate(event: &str, policy: &EventPolicy) -> Result<(), String> {
+    if !EVENTS.contains(&event) {
+        return Err(format!("Unknown event \"{}\" (expected one of {})", event, EVENTS.join(", ")));
+    }
+    if !(policy.max_per_sec > 0.0 && policy.max_per_sec <= MAX_RATE) {
+        return Err(format!("Rate must be above 0 and at most {} events per second", MAX_RATE));
+    }
+    match (default_policy(event).mode, policy.mode) {
+        (Coalesce::Latest, Coalesce::Latest) => Ok(()),
+        (Coalesce::Batch { .. }, Coalesce::Batch { max_batch }) if max_batch >= 1 => Ok(()),
+        (Coalesce::Batch { .. }, Coalesce::Batch { .. }) => Err("Batches need room for at least one item".to_string()),
+        _ => Err(format!("{} keeps its coalescing mode; only rate and batch size change", event)),
+    }
+}
+
+/// Load the saved overrides; an unreadable file is logged and overrides nothing.
+pub fn load_saved(app: &tauri::AppHandle) {
+    let loaded = crate::custom_paths::config_file(app, POLICIES_FILE).and_then(|f| read_json::<PolicyFile>(&f));
+    match loaded {
+        Ok(file) => {
+            let valid = file.policies.into_iter().filter(|(event, policy)| match validate(event, policy) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("[events] Ignoring saved policy for {}: {}", event, e);
+                    false
+                }
+            });
+            *OVERRIDES.write().unwrap_or_else(|e| e.into_inner()) = Some(valid.collect());
+        }
+        Err(e) => eprintln!("[events] {}", e),
+    }
+}
+
+/// An event type's policy and whether it is overridden.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPolicyEntry {
+    pub event: &'static str,
+    pub policy: EventPolicy,
+    pub overridden: bool,
+}
+
+/// The policy in effect for each event type.
+#[tauri::command]
+pub fn list_event_policies() -> Vec<EventPolicyEntry> {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    EVENTS
+        .iter()
+        .map(|&event| {
+            let saved = overrides.as_ref().and_then(|o| o.get(event).copied());
+            let policy = saved.unwrap_or_else(|| default_policy(event));
+            EventPolicyEntry { event, policy, overridden: saved.is_some() }
+        })
+        .collect()
+}
+
+/// Override `event`'s policy, or go back to the default with `None`. Running
+/// jobs pick the new rate up with their next item. Returns the policy now in
+/// effect.
+#[tauri::command]
+pub fn set_event_policy(
+    app: tauri::AppHandle,
+    event: String,
+    policy: Option<EventPolicy>,
+) -> Result<EventPolicy, String> {
+    if let Some(policy) = &policy {
+        validate(&event, policy)?;
+    } else if !EVENTS.contains(&event.as_str()) {
+        return Err(format!("Unknown event \"{}\"", event));
+    }
+    let file = crate::custom_paths::config_file(&app, POLICIES_FILE)?;
+    let mut current = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    let mut policies = current.clone().unwrap_or_default();
+    match policy {
+        Some(policy) => policies.insert(event.clone(), policy),
+        None => policies.remove(&event),
+    };
+    write_json(&file, &PolicyFile { policies: policies.clone() })?;
+    *current = Some(policies);
+    drop(current);
+    Ok(policy_for(&event))
+}
+
+/// Progress of a blockfile index parse.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexProgress {
+    /// "entries" while decoding entries, "linking" while attaching sparse children
+    pub phase: &'static str,
+    pub current: usize,
+    pub total: usize,
+}
+
+/// Rate-limits one job's stream of items for a single event type.
+/// The first item goes out immediately; anything still queued is sent by
+/// `flush`, which also runs on drop, so the final state is never lost.
+pub struct Coalescer<T> {
+    policy: EventPolicy,
+    /// The event whose (possibly changed) policy to follow; None keeps `policy`
+    event: Option<&'static str>,
+    emit: Box<dyn FnMut(Vec<T>) + Send>,
+    pending: Vec<T>,
+    last_emit: Option<Instant>,
+    emitted: usize,
+    dropped: usize,
+}
+
+impl<T> Coalescer<T> {
+    /// `emit` receives the queued items: always exactly one in `Latest` mode.
+    pub fn new(policy: EventPolicy, emit: impl FnMut(Vec<T>) + Send + 'static) -> Self {
+        Self {
+            policy,
+            event: None,
+            emit: Box::new(emit),
+            pending: Vec::new(),
+            last_emit: None,
+            emitted: 0,
+            dropped: 0,
+        }
+    }
+
+    /// The policy now in effect; overrides never change the mode.
+    fn policy(&self) -> EventPolicy {
+        self.event.map_or(self.policy, policy_for)
+    }
+
+    pub fn push(&mut self, item: T) {
+        let policy = self.policy();
+        match policy.mode {
+            Coalesce::Latest => {
+                if self.pending.pop().is_some() {
+                    self.dropped += 1;
+                }
+                self.pending.push(item);
+            }
+            Coalesce::Batch { max_batch } => {
+                self.pending.push(item);
+                if self.pending.len() >= max_batch {
+                    self.flush();
+                    return;
+                }
+            }
+        }
+        if self.last_emit.is_none_or(|t| t.elapsed() >= policy.interval()) {
+            self.flush();
+        }
+    }
+
+    /// Emit what is queued if the rate limit allows it by now. For
+    /// long-lived coalescers whose items come rarely, so the last one
+    /// doesn't wait for the next.
+    pub fn tick(&mut self) {
+        if self.last_emit.is_none_or(|t| t.elapsed() >= self.policy().interval()) {
+            self.flush();
+        }
+    }
+
+    /// Emit whatever is still queued, ignoring the rate limit.
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        (self.emit)(std::mem::take(&mut self.pending));
+        self.last_emit = Some(Instant::now());
+        self.emitted += 1;
+    }
+
+    /// Number of events sent so far.
+    pub fn emitted(&self) -> usize {
+        self.emitted
+    }
+
+    /// Number of `Latest` items replaced before they could be sent.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl<T: Serialize + Clone + Send + 'static> Coalescer<T> {
+    /// Coalescer emitting `event` to the frontend under its configured policy.
+    pub fn for_app(app: tauri::AppHandle, event: &'static str) -> Self {
+        use tauri::Emitter;
+        let policy = policy_for(event);
+        let mut coalescer = Self::new(policy, move |mut items: Vec<T>| {
+            let result = match policy.mode {
+                Coalesce::Latest => match items.pop() {
+                    Some(item) => app.emit(event, item),
+                    None => Ok(()),
+                },
+                Coalesce::Batch { .. } => app.emit(event, items),
+            };
+            if let Err(e) = result {
+                eprintln!("[events] Failed to emit {}: {}", event, e);
+            }
+        });
+        coalescer.event = Some(event);
+        coalescer
+    }
+}
+
+impl<T> Drop for Coalescer<T> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    type Sent<T> = Arc<Mutex<Vec<Vec<T>>>>;
+
+    fn recording<T: Send + 'static>(policy: EventPolicy) -> (Coalescer<T>, Sent<T>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sink = sent.clone();
+        let c = Coalescer::new(policy, move |items| sink.lock().unwrap().push(items));
+        (c, sent)
+    }
+
+    #[test]
+    fn latest_state_wins() {
+        let (mut c, sent) = recording(EventPolicy { max_per_sec: 0.001, mode: Coalesce::Latest });
+        for i in 0..100 {
+            c.push(i);
+        }
+        c.flush();
+        assert_eq!(*sent.lock().unwrap(), vec![vec![0], vec![99]]);
+        assert_eq!(c.dropped(), 98);
+    }
+
+    #[test]
+    fn batches_flush_when_full_and_on_drop() {
+        let (mut c, sent) = recording(EventPolicy {
+            max_per_sec: 0.001,
+            mode: Coalesce::Batch { max_batch: 3 },
+        });
+        for i in 0..6 {
+            c.push(i);
+        }
+        drop(c);
+        assert_eq!(*sent.lock().unwrap(), vec![vec![0], vec![1, 2, 3], vec![4, 5]]);
+    }
+
+    #[test]
+    fn overrides_keep_the_mode() {
+        let batch = EventPolicy { max_per_sec: 20.0, mode: Coalesce::Batch { max_batch: 10 } };
+        assert!(validate(crate::hot_items::HOT_ITEMS_EVENT, &batch).is_ok());
+        let latest = EventPolicy { max_per_sec: 20.0, mode: Coalesce::Latest };
+        assert!(validate(crate::hot_items::HOT_ITEMS_EVENT, &latest).unwrap_err().contains("coalescing mode"));
+        assert!(validate(INDEX_PROGRESS_EVENT, &EventPolicy { max_per_sec: 0.0, ..latest }).is_err());
+        assert!(validate("no-such-event", &latest).unwrap_err().contains("Unknown event"));
+        let json = serde_json::to_string(&batch).unwrap();
+        assert_eq!(json, r#"{"max_per_sec":20.0,"mode":{"kind":"batch","max_batch":10}}"#);
+    }
+}
@@ -0,0 +1,175 @@
+// ─── Eviction risk ──────────────────────────────────────────────────────────
+//
+// Chromium's blockfile backend trims the cache once `num_bytes` exceeds the
+// configured max size (see net/disk_cache/blockfile/eviction.cc). With the
+// "new eviction" algorithm entries live in one of three LRU lists chosen by
+// reuse count, and trimming walks the lists in order NO_USE -> LOW_USE ->
+// HIGH_USE, oldest entry first. Large entries are the cheapest way to free
+// space, so old + large + never-reused entries disappear first.
+//
+// The Simple Cache backend has no LRU lists: when over its limit it sorts all
+// entries by last-used time weighted by size (simple_index.cc) and removes
+// from the front. `assess_simple` models that.
+//
+// We don't know the exact max size Chromium picked (it scales with free disk
+// space), so callers may pass one; otherwise kDefaultCacheSize is assumed.
+
+use serde::Serialize;
+
+/// Chromium's `kDefaultCacheSize` (80 MiB). Real caches on large disks may be
+/// allowed more, which only lowers the actual risk.
+pub const DEFAULT_MAX_CACHE_BYTES: u64 = 80 * 1024 * 1024;
+
+/// Reuse count at which an entry moves to the HIGH_USE list (`kHighUse` in eviction.cc).
+const HIGH_USE_REUSE_COUNT: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvictionRisk {
+    /// 0.0 (safe) .. 1.0 (next in line for eviction)
+    pub score: f64,
+    pub level: RiskLevel,
+    /// LRU list the entry sits on: "no_use", "low_use" or "high_use";
+    /// "simple" for Simple Cache entries, which have a single list
+    pub lru_list: &'static str,
+}
+
+/// Per-entry data needed to estimate risk.
+pub struct RiskInput {
+    /// Last time the entry was used (unix seconds); creation/response time if unknown.
+    pub last_used: Option<f64>,
+    pub size: u64,
+    pub reuse_count: u32,
+}
+
+fn lru_list(reuse_count: u32) -> (&'static str, f64) {
+    if reuse_count == 0 {
+        ("no_use", 1.0)
+    } else if reuse_count < HIGH_USE_REUSE_COUNT {
+        ("low_use", 0.6)
+    } else {
+        ("high_use", 0.25)
+    }
+}
+
+/// Fractional rank of each value (0.0 = smallest, 1.0 = largest).
+pub(crate) fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let denom = values.len().saturating_sub(1).max(1) as f64;
+    let mut out = vec![0.0; values.len()];
+    for (rank, idx) in order.into_iter().enumerate() {
+        out[idx] = rank as f64 / denom;
+    }
+    out
+}
+
+/// How full the cache is, 0.0..1.0. Eviction only runs near the limit; a
+/// half-empty cache keeps even stale entries around.
+fn pressure(cache_bytes: u64, max_bytes: u64) -> f64 {
+    if max_bytes == 0 {
+        1.0
+    } else {
+        (cache_bytes as f64 / max_bytes as f64).clamp(0.0, 1.0)
+    }
+}
+
+fn level(score: f64) -> RiskLevel {
+    if score >= 0.66 {
+        RiskLevel::High
+    } else if score >= 0.33 {
+        RiskLevel::Medium
+    } else {
+        RiskLevel::Low
+    }
+}
+
+/// Score every entry. `cache_bytes` is the index header's `num_bytes`.
+pub fn assess(inputs: &[RiskInput], cache_bytes: u64, max_bytes: u64) -> Vec<EvictionRisk> {
+    let pressure = pressure(cache_bytes, max_bytes);
+
+    // Older = larger age value. Unknown timestamps count as oldest.
+    let ages: Vec<f64> = inputs
+        .iter()
+        .map(|i| -i.last_used.unwrap_or(f64::MIN))
+        .collect();
+    let sizes: Vec<f64> = inputs.iter().map(|i| i.size as f64).collect();
+    let age_ranks = ranks(&ages);
+    let size_ranks = ranks(&sizes);
+
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            let (list, list_factor) = lru_list(input.reuse_count);
+            let base = 0.5 * age_ranks[i] + 0.2 * size_ranks[i] + 0.3 * list_factor;
+            let score = (base * (0.25 + 0.75 * pressure)).clamp(0.0, 1.0);
+            EvictionRisk { score, level: level(score), lru_list: list }
+        })
+        .collect()
+}
+
+/// Score Simple Cache entries (`reuse_count` is ignored). `cache_bytes` is
+/// the-real-index's `cache_size`.
+pub fn assess_simple(inputs: &[RiskInput], cache_bytes: u64, max_bytes: u64) -> Vec<EvictionRisk> {
+    let pressure = pressure(cache_bytes, max_bytes);
+    let ages: Vec<f64> = inputs
+        .iter()
+        .map(|i| -i.last_used.unwrap_or(f64::MIN))
+        .collect();
+    let sizes: Vec<f64> = inputs.iter().map(|i| i.size as f64).collect();
+    let age_ranks = ranks(&ages);
+    let size_ranks = ranks(&sizes);
+
+    (0..inputs.len())
+        .map(|i| {
+            let base = 0.7 * age_ranks[i] + 0.3 * size_ranks[i];
+            let score = (base * (0.25 + 0.75 * pressure)).clamp(0.0, 1.0);
+            EvictionRisk { score, level: level(score), lru_list: "simple" }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(last_used: Option<f64>, size: u64, reuse_count: u32) -> RiskInput {
+        RiskInput { last_used, size, reuse_count }
+    }
+
+    #[test]
+    fn ranks_spread_from_zero_to_one() {
+        assert_eq!(ranks(&[3.0, 1.0, 2.0]), [1.0, 0.0, 0.5]);
+        assert_eq!(ranks(&[7.0]), [0.0]);
+    }
+
+    #[test]
+    fn old_large_unused_entries_go_first() {
+        // Old and large, never reused; recent, small and hot; time unknown.
+        let inputs = [input(Some(100.0), 10 << 20, 0), input(Some(1000.0), 1024, 20), input(None, 5 << 20, 3)];
+        let full = assess(&inputs, DEFAULT_MAX_CACHE_BYTES, DEFAULT_MAX_CACHE_BYTES);
+        let levels: Vec<RiskLevel> = full.iter().map(|r| r.level).collect();
+        assert_eq!(levels, [RiskLevel::High, RiskLevel::Low, RiskLevel::High]);
+        assert_eq!(full.iter().map(|r| r.lru_list).collect::<Vec<_>>(), ["no_use", "high_use", "low_use"]);
+        assert!((full[0].score - 0.75).abs() < 1e-9);
+        // An unknown time counts as the oldest.
+        assert!(full[2].score > full[0].score);
+
+        // A near-empty cache trims nothing soon.
+        let empty = assess(&inputs, 0, DEFAULT_MAX_CACHE_BYTES);
+        assert!(empty.iter().all(|r| r.level == RiskLevel::Low));
+
+        let simple = assess_simple(&inputs, DEFAULT_MAX_CACHE_BYTES, DEFAULT_MAX_CACHE_BYTES);
+        let levels: Vec<RiskLevel> = simple.iter().map(|r| r.level).collect();
+        assert_eq!(levels, [RiskLevel::Medium, RiskLevel::Low, RiskLevel::High]);
+        assert!(simple.iter().all(|r| r.lru_list == "simple"));
+    }
+}
@@ -0,0 +1,327 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// ─── Export mapping file ────────────────────────────────────────────────────
+//
+// Once files are named by a template or after their URL, nothing in the
+// output folder says which cache entry a file came from. A batch export keeps
+// a `mapping.csv` next to its outputs: cache path, output path (relative to
+// the folder), URL and SHA-256 of the output, one row per file. Rows are added only when a recovery
+// job commits (`end_output_job` with success), so a failed or cancelled
+// resource never appears, and the file is rewritten in one atomic replace
+// when the batch finishes, merged with the rows of earlier runs whose outputs
+// still exist.
+//
+// The same rows show naming collisions: outputs in one folder that share a
+// name once a " (2)" dedup suffix is removed, or that a later run overwrote,
+// but came from different entries. Their digests show the opposite: outputs
+// under different names with the same bytes.
+
+pub const MAPPING_FILE: &str = "mapping.csv";
+const HEADER: &str = "cache_path,output_path,url,sha256";
+
+#[derive(Debug, Clone, PartialEq)]
+struct MappingRow {
+    cache_path: String,
+    /// Relative to the batch folder, '/'-separated; absolute if outside it
+    output_path: String,
+    url: String,
+    /// Empty until the batch is written, or if the output couldn't be read
+    sha256: String,
+}
+
+#[derive(Debug)]
+struct Batch {
+    include_urls: bool,
+    rows: Vec<MappingRow>,
+}
+
+/// Open batches by output folder.
+static BATCHES: Mutex<Option<HashMap<PathBuf, Batch>>> = Mutex::new(None);
+
+fn with_batches<T>(f: impl FnOnce(&mut HashMap<PathBuf, Batch>) -> T) -> T {
+    let mut batches = BATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    f(batches.get_or_insert_with(HashMap::new))
+}
+
+#[derive(Debug, Serialize)]
+pub struct NameCollision {
+    /// The name the outputs share, without a dedup suffix
+    pub name: String,
+    pub output_paths: Vec<String>,
+    pub cache_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MappingReport {
+    /// Where mapping.csv was written; None if there was nothing to write
+    pub path: Option<String>,
+    pub rows: usize,
+    /// Rows this batch added
+    pub added: usize,
+    /// Rows of earlier runs dropped because their output is gone or was replaced
+    pub dropped: usize,
+    pub collisions: Vec<NameCollision>,
+    /// Outputs with identical contents, grouped; relative to the folder
+    pub duplicates: Vec<Vec<String>>,
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split CSV text into records; quoted fields may hold commas, quotes and newlines.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let (mut record, mut field) = (Vec::new(), String::new());
+    let mut chars = text.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            '\r' if !quoted => {}
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn read_rows(file: &Path) -> Vec<MappingRow> {
+    let Ok(text) = std::fs::read_to_string(file) else {
+        return Vec::new();
+    };
+    parse_csv(&text)
+        .into_iter()
+        .skip(1)
+        .filter_map(|r| {
+            let mut fields = r.into_iter();
+            let (cache_path, output_path) = (fields.next()?, fields.next()?);
+            let url = fields.next().unwrap_or_default();
+            Some(MappingRow { cache_path, output_path, url, sha256: fields.next().unwrap_or_default() })
+        })
+        .collect()
+}
+
+fn render(rows: &[MappingRow]) -> String {
+    let mut out = String::from(HEADER);
+    out.push('\n');
+    for row in rows {
+        let fields = [&row.cache_path, &row.output_path, &row.url, &row.sha256];
+        out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// "clip (2).mp4" → "clip.mp4"; names without a dedup suffix are unchanged.
+fn undeduped(name: &str) -> String {
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot..]),
+        _ => (name, ""),
+    };
+    let suffix_at = stem.rfind(" (").filter(|&i| {
+        let digits = stem[i + 2..].strip_suffix(')').unwrap_or("");
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    });
+    let stem = suffix_at.map_or(stem, |i| &stem[..i]);
+    format!("{}{}", stem, ext).to_lowercase()
+}
+
+/// Groups of outputs whose digests match, in row order.
+fn duplicates(rows: &[MappingRow]) -> Vec<Vec<String>> {
+    let mut order: Vec<&str> = Vec::new();
+    let mut groups: HashMap<&str, Vec<String>> = HashMap::new();
+    for row in rows.iter().filter(|r| !r.sha256.is_empty()) {
+        if !groups.contains_key(row.sha256.as_str()) {
+            order.push(&row.sha256);
+        }
+        groups.entry(&row.sha256).or_default().push(row.output_path.clone());
+    }
+    order.into_iter().filter_map(|digest| groups.remove(digest).filter(|g| g.len() > 1)).collect()
+}
+
+/// Groups of rows from different entries whose outputs share a name.
+fn collisions(rows: &[MappingRow]) -> Vec<NameCollision> {
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: HashMap<String, Vec<&MappingRow>> = HashMap::new();
+    for row in rows {
+        let key = undeduped(&row.output_path);
+        if !groups.contains_key(&key) {
+            order.push(key.clone());
+        }
+        groups.entry(key).or_default().push(row);
+    }
+    order
+        .into_iter()
+        .filter_map(|key| {
+            let group = &groups[&key];
+            let mut cache_paths: Vec<String> = Vec::new();
+            for row in group {
+                if !cache_paths.contains(&row.cache_path) {
+                    cache_paths.push(row.cache_path.clone());
+                }
+            }
+            (cache_paths.len() > 1).then(|| NameCollision {
+                name: key.rsplit('/').next().unwrap_or(&key).to_string(),
+                output_paths: group.iter().map(|r| r.output_path.clone()).collect(),
+                cache_paths,
+            })
+        })
+        .collect()
+}
+
+/// The URL of a Simple Cache entry file, if `source` is one.
+fn url_of(source: &str) -> Option<String> {
+    let key = crate::read_simple_cache_key(source)?;
+    Some(crate::cache_key::effective_url(&key).to_string())
+}
+
+/// Add the outputs of a committed job to the open batch covering `root`.
+pub(crate) fn commit(root: &Path, source: &str, url: Option<&str>, outputs: &[PathBuf]) {
+    with_batches(|batches| {
+        let Some((batch_root, batch)) = batches.iter_mut().find(|(r, _)| root.starts_with(r)) else {
+            return;
+        };
+        let url = if batch.include_urls { url.map(str::to_string).or_else(|| url_of(source)) } else { None };
+        for output in outputs {
+            batch.rows.push(MappingRow {
+                cache_path: source.to_string(),
+                output_path: relative(batch_root, output),
+                url: url.clone().unwrap_or_default(),
+                sha256: String::new(),
+            });
+        }
+    })
+}
+
+/// Start collecting mapping rows for outputs under `folder`. With
+/// `include_urls` false, the URL column is left empty, for earlier rows too.
+#[tauri::command]
+pub fn begin_export_mapping(folder: String, include_urls: bool) {
+    let root = PathBuf::from(crate::path_display::expand_path(&folder));
+    with_batches(|batches| batches.insert(root, Batch { include_urls, rows: Vec::new() }));
+}
+
+/// Close the batch for `folder` and write its `mapping.csv`, merged with the
+/// rows of earlier runs. Returns the collisions among all rows. Requires a
+/// `consent` token covering `folder`.
+#[tauri::command]
+pub fn finish_export_mapping(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    folder: String,
+    consent: String,
+) -> Result<MappingReport, String> {
+    let folder = state.check(&consent, &folder)?;
+    write_mapping(folder)
+}
+
+fn write_mapping(folder: String) -> Result<MappingReport, String> {
+    let root = PathBuf::from(crate::path_display::expand_path(&folder));
+    let batch =
+        with_batches(|batches| batches.remove(&root)).ok_or_else(|| format!("No open export batch for {}", folder))?;
+    let file = root.join(MAPPING_FILE);
+    let added: Vec<MappingRow> = batch.rows.into_iter().filter(|r| root.join(&r.output_path).is_file()).collect();
+    let earlier = read_rows(&file);
+    let earlier_count = earlier.len();
+    let kept: Vec<MappingRow> = earlier.into_iter().filter(|r| root.join(&r.output_path).is_file()).collect();
+
+    // Collisions include earlier rows a new output replaced; those rows are
+    // then dropped, since their file now holds another entry.
+    let collisions = collisions(&[kept.as_slice(), added.as_slice()].concat());
+    let mut rows: Vec<MappingRow> =
+        kept.into_iter().filter(|r| !added.iter().any(|a| a.output_path == r.output_path)).collect();
+    rows.extend(added.iter().cloned());
+    if !batch.include_urls {
+        rows.iter_mut().for_each(|r| r.url.clear());
+    }
+    let outputs: Vec<String> = rows.iter().map(|r| root.join(&r.output_path).to_string_lossy().to_string()).collect();
+    for (row, hash) in rows.iter_mut().zip(crate::hashing::hash_files_with(&outputs, &|_| {})) {
+        row.sha256 = hash.sha256.unwrap_or_default();
+    }
+
+    let report = MappingReport {
+        path: None,
+        rows: rows.len(),
+        added: added.len(),
+        dropped: earlier_count + added.len() - rows.len(),
+        collisions,
+        duplicates: duplicates(&rows),
+    };
+    if rows.is_empty() {
+        return Ok(report);
+    }
+    crate::output_file::write_output(&file.to_string_lossy(), render(&rows).as_bytes())?;
+    println!(
+        "[mapping] {}: {} rows ({} new, {} dropped), {} name collisions, {} duplicate groups",
+        file.display(),
+        report.rows,
+        report.added,
+        report.dropped,
+        report.collisions.len(),
+        report.duplicates.len()
+    );
+    Ok(MappingReport { path: Some(file.to_string_lossy().to_string()), ..report })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_rows_and_reports_collisions() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-mapping-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("videos")).unwrap();
+        let folder = dir.to_string_lossy().to_string();
+        let (a, b) = (dir.join("videos/clip.mp4"), dir.join("videos/clip (2).mp4"));
+        std::fs::write(&a, b"a").unwrap();
+        std::fs::write(&b, b"b").unwrap();
+        std::fs::write(dir.join("copy.mp4"), b"a").unwrap();
+
+        begin_export_mapping(folder.clone(), true);
+        commit(&dir, "/cache/aaaa_0", Some("https://x/clip.mp4"), std::slice::from_ref(&a));
+        commit(&dir, "/cache/bbbb_0", Some("https://y/clip.mp4?a,b"), &[b.clone(), dir.join("gone.mp4")]);
+        commit(&dir, "/cache/cccc_0", None, &[dir.join("copy.mp4")]);
+        let report = write_mapping(folder.clone()).unwrap();
+        let written = std::fs::read_to_string(dir.join(MAPPING_FILE)).unwrap();
+
+        // A second batch without URLs keeps the earlier rows but blanks their URLs.
+        begin_export_mapping(folder.clone(), false);
+        let again = write_mapping(folder).unwrap();
+        let rows = read_rows(&dir.join(MAPPING_FILE));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!((report.rows, report.added, report.collisions.len()), (3, 3, 1));
+        assert_eq!(report.collisions[0].name, "clip.mp4");
+        assert_eq!(report.duplicates, vec![vec!["videos/clip.mp4".to_string(), "copy.mp4".to_string()]]);
+        let sha_b = "3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d";
+        assert!(written.contains(&format!("/cache/bbbb_0,videos/clip (2).mp4,\"https://y/clip.mp4?a,b\",{}\n", sha_b)));
+        assert_eq!((again.rows, again.added), (3, 0));
+        assert!(rows.iter().all(|r| r.url.is_empty()));
+        assert_eq!(undeduped("a/b (12).MP4"), "a/b.mp4");
+        assert_eq!(undeduped("a (.mp4"), "a (.mp4");
+    }
+}
@@ -0,0 +1,650 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// ---------------------------------------------------------------------------
+// Export targets
+// ---------------------------------------------------------------------------
+//
+// Recovered files are normally written to a local folder. An export target
+// sends each one on as soon as its recovery job has finished it — to an
+// attached Android phone over MTP, or a NAS over SMB or SFTP — and can drop
+// the local copy once it arrived, so the output folder only stages files and
+// nothing has to be copied over by hand afterwards. MTP has no portable
+// filesystem API, so each OS uses what it already ships:
+//   Linux:   gvfs mounts MTP devices under $XDG_RUNTIME_DIR/gvfs/mtp:host=...
+//            and they behave like ordinary directories.
+//   Windows: devices live in the Shell namespace ("This PC"); files are pushed
+//            with Shell.Application Folder.CopyHere via PowerShell.
+//   macOS:   no built-in MTP support — devices are never listed.
+//
+// Network targets are retried with backoff and resume where they left off:
+//   SMB:  a UNC path (\\nas\share\dir) or a mounted share. Files are written
+//         through to the server as `<name>.part`, renamed when complete; a
+//         leftover `.part` whose tail matches the source is continued.
+//   SFTP: the OpenSSH `sftp` client in batch mode (Windows 10+, macOS and
+//         Linux ship it), so authentication must work without a prompt — a
+//         key in the agent or ~/.ssh. A retry resumes with `reput`.
+
+/// Folder created on the device (under its first storage) for pushed files.
+const DEVICE_EXPORT_FOLDER: &str = "CachePhoenix";
+/// Tries per file for network targets, and the wait before the first retry
+/// (doubled after each failure).
+const NETWORK_ATTEMPTS: u32 = 4;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const PART_SUFFIX: &str = ".part";
+const COPY_CHUNK: usize = 4 * 1024 * 1024;
+/// Bytes before the resume point compared with the source; any difference
+/// restarts the copy.
+const RESUME_CHECK_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportTargetInfo {
+    /// Opaque identifier passed back to `export_to_target`
+    pub id: String,
+    /// Human-readable label, e.g. "Pixel 7 (MTP)"
+    pub label: String,
+    /// "local", "mtp", "smb" or "sftp"
+    pub kind: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportFileResult {
+    pub source: String,
+    pub ok: bool,
+    pub bytes: u64,
+    pub error: Option<String>,
+}
+
+/// Somewhere recovered files can be delivered to.
+trait ExportTarget {
+    /// Copy `source` into the target as `file_name`. `resume` is set when
+    /// retrying after a failed attempt. Returns bytes written.
+    fn push(&self, source: &Path, file_name: &str, resume: bool) -> Result<u64, String>;
+
+    /// How many times a failed push is tried before giving up.
+    fn attempts(&self) -> u32 {
+        1
+    }
+}
+
+/// A plain directory (local folder, or an MTP device mounted by gvfs).
+struct DirectoryTarget {
+    root: PathBuf,
+}
+
+impl ExportTarget for DirectoryTarget {
+    fn push(&self, source: &Path, file_name: &str, _resume: bool) -> Result<u64, String> {
+        std::fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create dir {}: {}", self.root.display(), e))?;
+        let dst = self.root.join(file_name);
+        let source = std::fs::canonicalize(source).map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        // Copying a file onto itself would truncate it.
+        if std::fs::canonicalize(&dst).is_ok_and(|d| d == source) {
+            return Err(format!("{} is already in {}", file_name, self.root.display()));
+        }
+        // gvfs MTP mounts don't support seeking writes, so a plain copy is the
+        // most compatible way to stream the file across.
+        std::fs::copy(&source, &dst)
+            .map_err(|e| format!("Failed to copy {} -> {}: {}", source.display(), dst.display(), e))
+    }
+}
+
+/// A Windows portable device addressed through the Shell namespace.
+#[cfg(target_os = "windows")]
+struct ShellDeviceTarget {
+    device_name: String,
+}
+
+#[cfg(target_os = "windows")]
+impl ExportTarget for ShellDeviceTarget {
+    fn push(&self, source: &Path, file_name: &str, _resume: bool) -> Result<u64, String> {
+        let size = std::fs::metadata(source)
+            .map_err(|e| format!("Failed to stat {}: {}", source.display(), e))?
+            .len();
+        // CopyHere keeps the source name, so stage a correctly named copy first.
+        let staging = std::env::temp_dir().join("cachephoenix_mtp").join(file_name);
+        if let Some(parent) = staging.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
+        }
+        std::fs::copy(source, &staging).map_err(|e| format!("Failed to stage file: {}", e))?;
+        let script = format!(
+            r#"
+$ErrorActionPreference = 'Stop'
+$shell = New-Object -ComObject Shell.Application
+$device = $shell.Namespace(17).Items() | Where-Object {{ $_.Name -eq '{device}' }} | Select-Object -First 1
+if (-not $device) {{ throw 'Device not found: {device}' }}
+$storage = $device.GetFolder.Items() | Select-Object -First 1
+if (-not $storage) {{ throw 'Device has no storage (is it unlocked and set to File Transfer?)' }}
+$dest = $storage.GetFolder.Items() | Where-Object {{ $_.Name -eq '{folder}' }} | Select-Object -First 1
+if (-not $dest) {{ $storage.GetFolder.NewFolder('{folder}'); Start-Sleep -Milliseconds 500
+  $dest = $storage.GetFolder.Items() | Where-Object {{ $_.Name -eq '{folder}' }} | Select-Object -First 1 }}
+$dest.GetFolder.CopyHere('{src}', 4 + 16)
+for ($i = 0; $i -lt 600; $i++) {{
+  if ($dest.GetFolder.ParseName('{name}')) {{ exit 0 }}
+  Start-Sleep -Milliseconds 500
+}}
+throw 'Timed out waiting for the device to accept the file'
+"#,
+            device = ps_escape(&self.device_name),
+            folder = DEVICE_EXPORT_FOLDER,
+            src = ps_escape(&staging.display().to_string()),
+            name = ps_escape(file_name),
+        );
+        let result = run_powershell(&script);
+        let _ = std::fs::remove_file(&staging);
+        result.map(|_| size)
+    }
+}
+
+/// Escape a value for a single-quoted PowerShell string.
+#[cfg(target_os = "windows")]
+fn ps_escape(s: &str) -> String {
+    s.replace('\'', "''")
+}
+
+#[cfg(target_os = "windows")]
+fn run_powershell(script: &str) -> Result<String, String> {
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .output()
+        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// An SMB share: a UNC path on Windows, or a share mounted as a folder.
+struct ShareTarget {
+    root: PathBuf,
+}
+
+impl ExportTarget for ShareTarget {
+    fn push(&self, source: &Path, file_name: &str, _resume: bool) -> Result<u64, String> {
+        std::fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create dir {}: {}", self.root.display(), e))?;
+        let dst = self.root.join(file_name);
+        let part = self.root.join(format!("{}{}", file_name, PART_SUFFIX));
+        let mut src = File::open(source).map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        let total = src.metadata().map_err(|e| format!("Failed to stat {}: {}", source.display(), e))?.len();
+        // A `.part` from an earlier attempt (or run) is continued if it matches.
+        let offset = resume_offset(&mut src, &part, total);
+        if offset > 0 {
+            println!("[export] Resuming {} at {} of {} bytes", part.display(), offset, total);
+        }
+
+        let write_err = |e: std::io::Error| format!("Failed to write {}: {}", part.display(), e);
+        let mut out = open_write_through(&part).map_err(write_err)?;
+        out.set_len(offset).map_err(write_err)?;
+        out.seek(SeekFrom::Start(offset)).map_err(write_err)?;
+        src.seek(SeekFrom::Start(offset)).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let mut buf = vec![0u8; COPY_CHUNK];
+        loop {
+            let n = src.read(&mut buf).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+            if n == 0 {
+                break;
+            }
+            out.write_all(&buf[..n]).map_err(write_err)?;
+            // Flushed per chunk, so a dropped connection loses at most one
+            // chunk and the `.part` length is a safe resume point.
+            out.sync_data().map_err(write_err)?;
+        }
+        drop(out);
+        std::fs::rename(&part, &dst)
+            .map_err(|e| format!("Failed to rename {} -> {}: {}", part.display(), dst.display(), e))?;
+        Ok(total)
+    }
+
+    fn attempts(&self) -> u32 {
+        NETWORK_ATTEMPTS
+    }
+}
+
+/// Where to continue copying `src` into `part`: its length if the bytes just
+/// before that point match the source, else 0.
+fn resume_offset(src: &mut File, part: &Path, total: u64) -> u64 {
+    let Ok(len) = std::fs::metadata(part).map(|m| m.len()) else {
+        return 0;
+    };
+    if len == 0 || len > total {
+        return 0;
+    }
+    let check = len.min(RESUME_CHECK_BYTES);
+    let read_tail = |file: &mut File| -> std::io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; check as usize];
+        file.seek(SeekFrom::Start(len - check))?;
+        file.read_exact(&mut buf)?;
+        Ok(buf)
+    };
+    let matches = File::open(part)
+        .and_then(|mut f| read_tail(&mut f))
+        .and_then(|written| Ok(written == read_tail(src)?))
+        .unwrap_or(false);
+    if matches {
+        len
+    } else {
+        0
+    }
+}
+
+/// Open `path` for writing without truncating; on Windows writes go
+/// straight to the server instead of the redirector's cache.
+fn open_write_through(path: &Path) -> std::io::Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(false);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_FLAG_WRITE_THROUGH: u32 = 0x8000_0000;
+        options.custom_flags(FILE_FLAG_WRITE_THROUGH);
+    }
+    options.open(path)
+}
+
+/// An SFTP server directory, from "sftp:[user@]host[:port][/dir]".
+#[derive(Debug, PartialEq)]
+struct SftpTarget {
+    /// "[user@]host"
+    destination: String,
+    port: Option<u16>,
+    /// Remote directory; "." is the login directory
+    dir: String,
+}
+
+impl SftpTarget {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let (authority, dir) = match spec.find('/') {
+            Some(i) => (&spec[..i], &spec[i..]),
+            None => (spec, "."),
+        };
+        let host_start = authority.rfind('@').map_or(0, |i| i + 1);
+        let (destination, port) = match authority[host_start..].split_once(':') {
+            Some((host, port)) => {
+                let port = port.parse().map_err(|_| format!("Bad SFTP port in {}", spec))?;
+                (&authority[..host_start + host.len()], Some(port))
+            }
+            None => (authority, None),
+        };
+        if destination.is_empty() || destination.ends_with('@') {
+            return Err(format!("No SFTP host in {}", spec));
+        }
+        // sftp would take it for an option.
+        if destination.starts_with('-') {
+            return Err(format!("Bad SFTP host in {}", spec));
+        }
+        let dir = dir.trim_end_matches('/');
+        Ok(SftpTarget {
+            destination: destination.to_string(),
+            port,
+            dir: if dir.is_empty() { "/".to_string() } else { dir.to_string() },
+        })
+    }
+
+    /// The batch script uploading `source` as `file_name`, creating the
+    /// directory first. Failing `mkdir`s (the `-` prefix) are ignored.
+    fn script(&self, source: &Path, file_name: &str, resume: bool) -> String {
+        let mut script = String::new();
+        let mut dir = if self.dir.starts_with('/') { "/".to_string() } else { String::new() };
+        for part in self.dir.split('/').filter(|p| !p.is_empty() && *p != ".") {
+            dir.push_str(part);
+            script.push_str(&format!("-mkdir {}\n", sftp_quote(&dir)));
+            dir.push('/');
+        }
+        let remote = format!("{}{}", dir, file_name);
+        let command = if resume { "reput" } else { "put" };
+        script.push_str(&format!("{} {} {}\n", command, sftp_quote(&source.to_string_lossy()), sftp_quote(&remote)));
+        script
+    }
+}
+
+/// Quote an argument for an sftp batch file.
+fn sftp_quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl ExportTarget for SftpTarget {
+    fn push(&self, source: &Path, file_name: &str, resume: bool) -> Result<u64, String> {
+        let size = std::fs::metadata(source)
+            .map_err(|e| format!("Failed to stat {}: {}", source.display(), e))?
+            .len();
+        let mut command = std::process::Command::new("sftp");
+        command.args(["-b", "-", "-o", "BatchMode=yes", "-o", "ConnectTimeout=20"]);
+        if let Some(port) = self.port {
+            command.args(["-P", &port.to_string()]);
+        }
+        let mut child = command
+            .arg("--")
+            .arg(&self.destination)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run sftp (is OpenSSH installed?): {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(self.script(source, file_name, resume).as_bytes())
+                .map_err(|e| format!("Failed to send sftp commands: {}", e))?;
+        }
+        let output = child.wait_with_output().map_err(|e| format!("sftp failed: {}", e))?;
+        if output.status.success() {
+            Ok(size)
+        } else {
+            Err(format!("sftp to {}: {}", self.destination, String::from_utf8_lossy(&output.stderr).trim()))
+        }
+    }
+
+    fn attempts(&self) -> u32 {
+        NETWORK_ATTEMPTS
+    }
+}
+
+/// Push with the target's retry policy, resuming on each retry.
+fn push_with_retry(target: &dyn ExportTarget, source: &Path, file_name: &str) -> Result<u64, String> {
+    let attempts = target.attempts().max(1);
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match target.push(source, file_name, attempt > 1) {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) if attempt < attempts => {
+                eprintln!(
+                    "[export] {} attempt {}/{} failed: {}; retrying in {}s",
+                    file_name,
+                    attempt,
+                    attempts,
+                    e,
+                    delay.as_secs()
+                );
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Device discovery
+// ---------------------------------------------------------------------------
+
+/// gvfs mount root for the current user.
+#[cfg(target_os = "linux")]
+fn gvfs_root() -> Option<PathBuf> {
+    if let Ok(runtime) = std::env::var("XDG_RUNTIME_DIR") {
+        return Some(PathBuf::from(runtime).join("gvfs"));
+    }
+    // Fall back to /run/user/<uid>; read the uid from /proc to avoid libc.
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let uid = status
+        .lines()
+        .find(|l| l.starts_with("Uid:"))?
+        .split_whitespace()
+        .nth(1)?
+        .to_string();
+    Some(PathBuf::from("/run/user").join(uid).join("gvfs"))
+}
+
+/// List MTP devices mounted by gvfs as (mount dir, label).
+#[cfg(target_os = "linux")]
+fn list_mtp_devices() -> Vec<(String, String)> {
+    let mut devices = Vec::new();
+    let Some(root) = gvfs_root() else {
+        return devices;
+    };
+    if let Ok(entries) = std::fs::read_dir(&root) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // e.g. "mtp:host=SAMSUNG_SAMSUNG_Android_R58N12345"
+            if let Some(host) = name.strip_prefix("mtp:host=") {
+                let label = crate::blockfile_index::url_decode(host).replace('_', " ");
+                devices.push((entry.path().to_string_lossy().to_string(), label));
+            }
+        }
+    }
+    devices
+}
+
+/// List portable devices visible in "This PC" as (device name, label).
+#[cfg(target_os = "windows")]
+fn list_mtp_devices() -> Vec<(String, String)> {
+    // Non-filesystem items under ssfDRIVES (17) are portable devices.
+    let script = "$shell = New-Object -ComObject Shell.Application; \
+        $shell.Namespace(17).Items() | Where-Object { -not $_.IsFileSystem } | ForEach-Object { $_.Name }";
+    match run_powershell(script) {
+        Ok(out) => out
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| (l.to_string(), l.to_string()))
+            .collect(),
+        Err(e) => {
+            eprintln!("[export] Portable device enumeration failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn list_mtp_devices() -> Vec<(String, String)> {
+    Vec::new()
+}
+
+/// List SMB shares mounted by gvfs as (mount dir, label).
+#[cfg(target_os = "linux")]
+fn list_smb_shares() -> Vec<(String, String)> {
+    let Some(root) = gvfs_root() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(&root) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            // e.g. "smb-share:server=nas.local,share=media"
+            let name = entry.file_name().to_string_lossy().to_string();
+            let params = name.strip_prefix("smb-share:")?;
+            let param = |key: &str| params.split(',').find_map(|p| p.strip_prefix(key)).map(str::to_string);
+            let label = format!("{} on {}", param("share=")?, param("server=")?);
+            Some((entry.path().to_string_lossy().to_string(), label))
+        })
+        .collect()
+}
+
+/// Windows reaches shares by UNC path directly; nothing is mounted to list.
+#[cfg(not(target_os = "linux"))]
+fn list_smb_shares() -> Vec<(String, String)> {
+    Vec::new()
+}
+
+/// Whether `path` is a UNC path naming a server and a share
+/// (`\\server\share[\dir...]`, either slash), without `..` in it.
+fn is_unc_path(path: &str) -> bool {
+    let Some(rest) = path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//")) else {
+        return false;
+    };
+    let parts: Vec<&str> = rest.split(['\\', '/']).collect();
+    parts.len() >= 2 && !parts[0].is_empty() && !parts[1].is_empty() && !parts.contains(&"..")
+}
+
+/// Whether `path` is a listed mounted share or a folder inside one.
+fn in_mounted_share(path: &str, shares: &[(String, String)]) -> bool {
+    let path = Path::new(path);
+    !path.components().any(|c| c == std::path::Component::ParentDir)
+        && shares.iter().any(|(root, _)| path.starts_with(root))
+}
+
+/// Resolve a target id from `list_export_targets` into an implementation.
+/// Ids are "mtp:<listed device>", "smb:<UNC path, or a listed mounted share>"
+/// or "sftp:[user@]host[:port][/dir]". Local folders are resolved by
+/// `export_to_target`, against the consent.
+fn resolve_target(id: &str) -> Result<Box<dyn ExportTarget>, String> {
+    if let Some(share) = id.strip_prefix("smb:") {
+        if !is_unc_path(share) && !in_mounted_share(share, &list_smb_shares()) {
+            return Err(format!("{} is neither a UNC path nor a mounted share", share));
+        }
+        return Ok(Box::new(ShareTarget { root: PathBuf::from(share) }));
+    }
+    if let Some(spec) = id.strip_prefix("sftp:") {
+        return Ok(Box::new(SftpTarget::parse(spec)?));
+    }
+    if let Some(device) = id.strip_prefix("mtp:") {
+        if !list_mtp_devices().iter().any(|(listed, _)| listed == device) {
+            return Err(format!("{} is not a connected MTP device", device));
+        }
+        #[cfg(target_os = "linux")]
+        {
+            // Push into the first storage (e.g. "Internal shared storage").
+            let mount = Path::new(device);
+            let storage = std::fs::read_dir(mount)
+                .map_err(|e| format!("Cannot open device {}: {}", device, e))?
+                .flatten()
+                .map(|e| e.path())
+                .find(|p| p.is_dir())
+                .ok_or_else(|| "Device has no storage (is it unlocked and set to File Transfer?)".to_string())?;
+            return Ok(Box::new(DirectoryTarget { root: storage.join(DEVICE_EXPORT_FOLDER) }));
+        }
+        #[cfg(target_os = "windows")]
+        {
+            return Ok(Box::new(ShellDeviceTarget { device_name: device.to_string() }));
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            return Err(format!("MTP export is not supported on this platform ({})", device));
+        }
+    }
+    Err(format!("Unknown export target: {}", id))
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// List MTP devices and mounted SMB shares that recovered files can be
+/// pushed to. Local folders ("local:<path>"), UNC paths ("smb:<path>") and
+/// SFTP servers ("sftp:...") are entered by the user and are not listed.
+#[tauri::command(async)]
+pub fn list_export_targets() -> Vec<ExportTargetInfo> {
+    let devices = list_mtp_devices().into_iter().map(|(id, label)| ExportTargetInfo {
+        id: format!("mtp:{}", id),
+        label: format!("{} (MTP)", label),
+        kind: "mtp",
+    });
+    let shares = list_smb_shares().into_iter().map(|(id, label)| ExportTargetInfo {
+        id: format!("smb:{}", id),
+        label: format!("{} (SMB)", label),
+        kind: "smb",
+    });
+    devices.chain(shares).collect()
+}
+
+/// Send the files a recovery job just finished to an export target, keeping
+/// their file names. Each file reports its own result so one failure doesn't
+/// abort the batch; network targets retry a failed file and resume it. With
+/// `remove_sources` a file that arrived is deleted locally. `consent` must
+/// cover every source, and a local target must be inside the folder it
+/// covers (the output folder).
+#[tauri::command(async)]
+pub fn export_to_target(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    target: String,
+    paths: Vec<String>,
+    remove_sources: bool,
+    consent: String,
+) -> Result<Vec<ExportFileResult>, String> {
+    // Send (and remove) the sources as the consent resolved them.
+    let paths = paths.iter().map(|p| state.check(&consent, p)).collect::<Result<Vec<_>, _>>()?;
+    let target_impl: Box<dyn ExportTarget> = match target.strip_prefix("local:") {
+        // Into the folder as the consent resolved it, so `..` or a symlink
+        // can't lead out of the output folder.
+        Some(folder) => Box::new(DirectoryTarget { root: state.resolve(&consent, folder)? }),
+        None => resolve_target(&target)?,
+    };
+    let mut results = Vec::with_capacity(paths.len());
+    for p in &paths {
+        let source = Path::new(p);
+        let file_name = source
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| format!("Not a file path: {}", p))?;
+        match push_with_retry(target_impl.as_ref(), source, &file_name) {
+            Ok(bytes) => {
+                if remove_sources {
+                    if let Err(e) = std::fs::remove_file(source) {
+                        eprintln!("[export] Sent {} but could not remove it: {}", p, e);
+                    }
+                }
+                results.push(ExportFileResult { source: p.clone(), ok: true, bytes, error: None });
+            }
+            Err(e) => {
+                eprintln!("[export] {} -> {} failed: {}", p, target, e);
+                results.push(ExportFileResult { source: p.clone(), ok: false, bytes: 0, error: Some(e) });
+            }
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_copy_resumes_matching_part_and_sftp_specs_parse() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-export-{}", std::process::id()));
+        let share = dir.join("share");
+        std::fs::create_dir_all(&share).unwrap();
+        let source = dir.join("clip.mp4");
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&source, &data).unwrap();
+        // A matching partial copy is continued; a stale one is started over.
+        std::fs::write(share.join("clip.mp4.part"), &data[..70_000]).unwrap();
+        let target = ShareTarget { root: share.clone() };
+        let mut src = File::open(&source).unwrap();
+        let resumed_at = resume_offset(&mut src, &share.join("clip.mp4.part"), data.len() as u64);
+        let bytes = push_with_retry(&target, &source, "clip.mp4").unwrap();
+        let copied = std::fs::read(share.join("clip.mp4")).unwrap();
+        std::fs::write(share.join("clip.mp4.part"), b"something else").unwrap();
+        let stale_at = resume_offset(&mut src, &share.join("clip.mp4.part"), data.len() as u64);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!((resumed_at, stale_at, bytes), (70_000, 0, data.len() as u64));
+        assert_eq!(copied, data);
+
+        let sftp = SftpTarget::parse("me@nas.local:2222/volume1/Cache Phoenix/").unwrap();
+        assert_eq!(
+            sftp,
+            SftpTarget { destination: "me@nas.local".into(), port: Some(2222), dir: "/volume1/Cache Phoenix".into() }
+        );
+        let script = sftp.script(Path::new("C:\\out\\a \"b\".mp4"), "a.mp4", true);
+        assert_eq!(
+            script,
+            "-mkdir \"/volume1\"\n-mkdir \"/volume1/Cache Phoenix\"\n\
+             reput \"C:\\\\out\\\\a \\\"b\\\".mp4\" \"/volume1/Cache Phoenix/a.mp4\"\n"
+        );
+        assert_eq!(SftpTarget::parse("nas").unwrap().script(Path::new("x"), "x", false), "put \"x\" \"x\"\n");
+        assert!(SftpTarget::parse("me@:22/x").is_err());
+        assert!(SftpTarget::parse("-oProxyCommand=touch x/dir").is_err());
+    }
+
+    #[test]
+    fn shares_and_devices_must_be_real() {
+        assert!(is_unc_path(r"\\nas\media\CachePhoenix"));
+        assert!(is_unc_path("//nas/media"));
+        assert!(!is_unc_path(r"\\nas"));
+        assert!(!is_unc_path(r"\\nas\media\..\..\c$"));
+        assert!(!is_unc_path("/home/me"));
+        let shares = [("/run/user/1000/gvfs/smb-share:server=nas,share=media".to_string(), String::new())];
+        assert!(in_mounted_share("/run/user/1000/gvfs/smb-share:server=nas,share=media/dir", &shares));
+        assert!(!in_mounted_share("/run/user/1000/gvfs/smb-share:server=nas,share=media/../x", &shares));
+        assert!(!in_mounted_share("/etc", &shares));
+        assert!(resolve_target("smb:/etc").is_err());
+        let device = resolve_target("mtp:/not/a/device").err().unwrap();
+        assert!(device.contains("not a connected MTP device"));
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// ─── Failure injection (debug builds only) ──────────────────────────────────
+//
+// The UI's error and retry paths (Full Disk Access prompt, "Discord holds a
+// lock" retry, corrupt-entry badges) are hard to reach on a healthy machine.
+// In debug builds `set_fault_injection` installs rules that make the file
+// reading commands fail the way a broken cache would: EPERM, EACCES, a slow
+// read, or bytes flipped before parsing. Errors go through
+// `format_read_error`, so the UI sees exactly the text a real failure gives.
+// Release builds reject the command and `inject`/`corrupt` compile to no-ops.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultRule {
+    /// Command name to match (e.g. "read_sparse_cache_file"); None matches all
+    pub command: Option<String>,
+    /// Substring of the path to match; None matches all
+    pub path_contains: Option<String>,
+    /// "eperm", "eacces", "slow" or "corrupt"
+    pub fault: String,
+    /// Delay for "slow" (default 2000 ms)
+    pub delay_ms: Option<u64>,
+    /// Fire this many times, then stop matching; None fires forever.
+    /// Lets a retry succeed after N failures.
+    pub times: Option<u32>,
+}
+
+const FAULTS: &[&str] = &["eperm", "eacces", "slow", "corrupt"];
+const DEFAULT_DELAY_MS: u64 = 2000;
+
+static RULES: Mutex<Vec<FaultRule>> = Mutex::new(Vec::new());
+
+/// The first live rule of the installed ones matching `command`/`path` for
+/// one of `faults`, consuming one of its `times`.
+fn take(command: &str, path: &str, faults: &[&str]) -> Option<FaultRule> {
+    if !cfg!(debug_assertions) {
+        return None;
+    }
+    take_from(&mut RULES.lock().unwrap_or_else(|e| e.into_inner()), command, path, faults)
+}
+
+fn take_from(rules: &mut [FaultRule], command: &str, path: &str, faults: &[&str]) -> Option<FaultRule> {
+    let rule = rules.iter_mut().find(|r| {
+        faults.contains(&r.fault.as_str())
+            && r.times != Some(0)
+            && r.command.as_deref().is_none_or(|c| c == command)
+            && r.path_contains.as_deref().is_none_or(|p| path.contains(p))
+    })?;
+    if let Some(times) = rule.times.as_mut() {
+        *times -= 1;
+    }
+    Some(rule.clone())
+}
+
+/// Apply an EPERM / EACCES / slow rule before `command` touches `path`.
+pub fn inject(command: &str, path: &str) -> Result<(), String> {
+    match take(command, path, &["eperm", "eacces", "slow"]) {
+        Some(rule) => fail(&rule, command, path),
+        None => Ok(()),
+    }
+}
+
+fn fail(rule: &FaultRule, command: &str, path: &str) -> Result<(), String> {
+    eprintln!("[fault_inject] {} on {} ({})", rule.fault, path, command);
+    let errno = match rule.fault.as_str() {
+        "eperm" => 1,
+        "eacces" => 13,
+        _ => {
+            std::thread::sleep(Duration::from_millis(rule.delay_ms.unwrap_or(DEFAULT_DELAY_MS)));
+            return Ok(());
+        }
+    };
+    Err(crate::format_read_error(path, &std::io::Error::from_raw_os_error(errno)))
+}
+
+/// Apply a "corrupt" rule to bytes `command` read from `path`: every 64th
+/// byte is inverted, which breaks magics, lengths and media headers alike.
+pub fn corrupt(command: &str, path: &str, data: &mut [u8]) {
+    if take(command, path, &["corrupt"]).is_some() {
+        eprintln!("[fault_inject] corrupting {} bytes of {} ({})", data.len(), path, command);
+        flip(data);
+    }
+}
+
+fn flip(data: &mut [u8]) {
+    for byte in data.iter_mut().step_by(64) {
+        *byte = !*byte;
+    }
+}
+
+/// Replace the active rules (an empty list turns injection off).
+/// Returns the number of rules installed.
+#[tauri::command]
+pub fn set_fault_injection(rules: Vec<FaultRule>) -> Result<usize, String> {
+    if !cfg!(debug_assertions) {
+        return Err("Fault injection is only available in debug builds".to_string());
+    }
+    validate(&rules)?;
+    let mut active = RULES.lock().unwrap_or_else(|e| e.into_inner());
+    *active = rules;
+    println!("[fault_inject] {} rules active", active.len());
+    Ok(active.len())
+}
+
+fn validate(rules: &[FaultRule]) -> Result<(), String> {
+    match rules.iter().find(|r| !FAULTS.contains(&r.fault.as_str())) {
+        Some(bad) => Err(format!("Unknown fault \"{}\" (expected one of {})", bad.fault, FAULTS.join(", "))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_fire_then_expire() {
+        let rule = |fault: &str, times| FaultRule {
+            command: Some("read_file_bytes".to_string()),
+            path_contains: Some("fault-test".to_string()),
+            fault: fault.to_string(),
+            delay_ms: None,
+            times,
+        };
+        assert!(validate(&[rule("enoent", None)]).is_err());
+        // A local rule set: the installed rules stay untouched for other tests.
+        let mut rules = vec![rule("eacces", Some(1)), rule("corrupt", Some(1))];
+        validate(&rules).unwrap();
+        let path = "/tmp/fault-test/f_000001";
+        let mut inject = |command: &str| match take_from(&mut rules, command, path, &["eperm", "eacces", "slow"]) {
+            Some(rule) => fail(&rule, command, path),
+            None => Ok(()),
+        };
+
+        assert!(inject("get_file_size").is_ok());
+        let err = inject("read_file_bytes").unwrap_err();
+        assert!(err.contains("EACCES"), "{}", err);
+        assert!(inject("read_file_bytes").is_ok());
+
+        let mut data = vec![0u8; 130];
+        assert!(take_from(&mut rules, "read_file_bytes", path, &["corrupt"]).is_some());
+        flip(&mut data);
+        assert_eq!(data.iter().filter(|b| **b == 0xff).count(), 3);
+        assert!(take_from(&mut rules, "read_file_bytes", path, &["corrupt"]).is_none());
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    FLAG_HAS_CRC32, FLAG_HAS_KEY_SHA256, SIMPLE_CACHE_EOF_MAGIC, SIMPLE_CACHE_MAGIC, SPARSE_RANGE_MAGIC,
+};
+
+// ---------------------------------------------------------------------------
+// Synthetic Simple Cache fixtures
+// ---------------------------------------------------------------------------
+//
+// Builds byte-exact Simple Cache entry files (the same layouts the parsers in
+// lib.rs read) so the pipeline can be exercised without a real browser cache.
+// Key hashes are not Chromium's — file names only need to look like
+// `{16 hex}_{stream}` for the scanner to pick them up.
+
+/// Simple Cache on-disk format version written into fixture headers.
+const FIXTURE_VERSION: u32 = 5;
+
+/// A directory of synthetic cache entry files.
+pub struct FixtureCache {
+    dir: PathBuf,
+}
+
+impl FixtureCache {
+    /// Create (or reuse) `dir` as the fixture cache directory.
+    pub fn create(dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create dir {}: {}", dir.display(), e))?;
+        Ok(Self { dir: dir.to_path_buf() })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write a `_0` entry holding `body` with the given response headers.
+    /// Returns the file path.
+    pub fn add_entry(&self, key: &str, headers: &[&str], body: &[u8]) -> Result<String, String> {
+        self.write(key, "0", &simple_entry(key, headers, body))
+    }
+
+    /// Write a `_1` file holding `body` as stream 2. Returns the file path.
+    pub fn add_stream2_entry(&self, key: &str, body: &[u8]) -> Result<String, String> {
+        self.write(key, "1", &stream2_entry(key, body))
+    }
+
+    /// Write a `_s` sparse entry from (offset, data) ranges, in the given order.
+    /// Returns the file path.
+    pub fn add_sparse_entry(&self, key: &str, ranges: &[(u64, &[u8])]) -> Result<String, String> {
+        self.write(key, "s", &sparse_entry(key, ranges))
+    }
+
+    /// Write `index-dir/the-real-index` listing the given keys with
+    /// (last_used µs since 1601, size) metadata.
+    pub fn add_index(&self, entries: &[(&str, i64, u64)]) -> Result<(), String> {
+        let entries: Vec<(u64, i64, u64)> = entries
+            .iter()
+            .map(|(key, last_used, size)| (key_hash(key), *last_used, *size))
+            .collect();
+        let dir = self.dir.join("index-dir");
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create dir {}: {}", dir.display(), e))?;
+        let path = dir.join("the-real-index");
+        std::fs::write(&path, simple_index(9, &entries))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn write(&self, key: &str, stream: &str, data: &[u8]) -> Result<String, String> {
+        let path = self.dir.join(format!("{:016x}_{}", key_hash(key), stream));
+        std::fs::write(&path, data)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path.to_string_lossy().to_string())
+    }
+}
+
+/// FNV-1a; only used to derive stable, distinct fixture file names.
+fn key_hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn file_header(key: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(crate::SIMPLE_CACHE_HEADER_SIZE + key.len());
+    out.extend_from_slice(&SIMPLE_CACHE_MAGIC.to_le_bytes());
+    out.extend_from_slice(&FIXTURE_VERSION.to_le_bytes());
+    out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crate::key_hash::super_fast_hash(key.as_bytes()).to_le_bytes());
+    out.extend_from_slice(&[0u8; 4]);
+    out.extend_from_slice(key.as_bytes());
+    out
+}
+
+fn eof_record(flags: u32, stream: &[u8]) -> [u8; crate::SIMPLE_CACHE_EOF_SIZE] {
+    let mut out = [0u8; crate::SIMPLE_CACHE_EOF_SIZE];
+    out[0..8].copy_from_slice(&SIMPLE_CACHE_EOF_MAGIC.to_le_bytes());
+    out[8..12].copy_from_slice(&(flags | FLAG_HAS_CRC32).to_le_bytes());
+    out[12..16].copy_from_slice(&crc32fast::hash(stream).to_le_bytes());
+    out[16..20].copy_from_slice(&(stream.len() as u32).to_le_bytes());
+    out
+}
+
+/// Stream 0 as Chromium writes it: a Pickle of HttpResponseInfo (version 3)
+/// around the NUL-separated headers, with a one-certificate chain if `cert`
+/// is given. Request/response times are 2023-11-14 22:13:19/20 UTC.
+pub fn response_info_pickle(headers: &[&str], cert: Option<&[u8]>) -> Vec<u8> {
+    const FROM_1601_US: i64 = 11_644_473_600 * 1_000_000;
+    fn string(out: &mut Vec<u8>, bytes: &[u8]) {
+        out.extend_from_slice(&(bytes.len() as i32).to_le_bytes());
+        out.extend_from_slice(bytes);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    let mut raw = headers.join("\0").into_bytes();
+    raw.extend_from_slice(b"\0\0");
+    // version 3, RESPONSE_INFO_HAS_CERT | RESPONSE_INFO_HAS_CERT_STATUS with a cert
+    let flags: u32 = 3 | if cert.is_some() { (1 << 8) | (1 << 10) } else { 0 };
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&flags.to_le_bytes());
+    payload.extend_from_slice(&(1_699_999_999_000_000 + FROM_1601_US).to_le_bytes());
+    payload.extend_from_slice(&(1_700_000_000_000_000 + FROM_1601_US).to_le_bytes());
+    string(&mut payload, &raw);
+    if let Some(cert) = cert {
+        payload.extend_from_slice(&1i32.to_le_bytes());
+        string(&mut payload, cert);
+        payload.extend_from_slice(&0u32.to_le_bytes());
+    }
+    let mut out = (payload.len() as u32).to_le_bytes().to_vec();
+    out.extend_from_slice(&payload);
+    out
+}
+
+/// Bytes of a `_0` file: header, key, body (stream 1), EOF1, headers (stream 0), EOF0.
+/// Stream 0 is a pickled HttpResponseInfo, see `response_info_pickle`.
+pub fn simple_entry(key: &str, headers: &[&str], body: &[u8]) -> Vec<u8> {
+    let stream0 = response_info_pickle(headers, None);
+
+    let mut out = file_header(key);
+    out.extend_from_slice(body);
+    out.extend_from_slice(&eof_record(0, body));
+    out.extend_from_slice(&stream0);
+    out.extend_from_slice(&<sha2::Sha256 as sha2::Digest>::digest(key.as_bytes()));
+    out.extend_from_slice(&eof_record(FLAG_HAS_KEY_SHA256, &stream0));
+    out
+}
+
+/// Bytes of a `_1` file: header, key, body (stream 2), EOF.
+pub fn stream2_entry(key: &str, body: &[u8]) -> Vec<u8> {
+    let mut out = file_header(key);
+    out.extend_from_slice(body);
+    out.extend_from_slice(&eof_record(0, body));
+    out
+}
+
+/// Bytes of a `_s` file: header, key, then one SparseRangeHeader + data per range.
+pub fn sparse_entry(key: &str, ranges: &[(u64, &[u8])]) -> Vec<u8> {
+    let mut out = file_header(key);
+    for (offset, data) in ranges {
+        out.extend_from_slice(&SPARSE_RANGE_MAGIC.to_le_bytes());
+        out.extend_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        out.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+        out.extend_from_slice(&[0u8; 4]);
+        out.extend_from_slice(data);
+    }
+    out
+}
+
+/// Bytes of an `index-dir/the-real-index` file listing (hash, last_used µs
+/// since 1601, size in bytes) entries.
+pub fn simple_index(version: u32, entries: &[(u64, i64, u64)]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&0x656e74657220796fu64.to_le_bytes());
+    payload.extend_from_slice(&version.to_le_bytes());
+    payload.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    let cache_size: u64 = entries.iter().map(|e| e.2).sum();
+    payload.extend_from_slice(&cache_size.to_le_bytes());
+    if version >= 7 {
+        payload.extend_from_slice(&0u32.to_le_bytes());
+    }
+    for (hash, last_used, size) in entries {
+        payload.extend_from_slice(&hash.to_le_bytes());
+        payload.extend_from_slice(&last_used.to_le_bytes());
+        let size = if version >= 8 { (size.div_ceil(256)) << 8 } else { *size };
+        payload.extend_from_slice(&size.to_le_bytes());
+    }
+    payload.extend_from_slice(&0i64.to_le_bytes());
+
+    let mut out = Vec::with_capacity(8 + payload.len());
+    out.extend_from_slice(&(payload.len() as u32 + 4).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes()); // crc, unchecked
+    out.extend_from_slice(&payload);
+    out
+}
+
+// ---------------------------------------------------------------------------
+// MP4 boxes for tests
+// ---------------------------------------------------------------------------
+
+/// A box around `payload`, as the moov rebuild writes it.
+#[cfg(test)]
+pub(crate) use crate::moov_rebuild::mp4_box as boxed;
+
+/// Payload of a full box (version 0, no flags) holding big-endian integers.
+#[cfg(test)]
+pub(crate) fn full(values: &[u32]) -> Vec<u8> {
+    let mut p = vec![0u8; 4];
+    p.extend(values.iter().flat_map(|v| v.to_be_bytes()));
+    p
+}
@@ -0,0 +1,384 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::mp4_samples::{be_u32, be_u64, child, children};
+use crate::reconstruction::{GapPolicy, ReconstructionResult};
+
+// ─── Fragmented MP4 reassembly ──────────────────────────────────────────────
+//
+// Streamed video (MSE players, HLS with fMP4, DASH) is not one mdat with a
+// trailing moov: an init segment carries ftyp and a moov whose mvex announces
+// fragments, and the media follows as moof/mdat pairs, usually one HTTP
+// response — one cache entry — per segment. Each moof is self-describing: its
+// mfhd sequence number gives the order, tfdt the decode time, and the trun
+// data offsets are relative to the moof itself, so fragments can be put back
+// in order and simply appended to the init segment. A missing fragment leaves
+// a jump in playback rather than corrupting what follows, which is why gaps
+// are never zero-filled here.
+
+/// Boxes dropped from fragments. styp/sidx/ssix describe the segment file
+/// they came in and mfra points at byte offsets in the original stream;
+/// neither holds once the fragments are joined.
+const DROPPED_BOXES: &[&[u8; 4]] = &[b"styp", b"sidx", b"ssix", b"mfra"];
+
+/// tfhd flag: base_data_offset present (absolute file offsets).
+const TFHD_BASE_DATA_OFFSET: u32 = 0x1;
+
+/// (type, whole box including its header)
+pub(crate) type WholeBox<'a> = ([u8; 4], &'a [u8]);
+
+/// Top-level boxes. A box running past the end of the data ends the list and
+/// its type is returned separately as cut off.
+pub(crate) fn top_level_boxes(data: &[u8]) -> (Vec<WholeBox<'_>>, Option<[u8; 4]>) {
+    let mut boxes = Vec::new();
+    let mut pos = 0usize;
+    while pos + 8 <= data.len() {
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        if !kind.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            break;
+        }
+        let size = match be_u32(data, pos) {
+            Some(1) => be_u64(data, pos + 8).unwrap_or(0),
+            Some(0) => (data.len() - pos) as u64,
+            size => size.unwrap_or(0) as u64,
+        };
+        if size < 8 {
+            break;
+        }
+        if pos as u64 + size > data.len() as u64 {
+            return (boxes, Some(kind));
+        }
+        let end = pos + size as usize;
+        boxes.push((kind, &data[pos..end]));
+        pos = end;
+    }
+    (boxes, None)
+}
+
+/// Payload of a whole box.
+fn payload(whole: &[u8]) -> &[u8] {
+    crate::box_payload(whole, 0, whole.len())
+}
+
+/// An init segment: ftyp (if present) and a moov with mvex.
+struct InitSegment<'a> {
+    ftyp: Option<&'a [u8]>,
+    moov: &'a [u8],
+    track_ids: HashSet<u32>,
+}
+
+fn init_segment(data: &[u8]) -> Option<InitSegment<'_>> {
+    let (boxes, _) = top_level_boxes(data);
+    let ftyp = boxes.iter().find(|(kind, _)| kind == b"ftyp").map(|(_, b)| *b);
+    let moov = boxes.iter().find(|(kind, _)| kind == b"moov").map(|(_, b)| *b)?;
+    child(payload(moov), b"mvex")?;
+    let track_ids = children(payload(moov))
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .filter_map(|(_, trak)| {
+            // tkhd: version(1) flags(3), then times (v0: 2×4, v1: 2×8), track_ID
+            let tkhd = child(trak, b"tkhd")?;
+            be_u32(tkhd, if tkhd.first() == Some(&1) { 20 } else { 12 })
+        })
+        .collect();
+    Some(InitSegment { ftyp, moov, track_ids })
+}
+
+/// Whether `data` starts like an fMP4 init segment (the header of a chunked
+/// fragmented upload looks like this instead of ftyp + mdat).
+pub(crate) fn is_init_segment(data: &[u8]) -> bool {
+    init_segment(data).is_some() && crate::find_mp4_box(data, b"mdat").is_none()
+}
+
+/// Whether `data` starts with ftyp/moov of a fragmented MP4, whatever follows.
+pub(crate) fn has_init_segment(data: &[u8]) -> bool {
+    init_segment(data).is_some()
+}
+
+#[derive(Debug)]
+struct Fragment<'a> {
+    sequence: u32,
+    decode_time: Option<u64>,
+    track_ids: Vec<u32>,
+    /// moof with anything that belongs to it (emsg/prft before it, mdat after)
+    boxes: Vec<&'a [u8]>,
+    absolute_offsets: bool,
+}
+
+impl Fragment<'_> {
+    fn len(&self) -> usize {
+        self.boxes.iter().map(|b| b.len()).sum()
+    }
+}
+
+fn parse_moof(moof: &[u8]) -> Option<(u32, Option<u64>, Vec<u32>, bool)> {
+    let body = payload(moof);
+    // mfhd: version/flags(4) sequence_number(4)
+    let sequence = be_u32(child(body, b"mfhd")?, 4)?;
+    let mut decode_time = None;
+    let mut track_ids = Vec::new();
+    let mut absolute = false;
+    for (_, traf) in children(body).into_iter().filter(|(kind, _)| kind == b"traf") {
+        // tfhd: version(1) flags(3) track_ID(4)
+        let tfhd = child(traf, b"tfhd")?;
+        absolute |= be_u32(tfhd, 0)? & 0xFF_FFFF & TFHD_BASE_DATA_OFFSET != 0;
+        track_ids.push(be_u32(tfhd, 4)?);
+        // tfdt: version(1) flags(3) baseMediaDecodeTime (v0: 4, v1: 8)
+        if let Some(tfdt) = child(traf, b"tfdt") {
+            let time = if tfdt.first() == Some(&1) { be_u64(tfdt, 4) } else { be_u32(tfdt, 4).map(u64::from) };
+            decode_time = decode_time.max(time);
+        }
+    }
+    Some((sequence, decode_time, track_ids, absolute))
+}
+
+/// The fragments in one file, and whether it ended in a cut-off box.
+fn fragments(data: &[u8]) -> (Vec<Fragment<'_>>, bool) {
+    let (boxes, cut_off) = top_level_boxes(data);
+    let mut out: Vec<Fragment> = Vec::new();
+    let mut pending: Vec<&[u8]> = Vec::new();
+    for (kind, whole) in boxes {
+        match &kind {
+            b"moof" => {
+                let Some((sequence, decode_time, track_ids, absolute_offsets)) = parse_moof(whole) else {
+                    pending.clear();
+                    continue;
+                };
+                pending.push(whole);
+                let boxes = std::mem::take(&mut pending);
+                out.push(Fragment { sequence, decode_time, track_ids, boxes, absolute_offsets });
+            }
+            b"mdat" => {
+                if let Some(fragment) = out.last_mut().filter(|_| pending.is_empty()) {
+                    fragment.boxes.push(whole);
+                }
+            }
+            b"emsg" | b"prft" => pending.push(whole),
+            _ => {}
+        }
+    }
+    // A moof whose mdat was cut off can't play.
+    let before = out.len();
+    out.retain(|f| f.boxes.iter().any(|b| &b[4..8] == b"mdat"));
+    let truncated = cut_off.is_some() || out.len() < before;
+    (out, truncated)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Fmp4Reconstruction {
+    #[serde(flatten)]
+    pub result: ReconstructionResult,
+    /// Fragments written, in sequence order
+    pub fragments: usize,
+    /// Sequence numbers absent between the first and last fragment of each track
+    pub missing_fragments: u64,
+    /// Fragments seen more than once (re-fetched segments); one copy is kept
+    pub duplicate_fragments: usize,
+    /// Fragments for tracks the init segment doesn't have (another rendition)
+    pub foreign_fragments: usize,
+    /// Fragments cut off before the end of their mdat
+    pub cut_off_fragments: usize,
+    /// Fragments addressing their data by absolute offset; these may not play
+    pub absolute_offsets: usize,
+}
+
+/// Join the init segment and fragments found in `files` into one fMP4.
+pub fn reassemble(files: &[(String, Vec<u8>)], policy: GapPolicy) -> Result<(Vec<u8>, Fmp4Reconstruction), String> {
+    let mut all: Vec<Fragment> = Vec::new();
+    let mut cut_off_fragments = 0;
+    for (path, data) in files {
+        let (found, truncated) = fragments(data);
+        if truncated {
+            println!("[fmp4] {} ends in a cut-off fragment", path);
+            cut_off_fragments += 1;
+        }
+        all.extend(found);
+    }
+    // With several init segments (renditions), take the one most fragments belong to.
+    let init = files
+        .iter()
+        .filter_map(|(_, data)| init_segment(data))
+        .max_by_key(|init| all.iter().filter(|f| f.track_ids.iter().all(|id| init.track_ids.contains(id))).count())
+        .ok_or("No init segment (ftyp + moov with mvex) among the files")?;
+
+    let before = all.len();
+    all.retain(|f| f.track_ids.iter().all(|id| init.track_ids.contains(id)));
+    let foreign_fragments = before - all.len();
+    // Each track of a demuxed stream (DASH audio and video) counts its own
+    // sequence numbers, so a fragment is only a copy of one for the same tracks.
+    all.sort_by(|a, b| (a.sequence, &a.track_ids, a.decode_time).cmp(&(b.sequence, &b.track_ids, b.decode_time)));
+    // Keep the largest copy of a re-fetched fragment.
+    let before = all.len();
+    let mut deduped: Vec<Fragment> = Vec::with_capacity(all.len());
+    for fragment in all {
+        match deduped.last_mut() {
+            Some(last) if last.sequence == fragment.sequence && last.track_ids == fragment.track_ids => {
+                if fragment.len() > last.len() {
+                    *last = fragment;
+                }
+            }
+            _ => deduped.push(fragment),
+        }
+    }
+    let duplicate_fragments = before - deduped.len();
+    if deduped.is_empty() {
+        return Err("No fragments (moof + mdat) for the init segment's tracks".to_string());
+    }
+
+    let mut result = ReconstructionResult::new(policy);
+    let mut out = Vec::with_capacity(
+        init.ftyp.map_or(0, <[u8]>::len) + init.moov.len() + deduped.iter().map(Fragment::len).sum::<usize>(),
+    );
+    out.extend_from_slice(init.ftyp.unwrap_or_default());
+    out.extend_from_slice(init.moov);
+    let average = deduped.iter().map(Fragment::len).sum::<usize>() as u64 / deduped.len() as u64;
+    let mut missing_fragments = 0;
+    let mut written = 0;
+    let mut absolute_offsets = 0;
+    let mut previous: HashMap<&[u32], u32> = HashMap::new();
+    for fragment in &deduped {
+        let last = previous.insert(&fragment.track_ids, fragment.sequence);
+        if let Some(missing) = last.map(|p| fragment.sequence.saturating_sub(p).saturating_sub(1) as u64) {
+            if missing > 0 {
+                missing_fragments += missing;
+                result.record_gap(missing * average);
+                println!(
+                    "[fmp4] {} fragment(s) missing before sequence {} ({:?})",
+                    missing, fragment.sequence, policy
+                );
+                // Fragments can't be zero-filled; padding means the same as
+                // dropping here, truncation stops at the first hole.
+                if policy == GapPolicy::TruncateAtGap {
+                    result.truncated = true;
+                    break;
+                }
+            }
+        }
+        absolute_offsets += fragment.absolute_offsets as usize;
+        for part in &fragment.boxes {
+            if !DROPPED_BOXES.iter().any(|kind| &part[4..8] == *kind) {
+                out.extend_from_slice(part);
+            }
+        }
+        written += 1;
+    }
+    if absolute_offsets > 0 {
+        eprintln!("[fmp4] {} fragment(s) use absolute base_data_offset and may not play", absolute_offsets);
+    }
+    result.finish(&out);
+    let report = Fmp4Reconstruction {
+        result,
+        fragments: written,
+        missing_fragments,
+        duplicate_fragments,
+        foreign_fragments,
+        cut_off_fragments,
+        absolute_offsets,
+    };
+    Ok((out, report))
+}
+
+/// Read `paths` and reassemble them into `output`.
+pub(crate) fn reconstruct(paths: &[String], output: &str, policy: GapPolicy) -> Result<Fmp4Reconstruction, String> {
+    let files = paths
+        .iter()
+        .map(|path| crate::read_cache_body(path).map(|data| (path.clone(), data)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let (out, report) = reassemble(&files, policy)?;
+    crate::output_file::write_output(output, &out)?;
+    println!(
+        "[fmp4] Wrote {} fragments ({} missing, {} duplicate) to {}",
+        report.fragments, report.missing_fragments, report.duplicate_fragments, output
+    );
+    Ok(report)
+}
+
+/// Reassemble a fragmented MP4 from its init segment and fragment files, in
+/// any order; the init segment is found among `paths`. `gap_policy` decides
+/// whether playback may jump over missing fragments or stops before them.
+/// Requires a `consent` token covering `output`.
+#[tauri::command(async)]
+pub fn reconstruct_fragmented_mp4(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    paths: Vec<String>,
+    output: String,
+    gap_policy: Option<GapPolicy>,
+    consent: String,
+) -> Result<Fmp4Reconstruction, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("reconstructing {}", output));
+    if let Some(parent) = std::path::Path::new(&output).parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
+    }
+    reconstruct(&paths, &output, gap_policy.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{boxed, full};
+
+    fn init(tracks: &[u32]) -> Vec<u8> {
+        let mut moov = boxed(b"mvhd", &[0; 100]);
+        let mut mvex = Vec::new();
+        for &track in tracks {
+            moov.extend(boxed(b"trak", &boxed(b"tkhd", &full(&[0, 0, track, 0, 0]))));
+            mvex.extend(boxed(b"trex", &full(&[track, 1, 0, 0, 0])));
+        }
+        moov.extend(boxed(b"mvex", &mvex));
+        [boxed(b"ftyp", b"iso6\0\0\0\0"), boxed(b"moov", &moov)].concat()
+    }
+
+    fn fragment(track: u32, sequence: u32, media: &[u8]) -> Vec<u8> {
+        // tfhd flags: default-base-is-moof
+        let tfhd = [0x02_0000u32.to_be_bytes(), track.to_be_bytes()].concat();
+        let traf = [boxed(b"tfhd", &tfhd), boxed(b"tfdt", &full(&[sequence * 1000]))];
+        let moof = [boxed(b"mfhd", &full(&[sequence])), boxed(b"traf", &traf.concat())].concat();
+        [boxed(b"styp", b"msdh\0\0\0\0"), boxed(b"moof", &moof), boxed(b"mdat", media)].concat()
+    }
+
+    #[test]
+    fn orders_fragments_and_skips_foreign_ones() {
+        let file = |name: &str, data: Vec<u8>| (name.to_string(), data);
+        let files = vec![
+            file("seg3", fragment(1, 3, b"third")),
+            file("audio", [init(&[2]), fragment(2, 1, b"aac")].concat()),
+            file("seg1", fragment(1, 1, b"first")),
+            file("init", init(&[1])),
+            file("seg5", [fragment(1, 5, b"fifth"), fragment(1, 6, b"sixth")[..40].to_vec()].concat()),
+            file("seg1-refetch", fragment(1, 1, b"first!")),
+        ];
+        let (out, report) = reassemble(&files, GapPolicy::PadZeros).unwrap();
+        assert_eq!(
+            (report.fragments, report.missing_fragments, report.duplicate_fragments, report.foreign_fragments),
+            (3, 2, 1, 1)
+        );
+        assert_eq!(report.cut_off_fragments, 1);
+        let (boxes, cut_off) = top_level_boxes(&out);
+        assert!(cut_off.is_none());
+        let kinds: Vec<[u8; 4]> = boxes.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [*b"ftyp", *b"moov", *b"moof", *b"mdat", *b"moof", *b"mdat", *b"moof", *b"mdat"]);
+        let media: Vec<&[u8]> = boxes.iter().filter(|(kind, _)| kind == b"mdat").map(|(_, b)| &b[8..]).collect();
+        assert_eq!(media, [&b"first!"[..], b"third", b"fifth"]);
+        assert!(is_init_segment(&init(&[1])) && !is_init_segment(&fragment(1, 1, b"x")));
+
+        let (_, report) = reassemble(&files, GapPolicy::TruncateAtGap).unwrap();
+        assert_eq!((report.fragments, report.result.truncated), (1, true));
+    }
+
+    #[test]
+    fn keeps_each_tracks_sequence_apart() {
+        // Demuxed audio and video fragments both start at sequence 1.
+        let files = vec![
+            ("init".to_string(), init(&[1, 2])),
+            ("video".to_string(), [fragment(1, 1, b"v1"), fragment(1, 2, b"v2")].concat()),
+            ("audio".to_string(), [fragment(2, 1, b"a1"), fragment(2, 3, b"a3")].concat()),
+            ("audio-refetch".to_string(), fragment(2, 3, b"a3!")),
+        ];
+        let (out, report) = reassemble(&files, GapPolicy::PadZeros).unwrap();
+        assert_eq!((report.fragments, report.missing_fragments, report.duplicate_fragments), (4, 1, 1));
+        let (boxes, _) = top_level_boxes(&out);
+        let media: Vec<&[u8]> = boxes.iter().filter(|(kind, _)| kind == b"mdat").map(|(_, b)| &b[8..]).collect();
+        assert_eq!(media, [&b"v1"[..], b"a1", b"v2", b"a3!"]);
+    }
+}
@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::custom_paths::{read_json, write_json};
+
+// ─── File hashing ───────────────────────────────────────────────────────────
+//
+// SHA-256 of recovered files (the `sha256` column of mapping.csv, and the
+// duplicate outputs it reports) is computed by a small worker pool reading in
+// fixed-size chunks, with progress reported in bytes. Digests are remembered
+// keyed by path and validated against size + mtime, so a second export over
+// the same files reads nothing. The memo is kept in `hash_memo.json` in the
+// app config folder between runs, and split into shards picked by a hash
+// prefix of the path, so workers finishing at the same time don't queue on
+// one lock.
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+const SHARDS: usize = 16;
+const MAX_WORKERS: usize = 8;
+const MEMO_FILE: &str = "hash_memo.json";
+/// Digests kept in the memo file; the rest are dropped, oldest saved first.
+const MAX_SAVED: usize = 50_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileHash {
+    pub path: String,
+    /// Lowercase hex SHA-256; None if the file could not be read
+    pub sha256: Option<String>,
+    pub size: u64,
+    /// Served from the memo without reading the file
+    pub cached: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HashProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedDigest {
+    path: String,
+    #[serde(flatten)]
+    stamp: Stamp,
+    sha256: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct MemoFile {
+    digests: Vec<SavedDigest>,
+}
+
+/// Where the memo is saved; unset (tests, no config dir) keeps it in memory.
+static SAVE_TO: OnceLock<PathBuf> = OnceLock::new();
+
+struct Memo {
+    shards: Vec<Mutex<HashMap<String, (Stamp, String)>>>,
+}
+
+impl Memo {
+    fn shard(&self, path: &str) -> &Mutex<HashMap<String, (Stamp, String)>> {
+        // FNV-1a; the top bits pick the shard.
+        let h = path.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+        &self.shards[(h >> 60) as usize % SHARDS]
+    }
+
+    fn get(&self, path: &str, stamp: &Stamp) -> Option<String> {
+        let shard = self.shard(path).lock().unwrap_or_else(|e| e.into_inner());
+        shard.get(path).filter(|(s, _)| s == stamp).map(|(_, digest)| digest.clone())
+    }
+
+    fn put(&self, path: &str, stamp: Stamp, digest: String) {
+        let mut shard = self.shard(path).lock().unwrap_or_else(|e| e.into_inner());
+        shard.insert(path.to_string(), (stamp, digest));
+    }
+
+    /// Digests of files that still exist unchanged, at most `MAX_SAVED`.
+    fn current(&self) -> Vec<SavedDigest> {
+        let mut out = Vec::new();
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap_or_else(|e| e.into_inner());
+            out.extend(shard.iter().map(|(path, (stamp, sha256))| SavedDigest {
+                path: path.clone(),
+                stamp: stamp.clone(),
+                sha256: sha256.clone(),
+            }));
+        }
+        out.retain(|d| stamp_of(Path::new(&d.path)).is_some_and(|stamp| stamp == d.stamp));
+        out.truncate(MAX_SAVED);
+        out
+    }
+}
+
+fn memo() -> &'static Memo {
+    static MEMO: OnceLock<Memo> = OnceLock::new();
+    MEMO.get_or_init(|| Memo { shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect() })
+}
+
+fn stamp_of(path: &Path) -> Option<Stamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some(Stamp { size: meta.len(), modified: meta.modified().ok() })
+}
+
+/// Fill the memo from `hash_memo.json` and save it there from now on. An
+/// unreadable file is logged and starts an empty memo.
+pub fn load_saved(app: &tauri::AppHandle) {
+    let file = match crate::custom_paths::config_file(app, MEMO_FILE) {
+        Ok(file) => file,
+        Err(e) => return eprintln!("[hashing] {}", e),
+    };
+    match read_json::<MemoFile>(&file) {
+        Ok(saved) => {
+            for d in saved.digests {
+                memo().put(&d.path, d.stamp, d.sha256);
+            }
+        }
+        Err(e) => eprintln!("[hashing] {}", e),
+    }
+    let _ = SAVE_TO.set(file);
+}
+
+fn save() {
+    let Some(file) = SAVE_TO.get() else {
+        return;
+    };
+    if let Err(e) = write_json(file, &MemoFile { digests: memo().current() }) {
+        eprintln!("[hashing] {}", e);
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hash one file in chunks, reporting bytes read through `on_bytes`.
+fn hash_file(path: &str, on_bytes: &dyn Fn(u64)) -> Result<String, String> {
+    let _permit = crate::io_limit::acquire_for(path);
+    let mut file = std::fs::File::open(path).map_err(|e| crate::format_read_error(path, &e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        on_bytes(n as u64);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn hash_one(path: &str, on_bytes: &dyn Fn(u64)) -> FileHash {
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) => {
+            return FileHash {
+                path: path.to_string(),
+                sha256: None,
+                size: 0,
+                cached: false,
+                error: Some(crate::format_read_error(path, &e)),
+            }
+        }
+    };
+    let stamp = Stamp { size: meta.len(), modified: meta.modified().ok() };
+    if let Some(digest) = memo().get(path, &stamp) {
+        on_bytes(stamp.size);
+        return FileHash { path: path.to_string(), sha256: Some(digest), size: stamp.size, cached: true, error: None };
+    }
+    match hash_file(path, on_bytes) {
+        Ok(digest) => {
+            let size = stamp.size;
+            memo().put(path, stamp, digest.clone());
+            FileHash { path: path.to_string(), sha256: Some(digest), size, cached: false, error: None }
+        }
+        Err(e) => FileHash { path: path.to_string(), sha256: None, size: stamp.size, cached: false, error: Some(e) },
+    }
+}
+
+/// Hash `paths` on a worker pool. Results are in input order. The memo is
+/// saved when anything new was read.
+pub fn hash_files_with(paths: &[String], on_progress: &(dyn Fn(HashProgress) + Sync)) -> Vec<FileHash> {
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(MAX_WORKERS)
+        .min(paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let bytes = AtomicU64::new(0);
+    let results: Vec<Mutex<Option<FileHash>>> = paths.iter().map(|_| Mutex::new(None)).collect();
+    let report = || {
+        on_progress(HashProgress {
+            files_done: done.load(Ordering::Relaxed),
+            files_total: paths.len(),
+            bytes_done: bytes.load(Ordering::Relaxed),
+        })
+    };
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+                let hash = hash_one(path, &|n| {
+                    bytes.fetch_add(n, Ordering::Relaxed);
+                    report();
+                });
+                *results[i].lock().unwrap_or_else(|e| e.into_inner()) = Some(hash);
+                done.fetch_add(1, Ordering::Relaxed);
+                report();
+            });
+        }
+    });
+    let hashes: Vec<FileHash> = results
+        .into_iter()
+        .filter_map(|slot| slot.into_inner().unwrap_or_else(|e| e.into_inner()))
+        .collect();
+    if hashes.iter().any(|h| !h.cached && h.sha256.is_some()) {
+        save();
+    }
+    println!(
+        "[hashing] {} files ({} from memo), {} errors",
+        hashes.len(),
+        hashes.iter().filter(|h| h.cached).count(),
+        hashes.iter().filter(|h| h.error.is_some()).count()
+    );
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha256_hex(data: &[u8]) -> String {
+        to_hex(&Sha256::digest(data))
+    }
+
+    #[test]
+    fn hashes_in_order_and_memoizes() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-hashing-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<String> = (0..5)
+            .map(|i| {
+                let p = dir.join(format!("f{}", i));
+                std::fs::write(&p, vec![i as u8; 1000 * (i + 1)]).unwrap();
+                p.to_string_lossy().to_string()
+            })
+            .collect();
+        let mut with_missing = paths.clone();
+        with_missing.push(dir.join("missing").to_string_lossy().to_string());
+
+        let first = hash_files_with(&with_missing, &|_| {});
+        let second = hash_files_with(&paths, &|_| {});
+        let saved = serde_json::to_string(&MemoFile { digests: memo().current() }).unwrap();
+        let reloaded: MemoFile = serde_json::from_str(&saved).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(first.len(), 6);
+        assert_eq!(first[2].sha256.as_deref(), Some(sha256_hex(&[2u8; 3000]).as_str()));
+        assert!(first[5].error.is_some());
+        assert!(second.iter().all(|h| h.cached));
+        let digest = reloaded.digests.iter().find(|d| d.path == paths[2]).unwrap();
+        assert_eq!((digest.stamp.size, digest.sha256.as_str()), (3000, first[2].sha256.as_deref().unwrap()));
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+}
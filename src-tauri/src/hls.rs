@@ -0,0 +1,510 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::reconstruction::{GapPolicy, ReconstructionResult};
+
+// ─── HLS transport-stream reassembly ────────────────────────────────────────
+//
+// HLS players fetch a video as a run of short MPEG-TS segments listed in an
+// .m3u8 playlist, and the cache keeps each segment as its own entry. A
+// transport stream is a sequence of 188-byte packets that each start with the
+// sync byte 0x47, so segments can be joined by plain concatenation once they
+// are in order. The order comes from the playlist when one was cached
+// (#EXT-X-MEDIA-SEQUENCE plus the segment's position), otherwise from the
+// number in the segment file names, and as a last resort from the first
+// presentation timestamp in each segment. Like fMP4 fragments, a missing
+// segment can't be zero-filled: playback jumps over it.
+
+const TS_PACKET: usize = 188;
+/// (packet size, offset of the sync byte): plain TS, M2TS with a 4-byte
+/// timestamp prefix, and TS followed by 16 bytes of Reed-Solomon parity.
+const PACKET_LAYOUTS: [(usize, usize); 3] = [(188, 0), (192, 4), (204, 0)];
+/// Sync bytes checked before calling data a transport stream.
+const MIN_SYNC_RUN: usize = 5;
+const SYNC_BYTE: u8 = 0x47;
+
+/// How the segments were put in order, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentOrder {
+    /// Position in a cached media playlist.
+    Playlist,
+    /// The number in each segment's file name (segment_00042.ts).
+    UrlNumber,
+    /// First PES presentation timestamp; gaps can't be detected.
+    Timestamp,
+    /// Nothing covered every segment; kept in the order given.
+    Input,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HlsReconstruction {
+    #[serde(flatten)]
+    pub result: ReconstructionResult,
+    /// Segments written
+    pub segments: usize,
+    /// Sequence numbers absent between the first and last segment
+    pub missing_segments: u64,
+    /// Re-fetched segments; the largest copy is kept
+    pub duplicate_segments: usize,
+    /// Segments the playlist marks as encrypted; skipped
+    pub encrypted_segments: usize,
+    /// Segments ending in a partial packet, which was dropped
+    pub cut_off_segments: usize,
+    /// Files that were neither a transport stream nor a playlist
+    pub unrecognised_files: usize,
+    /// Media playlists found among the files
+    pub playlists: usize,
+    pub segment_order: SegmentOrder,
+}
+
+/// Packet size and sync offset if `data` starts as a transport stream.
+fn packet_layout(data: &[u8]) -> Option<(usize, usize)> {
+    PACKET_LAYOUTS.into_iter().find(|&(size, offset)| {
+        let packets = data.len().saturating_sub(offset) / size;
+        let run = packets.min(MIN_SYNC_RUN);
+        // A lone packet only counts when it is the whole body.
+        (run >= 2 || (run == 1 && data.len() == size))
+            && (0..run).all(|i| data[offset + i * size] == SYNC_BYTE)
+    })
+}
+
+/// Whether `data` starts with MPEG-TS packets (0x47 repeating every 188,
+/// 192 or 204 bytes).
+pub fn is_transport_stream(data: &[u8]) -> bool {
+    packet_layout(data).is_some()
+}
+
+/// The 188-byte packets of a segment, and whether it ended mid-packet.
+/// After a lost sync the next position where two packets line up is used.
+fn packets(data: &[u8]) -> (Vec<&[u8]>, bool) {
+    let Some((size, offset)) = packet_layout(data) else {
+        return (Vec::new(), false);
+    };
+    let mut found = Vec::new();
+    let mut pos = offset;
+    while pos + TS_PACKET <= data.len() {
+        if data[pos] == SYNC_BYTE {
+            found.push(&data[pos..pos + TS_PACKET]);
+            pos += size;
+            continue;
+        }
+        let resync = (pos + 1..data.len()).find(|&p| {
+            data[p] == SYNC_BYTE && data.get(p + size).is_none_or(|&b| b == SYNC_BYTE)
+        });
+        match resync {
+            Some(p) => pos = p,
+            None => return (found, false),
+        }
+    }
+    (found, pos < data.len())
+}
+
+/// Presentation timestamp of the first PES header carrying one.
+fn first_pts(packets: &[&[u8]]) -> Option<u64> {
+    packets.iter().find_map(|packet| {
+        // payload_unit_start_indicator
+        if packet[1] & 0x40 == 0 {
+            return None;
+        }
+        let payload = match (packet[3] >> 4) & 0x3 {
+            0x1 => 4,
+            0x3 => 5 + packet[4] as usize,
+            _ => return None,
+        };
+        let pes = packet.get(payload..)?;
+        if pes.len() < 14 || pes[0..3] != [0, 0, 1] || pes[7] & 0x80 == 0 {
+            return None;
+        }
+        let p = &pes[9..14];
+        Some(
+            ((p[0] as u64 >> 1) & 0x7) << 30
+                | (p[1] as u64) << 22
+                | (p[2] as u64 >> 1) << 15
+                | (p[3] as u64) << 7
+                | p[4] as u64 >> 1,
+        )
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Playlists
+// ---------------------------------------------------------------------------
+
+struct PlaylistSegment {
+    url: String,
+    sequence: u64,
+    encrypted: bool,
+}
+
+/// Segments of a media playlist with their sequence numbers; None for
+/// anything that isn't an .m3u8 (master playlists come back empty).
+fn parse_playlist(data: &[u8], playlist_url: &str) -> Option<Vec<PlaylistSegment>> {
+    let text = std::str::from_utf8(data).ok()?.trim_start_matches('\u{feff}');
+    if !text.trim_start().starts_with("#EXTM3U") {
+        return None;
+    }
+    let mut sequence = 0u64;
+    let mut encrypted = false;
+    let mut segments = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(first) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            sequence = first.trim().parse().unwrap_or(0);
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            encrypted = !attributes.split(',').any(|a| a.trim() == "METHOD=NONE");
+        } else if !line.starts_with('#') {
+            segments.push(PlaylistSegment { url: resolve(playlist_url, line), sequence, encrypted });
+            sequence += 1;
+        }
+    }
+    Some(segments)
+}
+
+/// `reference` resolved against `base` (RFC 3986, minus the corner cases
+/// playlists don't use).
+fn resolve(base: &str, reference: &str) -> String {
+    let Some(scheme_end) = base.find("://") else {
+        return reference.to_string();
+    };
+    if reference.contains("://") {
+        return reference.to_string();
+    }
+    if let Some(rest) = reference.strip_prefix("//") {
+        return format!("{}://{}", &base[..scheme_end], rest);
+    }
+    let base = base.split(['?', '#']).next().unwrap_or(base);
+    let authority_end = base[scheme_end + 3..].find('/').map_or(base.len(), |i| scheme_end + 3 + i);
+    let (origin, base_path) = base.split_at(authority_end);
+    let (reference_path, query) = reference.find('?').map_or((reference, ""), |i| reference.split_at(i));
+    let joined = if reference_path.starts_with('/') {
+        reference_path.to_string()
+    } else {
+        let dir = base_path.rfind('/').map_or("/", |i| &base_path[..=i]);
+        format!("{}{}", dir, reference_path)
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for part in joined.split('/').skip(1) {
+        match part {
+            "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    format!("{}/{}{}", origin, parts.join("/"), query)
+}
+
+/// URL without query string or fragment, for matching segments whose
+/// signed query parameters changed between playlist and request.
+fn without_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+/// The run of digits in each file name that tells the segments apart:
+/// the last one whose values are all distinct.
+fn file_numbers(urls: &[Option<&str>]) -> Option<Vec<u64>> {
+    let runs: Vec<Vec<u64>> = urls
+        .iter()
+        .map(|url| {
+            let name = without_query((*url)?).rsplit('/').next()?;
+            let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+            let numbers: Vec<u64> =
+                stem.split(|c: char| !c.is_ascii_digit()).filter_map(|run| run.parse().ok()).collect();
+            Some(numbers)
+        })
+        .collect::<Option<_>>()?;
+    let width = runs.first()?.len();
+    if runs.iter().any(|r| r.len() != width) {
+        return None;
+    }
+    (0..width).rev().find_map(|column| {
+        let values: Vec<u64> = runs.iter().map(|r| r[column]).collect();
+        let mut sorted = values.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        (sorted.len() == values.len() || runs.len() == 1).then_some(values)
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Reassembly
+// ---------------------------------------------------------------------------
+
+/// One cache entry given to the reassembly.
+pub struct HlsFile {
+    pub path: String,
+    /// Request URL; needed to match segments to a playlist and to resolve
+    /// the playlist's relative segment URIs.
+    pub url: Option<String>,
+    pub data: Vec<u8>,
+}
+
+struct Segment<'a> {
+    url: Option<&'a str>,
+    packets: Vec<&'a [u8]>,
+}
+
+/// Join the segments found in `files` into one transport stream. Playlists
+/// among the files are used for ordering and are not written.
+pub fn reassemble(files: &[HlsFile], policy: GapPolicy) -> Result<(Vec<u8>, HlsReconstruction), String> {
+    let mut sequences: HashMap<&str, (u64, bool)> = HashMap::new();
+    let mut loose_sequences: HashMap<&str, Option<(u64, bool)>> = HashMap::new();
+    let mut playlist_segments = Vec::new();
+    let mut segments = Vec::new();
+    let mut unrecognised_files = 0;
+    let mut cut_off_segments = 0;
+    for file in files {
+        if is_transport_stream(&file.data) {
+            let (found, cut_off) = packets(&file.data);
+            if cut_off {
+                println!("[hls] {} ends in a partial packet", file.path);
+                cut_off_segments += 1;
+            }
+            segments.push(Segment { url: file.url.as_deref(), packets: found });
+        } else if let Some(listed) = parse_playlist(&file.data, file.url.as_deref().unwrap_or_default()) {
+            if !listed.is_empty() {
+                playlist_segments.push(listed);
+            }
+        } else {
+            println!("[hls] {} is neither a transport stream nor a playlist", file.path);
+            unrecognised_files += 1;
+        }
+    }
+    for segment in playlist_segments.iter().flatten() {
+        sequences.insert(&segment.url, (segment.sequence, segment.encrypted));
+        loose_sequences
+            .entry(without_query(&segment.url))
+            .and_modify(|known| {
+                if *known != Some((segment.sequence, segment.encrypted)) {
+                    *known = None;
+                }
+            })
+            .or_insert(Some((segment.sequence, segment.encrypted)));
+    }
+    if segments.is_empty() {
+        return Err("No MPEG-TS segments among the files".to_string());
+    }
+
+    // Strongest signal that covers every segment.
+    let listed: Option<Vec<(u64, bool)>> = segments
+        .iter()
+        .map(|s| {
+            let url = s.url?;
+            sequences.get(url).copied().or_else(|| loose_sequences.get(without_query(url)).copied().flatten())
+        })
+        .collect();
+    let urls: Vec<Option<&str>> = segments.iter().map(|s| s.url).collect();
+    let (keys, encrypted, segment_order) = if let Some(listed) = listed {
+        let (keys, encrypted) = listed.into_iter().unzip();
+        (keys, encrypted, SegmentOrder::Playlist)
+    } else if let Some(numbers) = file_numbers(&urls) {
+        (numbers, vec![false; segments.len()], SegmentOrder::UrlNumber)
+    } else if let Some(times) = segments.iter().map(|s| first_pts(&s.packets)).collect::<Option<Vec<u64>>>() {
+        (times, vec![false; segments.len()], SegmentOrder::Timestamp)
+    } else {
+        ((0..segments.len() as u64).collect(), vec![false; segments.len()], SegmentOrder::Input)
+    };
+    let mut ordered: Vec<(u64, bool, Segment)> =
+        keys.into_iter().zip(encrypted).zip(segments).map(|((key, enc), s)| (key, enc, s)).collect();
+    ordered.sort_by_key(|(key, _, _)| *key);
+
+    let encrypted_segments = ordered.iter().filter(|(_, encrypted, _)| *encrypted).count();
+    if encrypted_segments > 0 {
+        eprintln!("[hls] {} segment(s) are encrypted (EXT-X-KEY) and were skipped", encrypted_segments);
+    }
+    let before = ordered.len();
+    let mut deduped: Vec<(u64, Segment)> = Vec::with_capacity(ordered.len());
+    for (key, _, segment) in ordered.into_iter().filter(|(_, encrypted, _)| !encrypted) {
+        match deduped.last_mut() {
+            Some((last, kept)) if *last == key => {
+                if segment.packets.len() > kept.packets.len() {
+                    *kept = segment;
+                }
+            }
+            _ => deduped.push((key, segment)),
+        }
+    }
+    let duplicate_segments = before - encrypted_segments - deduped.len();
+    if deduped.iter().all(|(_, s)| s.packets.is_empty()) {
+        return Err("Every MPEG-TS segment is encrypted or empty".to_string());
+    }
+
+    let numbered = matches!(segment_order, SegmentOrder::Playlist | SegmentOrder::UrlNumber);
+    let mut result = ReconstructionResult::new(policy);
+    let total: usize = deduped.iter().map(|(_, s)| s.packets.len() * TS_PACKET).sum();
+    let average = total as u64 / deduped.len() as u64;
+    let mut out = Vec::with_capacity(total);
+    let mut missing_segments = 0;
+    let mut written = 0;
+    let mut previous: Option<u64> = None;
+    for (key, segment) in &deduped {
+        let missing = previous.filter(|_| numbered).map_or(0, |p| key.saturating_sub(p).saturating_sub(1));
+        if missing > 0 {
+            missing_segments += missing;
+            result.record_gap(missing * average);
+            println!("[hls] {} segment(s) missing before sequence {} ({:?})", missing, key, policy);
+            // Segments can't be zero-filled; padding means the same as
+            // dropping here, truncation stops at the first hole.
+            if policy == GapPolicy::TruncateAtGap {
+                result.truncated = true;
+                break;
+            }
+        }
+        previous = Some(*key);
+        for packet in &segment.packets {
+            out.extend_from_slice(packet);
+        }
+        written += 1;
+    }
+    result.finish(&out);
+    let report = HlsReconstruction {
+        result,
+        segments: written,
+        missing_segments,
+        duplicate_segments,
+        encrypted_segments,
+        cut_off_segments,
+        unrecognised_files,
+        playlists: playlist_segments.len(),
+        segment_order,
+    };
+    Ok((out, report))
+}
+
+/// A cache entry and, when the caller knows it, the URL it was fetched from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HlsSource {
+    pub path: String,
+    pub url: Option<String>,
+}
+
+/// Reassemble HLS segments (and any cached .m3u8 playlists) from `sources`
+/// into one .ts file. URLs not given are read from Simple Cache keys.
+/// `gap_policy` decides whether playback may jump over missing segments or
+/// stops before them. Requires a `consent` token covering `output`.
+#[tauri::command(async)]
+pub fn reconstruct_hls(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    sources: Vec<HlsSource>,
+    output: String,
+    gap_policy: Option<GapPolicy>,
+    consent: String,
+) -> Result<HlsReconstruction, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("reconstructing {}", output));
+    let files = sources
+        .into_iter()
+        .map(|source| {
+            let data = crate::read_cache_body(&source.path)?;
+            let url = source.url.or_else(|| {
+                crate::read_simple_cache_key(&source.path).map(|key| crate::cache_key::effective_url(&key).to_string())
+            });
+            Ok(HlsFile { path: source.path, url, data })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let (out, report) = reassemble(&files, gap_policy.unwrap_or_default())?;
+    crate::output_file::write_output(&output, &out)?;
+    println!(
+        "[hls] Wrote {} segments ({} missing, ordered by {:?}) to {}",
+        report.segments, report.missing_segments, report.segment_order, output
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A segment of `count` packets; the first starts a PES with `pts`.
+    fn segment(count: usize, pts: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        for i in 0..count {
+            let mut packet = vec![0xFFu8; TS_PACKET];
+            packet[0..4].copy_from_slice(&[SYNC_BYTE, if i == 0 { 0x41 } else { 0x01 }, 0x00, 0x10]);
+            if i == 0 {
+                let pts_bytes = [
+                    0x21 | ((pts >> 29) & 0xE) as u8,
+                    (pts >> 22) as u8,
+                    ((pts >> 14) & 0xFE) as u8 | 1,
+                    (pts >> 7) as u8,
+                    ((pts << 1) & 0xFE) as u8 | 1,
+                ];
+                packet[4..13].copy_from_slice(&[0, 0, 1, 0xE0, 0, 0, 0x80, 0x80, 5]);
+                packet[13..18].copy_from_slice(&pts_bytes);
+            }
+            out.extend_from_slice(&packet);
+        }
+        out
+    }
+
+    fn file(url: &str, data: Vec<u8>) -> HlsFile {
+        HlsFile { path: url.rsplit('/').next().unwrap().to_string(), url: Some(url.to_string()), data }
+    }
+
+    #[test]
+    fn recognises_sync_bytes() {
+        assert!(is_transport_stream(&segment(3, 0)));
+        let m2ts: Vec<u8> = segment(3, 0).chunks(TS_PACKET).flat_map(|p| [&[0u8; 4][..], p].concat()).collect();
+        assert!(is_transport_stream(&m2ts));
+        assert_eq!(packets(&m2ts).0.len(), 3);
+        assert!(!is_transport_stream(b"GIF89a"));
+        assert!(!is_transport_stream(&[SYNC_BYTE; 100]));
+        assert_eq!(first_pts(&packets(&segment(2, 90_000 * 7)).0), Some(630_000));
+    }
+
+    #[test]
+    fn takes_a_chunk_for_a_segment_only_when_served_as_ts() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-ts-entry-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir).unwrap();
+        let data = segment(4, 0);
+        let ts = cache.add_entry("https://a/seg.ts", &["HTTP/1.1 200 OK", "Content-Type: video/MP2T"], &data).unwrap();
+        let mp4 = cache.add_entry("https://a/v.mp4", &["HTTP/1.1 200 OK", "Content-Type: video/mp4"], &data).unwrap();
+        assert!(crate::is_transport_stream_entry(&ts, &data));
+        assert!(!crate::is_transport_stream_entry(&mp4, &data));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn orders_segments_by_playlist_and_skips_encrypted() {
+        let playlist = b"#EXTM3U\n#EXT-X-MEDIA-SEQUENCE:100\n#EXTINF:4,\nseg_a.ts?t=1\n#EXTINF:4,\nseg_b.ts\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"k\"\n#EXTINF:4,\n../other/seg_c.ts\n";
+        let files = [
+            file("https://cdn.example/v/seg_b.ts", segment(2, 0)),
+            file("https://cdn.example/other/seg_c.ts", segment(9, 0)),
+            file("https://cdn.example/v/index.m3u8", playlist.to_vec()),
+            file("https://cdn.example/v/seg_a.ts?t=2", segment(1, 0)),
+        ];
+        let (out, report) = reassemble(&files, GapPolicy::default()).unwrap();
+        assert_eq!(report.segment_order, SegmentOrder::Playlist);
+        assert_eq!((report.segments, report.encrypted_segments, report.playlists), (2, 1, 1));
+        assert_eq!(out.len(), 3 * TS_PACKET);
+        // seg_a's single packet starts the PES
+        assert_eq!(out[1], 0x41);
+    }
+
+    #[test]
+    fn falls_back_to_file_numbers_and_counts_gaps() {
+        let files = [
+            file("https://cdn.example/720p/seg-3-v1.ts", segment(1, 0)),
+            file("https://cdn.example/720p/seg-1-v1.ts", segment(1, 0)),
+            file("https://cdn.example/720p/seg-4-v1.ts", segment(1, 0)),
+        ];
+        let (_, report) = reassemble(&files, GapPolicy::CloseGaps).unwrap();
+        assert_eq!(report.segment_order, SegmentOrder::UrlNumber);
+        assert_eq!((report.segments, report.missing_segments), (3, 1));
+
+        let (out, report) = reassemble(&files, GapPolicy::TruncateAtGap).unwrap();
+        assert!(report.result.truncated);
+        assert_eq!(out.len(), TS_PACKET);
+    }
+
+    #[test]
+    fn resolves_relative_segment_uris() {
+        let base = "https://cdn.example/a/b/index.m3u8?token=x";
+        assert_eq!(resolve(base, "s1.ts"), "https://cdn.example/a/b/s1.ts");
+        assert_eq!(resolve(base, "../c/s1.ts?x=1"), "https://cdn.example/a/c/s1.ts?x=1");
+        assert_eq!(resolve(base, "/root.ts"), "https://cdn.example/root.ts");
+        assert_eq!(resolve(base, "//other.example/s.ts"), "https://other.example/s.ts");
+    }
+}
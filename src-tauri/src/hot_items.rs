@@ -0,0 +1,133 @@
+use serde::Serialize;
+
+use crate::blockfile_index::BlockfileCacheEntry;
+use crate::entry::CacheEntryGroup;
+use crate::events::Coalescer;
+
+// Early "hot item" notifications.
+//
+// A full scan of a large cache can take a while, and a user recovering one
+// urgent clip shouldn't have to wait for all of it. Entries that are very
+// likely complete, recoverable videos are reported through the
+// `scan-hot-items` event while the scan keeps running: blockfile entries as
+// the index is parsed, Simple Cache entries once they are grouped by key,
+// before the scanner sniffs and thumbnails each file. Events are batched and
+// throttled (see `events::policy_for`) so a cache full of videos can't flood
+// the IPC channel.
+
+/// Event name carrying a `Vec<HotItem>` payload.
+pub const HOT_ITEMS_EVENT: &str = "scan-hot-items";
+/// Smaller "videos" are usually thumbnails, previews or error bodies.
+const MIN_HOT_BODY_SIZE: u64 = 64 * 1024;
+/// Entries below this confidence are left for the final result.
+const MIN_CONFIDENCE: f64 = 0.8;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HotItem {
+    pub url: String,
+    pub content_type: String,
+    pub body_size: u64,
+    pub original_filename: Option<String>,
+    pub group_key: Option<String>,
+    /// 0.0..1.0; how sure we are the entry is a complete, recoverable video
+    pub confidence: f64,
+}
+
+/// Confidence that a cached video response is complete; `None` below
+/// `MIN_CONFIDENCE` or if it isn't a video with a body worth recovering.
+fn confidence(content_type: &str, body_size: u64, status_ok: bool, content_length: Option<u64>) -> Option<f64> {
+    let class = crate::mime_category::classify(Some(content_type), None);
+    if class.category != "video" || body_size < MIN_HOT_BODY_SIZE {
+        return None;
+    }
+    // Content-Length is the size of the full response for 200s; a cached body
+    // that matches it is complete.
+    let completeness = match content_length {
+        Some(len) if len > 0 => (body_size as f64 / len as f64).min(1.0),
+        _ => 0.85,
+    };
+    let confidence = if status_ok { completeness } else { completeness * 0.5 };
+    (confidence >= MIN_CONFIDENCE).then_some(confidence)
+}
+
+/// Score a parsed entry; `None` unless it is a high-confidence video.
+pub fn classify(entry: &BlockfileCacheEntry) -> Option<HotItem> {
+    let content_type = entry.content_type.as_deref()?.to_lowercase();
+    if entry.data_files.iter().all(|d| d.stream_index != 1) && entry.children.is_empty() {
+        return None;
+    }
+    let status_ok = entry
+        .http_status
+        .as_deref()
+        .is_some_and(|s| s.contains(" 200") || s.contains(" 206"));
+    let confidence = confidence(&content_type, entry.body_size, status_ok, entry.content_length)?;
+    Some(HotItem {
+        url: entry.url.clone(),
+        content_type,
+        body_size: entry.body_size,
+        original_filename: entry.original_filename.clone(),
+        group_key: entry.group_key.clone(),
+        confidence,
+    })
+}
+
+/// Score a Simple Cache entry the same way.
+pub fn classify_group(group: &CacheEntryGroup) -> Option<HotItem> {
+    let content_type = group.content_type.clone()?;
+    group.body_source?;
+    let status_ok = matches!(group.http_status, Some(200 | 206));
+    let confidence = confidence(&content_type, group.body_size, status_ok, group.content_length)?;
+    Some(HotItem {
+        url: group.url.clone(),
+        content_type,
+        body_size: group.body_size,
+        original_filename: group.original_filename.clone(),
+        group_key: crate::discord_cdn::match_response(&group.url, None).map(|m| m.group_key),
+        confidence,
+    })
+}
+
+/// Queues hot items and emits them under the `HOT_ITEMS_EVENT` policy
+/// (batched, rate-limited). The first item goes out immediately.
+pub struct HotItemNotifier {
+    events: Coalescer<HotItem>,
+    total: usize,
+}
+
+impl HotItemNotifier {
+    /// Notifier that emits `HOT_ITEMS_EVENT` to the frontend.
+    pub fn for_app(app: tauri::AppHandle) -> Self {
+        Self {
+            events: Coalescer::for_app(app, HOT_ITEMS_EVENT),
+            total: 0,
+        }
+    }
+
+    /// Check an entry and queue it if it is hot.
+    pub fn offer(&mut self, entry: &BlockfileCacheEntry) {
+        if let Some(item) = classify(entry) {
+            self.queue(item);
+        }
+    }
+
+    /// Check a Simple Cache entry and queue it if it is hot.
+    pub fn offer_group(&mut self, group: &CacheEntryGroup) {
+        if let Some(item) = classify_group(group) {
+            self.queue(item);
+        }
+    }
+
+    fn queue(&mut self, item: HotItem) {
+        self.total += 1;
+        self.events.push(item);
+    }
+
+    /// Emit whatever is still queued. Call once the scan is done.
+    pub fn flush(&mut self) {
+        self.events.flush();
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
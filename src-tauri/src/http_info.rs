@@ -0,0 +1,288 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+// ─── Stream 0: serialized HttpResponseInfo ──────────────────────────────────
+//
+// Stream 0 of a cache entry (Simple Cache `_0`, blockfile stream 0) is a
+// Chromium Pickle of HttpResponseInfo (net/http/http_response_info.cc):
+//
+//   u32    payload size (Pickle header)
+//   i32    flags — version in bits 0-7, RESPONSE_INFO_* bits above
+//   i32    extra_flags            (only with RESPONSE_INFO_HAS_EXTRA_FLAGS)
+//   i64    request_time           (µs since 1601-01-01)
+//   i64    response_time
+//   i64    original_response_time (only with EXTRA_INFO_HAS_ORIGINAL_RESPONSE_TIME)
+//   string raw headers, NUL-separated ("HTTP/1.1 200\0content-type: ...\0\0")
+//   with RESPONSE_INFO_HAS_CERT: i32 chain length, one string (DER) per cert
+//   with RESPONSE_INFO_HAS_CERT_STATUS: u32 cert status
+//   ...more SSL/connection fields, not decoded
+//
+// Pickle values are 4-byte aligned; strings are an i32 length plus bytes.
+// Some old or synthetic entries store the bare header block instead; those
+// parse as format "raw" with no flags or timestamps.
+
+const VERSION_MASK: u32 = 0xff;
+const HAS_CERT: u32 = 1 << 8;
+const HAS_CERT_STATUS: u32 = 1 << 10;
+const TRUNCATED: u32 = 1 << 12;
+const WAS_SPDY: u32 = 1 << 13;
+const WAS_ALPN: u32 = 1 << 14;
+const WAS_PROXY: u32 = 1 << 15;
+const HAS_EXTRA_FLAGS: u32 = 1 << 31;
+const EXTRA_HAS_ORIGINAL_RESPONSE_TIME: u32 = 1 << 2;
+/// Sanity bound for one certificate chain.
+const MAX_CHAIN_LENGTH: i32 = 32;
+
+/// Convert Chromium microsecond timestamp (since 1601-01-01) to Unix seconds.
+pub(crate) fn chrome_time_to_unix(us: i64) -> f64 {
+    (us as f64 / 1_000_000.0) - 11_644_473_600.0
+}
+
+/// Sequential reader over a Pickle payload.
+struct PickleReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PickleReader<'a> {
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.data.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.u32().map(|v| v as i32)
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        let bytes = self.data.get(self.pos..self.pos + 8)?;
+        self.pos += 8;
+        Some(i64::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = usize::try_from(self.i32()?).ok()?;
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos = (self.pos + len + 3) & !3;
+        Some(bytes)
+    }
+}
+
+/// The fixed leading fields of a pickled HttpResponseInfo.
+pub(crate) struct PickledResponse<'a> {
+    pub flags: u32,
+    pub extra_flags: u32,
+    pub request_time_us: i64,
+    pub response_time_us: i64,
+    pub original_response_time_us: Option<i64>,
+    /// NUL-separated status line and headers
+    pub raw_headers: &'a [u8],
+    reader: PickleReader<'a>,
+}
+
+/// Decode the Pickle framing and fixed fields of stream 0. None if `data`
+/// isn't a pickled HttpResponseInfo.
+pub(crate) fn read_pickled(data: &[u8]) -> Option<PickledResponse<'_>> {
+    let payload_size = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?) as usize;
+    if payload_size + 4 > data.len() {
+        return None;
+    }
+    let mut reader = PickleReader { data: &data[..payload_size + 4], pos: 4 };
+    let flags = reader.u32()?;
+    let version = flags & VERSION_MASK;
+    // Chromium has written versions 1-3; a zero version is not a pickle.
+    if version == 0 {
+        return None;
+    }
+    let extra_flags = if flags & HAS_EXTRA_FLAGS != 0 { reader.u32()? } else { 0 };
+    let request_time_us = reader.i64()?;
+    let response_time_us = reader.i64()?;
+    let original_response_time_us =
+        if extra_flags & EXTRA_HAS_ORIGINAL_RESPONSE_TIME != 0 { Some(reader.i64()?) } else { None };
+    let raw_headers = reader.bytes()?;
+    Some(PickledResponse {
+        flags,
+        extra_flags,
+        request_time_us,
+        response_time_us,
+        original_response_time_us,
+        raw_headers,
+        reader,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CertificateInfo {
+    /// Certificates in the chain, leaf first
+    pub chain_length: usize,
+    /// SHA-256 of the leaf certificate's DER, lowercase hex
+    pub leaf_sha256: String,
+    pub leaf_bytes: usize,
+    /// net::CertStatus bits, when stored
+    pub cert_status: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseInfo {
+    /// "pickle" (serialized HttpResponseInfo) or "raw" (bare header block)
+    pub format: &'static str,
+    /// HttpResponseInfo version (flags bits 0-7)
+    pub version: Option<u32>,
+    pub flags: u32,
+    pub extra_flags: u32,
+    /// The body was cut off when the entry was written
+    pub truncated: bool,
+    pub was_fetched_via_spdy: bool,
+    pub was_alpn_negotiated: bool,
+    pub was_fetched_via_proxy: bool,
+    pub status_line: Option<String>,
+    pub status_code: Option<u16>,
+    /// Headers in stored order; names as sent, duplicates kept
+    pub headers: Vec<HttpHeader>,
+    /// Unix seconds
+    pub request_time: Option<f64>,
+    pub response_time: Option<f64>,
+    pub original_response_time: Option<f64>,
+    pub certificate: Option<CertificateInfo>,
+}
+
+impl ResponseInfo {
+    /// First value of header `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.as_str())
+    }
+}
+
+fn unix_time(us: i64) -> Option<f64> {
+    (us > 0).then(|| chrome_time_to_unix(us))
+}
+
+/// Split a NUL-separated header block into status line and headers.
+fn split_headers(raw: &[u8]) -> (Option<String>, Vec<HttpHeader>) {
+    let mut parts = raw.split(|&b| b == 0).filter(|p| !p.is_empty()).map(String::from_utf8_lossy);
+    let status_line = parts.next().map(|s| s.trim().to_string());
+    let headers = parts
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some(HttpHeader { name: name.trim().to_string(), value: value.trim().to_string() })
+        })
+        .collect();
+    (status_line, headers)
+}
+
+fn read_certificate(pickled: &mut PickledResponse<'_>) -> Option<CertificateInfo> {
+    let reader = &mut pickled.reader;
+    let chain_length = reader.i32().filter(|n| (1..=MAX_CHAIN_LENGTH).contains(n))?;
+    let leaf = reader.bytes()?;
+    for _ in 1..chain_length {
+        reader.bytes()?;
+    }
+    let cert_status = if pickled.flags & HAS_CERT_STATUS != 0 { reader.u32() } else { None };
+    Some(CertificateInfo {
+        chain_length: chain_length as usize,
+        leaf_sha256: Sha256::digest(leaf).iter().map(|b| format!("{:02x}", b)).collect(),
+        leaf_bytes: leaf.len(),
+        cert_status,
+    })
+}
+
+/// Parse stream 0 into its structured form. None if it is neither a pickled
+/// HttpResponseInfo nor a bare "HTTP/..." header block.
+pub fn parse(stream0: &[u8]) -> Option<ResponseInfo> {
+    let (status_line, headers, mut info) = if let Some(mut pickled) = read_pickled(stream0) {
+        let (status_line, headers) = split_headers(pickled.raw_headers);
+        let certificate = if pickled.flags & HAS_CERT != 0 { read_certificate(&mut pickled) } else { None };
+        let flags = pickled.flags;
+        let info = ResponseInfo {
+            format: "pickle",
+            version: Some(flags & VERSION_MASK),
+            flags,
+            extra_flags: pickled.extra_flags,
+            truncated: flags & TRUNCATED != 0,
+            was_fetched_via_spdy: flags & WAS_SPDY != 0,
+            was_alpn_negotiated: flags & WAS_ALPN != 0,
+            was_fetched_via_proxy: flags & WAS_PROXY != 0,
+            status_line: None,
+            status_code: None,
+            headers: Vec::new(),
+            request_time: unix_time(pickled.request_time_us),
+            response_time: unix_time(pickled.response_time_us),
+            original_response_time: pickled.original_response_time_us.and_then(unix_time),
+            certificate,
+        };
+        (status_line, headers, info)
+    } else if stream0.starts_with(b"HTTP/") {
+        let (status_line, headers) = split_headers(stream0);
+        let info = ResponseInfo {
+            format: "raw",
+            version: None,
+            flags: 0,
+            extra_flags: 0,
+            truncated: false,
+            was_fetched_via_spdy: false,
+            was_alpn_negotiated: false,
+            was_fetched_via_proxy: false,
+            status_line: None,
+            status_code: None,
+            headers: Vec::new(),
+            request_time: None,
+            response_time: None,
+            original_response_time: None,
+            certificate: None,
+        };
+        (status_line, headers, info)
+    } else {
+        return None;
+    };
+    info.status_code = status_line
+        .as_deref()
+        .and_then(|s| s.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok());
+    info.status_line = status_line;
+    info.headers = headers;
+    Some(info)
+}
+
+/// Status, headers, timestamps and certificate summary from the stream 0 of
+/// a Simple Cache `_0` file.
+#[tauri::command(async)]
+pub fn read_entry_headers(path: String) -> Result<ResponseInfo, String> {
+    let _permit = crate::io_limit::acquire_for(&path);
+    crate::fault_inject::inject("read_entry_headers", &path)?;
+    let data = crate::read_with_lock_retry(&path).map_err(|e| crate::format_read_error(&path, &e))?;
+    let stream0 = crate::extract_simple_cache_headers(&data)
+        .ok_or_else(|| "Not a Simple Cache file or no headers".to_string())?;
+    parse(&stream0).ok_or_else(|| format!("Stream 0 of {} is not an HTTP response", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pickled_response_info() {
+        let headers = ["HTTP/1.1 206 Partial Content", "Content-Type: video/mp4", "Set-Cookie: a=1", "set-cookie: b=2"];
+        let cert = [0x30u8, 0x82, 0x01, 0x0a, 0x02];
+        let stream0 = crate::fixture::response_info_pickle(&headers, Some(&cert));
+
+        let info = parse(&stream0).unwrap();
+        assert_eq!((info.format, info.version, info.status_code), ("pickle", Some(3), Some(206)));
+        assert_eq!(info.header("content-type"), Some("video/mp4"));
+        assert_eq!(info.headers.iter().filter(|h| h.name.eq_ignore_ascii_case("set-cookie")).count(), 2);
+        assert_eq!(info.response_time.map(|t| t.round()), Some(1_700_000_000.0));
+        let certificate = info.certificate.unwrap();
+        assert_eq!((certificate.chain_length, certificate.leaf_bytes, certificate.cert_status), (1, 5, Some(0)));
+        assert_eq!(certificate.leaf_sha256, format!("{:x}", Sha256::digest(cert)));
+
+        let raw = parse(b"HTTP/1.1 200 OK\0content-length: 5\0\0").unwrap();
+        assert_eq!((raw.format, raw.header("Content-Length")), ("raw", Some("5")));
+        assert!(parse(b"\x08\0\0\0garbage!").is_none());
+    }
+}
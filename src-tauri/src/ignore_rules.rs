@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::cache::CacheFileEntry;
+use crate::custom_paths::{normalize, read_json, same_path, write_json};
+
+// ─── Scan ignore rules ──────────────────────────────────────────────────────
+//
+// On shared or family machines a cache holds far more than the user is after.
+// Ignore rules drop files while they are listed, before any of them is read:
+// a rule names conditions (file-name glob, size, age, file kind, source
+// folder) and a file matching every condition a rule sets is skipped. Rules
+// are kept in `ignore_rules.json` in the app config folder, next to the
+// custom cache paths.
+
+const RULES_FILE: &str = "ignore_rules.json";
+
+/// Serializes read-modify-write of the rules file.
+static RULES_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IgnoreRule {
+    /// Only apply to sources inside this folder; None applies everywhere
+    pub source: Option<String>,
+    /// File-name glob, `*` and `?` (e.g. "*_0"); case-insensitive
+    pub pattern: Option<String>,
+    /// Match files smaller than this many bytes
+    pub smaller_than: Option<u64>,
+    /// Match files larger than this many bytes
+    pub larger_than: Option<u64>,
+    /// Match files not used (or, without an index, not modified) for this many days
+    pub older_than_days: Option<u32>,
+    /// "simple_cache" (`_0`/`_1`), "sparse" (`_s`) or "blockfile" (`f_`)
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RuleFile {
+    rules: Vec<IgnoreRule>,
+}
+
+const KINDS: &[&str] = &["simple_cache", "sparse", "blockfile"];
+
+impl IgnoreRule {
+    fn validate(&self) -> Result<(), String> {
+        let has_condition = self.pattern.is_some()
+            || self.smaller_than.is_some()
+            || self.larger_than.is_some()
+            || self.older_than_days.is_some()
+            || self.kind.is_some();
+        if !has_condition {
+            return Err("An ignore rule needs a pattern, size, age or kind — it would skip every file".to_string());
+        }
+        if let Some(kind) = self.kind.as_deref().filter(|k| !KINDS.contains(k)) {
+            return Err(format!("Unknown file kind \"{}\" (expected one of {})", kind, KINDS.join(", ")));
+        }
+        Ok(())
+    }
+
+    fn applies_to(&self, dir: &str) -> bool {
+        self.source.as_deref().is_none_or(|source| {
+            let (dir, source) = (normalize(dir), normalize(source));
+            dir.get(..source.len()).is_some_and(|prefix| same_path(prefix, &source))
+                && matches!(dir.as_bytes().get(source.len()), None | Some(b'/') | Some(b'\\'))
+        })
+    }
+
+    fn matches(&self, file: &CacheFileEntry, now: f64) -> bool {
+        self.pattern.as_deref().is_none_or(|p| glob_match(p, &file.name))
+            && self.smaller_than.is_none_or(|n| file.size < n)
+            && self.larger_than.is_none_or(|n| file.size > n)
+            && self.older_than_days.is_none_or(|days| {
+                let seen = file.last_used.unwrap_or(file.modified_at);
+                seen > 0.0 && now - seen > days as f64 * 86_400.0
+            })
+            && self.kind.as_deref().is_none_or(|k| file_kind(&file.name) == Some(k))
+    }
+}
+
+fn file_kind(name: &str) -> Option<&'static str> {
+    let lower = name.to_ascii_lowercase();
+    if lower.starts_with("f_") {
+        Some("blockfile")
+    } else if lower.ends_with("_s") {
+        Some("sparse")
+    } else if lower.ends_with("_0") || lower.ends_with("_1") {
+        Some("simple_cache")
+    } else {
+        None
+    }
+}
+
+/// `*` matches any run of characters, `?` exactly one; ASCII case is ignored.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi].eq_ignore_ascii_case(&n[ni])) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Drop the files of `dir` that a rule matches. Returns how many were dropped.
+pub fn apply(rules: &[IgnoreRule], dir: &str, files: &mut Vec<CacheFileEntry>, now: f64) -> usize {
+    let active: Vec<&IgnoreRule> = rules.iter().filter(|r| r.applies_to(dir)).collect();
+    if active.is_empty() {
+        return 0;
+    }
+    let before = files.len();
+    files.retain(|file| !active.iter().any(|rule| rule.matches(file, now)));
+    let dropped = before - files.len();
+    if dropped > 0 {
+        println!("[ignore] {}: {} of {} files skipped by {} rule(s)", dir, dropped, before, active.len());
+    }
+    dropped
+}
+
+/// Apply the saved rules to a listing of `dir`.
+pub fn apply_saved(app: &tauri::AppHandle, dir: &str, files: &mut Vec<CacheFileEntry>) -> usize {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    apply(&saved_rules(app), dir, files, now)
+}
+
+fn load_rules(file: &Path) -> Result<Vec<IgnoreRule>, String> {
+    read_json::<RuleFile>(file).map(|f| f.rules)
+}
+
+/// The saved rules; an unreadable rules file is logged and ignores nothing.
+fn saved_rules(app: &tauri::AppHandle) -> Vec<IgnoreRule> {
+    let loaded = crate::custom_paths::config_file(app, RULES_FILE).and_then(|file| {
+        let _lock = RULES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        load_rules(&file)
+    });
+    loaded.unwrap_or_else(|e| {
+        eprintln!("[ignore] {}", e);
+        Vec::new()
+    })
+}
+
+/// Saved ignore rules, in order.
+#[tauri::command]
+pub fn list_ignore_rules(app: tauri::AppHandle) -> Result<Vec<IgnoreRule>, String> {
+    let file = crate::custom_paths::config_file(&app, RULES_FILE)?;
+    let _lock = RULES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    load_rules(&file)
+}
+
+/// Replace the saved ignore rules. Returns them as stored.
+#[tauri::command]
+pub fn set_ignore_rules(app: tauri::AppHandle, rules: Vec<IgnoreRule>) -> Result<Vec<IgnoreRule>, String> {
+    for rule in &rules {
+        rule.validate()?;
+    }
+    let rules: Vec<IgnoreRule> = rules
+        .into_iter()
+        .map(|mut rule| {
+            rule.source = rule.source.filter(|s| !s.trim().is_empty()).map(|s| normalize(&s));
+            rule
+        })
+        .collect();
+    let file = crate::custom_paths::config_file(&app, RULES_FILE)?;
+    let _lock = RULES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    write_json(&file, &RuleFile { rules: rules.clone() })?;
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: u64, modified_at: f64) -> CacheFileEntry {
+        CacheFileEntry {
+            name: name.to_string(),
+            path: format!("/cache/{}", name),
+            size,
+            modified_at,
+            last_used: None,
+            index_size: None,
+            eviction_risk: None,
+        }
+    }
+
+    #[test]
+    fn rules_skip_matching_files_per_source() {
+        assert!(glob_match("*_0", "0123456789ABCDEF_0"));
+        assert!(glob_match("f_00??0*", "f_000101"));
+        assert!(!glob_match("*_0", "0123456789abcdef_s"));
+
+        let now = 200.0 * 86_400.0;
+        let rules = vec![
+            IgnoreRule { pattern: Some("*_0".into()), smaller_than: Some(1024), ..Default::default() },
+            IgnoreRule { older_than_days: Some(90), source: Some("/home/kid/.config/discord".into()), ..Default::default() },
+        ];
+        let listing = || {
+            vec![
+                file("0123456789abcdef_0", 512, now),
+                file("0123456789abcdef_s", 512, now),
+                file("1123456789abcdef_0", 4096, 10.0 * 86_400.0),
+            ]
+        };
+
+        let mut files = listing();
+        assert_eq!(apply(&rules, "/home/kid/.config/discord/Cache/Cache_Data", &mut files, now), 2);
+        assert_eq!(files[0].name, "0123456789abcdef_s");
+
+        let mut files = listing();
+        assert_eq!(apply(&rules, "/home/kiddo/Cache", &mut files, now), 1);
+        assert!(IgnoreRule::default().validate().is_err());
+        assert!(IgnoreRule { kind: Some("index".into()), ..Default::default() }.validate().is_err());
+    }
+}
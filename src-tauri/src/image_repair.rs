@@ -0,0 +1,548 @@
+use serde::Serialize;
+
+// ─── Image validation and repair: PNG, JPEG, GIF, WebP and AVIF ─────────────
+//
+// Large images are fetched in ranges like videos, so a recovered image can be
+// the concatenation of several chunks — possibly with the tail missing or
+// trailing bytes from a neighbouring block. After reassembly the structure is
+// walked and fixed without re-encoding:
+//   - PNG: chunks are walked to IEND and their CRCs checked. Anything after
+//     IEND is cut; a truncated file is cut at its last whole chunk and given
+//     an IEND so viewers show the rows that are there.
+//   - JPEG: segments are walked to the scan, the entropy-coded data is walked
+//     to EOI. Anything after EOI is cut; a truncated file gets an EOI so
+//     decoders render the part that arrived (the rest shows grey). Baseline
+//     files are first cut back to their last whole MCU (jpeg.rs), optionally
+//     with the missing MCUs written out as grey.
+//   - GIF: the blocks are walked and the frames counted. Anything after the
+//     trailer is cut; a file cut off mid-frame is trimmed to its last whole
+//     frame and given a trailer, so an animation plays the frames it has.
+//   - WebP: the RIFF size is checked against the data and the chunks walked.
+//     Bytes past the RIFF size are cut; a file cut off after its image data
+//     loses the partial trailing chunk and gets its RIFF size fixed.
+//   - AVIF: top-level boxes are walked; bytes after the last whole box are
+//     cut. A cut-off `mdat` can't be closed without re-encoding, so such a
+//     file is only reported as incomplete.
+
+pub(crate) const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+const PNG_IEND: &[u8; 12] = b"\0\0\0\0IEND\xae\x42\x60\x82";
+/// WebP chunks holding image data (a still frame, its alpha, an animation frame).
+const WEBP_IMAGE_CHUNKS: &[&[u8; 4]] = &[b"VP8 ", b"VP8L", b"ALPH", b"ANMF"];
+
+#[derive(Debug, Serialize)]
+pub struct ImageRepairReport {
+    /// "png", "jpeg", "gif", "webp", "avif" or "unknown"
+    pub format: &'static str,
+    /// The image ends where its format says it should (IEND / EOI present)
+    pub complete: bool,
+    /// "none" (file was already sound), "repaired" or "unsupported"
+    pub action: &'static str,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// PNG chunks whose CRC did not match (kept; usually a damaged range)
+    pub bad_chunks: usize,
+    /// Truncated baseline JPEG: MCUs that decoded whole, out of the image's total
+    pub mcus: Option<u64>,
+    pub total_mcus: Option<u64>,
+    /// The missing MCUs were written as grey
+    pub gray_padded: bool,
+    /// GIF: whole frames kept
+    pub frames: Option<usize>,
+    pub details: Vec<String>,
+}
+
+impl ImageRepairReport {
+    fn new(format: &'static str, len: usize) -> Self {
+        Self {
+            format,
+            complete: false,
+            action: "none",
+            bytes_before: len as u64,
+            bytes_after: len as u64,
+            bad_chunks: 0,
+            mcus: None,
+            total_mcus: None,
+            gray_padded: false,
+            frames: None,
+            details: Vec::new(),
+        }
+    }
+
+    fn finish(mut self, out: Vec<u8>) -> (Vec<u8>, Self) {
+        self.bytes_after = out.len() as u64;
+        if self.action == "none" && self.bytes_after != self.bytes_before {
+            self.action = "repaired";
+        }
+        (out, self)
+    }
+}
+
+fn repair_png(data: &[u8]) -> (Vec<u8>, ImageRepairReport) {
+    let mut report = ImageRepairReport::new("png", data.len());
+    let mut pos = PNG_SIGNATURE.len();
+    let mut saw_idat = false;
+    while pos + 12 <= data.len() {
+        let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        if pos == PNG_SIGNATURE.len() && kind != b"IHDR" {
+            report.action = "unsupported";
+            report.details.push("first chunk is not IHDR".to_string());
+            return (data.to_vec(), report);
+        }
+        let end = match (pos + 12).checked_add(len) {
+            Some(end) if end <= data.len() && kind.iter().all(u8::is_ascii_alphabetic) => end,
+            _ => break,
+        };
+        let stored = u32::from_be_bytes(data[end - 4..end].try_into().unwrap());
+        if crc32fast::hash(&data[pos + 4..end - 4]) != stored {
+            report.bad_chunks += 1;
+        }
+        saw_idat |= kind == b"IDAT";
+        pos = end;
+        if kind == b"IEND" {
+            report.complete = true;
+            if pos < data.len() {
+                report.details.push(format!("removed {} bytes after IEND", data.len() - pos));
+            }
+            return report.finish(data[..pos].to_vec());
+        }
+    }
+    if !saw_idat {
+        report.action = "unsupported";
+        report.details.push("no image data (IDAT) before the end of the file".to_string());
+        return (data.to_vec(), report);
+    }
+    report.details.push(format!("truncated: cut {} bytes of a partial chunk and added IEND", data.len() - pos));
+    let mut out = data[..pos].to_vec();
+    out.extend_from_slice(PNG_IEND);
+    report.finish(out)
+}
+
+/// End of the entropy-coded data starting at `pos`: the offset of the first
+/// marker that isn't a stuffed byte or a restart marker.
+fn jpeg_scan_end(data: &[u8], mut pos: usize) -> Option<usize> {
+    while pos + 1 < data.len() {
+        if data[pos] == 0xFF {
+            match data[pos + 1] {
+                0x00 | 0xD0..=0xD7 | 0xFF => {}
+                _ => return Some(pos),
+            }
+        }
+        pos += 1;
+    }
+    None
+}
+
+fn repair_jpeg(data: &[u8], pad_gray: bool) -> (Vec<u8>, ImageRepairReport) {
+    let mut report = ImageRepairReport::new("jpeg", data.len());
+    let mut pos = 2;
+    let mut scans = 0;
+    let truncated_at;
+    loop {
+        // Fill bytes (0xFF padding) may precede any marker.
+        while pos + 1 < data.len() && data[pos] == 0xFF && data[pos + 1] == 0xFF {
+            pos += 1;
+        }
+        if pos + 1 >= data.len() || data[pos] != 0xFF {
+            truncated_at = pos.min(data.len());
+            break;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xD9 {
+            report.complete = true;
+            let end = pos + 2;
+            if end < data.len() {
+                report.details.push(format!("removed {} bytes after EOI", data.len() - end));
+            }
+            return report.finish(data[..end].to_vec());
+        }
+        if matches!(marker, 0x01 | 0xD0..=0xD7) {
+            pos += 2;
+            continue;
+        }
+        if pos + 4 > data.len() {
+            truncated_at = pos;
+            break;
+        }
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > data.len() {
+            truncated_at = pos;
+            break;
+        }
+        if marker == 0xDA {
+            scans += 1;
+            match jpeg_scan_end(data, end) {
+                Some(next) => pos = next,
+                None if scans == 1 => {
+                    // Ran out inside the only scan: cut at the last whole MCU if it can be decoded.
+                    if let Some(cut) = crate::jpeg::cut_at_last_mcu(data, pad_gray) {
+                        report.details.push(format!(
+                            "truncated: cut at the last whole MCU ({} of {}){} and added EOI",
+                            cut.mcus,
+                            cut.total_mcus,
+                            if cut.gray_padded { ", rest filled with grey" } else { "" }
+                        ));
+                        report.action = "repaired";
+                        report.mcus = Some(cut.mcus);
+                        report.total_mcus = Some(cut.total_mcus);
+                        report.gray_padded = cut.gray_padded;
+                        return report.finish(cut.data);
+                    }
+                    truncated_at = if data.last() == Some(&0xFF) { data.len() - 1 } else { data.len() };
+                    break;
+                }
+                None => {
+                    // Ran out inside the scan: keep all of it, minus a dangling 0xFF.
+                    truncated_at = if data.last() == Some(&0xFF) { data.len() - 1 } else { data.len() };
+                    break;
+                }
+            }
+        } else {
+            pos = end;
+        }
+    }
+    if scans == 0 {
+        report.action = "unsupported";
+        report.details.push("no image data (SOS) before the end of the file".to_string());
+        return (data.to_vec(), report);
+    }
+    report.details.push(format!("truncated: added EOI after {} bytes", truncated_at));
+    let mut out = data[..truncated_at].to_vec();
+    out.extend_from_slice(&[0xFF, 0xD9]);
+    report.finish(out)
+}
+
+/// End of the data sub-blocks starting at `pos` (after the zero-length
+/// terminator), or `None` if they run past the end of `data`.
+fn gif_sub_blocks_end(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)? as usize;
+        pos += 1 + len;
+        if len == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+fn repair_gif(data: &[u8]) -> (Vec<u8>, ImageRepairReport) {
+    let mut report = ImageRepairReport::new("gif", data.len());
+    // Header, logical screen descriptor and global colour table.
+    let packed = data.get(10).copied().unwrap_or(0);
+    let mut pos = 13 + if packed & 0x80 != 0 { 3 << ((packed & 7) + 1) } else { 0 };
+    let mut frames = 0;
+    let mut frame_end = pos;
+    let stopped = loop {
+        match data.get(pos) {
+            Some(0x3B) => {
+                report.complete = true;
+                report.frames = Some(frames);
+                if pos + 1 < data.len() {
+                    report.details.push(format!("removed {} bytes after the trailer", data.len() - pos - 1));
+                }
+                return report.finish(data[..pos + 1].to_vec());
+            }
+            Some(0x21) => match gif_sub_blocks_end(data, pos + 2) {
+                Some(end) => pos = end,
+                None => break "an extension block",
+            },
+            Some(0x2C) => {
+                let Some(&local) = data.get(pos + 9) else { break "an image descriptor" };
+                let table = if local & 0x80 != 0 { 3 << ((local & 7) + 1) } else { 0 };
+                // Descriptor, local colour table, LZW minimum code size.
+                match gif_sub_blocks_end(data, pos + 10 + table + 1) {
+                    Some(end) => {
+                        frames += 1;
+                        pos = end;
+                        frame_end = end;
+                    }
+                    None => break "the image data",
+                }
+            }
+            Some(_) => break "an unknown block",
+            None => break "the end of the data",
+        }
+    };
+    report.frames = Some(frames);
+    if frames == 0 {
+        report.action = "unsupported";
+        report.details.push(format!("no whole frame before {}", stopped));
+        return (data.to_vec(), report);
+    }
+    report.details.push(format!(
+        "truncated in {}: kept {} whole frame(s), cut {} bytes and added the trailer",
+        stopped,
+        frames,
+        data.len() - frame_end
+    ));
+    let mut out = data[..frame_end].to_vec();
+    out.push(0x3B);
+    report.action = "repaired";
+    report.finish(out)
+}
+
+/// MIME type of a HEIF image from its `ftyp`: AVIF by its major brand, or by
+/// a compatible brand under a generic HEIF one (`mif1`, `msf1`); HEIC otherwise.
+pub(crate) fn heif_mime(data: &[u8]) -> Option<&'static str> {
+    if data.len() < 16 || &data[4..8] != b"ftyp" {
+        return None;
+    }
+    let size = (crate::mp4_samples::be_u32(data, 0)? as usize).clamp(16, data.len());
+    let avif_compatible = data[16..size].chunks_exact(4).any(|b| b == b"avif" || b == b"avis");
+    match &data[8..12] {
+        b"avif" | b"avis" => Some("image/avif"),
+        b"mif1" | b"msf1" if avif_compatible => Some("image/avif"),
+        b"mif1" | b"msf1" | b"heic" | b"heix" | b"hevc" => Some("image/heic"),
+        _ => None,
+    }
+}
+
+fn repair_webp(data: &[u8]) -> (Vec<u8>, ImageRepairReport) {
+    let mut report = ImageRepairReport::new("webp", data.len());
+    let declared = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize + 8;
+    let mut pos = 12;
+    let mut image_chunks = 0;
+    let mut cut_off = None;
+    while pos + 8 <= declared.min(data.len()) {
+        let kind: [u8; 4] = data[pos..pos + 4].try_into().unwrap();
+        let len = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let end = pos + 8 + len + (len & 1);
+        if !kind.iter().all(|b| b.is_ascii_alphanumeric() || *b == b' ') || end > data.len() {
+            cut_off = Some(kind);
+            break;
+        }
+        image_chunks += WEBP_IMAGE_CHUNKS.contains(&&kind) as usize;
+        pos = end;
+    }
+    if cut_off.is_none() && pos >= declared {
+        report.complete = image_chunks > 0;
+        if data.len() > declared {
+            report.details.push(format!("removed {} bytes after the RIFF size", data.len() - declared));
+        }
+        return report.finish(data[..declared].to_vec());
+    }
+    if image_chunks == 0 {
+        report.details.push(format!(
+            "truncated: {} of {} bytes, inside the image data; WebP can't be closed without re-encoding",
+            data.len(),
+            declared
+        ));
+        return (data.to_vec(), report);
+    }
+    // Earlier chunks are whole: keep them and make the RIFF size match.
+    report.complete = !cut_off.is_some_and(|kind| WEBP_IMAGE_CHUNKS.contains(&&kind));
+    report.details.push(format!(
+        "truncated: cut a partial {} chunk and set the RIFF size to {} bytes",
+        cut_off.map_or("?".into(), |kind| String::from_utf8_lossy(&kind).trim().to_string()),
+        pos
+    ));
+    let mut out = data[..pos].to_vec();
+    out[4..8].copy_from_slice(&((pos - 8) as u32).to_le_bytes());
+    report.action = "repaired";
+    report.finish(out)
+}
+
+fn repair_avif(data: &[u8]) -> (Vec<u8>, ImageRepairReport) {
+    let mut report = ImageRepairReport::new("avif", data.len());
+    let (boxes, cut_off) = crate::fmp4::top_level_boxes(data);
+    let whole: usize = boxes.iter().map(|(_, b)| b.len()).sum();
+    let has = |kind: &[u8; 4]| boxes.iter().any(|(k, _)| k == kind);
+    if cut_off == Some(*b"mdat") || !has(b"meta") {
+        report.details.push(format!(
+            "truncated inside the {}; the image can't be closed without re-encoding",
+            if has(b"meta") { "image data (mdat)" } else { "item metadata (meta)" }
+        ));
+        return (data.to_vec(), report);
+    }
+    report.complete = has(b"mdat");
+    if whole < data.len() {
+        report.details.push(match cut_off {
+            Some(kind) => format!("cut a partial {} box", String::from_utf8_lossy(&kind)),
+            None => format!("removed {} bytes after the last box", data.len() - whole),
+        });
+    }
+    report.finish(data[..whole].to_vec())
+}
+
+/// Validate and repair `data` if it is a PNG, JPEG, GIF, WebP or AVIF. `pad_gray` fills the
+/// missing part of a truncated baseline JPEG with grey MCUs.
+pub fn repair(data: &[u8], pad_gray: bool) -> (Vec<u8>, ImageRepairReport) {
+    if data.starts_with(PNG_SIGNATURE) {
+        repair_png(data)
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        repair_jpeg(data, pad_gray)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        repair_gif(data)
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        repair_webp(data)
+    } else if heif_mime(data) == Some("image/avif") {
+        repair_avif(data)
+    } else {
+        let mut report = ImageRepairReport::new("unknown", data.len());
+        report.action = "unsupported";
+        (data.to_vec(), report)
+    }
+}
+
+/// Check and repair a reassembled PNG/JPEG/GIF/WebP/AVIF in place (trailing garbage cut,
+/// missing IEND/EOI added, a truncated JPEG cut at its last whole MCU and,
+/// with `pad_gray`, completed with grey). The file is only rewritten if
+/// something changed. Requires a `consent` token covering `path`.
+#[tauri::command(async)]
+pub fn repair_image(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    path: String,
+    consent: String,
+    pad_gray: Option<bool>,
+) -> Result<ImageRepairReport, String> {
+    let path = state.check(&consent, &path)?;
+    let data = crate::read_with_lock_retry(&path).map_err(|e| crate::format_read_error(&path, &e))?;
+    let (fixed, report) = repair(&data, pad_gray.unwrap_or(false));
+    if report.action == "repaired" {
+        crate::output_file::write_output(&path, &fixed)?;
+    }
+    println!(
+        "[image] {} {}: {} ({}) — {}",
+        report.format,
+        path,
+        report.action,
+        if report.complete { "complete" } else { "incomplete" },
+        report.details.join("; ")
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::boxed;
+
+    fn png_chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut c = (body.len() as u32).to_be_bytes().to_vec();
+        c.extend_from_slice(kind);
+        c.extend_from_slice(body);
+        let crc = crc32fast::hash(&c[4..]);
+        c.extend_from_slice(&crc.to_be_bytes());
+        c
+    }
+
+    #[test]
+    fn png_trims_tail_and_closes_truncation() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(png_chunk(b"IHDR", &[0; 13]));
+        png.extend(png_chunk(b"IDAT", &[1; 100]));
+        let partial_end = png.len();
+        png.extend(png_chunk(b"IDAT", &[2; 100]));
+        png.extend_from_slice(PNG_IEND);
+        let whole = png.len();
+
+        png.extend_from_slice(&[0xAA; 500]);
+        let (out, report) = repair(&png, false);
+        assert_eq!((out.len(), report.complete, report.action, report.bad_chunks), (whole, true, "repaired", 0));
+
+        let (out, report) = repair(&png[..partial_end + 50], false);
+        assert!(!report.complete);
+        assert_eq!(out.len(), partial_end + PNG_IEND.len());
+        assert!(out.ends_with(PNG_IEND));
+    }
+
+    #[test]
+    fn png_counts_bad_crcs_and_closes_at_a_chunk_boundary() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(png_chunk(b"IHDR", &[0; 13]));
+        let mut damaged = png_chunk(b"IDAT", &[1; 100]);
+        damaged[20] ^= 0xFF;
+        png.extend(damaged);
+        let (out, report) = repair(&png, false);
+        assert_eq!((report.bad_chunks, report.complete, report.action), (1, false, "repaired"));
+        assert_eq!(out, [png.as_slice(), PNG_IEND].concat());
+    }
+
+    #[test]
+    fn gif_counts_frames_and_trims_to_the_last_whole_one() {
+        // 89a header, 2×2 screen with a 2-colour global table.
+        let mut gif = b"GIF89a\x02\0\x02\0\x80\0\0".to_vec();
+        gif.extend_from_slice(&[0, 0, 0, 255, 255, 255]);
+        gif.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\0\0\0");
+        let frame = [b"\x21\xF9\x04\0\x0A\0\0\0".as_slice(), b"\x2C\0\0\0\0\x02\0\x02\0\0\x02\x02\x44\x01\0"].concat();
+        gif.extend_from_slice(&frame);
+        let one = gif.len();
+        gif.extend_from_slice(&frame);
+        gif.push(0x3B);
+        let whole = gif.len();
+
+        gif.extend_from_slice(&[0; 20]);
+        let (out, report) = repair(&gif, false);
+        assert_eq!((out.len(), report.complete, report.frames), (whole, true, Some(2)));
+
+        let (out, report) = repair(&gif[..whole - 4], false);
+        assert_eq!((report.complete, report.frames, report.action), (false, Some(1), "repaired"));
+        assert_eq!(out, [&gif[..one], &[0x3B]].concat());
+    }
+
+    fn riff_chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut c = kind.to_vec();
+        c.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        c.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            c.push(0);
+        }
+        c
+    }
+
+    #[test]
+    fn webp_checks_the_riff_size() {
+        let chunks = [riff_chunk(b"VP8 ", &[1; 101]), riff_chunk(b"EXIF", &[2; 40])].concat();
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+        webp.extend_from_slice(b"WEBP");
+        webp.extend_from_slice(&chunks);
+        let whole = webp.len();
+
+        webp.extend_from_slice(&[0xAA; 64]);
+        let (out, report) = repair(&webp, false);
+        assert_eq!((out.len(), report.complete, report.action), (whole, true, "repaired"));
+
+        // EXIF cut off after a whole VP8 frame: the frame is kept.
+        let (out, report) = repair(&webp[..whole - 10], false);
+        assert_eq!((out.len(), report.complete), (12 + 8 + 102, true));
+        assert_eq!(u32::from_le_bytes(out[4..8].try_into().unwrap()) as usize, out.len() - 8);
+
+        let (out, report) = repair(&webp[..60], false);
+        assert_eq!((out.len(), report.complete, report.action), (60, false, "none"));
+    }
+
+    #[test]
+    fn avif_brands_and_trailing_bytes() {
+        let ftyp = boxed(b"ftyp", b"mif1\0\0\0\0mif1avifmiaf");
+        assert_eq!(heif_mime(&ftyp), Some("image/avif"));
+        assert_eq!(heif_mime(&boxed(b"ftyp", b"heic\0\0\0\0mif1heic")), Some("image/heic"));
+
+        let avif = [ftyp, boxed(b"meta", &[0; 40]), boxed(b"mdat", &[3; 200])].concat();
+        let (out, report) = repair(&[avif.as_slice(), &[0; 30]].concat(), false);
+        assert_eq!((out, report.complete, report.action), (avif.clone(), true, "repaired"));
+
+        let (_, report) = repair(&avif[..avif.len() - 50], false);
+        assert_eq!((report.complete, report.action), (false, "none"));
+    }
+
+    #[test]
+    fn jpeg_trims_tail_and_closes_truncation() {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x4A, 0x46];
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x03, 0x01]);
+        jpeg.extend_from_slice(&[0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD0, 0x56]);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        let whole = jpeg.len();
+
+        let (out, report) = repair(&jpeg, false);
+        assert_eq!((out.len(), report.complete, report.action), (whole, true, "none"));
+
+        jpeg.extend_from_slice(&[0x99; 64]);
+        let (out, report) = repair(&jpeg, false);
+        assert_eq!((out.len(), report.action), (whole, "repaired"));
+
+        let (out, report) = repair(&jpeg[..whole - 4], false);
+        assert!(!report.complete);
+        assert!(out.ends_with(&[0x34, 0xFF, 0xD9]));
+    }
+}
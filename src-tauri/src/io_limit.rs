@@ -0,0 +1,161 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Condvar, Mutex, OnceLock};
+
+// ─── File I/O concurrency limit ─────────────────────────────────────────────
+//
+// A scan fires hundreds of `read_file_header`/`get_file_size` calls at once;
+// run on the async pool, each holds a file handle, and on systems with a low
+// descriptor limit (macOS defaults to 256) later opens start failing with
+// "too many open files". Those commands take an `IoPermit` first. Waiters are
+// grouped by job (the cache folder a file lives in) and admitted round-robin
+// across jobs, FIFO within a job, so one big folder can't starve a second
+// scan or a recovery running alongside it.
+
+/// Concurrent file operations allowed until `set_io_concurrency` changes it.
+pub const DEFAULT_IO_CONCURRENCY: usize = 32;
+const MAX_IO_CONCURRENCY: usize = 256;
+
+#[derive(Default)]
+struct Queue {
+    limit: usize,
+    in_use: usize,
+    next_ticket: u64,
+    /// Waiting tickets per job
+    waiting: HashMap<String, VecDeque<u64>>,
+    /// Jobs with waiters, in the order they get their next turn
+    rotation: VecDeque<String>,
+}
+
+impl Queue {
+    /// The ticket admitted next: the oldest waiter of the job whose turn it is.
+    fn next_up(&self) -> Option<u64> {
+        let job = self.rotation.front()?;
+        self.waiting.get(job)?.front().copied()
+    }
+}
+
+pub struct IoLimiter {
+    queue: Mutex<Queue>,
+    freed: Condvar,
+}
+
+/// Held while a file operation runs; frees its slot on drop.
+pub struct IoPermit<'a>(&'a IoLimiter);
+
+impl Drop for IoPermit<'_> {
+    fn drop(&mut self) {
+        let mut queue = self.0.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.in_use -= 1;
+        drop(queue);
+        self.0.freed.notify_all();
+    }
+}
+
+impl IoLimiter {
+    pub fn new(limit: usize) -> Self {
+        IoLimiter {
+            queue: Mutex::new(Queue { limit: limit.clamp(1, MAX_IO_CONCURRENCY), ..Queue::default() }),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Wait for a free slot, taking turns with other jobs.
+    pub fn acquire(&self, job: &str) -> IoPermit<'_> {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if queue.in_use < queue.limit && queue.rotation.is_empty() {
+            queue.in_use += 1;
+            return IoPermit(self);
+        }
+        let ticket = queue.next_ticket;
+        queue.next_ticket += 1;
+        let waiters = queue.waiting.entry(job.to_string()).or_default();
+        waiters.push_back(ticket);
+        if waiters.len() == 1 {
+            queue.rotation.push_back(job.to_string());
+        }
+        while !(queue.in_use < queue.limit && queue.next_up() == Some(ticket)) {
+            queue = self.freed.wait(queue).unwrap_or_else(|e| e.into_inner());
+        }
+        // Admitted: leave the queue and send this job to the back of the line.
+        let job = queue.rotation.pop_front().unwrap_or_default();
+        let more = match queue.waiting.get_mut(&job) {
+            Some(waiters) => {
+                waiters.pop_front();
+                !waiters.is_empty()
+            }
+            None => false,
+        };
+        if more {
+            queue.rotation.push_back(job);
+        } else {
+            queue.waiting.remove(&job);
+        }
+        queue.in_use += 1;
+        drop(queue);
+        // Another slot may still be free for the next job in line.
+        self.freed.notify_all();
+        IoPermit(self)
+    }
+
+    pub fn set_limit(&self, limit: usize) -> usize {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.limit = limit.clamp(1, MAX_IO_CONCURRENCY);
+        let limit = queue.limit;
+        drop(queue);
+        self.freed.notify_all();
+        limit
+    }
+}
+
+fn global() -> &'static IoLimiter {
+    static LIMITER: OnceLock<IoLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| IoLimiter::new(DEFAULT_IO_CONCURRENCY))
+}
+
+/// Permit for one operation on `path`; its folder is the job it queues under.
+pub fn acquire_for(path: &str) -> IoPermit<'static> {
+    let job = Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    global().acquire(&job)
+}
+
+/// Set how many file operations may run at once (1–256). Returns the
+/// limit now in effect.
+#[tauri::command]
+pub fn set_io_concurrency(limit: usize) -> usize {
+    let limit = global().set_limit(limit);
+    println!("[io_limit] Concurrency limit set to {}", limit);
+    limit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn alternates_between_jobs() {
+        let limiter = Arc::new(IoLimiter::new(1));
+        let held = limiter.acquire("busy");
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut workers = Vec::new();
+        // Three waiters from folder A queue up before one from folder B.
+        for job in ["a", "a", "a", "b"] {
+            let (limiter, order) = (limiter.clone(), order.clone());
+            workers.push(std::thread::spawn(move || {
+                let _permit = limiter.acquire(job);
+                order.lock().unwrap().push(job);
+            }));
+            std::thread::sleep(Duration::from_millis(30));
+        }
+        drop(held);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec!["a", "b", "a", "a"]);
+    }
+}
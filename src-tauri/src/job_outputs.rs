@@ -0,0 +1,228 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// ─── Per-job output tracking ────────────────────────────────────────────────
+//
+// `OutputFile` already keeps a single write atomic, but one recovery job
+// produces several files — the raw reassembly, a remux, a thumbnail — and a
+// job that fails or is cancelled half way leaves the finished ones behind.
+// Those leftovers look like valid outputs to the next run's dedup and
+// idempotency checks. A job registers its output folder; every file
+// `OutputFile` creates under that folder (and anything the frontend tracks
+// explicitly, such as ffmpeg outputs) is recorded, and on failure or cancel
+// the recorded files are removed. Cancelling also makes the job's in-flight
+// writes fail at their next chunk.
+
+#[derive(Debug, Default)]
+struct Job {
+    root: PathBuf,
+    /// Cache file the job recovers and its URL, for the export mapping
+    source: Option<String>,
+    url: Option<String>,
+    /// Files the job created, in creation order
+    outputs: Vec<PathBuf>,
+    /// Every file the job finished writing, including ones it overwrote
+    written: Vec<PathBuf>,
+    cancelled: bool,
+}
+
+static JOBS: Mutex<Option<HashMap<String, Job>>> = Mutex::new(None);
+
+fn with_jobs<T>(f: impl FnOnce(&mut HashMap<String, Job>) -> T) -> T {
+    let mut jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    f(jobs.get_or_insert_with(HashMap::new))
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobCleanup {
+    pub job_id: String,
+    pub removed: Vec<String>,
+    /// Recorded files that could not be removed, with the error
+    pub failed: Vec<String>,
+    /// Files a successful job finished writing and that are still there
+    pub kept: Vec<String>,
+}
+
+/// Record `path` as written by every open job whose folder contains it;
+/// `is_new` if nothing was there before, which makes it the job's to remove.
+pub(crate) fn record(path: &Path, is_new: bool) {
+    with_jobs(|jobs| {
+        for job in jobs.values_mut().filter(|j| path.starts_with(&j.root)) {
+            if is_new && !job.outputs.iter().any(|p| p == path) {
+                job.outputs.push(path.to_path_buf());
+            }
+            if !job.written.iter().any(|p| p == path) {
+                job.written.push(path.to_path_buf());
+            }
+        }
+    })
+}
+
+/// Err if a job covering `path` has been cancelled.
+pub(crate) fn check_cancelled(path: &Path) -> Result<(), String> {
+    let cancelled = with_jobs(|jobs| jobs.values().any(|j| j.cancelled && path.starts_with(&j.root)));
+    if cancelled {
+        Err(format!("Cancelled while writing {}", path.display()))
+    } else {
+        Ok(())
+    }
+}
+
+fn remove_outputs(job_id: &str, outputs: Vec<PathBuf>) -> JobCleanup {
+    let mut cleanup =
+        JobCleanup { job_id: job_id.to_string(), removed: Vec::new(), failed: Vec::new(), kept: Vec::new() };
+    for path in outputs.into_iter().rev() {
+        match std::fs::remove_file(&path) {
+            Ok(()) => cleanup.removed.push(path.to_string_lossy().to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => cleanup.failed.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    if !cleanup.removed.is_empty() || !cleanup.failed.is_empty() {
+        println!(
+            "[jobs] {}: removed {} output(s), {} could not be removed",
+            job_id,
+            cleanup.removed.len(),
+            cleanup.failed.len()
+        );
+    }
+    cleanup
+}
+
+/// Open a job writing into `folder`. Reopening an id starts it afresh.
+/// `source` and `url` name the entry being recovered in the export mapping.
+/// Requires a `consent` token covering `folder`, since the job may later
+/// delete what it wrote there.
+#[tauri::command]
+pub fn begin_output_job(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    job_id: String,
+    folder: String,
+    source: Option<String>,
+    url: Option<String>,
+    consent: String,
+) -> Result<(), String> {
+    let folder = state.check(&consent, &folder)?;
+    open_job(job_id, folder, source, url);
+    Ok(())
+}
+
+fn open_job(job_id: String, folder: String, source: Option<String>, url: Option<String>) {
+    let root = PathBuf::from(crate::path_display::expand_path(&folder));
+    with_jobs(|jobs| jobs.insert(job_id, Job { root, source, url, ..Job::default() }));
+}
+
+/// Record a file the job created outside `OutputFile` (e.g. an ffmpeg output).
+/// A file that already exists is not the job's and is left alone; one outside
+/// the job's folder is refused.
+#[tauri::command]
+pub fn track_job_output(job_id: String, path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let is_new = !path.exists();
+    with_jobs(|jobs| {
+        let job = jobs.get_mut(&job_id).ok_or_else(|| format!("No open output job {}", job_id))?;
+        if path.components().any(|c| c == std::path::Component::ParentDir) || !path.starts_with(&job.root) {
+            return Err(format!("{} is outside the job's folder {}", path.display(), job.root.display()));
+        }
+        if is_new {
+            job.outputs.push(path.clone());
+        }
+        job.written.push(path);
+        Ok(())
+    })
+}
+
+/// Make the job's in-flight and future writes fail. Its files are removed by
+/// `end_output_job` or `clean_job_outputs`.
+#[tauri::command]
+pub fn cancel_output_job(job_id: String) -> Result<(), String> {
+    with_jobs(|jobs| {
+        let job = jobs.get_mut(&job_id).ok_or_else(|| format!("No open output job {}", job_id))?;
+        job.cancelled = true;
+        Ok(())
+    })
+}
+
+/// Remove every file the job has created so far; the job stays open.
+/// Requires a `consent` token covering the job's folder.
+#[tauri::command]
+pub fn clean_job_outputs(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    job_id: String,
+    consent: String,
+) -> Result<JobCleanup, String> {
+    let root = with_jobs(|jobs| jobs.get(&job_id).map(|job| job.root.clone()))
+        .ok_or_else(|| format!("No open output job {}", job_id))?;
+    state.check(&consent, &root.to_string_lossy())?;
+    let outputs = with_jobs(|jobs| {
+        let job = jobs.get_mut(&job_id).ok_or_else(|| format!("No open output job {}", job_id))?;
+        Ok::<_, String>(std::mem::take(&mut job.outputs))
+    })?;
+    Ok(remove_outputs(&job_id, outputs))
+}
+
+/// Close the job. Unless it succeeded (and wasn't cancelled), its files are
+/// removed; if it did, they are added to the open export mapping and listed
+/// in `kept`, e.g. for sending on to an export target. Requires a
+/// `consent` token covering the job's folder before anything is removed.
+#[tauri::command]
+pub fn end_output_job(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    job_id: String,
+    succeeded: bool,
+    consent: String,
+) -> Result<JobCleanup, String> {
+    if let Some(root) = with_jobs(|jobs| jobs.get(&job_id).map(|job| job.root.clone())) {
+        state.check(&consent, &root.to_string_lossy())?;
+    }
+    Ok(close_job(job_id, succeeded))
+}
+
+fn close_job(job_id: String, succeeded: bool) -> JobCleanup {
+    let job = with_jobs(|jobs| jobs.remove(&job_id)).unwrap_or_default();
+    if succeeded && !job.cancelled {
+        if let Some(source) = &job.source {
+            crate::export_mapping::commit(&job.root, source, job.url.as_deref(), &job.written);
+        }
+        let mut kept: Vec<String> = Vec::new();
+        for path in job.written.iter().filter(|p| p.is_file()) {
+            let path = path.to_string_lossy().to_string();
+            if !kept.contains(&path) {
+                kept.push(path);
+            }
+        }
+        return JobCleanup { job_id, removed: Vec::new(), failed: Vec::new(), kept };
+    }
+    remove_outputs(&job_id, job.outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_job_removes_its_outputs_only() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-jobs-{}", std::process::id()));
+        let folder = dir.to_string_lossy().to_string();
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("existing.mp4");
+        std::fs::write(&existing, b"keep").unwrap();
+
+        open_job("job-a".into(), folder.clone(), None, None);
+        let raw = dir.join("clip_raw.mp4");
+        crate::output_file::write_output(&raw.to_string_lossy(), b"raw").unwrap();
+        track_job_output("job-a".into(), existing.to_string_lossy().to_string()).unwrap();
+        cancel_output_job("job-a".into()).unwrap();
+        let write_after_cancel = crate::output_file::write_output(&dir.join("late.mp4").to_string_lossy(), b"x");
+        let cleanup = close_job("job-a".into(), true);
+
+        let raw_left = raw.exists();
+        let existing_left = existing.exists();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(write_after_cancel.is_err());
+        assert_eq!(cleanup.removed, vec![raw.to_string_lossy().to_string()]);
+        assert!(!raw_left && existing_left);
+    }
+}
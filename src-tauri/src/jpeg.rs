@@ -0,0 +1,422 @@
+// ─── JPEG: cutting a truncated scan at its last whole MCU ───────────────────
+//
+// When a JPEG's tail never arrived, closing the file with an EOI right where
+// the data stops leaves the last MCU (the 8×8 / 16×16 block group the image
+// is coded in) half-decoded, which shows up as a smeared or miscoloured
+// block, and some decoders reject the file outright. For baseline Huffman
+// JPEGs the entropy-coded data is decoded (coefficients are read, not
+// transformed) up to the last MCU that decodes whole; the scan is cut there
+// with its final byte padded with 1-bits as the standard requires. The rest
+// of the image can optionally be filled with grey MCUs (DC 0, end-of-block)
+// so every decoder shows the full picture size. Progressive and arithmetic
+// coded files are left to the marker-level repair.
+
+/// Sets of Huffman tables a baseline/extended JPEG can define.
+const HUFFMAN_SLOTS: usize = 4;
+/// Largest DC difference category (12-bit samples go up to 15, not baseline).
+const MAX_DC_CATEGORY: u8 = 11;
+const EOB: u8 = 0x00;
+const ZRL: u8 = 0xF0;
+
+/// A truncated JPEG cut at its last whole MCU.
+pub struct McuCut {
+    /// The whole file: headers, the scan up to the cut (plus grey MCUs), EOI
+    pub data: Vec<u8>,
+    pub mcus: u64,
+    pub total_mcus: u64,
+    pub gray_padded: bool,
+}
+
+/// Canonical Huffman table, in the decoding form of JPEG Annex F plus the
+/// codes by symbol for writing grey MCUs.
+struct Huffman {
+    min_code: [i32; 17],
+    max_code: [i32; 17],
+    first_value: [usize; 17],
+    values: Vec<u8>,
+    codes: [Option<(u32, u8)>; 256],
+}
+
+impl Huffman {
+    /// Build from the DHT counts per code length (1–16) and symbols.
+    fn new(counts: &[u8], values: &[u8]) -> Option<Self> {
+        let mut table = Huffman {
+            min_code: [0; 17],
+            max_code: [-1; 17],
+            first_value: [0; 17],
+            values: values.to_vec(),
+            codes: [None; 256],
+        };
+        let mut code = 0u32;
+        let mut k = 0;
+        for len in 1..=16 {
+            let count = counts[len - 1] as usize;
+            if count > 0 {
+                table.first_value[len] = k;
+                table.min_code[len] = code as i32;
+                for _ in 0..count {
+                    if code >= 1 << len {
+                        return None;
+                    }
+                    let symbol = *values.get(k)? as usize;
+                    table.codes[symbol].get_or_insert((code, len as u8));
+                    code += 1;
+                    k += 1;
+                }
+                table.max_code[len] = code as i32 - 1;
+            }
+            code <<= 1;
+        }
+        Some(table)
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Option<u8> {
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = code << 1 | bits.bit()? as i32;
+            if code <= self.max_code[len] {
+                return self.values.get(self.first_value[len] + (code - self.min_code[len]) as usize).copied();
+            }
+        }
+        None
+    }
+}
+
+/// Entropy-coded data reader: skips stuffed zero bytes, stops at a marker.
+#[derive(Clone)]
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Where the byte being read starts (before `pos` and any stuffed zero)
+    byte_start: usize,
+    byte: u8,
+    left: u8,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Option<u32> {
+        if self.left == 0 {
+            let byte = *self.data.get(self.pos)?;
+            if byte == 0xFF && *self.data.get(self.pos + 1)? != 0x00 {
+                return None;
+            }
+            self.byte_start = self.pos;
+            self.pos += if byte == 0xFF { 2 } else { 1 };
+            self.byte = byte;
+            self.left = 8;
+        }
+        self.left -= 1;
+        Some((self.byte >> self.left) as u32 & 1)
+    }
+
+    fn bits(&mut self, n: u8) -> Option<u32> {
+        (0..n).try_fold(0, |acc, _| Some(acc << 1 | self.bit()?))
+    }
+
+    /// Drop the rest of the byte and read RSTn.
+    fn restart(&mut self, n: u8) -> bool {
+        self.left = 0;
+        if self.data.get(self.pos..self.pos + 2) != Some(&[0xFF, 0xD0 + n][..]) {
+            return false;
+        }
+        self.pos += 2;
+        true
+    }
+}
+
+/// Bit writer for grey MCUs, stuffing a zero after every 0xFF.
+struct BitWriter {
+    out: Vec<u8>,
+    byte: u32,
+    used: u8,
+}
+
+impl BitWriter {
+    fn put(&mut self, code: u32, len: u8) {
+        for i in (0..len).rev() {
+            self.byte = self.byte << 1 | (code >> i & 1);
+            self.used += 1;
+            if self.used == 8 {
+                self.out.push(self.byte as u8);
+                if self.byte == 0xFF {
+                    self.out.push(0x00);
+                }
+                self.byte = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    /// Pad the last byte with 1-bits.
+    fn align(&mut self) {
+        while self.used != 0 {
+            self.put(1, 1);
+        }
+    }
+}
+
+/// One component of the scan: frame component index, blocks per MCU and
+/// its DC/AC table slots.
+struct ScanComponent {
+    index: usize,
+    blocks: u32,
+    dc: usize,
+    ac: usize,
+}
+
+struct Scan {
+    components: Vec<ScanComponent>,
+    dc: [Option<Huffman>; HUFFMAN_SLOTS],
+    ac: [Option<Huffman>; HUFFMAN_SLOTS],
+    restart_interval: u64,
+    total_mcus: u64,
+    /// Offset of the entropy-coded data
+    start: usize,
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<usize> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?) as usize)
+}
+
+/// Read the headers up to the first SOS of a baseline or extended Huffman
+/// JPEG. `None` for other coding processes or damaged headers.
+fn first_scan(data: &[u8]) -> Option<Scan> {
+    // (id, horizontal, vertical sampling) per frame component
+    let mut frame: Vec<(u8, u32, u32)> = Vec::new();
+    let (mut width, mut height) = (0, 0);
+    let mut dc: [Option<Huffman>; HUFFMAN_SLOTS] = Default::default();
+    let mut ac: [Option<Huffman>; HUFFMAN_SLOTS] = Default::default();
+    let mut restart_interval = 0;
+    let mut pos = 2;
+    loop {
+        while data.get(pos..pos + 2)? == [0xFF, 0xFF] {
+            pos += 1;
+        }
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        let len = be_u16(data, pos + 2)?;
+        let segment = data.get(pos + 4..pos + 2 + len)?;
+        match marker {
+            0xC0 | 0xC1 => {
+                height = be_u16(segment, 1)? as u32;
+                width = be_u16(segment, 3)? as u32;
+                let count = *segment.get(5)? as usize;
+                frame = segment.get(6..6 + 3 * count)?
+                    .chunks(3)
+                    .map(|c| (c[0], c[1] as u32 >> 4, c[1] as u32 & 15))
+                    .collect();
+            }
+            0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return None,
+            0xC4 => {
+                let mut p = 0;
+                while p < segment.len() {
+                    let (class, slot) = (segment[p] >> 4, (segment[p] & 15) as usize);
+                    let counts = segment.get(p + 1..p + 17)?;
+                    let n: usize = counts.iter().map(|&c| c as usize).sum();
+                    let table = Huffman::new(counts, segment.get(p + 17..p + 17 + n)?)?;
+                    match class {
+                        0 => *dc.get_mut(slot)? = Some(table),
+                        1 => *ac.get_mut(slot)? = Some(table),
+                        _ => return None,
+                    }
+                    p += 17 + n;
+                }
+            }
+            0xDD => restart_interval = be_u16(segment, 0)? as u64,
+            0xDA => break,
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+
+    let segment = data.get(pos + 4..pos + 2 + be_u16(data, pos + 2)?)?;
+    let h_max = frame.iter().map(|c| c.1).max()?;
+    let v_max = frame.iter().map(|c| c.2).max()?;
+    if width == 0 || height == 0 || h_max == 0 || v_max == 0 {
+        return None;
+    }
+    let count = *segment.first()? as usize;
+    let mut components = Vec::with_capacity(count);
+    for c in segment.get(1..1 + 2 * count)?.chunks(2) {
+        let index = frame.iter().position(|f| f.0 == c[0])?;
+        let (dc_slot, ac_slot) = ((c[1] >> 4) as usize, (c[1] & 15) as usize);
+        dc.get(dc_slot)?.as_ref()?;
+        ac.get(ac_slot)?.as_ref()?;
+        let (_, h, v) = frame[index];
+        components.push(ScanComponent { index, blocks: h * v, dc: dc_slot, ac: ac_slot });
+    }
+    let total_mcus = if let [only] = components.as_mut_slice() {
+        // Non-interleaved: one block per MCU over the component's own size.
+        let (_, h, v) = frame[only.index];
+        only.blocks = 1;
+        ((width * h).div_ceil(h_max).div_ceil(8) * (height * v).div_ceil(v_max).div_ceil(8)) as u64
+    } else {
+        (width.div_ceil(8 * h_max) * height.div_ceil(8 * v_max)) as u64
+    };
+    Some(Scan { components, dc, ac, restart_interval, total_mcus, start: pos + 2 + segment.len() + 2 })
+}
+
+/// Decode one MCU's coefficients, updating the DC predictors.
+fn decode_mcu(scan: &Scan, bits: &mut BitReader, predictors: &mut [i32]) -> Option<()> {
+    for (i, component) in scan.components.iter().enumerate() {
+        let dc = scan.dc[component.dc].as_ref()?;
+        let ac = scan.ac[component.ac].as_ref()?;
+        for _ in 0..component.blocks {
+            let category = dc.decode(bits)?;
+            if category > MAX_DC_CATEGORY {
+                return None;
+            }
+            let value = bits.bits(category)? as i32;
+            predictors[i] += if category > 0 && value < 1 << (category - 1) {
+                value - (1 << category) + 1
+            } else {
+                value
+            };
+            let mut k = 1;
+            while k < 64 {
+                let rs = ac.decode(bits)?;
+                match rs {
+                    EOB => break,
+                    ZRL => k += 16,
+                    _ => {
+                        k += (rs >> 4) as usize;
+                        if k > 63 {
+                            return None;
+                        }
+                        bits.bits(rs & 15)?;
+                        k += 1;
+                    }
+                }
+            }
+            if k > 64 {
+                return None;
+            }
+        }
+    }
+    Some(())
+}
+
+/// Append grey MCUs from `from` to the end of the scan: each component's
+/// first block cancels its DC predictor, after that every block is DC 0 and
+/// end-of-block. `None` if a needed code is missing from the tables.
+fn pad_gray(scan: &Scan, writer: &mut BitWriter, from: u64, predictors: &mut [i32]) -> Option<()> {
+    for mcu in from..scan.total_mcus {
+        if scan.restart_interval > 0 && mcu > 0 && mcu % scan.restart_interval == 0 {
+            writer.align();
+            writer.out.extend_from_slice(&[0xFF, 0xD0 + ((mcu / scan.restart_interval - 1) % 8) as u8]);
+            predictors.fill(0);
+        }
+        for (i, component) in scan.components.iter().enumerate() {
+            let dc = scan.dc[component.dc].as_ref()?;
+            let ac = scan.ac[component.ac].as_ref()?;
+            for _ in 0..component.blocks {
+                let diff = -std::mem::take(&mut predictors[i]);
+                let category = (32 - diff.unsigned_abs().leading_zeros()) as u8;
+                if category > MAX_DC_CATEGORY {
+                    return None;
+                }
+                let (code, len) = dc.codes[category as usize]?;
+                writer.put(code, len);
+                let value = if diff < 0 { diff - 1 } else { diff };
+                writer.put(value as u32 & ((1 << category) - 1), category);
+                let (code, len) = ac.codes[EOB as usize]?;
+                writer.put(code, len);
+            }
+        }
+    }
+    Some(())
+}
+
+/// Cut a JPEG whose first scan runs to the end of `data` at the last MCU
+/// that decodes whole, optionally filling the rest with grey, and close it
+/// with EOI. `None` if the file isn't a baseline/extended Huffman JPEG.
+pub fn cut_at_last_mcu(data: &[u8], gray: bool) -> Option<McuCut> {
+    let scan = first_scan(data)?;
+    let mut bits = BitReader { data, pos: scan.start, byte_start: scan.start, byte: 0, left: 0 };
+    let mut predictors = vec![0; scan.components.len()];
+    let mut last = (bits.clone(), predictors.clone());
+    let mut mcus = 0;
+    while mcus < scan.total_mcus {
+        if scan.restart_interval > 0 && mcus > 0 && mcus % scan.restart_interval == 0 {
+            if !bits.restart(((mcus / scan.restart_interval - 1) % 8) as u8) {
+                break;
+            }
+            predictors.fill(0);
+        }
+        if decode_mcu(&scan, &mut bits, &mut predictors).is_none() {
+            break;
+        }
+        mcus += 1;
+        last = (bits.clone(), predictors.clone());
+    }
+
+    // The byte holding the end of the last MCU is rewritten with its used bits.
+    let (end, mut predictors) = last;
+    let cut = if end.left > 0 { end.byte_start } else { end.pos };
+    let mut writer = BitWriter { out: data[..cut].to_vec(), byte: 0, used: 0 };
+    if end.left > 0 {
+        writer.put((end.byte >> end.left) as u32, 8 - end.left);
+    }
+    let mut gray_padded = false;
+    if gray && mcus < scan.total_mcus {
+        let mut padded = BitWriter { out: writer.out.clone(), byte: writer.byte, used: writer.used };
+        if pad_gray(&scan, &mut padded, mcus, &mut predictors).is_some() {
+            writer = padded;
+            gray_padded = true;
+        }
+    }
+    writer.align();
+    writer.out.extend_from_slice(&[0xFF, 0xD9]);
+    Some(McuCut { data: writer.out, mcus, total_mcus: scan.total_mcus, gray_padded })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16×8 greyscale baseline JPEG (two MCUs), one-code tables: DC category
+    /// 0 is "0", AC end-of-block is "0". Each block is two 0-bits.
+    fn gray_jpeg(restart: bool) -> Vec<u8> {
+        let mut j = vec![0xFF, 0xD8];
+        j.extend_from_slice(&[0xFF, 0xC0, 0, 11, 8, 0, 8, 0, 16, 1, 1, 0x11, 0]);
+        let mut counts = [0u8; 16];
+        counts[0] = 1;
+        for class in [0x00, 0x10] {
+            j.extend_from_slice(&[0xFF, 0xC4, 0, 20, class]);
+            j.extend_from_slice(&counts);
+            j.push(0);
+        }
+        if restart {
+            j.extend_from_slice(&[0xFF, 0xDD, 0, 4, 0, 1]);
+        }
+        j.extend_from_slice(&[0xFF, 0xDA, 0, 8, 1, 1, 0x00, 0, 63, 0]);
+        j
+    }
+
+    #[test]
+    fn cuts_at_the_last_whole_mcu() {
+        // Both MCUs are in the first four bits; the rest of the byte is padding.
+        let data = [gray_jpeg(false), vec![0b0000_1111]].concat();
+        let cut = cut_at_last_mcu(&data, false).unwrap();
+        assert_eq!((cut.mcus, cut.total_mcus, cut.gray_padded), (2, 2, false));
+        assert!(cut.data.ends_with(&[0b0000_1111, 0xFF, 0xD9]));
+
+        // With a restart interval of one MCU, the RST0 after the first never arrived.
+        let truncated = [gray_jpeg(true), vec![0b0011_1111]].concat();
+        let cut = cut_at_last_mcu(&truncated, false).unwrap();
+        assert_eq!((cut.mcus, cut.total_mcus), (1, 2));
+        assert!(cut.data.ends_with(&[0b0011_1111, 0xFF, 0xD9]));
+    }
+
+    #[test]
+    fn fills_the_missing_mcus_with_grey() {
+        let truncated = [gray_jpeg(true), vec![0b0011_1111]].concat();
+        let cut = cut_at_last_mcu(&truncated, true).unwrap();
+        assert_eq!((cut.mcus, cut.gray_padded), (1, true));
+        // RST0, then one grey block (DC 0, EOB) padded with 1-bits.
+        assert!(cut.data.ends_with(&[0b0011_1111, 0xFF, 0xD0, 0b0011_1111, 0xFF, 0xD9]));
+        assert!(cut_at_last_mcu(&[0xFF, 0xD8, 0xFF, 0xC2, 0, 2], true).is_none());
+    }
+}
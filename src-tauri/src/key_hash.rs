@@ -0,0 +1,276 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{FLAG_HAS_KEY_SHA256, SIMPLE_CACHE_EOF_MAGIC, SIMPLE_CACHE_EOF_SIZE, SIMPLE_CACHE_HEADER_SIZE, SIMPLE_CACHE_MAGIC};
+
+// ─── Simple Cache key_hash verification ─────────────────────────────────────
+//
+// Every Simple Cache file header stores `key_hash`, Chromium's
+// `base::PersistentHash` (Paul Hsieh's SuperFastHash) of the URL key that
+// follows it. Checking it catches a damaged header before its `key_length`
+// is used to find the body: a trashed length would otherwise send every
+// later offset into the wrong bytes. When the hash doesn't match, the key is
+// assumed to be intact text and one repair is tried — the key ends where the
+// printable URL characters end — and accepted only if that length hashes to
+// the stored value.
+//
+// `_0` files written with FLAG_HAS_KEY_SHA256 also carry the key's SHA-256
+// just before EOF0. That digest is written when the entry is closed, so a
+// match says the key at the start and the record at the end belong to the
+// same write — the entry wasn't partially overwritten.
+
+/// Keys are URLs (plus an optional isolation prefix); anything longer is corruption.
+pub(crate) const MAX_KEY_LENGTH: usize = 64 * 1024;
+
+/// Chromium's SuperFastHash (base/third_party/superfasthash). Tail bytes are
+/// sign-extended, as in the C original's `signed char` casts.
+pub fn super_fast_hash(data: &[u8]) -> u32 {
+    if data.is_empty() {
+        return 0;
+    }
+    let get16 = |d: &[u8]| u16::from_le_bytes([d[0], d[1]]) as u32;
+    let mut hash = data.len() as u32;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        hash = hash.wrapping_add(get16(block));
+        let tmp = (get16(&block[2..]) << 11) ^ hash;
+        hash = (hash << 16) ^ tmp;
+        hash = hash.wrapping_add(hash >> 11);
+    }
+    let rem = blocks.remainder();
+    match rem.len() {
+        3 => {
+            hash = hash.wrapping_add(get16(rem));
+            hash ^= hash << 16;
+            hash ^= ((rem[2] as i8 as i32) << 18) as u32;
+            hash = hash.wrapping_add(hash >> 11);
+        }
+        2 => {
+            hash = hash.wrapping_add(get16(rem));
+            hash ^= hash << 11;
+            hash = hash.wrapping_add(hash >> 17);
+        }
+        1 => {
+            hash = hash.wrapping_add(rem[0] as i8 as i32 as u32);
+            hash ^= hash << 10;
+            hash = hash.wrapping_add(hash >> 1);
+        }
+        _ => {}
+    }
+    hash ^= hash << 3;
+    hash = hash.wrapping_add(hash >> 5);
+    hash ^= hash << 4;
+    hash = hash.wrapping_add(hash >> 17);
+    hash ^= hash << 25;
+    hash = hash.wrapping_add(hash >> 6);
+    hash
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct KeyHashCheck {
+    pub path: String,
+    /// "ok", "repaired" (key_length was wrong, the key itself matched),
+    /// "mismatch", "not_simple_cache" or "unreadable"
+    pub status: &'static str,
+    pub stored_hash: Option<u32>,
+    pub computed_hash: Option<u32>,
+    /// The trustworthy key length, if any
+    pub key_length: Option<usize>,
+    /// The key matched the SHA-256 stored before EOF0; None if the file
+    /// carries no digest (not a `_0` file, or FLAG_HAS_KEY_SHA256 unset)
+    pub key_verified: Option<bool>,
+}
+
+/// Check `data` (a Simple Cache file, or at least its header + key) and
+/// return the key length that can be trusted.
+pub fn check_key(data: &[u8]) -> (Option<usize>, KeyHashCheck) {
+    let mut check = KeyHashCheck {
+        path: String::new(),
+        status: "not_simple_cache",
+        stored_hash: None,
+        computed_hash: None,
+        key_length: None,
+        key_verified: None,
+    };
+    if data.len() < SIMPLE_CACHE_HEADER_SIZE || u64::from_le_bytes(data[0..8].try_into().unwrap()) != SIMPLE_CACHE_MAGIC {
+        return (None, check);
+    }
+    let key_length = u32::from_le_bytes(data[12..16].try_into().unwrap()) as usize;
+    let stored = u32::from_le_bytes(data[16..20].try_into().unwrap());
+    check.stored_hash = Some(stored);
+    let key_at = |len: usize| {
+        (len > 0 && len <= MAX_KEY_LENGTH).then(|| data.get(SIMPLE_CACHE_HEADER_SIZE..SIMPLE_CACHE_HEADER_SIZE + len)).flatten()
+    };
+    if let Some(key) = key_at(key_length) {
+        let computed = super_fast_hash(key);
+        check.computed_hash = Some(computed);
+        if computed == stored {
+            check.status = "ok";
+            check.key_length = Some(key_length);
+            return (Some(key_length), check);
+        }
+    }
+    let printable = data[SIMPLE_CACHE_HEADER_SIZE..]
+        .iter()
+        .take(MAX_KEY_LENGTH + 1)
+        .take_while(|b| (0x20..0x7f).contains(*b))
+        .count();
+    if let Some(key) = key_at(printable) {
+        if super_fast_hash(key) == stored {
+            check.status = "repaired";
+            check.key_length = Some(printable);
+            return (Some(printable), check);
+        }
+    }
+    check.status = "mismatch";
+    (None, check)
+}
+
+/// Trusted key length of the Simple Cache file in `data`, logging when the
+/// stored `key_length` had to be corrected or could not be trusted.
+pub fn checked_key_length(data: &[u8], path: &str) -> Option<usize> {
+    let (key_length, check) = check_key(data);
+    match check.status {
+        "repaired" => eprintln!(
+            "[key_hash] {}: key_length {} is wrong, key hashes correctly at {}",
+            path,
+            u32::from_le_bytes(data[12..16].try_into().unwrap()),
+            key_length.unwrap_or(0)
+        ),
+        "mismatch" => eprintln!(
+            "[key_hash] {}: key_hash {:08x} does not match the stored key — header is corrupt",
+            path,
+            check.stored_hash.unwrap_or(0)
+        ),
+        _ => {}
+    }
+    key_length
+}
+
+/// Trusted key length for an open Simple Cache file positioned just past its
+/// `header`. The stored key is read first; only if it doesn't hash correctly
+/// is more read (up to MAX_KEY_LENGTH) to look for the real end of the key.
+pub fn read_checked_key_length(file: &mut impl Read, header: &[u8], path: &str) -> Option<usize> {
+    let stored_length = u32::from_le_bytes(header.get(12..16)?.try_into().ok()?) as usize;
+    let mut head = header.to_vec();
+    // One byte past the key, so the printable-run repair can see where it ends.
+    let first = stored_length.min(MAX_KEY_LENGTH) + 1;
+    file.take(first as u64).read_to_end(&mut head).ok()?;
+    if let (Some(key_length), KeyHashCheck { status: "ok", .. }) = check_key(&head) {
+        return Some(key_length);
+    }
+    file.take((MAX_KEY_LENGTH + 1 - first) as u64).read_to_end(&mut head).ok()?;
+    checked_key_length(&head, path)
+}
+
+/// Check `key` against the SHA-256 in `tail`, the last bytes of a `_0` file
+/// (digest + EOF0). None if EOF0 is missing or has no digest.
+pub fn check_key_sha256(tail: &[u8], key: &[u8]) -> Option<bool> {
+    let eof0 = tail.get(tail.len().checked_sub(SIMPLE_CACHE_EOF_SIZE)?..)?;
+    let magic = u64::from_le_bytes(eof0[0..8].try_into().ok()?);
+    let flags = u32::from_le_bytes(eof0[8..12].try_into().ok()?);
+    if magic != SIMPLE_CACHE_EOF_MAGIC || flags & FLAG_HAS_KEY_SHA256 == 0 {
+        return None;
+    }
+    let digest_at = tail.len().checked_sub(SIMPLE_CACHE_EOF_SIZE + 32)?;
+    Some(Sha256::digest(key).as_slice() == &tail[digest_at..digest_at + 32])
+}
+
+fn verify_file(path: &str) -> std::io::Result<KeyHashCheck> {
+    let mut file = std::fs::File::open(path)?;
+    let mut head = Vec::new();
+    (&mut file).take((SIMPLE_CACHE_HEADER_SIZE + MAX_KEY_LENGTH) as u64).read_to_end(&mut head)?;
+    let (key_length, mut check) = check_key(&head);
+    let is_stream0_file = path.ends_with("_0");
+    if let (Some(key_length), true) = (key_length, is_stream0_file) {
+        let tail_len = (SIMPLE_CACHE_EOF_SIZE + 32) as u64;
+        if file.metadata()?.len() >= tail_len {
+            let mut tail = vec![0u8; tail_len as usize];
+            file.seek(SeekFrom::End(-(tail_len as i64)))?;
+            file.read_exact(&mut tail)?;
+            let key = &head[SIMPLE_CACHE_HEADER_SIZE..SIMPLE_CACHE_HEADER_SIZE + key_length];
+            check.key_verified = check_key_sha256(&tail, key);
+        }
+    }
+    Ok(check)
+}
+
+/// Verify the header `key_hash` of each Simple Cache file, and for `_0`
+/// files the key SHA-256 before EOF0. Only the header, key and the last
+/// 56 bytes are read.
+#[tauri::command(async)]
+pub fn verify_key_hashes(paths: Vec<String>) -> Vec<KeyHashCheck> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let _permit = crate::io_limit::acquire_for(&path);
+            let mut check = verify_file(&path).unwrap_or(KeyHashCheck {
+                path: String::new(),
+                status: "unreadable",
+                stored_hash: None,
+                computed_hash: None,
+                key_length: None,
+                key_verified: None,
+            });
+            check.path = path;
+            check
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_chromium_superfasthash() {
+        // Reference values from base/third_party/superfasthash/superfasthash.c
+        assert_eq!(super_fast_hash(b""), 0);
+        assert_eq!(super_fast_hash(b"a"), 0x115ea782);
+        assert_eq!(super_fast_hash(b"ab"), 0x516b8b44);
+        assert_eq!(super_fast_hash(b"abc"), 0xd2be198a);
+        assert_eq!(super_fast_hash(b"abcd"), 0xdad8b8db);
+        assert_eq!(super_fast_hash(b"https://cdn.discordapp.com/attachments/1/2/clip.mp4"), 0x8a8c8153);
+        assert_eq!(super_fast_hash(&[0xff, 0x80, 0xc3]), 0x056e8ffd);
+        assert_eq!(super_fast_hash(&[0xc3]), 0x3572fe0a);
+    }
+
+    #[test]
+    fn flags_and_repairs_bad_headers() {
+        let key = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let entry = crate::fixture::simple_entry(key, &["HTTP/1.1 200 OK"], &[0u8, 1, 2, 3]);
+        assert_eq!(check_key(&entry).1.status, "ok");
+
+        let mut bad_length = entry.clone();
+        bad_length[12..16].copy_from_slice(&9000u32.to_le_bytes());
+        let (len, check) = check_key(&bad_length);
+        assert_eq!((len, check.status), (Some(key.len()), "repaired"));
+
+        let mut bad_key = entry.clone();
+        bad_key[30] ^= 0x01;
+        let (len, check) = check_key(&bad_key);
+        assert_eq!((len, check.status), (None, "mismatch"));
+    }
+
+    #[test]
+    fn verifies_key_sha256_before_eof0() {
+        let key = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let entry = crate::fixture::simple_entry(key, &["HTTP/1.1 200 OK"], b"body");
+        let tail = &entry[entry.len() - 56..];
+        assert_eq!(check_key_sha256(tail, key.as_bytes()), Some(true));
+        assert_eq!(check_key_sha256(tail, b"https://cdn.discordapp.com/other"), Some(false));
+
+        let mut no_digest = tail.to_vec();
+        no_digest[32 + 8] &= !(FLAG_HAS_KEY_SHA256 as u8);
+        assert_eq!(check_key_sha256(&no_digest, key.as_bytes()), None);
+
+        let dir = std::env::temp_dir().join(format!("cachephoenix-keysha-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("0123456789abcdef_0");
+        std::fs::write(&path, &entry).unwrap();
+        let checks = verify_key_hashes(vec![path.to_string_lossy().to_string()]);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!((checks[0].status, checks[0].key_verified), ("ok", Some(true)));
+    }
+}
@@ -0,0 +1,583 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+// ─── Legacy containers: Ogg (OGV/Theora) and AVI ────────────────────────────
+//
+// Old shared clips still turn up as OGV or AVI. Both survive in a cache the
+// same way MP4s do — truncated, sometimes with a damaged page — so they get a
+// pass-through repair (no re-encoding) before ffmpeg sees them:
+//   - Ogg: every page is checked (capture pattern, version, length, CRC,
+//     granule position never going backwards). Corrupt pages are dropped,
+//     the stream is resynced at the next "OggS", jumps in the page sequence
+//     are counted as lost pages, a truncated tail is cut at the last complete
+//     page and the last page of each stream gets EOS set. This is also the
+//     path for Discord voice messages, which are Ogg Opus.
+//   - AVI: the `movi` list is walked chunk by chunk; a truncated tail is cut,
+//     RIFF/movi sizes are fixed and a missing or short `idx1` index is
+//     rebuilt so players can seek. OpenDML (AVIX) extensions are left alone.
+// MP3 frame trimming (mp3.rs) reports through the same type.
+
+const OGG_CAPTURE: &[u8; 4] = b"OggS";
+const OGG_HEADER_SIZE: usize = 27;
+const OGG_FLAG_BOS: u8 = 0x02;
+const OGG_FLAG_EOS: u8 = 0x04;
+/// Granule position of a page on which no packet ends.
+const OGG_NO_GRANULE: u64 = u64::MAX;
+/// Opus granule positions always count 48 kHz samples.
+const OPUS_RATE: f64 = 48_000.0;
+
+const AVIF_HASINDEX: u32 = 0x10;
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+#[derive(Debug, Serialize)]
+pub struct LegacyRepairReport {
+    /// "ogg", "avi", "mp3" or "unknown"
+    pub container: &'static str,
+    /// First stream's codec: "theora", "vorbis", "opus", "flac", "mp3", or an AVI handler FourCC
+    pub codec: Option<String>,
+    /// "none" (file was already sound), "repaired" or "unsupported"
+    pub action: &'static str,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// Ogg pages kept / AVI chunks in `movi` / MPEG audio frames kept
+    pub units: usize,
+    /// Ogg pages dropped for a bad CRC or header
+    pub bad_pages: usize,
+    /// Ogg pages missing according to the page sequence numbers
+    pub missing_pages: u64,
+    /// Playing time from the first stream's last granule position (Opus, Vorbis) or the MP3 frames kept
+    pub duration_secs: Option<f64>,
+    /// Bytes of garbage or truncated data removed
+    pub dropped_bytes: u64,
+    /// Number of AVI idx1 entries written (0 if the index was kept)
+    pub index_entries: usize,
+    pub details: Vec<String>,
+}
+
+impl LegacyRepairReport {
+    pub(crate) fn new(container: &'static str, len: usize) -> Self {
+        Self {
+            container,
+            codec: None,
+            action: "none",
+            bytes_before: len as u64,
+            bytes_after: len as u64,
+            units: 0,
+            bad_pages: 0,
+            missing_pages: 0,
+            duration_secs: None,
+            dropped_bytes: 0,
+            index_entries: 0,
+            details: Vec::new(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Ogg
+// ---------------------------------------------------------------------------
+
+/// CRC-32 as used by Ogg: polynomial 0x04C11DB7, MSB-first, init 0, no final xor.
+fn ogg_crc(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &b in data {
+        crc ^= (b as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04C1_1DB7 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Granule position and page sequence number of a page header.
+fn ogg_position(page: &[u8]) -> (u64, u32) {
+    let granule = u64::from_le_bytes(page[6..14].try_into().unwrap());
+    (granule, u32::from_le_bytes(page[18..22].try_into().unwrap()))
+}
+
+/// Samples per second that granule positions count, for Opus and Vorbis.
+/// Opus also has pre-skip samples to subtract.
+fn ogg_granule_rate(bos_page: &[u8]) -> Option<(f64, u64)> {
+    let packet = bos_page.get(OGG_HEADER_SIZE + bos_page[26] as usize..)?;
+    if packet.starts_with(b"OpusHead") {
+        let pre_skip = u16::from_le_bytes(packet.get(10..12)?.try_into().ok()?);
+        Some((OPUS_RATE, pre_skip as u64))
+    } else if packet.starts_with(b"\x01vorbis") {
+        let rate = u32::from_le_bytes(packet.get(12..16)?.try_into().ok()?);
+        (rate > 0).then_some((rate as f64, 0))
+    } else {
+        None
+    }
+}
+
+/// A page starting at `data[0]`: (total length, serial, header_type), or
+/// `None` if the header is invalid or the page runs past the end of `data`.
+fn ogg_page(data: &[u8]) -> Option<(usize, u32, u8)> {
+    if data.len() < OGG_HEADER_SIZE || &data[0..4] != OGG_CAPTURE || data[4] != 0 {
+        return None;
+    }
+    let segments = data[26] as usize;
+    let header_len = OGG_HEADER_SIZE + segments;
+    if data.len() < header_len {
+        return None;
+    }
+    let body_len: usize = data[OGG_HEADER_SIZE..header_len].iter().map(|&s| s as usize).sum();
+    let total = header_len + body_len;
+    if data.len() < total {
+        return None;
+    }
+    let serial = u32::from_le_bytes(data[14..18].try_into().ok()?);
+    Some((total, serial, data[5]))
+}
+
+fn ogg_page_crc_ok(page: &[u8]) -> bool {
+    let stored = u32::from_le_bytes([page[22], page[23], page[24], page[25]]);
+    let mut copy = page.to_vec();
+    copy[22..26].fill(0);
+    ogg_crc(&copy) == stored
+}
+
+fn set_ogg_crc(page: &mut [u8]) {
+    page[22..26].fill(0);
+    let crc = ogg_crc(page);
+    page[22..26].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Codec of the first logical stream, from its BOS packet.
+pub fn ogg_codec(data: &[u8]) -> Option<&'static str> {
+    if data.len() < OGG_HEADER_SIZE || &data[0..4] != OGG_CAPTURE {
+        return None;
+    }
+    let packet = data.get(OGG_HEADER_SIZE + data[26] as usize..)?;
+    let codecs: &[(&[u8], &str)] = &[
+        (b"\x80theora", "theora"),
+        (b"\x01vorbis", "vorbis"),
+        (b"OpusHead", "opus"),
+        (b"\x7fFLAC", "flac"),
+        (b"Speex   ", "speex"),
+        (b"\x80daala", "daala"),
+    ];
+    codecs.iter().find(|(magic, _)| packet.starts_with(magic)).map(|(_, c)| *c)
+}
+
+fn repair_ogg(data: &[u8]) -> (Vec<u8>, LegacyRepairReport) {
+    let mut report = LegacyRepairReport::new("ogg", data.len());
+    report.codec = ogg_codec(data).map(str::to_string);
+    let mut out = Vec::with_capacity(data.len());
+    // Offset in `out` of the last page seen for each serial.
+    let mut last_page: HashMap<u32, (usize, usize)> = HashMap::new();
+    // Next page sequence number and last granule position for each serial.
+    let mut positions: HashMap<u32, (u32, u64)> = HashMap::new();
+    let first_serial = ogg_page(data).map(|(_, serial, _)| serial);
+    let granule_rate = ogg_page(data).and_then(|(len, _, _)| ogg_granule_rate(&data[..len]));
+    let mut backwards = 0;
+    let mut pos = 0usize;
+    while pos < data.len() {
+        let rest = &data[pos..];
+        match ogg_page(rest) {
+            Some((len, serial, _)) if ogg_page_crc_ok(&rest[..len]) => {
+                let (granule, sequence) = ogg_position(rest);
+                let (next, last_granule) = positions.get(&serial).copied().unwrap_or((sequence, 0));
+                if granule != OGG_NO_GRANULE && granule < last_granule {
+                    backwards += 1;
+                    report.bad_pages += 1;
+                    report.dropped_bytes += len as u64;
+                    pos += len;
+                    continue;
+                }
+                report.missing_pages += sequence.saturating_sub(next) as u64;
+                let granule = if granule == OGG_NO_GRANULE { last_granule } else { granule };
+                positions.insert(serial, (sequence.wrapping_add(1), granule));
+                last_page.insert(serial, (out.len(), len));
+                out.extend_from_slice(&rest[..len]);
+                report.units += 1;
+                pos += len;
+                continue;
+            }
+            Some(_) => report.bad_pages += 1,
+            None if rest.starts_with(OGG_CAPTURE) && find(&rest[4..], OGG_CAPTURE).is_none() => {
+                report.details.push(format!("Truncated final page at offset {}", pos));
+                report.dropped_bytes += rest.len() as u64;
+                break;
+            }
+            None if rest.starts_with(OGG_CAPTURE) => report.bad_pages += 1,
+            None => {}
+        }
+        // Resync at the next capture pattern.
+        let skip = find(&rest[1..], OGG_CAPTURE).map(|i| i + 1).unwrap_or(rest.len());
+        report.dropped_bytes += skip as u64;
+        pos += skip;
+    }
+    if report.bad_pages > 0 {
+        report.details.push(format!("Dropped {} corrupt pages", report.bad_pages));
+    }
+    if backwards > 0 {
+        report.details.push(format!("{} of them had a granule position going backwards", backwards));
+    }
+    if report.missing_pages > 0 {
+        report.details.push(format!("{} pages missing from the sequence; playback skips there", report.missing_pages));
+    }
+    let first_granule = first_serial.and_then(|serial| positions.get(&serial)).map(|&(_, granule)| granule);
+    if let (Some((rate, pre_skip)), Some(granule)) = (granule_rate, first_granule) {
+        report.duration_secs = Some(granule.saturating_sub(pre_skip) as f64 / rate);
+    }
+    let mut eos_fixed = 0;
+    for (start, len) in last_page.values() {
+        let page = &mut out[*start..*start + *len];
+        if page[5] & OGG_FLAG_EOS == 0 {
+            page[5] |= OGG_FLAG_EOS;
+            set_ogg_crc(page);
+            eos_fixed += 1;
+        }
+    }
+    if eos_fixed > 0 {
+        report.details.push(format!("Marked end of stream on {} streams", eos_fixed));
+    }
+    if out.get(5).is_some_and(|flags| flags & OGG_FLAG_BOS == 0) {
+        report.details.push("First page is not a stream start; codec headers are missing".to_string());
+    }
+    if out.len() != data.len() || eos_fixed > 0 {
+        report.action = "repaired";
+    }
+    report.bytes_after = out.len() as u64;
+    (out, report)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+// ---------------------------------------------------------------------------
+// AVI
+// ---------------------------------------------------------------------------
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// A movi chunk: (offset of the chunk header, FourCC, data size).
+type MoviChunk = (usize, [u8; 4], u32);
+
+/// `##dc`, `##db`, `##wb`, `##pc`, `##tx`: the stream data chunk ids.
+fn is_stream_chunk(id: &[u8; 4]) -> bool {
+    id[0].is_ascii_digit() && id[1].is_ascii_digit() && id[2].is_ascii_lowercase() && id[3].is_ascii_lowercase()
+}
+
+/// Best-effort keyframe test for a video chunk: MPEG-4 Part 2 VOP type or
+/// an H.264 IDR NAL. Unknown codecs are treated as all-keyframe.
+fn is_keyframe(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(256)];
+    let mut saw_h264_slice = false;
+    for i in 0..head.len().saturating_sub(4) {
+        if head[i] != 0 || head[i + 1] != 0 || head[i + 2] != 1 {
+            continue;
+        }
+        let code = head[i + 3];
+        if code == 0xB6 {
+            return head.get(i + 4).is_some_and(|b| b >> 6 == 0);
+        }
+        match code & 0x1F {
+            5 if code & 0x80 == 0 => return true,
+            1 if code & 0x80 == 0 => saw_h264_slice = true,
+            _ => {}
+        }
+    }
+    !saw_h264_slice
+}
+
+/// Walk chunks in `data[start..end]`, descending into `LIST rec ` groups.
+/// Returns the chunks and the offset where the last complete chunk ends.
+fn walk_movi(data: &[u8], start: usize, end: usize, chunks: &mut Vec<MoviChunk>) -> usize {
+    let mut pos = start;
+    while pos + 8 <= end {
+        let id: [u8; 4] = data[pos..pos + 4].try_into().unwrap_or([0; 4]);
+        let size = le_u32(data, pos + 4).unwrap_or(0);
+        let next = pos + 8 + size as usize + (size as usize & 1);
+        if pos + 8 + size as usize > end {
+            break;
+        }
+        if &id == b"LIST" && data.get(pos + 8..pos + 12) == Some(b"rec ") {
+            let inner_end = walk_movi(data, pos + 12, pos + 8 + size as usize, chunks);
+            if inner_end < pos + 8 + size as usize {
+                return inner_end;
+            }
+        } else if is_stream_chunk(&id) {
+            chunks.push((pos, id, size));
+        } else if &id != b"JUNK" {
+            break;
+        }
+        pos = next.min(end);
+    }
+    pos
+}
+
+fn repair_avi(data: &[u8]) -> (Vec<u8>, LegacyRepairReport) {
+    let mut report = LegacyRepairReport::new("avi", data.len());
+    let unchanged = |mut report: LegacyRepairReport, msg: &str| {
+        report.details.push(msg.to_string());
+        report.action = "unsupported";
+        (data.to_vec(), report)
+    };
+
+    // Top-level chunks: find hdrl, movi and idx1.
+    let mut hdrl: Option<(usize, usize)> = None;
+    let mut movi: Option<(usize, usize)> = None;
+    let mut idx1: Option<(usize, usize)> = None;
+    let mut pos = 12usize;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = le_u32(data, pos + 4).unwrap_or(0) as usize;
+        let end = (pos + 8 + size).min(data.len());
+        match (id, data.get(pos + 8..pos + 12)) {
+            (b"LIST", Some(b"hdrl")) => hdrl = Some((pos, end)),
+            (b"LIST", Some(b"movi")) => movi = Some((pos, end)),
+            (b"idx1", _) => idx1 = Some((pos, end)),
+            _ => {}
+        }
+        pos = pos + 8 + size + (size & 1);
+    }
+    let Some((movi_start, movi_end)) = movi else {
+        return unchanged(report, "No movi list found");
+    };
+    if let Some((start, end)) = hdrl {
+        // First strh: fccType at +0, fccHandler at +4 of its data.
+        if let Some(i) = find(&data[start..end], b"strh") {
+            let h = start + i + 8 + 4;
+            if let Some(handler) = data.get(h..h + 4) {
+                let handler = String::from_utf8_lossy(handler).trim_end_matches(['\0', ' ']).to_string();
+                if !handler.is_empty() {
+                    report.codec = Some(handler);
+                }
+            }
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let movi_data_end = walk_movi(data, movi_start + 12, movi_end, &mut chunks);
+    report.units = chunks.len();
+    let truncated = movi_data_end < movi_end || movi_end < movi_start + 8 + le_u32(data, movi_start + 4).unwrap_or(0) as usize;
+    let index_ok = idx1.is_some_and(|(start, end)| {
+        let declared = le_u32(data, start + 4).unwrap_or(0) as usize;
+        start + 8 + declared == end && declared / 16 >= chunks.len()
+    });
+    if !truncated && index_ok {
+        return (data.to_vec(), report);
+    }
+
+    // Rebuild: everything before movi, a movi list of complete chunks, then idx1.
+    let mut out = Vec::with_capacity(data.len() + chunks.len() * 16 + 8);
+    out.extend_from_slice(&data[..movi_start]);
+    let movi_list_pos = out.len();
+    out.extend_from_slice(&data[movi_start..movi_data_end]);
+    let movi_size = (movi_data_end - movi_start - 8) as u32;
+    out[movi_list_pos + 4..movi_list_pos + 8].copy_from_slice(&movi_size.to_le_bytes());
+    if out.len() % 2 == 1 {
+        out.push(0);
+    }
+
+    // idx1 offsets are relative to the "movi" FourCC.
+    let movi_fourcc = movi_start + 8;
+    let video_stream = chunks
+        .iter()
+        .find(|(_, id, _)| &id[2..4] == b"dc" || &id[2..4] == b"db")
+        .map(|(_, id, _)| [id[0], id[1]]);
+    let mut video_frames = 0u32;
+    let mut index = Vec::with_capacity(chunks.len() * 16);
+    for (chunk_pos, id, size) in &chunks {
+        let is_video = Some([id[0], id[1]]) == video_stream;
+        let body = &data[chunk_pos + 8..chunk_pos + 8 + *size as usize];
+        let key = !is_video || &id[2..4] == b"db" || is_keyframe(body);
+        if is_video {
+            video_frames += 1;
+        }
+        index.extend_from_slice(id);
+        index.extend_from_slice(&(if key { AVIIF_KEYFRAME } else { 0 }).to_le_bytes());
+        index.extend_from_slice(&((chunk_pos - movi_fourcc) as u32).to_le_bytes());
+        index.extend_from_slice(&size.to_le_bytes());
+    }
+    out.extend_from_slice(b"idx1");
+    out.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    out.extend_from_slice(&index);
+    report.index_entries = chunks.len();
+
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    // avih: dwFlags at +12, dwTotalFrames at +16 of its data.
+    if let Some((start, end)) = hdrl {
+        if let Some(i) = find(&out[start..end], b"avih") {
+            let avih = start + i + 8;
+            if avih + 20 <= out.len() {
+                let flags = le_u32(&out, avih + 12).unwrap_or(0) | AVIF_HASINDEX;
+                out[avih + 12..avih + 16].copy_from_slice(&flags.to_le_bytes());
+                if video_frames > 0 {
+                    out[avih + 16..avih + 20].copy_from_slice(&video_frames.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    if truncated {
+        report.dropped_bytes = (movi_end - movi_data_end) as u64;
+        report.details.push(format!("Cut truncated movi data at offset {}", movi_data_end));
+    }
+    report.details.push(format!(
+        "Rebuilt idx1 with {} entries ({} video frames){}",
+        chunks.len(),
+        video_frames,
+        if idx1.is_some() { ", replacing a damaged index" } else { "" }
+    ));
+    report.action = "repaired";
+    report.bytes_after = out.len() as u64;
+    (out, report)
+}
+
+/// Repair `data` if it is an Ogg, AVI or MP3 file.
+pub fn repair(data: &[u8]) -> (Vec<u8>, LegacyRepairReport) {
+    if data.starts_with(OGG_CAPTURE) {
+        repair_ogg(data)
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"AVI " {
+        repair_avi(data)
+    } else if crate::mp3::is_mp3(data) {
+        crate::mp3::repair(data)
+    } else {
+        let mut report = LegacyRepairReport::new("unknown", data.len());
+        report.action = "unsupported";
+        (data.to_vec(), report)
+    }
+}
+
+/// Check and repair an OGV/Ogg, AVI or MP3 file in place (page checks for
+/// Ogg, `idx1` rebuild for AVI, frame trimming for MP3). The file is only rewritten if something changed.
+/// Requires a `consent` token covering `path`.
+#[tauri::command(async)]
+pub fn repair_legacy_media(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    path: String,
+    consent: String,
+) -> Result<LegacyRepairReport, String> {
+    let path = state.check(&consent, &path)?;
+    let data = crate::read_with_lock_retry(&path).map_err(|e| crate::format_read_error(&path, &e))?;
+    let (fixed, report) = repair(&data);
+    if report.action == "repaired" {
+        crate::output_file::write_output(&path, &fixed)?;
+    }
+    println!(
+        "[legacy] {} {} ({}): {} — {}",
+        report.container,
+        path,
+        report.codec.as_deref().unwrap_or("?"),
+        report.action,
+        report.details.join("; ")
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(serial: u32, seq: u32, flags: u8, body: &[u8]) -> Vec<u8> {
+        granule_page(serial, seq, flags, 0, body)
+    }
+
+    fn granule_page(serial: u32, seq: u32, flags: u8, granule: u64, body: &[u8]) -> Vec<u8> {
+        let mut p = Vec::new();
+        p.extend_from_slice(b"OggS\0");
+        p.push(flags);
+        p.extend_from_slice(&granule.to_le_bytes());
+        p.extend_from_slice(&serial.to_le_bytes());
+        p.extend_from_slice(&seq.to_le_bytes());
+        p.extend_from_slice(&[0; 4]);
+        p.push(1);
+        p.push(body.len() as u8);
+        p.extend_from_slice(body);
+        set_ogg_crc(&mut p);
+        p
+    }
+
+    #[test]
+    fn ogg_drops_bad_pages_and_truncated_tail() {
+        let first = page(7, 0, OGG_FLAG_BOS, b"\x80theora-header");
+        let mut bad = page(7, 1, 0, b"frame one");
+        bad[30] ^= 0xFF;
+        let good = page(7, 2, 0, b"frame two");
+        let tail = page(7, 3, 0, b"frame three");
+        let mut data = [first.clone(), bad, good.clone()].concat();
+        data.extend_from_slice(&tail[..tail.len() - 4]);
+
+        assert_eq!(ogg_codec(&data), Some("theora"));
+        let (out, report) = repair(&data);
+        assert_eq!(report.action, "repaired");
+        assert_eq!((report.units, report.bad_pages), (2, 1));
+        assert_eq!(out.len(), first.len() + good.len());
+        let last = &out[first.len()..];
+        assert!(last[5] & OGG_FLAG_EOS != 0 && ogg_page_crc_ok(last));
+    }
+
+    #[test]
+    fn opus_voice_message_counts_lost_pages_and_duration() {
+        // OpusHead: version 1, 1 channel, pre-skip 312
+        let head = [b"OpusHead".as_slice(), &[1, 1], &312u16.to_le_bytes(), &[0x80, 0xBB, 0, 0, 0, 0, 0]].concat();
+        let pages = [
+            granule_page(9, 0, OGG_FLAG_BOS, 0, &head),
+            granule_page(9, 1, 0, 0, b"OpusTags"),
+            granule_page(9, 2, 0, 48_312, b"audio one"),
+            // Page 3 lost; page 5 jumps back in time and is dropped
+            granule_page(9, 4, 0, 144_312, b"audio three"),
+            granule_page(9, 5, 0, 96_312, b"stray page"),
+        ];
+        let (out, report) = repair(&pages.concat());
+        assert_eq!(report.codec.as_deref(), Some("opus"));
+        assert_eq!((report.units, report.bad_pages, report.missing_pages), (4, 1, 1));
+        assert_eq!(report.duration_secs, Some(3.0));
+        assert_eq!(out.len(), pages[..4].iter().map(Vec::len).sum::<usize>());
+    }
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut c = id.to_vec();
+        c.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        c.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            c.push(0);
+        }
+        c
+    }
+
+    fn list(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        chunk(b"LIST", &[kind.as_slice(), body].concat())
+    }
+
+    #[test]
+    fn avi_rebuilds_missing_index() {
+        let avih = chunk(b"avih", &[0u8; 56]);
+        let strh = chunk(b"strh", &[b"vids".as_slice(), b"XVID", &[0u8; 48]].concat());
+        let hdrl = list(b"hdrl", &[avih, list(b"strl", &strh)].concat());
+        let frames = [
+            chunk(b"00dc", &[0, 0, 1, 0xB6, 0x00, 1, 2]), // I-VOP
+            chunk(b"01wb", &[9; 10]),
+            chunk(b"00dc", &[0, 0, 1, 0xB6, 0x40, 3]), // P-VOP
+        ];
+        let movi = list(b"movi", &frames.concat());
+        let mut data = b"RIFF\0\0\0\0AVI ".to_vec();
+        data.extend_from_slice(&hdrl);
+        data.extend_from_slice(&movi);
+        // A cut-off fourth chunk
+        data.extend_from_slice(b"00dc\x40\0\0\0abc");
+        let movi_pos = 12 + hdrl.len();
+        let movi_size = (data.len() - movi_pos - 8 + 61) as u32;
+        data[movi_pos + 4..movi_pos + 8].copy_from_slice(&movi_size.to_le_bytes());
+
+        let (out, report) = repair(&data);
+        assert_eq!(report.codec.as_deref(), Some("XVID"));
+        assert_eq!((report.action, report.units, report.index_entries), ("repaired", 3, 3));
+        let idx = find(&out, b"idx1").unwrap();
+        let flags: Vec<u32> = (0..3).map(|i| le_u32(&out, idx + 8 + i * 16 + 4).unwrap()).collect();
+        assert_eq!(flags, vec![AVIIF_KEYFRAME, AVIIF_KEYFRAME, 0]);
+        assert_eq!(le_u32(&out, 4).unwrap() as usize, out.len() - 8);
+        assert_eq!(le_u32(&out, idx + 8 + 8).unwrap(), 4); // first chunk right after "movi"
+
+        // Running it again finds nothing to do.
+        let (_, again) = repair(&out);
+        assert_eq!(again.action, "none");
+    }
+}
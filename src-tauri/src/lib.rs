@@ -1,8 +1,78 @@
 ﻿use std::sync::Mutex;
 use tauri::State;
 
+mod archive_export;
+mod audio_salvage;
 mod cache;
+mod cache_key;
+mod cache_audit;
+mod chunk_classify;
+mod chunk_layout;
+mod compare;
+mod compat;
+mod consent;
+mod crash;
+mod crc_check;
+mod custom_paths;
+mod dash;
 mod blockfile_index;
+mod content_disposition;
+mod content_encoding;
+mod deleted_entries;
+mod discord_build;
+mod disk_carve;
+mod discord_cdn;
+mod drive_scan;
+mod eviction_risk;
+mod entry;
+mod entropy;
+mod events;
+mod export_mapping;
+mod export_target;
+mod fault_inject;
+mod fixture;
+mod fmp4;
+mod hashing;
+mod hls;
+mod hot_items;
+mod http_info;
+mod ignore_rules;
+mod image_repair;
+mod job_outputs;
+mod key_hash;
+mod io_limit;
+mod jpeg;
+mod legacy_formats;
+mod media_probe;
+mod mime_category;
+mod moov_rebuild;
+mod mp3;
+mod mp4_samples;
+mod output_file;
+mod output_naming;
+mod partial_export;
+mod path_display;
+mod pipeline_harness;
+mod privacy;
+mod quick_scan;
+mod remux;
+mod reconstruction;
+mod reconstruction_plan;
+mod safari_cache;
+mod saved_media;
+mod scan_session;
+mod self_test;
+mod sidecar;
+mod snapshot;
+mod sparse_merge;
+mod sparse_watch;
+mod thumbnail;
+mod user_media;
+mod watchdog;
+mod webm;
+
+use reconstruction::{GapPolicy, ReconstructionResult};
+use reconstruction_plan::{MdatFit, Step};
 
 
 /// Enhanced error message for file read failures.
@@ -29,15 +99,20 @@ fn format_read_error(path: &str, e: &std::io::Error) -> String {
 
 
 /// Read file bytes with automatic retry on EACCES (errno 13).
+fn read_with_lock_retry(path: &str) -> Result<Vec<u8>, std::io::Error> {
+    with_lock_retry(path, || watchdog::read_file(path))
+}
+
+/// Run `op` on the file at `path`, retrying on EACCES (errno 13).
 /// On macOS, EACCES on _s sparse cache files is caused by mandatory byte-range lock
 /// conflicts with Discord (which holds _s files open while running). Retrying with
 /// exponential backoff resolves the conflict once Discord releases the lock.
 /// Falls through immediately on any other error.
-fn read_with_lock_retry(path: &str) -> Result<Vec<u8>, std::io::Error> {
+fn with_lock_retry<T>(path: &str, mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
     let mut attempt: u64 = 0;
     loop {
-        match std::fs::read(path) {
-            Ok(data) => return Ok(data),
+        match op() {
+            Ok(value) => return Ok(value),
             Err(e) if e.raw_os_error() == Some(13) && attempt < 5 => {
                 attempt += 1;
                 eprintln!(
@@ -59,6 +134,8 @@ const SIMPLE_CACHE_HEADER_SIZE: usize = 24;
 const SIMPLE_CACHE_EOF_MAGIC: u64 = 0xf4fa6f45970d41d8;
 /// Size of a SimpleFileEOF record: magic(8) + flags(4) + data_crc32(4) + stream_size(4) + padding(4) = 24
 const SIMPLE_CACHE_EOF_SIZE: usize = 24;
+/// FLAG_HAS_CRC32 bit in SimpleFileEOF flags field: data_crc32 covers the stream
+const FLAG_HAS_CRC32: u32 = 1;
 /// FLAG_HAS_KEY_SHA256 bit in SimpleFileEOF flags field
 const FLAG_HAS_KEY_SHA256: u32 = 2;
 
@@ -89,7 +166,8 @@ fn parse_simple_cache_layout(data: &[u8]) -> Option<SimpleCacheLayout> {
     if magic != SIMPLE_CACHE_MAGIC {
         return None;
     }
-    let key_length = u32::from_le_bytes(data[12..16].try_into().ok()?) as usize;
+    compat::note_simple_entry(data);
+    let key_length = key_hash::check_key(data).0?;
     let stream1_start = SIMPLE_CACHE_HEADER_SIZE + key_length;
     if stream1_start >= data.len() {
         return None;
@@ -164,7 +242,8 @@ fn parse_simple_cache_stream2_layout(data: &[u8]) -> Option<SimpleCacheLayout> {
     if magic != SIMPLE_CACHE_MAGIC {
         return None;
     }
-    let key_length = u32::from_le_bytes(data[12..16].try_into().ok()?) as usize;
+    compat::note_simple_entry(data);
+    let key_length = key_hash::check_key(data).0?;
     let body_start = SIMPLE_CACHE_HEADER_SIZE + key_length;
     // Single EOF at the end of file — body extends to just before it
     let eof_start = data.len() - SIMPLE_CACHE_EOF_SIZE;
@@ -205,15 +284,18 @@ fn is_simple_cache_sparse(path: &str) -> bool {
         .file_name()
         .and_then(|f| f.to_str())
         .unwrap_or("");
-    // Simple Cache _s files: 16 hex chars + "_s"
-    filename.len() == 18 && filename.ends_with("_s")
+    // Simple Cache _s files: 16 hex chars + "_s" (any case)
+    filename.len() == 18 && filename.to_ascii_lowercase().ends_with("_s")
         && filename[..16].chars().all(|c| c.is_ascii_hexdigit())
 }
 
-/// Reassemble sparse cache data from already-read file bytes.
-/// Extracts and sorts range chunks, zero-fills gaps, returns contiguous buffer.
-/// Used by both `read_sparse_cache_file` (Tauri command) and `concat_files` (internal).
-fn reassemble_sparse_data(data: &[u8], path: &str) -> Result<Vec<u8>, String> {
+/// One stored range of a sparse entry: (offset in the resource, bytes).
+type SparseRange<'a> = (u64, &'a [u8]);
+
+/// Walk the SparseRangeHeaders of a `_s` file.
+/// Returns the key length and each range's (offset, data) in file order.
+/// A truncated final range yields whatever bytes are present.
+fn parse_sparse_ranges<'a>(data: &'a [u8], path: &str) -> Result<(usize, Vec<SparseRange<'a>>), String> {
     if data.len() < SIMPLE_CACHE_HEADER_SIZE {
         return Err(format!("File too small to be a sparse cache file: {}", path));
     }
@@ -221,23 +303,46 @@ fn reassemble_sparse_data(data: &[u8], path: &str) -> Result<Vec<u8>, String> {
     if magic != SIMPLE_CACHE_MAGIC {
         return Err(format!("Not a Simple Cache file (bad magic): {}", path));
     }
-    let key_length = u32::from_le_bytes(
-        data[12..16].try_into().map_err(|_| "read key_len".to_string())?
-    ) as usize;
+    compat::note_simple_entry(data);
+    let key_length = key_hash::checked_key_length(data, path)
+        .ok_or_else(|| format!("Corrupt Simple Cache header (key_hash mismatch): {}", path))?;
     let mut pos = SIMPLE_CACHE_HEADER_SIZE + key_length;
     if pos > data.len() {
         return Err(format!("key_length extends past end of file: {}", path));
     }
-    let mut chunks: Vec<(u64, &[u8])> = Vec::new();
+    let mut chunks: Vec<SparseRange> = Vec::new();
     while pos + SPARSE_RANGE_HEADER_SIZE <= data.len() {
         let hdr = &data[pos..pos + SPARSE_RANGE_HEADER_SIZE];
         let range_magic = u64::from_le_bytes(hdr[0..8].try_into().map_err(|_| "range magic".to_string())?);
-        if range_magic != SPARSE_RANGE_MAGIC { break; }
+        if range_magic != SPARSE_RANGE_MAGIC {
+            // A damaged record: skip to the next intact header, if any.
+            match next_sparse_range(data, pos + 1) {
+                Some(next) => {
+                    eprintln!(
+                        "[sparse] Corrupt range header at {} in {} — resynced at {}, skipped {} bytes",
+                        pos, path, next, next - pos
+                    );
+                    pos = next;
+                    continue;
+                }
+                None => break,
+            }
+        }
         let offset = u64::from_le_bytes(hdr[8..16].try_into().map_err(|_| "range offset".to_string())?);
         let length = u64::from_le_bytes(hdr[16..24].try_into().map_err(|_| "range length".to_string())?);
         let data_start = pos + SPARSE_RANGE_HEADER_SIZE;
-        let data_end = data_start + length as usize;
+        let data_end = usize::try_from(length).ok().and_then(|l| data_start.checked_add(l)).unwrap_or(usize::MAX);
         if data_end > data.len() {
+            // Either the last range was cut short, or this header's length is
+            // garbage and intact ranges follow.
+            if let Some(next) = next_sparse_range(data, data_start) {
+                eprintln!(
+                    "[sparse] Range header at {} in {} has a bad length ({}) — resynced at {}, skipped {} bytes",
+                    pos, path, length, next, next - pos
+                );
+                pos = next;
+                continue;
+            }
             let available = &data[data_start..data.len()];
             if !available.is_empty() {
                 chunks.push((offset, available));
@@ -247,6 +352,41 @@ fn reassemble_sparse_data(data: &[u8], path: &str) -> Result<Vec<u8>, String> {
         chunks.push((offset, &data[data_start..data_end]));
         pos = data_end;
     }
+    Ok((key_length, chunks))
+}
+
+/// Position of the next plausible sparse range header at or after `from`:
+/// the range magic followed by a length that fits in the file. The last
+/// header in the file may run past its end — that is the cut-off range a
+/// truncated file ends with, and its present bytes are still worth keeping.
+fn next_sparse_range(data: &[u8], from: usize) -> Option<usize> {
+    let magic = SPARSE_RANGE_MAGIC.to_le_bytes();
+    let mut pos = from;
+    while pos + SPARSE_RANGE_HEADER_SIZE <= data.len() {
+        let found = pos + data[pos..].windows(magic.len()).position(|w| w == magic)?;
+        if found + SPARSE_RANGE_HEADER_SIZE > data.len() {
+            return None;
+        }
+        let hdr = &data[found..found + SPARSE_RANGE_HEADER_SIZE];
+        let offset = u64::from_le_bytes(hdr[8..16].try_into().ok()?);
+        let length = u64::from_le_bytes(hdr[16..24].try_into().ok()?);
+        let remaining = (data.len() - found - SPARSE_RANGE_HEADER_SIZE) as u64;
+        let last = || data[found + 1..].windows(magic.len()).all(|w| w != magic);
+        let fits = length > 0 && offset.checked_add(length).is_some() && (length <= remaining || last());
+        if fits {
+            return Some(found);
+        }
+        pos = found + 1;
+    }
+    None
+}
+
+/// Reassemble sparse cache data from already-read file bytes.
+/// Places range chunks at their offsets (later-written ranges win overlaps),
+/// zero-fills gaps, returns contiguous buffer.
+/// Used by both `read_sparse_cache_file` (Tauri command) and `concat_files` (internal).
+fn reassemble_sparse_data(data: &[u8], path: &str) -> Result<Vec<u8>, String> {
+    let (key_length, chunks) = parse_sparse_ranges(data, path)?;
     if chunks.is_empty() {
         // No SparseRangeHeaders found. The _s file may store data directly after
         // the SimpleFileHeader+key (non-sparse format variant), or it may also have
@@ -283,17 +423,76 @@ fn reassemble_sparse_data(data: &[u8], path: &str) -> Result<Vec<u8>, String> {
         }
         return Ok(Vec::new());
     }
-    chunks.sort_by_key(|(offset, _)| *offset);
+    let (buf, overlaps) = assemble_sparse_ranges(&chunks, data.len() as u64);
+    if overlaps.ranges > 0 {
+        eprintln!(
+            "[sparse] {}: {} range(s) overlapped earlier data ({} bytes, {} differing) — kept the later-written bytes",
+            path, overlaps.ranges, overlaps.bytes, overlaps.conflicting_bytes
+        );
+    }
+    Ok(buf)
+}
+
+/// Overlaps found while placing sparse ranges.
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct SparseOverlaps {
+    /// Ranges that overlapped bytes already placed by an earlier range
+    pub ranges: usize,
+    /// Total overlapping bytes
+    pub bytes: u64,
+    /// Overlapping bytes whose value changed
+    pub conflicting_bytes: u64,
+}
+
+/// Place ranges into a zero-filled buffer in file order. A range written
+/// later in the file is newer (Chromium appends when a range is re-fetched),
+/// so where ranges overlap its bytes win over the earlier ones.
+///
+/// The body can only be larger than the `file_size` bytes it was read from
+/// by its zero-filled holes. A range ending further out than
+/// `MAX_SPARSE_HOLE_BYTES` past that has a garbage offset and is dropped
+/// rather than sizing the buffer after it.
+fn assemble_sparse_ranges(chunks: &[SparseRange], file_size: u64) -> (Vec<u8>, SparseOverlaps) {
+    let limit = file_size.saturating_add(MAX_SPARSE_HOLE_BYTES);
+    let chunks: Vec<&SparseRange> = chunks
+        .iter()
+        .filter(|(offset, chunk)| match offset.checked_add(chunk.len() as u64) {
+            Some(end) if end <= limit => true,
+            _ => {
+                eprintln!(
+                    "[sparse] Dropped a {}-byte range at offset {} — past the {}-byte size limit",
+                    chunk.len(), offset, limit
+                );
+                false
+            }
+        })
+        .collect();
     let total_size = chunks.iter().map(|(off, d)| off + d.len() as u64).max().unwrap_or(0) as usize;
     let mut buf = vec![0u8; total_size];
-    for (offset, chunk) in &chunks {
-        let start = *offset as usize;
-        let end = start + chunk.len();
-        if end <= buf.len() {
-            buf[start..end].copy_from_slice(chunk);
+    let mut placed: Vec<[u64; 2]> = Vec::new();
+    let mut overlaps = SparseOverlaps::default();
+    for (offset, chunk) in chunks {
+        let (start, end) = (*offset, offset + chunk.len() as u64);
+        let mut overlapped = false;
+        for r in &placed {
+            let (lo, hi) = (start.max(r[0]), end.min(r[1]));
+            if lo >= hi {
+                continue;
+            }
+            overlapped = true;
+            overlaps.bytes += hi - lo;
+            let old = &buf[lo as usize..hi as usize];
+            let new = &chunk[(lo - start) as usize..(hi - start) as usize];
+            overlaps.conflicting_bytes += old.iter().zip(new).filter(|(a, b)| a != b).count() as u64;
         }
+        if overlapped {
+            overlaps.ranges += 1;
+        }
+        buf[start as usize..end as usize].copy_from_slice(chunk);
+        placed.push([start, end]);
+        placed = compare::normalize(placed);
     }
-    Ok(buf)
+    (buf, overlaps)
 }
 
 
@@ -317,12 +516,20 @@ fn strip_simple_cache_wrapper(data: Vec<u8>, path: &str) -> Vec<u8> {
 /// Handles _s (sparse) files via reassembly, and _0/_1 files via wrapper stripping.
 /// For plain (blockfile) files, returns the raw bytes unchanged.
 fn read_cache_body(path: &str) -> Result<Vec<u8>, String> {
+    read_cache_body_with(path, true)
+}
+
+/// `read_cache_body`, optionally leaving a Content-Encoding (gzip, br, ...)
+/// in place.
+fn read_cache_body_with(path: &str, decode_content: bool) -> Result<Vec<u8>, String> {
     let data = read_with_lock_retry(path).map_err(|e| format_read_error(path, &e))?;
-    if is_simple_cache_sparse(path) {
-        reassemble_sparse_data(&data, path)
+    let encoding = if decode_content { content_encoding::encoding_of(path, &data) } else { None };
+    let body = if is_simple_cache_sparse(path) {
+        reassemble_sparse_data(&data, path)?
     } else {
-        Ok(strip_simple_cache_wrapper(data, path))
-    }
+        strip_simple_cache_wrapper(data, path)
+    };
+    Ok(content_encoding::decode_body(path, encoding.as_deref(), body))
 }
 
 /// Extract the HTTP response headers (stream 0) from a Simple Cache file.
@@ -339,6 +546,29 @@ fn extract_simple_cache_headers(data: &[u8]) -> Option<Vec<u8>> {
     }
 }
 
+/// Read just the URL key of a Simple Cache entry file (header + key, nothing else).
+fn read_simple_cache_key(path: &str) -> Option<String> {
+    use std::io::Read;
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut header = [0u8; SIMPLE_CACHE_HEADER_SIZE];
+    file.read_exact(&mut header).ok()?;
+    if u64::from_le_bytes(header[0..8].try_into().ok()?) != SIMPLE_CACHE_MAGIC {
+        return None;
+    }
+    let key_length = u32::from_le_bytes(header[12..16].try_into().ok()?) as usize;
+    // Keys are URLs (plus an optional isolation prefix); anything huge is corruption.
+    if key_length == 0 || key_length > 64 * 1024 {
+        return None;
+    }
+    let mut key = vec![0u8; key_length];
+    file.read_exact(&mut key).ok()?;
+    // A key that doesn't match key_hash is damaged; don't match URLs against it.
+    if key_hash::super_fast_hash(&key) != u32::from_le_bytes(header[16..20].try_into().ok()?) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&key).to_string())
+}
+
 /// Application state shared across commands
 pub struct AppState {
     pub scan_running: bool,
@@ -359,14 +589,14 @@ fn validate_cache_path(path: String) -> Result<cache::CachePathInfo, String> {
 
 /// Read the first N bytes of a file (for magic byte detection in TS).
 /// For Simple Cache files, skips the header+key to return actual HTTP body bytes.
-#[tauri::command]
+#[tauri::command(async)]
 fn read_file_header(path: String, size: usize) -> Result<Vec<u8>, String> {
     use std::io::{Read, Seek, SeekFrom};
+    let _permit = io_limit::acquire_for(&path);
+    fault_inject::inject("read_file_header", &path)?;
     let mut file =
         std::fs::File::open(&path).map_err(|e| format_read_error(&path, &e))?;
     // Read the fixed-size Simple Cache header (24 bytes) to check magic and get key_length.
-    // We only need 24 bytes to determine whether this is a Simple Cache file and compute
-    // the body offset — we do NOT need the full key in memory.
     let mut header_buf = [0u8; 24];
     let header_read = file
         .read(&mut header_buf)
@@ -377,9 +607,16 @@ fn read_file_header(path: String, size: usize) -> Result<Vec<u8>, String> {
         magic == SIMPLE_CACHE_MAGIC
     };
 
-    if is_simple_cache {
-        let key_length = u32::from_le_bytes(header_buf[12..16].try_into().unwrap()) as u64;
-        let body_offset = SIMPLE_CACHE_HEADER_SIZE as u64 + key_length;
+    // key_length only decides the body offset once the key hashes to the
+    // header's key_hash; a corrupt header is returned raw.
+    let key_length = if is_simple_cache {
+        key_hash::read_checked_key_length(&mut file, &header_buf, &path)
+    } else {
+        None
+    };
+
+    if let Some(key_length) = key_length {
+        let body_offset = (SIMPLE_CACHE_HEADER_SIZE + key_length) as u64;
         // Seek directly to the HTTP body and read the requested number of bytes
         file.seek(SeekFrom::Start(body_offset))
             .map_err(|e| format!("Failed to seek {}: {}", path, e))?;
@@ -388,6 +625,7 @@ fn read_file_header(path: String, size: usize) -> Result<Vec<u8>, String> {
             .read(&mut buffer)
             .map_err(|e| format!("Failed to read {}: {}", path, e))?;
         buffer.truncate(bytes_read);
+        fault_inject::corrupt("read_file_header", &path, &mut buffer);
         Ok(buffer)
     } else {
         // Blockfile or unknown — return bytes from start
@@ -399,52 +637,79 @@ fn read_file_header(path: String, size: usize) -> Result<Vec<u8>, String> {
             .read(&mut buffer)
             .map_err(|e| format!("Failed to read {}: {}", path, e))?;
         buffer.truncate(bytes_read);
+        fault_inject::corrupt("read_file_header", &path, &mut buffer);
         Ok(buffer)
     }
 }
 
 /// Read entire file as bytes (for MP4 box parsing in TS).
 /// For Simple Cache files, strips the header+key and returns only HTTP body data.
-#[tauri::command]
+#[tauri::command(async)]
 fn read_file_bytes(path: String) -> Result<Vec<u8>, String> {
-    let data = std::fs::read(&path).map_err(|e| format_read_error(&path, &e))?;
+    let _permit = io_limit::acquire_for(&path);
+    fault_inject::inject("read_file_bytes", &path)?;
+    let mut data = std::fs::read(&path).map_err(|e| format_read_error(&path, &e))?;
+    fault_inject::corrupt("read_file_bytes", &path, &mut data);
     Ok(strip_simple_cache_wrapper(data, &path))
 }
 
-/// Copy a file from src to dst, stripping Simple Cache wrapper if present.
+/// Copy a file from src to dst, stripping Simple Cache wrapper if present and
+/// undoing the response's Content-Encoding unless `decode_content` is false.
+/// Needs a `consent` token covering `dst` unless it is a temp scratch file.
 #[tauri::command]
-fn copy_file(src: String, dst: String) -> Result<(), String> {
-    // Ensure parent directory exists
-    if let Some(parent) = std::path::Path::new(&dst).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
-    }
+fn copy_file(
+    state: tauri::State<'_, consent::ConsentState>,
+    src: String,
+    dst: String,
+    decode_content: Option<bool>,
+    consent: Option<String>,
+) -> Result<(), String> {
+    let dst = state.check_output(consent.as_deref(), &dst)?;
+    copy_body(src, dst, decode_content)
+}
+
+fn copy_body(src: String, dst: String, decode_content: Option<bool>) -> Result<(), String> {
     let data = std::fs::read(&src).map_err(|e| format_read_error(&src, &e))?;
-    let body = strip_simple_cache_wrapper(data, &src);
-    std::fs::write(&dst, &body).map_err(|e| format!("Failed to write {}: {}", dst, e))?;
+    let encoding = if decode_content.unwrap_or(true) { content_encoding::encoding_of(&src, &data) } else { None };
+    let body = content_encoding::decode_body(&src, encoding.as_deref(), strip_simple_cache_wrapper(data, &src));
+    output_file::write_output(&dst, &body)?;
     Ok(())
 }
 
-/// Write bytes to a file
+/// Write bytes to a file. Requires a `consent` token covering `path`.
 #[tauri::command]
-fn write_file_bytes(path: String, data: Vec<u8>) -> Result<(), String> {
-    if let Some(parent) = std::path::Path::new(&path).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
-    }
-    std::fs::write(&path, &data).map_err(|e| format!("Failed to write {}: {}", path, e))
+fn write_file_bytes(
+    state: tauri::State<'_, consent::ConsentState>,
+    path: String,
+    data: Vec<u8>,
+    consent: String,
+) -> Result<(), String> {
+    let path = state.check(&consent, &path)?;
+    output_file::write_output(&path, &data)?;
+    Ok(())
 }
 
 /// Get file size
-#[tauri::command]
+#[tauri::command(async)]
 fn get_file_size(path: String) -> Result<u64, String> {
+    let _permit = io_limit::acquire_for(&path);
+    fault_inject::inject("get_file_size", &path)?;
     std::fs::metadata(&path)
         .map(|m| m.len())
         .map_err(|e| format!("Failed to stat {}: {}", path, e))
 }
 
-/// List files in a directory matching the cache pattern
-#[tauri::command]
-fn list_cache_files(dir: String) -> Result<Vec<cache::CacheFileEntry>, String> {
-    cache::list_cache_files(&dir).map_err(|e| e.to_string())
+/// List files in a directory matching the cache pattern, minus those the
+/// saved ignore rules skip. Extracts an archived snapshot first, so it runs
+/// off the UI thread.
+#[tauri::command(async)]
+fn list_cache_files(app: tauri::AppHandle, dir: String) -> Result<Vec<cache::CacheFileEntry>, String> {
+    let dir = path_display::expand_path(&dir);
+    let _job = crash::job(format!("listing {}", dir));
+    fault_inject::inject("list_cache_files", &dir)?;
+    let mut files = cache::list_cache_files(&dir).map_err(|e| e.to_string())?;
+    ignore_rules::apply_saved(&app, &dir, &mut files);
+    Ok(files)
 }
 
 /// Open a folder in the system file explorer
@@ -475,29 +740,54 @@ fn open_folder(path: String) -> Result<(), String> {
 }
 
 /// Concatenate multiple files into a single output file (avoids JS memory limits).
-/// Strips Simple Cache wrappers from each input file before concatenation.
+/// Strips Simple Cache wrappers from each input file and, unless
+/// `decode_content` is false, undoes the Content-Encoding of the joined body.
+/// Needs a `consent` token covering `output` unless it is a temp scratch file.
 #[tauri::command]
-fn concat_files(paths: Vec<String>, output: String) -> Result<u64, String> {
-    use std::io::Write;
-    if let Some(parent) = std::path::Path::new(&output).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
-    }
-    let mut out = std::fs::File::create(&output)
-        .map_err(|e| format!("Failed to create {}: {}", output, e))?;
+fn concat_files(
+    state: tauri::State<'_, consent::ConsentState>,
+    paths: Vec<String>,
+    output: String,
+    decode_content: Option<bool>,
+    consent: Option<String>,
+) -> Result<u64, String> {
+    let output = state.check_output(consent.as_deref(), &output)?;
+    join_bodies(paths, &output, decode_content.unwrap_or(true))
+}
+
+fn join_bodies(paths: Vec<String>, output: &str, decode_content: bool) -> Result<u64, String> {
+    let _job = crash::job(format!("joining {} files into {}", paths.len(), output));
+    // A compressed stream split across files doesn't decode piece by piece:
+    // the bodies are joined as stored and the encoding of the first file's
+    // entry is undone once over the whole.
+    let first = paths.first().cloned().unwrap_or_default();
+    let encoding = if decode_content && !first.is_empty() {
+        let data = read_with_lock_retry(&first).map_err(|e| format_read_error(&first, &e))?;
+        content_encoding::encoding_of(&first, &data)
+    } else {
+        None
+    };
+    let mut out = output_file::OutputFile::create(output)?;
     let mut total: u64 = 0;
-    for p in &paths {
-        let data = read_with_lock_retry(p).map_err(|e| format_read_error(p, &e))?;
-        // Sparse _s files need reassembly; _0/_1 files need wrapper stripping
-        let body = if is_simple_cache_sparse(p) {
-            reassemble_sparse_data(&data, p)?
-        } else {
-            strip_simple_cache_wrapper(data, p)
-        };
+    let mut encoded = Vec::new();
+    // The next file is read while the current one is written.
+    // Sparse _s files need reassembly; _0/_1 files need wrapper stripping
+    let read = |p: &str| read_cache_body_with(p, false);
+    for (_, body) in reconstruction::read_ahead(paths, read) {
+        let body = body?;
+        if encoding.is_some() {
+            encoded.extend_from_slice(&body);
+            continue;
+        }
         total += body.len() as u64;
-        out.write_all(&body)
-            .map_err(|e| format!("Failed to write: {}", e))?;
+        out.write_all(&body)?;
+    }
+    if encoding.is_some() {
+        let body = content_encoding::decode_body(&first, encoding.as_deref(), encoded);
+        total = body.len() as u64;
+        out.write_all(&body)?;
     }
-    out.flush().map_err(|e| format!("Failed to flush: {}", e))?;
+    out.finish()?;
     Ok(total)
 }
 
@@ -537,7 +827,7 @@ pub fn find_mp4_box(data: &[u8], box_type: &[u8; 4]) -> Option<(usize, u64, usiz
         }
 
         // Validate box type is printable ASCII
-        if !btype.iter().all(|&b| b >= 0x20 && b <= 0x7e) {
+        if !btype.iter().all(|b| (0x20..=0x7e).contains(b)) {
             break;
         }
 
@@ -554,11 +844,14 @@ pub fn find_mp4_box(data: &[u8], box_type: &[u8; 4]) -> Option<(usize, u64, usiz
     None
 }
 
-/// Scan raw bytes for valid moov atoms. Returns (offset, size) of the first valid one.
+/// Largest moov `scan_for_moov` accepts. Sample tables grow with duration:
+/// a few hours of 4K60 with audio is tens of MB of stsz/stts/co64.
+const MAX_MOOV_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Scan raw bytes for valid moov atoms (32- or 64-bit size). Returns
+/// (offset, size) of the last valid one.
 pub fn scan_for_moov(data: &[u8]) -> Option<(usize, usize)> {
     let moov_sig: [u8; 4] = [0x6d, 0x6f, 0x6f, 0x76]; // "moov"
-    let mvhd_sig: [u8; 4] = [0x6d, 0x76, 0x68, 0x64]; // "mvhd"
-    let trak_sig: [u8; 4] = [0x74, 0x72, 0x61, 0x6b]; // "trak"
 
     let mut search_from = 0usize;
     let mut last_valid: Option<(usize, usize)> = None;
@@ -570,23 +863,23 @@ pub fn scan_for_moov(data: &[u8]) -> Option<(usize, usize)> {
         };
 
         if idx >= 4 {
-            let box_size =
-                u32::from_be_bytes([data[idx - 4], data[idx - 3], data[idx - 2], data[idx - 1]])
-                    as usize;
-
-            // Validate moov size: typically 500B-2MB
-            if box_size >= 500 && box_size <= 2_000_000 {
-                let box_end = idx - 4 + box_size;
-                if box_end <= data.len() {
-                    let inner = &data[idx - 4..box_end];
-                    let has_mvhd = inner.windows(4).any(|w| w == mvhd_sig);
-                    let has_trak = inner.windows(4).any(|w| w == trak_sig);
-                    if has_mvhd && has_trak {
-                        // Keep searching — we want the LAST valid moov, not the first.
-                        // In streamed MP4s the real moov is at the end; earlier matches
-                        // inside raw media data are false positives.
-                        last_valid = Some((idx - 4, box_size));
-                    }
+            let start = idx - 4;
+            let (box_size, header) = match mp4_samples::be_u32(data, start) {
+                Some(1) => (mp4_samples::be_u64(data, idx + 4).unwrap_or(0), 16),
+                size => (size.unwrap_or(0) as u64, 8),
+            };
+
+            // Validate moov size: at least 500B; multi-hour recordings run to tens of MB
+            if (500..=MAX_MOOV_SIZE).contains(&box_size) && start as u64 + box_size <= data.len() as u64 {
+                let box_end = start + box_size as usize;
+                let kids = mp4_samples::children(&data[start + header..box_end]);
+                let has_mvhd = kids.iter().any(|(kind, _)| kind == b"mvhd");
+                let has_trak = kids.iter().any(|(kind, _)| kind == b"trak");
+                if has_mvhd && has_trak {
+                    // Keep searching — we want the LAST valid moov, not the first.
+                    // In streamed MP4s the real moov is at the end; earlier matches
+                    // inside raw media data are false positives.
+                    last_valid = Some((start, box_size as usize));
                 }
             }
         }
@@ -595,11 +888,17 @@ pub fn scan_for_moov(data: &[u8]) -> Option<(usize, usize)> {
     last_valid
 }
 
+/// Payload of the box at `pos` spanning `size` bytes, past its 8- or 16-byte header.
+pub(crate) fn box_payload(data: &[u8], pos: usize, size: usize) -> &[u8] {
+    let header = if mp4_samples::be_u32(data, pos) == Some(1) { 16 } else { 8 };
+    &data[(pos + header).min(pos + size)..pos + size]
+}
+
 /// Extract hex number from a cache filename like "f_00630b"
 fn parse_cache_hex(path: &str) -> Option<u64> {
     let filename = std::path::Path::new(path).file_name()?.to_str()?;
-    if filename.starts_with("f_") && filename.len() == 8 {
-        u64::from_str_radix(&filename[2..], 16).ok()
+    if filename.len() == 8 && filename.get(..2).is_some_and(|p| p.eq_ignore_ascii_case("f_")) {
+        u64::from_str_radix(filename.get(2..)?, 16).ok()
     } else {
         None
     }
@@ -635,50 +934,20 @@ fn is_standalone_file_header(data: &[u8]) -> bool {
     false
 }
 
-/// Reconstruct a chunked MP4 from Discord cache files.
-/// chunk_paths = ALL non-header cache files (sorted by name); Rust identifies the tail via moov scan.
-#[tauri::command]
-fn reconstruct_chunked_mp4(
-    header_path: String,
-    chunk_paths: Vec<String>,
-    output: String,
-) -> Result<u64, String> {
-    use std::io::Write;
-
-    // Ensure output directory exists
-    if let Some(parent) = std::path::Path::new(&output).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
-    }
-
-    let header_data = read_cache_body(&header_path)?;
-
-    let ftyp_box = find_mp4_box(&header_data, b"ftyp")
-        .ok_or_else(|| "No ftyp box found in header file".to_string())?;
-    let mdat_box = find_mp4_box(&header_data, b"mdat")
-        .ok_or_else(|| "No mdat box found in header file".to_string())?;
-
-    let ftyp_offset = ftyp_box.0;
-    let ftyp_size = ftyp_box.1 as usize;
-    let mdat_offset = mdat_box.0;
-    let mdat_declared_size = mdat_box.1;
-    let mdat_header_size = mdat_box.2;
-
-    // Bytes between ftyp end and mdat start (e.g. a "free" box).
-    // These must be preserved so that the reconstructed file layout matches
-    // the original offsets that moov references use.
-    let gap_before_mdat = mdat_offset.saturating_sub(ftyp_offset + ftyp_size);
-
-    println!("[reconstruct] ftyp: {} bytes, mdat_offset: {}", ftyp_size, mdat_offset);
-    println!(
-        "[reconstruct] mdat: declared size = {} bytes (header: {} bytes), gap_before_mdat: {}",
-        mdat_declared_size, mdat_header_size, gap_before_mdat
-    );
+/// An MPEG-TS segment in a chunk list: sync bytes repeating every packet, in
+/// an entry served as TS. MP4 media can line up 0x47 bytes by chance, so the
+/// bytes alone don't make a chunk a segment of its own.
+fn is_transport_stream_entry(path: &str, data: &[u8]) -> bool {
+    hls::is_transport_stream(data) && simple_cache_content_type(path).is_ok_and(|ct| ct == "video/mp2t")
+}
 
+/// Size of a full chunk of a chunked MP4: the most common size among the
+/// non-header chunks, else the largest file.
+fn full_chunk_size(header_path: &str, header_size: u64, chunk_paths: &[String]) -> Result<u64, String> {
     // chunk_size_standard = max(all file sizes), used for gap padding
-    let header_size = header_data.len() as u64;
     let mut chunk_sizes: Vec<(String, u64)> = Vec::new();
-    chunk_sizes.push((header_path.clone(), header_size));
-    for cp in &chunk_paths {
+    chunk_sizes.push((header_path.to_string(), header_size));
+    for cp in chunk_paths {
         let meta = std::fs::metadata(cp).map_err(|e| format!("Failed to stat {}: {}", cp, e))?;
         chunk_sizes.push((cp.clone(), meta.len()));
     }
@@ -693,10 +962,6 @@ fn reconstruct_chunked_mp4(
         chunk_size_standard
     );
 
-    // Identify tail: first undersized chunk containing a valid moov atom
-    let mut tail_path: Option<String> = None;
-    let mut middle_paths: Vec<String> = Vec::new();
-
     // full_chunk_size = most-common size (for size comparison during tail detection)
     let mut size_counts: std::collections::HashMap<u64, usize> = std::collections::HashMap::new();
     for (_, sz) in &chunk_sizes[1..] {
@@ -712,73 +977,285 @@ fn reconstruct_chunked_mp4(
         "[reconstruct] full_chunk_size (most common): {}",
         full_chunk_size
     );
+    Ok(full_chunk_size)
+}
+
+/// Why an undersized chunk that starts like another file is left out.
+const STANDALONE_FILE: &str = "starts like a file of its own";
+/// Why a chunk whose bitstream is another stream's is left out.
+const OTHER_STREAM: &str = "holds another stream's bitstream";
+
+/// What the one read of a chunk in `sort_chunks` found.
+pub(crate) struct ChunkFacts {
+    /// Body length, past any Simple Cache wrapper
+    pub(crate) bytes: u64,
+    /// Offset and size of a moov in the body
+    pub(crate) moov: Option<(usize, usize)>,
+    /// Why the chunk is not this video's, if it isn't
+    pub(crate) left_out: Option<&'static str>,
+}
 
-    for cp in &chunk_paths {
+impl ChunkFacts {
+    /// Another undersized chunk with a moov: the assembly skips it.
+    pub(crate) fn is_duplicate_tail(&self, full_chunk_size: u64) -> bool {
+        self.bytes != full_chunk_size && self.moov.is_some()
+    }
+}
+
+/// The chunks of a chunked MP4 by role.
+pub(crate) struct ChunkSort {
+    /// The chunk holding the moov, appended last
+    pub(crate) tail: Option<String>,
+    /// Media data between header and tail, in input order
+    pub(crate) middle: Vec<String>,
+    /// Chunks that are not this video: undersized ones that start like another
+    /// kind of file (WebM, PNG, ...), and any whose bitstream is another one's
+    pub(crate) foreign: Vec<String>,
+    /// Every chunk's facts, so the assembly and the plan don't read a chunk
+    /// again just to ask
+    pub(crate) facts: std::collections::HashMap<String, ChunkFacts>,
+}
+
+/// Check a chunk without a moov against the codec config: media of this
+/// video is kept whatever its size or first bytes, another bitstream is left
+/// out, and anything in between keeps `by_size`.
+fn judge_by_content(
+    classifier: &chunk_classify::NalClassifier,
+    cp: &str,
+    chunk: &[u8],
+    by_size: Option<&'static str>,
+) -> Option<&'static str> {
+    let class = classifier.classify(chunk);
+    let left_out = match class {
+        chunk_classify::ChunkClass::Video => None,
+        chunk_classify::ChunkClass::Foreign => Some(OTHER_STREAM),
+        chunk_classify::ChunkClass::Unknown => by_size,
+    };
+    if left_out.is_some() != by_size.is_some() {
+        println!(
+            "[reconstruct] {} {} by content ({} bytes, {:?})",
+            std::path::Path::new(cp).file_name().unwrap_or_default().to_string_lossy(),
+            if left_out.is_none() { "kept as media" } else { "left out as another stream" },
+            chunk.len(),
+            class
+        );
+    }
+    left_out
+}
+
+/// Identify the tail: the first undersized chunk containing a valid moov, or
+/// failing that any chunk with one. Every other chunk is media data, except
+/// undersized ones that are files of their own. Once a moov (the header's or
+/// the tail's) gives the codec, chunk contents overrule their sizes.
+///
+/// Undersized chunks are read first, since the tail is usually one of them;
+/// then the full-size ones. Each chunk is read once, except undersized ones
+/// read before the tail gave the codec, which are read again to classify.
+pub(crate) fn sort_chunks(header: &[u8], chunk_paths: &[String], full_chunk_size: u64) -> Result<ChunkSort, String> {
+    let mut undersized: Vec<String> = Vec::new();
+    let mut full_size: Vec<String> = Vec::new();
+    for cp in chunk_paths {
         let sz = std::fs::metadata(cp)
             .map_err(|e| format!("Failed to stat {}: {}", cp, e))?
             .len();
         if sz < full_chunk_size {
-            let chunk_data = read_cache_body(cp)?;
-            // Check if this undersized chunk contains a moov atom (= tail chunk)
-            if scan_for_moov(&chunk_data).is_some() {
-                if tail_path.is_none() {
-                    println!(
-                        "[reconstruct] Tail identified (has moov): {} ({} bytes)",
-                        cp, sz
-                    );
-                    tail_path = Some(cp.clone());
-                } else {
-                    println!(
-                        "[reconstruct] Extra moov chunk (already have tail): {} ({} bytes)",
-                        cp, sz
-                    );
-                    middle_paths.push(cp.clone());
-                }
-            } else if is_standalone_file_header(&chunk_data) {
+            undersized.push(cp.clone());
+        } else {
+            full_size.push(cp.clone());
+        }
+    }
+
+    let mut facts: std::collections::HashMap<String, ChunkFacts> = std::collections::HashMap::new();
+    let mut tail_path: Option<String> = None;
+    let mut classifier = mp4_samples::find_moov(header).and_then(chunk_classify::NalClassifier::from_moov);
+    let mut unclassified: Vec<String> = Vec::new();
+
+    for (cp, chunk_data) in reconstruction::read_ahead(undersized, read_cache_body) {
+        let chunk_data = chunk_data?;
+        let sz = chunk_data.len();
+        let moov = scan_for_moov(&chunk_data);
+        let mut left_out = None;
+        // Check if this undersized chunk contains a moov atom (= tail chunk)
+        if moov.is_some() {
+            if tail_path.is_none() {
+                println!(
+                    "[reconstruct] Tail identified (has moov): {} ({} bytes)",
+                    cp, sz
+                );
+                tail_path = Some(cp.clone());
+                classifier = classifier.or_else(|| {
+                    mp4_samples::find_moov(&chunk_data).and_then(chunk_classify::NalClassifier::from_moov)
+                });
+            } else {
+                println!(
+                    "[reconstruct] Extra moov chunk (already have tail): {} ({} bytes)",
+                    cp, sz
+                );
+            }
+        } else {
+            if is_standalone_file_header(&chunk_data) || is_transport_stream_entry(&cp, &chunk_data) {
                 println!(
                     "[reconstruct] SKIPPING standalone file in chunk list: {} ({} bytes, starts {:02X} {:02X} {:02X} {:02X})",
-                    std::path::Path::new(cp).file_name().unwrap_or_default().to_string_lossy(),
+                    std::path::Path::new(&cp).file_name().unwrap_or_default().to_string_lossy(),
                     sz,
-                    chunk_data.get(0).unwrap_or(&0),
+                    chunk_data.first().unwrap_or(&0),
                     chunk_data.get(1).unwrap_or(&0),
                     chunk_data.get(2).unwrap_or(&0),
                     chunk_data.get(3).unwrap_or(&0),
                 );
-                // Do NOT add to middle_paths — this is a foreign file (WebM, PNG, JPEG, GIF)
+                // A foreign file (WebM, PNG, JPEG, GIF, TS) unless its content says otherwise
+                left_out = Some(STANDALONE_FILE);
             } else {
                 println!(
                     "[reconstruct] Undersized chunk (no moov): {} ({} bytes, starts {:02X} {:02X} {:02X} {:02X})",
-                    std::path::Path::new(cp).file_name().unwrap_or_default().to_string_lossy(),
+                    std::path::Path::new(&cp).file_name().unwrap_or_default().to_string_lossy(),
                     sz,
-                    chunk_data.get(0).unwrap_or(&0),
+                    chunk_data.first().unwrap_or(&0),
                     chunk_data.get(1).unwrap_or(&0),
                     chunk_data.get(2).unwrap_or(&0),
                     chunk_data.get(3).unwrap_or(&0),
                 );
-                middle_paths.push(cp.clone());
             }
-        } else {
-            middle_paths.push(cp.clone());
+            match &classifier {
+                Some(classifier) => left_out = judge_by_content(classifier, &cp, &chunk_data, left_out),
+                None => unclassified.push(cp.clone()),
+            }
         }
+        facts.insert(cp, ChunkFacts { bytes: sz as u64, moov, left_out });
     }
 
-    // Fallback: scan ALL chunks for moov (may be in a full-size chunk)
+    // Full-size chunks, plus the undersized ones the tail's codec can now
+    // judge. Without an undersized tail, the first chunk with a moov is it.
     if tail_path.is_none() {
         println!("[reconstruct] No tail found by size heuristic, scanning all chunks for moov...");
-        for cp in &chunk_paths {
-            let chunk_data = read_cache_body(cp)?;
-            if scan_for_moov(&chunk_data).is_some() {
+    }
+    let recheck = if classifier.is_some() { unclassified } else { Vec::new() };
+    let second_pass: Vec<String> = full_size.into_iter().chain(recheck).collect();
+    for (cp, chunk_data) in reconstruction::read_ahead(second_pass, read_cache_body) {
+        let chunk_data = chunk_data?;
+        if let (Some(known), Some(classifier)) = (facts.get_mut(&cp), &classifier) {
+            known.left_out = judge_by_content(classifier, &cp, &chunk_data, known.left_out);
+            continue;
+        }
+        let moov = scan_for_moov(&chunk_data);
+        let mut left_out = None;
+        if moov.is_some() {
+            if tail_path.is_none() {
                 println!("[reconstruct] Tail found in full scan: {} ", cp);
                 tail_path = Some(cp.clone());
-                middle_paths.retain(|p| p != cp);
-                break;
             }
+        } else if let Some(classifier) = &classifier {
+            left_out = judge_by_content(classifier, &cp, &chunk_data, None);
+        }
+        facts.insert(cp, ChunkFacts { bytes: chunk_data.len() as u64, moov, left_out });
+    }
+
+    let mut middle_paths: Vec<String> = Vec::new();
+    let mut foreign: Vec<String> = Vec::new();
+    for cp in chunk_paths {
+        if tail_path.as_ref() == Some(cp) {
+            continue;
+        }
+        if facts.get(cp).is_some_and(|f| f.left_out.is_some()) {
+            foreign.push(cp.clone());
+        } else {
+            middle_paths.push(cp.clone());
+        }
+    }
+    Ok(ChunkSort { tail: tail_path, middle: middle_paths, foreign, facts })
+}
+
+/// Header, media chunks and tail back to back, duplicate tails left out: the
+/// output when the moov isn't at the end, or there is none.
+fn concatenate_chunks(
+    header: &[u8],
+    middle: &[String],
+    tail: Option<&str>,
+    facts: &std::collections::HashMap<String, ChunkFacts>,
+    full_chunk_size: u64,
+) -> Result<Vec<u8>, String> {
+    let mut all_data = header.to_vec();
+    for (mp, chunk) in reconstruction::read_ahead(middle.to_vec(), read_cache_body) {
+        let chunk = chunk?;
+        if !facts[&mp].is_duplicate_tail(full_chunk_size) {
+            all_data.extend_from_slice(&chunk);
         }
     }
+    if let Some(tp) = tail {
+        all_data.extend_from_slice(&read_cache_body(tp)?);
+    }
+    Ok(all_data)
+}
 
-    // Sort middle_paths by hex number to ensure correct sequential ordering.
-    // Input order from chunk_paths may not be numerically sorted.
-    middle_paths.sort_by_key(|p| parse_cache_hex(p).unwrap_or(u64::MAX));
+/// Reconstruct a chunked MP4 from Discord cache files.
+/// chunk_paths = ALL non-header cache files (sorted by name); Rust identifies the tail via moov scan.
+/// `gap_policy` controls how missing chunks are handled (defaults to zero padding).
+/// Needs a `consent` token covering `output` unless it is a temp scratch file.
+#[tauri::command]
+fn reconstruct_chunked_mp4(
+    state: tauri::State<'_, consent::ConsentState>,
+    header_path: String,
+    chunk_paths: Vec<String>,
+    output: String,
+    gap_policy: Option<GapPolicy>,
+    consent: Option<String>,
+) -> Result<ReconstructionResult, String> {
+    let output = state.check_output(consent.as_deref(), &output)?;
+    assemble_chunked_mp4(header_path, chunk_paths, output, gap_policy)
+}
+
+fn assemble_chunked_mp4(
+    header_path: String,
+    chunk_paths: Vec<String>,
+    output: String,
+    gap_policy: Option<GapPolicy>,
+) -> Result<ReconstructionResult, String> {
+    let _job = crash::job(format!("reconstructing {}", output));
+    let gap_policy = gap_policy.unwrap_or_default();
+    let mut result = ReconstructionResult::new(gap_policy);
+
+    // Ensure output directory exists
+    if let Some(parent) = std::path::Path::new(&output).parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
+    }
+
+    let header_data = read_cache_body(&header_path)?;
+
+    // A fragmented upload: the chunks are moof/mdat fragments, not one mdat.
+    if fmp4::is_init_segment(&header_data) {
+        println!("[reconstruct] Header is an fMP4 init segment — reassembling fragments");
+        let paths: Vec<String> = std::iter::once(header_path).chain(chunk_paths).collect();
+        return fmp4::reconstruct(&paths, &output, gap_policy).map(|r| r.result);
+    }
+
+    let boxes = reconstruction_plan::HeaderBoxes::find(&header_data)?;
+    let ftyp_offset = boxes.ftyp_offset;
+    let ftyp_size = boxes.ftyp_size;
+    let mdat_offset = boxes.mdat_offset;
+    let mdat_declared_size = boxes.mdat_declared_size;
+    let mdat_header_size = boxes.mdat_header_size;
+
+    // Bytes between ftyp end and mdat start (e.g. a "free" box).
+    // These must be preserved so that the reconstructed file layout matches
+    // the original offsets that moov references use.
+    let gap_before_mdat = mdat_offset - (ftyp_offset + ftyp_size);
+
+    println!("[reconstruct] ftyp: {} bytes, mdat_offset: {}", ftyp_size, mdat_offset);
+    println!(
+        "[reconstruct] mdat: declared size = {} bytes (header: {} bytes), gap_before_mdat: {}",
+        mdat_declared_size, mdat_header_size, gap_before_mdat
+    );
+
+    let full_chunk_size = full_chunk_size(&header_path, header_data.len() as u64, &chunk_paths)?;
+    let ChunkSort { tail: tail_path, middle: mut middle_paths, foreign, facts } =
+        sort_chunks(&header_data, &chunk_paths, full_chunk_size)?;
+
+    // Put middle_paths in download order: hex numbers when every chunk has
+    // one, else Content-Range offsets, else mtimes. Input order from
+    // chunk_paths may not be sequential.
+    let ordering = reconstruction::order_chunks(&mut middle_paths);
+    result.ordering = Some(ordering);
+    println!("[reconstruct] Ordered {} middle chunks by {:?}", middle_paths.len(), ordering);
 
     // Log all chunk details for debugging
     println!("[reconstruct] === Chunk inventory ===");
@@ -802,46 +1279,34 @@ fn reconstruct_chunked_mp4(
         if tail_path.is_some() { "yes" } else { "no" }
     );
 
-    let mut all_data = Vec::with_capacity(header_data.len());
-    all_data.extend_from_slice(&header_data);
+    // Duplicate tails (undersized, with a moov) are left out; foreign files
+    // were already sorted out. sort_chunks has read every chunk, so where
+    // the moov sits is known without reading them again.
     for mp in &middle_paths {
-        let chunk = read_cache_body(mp)?;
-        // Skip duplicate tail chunks (contain moov) and standalone foreign files.
-        if chunk.len() as u64 != full_chunk_size {
-            if scan_for_moov(&chunk).is_some() {
-                continue; // duplicate tail, already accounted for separately
-            }
-            if is_standalone_file_header(&chunk) {
-                println!(
-                    "[reconstruct] SKIPPING standalone file during assembly: {}",
-                    std::path::Path::new(mp).file_name().unwrap_or_default().to_string_lossy()
-                );
-                continue;
-            }
+        let c = &facts[mp];
+        if !c.is_duplicate_tail(full_chunk_size) {
+            result.record_chunk(c.bytes == full_chunk_size);
         }
-        all_data.extend_from_slice(&chunk);
-    }
-    if let Some(ref tp) = tail_path {
-        let tail = read_cache_body(tp)?;
-        all_data.extend_from_slice(&tail);
     }
-
-    let moov_result = scan_for_moov(&all_data);
+    let (moov_result, concat_len) = reconstruction_plan::moov_position(
+        &header_data,
+        &middle_paths,
+        tail_path.as_deref(),
+        &facts,
+        full_chunk_size,
+    );
 
     println!(
         "[reconstruct] Total raw data: {} bytes ({:.2} MB)",
-        all_data.len(),
-        all_data.len() as f64 / 1024.0 / 1024.0
+        concat_len,
+        concat_len as f64 / 1024.0 / 1024.0
     );
 
     match moov_result {
-        Some((moov_offset, moov_size)) => {
-            println!(
-                "[reconstruct] Found moov at offset {} (size: {} bytes)",
-                moov_offset, moov_size
-            );
+        Some(moov_offset) => {
+            println!("[reconstruct] Found moov at offset {}", moov_offset);
 
-            let moov_at_end = moov_offset > all_data.len() / 2;
+            let moov_at_end = moov_offset > concat_len / 2;
             println!(
                 "[reconstruct] Layout: {}",
                 if moov_at_end {
@@ -865,8 +1330,16 @@ fn reconstruct_chunked_mp4(
                     None
                 };
 
-                // Start building the output buffer.
-                let mut reconstructed: Vec<u8> = Vec::with_capacity(all_data.len() + 4 * 1024 * 1024);
+                // Start building the output buffer. Reserve the final size up front
+                // (the declared mdat when padding) so growth doesn't reallocate, and
+                // fail cleanly if memory is short.
+                let concat_len = concat_len as usize;
+                let padded_len = (mdat_offset as u64).saturating_add(mdat_declared_size) as usize;
+                let expected_len = if gap_policy.pads() { padded_len.max(concat_len) } else { concat_len };
+                let mut reconstructed: Vec<u8> = Vec::new();
+                reconstructed.try_reserve_exact(expected_len.saturating_add(16)).map_err(|e| {
+                    format!("Not enough memory for the {} byte reconstruction of {}: {}", expected_len, output, e)
+                })?;
 
                 // 1. Write ftyp box.
                 let ftyp_data = &header_data[ftyp_offset..ftyp_offset + ftyp_size];
@@ -894,17 +1367,39 @@ fn reconstruct_chunked_mp4(
                 let header_media = &header_data[header_media_start..];
                 reconstructed.extend_from_slice(header_media);
 
-                // 5. Write middle chunks with gap detection.
-                let mut last_written_hex: Option<u64> = header_hex;
+                // 5. Place middle chunks: slots from the hex numbers, checked
+                // against the tail moov's sample table when there is one.
+                let (placements, check) = reconstruction_plan::place_chunks(
+                    &header_path,
+                    boxes.slot_base(header_data.len() as u64),
+                    tail_path.as_deref().zip(tail_data.as_deref()),
+                    &mut middle_paths,
+                    full_chunk_size,
+                );
+                if let Some(check) = &check {
+                    println!("[reconstruct] Sample table check of chunk placement: {:?}", check);
+                }
+                result.layout_check = check;
+
+                // 6. Write middle chunks with gap detection.
+                let mut walk = reconstruction_plan::GapWalk::new(gap_policy);
+                // Bytes left out under CloseGaps, so gap offsets stay in the
+                // original layout.
+                let mut dropped = 0u64;
+                let missing_chunks = |slots: std::ops::Range<u64>| -> Vec<String> {
+                    slots.filter_map(|slot| chunk_layout::slot_file_name(slot, header_hex, tail_hex)).collect()
+                };
                 let mut skipped_non_standard = 0usize;
                 let mut written_middle = 0usize;
-                for mp in &middle_paths {
-                    let chunk = read_cache_body(mp)?;
+                let chunks = reconstruction::read_ahead(middle_paths.clone(), read_cache_body);
+                for ((mp, chunk), placement) in chunks.zip(placements.iter().copied()) {
+                    let chunk = chunk?;
+                    let mp = &mp;
 
                     // Filter: skip duplicate tail chunks (contain moov).
                     // Do NOT filter by magic bytes — raw video data has no signature.
                     if chunk.len() as u64 != full_chunk_size {
-                        if scan_for_moov(&chunk).is_some() {
+                        if facts[mp].moov.is_some() {
                             skipped_non_standard += 1;
                             println!(
                                 "[reconstruct] Skipping duplicate tail chunk {} ({} bytes, contains moov)",
@@ -917,39 +1412,50 @@ fn reconstruct_chunked_mp4(
                             "[reconstruct] Writing undersized chunk {} ({} bytes, starts {:02X} {:02X} {:02X} {:02X})",
                             std::path::Path::new(mp).file_name().unwrap_or_default().to_string_lossy(),
                             chunk.len(),
-                            chunk.get(0).unwrap_or(&0),
+                            chunk.first().unwrap_or(&0),
                             chunk.get(1).unwrap_or(&0),
                             chunk.get(2).unwrap_or(&0),
                             chunk.get(3).unwrap_or(&0),
                         );
                     }
 
-                    // Gap detection: insert zero padding for truly missing hex slots.
-                    if let (Some(prev_num), Some(curr_num)) =
-                        (last_written_hex, parse_cache_hex(mp))
-                    {
-                        let mut gap = curr_num.saturating_sub(prev_num).saturating_sub(1);
-                        // Tail hex occupies a slot but is placed at the end.
-                        if let Some(th) = tail_hex {
-                            if th > prev_num && th < curr_num {
-                                gap = gap.saturating_sub(1);
+                    // Gap detection: insert zero padding for truly missing slots.
+                    let step = walk.step(placement);
+                    if let Step::Write { gap: Some(gap) } | Step::Stop { gap } = &step {
+                        let gap_size = ((gap.end - gap.start) * full_chunk_size) as usize;
+                        result.record_gap_range(
+                            reconstructed.len() as u64 + dropped,
+                            gap_size as u64,
+                            missing_chunks(gap.clone()),
+                        );
+                        let chunk_name = std::path::Path::new(mp).file_name().unwrap_or_default().to_string_lossy();
+                        let missing = gap.end - gap.start;
+                        match gap_policy {
+                            GapPolicy::PadZeros => {
+                                println!(
+                                    "[reconstruct] Gap: {} missing chunk(s) before {} ({} bytes zero-fill)",
+                                    missing, chunk_name, gap_size,
+                                );
+                                reconstructed.resize(reconstructed.len() + gap_size, 0u8);
+                            }
+                            GapPolicy::TruncateAtGap => {
+                                println!(
+                                    "[reconstruct] Gap: {} missing chunk(s) before {} ({} bytes) — truncating media here",
+                                    missing, chunk_name, gap_size,
+                                );
+                            }
+                            GapPolicy::CloseGaps => {
+                                println!(
+                                    "[reconstruct] Gap: {} missing chunk(s) before {} ({} bytes) — closed up, later moov offsets stale",
+                                    missing, chunk_name, gap_size,
+                                );
+                                dropped += gap_size as u64;
                             }
-                        }
-                        if gap > 0 {
-                            let gap_size = (gap * full_chunk_size) as usize;
-                            println!(
-                                "[reconstruct] Gap: {} missing chunk(s) before {} ({} bytes zero-fill)",
-                                gap,
-                                std::path::Path::new(mp).file_name().unwrap_or_default().to_string_lossy(),
-                                gap_size,
-                            );
-                            reconstructed.resize(reconstructed.len() + gap_size, 0u8);
                         }
                     }
-
-                    // Update hex tracking.
-                    if let Some(num) = parse_cache_hex(mp) {
-                        last_written_hex = Some(num);
+                    if !matches!(step, Step::Write { .. }) {
+                        result.truncated = true;
+                        break;
                     }
 
                     // Write the chunk data.
@@ -962,13 +1468,14 @@ fn reconstruct_chunked_mp4(
                     written_middle, skipped_non_standard
                 );
 
-                // 6. Write tail data — but split out the moov atom.
+                // 7. Write tail data — but split out the moov atom.
                 // The tail chunk contains video data followed by the moov atom.
                 // Video data goes INSIDE mdat; moov goes AFTER mdat as a separate top-level box.
                 let mut tail_moov_data: Option<Vec<u8>> = None;
+                let mut tail_video_len = 0u64;
                 if let Some(ref td) = tail_data {
                     // Find moov in the tail data
-                    if let Some((moov_off, moov_sz)) = scan_for_moov(td) {
+                    if let Some((moov_off, moov_sz)) = tail_path.as_ref().and_then(|tp| facts[tp].moov) {
                         // Everything before moov = video data (inside mdat)
                         let tail_video = &td[..moov_off];
                         // The moov atom itself = separate top-level box (after mdat)
@@ -977,10 +1484,21 @@ fn reconstruct_chunked_mp4(
                             "[reconstruct] Tail split: {} bytes video + {} bytes moov (at offset {})",
                             tail_video.len(), tail_moov.len(), moov_off
                         );
-                        if !tail_video.is_empty() {
+                        if result.truncated {
+                            println!(
+                                "[reconstruct] Output truncated at gap — discarding {} bytes of tail video data",
+                                tail_video.len()
+                            );
+                        } else if !tail_video.is_empty() {
                             reconstructed.extend_from_slice(tail_video);
+                            tail_video_len = tail_video.len() as u64;
                         }
                         tail_moov_data = Some(tail_moov.to_vec());
+                    } else if result.truncated {
+                        println!(
+                            "[reconstruct] Output truncated at gap — discarding all {} bytes of tail data",
+                            td.len()
+                        );
                     } else {
                         // No moov found in tail — write it all as video data
                         println!(
@@ -988,76 +1506,108 @@ fn reconstruct_chunked_mp4(
                             td.len()
                         );
                         reconstructed.extend_from_slice(td);
+                        tail_video_len = td.len() as u64;
                     }
                 }
 
-                // 6b. Reconcile mdat size with actual assembled data.
+                // 7b. Reconcile mdat size with actual assembled data.
                 // For mp4_header_only files, the mdat declares the FULL original size
                 // (e.g., 47MB) so padding to that size preserves moov stco/co64 offsets.
                 // For mp4_complete files (or mdat with size=0), the declared size only
                 // covers the first ~1MB — truncating would discard most of the video.
                 // In that case, expand mdat to cover all assembled data; the tail moov
                 // (if present) or ffmpeg remux will provide correct sample tables.
-                let target_mdat_end = mdat_start + mdat_declared_size as usize;
-                let actual_mdat_size = (reconstructed.len() - mdat_start) as u64;
-                let final_mdat_size;
-
-                if reconstructed.len() < target_mdat_end {
-                    // Assembled data is smaller than declared mdat — zero-pad to preserve
-                    // moov offsets. Missing chunks become black/silent frames.
-                    let pad = target_mdat_end - reconstructed.len();
-                    println!(
-                        "[reconstruct] Padding mdat with {} zero bytes to match original declared size ({} bytes) for moov offset validity",
-                        pad, mdat_declared_size
-                    );
-                    reconstructed.resize(target_mdat_end, 0u8);
-                    final_mdat_size = mdat_declared_size;
-                } else if actual_mdat_size > mdat_declared_size * 2 {
-                    // Assembled data FAR exceeds the declared mdat size.
-                    // This happens when the header is an mp4_complete file whose mdat
-                    // only declares ~1MB, but the real video spans many chunks.
-                    // Do NOT truncate — expand mdat to cover all assembled data.
-                    // The tail chunk's moov (if found) references the full data,
-                    // and ffmpeg remux will rebuild sample tables correctly.
-                    final_mdat_size = actual_mdat_size;
-                    println!(
-                        "[reconstruct] Expanding mdat: assembled {} bytes >> declared {} bytes — using actual size (header was likely mp4_complete or mdat size=0)",
-                        actual_mdat_size, mdat_declared_size
-                    );
-                } else if reconstructed.len() > target_mdat_end {
-                    // Small overflow — likely rounding or alignment. Truncate to declared size.
-                    println!(
-                        "[reconstruct] Reconstructed mdat ({} bytes) slightly exceeds original declared size ({} bytes) — truncating",
-                        actual_mdat_size, mdat_declared_size
-                    );
-                    reconstructed.truncate(target_mdat_end);
-                    final_mdat_size = mdat_declared_size;
-                } else {
-                    // Exact match
-                    final_mdat_size = mdat_declared_size;
+                let len = reconstructed.len() as u64;
+                let fit = reconstruction_plan::MdatFit::of(len, mdat_start as u64, mdat_declared_size, gap_policy);
+                let final_mdat_size = fit.mdat_size(len, mdat_start as u64, mdat_declared_size);
+                let next_slot = walk.next_slot();
+
+                match fit {
+                    MdatFit::Pad(pad) => {
+                        // Assembled data is smaller than declared mdat — zero-pad to preserve
+                        // moov offsets. Missing chunks become black/silent frames. The
+                        // tail's media ends the upload, so the zeros go in front of it.
+                        let at = (len - tail_video_len) as usize;
+                        let slots = next_slot..next_slot + pad.div_ceil(full_chunk_size);
+                        result.record_gap_range(at as u64, pad, missing_chunks(slots));
+                        println!(
+                            "[reconstruct] Padding mdat with {} zero bytes to match original declared size ({} bytes) for moov offset validity",
+                            pad, mdat_declared_size
+                        );
+                        reconstructed.resize((len + pad) as usize, 0u8);
+                        reconstructed.copy_within(at..len as usize, at + pad as usize);
+                        reconstructed[at..at + pad as usize].fill(0);
+                    }
+                    MdatFit::Short(shortfall) => {
+                        // Same shortfall, but the policy forbids padding — keep mdat at its
+                        // real size. Ranges already counted as gaps are not counted again.
+                        if !result.truncated && shortfall > result.gap_bytes {
+                            let missing = shortfall - result.gap_bytes;
+                            let slots = next_slot..next_slot + missing.div_ceil(full_chunk_size);
+                            let at = (len + shortfall).saturating_sub(tail_video_len + missing);
+                            result.record_gap_range(at, missing, missing_chunks(slots));
+                        }
+                        println!(
+                            "[reconstruct] mdat is {} bytes short of declared size ({} bytes) — not padding ({:?})",
+                            shortfall, mdat_declared_size, gap_policy
+                        );
+                    }
+                    MdatFit::Grow => {
+                        // Assembled data FAR exceeds the declared mdat size.
+                        // This happens when the header is an mp4_complete file whose mdat
+                        // only declares ~1MB, but the real video spans many chunks.
+                        // Do NOT truncate — expand mdat to cover all assembled data.
+                        // The tail chunk's moov (if found) references the full data,
+                        // and ffmpeg remux will rebuild sample tables correctly.
+                        println!(
+                            "[reconstruct] Expanding mdat: assembled {} bytes >> declared {} bytes — using actual size (header was likely mp4_complete or mdat size=0)",
+                            final_mdat_size, mdat_declared_size
+                        );
+                    }
+                    MdatFit::Cut(cut) => {
+                        // Small overflow — likely rounding or alignment. Truncate to declared size.
+                        println!(
+                            "[reconstruct] Reconstructed mdat ({} bytes) slightly exceeds original declared size ({} bytes) — truncating",
+                            final_mdat_size + cut, mdat_declared_size
+                        );
+                        reconstructed.truncate((len - cut) as usize);
+                    }
+                    MdatFit::Exact => {}
                 }
 
-                // 7. Patch the mdat header with the final size.
+                // A chunk sort_chunks left out leaves a gap where its slot was.
+                let left_out: Vec<(String, &str)> = foreign
+                    .iter()
+                    .filter_map(|fp| {
+                        let name = std::path::Path::new(fp).file_name()?.to_string_lossy().into_owned();
+                        Some((name, facts[fp].left_out?))
+                    })
+                    .collect();
+                result.explain_gaps(&left_out);
+
+                // 8. Patch the mdat header with the final size.
+                let mut final_mdat_size = final_mdat_size;
                 if mdat_header_size == 16 {
                     reconstructed[mdat_start + 8..mdat_start + 16]
                         .copy_from_slice(&final_mdat_size.to_be_bytes());
+                } else if final_mdat_size > u32::MAX as u64 {
+                    // Past 4 GB the 32-bit header can't hold the size: switch to a
+                    // 64-bit one and move the moov's chunk offsets along with the media.
+                    println!(
+                        "[reconstruct] mdat is {} bytes — widening to a 64-bit header, chunk offsets +8",
+                        final_mdat_size
+                    );
+                    mp4_samples::widen_mdat_header(&mut reconstructed, mdat_start, final_mdat_size)?;
+                    if let Some(moov) = tail_moov_data.as_mut() {
+                        mp4_samples::shift_chunk_offsets(moov, 8)?;
+                    }
+                    final_mdat_size += 8;
                 } else {
-                    // 32-bit mdat header — cap at u32::MAX to prevent silent overflow.
-                    // In practice, browser cache videos never approach 4GB.
-                    let capped_size = if final_mdat_size > u32::MAX as u64 {
-                        println!(
-                            "[reconstruct] WARNING: final_mdat_size {} exceeds u32::MAX, capping to {} for 32-bit mdat header",
-                            final_mdat_size, u32::MAX
-                        );
-                        u32::MAX
-                    } else {
-                        final_mdat_size as u32
-                    };
                     reconstructed[mdat_start..mdat_start + 4]
-                        .copy_from_slice(&capped_size.to_be_bytes());
+                        .copy_from_slice(&(final_mdat_size as u32).to_be_bytes());
                 }
 
-                // 8. Append moov atom AFTER mdat as a separate top-level box.
+                // 9. Append moov atom AFTER mdat as a separate top-level box.
                 if let Some(ref moov_data) = tail_moov_data {
                     let moov_offset_in_file = reconstructed.len();
                     reconstructed.extend_from_slice(moov_data);
@@ -1077,47 +1627,33 @@ fn reconstruct_chunked_mp4(
                 );
 
                 // Moov is already correctly placed by the tail chunk above.
-                // Do NOT overwrite from all_data — all_data is a gap-less concatenation
-                // where moov_offset doesn't correspond to the real file layout.
-
-                let mut out_file = std::fs::File::create(&output)
-                    .map_err(|e| format!("Failed to create {}: {}", output, e))?;
-                out_file
-                    .write_all(&reconstructed)
-                    .map_err(|e| format!("Failed to write: {}", e))?;
-                out_file
-                    .flush()
-                    .map_err(|e| format!("Failed to flush: {}", e))?;
+                // moov_offset is in the gap-less concatenation, not the real file
+                // layout, so nothing is patched from it.
+
+                output_file::write_output(&output, &reconstructed)?;
 
                 println!(
                     "[reconstruct] Written {} bytes to {}",
                     reconstructed.len(),
                     output
                 );
-                Ok(reconstructed.len() as u64)
+                result.finish(&reconstructed);
+                Ok(result)
             } else {
-                let mut out_file = std::fs::File::create(&output)
-                    .map_err(|e| format!("Failed to create {}: {}", output, e))?;
-                out_file
-                    .write_all(&all_data)
-                    .map_err(|e| format!("Failed to write: {}", e))?;
-                out_file
-                    .flush()
-                    .map_err(|e| format!("Failed to flush: {}", e))?;
-                Ok(all_data.len() as u64)
+                let all_data =
+                    concatenate_chunks(&header_data, &middle_paths, tail_path.as_deref(), &facts, full_chunk_size)?;
+                output_file::write_output(&output, &all_data)?;
+                result.finish(&all_data);
+                Ok(result)
             }
         }
         None => {
             println!("[reconstruct] No moov found — writing concatenated data");
-            let mut out_file = std::fs::File::create(&output)
-                .map_err(|e| format!("Failed to create {}: {}", output, e))?;
-            out_file
-                .write_all(&all_data)
-                .map_err(|e| format!("Failed to write: {}", e))?;
-            out_file
-                .flush()
-                .map_err(|e| format!("Failed to flush: {}", e))?;
-            Ok(all_data.len() as u64)
+            let all_data =
+                concatenate_chunks(&header_data, &middle_paths, tail_path.as_deref(), &facts, full_chunk_size)?;
+            output_file::write_output(&output, &all_data)?;
+            result.finish(&all_data);
+            Ok(result)
         }
     }
 }
@@ -1127,9 +1663,15 @@ fn reconstruct_chunked_mp4(
 /// If the file contains two or more moov boxes, all but the first are
 /// removed and the file is rewritten in-place.
 /// Returns the number of moov boxes found (before fixing).
+/// Requires a `consent` token covering `path`.
 #[tauri::command]
-fn fix_mp4_moov(path: String) -> Result<u32, String> {
-    use std::io::Write;
+fn fix_mp4_moov(
+    state: tauri::State<'_, consent::ConsentState>,
+    path: String,
+    consent: String,
+) -> Result<u32, String> {
+    let path = state.check(&consent, &path)?;
+    let _job = crash::job(format!("fixing moov in {}", path));
     let data = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
 
     // Parse all top-level boxes
@@ -1151,7 +1693,7 @@ fn fix_mp4_moov(path: String) -> Result<u32, String> {
         };
 
         if actual_size < 8 { break; }
-        if !btype.iter().all(|&b| b >= 0x20 && b <= 0x7e) { break; }
+        if !btype.iter().all(|b| (0x20..=0x7e).contains(b)) { break; }
 
         let end = pos + actual_size;
         if end > data.len() { break; }
@@ -1183,34 +1725,116 @@ fn fix_mp4_moov(path: String) -> Result<u32, String> {
         fixed.extend_from_slice(&data[*offset..*offset + *size]);
     }
 
-    let mut out = std::fs::File::create(&path)
-        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
-    out.write_all(&fixed).map_err(|e| format!("Failed to write: {}", e))?;
-    out.flush().map_err(|e| format!("Failed to flush: {}", e))?;
+    output_file::write_output(&path, &fixed)?;
 
     println!("[fix_mp4_moov] Fixed: {} -> {} bytes", data.len(), fixed.len());
     Ok(moov_count)
 }
 
-/// Extract the Content-Type from a Simple Cache file's HTTP response headers (stream 0).
+/// Validate a PNG (signature, chunk CRCs, IEND) and repair it in place:
+/// anything after IEND is cut, a truncated file loses its broken trailing
+/// chunk and gets an IEND. Chunks with a bad CRC are counted but kept.
+/// Requires a `consent` token covering `path`.
+#[tauri::command(async)]
+fn repair_png(
+    state: tauri::State<'_, consent::ConsentState>,
+    path: String,
+    consent: String,
+) -> Result<image_repair::ImageRepairReport, String> {
+    let path = state.check(&consent, &path)?;
+    let _job = crash::job(format!("repairing {}", path));
+    let data = read_with_lock_retry(&path).map_err(|e| format_read_error(&path, &e))?;
+    if !data.starts_with(image_repair::PNG_SIGNATURE) {
+        return Err(format!("{} is not a PNG (bad signature)", path));
+    }
+    let (fixed, report) = image_repair::repair(&data, false);
+    if report.action == "repaired" {
+        output_file::write_output(&path, &fixed)?;
+    }
+    println!(
+        "[repair_png] {}: {} ({} bad CRCs) — {}",
+        path,
+        report.action,
+        report.bad_chunks,
+        report.details.join("; ")
+    );
+    Ok(report)
+}
+
+/// Find a header value in Simple Cache stream 0 data.
 /// Chromium stores headers as null-byte separated strings: "HTTP/1.1 200\0Content-Type: video/mp4\0..."
-#[tauri::command]
-fn read_file_content_type(path: String) -> Result<String, String> {
-    let data = std::fs::read(&path).map_err(|e| format_read_error(&path, &e))?;
-    let headers = extract_simple_cache_headers(&data)
-        .ok_or_else(|| "Not a Simple Cache file or no headers".to_string())?;
-    let header_str = String::from_utf8_lossy(&headers);
+/// `name` must be lowercase and include the trailing colon, e.g. "content-type:".
+fn find_stream0_header(headers: &[u8], name: &str) -> Option<String> {
+    let header_str = String::from_utf8_lossy(headers);
     // Chromium HttpResponseHeaders uses null-byte separators
     for part in header_str.split('\0') {
-        let lower = part.to_lowercase();
-        if lower.starts_with("content-type:") {
-            let ct = part["content-type:".len()..].trim();
-            // Strip parameters like charset, boundary, etc.
-            let mime = ct.split(';').next().unwrap_or(ct).trim();
-            return Ok(mime.to_lowercase());
+        if part.get(..name.len()).is_some_and(|p| p.eq_ignore_ascii_case(name)) {
+            return Some(part[name.len()..].trim().to_string());
         }
     }
-    Err("No Content-Type header found".to_string())
+    None
+}
+
+/// Read the lowercase MIME type (parameters stripped) from a Simple Cache file's stream 0.
+fn simple_cache_content_type(path: &str) -> Result<String, String> {
+    let data = std::fs::read(path).map_err(|e| format_read_error(path, &e))?;
+    let headers = extract_simple_cache_headers(&data)
+        .ok_or_else(|| "Not a Simple Cache file or no headers".to_string())?;
+    let ct = http_info::parse(&headers)
+        .and_then(|info| info.header("content-type").map(str::to_string))
+        .or_else(|| find_stream0_header(&headers, "content-type:"))
+        .ok_or_else(|| "No Content-Type header found".to_string())?;
+    // Strip parameters like charset, boundary, etc.
+    let mime = ct.split(';').next().unwrap_or(&ct).trim();
+    Ok(mime.to_lowercase())
+}
+
+/// Per-file outcome of `read_content_types`. Exactly one field is set.
+#[derive(Debug, serde::Serialize)]
+struct ContentTypeEntry {
+    content_type: Option<String>,
+    error: Option<String>,
+}
+
+/// Extract the Content-Type from many Simple Cache files' HTTP response headers
+/// (stream 0) in parallel and return a map keyed by path. A failing file gets an
+/// `error` entry instead of failing the whole batch, so one IPC call replaces
+/// thousands; only a worker that panicked fails it.
+#[tauri::command(async)]
+fn read_content_types(paths: Vec<String>) -> Result<std::collections::HashMap<String, ContentTypeEntry>, String> {
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(paths.len().max(1));
+    let per_worker = paths.len().div_ceil(workers).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(per_worker)
+            .map(|batch| {
+                scope.spawn(move || {
+                    batch
+                        .iter()
+                        .map(|p| {
+                            let _permit = io_limit::acquire_for(p);
+                            let read = fault_inject::inject("read_content_types", p)
+                                .and_then(|()| simple_cache_content_type(p));
+                            let entry = match read {
+                                Ok(ct) => ContentTypeEntry { content_type: Some(ct), error: None },
+                                Err(e) => ContentTypeEntry { content_type: None, error: Some(e) },
+                            };
+                            (p.clone(), entry)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut types = std::collections::HashMap::with_capacity(paths.len());
+        for handle in handles {
+            types.extend(handle.join().map_err(|_| "A Content-Type worker panicked".to_string())?);
+        }
+        Ok(types)
+    })
 }
 
 
@@ -1234,23 +1858,31 @@ fn read_file_content_type(path: String) -> Result<String, String> {
 const SPARSE_RANGE_MAGIC: u64 = 0xeb97bf016553676b;
 /// Size of a SparseRangeHeader: magic(8) + offset(8) + length(8) + crc32(4) + padding(4) = 32
 const SPARSE_RANGE_HEADER_SIZE: usize = 32;
+/// Zero-filled holes a reassembled sparse body may add on top of its file's size
+const MAX_SPARSE_HOLE_BYTES: u64 = 256 * 1024 * 1024;
 
 /// Parse a Chromium Simple Cache _s (sparse) file and return the reassembled data.
 /// Returns an error string if the file doesn't look like a valid sparse cache file.
 /// Returns an empty Vec if the file header is valid but contains no data chunks.
-#[tauri::command]
+#[tauri::command(async)]
 fn read_sparse_cache_file(path: String) -> Result<Vec<u8>, String> {
-    let data = read_with_lock_retry(&path)
+    let _permit = io_limit::acquire_for(&path);
+    fault_inject::inject("read_sparse_cache_file", &path)?;
+    let mut data = read_with_lock_retry(&path)
         .map_err(|e| format_read_error(&path, &e))?;
+    fault_inject::corrupt("read_sparse_cache_file", &path, &mut data);
     reassemble_sparse_data(&data, &path)
 }
 
 /// Get the total reassembled size of a sparse cache file without reading all data.
 /// Returns 0 if not a valid sparse file or if the file is empty.
-#[tauri::command]
+#[tauri::command(async)]
 fn get_sparse_cache_size(path: String) -> Result<u64, String> {
-    let data = read_with_lock_retry(&path)
+    let _permit = io_limit::acquire_for(&path);
+    fault_inject::inject("get_sparse_cache_size", &path)?;
+    let mut data = read_with_lock_retry(&path)
         .map_err(|e| format_read_error(&path, &e))?;
+    fault_inject::corrupt("get_sparse_cache_size", &path, &mut data);
 
     if data.len() < SIMPLE_CACHE_HEADER_SIZE {
         return Ok(0);
@@ -1262,10 +1894,9 @@ fn get_sparse_cache_size(path: String) -> Result<u64, String> {
     if magic != SIMPLE_CACHE_MAGIC {
         return Ok(0);
     }
-    let key_length = u32::from_le_bytes(match data[12..16].try_into() {
-        Ok(b) => b,
-        Err(_) => return Ok(0),
-    }) as usize;
+    let Some(key_length) = key_hash::checked_key_length(&data, &path) else {
+        return Ok(0);
+    };
 
     let mut pos = SIMPLE_CACHE_HEADER_SIZE + key_length;
     let mut max_end: u64 = 0;
@@ -1300,10 +1931,13 @@ fn get_sparse_cache_size(path: String) -> Result<u64, String> {
 /// Read the first N reassembled bytes from a sparse cache file (for magic byte / type detection).
 /// This avoids reading the entire file into memory just to check the first few hundred bytes.
 /// Returns the first `size` bytes starting from offset 0 of the reassembled data.
-#[tauri::command]
+#[tauri::command(async)]
 fn read_sparse_cache_header(path: String, size: usize) -> Result<Vec<u8>, String> {
-    let data = read_with_lock_retry(&path)
+    let _permit = io_limit::acquire_for(&path);
+    fault_inject::inject("read_sparse_cache_header", &path)?;
+    let mut data = read_with_lock_retry(&path)
         .map_err(|e| format_read_error(&path, &e))?;
+    fault_inject::corrupt("read_sparse_cache_header", &path, &mut data);
 
     if data.len() < SIMPLE_CACHE_HEADER_SIZE {
         return Err(format!("File too small to be a sparse cache file: {}", path));
@@ -1314,9 +1948,8 @@ fn read_sparse_cache_header(path: String, size: usize) -> Result<Vec<u8>, String
         return Err(format!("Not a Simple Cache file (bad magic): {}", path));
     }
 
-    let key_length = u32::from_le_bytes(
-        data[12..16].try_into().map_err(|_| "read key_len".to_string())?
-    ) as usize;
+    let key_length = key_hash::checked_key_length(&data, &path)
+        .ok_or_else(|| format!("Corrupt Simple Cache header (key_hash mismatch): {}", path))?;
 
     let mut pos = SIMPLE_CACHE_HEADER_SIZE + key_length;
     if pos > data.len() {
@@ -1369,21 +2002,19 @@ fn read_sparse_cache_header(path: String, size: usize) -> Result<Vec<u8>, String
 
 /// Copy a _s (sparse) Simple Cache file to dst, reassembling range chunks into contiguous data.
 /// This is the correct way to extract video data from macOS Discord _s cache files.
+/// Needs a `consent` token covering `dst` unless it is a temp scratch file.
 #[tauri::command]
-fn copy_sparse_file(src: String, dst: String) -> Result<u64, String> {
-    use std::io::Write;
-    if let Some(parent) = std::path::Path::new(&dst).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
-    }
+fn copy_sparse_file(
+    state: tauri::State<'_, consent::ConsentState>,
+    src: String,
+    dst: String,
+    consent: Option<String>,
+) -> Result<u64, String> {
+    let dst = state.check_output(consent.as_deref(), &dst)?;
     let data = read_with_lock_retry(&src)
         .map_err(|e| format_read_error(&src, &e))?;
     let buf = reassemble_sparse_data(&data, &src)?;
-    let total_size = buf.len() as u64;
-    let mut out = std::fs::File::create(&dst)
-        .map_err(|e| format!("Failed to create {}: {}", dst, e))?;
-    out.write_all(&buf).map_err(|e| format!("Failed to write: {}", e))?;
-    out.flush().map_err(|e| format!("Failed to flush: {}", e))?;
-    Ok(total_size)
+    output_file::write_output(&dst, &buf)
 }
 
 /// Get scan status
@@ -1453,10 +2084,7 @@ fn test_path_access(path: String) -> Result<serde_json::Value, String> {
         .unwrap_or_else(|_| "unknown".to_string());
 
     // First test: can we list the directory?
-    let can_list = match std::fs::read_dir(&path) {
-        Ok(_) => true,
-        Err(_) => false,
-    };
+    let can_list = std::fs::read_dir(&path).is_ok();
 
     // Second test: try to open+read the first file we find
     let mut read_result = serde_json::json!({
@@ -1477,8 +2105,9 @@ fn test_path_access(path: String) -> Result<serde_json::Value, String> {
                     Ok(mut f) => {
                         use std::io::Read;
                         let mut buf = [0u8; 1];
+                        // An empty file reads 0 bytes; opening it still passed.
                         match f.read(&mut buf) {
-                            Ok(_) => {
+                            Ok(_read) => {
                                 read_result = serde_json::json!({
                                     "tested": true,
                                     "success": true,
@@ -1521,6 +2150,53 @@ fn test_path_access(path: String) -> Result<serde_json::Value, String> {
     }))
 }
 
+/// Build and capability information about the running backend.
+#[derive(Debug, serde::Serialize)]
+struct BackendInfo {
+    version: &'static str,
+    /// Short commit hash embedded by build.rs, "unknown" outside a git checkout
+    git_hash: &'static str,
+    /// "debug" or "release"
+    profile: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    /// Optional cargo features compiled in: "vss", "carving", "network"
+    features: Vec<&'static str>,
+    /// Cache formats this binary can read
+    cache_backends: Vec<&'static str>,
+    /// Export target kinds (see export_target.rs)
+    export_targets: Vec<&'static str>,
+}
+
+/// Describe exactly which capabilities this binary has, for the UI and bug reports.
+#[tauri::command]
+fn get_backend_info() -> BackendInfo {
+    let mut features = Vec::new();
+    if cfg!(feature = "vss") {
+        features.push("vss");
+    }
+    if cfg!(feature = "carving") {
+        features.push("carving");
+    }
+    if cfg!(feature = "network") {
+        features.push("network");
+    }
+    let mut export_targets = vec!["local"];
+    if cfg!(any(target_os = "linux", target_os = "windows")) {
+        export_targets.push("mtp");
+    }
+    BackendInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: option_env!("CACHEPHOENIX_GIT_HASH").unwrap_or("unknown"),
+        profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        features,
+        cache_backends: vec!["simple_cache", "blockfile", "safari_cache_db"],
+        export_targets,
+    }
+}
+
 /// Get the current executable path. Useful for showing the user which binary
 /// needs Full Disk Access in System Settings.
 #[tauri::command]
@@ -1775,6 +2451,16 @@ pub fn run() {
             scan_running: false,
             recovery_running: false,
         }))
+        .manage(consent::ConsentState::default())
+        .manage(sparse_watch::SparseWatches::default())
+        .setup(|app| {
+            crash::install(app.handle().clone());
+            watchdog::start(app.handle().clone());
+            mime_category::load_saved(app.handle());
+            hashing::load_saved(app.handle());
+            events::load_saved(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_default_cache_paths,
             validate_cache_path,
@@ -1784,11 +2470,83 @@ pub fn run() {
             write_file_bytes,
             get_file_size,
             list_cache_files,
+            quick_scan::quick_scan,
+            compare::compare_entries,
+            sparse_watch::watch_sparse_entry,
+            sparse_watch::stop_watch_sparse_entry,
+            consent::request_write_consent,
+            consent::revoke_write_consent,
+            privacy::purge_temp_workspace,
+            entropy::check_encrypted_bodies,
+            io_limit::set_io_concurrency,
+            path_display::display_paths,
+            custom_paths::add_custom_cache_path,
+            custom_paths::remove_custom_cache_path,
+            custom_paths::list_custom_cache_paths,
+            saved_media::find_saved_originals,
+            drive_scan::scan_drive_for_caches,
+            fault_inject::set_fault_injection,
+            image_repair::repair_image,
+            key_hash::verify_key_hashes,
+            mp4_samples::check_mp4_coverage,
+            partial_export::export_partial,
+            partial_export::merge_partial,
+            crc_check::verify_crc32,
+            entry::read_entry,
+            entry::copy_entry,
+            ignore_rules::list_ignore_rules,
+            ignore_rules::set_ignore_rules,
+            sparse_merge::group_sparse_entries,
+            sparse_merge::merge_sparse_entries,
+            pipeline_harness::run_pipeline_on_fixture,
+            http_info::read_entry_headers,
+            mime_category::classify_mime,
+            mime_category::list_mime_overrides,
+            mime_category::set_mime_overrides,
+            events::list_event_policies,
+            events::set_event_policy,
+            job_outputs::begin_output_job,
+            job_outputs::track_job_output,
+            job_outputs::cancel_output_job,
+            job_outputs::clean_job_outputs,
+            job_outputs::end_output_job,
+            entry::group_cache_entries,
+            output_naming::recover_entry,
+            cache_audit::audit_cache,
+            export_mapping::begin_export_mapping,
+            export_mapping::finish_export_mapping,
+            deleted_entries::scan_deleted_entries,
+            deleted_entries::recover_deleted_entry,
+            disk_carve::carve_disk_image,
+            scan_session::open_scan_session,
+            scan_session::group_entries,
+            scan_session::session_entries,
+            scan_session::close_scan_session,
+            watchdog::watch_sidecar,
+            watchdog::report_operation_progress,
+            watchdog::end_operation_watch,
+            watchdog::list_stalled_operations,
+            watchdog::set_stall_timeout,
+            watchdog::skip_operation,
+            watchdog::kill_operation,
+            compat::compat_fingerprints,
+            webm::reconstruct_chunked_webm,
+            moov_rebuild::rebuild_moov,
+            fmp4::reconstruct_fragmented_mp4,
+            hls::reconstruct_hls,
+            dash::reconstruct_dash,
+            audio_salvage::salvage_audio,
+            safari_cache::get_default_safari_paths,
+            safari_cache::list_safari_entries,
+            safari_cache::extract_safari_entry,
+            legacy_formats::repair_legacy_media,
+            archive_export::export_zip,
             open_folder,
             concat_files,
             reconstruct_chunked_mp4,
             fix_mp4_moov,
-            read_file_content_type,
+            repair_png,
+            read_content_types,
             read_sparse_cache_file,
             get_sparse_cache_size,
             read_sparse_cache_header,
@@ -1797,10 +2555,22 @@ pub fn run() {
             probe_full_disk_access,
             test_path_access,
             get_app_binary_path,
+            get_backend_info,
             diagnose_file_read,
             fix_sidecar_permissions,
+            self_test::self_test,
             blockfile_index::parse_blockfile_index,
+            blockfile_index::list_blockfile_entries,
             blockfile_index::reconstruct_from_index,
+            discord_cdn::describe_cdn_url,
+            user_media::find_user_media,
+            output_file::check_output_folder,
+            export_target::list_export_targets,
+            export_target::export_to_target,
+            thumbnail::generate_thumbnail,
+            media_probe::probe_media,
+            remux::remux_mp4,
+            reconstruction_plan::plan_reconstruction,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+// ─── Playback check ─────────────────────────────────────────────────────────
+//
+// A reconstruction that wrote every byte can still be unplayable: a moov that
+// never arrived, an index pointing past the data, a stream ffmpeg has no
+// decoder for. Before a video or audio file is counted as recovered it is run
+// through the ffprobe sidecar, and what the demuxer found — streams, codecs,
+// duration, resolution, and the errors it logged on the way — decides whether
+// it plays.
+
+/// ffprobe error lines kept per file.
+const MAX_ERRORS: usize = 20;
+
+#[derive(Debug, Serialize)]
+pub struct StreamInfo {
+    pub index: u32,
+    /// "video", "audio", "subtitle", "data", ...
+    pub kind: String,
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub duration_secs: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MediaProbe {
+    pub path: String,
+    /// Demuxer ffprobe picked, e.g. "mov,mp4,m4a,3gp,3g2,mj2" or "matroska,webm"
+    pub format: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+    /// What ffprobe complained about while reading, first MAX_ERRORS lines
+    pub errors: Vec<String>,
+    /// ffprobe opened the file and found a decodable audio or video stream
+    /// with a usable size and duration
+    pub playable: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct ProbeJson {
+    #[serde(default)]
+    format: FormatJson,
+    #[serde(default)]
+    streams: Vec<StreamJson>,
+}
+
+#[derive(Deserialize, Default)]
+struct FormatJson {
+    format_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamJson {
+    #[serde(default)]
+    index: u32,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    duration: Option<String>,
+}
+
+/// "30000/1001" or "25/1"; "0/0" (unknown) gives None.
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+fn parse_secs(value: &Option<String>) -> Option<f64> {
+    value.as_deref()?.parse::<f64>().ok().filter(|d| d.is_finite() && *d >= 0.0)
+}
+
+/// Build the probe result from an ffprobe run's JSON, stderr and exit code.
+fn interpret(path: &str, stdout: &[u8], stderr: &str, code: Option<i32>) -> MediaProbe {
+    let parsed: ProbeJson = serde_json::from_slice(stdout).unwrap_or_default();
+    let mut errors: Vec<String> =
+        stderr.lines().map(str::trim).filter(|l| !l.is_empty()).take(MAX_ERRORS).map(String::from).collect();
+    if code != Some(0) && errors.is_empty() {
+        errors.push(format!("ffprobe exited with {}", code.map_or("a signal".to_string(), |c| c.to_string())));
+    }
+
+    let streams: Vec<StreamInfo> = parsed
+        .streams
+        .into_iter()
+        .map(|s| StreamInfo {
+            index: s.index,
+            kind: s.codec_type.unwrap_or_else(|| "unknown".to_string()),
+            codec: s.codec_name,
+            width: s.width,
+            height: s.height,
+            frame_rate: [s.avg_frame_rate, s.r_frame_rate].iter().flatten().find_map(|r| parse_rate(r)),
+            sample_rate: s.sample_rate.as_deref().and_then(|r| r.parse().ok()),
+            channels: s.channels,
+            duration_secs: parse_secs(&s.duration),
+        })
+        .collect();
+    let duration_secs = parse_secs(&parsed.format.duration)
+        .or_else(|| streams.iter().filter_map(|s| s.duration_secs).reduce(f64::max));
+
+    // Streams with no decoder name, or video without a frame size, are ones
+    // ffprobe could not make sense of. A missing duration is allowed (live
+    // WebM never has one); a zero one is not.
+    let decodable = |s: &StreamInfo| {
+        s.codec.is_some()
+            && match s.kind.as_str() {
+                "video" => s.width.unwrap_or(0) > 0 && s.height.unwrap_or(0) > 0,
+                "audio" => true,
+                _ => false,
+            }
+    };
+    let playable = code == Some(0) && streams.iter().any(decodable) && duration_secs.is_none_or(|d| d > 0.0);
+
+    MediaProbe {
+        path: path.to_string(),
+        format: parsed.format.format_name,
+        duration_secs,
+        bit_rate: parsed.format.bit_rate.as_deref().and_then(|b| b.parse().ok()),
+        streams,
+        errors,
+        playable,
+    }
+}
+
+/// Run ffprobe on `path` and report its streams and whether it plays.
+#[tauri::command]
+pub async fn probe_media(app: tauri::AppHandle, path: String) -> Result<MediaProbe, String> {
+    if !std::path::Path::new(&path).is_file() {
+        return Err(format!("Not a file: {}", path));
+    }
+    let args: Vec<String> = ["-v", "error", "-show_format", "-show_streams", "-print_format", "json", path.as_str()]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let output = crate::sidecar::run(&app, "ffprobe", &args, "probe", &path).await?;
+    let probe = interpret(&path, &output.stdout, &output.stderr, output.code);
+    println!(
+        "[probe_media] {}: {} stream(s), {:?}s, playable={}{}",
+        path,
+        probe.streams.len(),
+        probe.duration_secs,
+        probe.playable,
+        probe.errors.first().map(|e| format!(" ({})", e)).unwrap_or_default()
+    );
+    Ok(probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_streams_and_decides_playability() {
+        let json = br#"{
+            "streams": [
+                {"index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720,
+                 "avg_frame_rate": "30000/1001", "r_frame_rate": "30/1", "duration": "12.012"},
+                {"index": 1, "codec_type": "audio", "codec_name": "aac", "sample_rate": "48000", "channels": 2}
+            ],
+            "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "12.050000", "bit_rate": "2500000"}
+        }"#;
+        let probe = interpret("a.mp4", json, "", Some(0));
+        assert!(probe.playable);
+        assert_eq!(probe.duration_secs, Some(12.05));
+        assert_eq!(probe.bit_rate, Some(2_500_000));
+        assert!((probe.streams[0].frame_rate.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!((probe.streams[1].sample_rate, probe.streams[1].channels), (Some(48000), Some(2)));
+
+        // Video whose frame size never arrived, and a file ffprobe refused.
+        let sizeless = br#"{"streams": [{"index": 0, "codec_type": "video", "codec_name": "h264", "width": 0}]}"#;
+        assert!(!interpret("b.mp4", sizeless, "", Some(0)).playable);
+        let refused = interpret("c.mp4", b"{}", "[mov,mp4 @ 0x1] moov atom not found\nc.mp4: Invalid data\n", Some(1));
+        assert!(!refused.playable);
+        assert_eq!(refused.errors.len(), 2);
+        assert!(refused.streams.is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+use crate::custom_paths::{read_json, write_json};
+
+// ─── MIME → media category ──────────────────────────────────────────────────
+//
+// Whether a body is a video, image, audio, document or something else was
+// decided in several places — a `video/` prefix check here, a list of
+// non-media prefixes in the scanner there. The mapping now lives in one
+// table: the Content-Type decides, unless it is missing or generic
+// (`application/octet-stream`), in which case the sniffed type does. User
+// overrides (e.g. "application/x-mpegurl is video") are kept in
+// `mime_overrides.json` and take precedence over both.
+
+const OVERRIDES_FILE: &str = "mime_overrides.json";
+
+pub const CATEGORIES: &[&str] = &["video", "image", "audio", "document", "other"];
+
+/// (pattern, category). A pattern ending in `/` matches the whole top-level
+/// type; anything else matches the exact essence. First match wins.
+const RULES: &[(&str, &str)] = &[
+    ("application/mp4", "video"),
+    ("application/vnd.apple.mpegurl", "video"),
+    ("application/x-mpegurl", "video"),
+    ("application/dash+xml", "video"),
+    ("application/ogg", "audio"),
+    ("video/", "video"),
+    ("audio/", "audio"),
+    ("image/", "image"),
+    ("text/", "document"),
+    ("application/pdf", "document"),
+    ("application/json", "document"),
+    ("application/xml", "document"),
+    ("application/javascript", "document"),
+    ("application/x-javascript", "document"),
+    ("font/", "other"),
+    ("application/font-woff", "other"),
+    ("application/x-font-woff", "other"),
+    ("application/x-font-ttf", "other"),
+    ("application/wasm", "other"),
+];
+
+/// Types that say nothing about the content; a sniffed type overrides them.
+const GENERIC: &[&str] = &["application/octet-stream", "binary/octet-stream", "application/x-binary"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MimeOverride {
+    /// Exact MIME type, or a top-level type ending in `/` ("model/")
+    pub pattern: String,
+    pub category: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OverrideFile {
+    overrides: Vec<MimeOverride>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MimeClass {
+    /// The MIME type the category was taken from, parameters stripped
+    pub mime: Option<String>,
+    /// One of CATEGORIES
+    pub category: &'static str,
+    /// "override", "mime", "sniffed" or "unknown"
+    pub source: &'static str,
+}
+
+/// Overrides in effect; loaded at startup and replaced by `set_mime_overrides`.
+static OVERRIDES: RwLock<Vec<MimeOverride>> = RwLock::new(Vec::new());
+
+/// Lowercase essence of a Content-Type ("Video/MP4; codecs=..." → "video/mp4").
+pub fn essence(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+}
+
+fn pattern_matches(pattern: &str, mime: &str) -> bool {
+    if pattern.ends_with('/') {
+        mime.starts_with(pattern)
+    } else {
+        mime == pattern
+    }
+}
+
+fn category_name(category: &str) -> &'static str {
+    CATEGORIES.iter().find(|c| **c == category).copied().unwrap_or("other")
+}
+
+fn rule_category(mime: &str, overrides: &[MimeOverride]) -> Option<(&'static str, &'static str)> {
+    if let Some(o) = overrides.iter().find(|o| pattern_matches(&o.pattern, mime)) {
+        return Some((category_name(&o.category), "override"));
+    }
+    RULES.iter().find(|(pattern, _)| pattern_matches(pattern, mime)).map(|(_, category)| (*category, "mime"))
+}
+
+/// Classify a body from its Content-Type and/or sniffed MIME type.
+pub fn classify(mime: Option<&str>, sniffed: Option<&str>) -> MimeClass {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    classify_with(&overrides, mime, sniffed)
+}
+
+fn classify_with(overrides: &[MimeOverride], mime: Option<&str>, sniffed: Option<&str>) -> MimeClass {
+    let declared = mime.map(essence).filter(|m| !m.is_empty());
+    let sniffed = sniffed.map(essence).filter(|m| !m.is_empty());
+    let generic = declared.as_deref().is_none_or(|m| GENERIC.contains(&m));
+    if !generic {
+        let declared = declared.as_deref().unwrap_or_default();
+        if let Some((category, source)) = rule_category(declared, overrides) {
+            return MimeClass { mime: Some(declared.to_string()), category, source };
+        }
+    }
+    if let Some((category, source)) = sniffed.as_deref().and_then(|m| rule_category(m, overrides)) {
+        let source = if source == "override" { source } else { "sniffed" };
+        return MimeClass { mime: sniffed, category, source };
+    }
+    // A generic type is still a statement that the body isn't a known format.
+    let source = if declared.is_some() && generic { "mime" } else { "unknown" };
+    MimeClass { mime: declared.or(sniffed), category: "other", source }
+}
+
+fn validate(overrides: &[MimeOverride]) -> Result<(), String> {
+    for o in overrides {
+        if !o.pattern.contains('/') {
+            return Err(format!("\"{}\" is not a MIME type (expected e.g. \"video/x-foo\" or \"model/\")", o.pattern));
+        }
+        if !CATEGORIES.contains(&o.category.as_str()) {
+            return Err(format!("Unknown category \"{}\" (expected one of {})", o.category, CATEGORIES.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+/// Load the saved overrides; an unreadable file is logged and overrides nothing.
+pub fn load_saved(app: &tauri::AppHandle) {
+    let loaded = crate::custom_paths::config_file(app, OVERRIDES_FILE).and_then(|f| read_json::<OverrideFile>(&f));
+    match loaded {
+        Ok(file) => *OVERRIDES.write().unwrap_or_else(|e| e.into_inner()) = file.overrides,
+        Err(e) => eprintln!("[mime] {}", e),
+    }
+}
+
+/// Category of a body given its Content-Type and/or sniffed MIME type.
+#[tauri::command]
+pub fn classify_mime(mime: Option<String>, sniffed: Option<String>) -> MimeClass {
+    classify(mime.as_deref(), sniffed.as_deref())
+}
+
+/// Saved category overrides, in order.
+#[tauri::command]
+pub fn list_mime_overrides() -> Vec<MimeOverride> {
+    OVERRIDES.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Replace the category overrides. Returns them as stored.
+#[tauri::command]
+pub fn set_mime_overrides(app: tauri::AppHandle, overrides: Vec<MimeOverride>) -> Result<Vec<MimeOverride>, String> {
+    let overrides: Vec<MimeOverride> = overrides
+        .into_iter()
+        .map(|o| MimeOverride { pattern: o.pattern.trim().to_ascii_lowercase(), category: o.category })
+        .collect();
+    validate(&overrides)?;
+    let file = crate::custom_paths::config_file(&app, OVERRIDES_FILE)?;
+    let mut current = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    write_json(&file, &OverrideFile { overrides: overrides.clone() })?;
+    *current = overrides.clone();
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_type_wins_unless_generic() {
+        let none: &[MimeOverride] = &[];
+        let c = classify_with(none, Some("Video/MP4; codecs=avc1"), Some("image/png"));
+        assert_eq!((c.category, c.source, c.mime.as_deref()), ("video", "mime", Some("video/mp4")));
+        let c = classify_with(none, Some("application/octet-stream"), Some("audio/ogg"));
+        assert_eq!((c.category, c.source), ("audio", "sniffed"));
+        let c = classify_with(none, Some("application/octet-stream"), None);
+        assert_eq!((c.category, c.source), ("other", "mime"));
+        assert_eq!(classify_with(none, Some("text/html"), None).category, "document");
+        assert_eq!(classify_with(none, Some("application/x-unknown"), None).source, "unknown");
+
+        let overrides = [MimeOverride { pattern: "text/vtt".into(), category: "video".into() }];
+        let c = classify_with(&overrides, Some("text/vtt"), None);
+        assert_eq!((c.category, c.source), ("video", "override"));
+        assert!(validate(&[MimeOverride { pattern: "video".into(), category: "video".into() }]).is_err());
+    }
+}
@@ -0,0 +1,558 @@
+use serde::Serialize;
+
+use crate::mp4_samples::{be_u32, child, children};
+
+// ─── Rebuilding a lost moov from a reference file ───────────────────────────
+//
+// When the tail chunk holding the moov was evicted, the mdat still has the
+// media but nothing says where each sample starts. Like untrunc, a reference
+// MP4 recorded by the same source (same codec, same encoder settings) fills
+// in what the mdat can't: its ftyp, the video sample description (stsd, with
+// the avcC/hvcC parameter sets), the timescale and the frame duration. The
+// samples themselves are found by walking the mdat as length-prefixed NAL
+// units and cutting access units where a new picture begins. Bytes that
+// don't parse as NAL units — interleaved audio, zero-filled gaps — are
+// skipped. Audio frames carry no framing to find them by, so the rebuilt file
+// is video only.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Codec {
+    Avc,
+    Hevc,
+}
+
+impl Codec {
+    fn name(self) -> &'static str {
+        match self {
+            Codec::Avc => "h264",
+            Codec::Hevc => "h265",
+        }
+    }
+
+    fn header_len(self) -> usize {
+        match self {
+            Codec::Avc => 1,
+            Codec::Hevc => 2,
+        }
+    }
+
+    fn is_vcl(self, kind: u8) -> bool {
+        match self {
+            Codec::Avc => (1..=5).contains(&kind),
+            Codec::Hevc => kind <= 31,
+        }
+    }
+
+    fn is_sync(self, kind: u8) -> bool {
+        match self {
+            Codec::Avc => kind == 5,
+            Codec::Hevc => (16..=21).contains(&kind),
+        }
+    }
+
+    /// Non-VCL NAL units that open a new access unit (AUD, parameter sets, SEI).
+    fn starts_access_unit(self, kind: u8) -> bool {
+        match self {
+            Codec::Avc => (6..=9).contains(&kind),
+            Codec::Hevc => (32..=35).contains(&kind) || kind == 39,
+        }
+    }
+
+    /// NAL unit types an encoder actually writes; anything else is not a NAL.
+    fn plausible(self, kind: u8) -> bool {
+        match self {
+            Codec::Avc => (1..=12).contains(&kind),
+            Codec::Hevc => kind <= 9 || (16..=21).contains(&kind) || (32..=40).contains(&kind),
+        }
+    }
+}
+
+/// What the reference file contributes.
+struct Reference<'a> {
+    /// Whole ftyp box
+    ftyp: Vec<u8>,
+    /// moov payload
+    moov: &'a [u8],
+    /// Payload of the video trak
+    video_trak: &'a [u8],
+    codec: Codec,
+    /// Bytes of each NAL unit's length prefix, from avcC/hvcC
+    length_size: usize,
+    /// Most common sample duration, media timescale units
+    sample_delta: u32,
+    /// Largest video sample in the reference
+    max_sample: u64,
+    /// Handlers of the tracks that aren't rebuilt
+    dropped_tracks: Vec<String>,
+}
+
+pub(crate) fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// A full box (version 0, no flags) holding big-endian integers.
+pub(crate) fn table_box<T: Copy>(kind: &[u8; 4], values: &[T], to_bytes: impl Fn(T) -> Vec<u8>) -> Vec<u8> {
+    let mut payload = vec![0u8; 4];
+    for &value in values {
+        payload.extend(to_bytes(value));
+    }
+    mp4_box(kind, &payload)
+}
+
+pub(crate) fn handler_of(trak: &[u8]) -> String {
+    child(trak, b"mdia")
+        .and_then(|mdia| child(mdia, b"hdlr"))
+        .and_then(|hdlr| hdlr.get(8..12))
+        .map(|h| String::from_utf8_lossy(h).to_string())
+        .unwrap_or_default()
+}
+
+pub(crate) fn stbl_of(trak: &[u8]) -> Option<&[u8]> {
+    child(child(child(trak, b"mdia")?, b"minf")?, b"stbl")
+}
+
+/// Codec and NAL length-prefix size of a video track's sample description.
+pub(crate) fn nal_format(stsd: &[u8]) -> Result<(Codec, usize), String> {
+    // stsd: version/flags(4) entry_count(4), then the sample entry box. A
+    // VisualSampleEntry has 78 bytes of fixed fields before its child boxes.
+    let (entry_kind, entry) = children(stsd.get(8..).unwrap_or_default())
+        .into_iter()
+        .next()
+        .ok_or("The stsd is empty")?;
+    let config = children(entry.get(78..).unwrap_or_default());
+    let find = |kind: &[u8; 4]| config.iter().find(|(k, _)| k == kind).map(|(_, p)| *p);
+    match &entry_kind {
+        b"avc1" | b"avc3" => {
+            let avcc = find(b"avcC").ok_or("The H.264 track has no avcC")?;
+            Ok((Codec::Avc, (*avcc.get(4).ok_or("Short avcC")? & 3) as usize + 1))
+        }
+        b"hvc1" | b"hev1" => {
+            let hvcc = find(b"hvcC").ok_or("The H.265 track has no hvcC")?;
+            Ok((Codec::Hevc, (*hvcc.get(21).ok_or("Short hvcC")? & 3) as usize + 1))
+        }
+        other => Err(format!(
+            "Video codec '{}' isn't supported; only H.264 and H.265 can be rebuilt",
+            String::from_utf8_lossy(other)
+        )),
+    }
+}
+
+fn parse_reference(data: &[u8]) -> Result<Reference<'_>, String> {
+    let ftyp = crate::find_mp4_box(data, b"ftyp")
+        .and_then(|(pos, size, _)| data.get(pos..pos + size as usize))
+        .map_or_else(|| mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2avc1mp41"), <[u8]>::to_vec);
+    let (pos, size, header) = crate::find_mp4_box(data, b"moov").ok_or("The reference file has no moov")?;
+    let moov = &data[pos + header..(pos as u64 + size).min(data.len() as u64) as usize];
+
+    let traks: Vec<&[u8]> = children(moov).into_iter().filter(|(k, _)| k == b"trak").map(|(_, t)| t).collect();
+    let video_trak = *traks.iter().find(|t| handler_of(t) == "vide").ok_or("The reference file has no video track")?;
+    let dropped_tracks = traks.iter().map(|t| handler_of(t)).filter(|h| h != "vide").collect();
+    let stbl = stbl_of(video_trak).ok_or("The reference video track has no sample table")?;
+
+    let stsd = child(stbl, b"stsd").ok_or("The reference video track has no stsd")?;
+    let (codec, length_size) = nal_format(stsd)?;
+
+    // stts: version/flags(4) entry_count(4) [sample_count, sample_delta]
+    let stts = child(stbl, b"stts").ok_or("The reference video track has no stts")?;
+    let runs: Vec<(u32, u32)> = (0..be_u32(stts, 4).unwrap_or(0) as usize)
+        .map_while(|i| Some((be_u32(stts, 8 + 8 * i)?, be_u32(stts, 12 + 8 * i)?)))
+        .collect();
+    let mut weights: std::collections::HashMap<u32, u64> = std::collections::HashMap::new();
+    for (count, delta) in runs {
+        *weights.entry(delta).or_insert(0) += count as u64;
+    }
+    let sample_delta = weights
+        .into_iter()
+        .max_by_key(|&(delta, count)| (count, delta))
+        .map(|(delta, _)| delta)
+        .filter(|&delta| delta > 0)
+        .ok_or("The reference video track has no sample durations")?;
+
+    let stsz = child(stbl, b"stsz").ok_or("The reference video track has no stsz")?;
+    let max_sample = match be_u32(stsz, 4).unwrap_or(0) {
+        0 => (0..be_u32(stsz, 8).unwrap_or(0) as usize).map_while(|i| be_u32(stsz, 12 + 4 * i)).max().unwrap_or(0),
+        fixed => fixed,
+    } as u64;
+
+    Ok(Reference { ftyp, moov, video_trak, codec, length_size, sample_delta, max_sample, dropped_tracks })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Nal {
+    /// Length prefix + NAL unit
+    pub(crate) total: usize,
+    kind: u8,
+    /// The slice is the first of its picture (first_mb_in_slice == 0 /
+    /// first_slice_segment_in_pic_flag)
+    first_in_picture: bool,
+}
+
+pub(crate) struct NalScanner<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) codec: Codec,
+    pub(crate) length_size: usize,
+    pub(crate) max_nal: u64,
+}
+
+impl NalScanner<'_> {
+    /// The NAL unit starting at `pos`, if one plausibly does.
+    pub(crate) fn nal_at(&self, pos: usize) -> Option<Nal> {
+        let prefix = self.data.get(pos..pos + self.length_size)?;
+        let len = prefix.iter().fold(0u64, |acc, &b| acc << 8 | b as u64);
+        let header_len = self.codec.header_len();
+        if len <= header_len as u64 || len > self.max_nal {
+            return None;
+        }
+        let start = pos + self.length_size;
+        let total = self.length_size + len as usize;
+        if pos + total > self.data.len() {
+            return None;
+        }
+        let header = self.data[start];
+        if header & 0x80 != 0 {
+            return None;
+        }
+        let kind = match self.codec {
+            Codec::Avc => header & 0x1F,
+            Codec::Hevc => {
+                if self.data[start + 1] & 0x07 == 0 {
+                    return None;
+                }
+                (header >> 1) & 0x3F
+            }
+        };
+        if !self.codec.plausible(kind) {
+            return None;
+        }
+        let first_in_picture = self.data[start + header_len] & 0x80 != 0;
+        Some(Nal { total, kind, first_in_picture })
+    }
+
+    /// A place to pick up again after unparseable bytes: a NAL unit that can
+    /// open an access unit, followed by another NAL unit or the end.
+    pub(crate) fn resync_at(&self, pos: usize) -> bool {
+        let Some(nal) = self.nal_at(pos) else {
+            return false;
+        };
+        let opens = self.codec.starts_access_unit(nal.kind) || (self.codec.is_vcl(nal.kind) && nal.first_in_picture);
+        let next = pos + nal.total;
+        opens && (next == self.data.len() || self.nal_at(next).is_some())
+    }
+}
+
+#[derive(Debug, Default)]
+struct SampleScan {
+    /// (offset in the mdat payload, size, sync)
+    samples: Vec<(u64, u32, bool)>,
+    skipped_bytes: u64,
+}
+
+/// Split `data` into access units.
+fn scan_samples(scanner: &NalScanner) -> SampleScan {
+    let data = scanner.data;
+    let codec = scanner.codec;
+    let mut scan = SampleScan::default();
+    // (start, end, has_vcl, sync) of the access unit being built
+    let mut unit: Option<(usize, usize, bool, bool)> = None;
+    let finish = |unit: &mut Option<(usize, usize, bool, bool)>, scan: &mut SampleScan| {
+        if let Some((start, end, has_vcl, sync)) = unit.take() {
+            if has_vcl {
+                scan.samples.push((start as u64, (end - start) as u32, sync));
+            } else {
+                scan.skipped_bytes += (end - start) as u64;
+            }
+        }
+    };
+    let mut pos = 0;
+    while pos < data.len() {
+        match scanner.nal_at(pos) {
+            Some(nal) => {
+                let opens_new = unit.is_some_and(|(_, _, has_vcl, _)| {
+                    has_vcl
+                        && (codec.starts_access_unit(nal.kind) || (codec.is_vcl(nal.kind) && nal.first_in_picture))
+                });
+                if opens_new {
+                    finish(&mut unit, &mut scan);
+                }
+                let (start, _, has_vcl, sync) = unit.unwrap_or((pos, pos, false, false));
+                let has_vcl = has_vcl || codec.is_vcl(nal.kind);
+                unit = Some((start, pos + nal.total, has_vcl, sync || codec.is_sync(nal.kind)));
+                pos += nal.total;
+            }
+            None => {
+                finish(&mut unit, &mut scan);
+                let next = (pos + 1..data.len()).find(|&p| scanner.resync_at(p)).unwrap_or(data.len());
+                scan.skipped_bytes += (next - pos) as u64;
+                pos = next;
+            }
+        }
+    }
+    finish(&mut unit, &mut scan);
+    scan
+}
+
+/// Copy of a mvhd/tkhd/mdhd payload with its duration replaced.
+pub(crate) fn with_duration(payload: &[u8], v0_offset: usize, v1_offset: usize, duration: u64) -> Vec<u8> {
+    let mut out = payload.to_vec();
+    if out.first() == Some(&1) {
+        if let Some(field) = out.get_mut(v1_offset..v1_offset + 8) {
+            field.copy_from_slice(&duration.to_be_bytes());
+        }
+    } else if let Some(field) = out.get_mut(v0_offset..v0_offset + 4) {
+        field.copy_from_slice(&(duration.min(u32::MAX as u64) as u32).to_be_bytes());
+    }
+    out
+}
+
+/// mvhd/mdhd timescale.
+pub(crate) fn timescale(payload: &[u8]) -> Option<u32> {
+    be_u32(payload, if payload.first() == Some(&1) { 20 } else { 12 }).filter(|&t| t > 0)
+}
+
+/// Rebuild a container's children; `f` returns the replacement box, or None to drop it.
+pub(crate) fn rebuild_children(payload: &[u8], f: impl Fn(&[u8; 4], &[u8]) -> Option<Vec<u8>>) -> Vec<u8> {
+    children(payload).into_iter().filter_map(|(kind, body)| f(&kind, body)).flatten().collect()
+}
+
+/// New stbl for the found samples, one sample per chunk.
+fn build_stbl(stsd: &[u8], samples: &[(u64, u32, bool)], delta: u32, chunk_base: u64) -> Vec<u8> {
+    let count = samples.len() as u32;
+    let sync: Vec<u32> = (1..).zip(samples).filter(|(_, s)| s.2).map(|(n, _)| n).collect();
+    let mut stss = vec![sync.len() as u32];
+    stss.extend(sync);
+    let mut stsz = vec![0, count];
+    stsz.extend(samples.iter().map(|s| s.1));
+    let offsets: Vec<u64> = samples.iter().map(|s| chunk_base + s.0).collect();
+    let needs_co64 = offsets.last().is_some_and(|&o| o > u32::MAX as u64);
+    let chunk_table = if needs_co64 {
+        let mut payload = vec![0u8; 4];
+        payload.extend(count.to_be_bytes());
+        payload.extend(offsets.iter().flat_map(|o| o.to_be_bytes()));
+        mp4_box(b"co64", &payload)
+    } else {
+        let mut table = vec![count];
+        table.extend(offsets.iter().map(|&o| o as u32));
+        table_box(b"stco", &table, |v: u32| v.to_be_bytes().to_vec())
+    };
+    let u32s = |v: u32| v.to_be_bytes().to_vec();
+    [
+        mp4_box(b"stsd", stsd),
+        table_box(b"stts", &[1, count, delta], u32s),
+        table_box(b"stss", &stss, u32s),
+        table_box(b"stsc", &[1, 1, 1, 1], u32s),
+        table_box(b"stsz", &stsz, u32s),
+        chunk_table,
+    ]
+    .concat()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoovRebuild {
+    /// "h264" or "h265"
+    pub codec: &'static str,
+    pub samples: usize,
+    pub sync_samples: usize,
+    /// mdat bytes that weren't video: interleaved audio, zero-filled gaps, damage
+    pub skipped_bytes: u64,
+    /// Handlers of reference tracks left out, e.g. "soun"
+    pub dropped_tracks: Vec<String>,
+    pub duration_secs: f64,
+    pub bytes_written: u64,
+}
+
+/// The media of a moov-less recovery: the mdat payload if there is an mdat,
+/// else everything after an ftyp, else the whole file.
+fn orphaned_media(data: &[u8]) -> &[u8] {
+    if let Some((pos, size, header)) = crate::find_mp4_box(data, b"mdat") {
+        let declared_end = pos as u64 + size;
+        // A header chunk's mdat may declare the whole original size, or less
+        // than what was appended after it; take what is there.
+        let end = if declared_end < data.len() as u64 && declared_end > (pos + header) as u64 {
+            let after = &data[declared_end as usize..];
+            if crate::find_mp4_box(after, b"moov").is_some() { declared_end as usize } else { data.len() }
+        } else {
+            data.len()
+        };
+        return &data[pos + header..end];
+    }
+    match crate::find_mp4_box(data, b"ftyp") {
+        Some((0, size, _)) if size < data.len() as u64 => &data[size as usize..],
+        _ => data,
+    }
+}
+
+/// Build a playable MP4 from the media in `damaged` using `reference`'s
+/// sample description and timing.
+pub fn rebuild(damaged: &[u8], reference: &[u8]) -> Result<(Vec<u8>, MoovRebuild), String> {
+    let reference = parse_reference(reference)?;
+    let media = orphaned_media(damaged);
+    let scanner = NalScanner {
+        data: media,
+        codec: reference.codec,
+        length_size: reference.length_size,
+        max_nal: (reference.max_sample * 4).max(4 << 20),
+    };
+    let scan = scan_samples(&scanner);
+    if scan.samples.is_empty() {
+        return Err(format!("No {} samples found in the damaged file's media data", reference.codec.name()));
+    }
+    println!(
+        "[moov_rebuild] {} {} samples ({} sync), skipped {} bytes of {}",
+        scan.samples.len(),
+        reference.codec.name(),
+        scan.samples.iter().filter(|s| s.2).count(),
+        scan.skipped_bytes,
+        media.len()
+    );
+
+    let mdia = child(reference.video_trak, b"mdia").ok_or("The reference video track has no mdia")?;
+    let mdhd = child(mdia, b"mdhd").ok_or("The reference video track has no mdhd")?;
+    let mvhd = child(reference.moov, b"mvhd").ok_or("The reference moov has no mvhd")?;
+    let media_timescale = timescale(mdhd).ok_or("The reference mdhd has no timescale")?;
+    let movie_timescale = timescale(mvhd).unwrap_or(1000);
+    let media_duration = scan.samples.len() as u64 * reference.sample_delta as u64;
+    let movie_duration = media_duration * movie_timescale as u64 / media_timescale as u64;
+
+    // ftyp, then a 64-bit mdat header so any size fits, then the moov.
+    let chunk_base = reference.ftyp.len() as u64 + 16;
+    let stsd = child(stbl_of(reference.video_trak).unwrap_or_default(), b"stsd").unwrap_or_default();
+    let stbl = build_stbl(stsd, &scan.samples, reference.sample_delta, chunk_base);
+
+    let minf = child(mdia, b"minf").unwrap_or_default();
+    let new_minf = rebuild_children(minf, |kind, body| match kind {
+        b"stbl" => Some(mp4_box(b"stbl", &stbl)),
+        _ => Some(mp4_box(kind, body)),
+    });
+    let new_mdia = rebuild_children(mdia, |kind, body| match kind {
+        b"mdhd" => Some(mp4_box(kind, &with_duration(body, 16, 24, media_duration))),
+        b"minf" => Some(mp4_box(kind, &new_minf)),
+        _ => Some(mp4_box(kind, body)),
+    });
+    let new_trak = rebuild_children(reference.video_trak, |kind, body| match kind {
+        b"tkhd" => Some(mp4_box(kind, &with_duration(body, 20, 28, movie_duration))),
+        // The edit list describes the reference's timeline, not this one.
+        b"edts" => None,
+        b"mdia" => Some(mp4_box(kind, &new_mdia)),
+        _ => Some(mp4_box(kind, body)),
+    });
+    let video_trak = reference.video_trak;
+    let new_moov = rebuild_children(reference.moov, |kind, body| match kind {
+        b"mvhd" => Some(mp4_box(kind, &with_duration(body, 16, 24, movie_duration))),
+        b"trak" if std::ptr::eq(body, video_trak) => Some(mp4_box(kind, &new_trak)),
+        b"trak" | b"mvex" => None,
+        _ => Some(mp4_box(kind, body)),
+    });
+
+    let mut out = Vec::with_capacity(reference.ftyp.len() + 16 + media.len() + new_moov.len() + 8);
+    out.extend_from_slice(&reference.ftyp);
+    out.extend_from_slice(&1u32.to_be_bytes());
+    out.extend_from_slice(b"mdat");
+    out.extend_from_slice(&(media.len() as u64 + 16).to_be_bytes());
+    out.extend_from_slice(media);
+    out.extend(mp4_box(b"moov", &new_moov));
+
+    let report = MoovRebuild {
+        codec: reference.codec.name(),
+        samples: scan.samples.len(),
+        sync_samples: scan.samples.iter().filter(|s| s.2).count(),
+        skipped_bytes: scan.skipped_bytes,
+        dropped_tracks: reference.dropped_tracks,
+        duration_secs: media_duration as f64 / media_timescale as f64,
+        bytes_written: out.len() as u64,
+    };
+    Ok((out, report))
+}
+
+/// Regenerate the moov of `output` (a recovery whose moov was lost) from an
+/// intact video recorded by the same source. `output` is rewritten in place
+/// and needs a `consent` token covering it.
+#[tauri::command(async)]
+pub fn rebuild_moov(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    output: String,
+    reference_mp4: String,
+    consent: String,
+) -> Result<MoovRebuild, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("rebuilding moov of {}", output));
+    let damaged = std::fs::read(&output).map_err(|e| crate::format_read_error(&output, &e))?;
+    let reference = std::fs::read(&reference_mp4).map_err(|e| crate::format_read_error(&reference_mp4, &e))?;
+    let (rebuilt, report) = rebuild(&damaged, &reference)?;
+    crate::output_file::write_output(&output, &rebuilt)?;
+    println!(
+        "[moov_rebuild] Rebuilt {}: {} samples, {:.1}s, {} bytes",
+        output, report.samples, report.duration_secs, report.bytes_written
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::full;
+
+    /// A NAL unit with a 4-byte length prefix.
+    fn nal(header: u8, first_in_picture: bool, len: usize) -> Vec<u8> {
+        let mut unit = vec![header, if first_in_picture { 0x88 } else { 0x08 }];
+        unit.resize(len, 0x5A);
+        let mut out = (len as u32).to_be_bytes().to_vec();
+        out.extend(unit);
+        out
+    }
+
+    fn reference() -> Vec<u8> {
+        let mut avc1 = vec![0u8; 78];
+        avc1.extend(mp4_box(b"avcC", &[1, 0x64, 0, 0x1F, 0xFF, 0xE0, 0]));
+        let mut stsd = full(&[1]);
+        stsd.extend(mp4_box(b"avc1", &avc1));
+        let stbl = [
+            mp4_box(b"stsd", &stsd),
+            mp4_box(b"stts", &full(&[1, 2, 512])),
+            mp4_box(b"stsc", &full(&[1, 1, 2, 1])),
+            mp4_box(b"stsz", &full(&[0, 2, 300, 200])),
+            mp4_box(b"stco", &full(&[1, 40])),
+        ]
+        .concat();
+        let mut hdlr = vec![0u8; 8];
+        hdlr.extend_from_slice(b"vide");
+        let mdhd = full(&[0, 0, 12800, 1024]);
+        let minf = mp4_box(b"minf", &mp4_box(b"stbl", &stbl));
+        let mdia = mp4_box(b"mdia", &[mp4_box(b"mdhd", &mdhd), mp4_box(b"hdlr", &hdlr), minf].concat());
+        let tkhd = full(&[0, 0, 1, 0, 80]);
+        let video = mp4_box(b"trak", &[mp4_box(b"tkhd", &tkhd), mp4_box(b"edts", &[0; 8]), mdia].concat());
+        let mut sound_hdlr = vec![0u8; 8];
+        sound_hdlr.extend_from_slice(b"soun");
+        let sound = mp4_box(b"trak", &mp4_box(b"mdia", &mp4_box(b"hdlr", &sound_hdlr)));
+        let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &full(&[0, 0, 1000, 80])), video, sound].concat());
+        [mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1"), mp4_box(b"mdat", &[0; 500]), moov].concat()
+    }
+
+    #[test]
+    fn rebuilds_video_samples_around_audio() {
+        // IDR picture with parameter sets, audio, a P picture, then a P
+        // picture split into two slices.
+        let first = [nal(0x67, true, 12), nal(0x68, true, 6), nal(0x65, true, 180)].concat();
+        let audio = vec![0xFFu8; 37];
+        let second = nal(0x41, true, 90);
+        let third = [nal(0x41, true, 50), nal(0x41, false, 40)].concat();
+        let media = [first.clone(), audio, second.clone(), third.clone()].concat();
+        let damaged = [mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1"), mp4_box(b"mdat", &media)].concat();
+
+        let (out, report) = rebuild(&damaged, &reference()).unwrap();
+        assert_eq!((report.codec, report.samples, report.sync_samples, report.skipped_bytes), ("h264", 3, 1, 37));
+        assert_eq!(report.dropped_tracks, ["soun"]);
+        assert_eq!(report.duration_secs, 3.0 * 512.0 / 12800.0);
+
+        let moov = crate::find_mp4_box(&out, b"moov").unwrap();
+        let tracks = crate::mp4_samples::tracks(&out[moov.0 + moov.2..]);
+        assert_eq!(tracks.len(), 1);
+        let sizes: Vec<u32> = tracks[0].samples.iter().map(|s| s.1).collect();
+        assert_eq!(sizes, [first.len() as u32, second.len() as u32, third.len() as u32]);
+        let (offset, size) = tracks[0].samples[1];
+        assert_eq!(&out[offset as usize..offset as usize + size as usize], &second[..]);
+        assert_eq!(crate::mp4_samples::coverage(&out).unwrap().missing_samples, 0);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::legacy_formats::LegacyRepairReport;
+
+// ─── MP3: frame-sync trimming ───────────────────────────────────────────────
+//
+// MP3 has no container to check: the file is an optional ID3v2 tag, a run of
+// self-delimiting MPEG audio frames and an optional 128-byte ID3v1 tag. A
+// cached attachment can start mid-frame (a range request, or bytes of another
+// body in front of it) and usually ends in a partial frame. The frames are
+// walked from the first position where several whole frames follow each
+// other; garbage before it is dropped (the ID3v2 tag is kept), damaged
+// stretches are skipped by resyncing on the next frame of the same stream,
+// and the tail is cut at the last whole frame. A Xing/Info header that still
+// counts the lost frames is corrected so players show the right duration.
+
+const ID3V2_HEADER_SIZE: usize = 10;
+const ID3V1_SIZE: usize = 128;
+/// Whole frames that must follow each other before a sync is trusted.
+const MIN_SYNC_FRAMES: usize = 3;
+/// How far past the ID3v2 tag the first frame is looked for when sniffing.
+const SNIFF_WINDOW: usize = 64 * 1024;
+
+const XING_FRAMES: u32 = 0x1;
+const XING_BYTES: u32 = 0x2;
+
+/// kbit/s by bitrate index 1–14: MPEG-1 layers I–III, then MPEG-2/2.5 layer I
+/// and layers II/III.
+const BITRATES: [[u32; 14]; 5] = [
+    [32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    [32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+    [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+/// MPEG-1 sample rates; MPEG-2 halves them and MPEG-2.5 quarters them.
+const SAMPLE_RATES: [u32; 3] = [44_100, 48_000, 32_000];
+
+/// Version bits (3 = MPEG-1, 2 = MPEG-2, 0 = MPEG-2.5), layer and sample
+/// rate: every frame of one stream has the same.
+type Stream = (u8, u8, u32);
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    stream: Stream,
+    len: usize,
+    samples: u32,
+    mono: bool,
+}
+
+/// Parse the frame header at `data[0]`. Free-format frames (bitrate index 0)
+/// have no length in the header and are not accepted.
+fn frame_header(data: &[u8]) -> Option<Frame> {
+    let h = data.get(..4)?;
+    if h[0] != 0xFF || h[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+    let version = (h[1] >> 3) & 3;
+    let layer_bits = (h[1] >> 1) & 3;
+    let bitrate_index = (h[2] >> 4) as usize;
+    let rate_index = ((h[2] >> 2) & 3) as usize;
+    if version == 1 || layer_bits == 0 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+    let layer = 4 - layer_bits;
+    let mpeg1 = version == 3;
+    let row = match (mpeg1, layer) {
+        (true, _) => layer as usize - 1,
+        (false, 1) => 3,
+        (false, _) => 4,
+    };
+    let bitrate = BITRATES[row][bitrate_index - 1] * 1000;
+    let sample_rate = SAMPLE_RATES[rate_index] / if mpeg1 { 1 } else if version == 2 { 2 } else { 4 };
+    let padding = ((h[2] >> 1) & 1) as u32;
+    let samples = match layer {
+        1 => 384,
+        3 if !mpeg1 => 576,
+        _ => 1152,
+    };
+    let len = if layer == 1 {
+        (12 * bitrate / sample_rate + padding) * 4
+    } else {
+        samples / 8 * bitrate / sample_rate + padding
+    };
+    Some(Frame { stream: (version, layer, sample_rate), len: len as usize, samples, mono: h[3] >> 6 == 3 })
+}
+
+/// Length of the ID3v2 tag at the start of `data` (0 if there is none).
+fn id3v2_len(data: &[u8]) -> usize {
+    if data.len() < ID3V2_HEADER_SIZE || !data.starts_with(b"ID3") || data[6..10].iter().any(|&b| b >= 0x80) {
+        return 0;
+    }
+    let size = data[6..10].iter().fold(0usize, |acc, &b| acc << 7 | b as usize);
+    let footer = if data[5] & 0x10 != 0 { ID3V2_HEADER_SIZE } else { 0 };
+    (ID3V2_HEADER_SIZE + size + footer).min(data.len())
+}
+
+fn is_id3v1_at(data: &[u8], pos: usize) -> bool {
+    data.len() - pos == ID3V1_SIZE && data[pos..].starts_with(b"TAG")
+}
+
+/// The frame at `pos` if it starts a run of whole frames of one stream: at
+/// least [`MIN_SYNC_FRAMES`], or fewer that end exactly at the end of the file.
+fn synced(data: &[u8], pos: usize) -> Option<Frame> {
+    let first = frame_header(&data[pos..])?;
+    let mut p = pos;
+    for _ in 0..MIN_SYNC_FRAMES {
+        match frame_header(&data[p..]) {
+            Some(f) if f.stream == first.stream && p + f.len <= data.len() => p += f.len,
+            _ => return None,
+        }
+        if p == data.len() || is_id3v1_at(data, p) {
+            break;
+        }
+    }
+    Some(first)
+}
+
+/// First synced position in `from..limit`, of `stream` if given.
+fn find_sync(data: &[u8], from: usize, limit: usize, stream: Option<Stream>) -> Option<usize> {
+    (from..limit.min(data.len().saturating_sub(3)))
+        .find(|&p| synced(data, p).is_some_and(|f| stream.is_none_or(|s| s == f.stream)))
+}
+
+/// Whether `data` looks like an MP3: an ID3v2 tag, or a run of frames near
+/// the start.
+pub fn is_mp3(data: &[u8]) -> bool {
+    let from = id3v2_len(data);
+    from > 0 || find_sync(data, from, from + SNIFF_WINDOW, None).is_some()
+}
+
+/// Offset of the Xing/Info tag in a layer III frame, after the side info.
+fn xing_offset(frame: &[u8], header: Frame) -> Option<usize> {
+    let (version, layer, _) = header.stream;
+    if layer != 3 {
+        return None;
+    }
+    let side_info = match (version == 3, header.mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    let at = 4 + side_info;
+    matches!(frame.get(at..at + 4), Some(b"Xing" | b"Info")).then_some(at)
+}
+
+/// Trim an MP3 to its whole frames. The ID3v2 and ID3v1 tags are kept.
+pub fn repair(data: &[u8]) -> (Vec<u8>, LegacyRepairReport) {
+    let mut report = LegacyRepairReport::new("mp3", data.len());
+    let tag_len = id3v2_len(data);
+    let Some(start) = find_sync(data, tag_len, data.len(), None) else {
+        report.action = "unsupported";
+        report.details.push("No run of MPEG audio frames found".to_string());
+        return (data.to_vec(), report);
+    };
+    let first = frame_header(&data[start..]).unwrap();
+    report.codec = Some(["mp1", "mp2", "mp3"][first.stream.1 as usize - 1].to_string());
+    if start > tag_len {
+        report.dropped_bytes += (start - tag_len) as u64;
+        report.details.push(format!("Dropped {} bytes before the first frame", start - tag_len));
+    }
+
+    let mut out = data[..tag_len].to_vec();
+    let mut pos = start;
+    let mut samples = 0u64;
+    let mut resyncs = 0;
+    while pos < data.len() {
+        if is_id3v1_at(data, pos) {
+            out.extend_from_slice(&data[pos..]);
+            pos = data.len();
+            break;
+        }
+        match frame_header(&data[pos..]) {
+            Some(f) if f.stream == first.stream && pos + f.len <= data.len() => {
+                // A frame cut short by other data: the next frame starts inside it.
+                let end = pos + f.len;
+                let followed = end == data.len()
+                    || is_id3v1_at(data, end)
+                    || frame_header(&data[end..]).is_some_and(|n| n.stream == first.stream);
+                if !followed {
+                    if let Some(next) = find_sync(data, pos + 1, end, Some(first.stream)) {
+                        report.dropped_bytes += (next - pos) as u64;
+                        resyncs += 1;
+                        pos = next;
+                        continue;
+                    }
+                }
+                out.extend_from_slice(&data[pos..end]);
+                samples += f.samples as u64;
+                report.units += 1;
+                pos = end;
+            }
+            // A frame of this stream running past the end: the truncated tail.
+            Some(f) if f.stream == first.stream => break,
+            _ => match find_sync(data, pos + 1, data.len(), Some(first.stream)) {
+                Some(next) => {
+                    report.dropped_bytes += (next - pos) as u64;
+                    resyncs += 1;
+                    pos = next;
+                }
+                None => break,
+            },
+        }
+    }
+    if resyncs > 0 {
+        report.details.push(format!("Skipped {} damaged stretch(es) by resyncing on the next frame", resyncs));
+    }
+    if pos < data.len() {
+        report.dropped_bytes += (data.len() - pos) as u64;
+        report.details.push(format!("Cut {} bytes after the last whole frame", data.len() - pos));
+    }
+
+    // The Xing/Info frame carries no audio; its counts cover the frames after it.
+    if let Some(at) = xing_offset(&out[tag_len..], first).map(|at| tag_len + at) {
+        samples -= first.samples as u64;
+        let flags = crate::mp4_samples::be_u32(&out, at + 4).unwrap_or(0);
+        let frames = (report.units - 1) as u32;
+        if flags & XING_FRAMES != 0 && crate::mp4_samples::be_u32(&out, at + 8).is_some_and(|n| n > frames) {
+            out[at + 8..at + 12].copy_from_slice(&frames.to_be_bytes());
+            if flags & XING_BYTES != 0 && at + 16 <= out.len() {
+                let id3v1 = out.len() >= ID3V1_SIZE && is_id3v1_at(&out, out.len() - ID3V1_SIZE);
+                let audio_bytes = (out.len() - tag_len - if id3v1 { ID3V1_SIZE } else { 0 }) as u32;
+                out[at + 12..at + 16].copy_from_slice(&audio_bytes.to_be_bytes());
+            }
+            report.details.push(format!("Set the Xing frame count to the {} frames present", frames));
+        }
+    }
+    report.duration_secs = Some(samples as f64 / first.stream.2 as f64);
+    if out != data {
+        report.action = "repaired";
+    }
+    report.bytes_after = out.len() as u64;
+    (out, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// MPEG-1 layer III, 128 kbit/s, 44.1 kHz, stereo: 417 bytes unpadded.
+    fn frame(fill: u8) -> Vec<u8> {
+        let mut f = vec![fill; 417];
+        f[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        f
+    }
+
+    #[test]
+    fn parses_frame_headers() {
+        let f = frame_header(&frame(0)).unwrap();
+        assert_eq!((f.stream, f.len, f.samples), ((3, 3, 44_100), 417, 1152));
+        // MPEG-2 layer III, 64 kbit/s, 22.05 kHz, padded.
+        let f = frame_header(&[0xFF, 0xF3, 0x82, 0x00]).unwrap();
+        assert_eq!((f.stream, f.len, f.samples), ((2, 3, 22_050), 209, 576));
+        assert!(frame_header(&[0xFF, 0xFB, 0xF0, 0x00]).is_none());
+    }
+
+    #[test]
+    fn strips_leading_garbage_and_the_partial_last_frame() {
+        let tag = [b"ID3\x04\0\0\0\0\0\x02".as_slice(), &[0, 0]].concat();
+        let frames: Vec<u8> = (1..=4).flat_map(frame).collect();
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(ID3V1_SIZE, b' ');
+        let data = [tag.clone(), vec![0xFF; 50], frames.clone(), frame(5)[..200].to_vec()].concat();
+        assert!(is_mp3(&data));
+
+        let (out, report) = repair(&data);
+        assert_eq!(report.action, "repaired");
+        assert_eq!((report.units, report.dropped_bytes), (4, 250));
+        assert_eq!(out, [tag.clone(), frames.clone()].concat());
+        assert!((report.duration_secs.unwrap() - 4.0 * 1152.0 / 44_100.0).abs() < 1e-9);
+
+        // A damaged frame in the middle is skipped; the ID3v1 tag is kept.
+        let data = [tag, frame(1), frame(2), frame(3)[..100].to_vec(), frames, id3v1.clone()].concat();
+        let (out, report) = repair(&data);
+        assert_eq!((report.units, report.dropped_bytes), (6, 100));
+        assert!(out.ends_with(&id3v1));
+        assert!(!is_mp3(&[0u8; 4096]));
+    }
+}
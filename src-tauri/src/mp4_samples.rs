@@ -0,0 +1,426 @@
+use serde::Serialize;
+
+// ─── MP4 sample tables and coverage ─────────────────────────────────────────
+//
+// A reconstruction can succeed byte-wise and still stop playing halfway: the
+// moov describes every sample of the original upload, but the mdat only holds
+// what the cache kept. The sample tables (stsz sizes, stsc chunk layout,
+// stco/co64 chunk offsets) give each sample's position in the file, so the
+// shortfall can be measured directly. A sample counts as recovered if it lies
+// inside the file and isn't all zeros — zero-filled gaps (GapPolicy::PadZeros)
+// keep offsets valid but carry no media.
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackSamples {
+    /// Handler type from hdlr ("vide", "soun", ...)
+    pub handler: String,
+    /// (absolute file offset, size) of each sample, in decode order; offset
+    /// plus size always fits in a u64
+    pub samples: Vec<(u64, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SampleCoverage {
+    /// Sample bytes the moov references, all tracks
+    pub expected_bytes: u64,
+    /// Of those, bytes present in the file
+    pub recovered_bytes: u64,
+    pub total_samples: usize,
+    pub missing_samples: usize,
+    /// Share of expected sample bytes that is missing, 0–100
+    pub shortfall_percent: f64,
+    /// Fraction of the video track present before its first missing sample,
+    /// 0–100: roughly where playback will stop
+    pub playable_percent: f64,
+    /// The same, track by track
+    pub tracks: Vec<TrackCoverage>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TrackCoverage {
+    /// Handler type from hdlr ("vide", "soun", ...)
+    pub handler: String,
+    pub total_samples: usize,
+    pub present_samples: usize,
+    /// Samples that end past the end of the file
+    pub outside_file: usize,
+    /// Samples inside the file but all zeros (a padded gap)
+    pub zero_filled: usize,
+    /// Share of this track's samples present, 0–100
+    pub present_percent: f64,
+    /// Share of this track's samples before its first missing one, 0–100
+    pub playable_percent: f64,
+}
+
+pub(crate) fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+pub(crate) fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Child boxes of a container payload: (type, payload).
+pub(crate) fn children(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut out = Vec::new();
+    let mut pos = 0usize;
+    while pos + 8 <= data.len() {
+        let size = be_u32(data, pos).unwrap_or(0) as u64;
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let (size, header) = match size {
+            1 => match be_u64(data, pos + 8) {
+                Some(large) => (large, 16),
+                None => break,
+            },
+            0 => ((data.len() - pos) as u64, 8),
+            s => (s, 8),
+        };
+        if size < header as u64 || (pos as u64).checked_add(size).is_none_or(|end| end > data.len() as u64) {
+            break;
+        }
+        let end = pos + size as usize;
+        out.push((kind, &data[pos + header..end]));
+        pos = end;
+    }
+    out
+}
+
+pub(crate) fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    children(data).into_iter().find(|(k, _)| k == kind).map(|(_, payload)| payload)
+}
+
+/// Sample positions of one trak, from its stbl tables.
+pub(crate) fn track_samples(trak: &[u8]) -> Option<TrackSamples> {
+    let mdia = child(trak, b"mdia")?;
+    let handler = child(mdia, b"hdlr")
+        .and_then(|h| h.get(8..12))
+        .map(|h| String::from_utf8_lossy(h).to_string())
+        .unwrap_or_default();
+    let stbl = child(child(mdia, b"minf")?, b"stbl")?;
+
+    // stsz: version/flags(4) sample_size(4) sample_count(4) [sizes]
+    let stsz = child(stbl, b"stsz")?;
+    let fixed = be_u32(stsz, 4)?;
+    let count = be_u32(stsz, 8)? as usize;
+    let sizes: Vec<u32> = if fixed != 0 {
+        vec![fixed; count]
+    } else {
+        (0..count).map_while(|i| be_u32(stsz, 12 + 4 * i)).collect()
+    };
+
+    // stco / co64: version/flags(4) entry_count(4) [offsets]
+    let offsets: Vec<u64> = if let Some(stco) = child(stbl, b"stco") {
+        let n = be_u32(stco, 4)? as usize;
+        (0..n).map_while(|i| be_u32(stco, 8 + 4 * i).map(u64::from)).collect()
+    } else {
+        let co64 = child(stbl, b"co64")?;
+        let n = be_u32(co64, 4)? as usize;
+        (0..n).map_while(|i| be_u64(co64, 8 + 8 * i)).collect()
+    };
+
+    // stsc: version/flags(4) entry_count(4) [first_chunk, samples_per_chunk, desc_index]
+    let stsc = child(stbl, b"stsc")?;
+    let runs: Vec<(u32, u32)> = (0..be_u32(stsc, 4)? as usize)
+        .map_while(|i| Some((be_u32(stsc, 8 + 12 * i)?, be_u32(stsc, 12 + 12 * i)?)))
+        .collect();
+
+    let mut samples = Vec::with_capacity(sizes.len());
+    let mut next = sizes.iter();
+    'chunks: for (i, chunk_offset) in offsets.iter().enumerate() {
+        let chunk_no = i as u32 + 1;
+        let per_chunk = runs.iter().rev().find(|(first, _)| *first <= chunk_no).map(|(_, n)| *n).unwrap_or(0);
+        let mut offset = *chunk_offset;
+        for _ in 0..per_chunk {
+            // A hostile co64 can point samples past the end of the address space.
+            let Some((size, end)) = next.next().and_then(|&size| Some((size, offset.checked_add(size as u64)?))) else {
+                break 'chunks;
+            };
+            samples.push((offset, size));
+            offset = end;
+        }
+    }
+    Some(TrackSamples { handler, samples })
+}
+
+/// Sample durations from an stts box, one per sample.
+pub(crate) fn sample_deltas(stts: &[u8], count: usize) -> Vec<u32> {
+    let runs = be_u32(stts, 4).unwrap_or(0) as usize;
+    let mut deltas: Vec<u32> = (0..runs)
+        .map_while(|i| Some((be_u32(stts, 8 + 8 * i)?, be_u32(stts, 12 + 8 * i)?)))
+        .flat_map(|(n, delta)| std::iter::repeat_n(delta, n as usize))
+        .take(count)
+        .collect();
+    let last = deltas.last().copied().unwrap_or(1024);
+    deltas.resize(count, last);
+    deltas
+}
+
+/// Sample tables of every track in `moov` (the box payload).
+pub fn tracks(moov: &[u8]) -> Vec<TrackSamples> {
+    children(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .filter_map(|(_, trak)| track_samples(trak))
+        .collect()
+}
+
+/// Add `delta` to every stco/co64 entry of a whole moov box, for when the
+/// media it points into moved. Nothing changes if a 32-bit stco entry would
+/// overflow.
+pub(crate) fn shift_chunk_offsets(moov: &mut [u8], delta: u64) -> Result<(), String> {
+    let header = if be_u32(moov, 0) == Some(1) { 16 } else { 8 };
+    let base = moov.as_ptr() as usize;
+    // (position in `moov`, entry width) of every chunk offset
+    let mut entries: Vec<(usize, usize)> = Vec::new();
+    for (kind, trak) in children(moov.get(header..).unwrap_or_default()) {
+        let Some(stbl) = (kind == *b"trak").then(|| child(child(child(trak, b"mdia")?, b"minf")?, b"stbl")).flatten()
+        else {
+            continue;
+        };
+        for (kind, table) in children(stbl) {
+            let width = match &kind {
+                b"stco" => 4,
+                b"co64" => 8,
+                _ => continue,
+            };
+            let start = table.as_ptr() as usize - base + 8;
+            let count = be_u32(table, 4).unwrap_or(0) as usize;
+            entries.extend((0..count).map(|i| (start + width * i, width)).filter(|&(at, w)| at + w <= moov.len()));
+        }
+    }
+    let stco_value = |at: usize| be_u32(moov, at).unwrap_or(0) as u64;
+    let overflows = |&&(at, width): &&(usize, usize)| width == 4 && stco_value(at) + delta > u32::MAX as u64;
+    if let Some(&(at, _)) = entries.iter().find(overflows) {
+        return Err(format!("Chunk offset {} + {} no longer fits in a 32-bit stco", stco_value(at), delta));
+    }
+    for (at, width) in entries {
+        if width == 4 {
+            let value = be_u32(moov, at).unwrap_or(0) + delta as u32;
+            moov[at..at + 4].copy_from_slice(&value.to_be_bytes());
+        } else {
+            let value = be_u64(moov, at).unwrap_or(0) + delta;
+            moov[at..at + 8].copy_from_slice(&value.to_be_bytes());
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite the 8-byte header of the mdat at `mdat_start` as a 16-byte one,
+/// for an mdat that has grown past 4 GB. `box_size` is the size with the old
+/// header; the new one is 8 bytes more. Everything after the header moves
+/// 8 bytes on: the chunk offsets of a moov in front of the mdat are shifted
+/// here, a moov appended afterwards needs `shift_chunk_offsets(moov, 8)`.
+pub(crate) fn widen_mdat_header(out: &mut Vec<u8>, mdat_start: usize, box_size: u64) -> Result<(), String> {
+    if let Some((pos, size, _)) = crate::find_mp4_box(&out[..mdat_start], b"moov") {
+        let end = (pos as u64).saturating_add(size).min(mdat_start as u64) as usize;
+        shift_chunk_offsets(&mut out[pos..end], 8)?;
+    }
+    let mut header = 1u32.to_be_bytes().to_vec();
+    header.extend_from_slice(b"mdat");
+    header.extend_from_slice(&(box_size + 8).to_be_bytes());
+    out.splice(mdat_start..mdat_start + 8, header);
+    Ok(())
+}
+
+/// Payload of the moov box in a whole MP4 file, top-level first, then by scan.
+pub(crate) fn find_moov(data: &[u8]) -> Option<&[u8]> {
+    if let Some((pos, size, header)) = crate::find_mp4_box(data, b"moov") {
+        let end = (pos as u64).saturating_add(size).min(data.len() as u64) as usize;
+        return Some(&data[pos + header..end]);
+    }
+    let (pos, size) = crate::scan_for_moov(data)?;
+    Some(crate::box_payload(data, pos, size))
+}
+
+/// Decode-time span, in seconds, of the video samples (the first track's
+/// without video) lying in `start..end` of the file `moov` describes.
+pub(crate) fn time_span(moov: &[u8], start: u64, end: u64) -> Option<(f64, f64)> {
+    let tracks: Vec<(&[u8], TrackSamples)> = children(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .filter_map(|(_, trak)| Some((trak, track_samples(trak)?)))
+        .collect();
+    let (trak, track) = tracks.iter().find(|(_, t)| t.handler == "vide").or(tracks.first())?;
+    let mdia = child(trak, b"mdia")?;
+    let scale = crate::moov_rebuild::timescale(child(mdia, b"mdhd")?)? as f64;
+    let stts = child(child(child(mdia, b"minf")?, b"stbl")?, b"stts")?;
+    let deltas = sample_deltas(stts, track.samples.len());
+
+    let mut time = 0u64;
+    let mut span: Option<(u64, u64)> = None;
+    for (&(offset, size), &delta) in track.samples.iter().zip(&deltas) {
+        if offset < end && offset.checked_add(size as u64).is_some_and(|sample_end| sample_end > start) {
+            span.get_or_insert((time, 0)).1 = time + delta as u64;
+        }
+        time += delta as u64;
+    }
+    span.map(|(first, last)| (first as f64 / scale, last as f64 / scale))
+}
+
+/// How much of the sample data the moov references is actually in `data`,
+/// overall and per track. None if there is no moov or it describes no
+/// samples (e.g. fragmented MP4).
+pub fn coverage(data: &[u8]) -> Option<SampleCoverage> {
+    let tracks = tracks(find_moov(data)?);
+    let mut cov = SampleCoverage {
+        expected_bytes: 0,
+        recovered_bytes: 0,
+        total_samples: 0,
+        missing_samples: 0,
+        shortfall_percent: 0.0,
+        playable_percent: 100.0,
+        tracks: Vec::with_capacity(tracks.len()),
+    };
+    for track in &tracks {
+        let mut track_cov = TrackCoverage {
+            handler: track.handler.clone(),
+            total_samples: track.samples.len(),
+            present_samples: 0,
+            outside_file: 0,
+            zero_filled: 0,
+            present_percent: 100.0,
+            playable_percent: 100.0,
+        };
+        let mut first_missing = None;
+        for (i, &(offset, size)) in track.samples.iter().enumerate() {
+            cov.expected_bytes += size as u64;
+            cov.total_samples += 1;
+            match offset.checked_add(size as u64).filter(|&end| end <= data.len() as u64) {
+                None => track_cov.outside_file += 1,
+                Some(end) if data[offset as usize..end as usize].iter().all(|b| *b == 0) => track_cov.zero_filled += 1,
+                Some(_) => {
+                    cov.recovered_bytes += size as u64;
+                    track_cov.present_samples += 1;
+                    continue;
+                }
+            }
+            cov.missing_samples += 1;
+            first_missing.get_or_insert(i);
+        }
+        if let (Some(i), false) = (first_missing, track.samples.is_empty()) {
+            let total = track.samples.len() as f64;
+            track_cov.present_percent = 100.0 * track_cov.present_samples as f64 / total;
+            track_cov.playable_percent = 100.0 * i as f64 / total;
+            if track.handler == "vide" {
+                cov.playable_percent = track_cov.playable_percent;
+            }
+        }
+        cov.tracks.push(track_cov);
+    }
+    if cov.total_samples == 0 {
+        return None;
+    }
+    cov.shortfall_percent = 100.0 * (cov.expected_bytes - cov.recovered_bytes) as f64 / cov.expected_bytes.max(1) as f64;
+    Some(cov)
+}
+
+/// Sample coverage of an MP4 on disk (a reconstruction or a copied file).
+#[tauri::command(async)]
+pub fn check_mp4_coverage(path: String) -> Result<Option<SampleCoverage>, String> {
+    let _permit = crate::io_limit::acquire_for(&path);
+    let data = std::fs::read(&path).map_err(|e| crate::format_read_error(&path, &e))?;
+    Ok(coverage(&data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{boxed, full};
+
+    /// An MP4 with one video track of `sizes.len()` samples, two per chunk,
+    /// laid out back to back in mdat.
+    fn mp4_with_samples(sizes: &[u32]) -> Vec<u8> {
+        let ftyp = boxed(b"ftyp", b"isom\0\0\0\0");
+        let body: Vec<u8> = sizes.iter().flat_map(|s| vec![0xAB; *s as usize]).collect();
+        let mdat_start = (ftyp.len() + 8) as u32;
+        let mut chunk_offsets = Vec::new();
+        let mut off = mdat_start;
+        for pair in sizes.chunks(2) {
+            chunk_offsets.push(off);
+            off += pair.iter().sum::<u32>();
+        }
+        let mut stsz = vec![0, sizes.len() as u32];
+        stsz.extend_from_slice(sizes);
+        let mut stco = vec![chunk_offsets.len() as u32];
+        stco.extend_from_slice(&chunk_offsets);
+        let stbl = [
+            boxed(b"stts", &full(&[1, sizes.len() as u32, 1000])),
+            boxed(b"stsc", &full(&[1, 1, 2, 1])),
+            boxed(b"stsz", &full(&stsz)),
+            boxed(b"stco", &full(&stco)),
+        ]
+        .concat();
+        let hdlr = boxed(b"hdlr", &[0, 0, 0, 0, 0, 0, 0, 0, b'v', b'i', b'd', b'e']);
+        // 1000 units per second, 1000 per sample: one second each.
+        let mdhd = boxed(b"mdhd", &full(&[0, 0, 1000, 1000 * sizes.len() as u32, 0]));
+        let mdia = boxed(b"mdia", &[mdhd, hdlr, boxed(b"minf", &boxed(b"stbl", &stbl))].concat());
+        let moov = boxed(b"moov", &boxed(b"trak", &mdia));
+        [ftyp, boxed(b"mdat", &body), moov].concat()
+    }
+
+    #[test]
+    fn measures_missing_and_zeroed_samples() {
+        let file = mp4_with_samples(&[100, 200, 300, 400]);
+        let cov = coverage(&file).unwrap();
+        assert_eq!((cov.expected_bytes, cov.recovered_bytes, cov.missing_samples), (1000, 1000, 0));
+
+        // Third sample zero-filled, as a padded gap would be.
+        let mut padded = file.clone();
+        let third = 16 + 8 + 300;
+        padded[third..third + 300].fill(0);
+        let cov = coverage(&padded).unwrap();
+        assert_eq!((cov.recovered_bytes, cov.missing_samples), (700, 1));
+        assert_eq!(cov.shortfall_percent, 30.0);
+        assert_eq!(cov.playable_percent, 50.0);
+        let video = &cov.tracks[0];
+        assert_eq!((video.handler.as_str(), video.present_samples, video.zero_filled), ("vide", 3, 1));
+        assert_eq!(video.present_percent, 75.0);
+        // One second per sample: the zeroed range is the third second.
+        let moov = find_moov(&padded).unwrap();
+        assert_eq!(time_span(moov, third as u64, third as u64 + 300), Some((2.0, 3.0)));
+        assert_eq!(time_span(moov, 300, 700), Some((1.0, 4.0)));
+        assert_eq!(time_span(moov, 5000, 6000), None);
+
+        // Second chunk pointing past the end of the file.
+        let mut cut = file.clone();
+        let stco = cut.windows(4).position(|w| w == b"stco").unwrap();
+        cut[stco + 16..stco + 20].copy_from_slice(&u32::MAX.to_be_bytes());
+        let video = &coverage(&cut).unwrap().tracks[0];
+        assert_eq!((video.present_samples, video.outside_file, video.playable_percent), (2, 2, 50.0));
+
+        // A co64 entry at the very end of the address space ends the track
+        // instead of overflowing.
+        let mut co64 = full(&[2]);
+        co64.extend([24u64, u64::MAX - 150].iter().flat_map(|o| o.to_be_bytes()));
+        let stbl = [boxed(b"stsz", &full(&[0, 4, 100, 100, 100, 100])), boxed(b"stsc", &full(&[1, 1, 2, 1]))];
+        let stbl = boxed(b"stbl", &[stbl.concat(), boxed(b"co64", &co64)].concat());
+        let trak = boxed(b"mdia", &boxed(b"minf", &stbl));
+        assert_eq!(track_samples(&trak).unwrap().samples, [(24, 100), (124, 100), (u64::MAX - 150, 100)]);
+
+        assert!(coverage(b"not an mp4").is_none());
+
+        // Widening the mdat header moves the media 8 bytes on; the trailing
+        // moov follows once its offsets are shifted.
+        let (mdat, mdat_size, _) = crate::find_mp4_box(&file, b"mdat").unwrap();
+        let moov_at = mdat + mdat_size as usize;
+        let mut widened = file[..moov_at].to_vec();
+        widen_mdat_header(&mut widened, mdat, mdat_size).unwrap();
+        let mut moov = file[moov_at..].to_vec();
+        shift_chunk_offsets(&mut moov, 8).unwrap();
+        widened.extend_from_slice(&moov);
+        assert_eq!(crate::find_mp4_box(&widened, b"mdat").unwrap(), (mdat, mdat_size + 8, 16));
+        let cov = coverage(&widened).unwrap();
+        assert_eq!((cov.recovered_bytes, cov.missing_samples), (1000, 0));
+        assert!(shift_chunk_offsets(&mut moov, u32::MAX as u64).is_err());
+
+        // A 3 MB moov behind a 64-bit header is still found.
+        let payload = [boxed(b"mvhd", &[0; 100]), boxed(b"trak", &boxed(b"free", &vec![0; 3 << 20]))].concat();
+        let mut large = vec![0x11; 64];
+        large.extend_from_slice(&1u32.to_be_bytes());
+        large.extend_from_slice(b"moov");
+        large.extend_from_slice(&(payload.len() as u64 + 16).to_be_bytes());
+        large.extend_from_slice(&payload);
+        assert_eq!(crate::scan_for_moov(&large), Some((64, payload.len() + 16)));
+        assert_eq!(find_moov(&large).unwrap().len(), payload.len());
+    }
+}
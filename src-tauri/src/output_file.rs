@@ -0,0 +1,277 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// ─── Output files ──────────────────────────────────────────────────────────
+//
+// Every recovered file is written through `OutputFile`:
+//   - data goes to a sibling `<name>.cachephoenix-part` file and is renamed into
+//     place only when complete, so sync clients and users never see a torn file;
+//   - inside a cloud-synced folder (Dropbox, OneDrive, iCloud Drive, ...) the
+//     data is written in CLOUD_CHUNK_SIZE pieces, each flushed to disk, and
+//     writes/renames are retried when the sync client briefly locks the file.
+
+/// Suffix for in-progress output files.
+pub(crate) const PART_SUFFIX: &str = ".cachephoenix-part";
+/// Chunk size for flushed writes into cloud-synced folders.
+const CLOUD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// Attempts per chunk write / final rename before giving up.
+const MAX_ATTEMPTS: u64 = 5;
+
+/// Path fragments (lowercase, '/'-separated) that identify a cloud-synced folder,
+/// paired with the provider name shown to the user.
+const CLOUD_PATH_MARKERS: &[(&str, &str)] = &[
+    ("/library/mobile documents/com~apple~clouddocs", "iCloud Drive"),
+    ("/icloud drive", "iCloud Drive"),
+    ("/icloudrive", "iCloud Drive"),
+    ("/library/cloudstorage/onedrive", "OneDrive"),
+    ("/library/cloudstorage/googledrive", "Google Drive"),
+    ("/library/cloudstorage/dropbox", "Dropbox"),
+    ("/library/cloudstorage/box", "Box"),
+    ("/onedrive", "OneDrive"),
+    ("/dropbox", "Dropbox"),
+    ("/google drive", "Google Drive"),
+    ("/my drive", "Google Drive"),
+    ("/box sync", "Box"),
+    ("/pcloud drive", "pCloud"),
+    ("/megasync", "MEGA"),
+    ("/nextcloud", "Nextcloud"),
+    ("/owncloud", "ownCloud"),
+    ("/yandex.disk", "Yandex Disk"),
+];
+
+/// Marker files sync clients drop at the root of the synced folder.
+const CLOUD_MARKER_FILES: &[(&str, &str)] = &[
+    (".dropbox", "Dropbox"),
+    (".dropbox.cache", "Dropbox"),
+    (".sync_config", "Sync.com"),
+    (".owncloudsync.log", "ownCloud"),
+    (".nextcloudsync.log", "Nextcloud"),
+];
+
+/// Detect whether `path` lives inside a cloud-synced folder.
+/// Returns the provider name, e.g. "OneDrive".
+pub fn detect_cloud_provider(path: &Path) -> Option<&'static str> {
+    let normalized = path.to_string_lossy().replace('\\', "/").to_lowercase();
+
+    // OneDrive on Windows exports its roots via environment variables.
+    for var in ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"] {
+        if let Ok(root) = std::env::var(var) {
+            let root = root.replace('\\', "/").to_lowercase();
+            if !root.is_empty() && normalized.starts_with(&root) {
+                return Some("OneDrive");
+            }
+        }
+    }
+
+    for (marker, provider) in CLOUD_PATH_MARKERS {
+        // Match whole path components: "/dropbox" must not match "/dropbox-old".
+        let mut search = normalized.as_str();
+        while let Some(pos) = search.find(marker) {
+            let after = &search[pos + marker.len()..];
+            if after.is_empty()
+                || after.starts_with('/')
+                || after.starts_with(" - ") // "OneDrive - Contoso", "Dropbox (Team)"
+                || after.starts_with(" (")
+                || after.starts_with('-') // macOS CloudStorage "OneDrive-Personal"
+            {
+                return Some(provider);
+            }
+            search = &search[pos + marker.len()..];
+        }
+    }
+
+    for dir in path.ancestors() {
+        for (file, provider) in CLOUD_MARKER_FILES {
+            if dir.join(file).exists() {
+                return Some(provider);
+            }
+        }
+    }
+    None
+}
+
+/// Errors worth retrying: sync clients hold short-lived locks on files they upload.
+fn is_transient(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    if matches!(e.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut) {
+        return true;
+    }
+    match e.raw_os_error() {
+        // Windows: ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+        #[cfg(windows)]
+        Some(5) | Some(32) | Some(33) => true,
+        // Unix: EAGAIN, EBUSY, ETXTBSY
+        #[cfg(unix)]
+        Some(11) | Some(16) | Some(26) => true,
+        _ => false,
+    }
+}
+
+fn retry_delay(attempt: u64) -> std::time::Duration {
+    std::time::Duration::from_millis(200 * attempt)
+}
+
+/// An output file being written. Call `finish` to publish it; dropping it
+/// without finishing removes the partial file.
+pub struct OutputFile {
+    final_path: PathBuf,
+    part_path: PathBuf,
+    file: Option<File>,
+    cloud_provider: Option<&'static str>,
+    written: u64,
+    /// Nothing was at `final_path` before; the file is this job's output
+    is_new: bool,
+}
+
+impl OutputFile {
+    /// Create the output (and its parent directories).
+    pub fn create(path: &str) -> Result<Self, String> {
+        let final_path = PathBuf::from(path);
+        if let Some(parent) = final_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
+        }
+        let cloud_provider = detect_cloud_provider(&final_path);
+        if let Some(provider) = cloud_provider {
+            eprintln!("[output] {} is inside a {} folder — using chunked flushed writes", path, provider);
+        }
+        let file_name = final_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| format!("Not a file path: {}", path))?;
+        let part_path = final_path.with_file_name(format!("{}{}", file_name, PART_SUFFIX));
+        let is_new = !final_path.exists();
+        let file = File::create(&part_path)
+            .map_err(|e| format!("Failed to create {}: {}", part_path.display(), e))?;
+        Ok(Self {
+            final_path,
+            part_path,
+            file: Some(file),
+            cloud_provider,
+            written: 0,
+            is_new,
+        })
+    }
+
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), String> {
+        crate::job_outputs::check_cancelled(&self.final_path)?;
+        if self.cloud_provider.is_none() {
+            let file = self.file.as_mut().ok_or("Output already finished")?;
+            file.write_all(data).map_err(|e| format!("Failed to write: {}", e))?;
+            self.written += data.len() as u64;
+            return Ok(());
+        }
+        for chunk in data.chunks(CLOUD_CHUNK_SIZE) {
+            self.write_chunk_with_retry(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Write one chunk, flushed to disk. On a transient failure, rewind to the
+    /// chunk start, drop whatever partially landed, and try again.
+    fn write_chunk_with_retry(&mut self, chunk: &[u8]) -> Result<(), String> {
+        let start = self.written;
+        let file = self.file.as_mut().ok_or("Output already finished")?;
+        let mut attempt = 0;
+        loop {
+            let result = file
+                .seek(SeekFrom::Start(start))
+                .and_then(|_| file.set_len(start))
+                .and_then(|_| file.write_all(chunk))
+                .and_then(|_| file.sync_data());
+            match result {
+                Ok(()) => {
+                    self.written += chunk.len() as u64;
+                    return Ok(());
+                }
+                Err(e) if is_transient(&e) && attempt + 1 < MAX_ATTEMPTS => {
+                    attempt += 1;
+                    eprintln!(
+                        "[output] Write to {} failed at offset {} (attempt {}): {} — retrying",
+                        self.part_path.display(), start, attempt, e
+                    );
+                    std::thread::sleep(retry_delay(attempt));
+                }
+                Err(e) => return Err(format!("Failed to write {}: {}", self.final_path.display(), e)),
+            }
+        }
+    }
+
+    /// Flush, close, and move the finished file into place. Returns bytes written.
+    pub fn finish(mut self) -> Result<u64, String> {
+        crate::job_outputs::check_cancelled(&self.final_path)?;
+        let file = self.file.as_mut().ok_or("Output already finished")?;
+        file.flush().map_err(|e| format!("Failed to flush: {}", e))?;
+        file.sync_all().map_err(|e| format!("Failed to flush: {}", e))?;
+        // Closed from here on, so Drop no longer cleans up: a failed rename does.
+        drop(self.file.take());
+
+        let mut attempt = 0;
+        loop {
+            match std::fs::rename(&self.part_path, &self.final_path) {
+                Ok(()) => {
+                    crate::job_outputs::record(&self.final_path, self.is_new);
+                    return Ok(self.written);
+                }
+                Err(e) if is_transient(&e) && attempt + 1 < MAX_ATTEMPTS => {
+                    attempt += 1;
+                    eprintln!(
+                        "[output] Rename to {} failed (attempt {}): {} — retrying",
+                        self.final_path.display(), attempt, e
+                    );
+                    std::thread::sleep(retry_delay(attempt));
+                }
+                Err(e) => {
+                    let _ = std::fs::remove_file(&self.part_path);
+                    return Err(format!(
+                        "Failed to move {} into place: {}",
+                        self.final_path.display(),
+                        e
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        // Not finished (error or early return): don't leave partial files behind.
+        if self.file.take().is_some() {
+            let _ = std::fs::remove_file(&self.part_path);
+        }
+    }
+}
+
+/// Write a whole buffer to `path` through `OutputFile`.
+pub fn write_output(path: &str, data: &[u8]) -> Result<u64, String> {
+    let mut out = OutputFile::create(path)?;
+    out.write_all(data)?;
+    out.finish()
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutputFolderCheck {
+    pub path: String,
+    /// Sync provider name if the folder is cloud-synced
+    pub cloud_provider: Option<&'static str>,
+    /// User-facing warning, if any
+    pub warning: Option<String>,
+}
+
+/// Check an output folder before recovery starts so the UI can warn about
+/// cloud-synced destinations.
+#[tauri::command]
+pub fn check_output_folder(path: String) -> OutputFolderCheck {
+    let cloud_provider = detect_cloud_provider(Path::new(&path));
+    let warning = cloud_provider.map(|provider| {
+        format!(
+            "This folder is synced by {}. Large recovered videos will be uploaded as they are written, \
+             which can be slow and may create sync conflicts. Consider a local folder outside {} and \
+             moving the results afterwards.",
+            provider, provider
+        )
+    });
+    OutputFolderCheck { path, cloud_provider, warning }
+}
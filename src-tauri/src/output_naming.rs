@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// ─── Output file naming ─────────────────────────────────────────────────────
+//
+// Recovered files were named after the scan ("Video 3.mp4") or the cache
+// hash, which says nothing about what the file was. The server usually said:
+// `Content-Disposition: attachment; filename="holiday.mp4"`, or failing that
+// the last segment of the URL (`.../attachments/1/2/holiday.mp4`). The name
+// is made safe for every platform's filesystem, given the expected extension
+// if it has none, and never overwrites an existing file — a clash becomes
+// "holiday (2).mp4".
+
+/// Longest name kept, in bytes; leaves room for a " (NN)" suffix under the
+/// usual 255-byte filesystem limit.
+const MAX_NAME_BYTES: usize = 200;
+
+/// Names Windows reserves regardless of extension.
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "lpt1",
+    "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Where the output name comes from. Each policy falls through to the next
+/// source when its own is missing: Content-Disposition → URL → fallback.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamingPolicy {
+    ContentDisposition,
+    Url,
+    Fallback,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveredEntry {
+    pub path: String,
+    pub name: String,
+    /// "content_disposition", "url" or "fallback"
+    pub name_source: &'static str,
+    pub bytes: u64,
+}
+
+/// Make `name` a valid file name on Windows, macOS and Linux. None if
+/// nothing usable is left.
+pub fn safe_name(name: &str) -> Option<String> {
+    let base = crate::content_disposition::sanitize(name)?;
+    let replaced: String =
+        base.chars().map(|c| if matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*') { '_' } else { c }).collect();
+    // Windows drops trailing dots and spaces, which would make two names collide.
+    let mut name = replaced.trim_end_matches(['.', ' ']).trim_start().to_string();
+    if name.is_empty() || name.chars().all(|c| c == '_' || c == '.') {
+        return None;
+    }
+    let stem = name.split('.').next().unwrap_or("").to_ascii_lowercase();
+    if RESERVED.contains(&stem.as_str()) {
+        name.insert(0, '_');
+    }
+    if name.len() > MAX_NAME_BYTES {
+        let (stem, ext) = split_extension(&name);
+        let keep = MAX_NAME_BYTES.saturating_sub(ext.len());
+        let cut = (0..=keep).rev().find(|&i| stem.is_char_boundary(i)).unwrap_or(0);
+        name = format!("{}{}", &stem[..cut], ext);
+    }
+    Some(name)
+}
+
+/// ("name", ".ext"); the extension is empty for "name" and ".hidden".
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 && i < name.len() - 1 => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    }
+}
+
+/// The last non-empty path segment of `url`, percent-decoded.
+pub fn url_filename(url: &str) -> Option<String> {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    let path = match without_query.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => without_query,
+    };
+    let segment = path.rsplit('/').find(|s| !s.is_empty())?;
+    safe_name(&crate::blockfile_index::url_decode(segment))
+}
+
+/// Append `extension` (with or without the dot) when `name` has none.
+pub fn with_extension(name: &str, extension: Option<&str>) -> String {
+    let ext = extension.map(|e| e.trim_start_matches('.')).filter(|e| !e.is_empty());
+    match ext {
+        Some(ext) if split_extension(name).1.is_empty() => format!("{}.{}", name, ext),
+        _ => name.to_string(),
+    }
+}
+
+/// `dir/name`, or `dir/stem (N).ext` with the lowest N ≥ 2 that doesn't exist.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let first = dir.join(name);
+    if !first.exists() {
+        return first;
+    }
+    let (stem, ext) = split_extension(name);
+    (2u32..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .unwrap_or(first)
+}
+
+/// Pick a name for an entry with the given headers ("Name: value" lines) and
+/// URL. Returns the name and which source it came from.
+pub fn choose_name(
+    policy: NamingPolicy,
+    headers: &[String],
+    url: Option<&str>,
+    fallback: &str,
+) -> (String, &'static str) {
+    if policy == NamingPolicy::ContentDisposition {
+        let disposition = headers.iter().find_map(|h| {
+            let (name, value) = h.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("content-disposition").then_some(value)
+        });
+        if let Some(name) = disposition.and_then(crate::content_disposition::filename).and_then(|n| safe_name(&n)) {
+            return (name, "content_disposition");
+        }
+    }
+    if policy != NamingPolicy::Fallback {
+        if let Some(name) = url.and_then(url_filename) {
+            return (name, "url");
+        }
+    }
+    (safe_name(fallback).unwrap_or_else(|| "recovered".to_string()), "fallback")
+}
+
+/// Recover the entry `src` belongs to into `output_dir`, naming the file by
+/// `naming`. `fallback_name` defaults to the entry's hash; `extension` is
+/// added when the chosen name has none. The body is decoded unless
+/// `decode_content` is false. Never overwrites an existing file. Requires a `consent` token covering
+/// `output_dir`.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
+pub fn recover_entry(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    src: String,
+    output_dir: String,
+    naming: NamingPolicy,
+    fallback_name: Option<String>,
+    extension: Option<String>,
+    decode_content: Option<bool>,
+    consent: String,
+) -> Result<RecoveredEntry, String> {
+    let output_dir = state.check(&consent, &output_dir)?;
+    recover_named(src, output_dir, naming, fallback_name, extension, decode_content)
+}
+
+fn recover_named(
+    src: String,
+    output_dir: String,
+    naming: NamingPolicy,
+    fallback_name: Option<String>,
+    extension: Option<String>,
+    decode_content: Option<bool>,
+) -> Result<RecoveredEntry, String> {
+    let _job = crate::crash::job(format!("recovering {}", src));
+    let entry = crate::entry::load_entry(&src, decode_content.unwrap_or(true))?;
+    if entry.body_source.is_none() {
+        return Err(format!("Entry has no body in any of its files: {}", src));
+    }
+    let url = crate::read_simple_cache_key(&src).map(|key| crate::cache_key::effective_url(&key).to_string());
+    let hash: String = Path::new(&src).file_name().and_then(|n| n.to_str()).unwrap_or("").chars().take(16).collect();
+    let fallback = fallback_name.unwrap_or(hash);
+    let (name, name_source) = choose_name(naming, &entry.headers, url.as_deref(), &fallback);
+    let name = with_extension(&name, extension.as_deref());
+
+    let dir = PathBuf::from(crate::path_display::expand_path(&output_dir));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = unique_path(&dir, &name).to_string_lossy().to_string();
+    let bytes = crate::output_file::write_output(&path, &entry.body)?;
+    println!("[naming] {} -> {} (name from {})", src, path, name_source);
+    let name = Path::new(&path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(name);
+    Ok(RecoveredEntry { path, name, name_source, bytes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_from_disposition_then_url_and_dedupes() {
+        let disposition = "Content-Disposition: attachment; filename=\"a:b?.mp4\"";
+        let headers = vec!["HTTP/1.1 200 OK".to_string(), disposition.to_string()];
+        let url = Some("https://cdn.discordapp.com/attachments/1/2/clip%20one.mp4?ex=1&hm=2");
+        let by_disposition = choose_name(NamingPolicy::ContentDisposition, &headers, url, "x");
+        assert_eq!(by_disposition, ("a_b_.mp4".into(), "content_disposition"));
+        assert_eq!(choose_name(NamingPolicy::Url, &headers, url, "x"), ("clip one.mp4".into(), "url"));
+        let no_segment = choose_name(NamingPolicy::Url, &[], Some("https://example.com/"), "0123abcd");
+        assert_eq!(no_segment, ("0123abcd".into(), "fallback"));
+        assert_eq!(safe_name("CON.txt").as_deref(), Some("_CON.txt"));
+        assert_eq!(safe_name("...").as_deref(), None);
+        assert_eq!(with_extension("watch", Some(".mp4")), "watch.mp4");
+        assert_eq!(with_extension("clip.webm", Some("mp4")), "clip.webm");
+
+        let dir = std::env::temp_dir().join(format!("cachephoenix-naming-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir.join("cache")).unwrap();
+        let headers = ["HTTP/1.1 200 OK", "Content-Type: video/mp4"];
+        let url = "https://cdn.discordapp.com/attachments/1/2/holiday.mp4";
+        let src = cache.add_entry(url, &headers, b"body").unwrap();
+        let out = dir.join("out").to_string_lossy().to_string();
+        let naming = NamingPolicy::ContentDisposition;
+        let first = recover_named(src.clone(), out.clone(), naming, None, None, None).unwrap();
+        let second = recover_named(src, out, naming, None, None, None).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!((first.name.as_str(), first.name_source, first.bytes), ("holiday.mp4", "url", 4));
+        assert_eq!(second.name, "holiday (2).mp4");
+    }
+}
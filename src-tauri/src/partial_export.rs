@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+
+use crate::compare::normalize;
+
+// ─── Partial export with retry metadata ─────────────────────────────────────
+//
+// An entry whose ranges are only partly readable (evicted ranges, or a `_s`
+// file Discord holds locked) is still exported: present bytes land at their
+// offsets and holes stay zero, like a padded reconstruction. Next to such an
+// output a `<name>.cachephoenix-part.json` file records the present and
+// missing ranges and the cache files they came from. `merge_partial` later
+// re-reads those files (plus any new ones), fills only the holes, and drops
+// the metadata once nothing is missing. Every source has to be an entry of
+// the URL the output was exported from; a file of another URL would fill the
+// holes with someone else's bytes. The URL is only written into the metadata
+// when the user keeps source URLs; otherwise merges recheck it from the keys
+// of the recorded cache files.
+//
+// The plain `.cachephoenix-part` suffix is taken by in-progress writes
+// (`output_file`), hence the `.json`.
+
+/// Suffix of the metadata file written next to an incomplete output.
+pub const PART_META_SUFFIX: &str = ".cachephoenix-part.json";
+const META_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PartMeta {
+    pub version: u32,
+    /// URL of the entry (isolation prefix stripped); None in older metadata
+    /// or when source URLs are kept out of exports
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Cache files the output was built from; re-read on merge
+    pub sources: Vec<String>,
+    /// Sources that could not be read last time (locked, permission denied)
+    pub unreadable: Vec<String>,
+    /// Full resource size (Content-Range total / Content-Length), if known
+    pub expected_size: Option<u64>,
+    /// Byte ranges of the output holding real data, as [start, end)
+    pub present: Vec<[u64; 2]>,
+    /// Byte ranges still missing (zero in the output, or past its end)
+    pub missing: Vec<[u64; 2]>,
+    /// Unix seconds of the last export or merge
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartialExport {
+    pub output: String,
+    pub bytes_written: u64,
+    /// Bytes of real data in the output
+    pub present_bytes: u64,
+    /// Bytes this call added that the output didn't have before
+    pub bytes_added: u64,
+    pub missing: Vec<[u64; 2]>,
+    pub unreadable: Vec<String>,
+    /// Nothing missing and every source read
+    pub complete: bool,
+    /// The metadata file, if the output is still incomplete
+    pub meta_path: Option<String>,
+}
+
+pub fn meta_path(output: &str) -> String {
+    format!("{}{}", output, PART_META_SUFFIX)
+}
+
+/// Gaps in sorted, merged `present` within [0, len).
+fn complement(present: &[[u64; 2]], len: u64) -> Vec<[u64; 2]> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    for r in present {
+        if r[0] >= len {
+            break;
+        }
+        if r[0] > pos {
+            out.push([pos, r[0]]);
+        }
+        pos = pos.max(r[1]);
+    }
+    if pos < len {
+        out.push([pos, len]);
+    }
+    out
+}
+
+/// Output bytes plus the ranges of them that hold real data.
+#[derive(Default)]
+struct Assembly {
+    data: Vec<u8>,
+    present: Vec<[u64; 2]>,
+    unreadable: Vec<String>,
+    added: u64,
+}
+
+impl Assembly {
+    /// Copy the bytes of `ranges` from `body` where nothing is present yet.
+    /// Bytes already in the output are never overwritten.
+    fn fill(&mut self, body: &[u8], ranges: &[[u64; 2]]) {
+        for &[start, end] in ranges {
+            let end = end.min(body.len() as u64);
+            for [lo, hi] in complement(&self.present, end) {
+                let lo = lo.max(start);
+                if lo >= hi {
+                    continue;
+                }
+                if (self.data.len() as u64) < hi {
+                    self.data.resize(hi as usize, 0);
+                }
+                self.data[lo as usize..hi as usize].copy_from_slice(&body[lo as usize..hi as usize]);
+                self.added += hi - lo;
+            }
+            self.present.push([start, end]);
+            self.present = normalize(std::mem::take(&mut self.present));
+        }
+    }
+
+    fn add_source(&mut self, path: &str) {
+        let _permit = crate::io_limit::acquire_for(path);
+        match crate::compare::load(path) {
+            Ok(body) => self.fill(&body.data, &body.ranges),
+            Err(e) => {
+                eprintln!("[partial] {} unreadable, left for a later merge: {}", path, e);
+                self.unreadable.push(path.to_string());
+            }
+        }
+    }
+}
+
+/// Full size of the resource from the Simple Cache response headers: the
+/// Content-Range total, else Content-Length. For a `_s` source the headers
+/// are in its `_0` sibling.
+fn expected_size(sources: &[String]) -> Option<u64> {
+    sources.iter().find_map(|source| {
+        let headers_file = if crate::is_simple_cache_sparse(source) {
+            format!("{}0", &source[..source.len() - 1])
+        } else {
+            source.clone()
+        };
+        let data = std::fs::read(&headers_file).ok()?;
+        let headers = crate::extract_simple_cache_headers(&data)?;
+        crate::find_stream0_header(&headers, "content-range:")
+            .and_then(|range| range.rsplit('/').next()?.trim().parse().ok())
+            .or_else(|| crate::find_stream0_header(&headers, "content-length:")?.parse().ok())
+    })
+}
+
+/// The URL of a Simple Cache entry file, None if its key can't be read.
+fn url_of(source: &str) -> Option<String> {
+    let key = crate::read_simple_cache_key(source)?;
+    Some(crate::cache_key::effective_url(&key).to_string())
+}
+
+/// The URL `sources` share, or an error naming the first source of another
+/// URL. Sources whose key can't be read (locked, not Simple Cache) are not
+/// held against it.
+fn common_url(known: Option<String>, sources: &[String]) -> Result<Option<String>, String> {
+    let mut url = known;
+    for source in sources {
+        let Some(found) = url_of(source) else {
+            continue;
+        };
+        match &url {
+            Some(expected) if *expected != found => {
+                return Err(format!("{} belongs to {}, not to {}", source, found, expected));
+            }
+            Some(_) => {}
+            None => url = Some(found),
+        }
+    }
+    Ok(url)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Write the output (unless `write_data` is false) and create, update or
+/// remove its metadata file.
+fn finish(
+    output: &str,
+    asm: Assembly,
+    url: Option<String>,
+    sources: Vec<String>,
+    expected_size: Option<u64>,
+    write_data: bool,
+) -> Result<PartialExport, String> {
+    let len = expected_size.unwrap_or(0).max(asm.data.len() as u64);
+    let missing = complement(&asm.present, len);
+    let bytes_written = if write_data {
+        crate::output_file::write_output(output, &asm.data)?
+    } else {
+        asm.data.len() as u64
+    };
+    let complete = missing.is_empty() && asm.unreadable.is_empty();
+    let meta_file = meta_path(output);
+    let meta_path = if complete {
+        if std::path::Path::new(&meta_file).exists() {
+            std::fs::remove_file(&meta_file).map_err(|e| format!("Failed to remove {}: {}", meta_file, e))?;
+        }
+        None
+    } else {
+        let meta = PartMeta {
+            version: META_VERSION,
+            url,
+            sources,
+            unreadable: asm.unreadable.clone(),
+            expected_size,
+            present: asm.present.clone(),
+            missing: missing.clone(),
+            updated_at: unix_now(),
+        };
+        let json = serde_json::to_vec_pretty(&meta).map_err(|e| format!("Failed to encode part metadata: {}", e))?;
+        crate::output_file::write_output(&meta_file, &json)?;
+        Some(meta_file)
+    };
+    let present_bytes = asm.present.iter().map(|r| r[1] - r[0]).sum();
+    println!(
+        "[partial] {}: {} of {} bytes present, {} added, {} missing range(s), {} unreadable source(s)",
+        output, present_bytes, len, asm.added, missing.len(), asm.unreadable.len()
+    );
+    Ok(PartialExport {
+        output: output.to_string(),
+        bytes_written,
+        present_bytes,
+        bytes_added: asm.added,
+        missing,
+        unreadable: asm.unreadable,
+        complete,
+        meta_path,
+    })
+}
+
+fn merge_into(output: &str, sources: Vec<String>, include_url: bool) -> Result<PartialExport, String> {
+    let meta_file = meta_path(output);
+    let raw = std::fs::read(&meta_file).map_err(|e| format!("Failed to read {}: {}", meta_file, e))?;
+    let meta: PartMeta =
+        serde_json::from_slice(&raw).map_err(|e| format!("Failed to parse {}: {}", meta_file, e))?;
+    let known = meta.url.or_else(|| meta.sources.iter().find_map(|source| url_of(source)));
+    let url = common_url(known, &sources)?;
+    let data = crate::read_with_lock_retry(output).map_err(|e| crate::format_read_error(output, &e))?;
+    let mut asm = Assembly { data, present: meta.present, ..Default::default() };
+
+    let mut all = meta.sources;
+    for source in sources {
+        if !all.contains(&source) {
+            all.push(source);
+        }
+    }
+    for source in &all {
+        asm.add_source(source);
+    }
+    let expected = meta.expected_size.or_else(|| expected_size(&all));
+    let changed = asm.added > 0;
+    finish(output, asm, url.filter(|_| include_url), all, expected, changed)
+}
+
+/// Export an entry from its cache files even if some ranges are missing or
+/// unreadable. If the result is incomplete, `<output>.cachephoenix-part.json`
+/// records what is there for `merge_partial`, with the URL only if
+/// `include_url`. Requires a `consent` token covering `output`.
+#[tauri::command(async)]
+pub fn export_partial(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    sources: Vec<String>,
+    output: String,
+    include_url: bool,
+    consent: String,
+) -> Result<PartialExport, String> {
+    let output = state.check(&consent, &output)?;
+    export_available(sources, output, include_url)
+}
+
+fn export_available(sources: Vec<String>, output: String, include_url: bool) -> Result<PartialExport, String> {
+    let _job = crate::crash::job(format!("exporting {}", output));
+    let url = common_url(None, &sources)?;
+    let mut asm = Assembly::default();
+    for source in &sources {
+        asm.add_source(source);
+    }
+    if asm.present.is_empty() {
+        return Err(format!("No readable data in {} source file(s) for {}", sources.len(), output));
+    }
+    let expected = expected_size(&sources);
+    finish(&output, asm, url.filter(|_| include_url), sources, expected, true)
+}
+
+/// Fill the holes of a partial export from its recorded cache files plus
+/// `sources` (e.g. a `_s` file that was locked, or a newly cached range).
+/// Requires a `consent` token covering `output`.
+#[tauri::command(async)]
+pub fn merge_partial(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    output: String,
+    sources: Vec<String>,
+    include_url: bool,
+    consent: String,
+) -> Result<PartialExport, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("merging into {}", output));
+    merge_into(&output, sources, include_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureCache;
+
+    #[test]
+    fn exports_with_holes_then_merges_new_ranges() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-partial-{}", std::process::id()));
+        let cache = FixtureCache::create(&dir.join("cache")).unwrap();
+        let key = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        cache.add_entry(key, &["HTTP/1.1 206", "Content-Range: bytes 0-99/300"], b"").unwrap();
+        let first = cache.add_sparse_entry(key, &[(0, &[1u8; 100]), (200, &[3u8; 50])]).unwrap();
+        let output = dir.join("out/clip.mp4").to_string_lossy().to_string();
+
+        // URLs kept out of exports: the metadata doesn't name it.
+        let export = export_available(vec![first.clone()], output.clone(), false).unwrap();
+        assert_eq!(export.missing, vec![[100, 200], [250, 300]]);
+        assert!(!export.complete);
+        let meta = std::fs::read_to_string(export.meta_path.as_ref().unwrap()).unwrap();
+        assert!(!meta.contains("clip.mp4"));
+
+        // A file of another URL is still refused.
+        let other = cache.add_sparse_entry("https://cdn.discordapp.com/other.mp4", &[(0, &[7u8; 300])]).unwrap();
+        assert!(merge_into(&output, vec![other], true).unwrap_err().contains("belongs to"));
+
+        // Later the cache holds the rest under another isolation prefix;
+        // bytes already exported are kept.
+        let later = cache
+            .add_sparse_entry(&format!("_dk_s_ 1/0/{}", key), &[(0, &[9u8; 150]), (150, &[2u8; 150])])
+            .unwrap();
+        let merged = merge_into(&output, vec![later], true).unwrap();
+        let data = std::fs::read(&output).unwrap();
+        let meta_left = std::path::Path::new(&meta_path(&output)).exists();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!((merged.bytes_added, merged.complete, meta_left), (150, true, false));
+        assert_eq!(data.len(), 300);
+        assert_eq!((data[99], data[100], data[249], data[299]), (1, 9, 3, 2));
+    }
+}
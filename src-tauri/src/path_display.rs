@@ -0,0 +1,116 @@
+// ─── Path display ───────────────────────────────────────────────────────────
+//
+// Full cache paths are long and mostly the same user folder. For display,
+// the home folder (and on Windows the AppData folders) are abbreviated the
+// way users write them: `~/Library/Application Support/discord/...`,
+// `%APPDATA%\discord\...`. `expand_path` reverses that, so a path pasted in
+// either form reaches the filesystem as an absolute path. `%VAR%` is only
+// expanded on Windows; elsewhere `%` is an ordinary file name character.
+
+/// Folders abbreviated for display, most specific first.
+fn prefixes() -> Vec<(String, String)> {
+    let vars: &[(&str, &str)] = if cfg!(target_os = "windows") {
+        &[("LOCALAPPDATA", "%LOCALAPPDATA%"), ("APPDATA", "%APPDATA%"), ("USERPROFILE", "%USERPROFILE%")]
+    } else {
+        &[("HOME", "~")]
+    };
+    vars.iter()
+        .filter_map(|(var, token)| {
+            let value = std::env::var(var).ok()?;
+            let value = value.trim_end_matches(['/', '\\']);
+            (!value.is_empty()).then(|| (value.to_string(), token.to_string()))
+        })
+        .collect()
+}
+
+fn abbreviate(path: &str, prefixes: &[(String, String)]) -> String {
+    for (value, token) in prefixes {
+        let Some(head) = path.get(..value.len()) else {
+            continue;
+        };
+        let matches = if cfg!(target_os = "windows") { head.eq_ignore_ascii_case(value) } else { head == value };
+        let rest = &path[value.len()..];
+        if matches && (rest.is_empty() || rest.starts_with(['/', '\\'])) {
+            return format!("{}{}", token, rest);
+        }
+    }
+    path.to_string()
+}
+
+fn expand(input: &str, windows: bool, lookup: impl Fn(&str) -> Option<String>) -> String {
+    // Only the line break a paste can bring along; spaces may be part of a name.
+    let input = input.trim_matches(['\r', '\n']);
+    if input == "~" || input.starts_with("~/") || input.starts_with("~\\") {
+        if let Some(home) = lookup("HOME").or_else(|| lookup("USERPROFILE")) {
+            return format!("{}{}", home.trim_end_matches(['/', '\\']), &input[1..]);
+        }
+    }
+    if !windows {
+        return input.to_string();
+    }
+    // %VAR% segments; unknown variables are left as written.
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) if end > 0 && !after[..end].contains(['/', '\\']) => match lookup(&after[..end]) {
+                Some(value) => {
+                    out.push_str(&rest[..start]);
+                    out.push_str(&value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    out.push_str(&rest[..start + end + 2]);
+                    rest = &after[end + 1..];
+                }
+            },
+            _ => break,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `path` with the home / AppData folder abbreviated for display.
+pub fn display_path(path: &str) -> String {
+    abbreviate(path, &prefixes())
+}
+
+/// Expand a leading `~` and, on Windows, `%VAR%` segments; other paths are
+/// returned as-is.
+pub fn expand_path(input: &str) -> String {
+    expand(input, cfg!(target_os = "windows"), |var| std::env::var(var).ok())
+}
+
+/// Display forms of several paths at once, for result lists.
+#[tauri::command]
+pub fn display_paths(paths: Vec<String>) -> Vec<String> {
+    let prefixes = prefixes();
+    paths.iter().map(|p| abbreviate(p, &prefixes)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abbreviates_and_expands() {
+        let prefixes = vec![("/home/ana".to_string(), "~".to_string())];
+        assert_eq!(abbreviate("/home/ana/.config/discord/Cache", &prefixes), "~/.config/discord/Cache");
+        assert_eq!(abbreviate("/home/anabel/x", &prefixes), "/home/anabel/x");
+
+        let lookup = |var: &str| match var {
+            "HOME" => Some("/home/ana".to_string()),
+            "APPDATA" => Some(r"C:\Users\ana\AppData\Roaming".to_string()),
+            _ => None,
+        };
+        assert_eq!(expand("~/.config/discord", false, lookup), "/home/ana/.config/discord");
+        assert_eq!(expand(r"%APPDATA%\discord\Cache", true, lookup), r"C:\Users\ana\AppData\Roaming\discord\Cache");
+        assert_eq!(expand(r"%NOPE%\x", true, lookup), r"%NOPE%\x");
+        assert_eq!(expand("/srv/100%/cache", true, lookup), "/srv/100%/cache");
+        // Unix names may hold `%` and begin or end in spaces.
+        assert_eq!(expand("/srv/%APPDATA%/cache", false, lookup), "/srv/%APPDATA%/cache");
+        assert_eq!(expand(" /srv/cache copy /\n", false, lookup), " /srv/cache copy /");
+    }
+}
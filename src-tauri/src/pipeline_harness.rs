@@ -0,0 +1,387 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::fixture::FixtureCache;
+use crate::self_test::{Runner, StageResult};
+
+// ─── End-to-end pipeline harness (debug builds only) ────────────────────────
+//
+// `self_test` checks one fixed cache. The reconstruction heuristics keep
+// changing, so automated tests need to describe their own caches: which URLs,
+// which on-disk layout, which byte ranges survived. `run_pipeline_on_fixture`
+// generates that cache, runs it through scan → group → reconstruct → verify
+// and reports, per stage and per entry, what passed. Recovered bodies are
+// compared against the synthetic originals with never-cached ranges zeroed,
+// which is what every reconstruction path promises for missing data. Grouping
+// is the scanner's own (`entry::group_entries`, merged by URL as the scanner
+// does for split sparse entries). The fixture is written to the temp
+// directory, so sizes are capped; release builds reject the command like
+// `set_fault_injection`.
+
+const LAYOUTS: &[&str] = &["inline", "stream2", "sparse", "split_sparse"];
+/// Largest synthetic body of one entry.
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+/// Largest sum of synthetic bodies in one spec.
+const MAX_TOTAL_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Distinguishes concurrent runs inside one process (e.g. parallel tests).
+static RUN: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureEntrySpec {
+    /// Resource URL; the cache key gets the usual `1/0/` prefix
+    pub url: String,
+    /// "inline" (body in `_0` stream 1), "stream2" (body in `_1` behind an
+    /// empty stream 1), "sparse" (`_s`) or "split_sparse" (ranges spread
+    /// over two `_s` files of the same URL)
+    pub layout: String,
+    /// Length of the synthetic MP4 body
+    pub size: u64,
+    /// Byte ranges [start, end) still cached, for the sparse layouts; all of
+    /// the body if omitted
+    #[serde(default)]
+    pub ranges: Option<Vec<[u64; 2]>>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureSpec {
+    pub entries: Vec<FixtureEntrySpec>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryOutcome {
+    pub url: String,
+    pub layout: String,
+    /// Cache files the group stage attributed to this URL
+    pub files: Vec<String>,
+    pub expected_bytes: u64,
+    pub recovered_bytes: u64,
+    /// "pass" or "fail"
+    pub status: &'static str,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PipelineReport {
+    pub passed: bool,
+    pub stages: Vec<StageResult>,
+    pub entries: Vec<EntryOutcome>,
+}
+
+/// One spec entry as written to disk.
+struct Planned {
+    spec: FixtureEntrySpec,
+    /// What a correct reconstruction yields: the body up to the last cached
+    /// byte, never-cached ranges zeroed
+    expected: Vec<u8>,
+    files: usize,
+}
+
+/// A structurally valid MP4 (ftyp + moov + mdat) of exactly `size` bytes,
+/// with a payload derived from `seed` so entries differ.
+fn synthetic_mp4(size: usize, seed: &str) -> Vec<u8> {
+    let seed = seed.bytes().fold(0u8, |h, b| h.wrapping_mul(31).wrapping_add(b));
+    let mut out = Vec::with_capacity(size);
+    if size >= 36 {
+        out.extend_from_slice(&20u32.to_be_bytes());
+        out.extend_from_slice(b"ftypisom\0\0\x02\0isom");
+        out.extend_from_slice(&8u32.to_be_bytes());
+        out.extend_from_slice(b"moov");
+        out.extend_from_slice(&(size as u32 - 28).to_be_bytes());
+        out.extend_from_slice(b"mdat");
+    }
+    let start = out.len();
+    out.extend((start..size).map(|i| (i % 251) as u8 ^ seed));
+    out
+}
+
+/// The cached ranges of `spec`, validated against its size.
+fn cached_ranges(spec: &FixtureEntrySpec) -> Result<Vec<[u64; 2]>, String> {
+    let ranges = spec.ranges.clone().unwrap_or_else(|| vec![[0, spec.size]]);
+    if let Some(bad) = ranges.iter().find(|r| r[0] >= r[1] || r[1] > spec.size) {
+        return Err(format!("{}: range {:?} is empty or beyond size {}", spec.url, bad, spec.size));
+    }
+    Ok(ranges)
+}
+
+fn write_entry(cache: &FixtureCache, spec: &FixtureEntrySpec) -> Result<Planned, String> {
+    if !LAYOUTS.contains(&spec.layout.as_str()) {
+        return Err(format!(
+            "{}: unknown layout \"{}\" (expected one of {})",
+            spec.url,
+            spec.layout,
+            LAYOUTS.join(", ")
+        ));
+    }
+    if spec.size > MAX_ENTRY_SIZE {
+        return Err(format!("{}: size {} is over the {} byte limit", spec.url, spec.size, MAX_ENTRY_SIZE));
+    }
+    let body = synthetic_mp4(spec.size as usize, &spec.url);
+    let key = format!("1/0/{}", spec.url);
+    let content_type = format!("content-type: {}", spec.content_type.as_deref().unwrap_or("video/mp4"));
+    let headers = ["HTTP/1.1 200", content_type.as_str()];
+    let planned = |expected: Vec<u8>, files| Planned { spec: spec.clone(), expected, files };
+
+    match spec.layout.as_str() {
+        "inline" => {
+            cache.add_entry(&key, &headers, &body)?;
+            Ok(planned(body, 1))
+        }
+        "stream2" => {
+            cache.add_entry(&key, &headers, b"")?;
+            cache.add_stream2_entry(&key, &body)?;
+            Ok(planned(body, 2))
+        }
+        _ => {
+            let ranges = cached_ranges(spec)?;
+            let chunks: Vec<(u64, &[u8])> =
+                ranges.iter().map(|r| (r[0], &body[r[0] as usize..r[1] as usize])).collect();
+            let files = if spec.layout == "sparse" {
+                cache.add_sparse_entry(&key, &chunks)?;
+                1
+            } else {
+                // Alternate ranges between two keys of the same URL; a single
+                // range is cut in half so both files hold data.
+                let mut split: Vec<(u64, &[u8])> = Vec::new();
+                for (offset, data) in &chunks {
+                    if chunks.len() == 1 && data.len() > 1 {
+                        let (a, b) = data.split_at(data.len() / 2);
+                        split.extend([(*offset, a), (*offset + a.len() as u64, b)]);
+                    } else {
+                        split.push((*offset, *data));
+                    }
+                }
+                let (mut even, mut odd) = (Vec::new(), Vec::new());
+                for (i, chunk) in split.into_iter().enumerate() {
+                    if i % 2 == 0 {
+                        even.push(chunk);
+                    } else {
+                        odd.push(chunk);
+                    }
+                }
+                cache.add_sparse_entry(&key, &even)?;
+                cache.add_sparse_entry(&format!("_dk_s_ {}", key), &odd)?;
+                2
+            };
+            let end = ranges.iter().map(|r| r[1]).max().unwrap_or(0) as usize;
+            let mut expected = vec![0u8; end];
+            for r in &ranges {
+                let (start, end) = (r[0] as usize, r[1] as usize);
+                expected[start..end].copy_from_slice(&body[start..end]);
+            }
+            Ok(planned(expected, files))
+        }
+    }
+}
+
+/// Group listed cache files into entries the way the scanner does, then
+/// entries by URL.
+fn group_files(files: &[crate::cache::CacheFileEntry]) -> HashMap<String, Vec<String>> {
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in crate::entry::group_entries(files) {
+        let paths = [entry.stream0_file, entry.stream2_file, entry.sparse_file].into_iter().flatten();
+        groups.entry(entry.url).or_default().extend(paths);
+    }
+    groups
+}
+
+/// Rebuild one group's body the way recovery does: sparse files merged,
+/// anything else read as a whole entry.
+fn reconstruct(url: &str, files: &[String]) -> Result<Vec<u8>, String> {
+    let sparse: Vec<String> = files.iter().filter(|f| crate::is_simple_cache_sparse(f)).cloned().collect();
+    if sparse.is_empty() {
+        let first = files.first().ok_or_else(|| format!("{}: no files", url))?;
+        return Ok(crate::entry::load_entry(first, true)?.body);
+    }
+    let (_, ordered) = crate::sparse_merge::group_by_key(&sparse)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("{}: sparse keys unreadable", url))?;
+    Ok(crate::sparse_merge::merge_files(url, &ordered)?.0)
+}
+
+fn run(runner: &mut Runner, root: &Path, spec: &FixtureSpec) -> Vec<EntryOutcome> {
+    let cache_dir = root.join("Cache").join("Cache_Data");
+    let output_dir = root.join("recovered");
+    let mut planned: Vec<Planned> = Vec::new();
+    let mut listed: Vec<crate::cache::CacheFileEntry> = Vec::new();
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    let mut outputs: HashMap<String, Result<String, String>> = HashMap::new();
+
+    runner.stage("fixture", || {
+        if spec.entries.is_empty() {
+            return Err("The spec lists no entries".to_string());
+        }
+        let total: u64 = spec.entries.iter().map(|e| e.size).sum();
+        if total > MAX_TOTAL_SIZE {
+            return Err(format!("The entries add up to {} bytes, over the {} byte limit", total, MAX_TOTAL_SIZE));
+        }
+        let cache = FixtureCache::create(&cache_dir)?;
+        for entry in &spec.entries {
+            planned.push(write_entry(&cache, entry)?);
+        }
+        let files: usize = planned.iter().map(|p| p.files).sum();
+        Ok(format!("Wrote {} entries ({} files) to {}", planned.len(), files, cache.dir().display()))
+    });
+
+    runner.stage("scan", || {
+        let want: usize = planned.iter().map(|p| p.files).sum();
+        listed = crate::cache::list_cache_files(&cache_dir.to_string_lossy())?;
+        if listed.len() != want {
+            return Err(format!("Expected {} cache files, found {}", want, listed.len()));
+        }
+        Ok(format!("Found {} cache files", listed.len()))
+    });
+
+    runner.stage("group", || {
+        groups = group_files(&listed);
+        if let Some(p) = planned.iter().find(|p| groups.get(&p.spec.url).map(Vec::len) != Some(p.files)) {
+            return Err(format!(
+                "{}: expected {} files in its group, found {}",
+                p.spec.url,
+                p.files,
+                groups.get(&p.spec.url).map_or(0, Vec::len)
+            ));
+        }
+        Ok(format!("{} groups", groups.len()))
+    });
+
+    runner.stage("reconstruct", || {
+        for (i, p) in planned.iter().enumerate() {
+            let files = groups.get(&p.spec.url).cloned().unwrap_or_default();
+            let dst = output_dir.join(format!("{:03}.mp4", i)).to_string_lossy().to_string();
+            let result = reconstruct(&p.spec.url, &files)
+                .and_then(|body| crate::output_file::write_output(&dst, &body))
+                .map(|_| dst);
+            outputs.insert(p.spec.url.clone(), result);
+        }
+        let failed = outputs.values().filter(|r| r.is_err()).count();
+        if failed > 0 {
+            return Err(format!("{} of {} entries could not be reconstructed", failed, outputs.len()));
+        }
+        Ok(format!("Wrote {} files to {}", outputs.len(), output_dir.display()))
+    });
+
+    let mut outcomes: Vec<EntryOutcome> = planned
+        .iter()
+        .map(|p| {
+            let files = groups.get(&p.spec.url).cloned().unwrap_or_default();
+            let (status, detail, recovered) = match outputs.get(&p.spec.url) {
+                None => ("fail", "Not reconstructed".to_string(), 0),
+                Some(Err(e)) => ("fail", e.clone(), 0),
+                Some(Ok(dst)) => match std::fs::read(dst) {
+                    Err(e) => ("fail", format!("Failed to read back {}: {}", dst, e), 0),
+                    Ok(got) if got == p.expected => ("pass", format!("{} bytes match", got.len()), got.len()),
+                    Ok(got) => {
+                        let first_diff = got.iter().zip(&p.expected).position(|(a, b)| a != b);
+                        let detail = match first_diff {
+                            Some(at) => format!("Differs at byte {}", at),
+                            None => format!("Length {} instead of {}", got.len(), p.expected.len()),
+                        };
+                        ("fail", detail, got.len())
+                    }
+                },
+            };
+            EntryOutcome {
+                url: p.spec.url.clone(),
+                layout: p.spec.layout.clone(),
+                files,
+                expected_bytes: p.expected.len() as u64,
+                recovered_bytes: recovered as u64,
+                status,
+                detail,
+            }
+        })
+        .collect();
+
+    runner.stage("verify", || {
+        // Source files must carry valid checksums, or the fixture itself is broken.
+        for path in listed.iter().map(|f| &f.path) {
+            let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let check = crate::crc_check::check_data(&data, path);
+            if check.status != "ok" {
+                return Err(format!("{}: checksum {} ({:?})", path, check.status, check.failed));
+            }
+        }
+        let failed: Vec<&EntryOutcome> = outcomes.iter().filter(|o| o.status == "fail").collect();
+        if let Some(first) = failed.first() {
+            return Err(format!("{} of {} entries differ; first {}: {}", failed.len(), outcomes.len(), first.url, first.detail));
+        }
+        Ok(format!("{} entries match their originals", outcomes.len()))
+    });
+    if runner.stages.last().is_some_and(|s| s.status == "skip") {
+        // An earlier stage failed; per-entry results would only repeat it.
+        outcomes.iter_mut().filter(|o| o.status == "pass").for_each(|o| {
+            o.status = "fail";
+            o.detail = "Pipeline stopped before verification".to_string();
+        });
+    }
+    outcomes
+}
+
+/// Generate a synthetic cache from `spec`, run it through scan → group →
+/// reconstruct → verify and report each stage and entry. Used by automated
+/// tests as a regression net for the reconstruction heuristics; nothing
+/// outside the temp directory is touched. Debug builds only.
+#[tauri::command(async)]
+pub fn run_pipeline_on_fixture(spec: FixtureSpec) -> Result<PipelineReport, String> {
+    if !cfg!(debug_assertions) {
+        return Err("The pipeline harness is only available in debug builds".to_string());
+    }
+    let _job = crate::crash::job(format!("pipeline fixture with {} entries", spec.entries.len()));
+    let root: PathBuf = std::env::temp_dir().join(format!(
+        "cachephoenix-pipeline-{}-{}",
+        std::process::id(),
+        RUN.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut runner = Runner::new();
+    let entries = run(&mut runner, &root, &spec);
+    runner.always("cleanup", || {
+        if !root.exists() {
+            return Ok("Nothing to clean up".to_string());
+        }
+        std::fs::remove_dir_all(&root)
+            .map(|_| "Removed temp files".to_string())
+            .map_err(|e| format!("Failed to remove {}: {}", root.display(), e))
+    });
+    let passed = runner.stages.iter().all(|s| s.status == "pass") && entries.iter().all(|e| e.status == "pass");
+    Ok(PipelineReport { passed, stages: runner.stages, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(url: &str, layout: &str, size: u64, ranges: Option<Vec<[u64; 2]>>) -> FixtureEntrySpec {
+        FixtureEntrySpec { url: url.to_string(), layout: layout.to_string(), size, ranges, content_type: None }
+    }
+
+    #[test]
+    fn every_layout_round_trips() {
+        let report = run_pipeline_on_fixture(FixtureSpec {
+            entries: vec![
+                entry("https://cdn.discordapp.com/attachments/1/2/a.mp4", "inline", 3000, None),
+                entry("https://cdn.discordapp.com/attachments/1/2/b.mp4", "stream2", 70_000, None),
+                entry("https://media.discordapp.net/attachments/1/2/c.mp4", "sparse", 9000, Some(vec![[4096, 9000], [0, 1024]])),
+                entry("https://media.discordapp.net/attachments/1/2/d.mp4", "split_sparse", 8192, None),
+            ],
+        })
+        .unwrap();
+        for stage in &report.stages {
+            assert_eq!(stage.status, "pass", "{}: {}", stage.name, stage.detail);
+        }
+        assert!(report.passed);
+        assert_eq!(report.entries[2].expected_bytes, 9000);
+        assert_eq!(report.entries[3].files.len(), 2);
+
+        let bad = run_pipeline_on_fixture(FixtureSpec { entries: vec![entry("https://x/y.mp4", "blockfile", 10, None)] });
+        assert_eq!(bad.unwrap().stages[0].status, "fail");
+        let huge = entry("https://x/z.mp4", "inline", MAX_ENTRY_SIZE + 1, None);
+        let too_big = run_pipeline_on_fixture(FixtureSpec { entries: vec![huge] }).unwrap();
+        assert!(too_big.stages[0].detail.contains("limit"));
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+// ─── Privacy / "forget everything" ──────────────────────────────────────────
+//
+// The backend keeps no catalog or log files of its own; what it can leave
+// behind is temp workspace: Safari Cache.db snapshots, MTP staging copies,
+// ZIP builds, extracted cache archives, and the frontend's thumbnail/remux
+// scratch files (`dccr_*`). Those survive a crash or a timed-out ffmpeg run
+// and, like the cache itself, read as browsing history. `purge_temp_workspace`
+// removes all of them; the frontend clears its own thumbnail cache and stored
+// paths alongside. While a backend job is running, entries touched in the last
+// few minutes may be its own scratch space and are kept; the frontend doesn't
+// offer the purge during a scan or recovery at all.
+
+/// Name prefixes of everything CachePhoenix creates in the temp directory.
+const TEMP_PREFIXES: &[&str] = &["cachephoenix", "dccr_"];
+/// While jobs run, entries modified more recently than this are left alone.
+const LIVE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeReport {
+    pub entries_removed: usize,
+    pub bytes_removed: u64,
+    /// Recent entries left alone because a job was running
+    pub entries_kept: usize,
+    pub errors: Vec<String>,
+}
+
+pub(crate) fn is_workspace_entry(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    TEMP_PREFIXES.iter().any(|prefix| lower.starts_with(prefix))
+}
+
+/// Total size of a file or directory tree (symlinks are not followed).
+fn tree_size(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| tree_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Remove CachePhoenix's entries from `temp`, except those modified after
+/// `keep_since`.
+fn purge_dir(temp: &Path, keep_since: Option<SystemTime>) -> PurgeReport {
+    let mut report = PurgeReport::default();
+    let entries = match std::fs::read_dir(temp) {
+        Ok(entries) => entries,
+        Err(e) => {
+            report.errors.push(format!("Failed to read dir {}: {}", temp.display(), e));
+            return report;
+        }
+    };
+    for entry in entries.flatten() {
+        if !is_workspace_entry(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let path = entry.path();
+        let modified = entry.metadata().and_then(|m| m.modified()).ok();
+        if keep_since.is_some_and(|since| modified.is_none_or(|m| m > since)) {
+            report.entries_kept += 1;
+            continue;
+        }
+        let size = tree_size(&path);
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        let removed = if is_dir { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+        match removed {
+            Ok(()) => {
+                report.entries_removed += 1;
+                report.bytes_removed += size;
+            }
+            Err(e) => report.errors.push(format!("Failed to remove {}: {}", path.display(), e)),
+        }
+    }
+    report
+}
+
+/// Delete every temp file and folder CachePhoenix has created that no running
+/// job may still be using.
+#[tauri::command(async)]
+pub fn purge_temp_workspace() -> PurgeReport {
+    let keep_since = crate::crash::jobs_running().then(|| SystemTime::now() - LIVE_WINDOW);
+    let report = purge_dir(&std::env::temp_dir(), keep_since);
+    println!(
+        "[privacy] Purged {} temp entries ({} bytes), kept {} in use, {} errors",
+        report.entries_removed,
+        report.bytes_removed,
+        report.entries_kept,
+        report.errors.len()
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purges_only_workspace_entries() {
+        let temp = std::env::temp_dir().join(format!("privacy-unit-{}", std::process::id()));
+        std::fs::create_dir_all(temp.join("cachephoenix-safari-1")).unwrap();
+        std::fs::write(temp.join("cachephoenix-safari-1").join("Cache.db"), b"12345").unwrap();
+        std::fs::write(temp.join("dccr_thumb_7.jpg"), b"abc").unwrap();
+        std::fs::write(temp.join("unrelated.txt"), b"keep").unwrap();
+
+        let kept = purge_dir(&temp, Some(SystemTime::now() - LIVE_WINDOW));
+        assert_eq!((kept.entries_removed, kept.entries_kept), (0, 2));
+        let report = purge_dir(&temp, None);
+        assert_eq!((report.entries_removed, report.bytes_removed), (2, 8));
+        assert!(temp.join("unrelated.txt").exists());
+        assert!(!temp.join("dccr_thumb_7.jpg").exists());
+        let _ = std::fs::remove_dir_all(&temp);
+    }
+}
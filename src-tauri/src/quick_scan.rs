@@ -0,0 +1,206 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::cache::CacheFileEntry;
+use crate::eviction_risk::ranks;
+
+// Time-boxed quick scan.
+//
+// A full scan reads every file's headers, which on a large cache takes a
+// while. Users who just want "the video from an hour ago" can instead give a
+// time budget: files are ranked by recency and size (recent, large files are
+// the likeliest to be what they're after), then sniffed in that order until
+// the budget runs out. Whatever was found by then is returned.
+
+/// Files smaller than this are almost never recoverable media.
+const MIN_MEDIA_SIZE: u64 = 8 * 1024;
+/// Bytes of body read per file for signature sniffing.
+const SNIFF_BYTES: usize = 64;
+/// Bytes read instead when the body starts with a TS sync byte: enough for
+/// the repeated sync bytes of a few MPEG-TS packets.
+const TS_SNIFF_BYTES: usize = 1024;
+const MIN_BUDGET_SECS: f64 = 0.5;
+const MAX_BUDGET_SECS: f64 = 600.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickScanHit {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub modified_at: f64,
+    pub mime_type: &'static str,
+    /// "video", "image" or "audio"
+    pub category: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuickScanResult {
+    /// Most recent first
+    pub hits: Vec<QuickScanHit>,
+    pub total_files: usize,
+    /// Files sniffed before the budget ran out
+    pub examined: usize,
+    /// True if some candidates were never looked at
+    pub timed_out: bool,
+    pub elapsed_ms: u64,
+}
+
+/// Identify media from the first bytes of a body: (MIME type, category).
+pub(crate) fn sniff(data: &[u8]) -> Option<(&'static str, &'static str)> {
+    let mime = sniff_mime(data)?;
+    Some((mime, crate::mime_category::classify(None, Some(mime)).category))
+}
+
+fn sniff_mime(data: &[u8]) -> Option<&'static str> {
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        if &data[8..12] == b"M4A " {
+            return Some("audio/mp4");
+        }
+        return Some(crate::image_repair::heif_mime(data).unwrap_or("video/mp4"));
+    }
+    // moov/mdat first: MP4 without a leading ftyp (or a continuation chunk)
+    if data.len() >= 8 && (&data[4..8] == b"moov" || &data[4..8] == b"mdat") {
+        return Some("video/mp4");
+    }
+    if crate::dash::is_media_segment(data) {
+        return Some("video/iso.segment");
+    }
+    if data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        return Some("video/webm");
+    }
+    if data.starts_with(b"OggS") {
+        if crate::legacy_formats::ogg_codec(data) == Some("theora") {
+            return Some("video/ogg");
+        }
+        return Some("audio/ogg");
+    }
+    if data.starts_with(b"ID3") || (data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0) {
+        return Some("audio/mpeg");
+    }
+    if crate::hls::is_transport_stream(data) {
+        return Some("video/mp2t");
+    }
+    if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        return Some("image/png");
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if data.starts_with(b"GIF8") {
+        return Some("image/gif");
+    }
+    if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    None
+}
+
+/// Order candidates so the likeliest "recent video" comes first.
+/// Recency dominates; size breaks ties between files of similar age.
+fn prioritize(files: Vec<CacheFileEntry>) -> Vec<CacheFileEntry> {
+    let files: Vec<CacheFileEntry> = files
+        .into_iter()
+        .filter(|f| f.size >= MIN_MEDIA_SIZE)
+        .collect();
+    let times: Vec<f64> = files
+        .iter()
+        .map(|f| f.last_used.unwrap_or(0.0).max(f.modified_at))
+        .collect();
+    let sizes: Vec<f64> = files.iter().map(|f| f.size as f64).collect();
+    let time_ranks = ranks(&times);
+    let size_ranks = ranks(&sizes);
+    let mut scored: Vec<(f64, CacheFileEntry)> = files
+        .into_iter()
+        .enumerate()
+        .map(|(i, f)| (0.7 * time_ranks[i] + 0.3 * size_ranks[i], f))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().map(|(_, f)| f).collect()
+}
+
+fn read_body_start(path: &str) -> Result<Vec<u8>, String> {
+    let read = |len| {
+        if crate::is_simple_cache_sparse(path) {
+            crate::read_sparse_cache_header(path.to_string(), len)
+        } else {
+            crate::read_file_header(path.to_string(), len)
+        }
+    };
+    let start = read(SNIFF_BYTES)?;
+    // Only a possible TS segment is worth the longer read.
+    if start.first() == Some(&0x47) && start.len() == SNIFF_BYTES {
+        return read(TS_SNIFF_BYTES);
+    }
+    Ok(start)
+}
+
+/// Scan `dir` for media, spending at most `seconds_budget` seconds.
+/// Returns whatever was found when the budget ran out.
+#[tauri::command(async)]
+pub fn quick_scan(app: tauri::AppHandle, dir: String, seconds_budget: f64) -> Result<QuickScanResult, String> {
+    let dir = crate::path_display::expand_path(&dir);
+    let _job = crate::crash::job(format!("quick scan of {}", dir));
+    let started = Instant::now();
+    let budget = Duration::from_secs_f64(seconds_budget.clamp(MIN_BUDGET_SECS, MAX_BUDGET_SECS));
+
+    let mut files = crate::cache::list_cache_files(&dir)?;
+    crate::ignore_rules::apply_saved(&app, &dir, &mut files);
+    let total_files = files.len();
+    let candidates = prioritize(files);
+    let candidate_count = candidates.len();
+
+    let mut hits = Vec::new();
+    let mut examined = 0usize;
+    for file in candidates {
+        if started.elapsed() >= budget {
+            break;
+        }
+        examined += 1;
+        // Simple Cache _0 files carry their Content-Type; trust it over sniffing.
+        let declared = if file.name.ends_with("_0") {
+            crate::simple_cache_content_type(&file.path).ok()
+        } else {
+            None
+        };
+        let sniffed = match read_body_start(&file.path) {
+            Ok(data) => sniff(&data),
+            Err(e) => {
+                eprintln!("[quick_scan] {}: {}", file.path, e);
+                continue;
+            }
+        };
+        let Some((mime_type, category)) = sniffed else {
+            continue;
+        };
+        if declared.as_deref().is_some_and(|ct| !ct.starts_with(category)) {
+            continue;
+        }
+        hits.push(QuickScanHit {
+            name: file.name,
+            path: file.path,
+            size: file.size,
+            modified_at: file.modified_at,
+            mime_type,
+            category,
+        });
+    }
+
+    hits.sort_by(|a, b| b.modified_at.total_cmp(&a.modified_at));
+    let timed_out = examined < candidate_count;
+    println!(
+        "[quick_scan] {} hits from {}/{} candidates ({} files) in {:?}{}",
+        hits.len(),
+        examined,
+        candidate_count,
+        total_files,
+        started.elapsed(),
+        if timed_out { " — budget exhausted" } else { "" }
+    );
+    Ok(QuickScanResult {
+        hits,
+        total_files,
+        examined,
+        timed_out,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
@@ -0,0 +1,489 @@
+use serde::{Deserialize, Serialize};
+
+use crate::chunk_layout::LayoutCheck;
+use crate::mp4_samples::SampleCoverage;
+
+// ---------------------------------------------------------------------------
+// Gap policy
+// ---------------------------------------------------------------------------
+
+/// How a reconstructor handles byte ranges that are missing from the cache
+/// (evicted chunks, absent sparse children).
+///
+/// The frontend passes this as a snake_case string: `"pad_zeros"`,
+/// `"truncate_at_gap"` or `"close_gaps"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapPolicy {
+    /// Zero-fill every missing range so all moov stco/co64 offsets stay valid.
+    /// Missing chunks become black/silent frames. This is the historical behavior.
+    #[default]
+    PadZeros,
+    /// Stop assembling at the first missing range. Everything before the gap is
+    /// byte-exact; a moov recovered from a separate tail chunk is still appended
+    /// so players can seek within the surviving prefix.
+    TruncateAtGap,
+    /// Skip missing ranges entirely and close them up. Segmented output (HLS,
+    /// DASH, fragmented MP4, WebM clusters) just loses the missing segments. A
+    /// progressive MP4's moov is not rewritten: its stco/co64 offsets past the
+    /// first gap point at the wrong bytes, so playback breaks from there on.
+    /// Smallest output, least faithful layout.
+    CloseGaps,
+}
+
+impl GapPolicy {
+    /// Whether missing ranges should be filled with zero bytes.
+    pub fn pads(self) -> bool {
+        self == GapPolicy::PadZeros
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Result object
+// ---------------------------------------------------------------------------
+
+/// Summary returned by the reconstruction commands.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconstructionResult {
+    /// Bytes written to the output file.
+    pub bytes_written: u64,
+    /// The gap policy that was applied.
+    pub gap_policy: GapPolicy,
+    /// Number of distinct missing ranges encountered.
+    pub gaps_found: usize,
+    /// Total size of the missing ranges (zero-filled, skipped, or cut off).
+    pub gap_bytes: u64,
+    /// True if output stopped early because of `GapPolicy::TruncateAtGap`.
+    pub truncated: bool,
+    /// Sample data the output's moov references vs. what is present; None
+    /// for non-MP4 output or a moov without sample tables.
+    pub coverage: Option<SampleCoverage>,
+    /// What put the chunks in order; None when the layout came from an index
+    /// or sparse ranges rather than separate chunk files.
+    pub ordering: Option<OrderingSignal>,
+    /// How the chunk placement held up against the moov's sample table; None
+    /// without a tail moov describing H.264/H.265 video.
+    pub layout_check: Option<LayoutCheck>,
+    /// Where the missing ranges are, when the reconstructor knows; the
+    /// chunked MP4 reconstruction reports them.
+    pub gap_ranges: Vec<GapRange>,
+    /// How likely the output is to be worth opening, from the above
+    pub confidence: Option<Confidence>,
+    /// Media chunks assembled at the full chunk size / at another size; both
+    /// zero when the reconstructor has no fixed chunk size.
+    #[serde(skip)]
+    chunk_sizes: (usize, usize),
+}
+
+/// One missing range of a reconstruction.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GapRange {
+    /// Start in the original upload's layout; where the output is zero-filled
+    /// (GapPolicy::PadZeros) that is its own offset
+    pub offset: u64,
+    pub bytes: u64,
+    /// Cache files that would have held it, inferred from the hex numbering
+    pub missing_chunks: Vec<String>,
+    /// Playback time the range covers, from the moov's sample tables: about
+    /// where the video glitches
+    pub start_secs: Option<f64>,
+    pub end_secs: Option<f64>,
+    /// Why the range is missing, where a chunk was there but left out
+    pub reason: Option<String>,
+}
+
+/// A 0–100 score for a reconstruction and what went into it. The factors
+/// multiply: missing media costs its share, each gap 10% (down to half),
+/// chunks the sample table contradicts their share, and odd-sized chunks up
+/// to half of theirs.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Confidence {
+    pub score: f64,
+    /// "high" (80+), "medium" (50+) or "low"
+    pub rating: &'static str,
+    /// Share of the media present: moov sample bytes when there is a moov,
+    /// else output bytes that aren't a gap
+    pub filled_percent: f64,
+    pub gaps: usize,
+    /// Share of the checkable chunks the sample table agrees with where they
+    /// ended up; None without a check
+    pub layout_agreement: Option<f64>,
+    /// Share of media chunks at the full chunk size; None without fixed-size chunks
+    pub chunk_consistency: Option<f64>,
+    /// The factors that cost points, worst first
+    pub reasons: Vec<String>,
+}
+
+impl ReconstructionResult {
+    pub fn new(gap_policy: GapPolicy) -> Self {
+        Self {
+            bytes_written: 0,
+            gap_policy,
+            gaps_found: 0,
+            gap_bytes: 0,
+            truncated: false,
+            coverage: None,
+            ordering: None,
+            layout_check: None,
+            gap_ranges: Vec::new(),
+            confidence: None,
+            chunk_sizes: (0, 0),
+        }
+    }
+
+    /// Record the final output: its size and how much of the media its moov
+    /// describes actually made it in.
+    pub fn finish(&mut self, output: &[u8]) {
+        self.bytes_written = output.len() as u64;
+        self.coverage = crate::mp4_samples::coverage(output);
+        if let Some(cov) = self.coverage.as_ref().filter(|c| c.missing_samples > 0) {
+            println!(
+                "[reconstruct] moov references {} sample bytes, {:.1}% missing ({} of {} samples); video plays to ~{:.0}%",
+                cov.expected_bytes, cov.shortfall_percent, cov.missing_samples, cov.total_samples, cov.playable_percent
+            );
+            for track in cov.tracks.iter().filter(|t| t.present_samples < t.total_samples) {
+                println!(
+                    "[reconstruct]   {}: {:.1}% of {} samples present ({} past end of file, {} zero-filled)",
+                    track.handler, track.present_percent, track.total_samples, track.outside_file, track.zero_filled
+                );
+            }
+        }
+        if let Some(moov) = crate::mp4_samples::find_moov(output).filter(|_| !self.gap_ranges.is_empty()) {
+            for range in &mut self.gap_ranges {
+                let span = crate::mp4_samples::time_span(moov, range.offset, range.offset + range.bytes);
+                (range.start_secs, range.end_secs) = (span.map(|s| s.0), span.map(|s| s.1));
+            }
+        }
+        for range in &self.gap_ranges {
+            let chunks = if range.missing_chunks.is_empty() {
+                "chunks unknown".to_string()
+            } else {
+                range.missing_chunks.join(", ")
+            };
+            println!(
+                "[reconstruct] Missing {} bytes at {} ({}), plays as {:?}..{:?}s{}",
+                range.bytes,
+                range.offset,
+                chunks,
+                range.start_secs,
+                range.end_secs,
+                range.reason.as_deref().map(|r| format!(": {}", r)).unwrap_or_default()
+            );
+        }
+        let confidence = self.score();
+        println!(
+            "[reconstruct] Confidence {:.0} ({}): {}",
+            confidence.score,
+            confidence.rating,
+            confidence.reasons.join("; ")
+        );
+        self.confidence = Some(confidence);
+    }
+
+    /// Record a missing range at `offset` of the original layout, and the
+    /// cache files it should have come from. A range that starts where the
+    /// last one ended — zero padding at the end of the mdat right after a
+    /// gap — is the same hole and extends it rather than counting again.
+    pub fn record_gap_range(&mut self, offset: u64, size: u64, missing_chunks: Vec<String>) {
+        if let Some(last) = self.gap_ranges.last_mut().filter(|r| r.offset.saturating_add(r.bytes) == offset) {
+            last.bytes += size;
+            for chunk in missing_chunks {
+                if !last.missing_chunks.contains(&chunk) {
+                    last.missing_chunks.push(chunk);
+                }
+            }
+            self.gap_bytes += size;
+            return;
+        }
+        self.record_gap(size);
+        self.gap_ranges.push(GapRange {
+            offset,
+            bytes: size,
+            missing_chunks,
+            start_secs: None,
+            end_secs: None,
+            reason: None,
+        });
+    }
+
+    /// Name the chunks in `left_out` (cache file name, why) that a gap would
+    /// have held: they were there, just not this video's.
+    pub fn explain_gaps(&mut self, left_out: &[(String, &str)]) {
+        for range in &mut self.gap_ranges {
+            let why: Vec<String> = left_out
+                .iter()
+                .filter(|(name, _)| range.missing_chunks.contains(name))
+                .map(|(name, why)| format!("{} left out, it {}", name, why))
+                .collect();
+            if !why.is_empty() {
+                range.reason = Some(why.join("; "));
+            }
+        }
+    }
+
+    /// Count a media chunk assembled at (`full`) or off the full chunk size.
+    pub fn record_chunk(&mut self, full: bool) {
+        if full {
+            self.chunk_sizes.0 += 1;
+        } else {
+            self.chunk_sizes.1 += 1;
+        }
+    }
+
+    fn score(&self) -> Confidence {
+        let mut factors: Vec<(f64, String)> = Vec::new();
+
+        let filled = match &self.coverage {
+            Some(cov) if cov.expected_bytes > 0 => cov.recovered_bytes as f64 / cov.expected_bytes as f64,
+            _ => {
+                // Zero-filled gaps are part of the output; skipped ones aren't.
+                let total = self.bytes_written + if self.gap_policy.pads() { 0 } else { self.gap_bytes };
+                if total == 0 { 0.0 } else { 1.0 - self.gap_bytes.min(total) as f64 / total as f64 }
+            }
+        };
+        factors.push((filled, format!("{:.1}% of the media present", filled * 100.0)));
+        if self.gaps_found > 0 {
+            let factor = 0.9f64.powi(self.gaps_found.min(i32::MAX as usize) as i32).max(0.5);
+            factors.push((factor, format!("{} gap(s)", self.gaps_found)));
+        }
+
+        let layout_agreement = self.layout_check.as_ref().and_then(|check| {
+            let checked = check.confirmed + check.moved + check.contradicted;
+            (checked > 0).then(|| (check.confirmed + check.moved) as f64 / checked as f64)
+        });
+        if let (Some(agreement), Some(check)) = (layout_agreement, &self.layout_check) {
+            factors.push((agreement, format!("{} chunk(s) contradicted by the sample table", check.contradicted)));
+        }
+
+        let (full, odd) = self.chunk_sizes;
+        let chunk_consistency = (full + odd > 0).then(|| full as f64 / (full + odd) as f64);
+        if let Some(consistency) = chunk_consistency {
+            factors.push((0.5 + consistency / 2.0, format!("{} chunk(s) of an unexpected size", odd)));
+        }
+
+        let score = factors.iter().map(|(f, _)| f.clamp(0.0, 1.0)).product::<f64>() * 100.0;
+        factors.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Confidence {
+            score,
+            rating: if score >= 80.0 { "high" } else if score >= 50.0 { "medium" } else { "low" },
+            filled_percent: filled * 100.0,
+            gaps: self.gaps_found,
+            layout_agreement: layout_agreement.map(|a| a * 100.0),
+            chunk_consistency: chunk_consistency.map(|c| c * 100.0),
+            reasons: factors.into_iter().filter(|(f, _)| *f < 1.0).map(|(_, reason)| reason).collect(),
+        }
+    }
+
+    /// Record a missing range of `size` bytes.
+    pub fn record_gap(&mut self, size: u64) {
+        self.gaps_found += 1;
+        self.gap_bytes += size;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Chunk ordering
+// ---------------------------------------------------------------------------
+
+/// The signal used to put chunk files in download order, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderingSignal {
+    /// Blockfile `f_XXXXXX` numbers, allocated sequentially as data arrives.
+    HexSequence,
+    /// Start offset of each entry's `Content-Range` response header.
+    ContentRange,
+    /// File modification times. Chromium writes chunks as they download, so
+    /// within one resource the mtimes follow download order to the millisecond.
+    Mtime,
+    /// None of the above covered every chunk; known hex numbers first, the
+    /// rest in the order given.
+    Input,
+}
+
+/// Start offset from the `Content-Range` header of the entry `path` belongs to.
+pub(crate) fn content_range_start(path: &str) -> Option<u64> {
+    let headers_file = crate::entry::sibling_file(path, "0")?;
+    let data = crate::read_with_lock_retry(&headers_file).ok()?;
+    let info = crate::http_info::parse(&crate::extract_simple_cache_headers(&data)?)?;
+    let range = info.header("content-range")?.trim().strip_prefix("bytes")?.trim();
+    range.split('-').next()?.trim().parse().ok()
+}
+
+fn mtime(path: &str) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Sort `paths` into download order using the strongest signal available for
+/// every one of them. Ties keep their given order.
+pub fn order_chunks(paths: &mut [String]) -> OrderingSignal {
+    let keyed = |key: &dyn Fn(&str) -> Option<u64>| -> Option<Vec<u64>> { paths.iter().map(|p| key(p)).collect() };
+    let signal = if let Some(keys) = keyed(&crate::parse_cache_hex) {
+        Some((keys, OrderingSignal::HexSequence))
+    } else if let Some(keys) = keyed(&content_range_start) {
+        Some((keys, OrderingSignal::ContentRange))
+    } else {
+        let since_epoch = |p: &str| mtime(p)?.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_nanos() as u64);
+        keyed(&since_epoch).map(|keys| (keys, OrderingSignal::Mtime))
+    };
+    let (keys, signal) = signal.unwrap_or_else(|| {
+        let keys = paths.iter().map(|p| crate::parse_cache_hex(p).unwrap_or(u64::MAX)).collect();
+        (keys, OrderingSignal::Input)
+    });
+    let mut order: Vec<(u64, String)> = keys.into_iter().zip(paths.iter().cloned()).collect();
+    order.sort_by_key(|(key, _)| *key);
+    for (slot, (_, path)) in paths.iter_mut().zip(order) {
+        *slot = path;
+    }
+    signal
+}
+
+// ---------------------------------------------------------------------------
+// Read-ahead
+// ---------------------------------------------------------------------------
+
+/// Yields `(path, contents)` for each path in order while a worker thread
+/// reads the next file, so disk/network latency overlaps with the caller's
+/// copy work. At most one chunk is held beyond the one being processed.
+pub struct ReadAhead {
+    rx: std::sync::mpsc::Receiver<(String, Result<Vec<u8>, String>)>,
+}
+
+/// Start reading `paths` in order with `read` on a background thread.
+/// Dropping the iterator early stops the worker after its current read. A
+/// read that panics yields an error for its path rather than ending the
+/// iteration, so callers can't mistake it for the last chunk.
+pub fn read_ahead<F>(paths: Vec<String>, read: F) -> ReadAhead
+where
+    F: Fn(&str) -> Result<Vec<u8>, String> + Send + 'static,
+{
+    // Rendezvous channel: the worker finishes one read, then waits until the
+    // caller takes it before starting the next.
+    let (tx, rx) = std::sync::mpsc::sync_channel(0);
+    std::thread::spawn(move || {
+        for path in paths {
+            let data = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| read(&path)))
+                .unwrap_or_else(|_| Err(format!("Reading {} panicked", path)));
+            if tx.send((path, data)).is_err() {
+                break;
+            }
+        }
+    });
+    ReadAhead { rx }
+}
+
+impl Iterator for ReadAhead {
+    type Item = (String, Result<Vec<u8>, String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_by_hex_else_mtime() {
+        let mut hex = vec!["c/f_00000a".to_string(), "c/f_000002".to_string()];
+        assert_eq!(order_chunks(&mut hex), OrderingSignal::HexSequence);
+        assert_eq!(hex, vec!["c/f_000002", "c/f_00000a"]);
+
+        let dir = std::env::temp_dir().join(format!("cachephoenix-order-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = std::time::SystemTime::now();
+        let mut paths = Vec::new();
+        for (name, offset_ms) in [("b", 2u64), ("a", 5), ("c", 1)] {
+            let path = dir.join(name);
+            std::fs::write(&path, name).unwrap();
+            let file = std::fs::File::options().append(true).open(&path).unwrap();
+            file.set_modified(base + std::time::Duration::from_millis(offset_ms)).unwrap();
+            paths.push(path.to_string_lossy().to_string());
+        }
+        let signal = order_chunks(&mut paths);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(signal, OrderingSignal::Mtime);
+        let names: Vec<&str> = paths.iter().map(|p| &p[p.len() - 1..]).collect();
+        assert_eq!(names, vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn confidence_falls_with_gaps_and_odd_chunks() {
+        let mut clean = ReconstructionResult::new(GapPolicy::PadZeros);
+        (0..4).for_each(|_| clean.record_chunk(true));
+        clean.finish(&[1; 4000]);
+        let clean = clean.confidence.unwrap();
+        assert_eq!((clean.score, clean.rating, clean.reasons.len()), (100.0, "high", 0));
+
+        // A quarter zero-filled in two gaps, one of four chunks short.
+        let mut holed = ReconstructionResult::new(GapPolicy::PadZeros);
+        holed.record_gap(500);
+        holed.record_gap(500);
+        (0..3).for_each(|_| holed.record_chunk(true));
+        holed.record_chunk(false);
+        holed.layout_check = Some(LayoutCheck { confirmed: 3, contradicted: 1, ..Default::default() });
+        holed.finish(&[1; 4000]);
+        let holed = holed.confidence.unwrap();
+        assert_eq!(holed.filled_percent, 75.0);
+        assert_eq!((holed.layout_agreement, holed.chunk_consistency), (Some(75.0), Some(75.0)));
+        let expected = 0.75 * 0.81 * 0.75 * 0.875 * 100.0;
+        assert!((holed.score - expected).abs() < 1e-9);
+        assert_eq!(holed.rating, "low");
+        assert_eq!(holed.reasons.len(), 4);
+
+        // Skipped gaps aren't in the output, so they count on top of it.
+        let mut dropped = ReconstructionResult::new(GapPolicy::CloseGaps);
+        dropped.record_gap(1000);
+        dropped.finish(&[1; 3000]);
+        assert_eq!(dropped.confidence.unwrap().filled_percent, 75.0);
+    }
+
+    #[test]
+    fn adjoining_gap_ranges_count_once() {
+        let mut result = ReconstructionResult::new(GapPolicy::PadZeros);
+        result.record_gap_range(100, 1000, vec!["f_000003".to_string()]);
+        result.record_gap_range(1100, 500, vec!["f_000003".to_string(), "f_000004".to_string()]);
+        result.record_gap_range(2000, 10, Vec::new());
+        assert_eq!((result.gaps_found, result.gap_bytes), (2, 1510));
+        assert_eq!((result.gap_ranges[0].bytes, result.gap_ranges[0].missing_chunks.len()), (1500, 2));
+    }
+
+    #[test]
+    fn gaps_name_the_chunks_left_out() {
+        let mut result = ReconstructionResult::new(GapPolicy::PadZeros);
+        result.record_gap_range(0, 10, vec!["f_00000a".to_string(), "f_00000b".to_string()]);
+        result.record_gap_range(20, 10, vec!["f_00000d".to_string()]);
+        result.explain_gaps(&[("f_00000b".to_string(), "holds another stream's bitstream")]);
+        let reasons: Vec<_> = result.gap_ranges.iter().map(|r| r.reason.as_deref()).collect();
+        assert_eq!(reasons, [Some("f_00000b left out, it holds another stream's bitstream"), None]);
+    }
+
+    #[test]
+    fn read_ahead_preserves_order_and_errors() {
+        let paths: Vec<String> = ["a", "bad", "c"].iter().map(|s| s.to_string()).collect();
+        let read = |p: &str| {
+            if p == "bad" {
+                Err(format!("Failed to read {}", p))
+            } else {
+                Ok(p.as_bytes().to_vec())
+            }
+        };
+        let got: Vec<(String, Result<Vec<u8>, String>)> = read_ahead(paths, read).collect();
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[0], ("a".to_string(), Ok(b"a".to_vec())));
+        assert!(got[1].1.is_err());
+        assert_eq!(got[2].0, "c");
+
+        let panicky = |p: &str| if p == "bad" { panic!("cut off") } else { Ok(p.as_bytes().to_vec()) };
+        let paths: Vec<String> = ["a", "bad", "c"].iter().map(|s| s.to_string()).collect();
+        let got: Vec<(String, Result<Vec<u8>, String>)> = read_ahead(paths, panicky).collect();
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[1].1, Err("Reading bad panicked".to_string()));
+
+        // Stopping early must not hang.
+        let mut early = read_ahead(vec!["x".into(), "y".into(), "z".into()], read);
+        assert!(early.next().is_some());
+        drop(early);
+    }
+}
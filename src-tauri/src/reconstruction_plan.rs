@@ -0,0 +1,524 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::chunk_layout::{self, LayoutCheck, Placement};
+use crate::reconstruction::{self, GapPolicy, OrderingSignal};
+
+// ─── Reconstruction dry run ─────────────────────────────────────────────────
+//
+// `plan_reconstruction` goes through the analysis `reconstruct_chunked_mp4`
+// does — header boxes, tail detection, chunk order, placement checked against
+// the sample table, gaps — and reports what the reconstruction would write
+// instead of writing it. The chunks are read (their bodies decide which are
+// duplicate tails, and where the moov is) but only one is held at a time. The
+// UI shows the plan; the user can drop chunks or pick another gap policy and
+// plan again before reconstructing.
+//
+// The decisions themselves — where mdat lands, which slot each chunk takes,
+// what a gap turns into under the policy, how mdat is squared with its
+// declared size — are made by the pieces below, which the assembly calls
+// too. A plan can't drift from what gets written.
+
+/// The header file's ftyp and mdat boxes.
+#[derive(Debug)]
+pub(crate) struct HeaderBoxes {
+    pub ftyp_offset: usize,
+    pub ftyp_size: usize,
+    pub mdat_offset: usize,
+    pub mdat_declared_size: u64,
+    pub mdat_header_size: usize,
+}
+
+impl HeaderBoxes {
+    /// Both boxes, ftyp ending before mdat starts.
+    pub(crate) fn find(header: &[u8]) -> Result<Self, String> {
+        let (ftyp_offset, ftyp_size, _) =
+            crate::find_mp4_box(header, b"ftyp").ok_or_else(|| "No ftyp box found in header file".to_string())?;
+        let (mdat_offset, mdat_declared_size, mdat_header_size) =
+            crate::find_mp4_box(header, b"mdat").ok_or_else(|| "No mdat box found in header file".to_string())?;
+        let ftyp_size = usize::try_from(ftyp_size).map_err(|_| format!("ftyp box of {} bytes", ftyp_size))?;
+        let ftyp_end = ftyp_offset.checked_add(ftyp_size).filter(|&end| end <= header.len());
+        if ftyp_end.and_then(|end| mdat_offset.checked_sub(end)).is_none() {
+            return Err(format!(
+                "mdat box at {} in header file does not follow the ftyp box at {} ({} bytes)",
+                mdat_offset, ftyp_offset, ftyp_size
+            ));
+        }
+        Ok(HeaderBoxes { ftyp_offset, ftyp_size, mdat_offset, mdat_declared_size, mdat_header_size })
+    }
+
+    /// Offset of mdat in the output, which starts at ftyp.
+    pub(crate) fn mdat_start(&self) -> u64 {
+        (self.mdat_offset - self.ftyp_offset) as u64
+    }
+
+    /// Size of the first chunk-sized slot: the header from ftyp on.
+    pub(crate) fn slot_base(&self, header_len: u64) -> u64 {
+        header_len - self.ftyp_offset as u64
+    }
+}
+
+/// Slots of the middle chunks of a moov-at-end layout, from their hex
+/// numbers, checked against the tail moov's sample table when there is one.
+/// The check may reorder `middle`; the placements come back in its order.
+pub(crate) fn place_chunks(
+    header_path: &str,
+    slot_base: u64,
+    tail: Option<(&str, &[u8])>,
+    middle: &mut [String],
+    full_chunk_size: u64,
+) -> (Vec<Placement>, Option<LayoutCheck>) {
+    let header_hex = crate::parse_cache_hex(header_path);
+    let tail_hex = tail.and_then(|(path, _)| crate::parse_cache_hex(path));
+    let mut placements = chunk_layout::hex_placements(middle, header_hex, tail_hex);
+    let layout = tail
+        .and_then(|(_, data)| crate::scan_for_moov(data).map(|(off, sz)| crate::box_payload(data, off, sz)))
+        .and_then(|moov| chunk_layout::SampleLayout::from_moov(moov, slot_base, full_chunk_size));
+    let check = layout
+        .map(|layout| chunk_layout::check_placements(&layout, middle, &mut placements, crate::read_cache_body));
+    (placements, check)
+}
+
+/// What becomes of one placed chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Step {
+    /// Write the chunk, after the missing `gap` slots (if any) are padded or
+    /// dropped as the policy says
+    Write { gap: Option<Range<u64>> },
+    /// Truncate-at-gap: the output stops at the missing `gap` slots before
+    /// this chunk
+    Stop { gap: Range<u64> },
+    /// Past the point the output stopped at
+    Skip,
+}
+
+/// Walks placed chunks in write order and finds the missing slots between
+/// them. Anchored placements that jump ahead leave a gap; unanchored ones
+/// just follow the previous chunk.
+pub(crate) struct GapWalk {
+    policy: GapPolicy,
+    next_slot: u64,
+    stopped: bool,
+}
+
+impl GapWalk {
+    pub(crate) fn new(policy: GapPolicy) -> Self {
+        GapWalk { policy, next_slot: 0, stopped: false }
+    }
+
+    pub(crate) fn step(&mut self, placement: Placement) -> Step {
+        if self.stopped {
+            return Step::Skip;
+        }
+        let gap = Some(self.next_slot..placement.slot).filter(|gap| placement.anchored && !gap.is_empty());
+        if let (Some(gap), GapPolicy::TruncateAtGap) = (&gap, self.policy) {
+            self.stopped = true;
+            return Step::Stop { gap: gap.clone() };
+        }
+        self.next_slot = placement.slot.max(self.next_slot) + 1;
+        Step::Write { gap }
+    }
+
+    /// The slot after the last chunk written.
+    pub(crate) fn next_slot(&self) -> u64 {
+        self.next_slot
+    }
+}
+
+/// How an assembled mdat is squared with the size the header declared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MdatFit {
+    /// Short by this many bytes: zero-padded to the declared size, so the
+    /// moov's offsets stay valid
+    Pad(u64),
+    /// Short by this many bytes and the policy forbids padding: kept as is
+    Short(u64),
+    /// More than twice the declared size — a header whose mdat only covers
+    /// itself: grown to cover every chunk
+    Grow,
+    /// Longer by this many bytes: cut to the declared size
+    Cut(u64),
+    Exact,
+}
+
+impl MdatFit {
+    /// The fit for an output `len` bytes long so far, mdat starting at
+    /// `mdat_start` and declared `declared` bytes.
+    pub(crate) fn of(len: u64, mdat_start: u64, declared: u64, policy: GapPolicy) -> Self {
+        let target_end = mdat_start.saturating_add(declared);
+        if len < target_end {
+            return if policy.pads() { MdatFit::Pad(target_end - len) } else { MdatFit::Short(target_end - len) };
+        }
+        if len - mdat_start > declared.saturating_mul(2) {
+            MdatFit::Grow
+        } else if len > target_end {
+            MdatFit::Cut(len - target_end)
+        } else {
+            MdatFit::Exact
+        }
+    }
+
+    /// Size of the mdat box once fitted.
+    pub(crate) fn mdat_size(self, len: u64, mdat_start: u64, declared: u64) -> u64 {
+        match self {
+            MdatFit::Short(_) | MdatFit::Grow => len - mdat_start,
+            MdatFit::Pad(_) | MdatFit::Cut(_) | MdatFit::Exact => declared,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedLayout {
+    /// fMP4 init segment: the chunks are handed to the fragment reassembly
+    Fragmented,
+    /// Streaming layout: mdat rebuilt chunk by chunk, the tail's moov after it
+    MoovAtEnd,
+    /// The chunks are concatenated as they are
+    MoovAtFront,
+    /// No moov anywhere: concatenated, left for ffmpeg to make sense of
+    NoMoov,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkRole {
+    Header,
+    Media,
+    /// Holds the moov; its media goes at the end of mdat, the moov after it
+    Tail,
+    /// Another undersized chunk with a moov in it, skipped
+    DuplicateTail,
+    /// Not this video: an undersized chunk that starts like a different file
+    /// (WebM, PNG, ...) or a chunk whose bitstream is another stream, skipped
+    Foreign,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedChunk {
+    pub path: String,
+    /// Body size, past any Simple Cache wrapper
+    pub bytes: u64,
+    pub hex: Option<u64>,
+    pub role: ChunkRole,
+    /// Chunk-sized slot after the header (media chunks of a moov-at-end layout)
+    pub slot: Option<u64>,
+    /// The slot comes from a hex number or the sample table rather than from
+    /// following the previous chunk
+    pub anchored: bool,
+    /// Ends up in the output (false for skipped chunks and everything past a
+    /// truncating gap)
+    pub written: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedGap {
+    /// Chunk the missing range comes before
+    pub before: String,
+    pub missing_chunks: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReconstructionPlan {
+    pub layout: PlannedLayout,
+    pub gap_policy: GapPolicy,
+    pub ordering: Option<OrderingSignal>,
+    pub full_chunk_size: u64,
+    pub mdat_declared_size: Option<u64>,
+    /// The header, then the other chunks in the order they would be written
+    pub chunks: Vec<PlannedChunk>,
+    pub gaps: Vec<PlannedGap>,
+    pub layout_check: Option<LayoutCheck>,
+    pub moov_bytes: Option<u64>,
+    /// Output stops at the first gap (truncate_at_gap)
+    pub truncated: bool,
+    /// Size of the file the reconstruction would write
+    pub expected_size: u64,
+    pub notes: Vec<String>,
+}
+
+/// Offset of the last moov in header, media chunks and tail laid end to end
+/// (duplicate tails left out), and the length of that concatenation: where
+/// the moov sits decides the layout, for the assembly and the plan alike.
+pub(crate) fn moov_position(
+    header: &[u8],
+    middle: &[String],
+    tail: Option<&str>,
+    facts: &HashMap<String, crate::ChunkFacts>,
+    full_chunk_size: u64,
+) -> (Option<u64>, u64) {
+    let mut concat_len = header.len() as u64;
+    let mut last_moov = crate::scan_for_moov(header).map(|(off, _)| off as u64);
+    let media = middle.iter().map(|path| &facts[path]).filter(|c| !c.is_duplicate_tail(full_chunk_size));
+    for c in media.chain(tail.map(|path| &facts[path])) {
+        if let Some((off, _)) = c.moov {
+            last_moov = Some(concat_len + off as u64);
+        }
+        concat_len += c.bytes;
+    }
+    (last_moov, concat_len)
+}
+
+fn chunk(path: &str, bytes: u64, role: ChunkRole, written: bool) -> PlannedChunk {
+    let hex = crate::parse_cache_hex(path);
+    PlannedChunk { path: path.to_string(), bytes, hex, role, slot: None, anchored: false, written }
+}
+
+fn plan(header_path: &str, chunk_paths: &[String], gap_policy: GapPolicy) -> Result<ReconstructionPlan, String> {
+    let header_data = crate::read_cache_body(header_path)?;
+    let header_len = header_data.len() as u64;
+    let mut plan = ReconstructionPlan {
+        layout: PlannedLayout::NoMoov,
+        gap_policy,
+        ordering: None,
+        full_chunk_size: 0,
+        mdat_declared_size: None,
+        chunks: vec![chunk(header_path, header_len, ChunkRole::Header, true)],
+        gaps: Vec::new(),
+        layout_check: None,
+        moov_bytes: None,
+        truncated: false,
+        expected_size: 0,
+        notes: Vec::new(),
+    };
+
+    if crate::fmp4::is_init_segment(&header_data) {
+        plan.layout = PlannedLayout::Fragmented;
+        plan.expected_size = header_len;
+        for path in chunk_paths {
+            let bytes = std::fs::metadata(path).map_err(|e| format!("Failed to stat {}: {}", path, e))?.len();
+            plan.expected_size += bytes;
+            plan.chunks.push(chunk(path, bytes, ChunkRole::Media, true));
+        }
+        plan.notes.push("Header is an fMP4 init segment: fragments are ordered by moof sequence number, \
+                         so the size is an upper bound"
+            .to_string());
+        return Ok(plan);
+    }
+
+    let boxes = HeaderBoxes::find(&header_data)?;
+    plan.mdat_declared_size = Some(boxes.mdat_declared_size);
+
+    let full_chunk_size = crate::full_chunk_size(header_path, header_len, chunk_paths)?;
+    plan.full_chunk_size = full_chunk_size;
+    let sort = crate::sort_chunks(&header_data, chunk_paths, full_chunk_size)?;
+    let info = &sort.facts;
+    let mut middle_paths = sort.middle;
+    plan.ordering = Some(reconstruction::order_chunks(&mut middle_paths));
+
+    let (last_moov, concat_len) =
+        moov_position(&header_data, &middle_paths, sort.tail.as_deref(), info, full_chunk_size);
+    let foreign = sort.foreign.iter().map(|path| chunk(path, info[path].bytes, ChunkRole::Foreign, false));
+
+    let moov_at_end = match last_moov {
+        Some(offset) => offset > concat_len / 2,
+        None => {
+            plan.notes.push("No moov found: the chunks are concatenated as they are".to_string());
+            false
+        }
+    };
+    if !moov_at_end {
+        if last_moov.is_some() {
+            plan.layout = PlannedLayout::MoovAtFront;
+        }
+        for path in &middle_paths {
+            let c = &info[path];
+            let skipped = c.is_duplicate_tail(full_chunk_size);
+            let role = if skipped { ChunkRole::DuplicateTail } else { ChunkRole::Media };
+            plan.chunks.push(chunk(path, c.bytes, role, !skipped));
+        }
+        if let Some(path) = &sort.tail {
+            plan.chunks.push(chunk(path, info[path].bytes, ChunkRole::Tail, true));
+        }
+        plan.chunks.extend(foreign);
+        plan.expected_size = concat_len;
+        return Ok(plan);
+    }
+    plan.layout = PlannedLayout::MoovAtEnd;
+
+    // The layout check needs the moov itself; that is the one more read.
+    let tail_data = sort.tail.as_deref().map(crate::read_cache_body).transpose()?;
+    let tail = sort.tail.as_deref().zip(tail_data.as_deref());
+    let slot_base = boxes.slot_base(header_len);
+    let (placements, check) = place_chunks(header_path, slot_base, tail, &mut middle_paths, full_chunk_size);
+    plan.layout_check = check;
+
+    let mut size = slot_base;
+    let mut walk = GapWalk::new(gap_policy);
+    for (path, placement) in middle_paths.iter().zip(placements) {
+        let c = &info[path];
+        if c.is_duplicate_tail(full_chunk_size) {
+            plan.chunks.push(chunk(path, c.bytes, ChunkRole::DuplicateTail, false));
+            continue;
+        }
+        let mut planned = chunk(path, c.bytes, ChunkRole::Media, false);
+        planned.slot = Some(placement.slot);
+        planned.anchored = placement.anchored;
+        let gap = match walk.step(placement) {
+            Step::Write { gap } => {
+                planned.written = true;
+                size += c.bytes;
+                gap
+            }
+            Step::Stop { gap } => {
+                plan.truncated = true;
+                Some(gap)
+            }
+            Step::Skip => None,
+        };
+        if let Some(gap) = gap {
+            let missing_chunks = gap.end - gap.start;
+            let bytes = missing_chunks * full_chunk_size;
+            plan.gaps.push(PlannedGap { before: path.clone(), missing_chunks, bytes });
+            if gap_policy.pads() {
+                size += bytes;
+            }
+        }
+        plan.chunks.push(planned);
+    }
+
+    if let Some((path, data)) = tail {
+        let tail_moov = info[path].moov;
+        let video = tail_moov.map_or(data.len(), |(off, _)| off) as u64;
+        if !plan.truncated {
+            size += video;
+        }
+        plan.chunks.push(chunk(path, data.len() as u64, ChunkRole::Tail, true));
+        plan.moov_bytes = tail_moov.map(|(_, sz)| sz as u64);
+    }
+    plan.chunks.extend(foreign);
+
+    // mdat against its declared size, as the assembly squares it.
+    let (mdat_start, declared) = (boxes.mdat_start(), boxes.mdat_declared_size);
+    let fit = MdatFit::of(size, mdat_start, declared, gap_policy);
+    let mdat_size = fit.mdat_size(size, mdat_start, declared);
+    match fit {
+        MdatFit::Pad(pad) => {
+            plan.notes.push(format!("mdat padded with {} zero bytes to its declared size", pad));
+            size += pad;
+        }
+        MdatFit::Short(short) => plan.notes.push(format!("mdat is {} bytes short of its declared size", short)),
+        MdatFit::Grow => plan.notes.push("mdat grows past its declared size to cover every chunk".to_string()),
+        MdatFit::Cut(cut) => {
+            plan.notes.push(format!("{} bytes past the declared mdat size are cut", cut));
+            size -= cut;
+        }
+        MdatFit::Exact => {}
+    }
+    if boxes.mdat_header_size == 8 && mdat_size > u32::MAX as u64 {
+        plan.notes.push("mdat header widened to 64 bits".to_string());
+        size += 8;
+    }
+    plan.expected_size = size + plan.moov_bytes.unwrap_or(0);
+    Ok(plan)
+}
+
+/// Everything `reconstruct_chunked_mp4` would do with `header_path` and
+/// `chunk_paths` under `gap_policy`, without writing the output.
+#[tauri::command]
+pub fn plan_reconstruction(
+    header_path: String,
+    chunk_paths: Vec<String>,
+    gap_policy: Option<GapPolicy>,
+) -> Result<ReconstructionPlan, String> {
+    let _job = crate::crash::job(format!("planning reconstruction of {}", header_path));
+    let plan = plan(&header_path, &chunk_paths, gap_policy.unwrap_or_default())?;
+    println!(
+        "[plan_reconstruction] {}: {:?}, {} chunk(s), {} gap(s), {} bytes expected",
+        header_path,
+        plan.layout,
+        plan.chunks.len(),
+        plan.gaps.len(),
+        plan.expected_size
+    );
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moov_rebuild::mp4_box;
+
+    #[test]
+    fn plan_matches_what_the_reconstruction_writes() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, data).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &[0; 100]), mp4_box(b"trak", &[0; 492])].concat());
+        // The header's mdat declares the whole upload: its own 976 bytes,
+        // three chunks and the tail's 100 bytes of media. f_000003 is missing.
+        let mut header = mp4_box(b"ftyp", b"isom\0\0\0\0isomiso2");
+        header.extend_from_slice(&(8u32 + 976 + 3 * 1000 + 100).to_be_bytes());
+        header.extend_from_slice(b"mdat");
+        header.extend_from_slice(&[1; 976]);
+        let header = write("f_000001", &header);
+        let chunks = vec![
+            write("f_000002", &[2; 1000]),
+            write("f_000004", &[4; 1000]),
+            write("f_000005", &[&[5u8; 100][..], &moov].concat()),
+        ];
+
+        let out = dir.join("out.mp4").to_string_lossy().to_string();
+        for (policy, media) in [(GapPolicy::PadZeros, 4108), (GapPolicy::TruncateAtGap, 2008)] {
+            let plan = plan(&header, &chunks, policy).unwrap();
+            assert_eq!(plan.layout, PlannedLayout::MoovAtEnd);
+            assert_eq!((plan.gaps.len(), plan.gaps[0].missing_chunks), (1, 1));
+            assert_eq!(plan.expected_size, (media + moov.len()) as u64);
+            assert_eq!(plan.truncated, policy == GapPolicy::TruncateAtGap);
+            assert_eq!(plan.chunks.last().map(|c| c.role), Some(ChunkRole::Tail));
+
+            let result =
+                crate::assemble_chunked_mp4(header.clone(), chunks.clone(), out.clone(), Some(policy)).unwrap();
+            assert_eq!(std::fs::metadata(&out).unwrap().len(), plan.expected_size);
+            let ranges: Vec<_> = result.gap_ranges.iter().map(|r| (r.bytes, r.missing_chunks.join(","))).collect();
+            assert_eq!(ranges, [(1000, "f_000003".to_string())]);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn pads_missing_end_chunks_in_front_of_the_tail_media() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-end-pad-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, data).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &[0; 100]), mp4_box(b"trak", &[0; 492])].concat());
+        // f_000003 is the last full chunk and it is gone; nothing after it
+        // says so but the declared mdat size.
+        let mut header = mp4_box(b"ftyp", b"isom\0\0\0\0isomiso2");
+        header.extend_from_slice(&(8u32 + 976 + 2 * 1000 + 100).to_be_bytes());
+        header.extend_from_slice(b"mdat");
+        header.extend_from_slice(&[1; 976]);
+        let header = write("f_000001", &header);
+        let chunks = vec![write("f_000002", &[2; 1000]), write("f_000004", &[&[4u8; 100][..], &moov].concat())];
+
+        let out = dir.join("out.mp4").to_string_lossy().to_string();
+        let result = crate::assemble_chunked_mp4(header, chunks, out.clone(), None).unwrap();
+        let data = std::fs::read(&out).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let media_end = 24 + 8 + 976 + 1000;
+        assert_eq!((result.gap_ranges[0].offset, result.gap_ranges[0].bytes), (media_end, 1000));
+        assert!(data[media_end as usize..media_end as usize + 1000].iter().all(|&b| b == 0));
+        assert_eq!(&data[media_end as usize + 1000..media_end as usize + 1100], &[4u8; 100]);
+    }
+
+    #[test]
+    fn refuses_a_header_with_mdat_before_ftyp() {
+        let header = [mp4_box(b"mdat", &[0; 16]), mp4_box(b"ftyp", b"isom\0\0\0\0")].concat();
+        assert!(HeaderBoxes::find(&header).unwrap_err().contains("does not follow the ftyp box"));
+        assert_eq!(MdatFit::of(100, 40, 100, GapPolicy::PadZeros), MdatFit::Pad(40));
+        assert_eq!(MdatFit::of(100, 40, 100, GapPolicy::CloseGaps), MdatFit::Short(40));
+        assert_eq!(MdatFit::of(100, 40, u64::MAX, GapPolicy::PadZeros), MdatFit::Pad(u64::MAX - 100));
+    }
+}
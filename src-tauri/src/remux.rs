@@ -0,0 +1,154 @@
+use serde::Serialize;
+
+use crate::output_file::PART_SUFFIX;
+
+// ─── MP4 remux ──────────────────────────────────────────────────────────────
+//
+// Stream-copies a video into a fresh MP4 through the ffmpeg sidecar: the
+// container is rewritten (timestamps regenerated, corrupt packets dropped)
+// while the audio and video data is kept as is. With faststart the moov is
+// moved in front of mdat so players can start before the whole file is read.
+// ffmpeg writes to a `.cachephoenix-part` file next to the output, which is
+// renamed over the output only once the remux succeeded; a failed run removes
+// the part file and leaves whatever was at the output untouched. Its
+// `-progress pipe:1` report (key=value lines closed by a `progress=` line per
+// update) gives the length of what was kept.
+
+#[derive(Debug, Serialize)]
+pub struct RemuxResult {
+    pub output: String,
+    pub bytes: u64,
+    /// Last timestamp written: the length of what was kept
+    pub duration_secs: Option<f64>,
+    pub faststart: bool,
+}
+
+/// Collects `-progress` key=value lines across stdout pieces.
+#[derive(Default)]
+struct ProgressParser {
+    line: Vec<u8>,
+    /// Position of the last packet written
+    out_time_secs: f64,
+    /// ffmpeg sent its final `progress=end` block
+    done: bool,
+}
+
+impl ProgressParser {
+    /// Feed a piece of stdout.
+    fn feed(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            if b != b'\n' {
+                self.line.push(b);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).trim().to_string();
+            self.line.clear();
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key {
+                "out_time_us" => {
+                    if let Ok(us) = value.parse::<i64>() {
+                        self.out_time_secs = us.max(0) as f64 / 1_000_000.0;
+                    }
+                }
+                "progress" => self.done = value == "end",
+                _ => {}
+            }
+        }
+    }
+}
+
+fn remux_args(input: &str, output: &str, faststart: bool) -> Vec<String> {
+    let mut args: Vec<&str> = vec![
+        "-y", "-nostats", "-v", "error",
+        "-progress", "pipe:1",
+        "-fflags", "+genpts+discardcorrupt+igndts",
+        "-analyzeduration", "100M",
+        "-probesize", "100M",
+        "-err_detect", "ignore_err",
+        "-i", input,
+        "-c", "copy",
+    ];
+    if faststart {
+        args.extend(["-movflags", "+faststart"]);
+    }
+    args.extend(["-max_muxing_queue_size", "1024", "-f", "mp4", output]);
+    args.into_iter().map(String::from).collect()
+}
+
+/// Stream-copy `input` into a new MP4 at `output`, moving the moov to the
+/// front when `faststart` is set.
+/// Needs a `consent` token covering `output` unless it is a temp scratch file.
+#[tauri::command]
+pub async fn remux_mp4(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    input: String,
+    output: String,
+    faststart: bool,
+    consent: Option<String>,
+) -> Result<RemuxResult, String> {
+    let output = state.check_output(consent.as_deref(), &output)?;
+    if !std::path::Path::new(&input).is_file() {
+        return Err(format!("Not a file: {}", input));
+    }
+    // ffmpeg would truncate its own input before reading it.
+    let canonical = |p: &str| std::fs::canonicalize(p).ok();
+    if input == output || canonical(&output).is_some_and(|out| Some(out) == canonical(&input)) {
+        return Err(format!("Remux output must differ from its input: {}", output));
+    }
+
+    let part = format!("{}{}", output, PART_SUFFIX);
+    let mut parser = ProgressParser::default();
+    let args = remux_args(&input, &part, faststart);
+    let result = crate::sidecar::run_with(&app, "ffmpeg", &args, "remux", &input, |bytes| parser.feed(bytes)).await;
+
+    let failure = match &result {
+        Ok(run) if run.success() => None,
+        Ok(run) => Some(
+            run.stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("ffmpeg failed").trim().to_string(),
+        ),
+        Err(e) => Some(e.clone()),
+    };
+    let bytes = std::fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+    let failure = failure
+        .or_else(|| (bytes == 0).then(|| "nothing was written".to_string()))
+        .or_else(|| std::fs::rename(&part, &output).err().map(|e| format!("could not move it into place: {}", e)));
+    if let Some(reason) = failure {
+        let _ = std::fs::remove_file(&part);
+        eprintln!("[remux_mp4] {} failed: {}", input, reason);
+        return Err(format!("Remux of {} failed: {}", input, reason));
+    }
+
+    let duration_secs = Some(parser.out_time_secs).filter(|d| *d > 0.0);
+    println!(
+        "[remux_mp4] {} -> {} ({} bytes, {:?}s, faststart={})",
+        input, output, bytes, duration_secs, faststart
+    );
+    Ok(RemuxResult { output, bytes, duration_secs, faststart })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_blocks_survive_split_pieces() {
+        let mut parser = ProgressParser::default();
+        let stream = b"frame=10\ntotal_size=4096\nout_time_us=1500000\nspeed=2.5x\nprogress=continue\n\
+                       total_size=8192\nout_time_us=N/A\nspeed=N/A\nprogress=end\n";
+        let (first, rest) = stream.split_at(30);
+        parser.feed(first);
+        assert_eq!(parser.out_time_secs, 0.0);
+        parser.feed(&rest[..60]);
+        assert_eq!(parser.out_time_secs, 1.5);
+        assert!(!parser.done);
+        parser.feed(&rest[60..]);
+        // N/A keeps the last known position.
+        assert_eq!(parser.out_time_secs, 1.5);
+        assert!(parser.done);
+
+        let args = remux_args("in.mp4", "out.mp4", false);
+        assert!(!args.iter().any(|a| a == "+faststart"));
+        assert!(remux_args("in.mp4", "out.mp4", true).iter().any(|a| a == "+faststart"));
+    }
+}
@@ -0,0 +1,333 @@
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+// ─── Safari Cache.db ────────────────────────────────────────────────────────
+//
+// Safari (and every app using NSURLCache) keeps its HTTP cache in a SQLite
+// database, `Cache.db`:
+//   - cfurl_cache_response       one row per entry: entry_ID, request_key (URL),
+//                                time_stamp ("YYYY-MM-DD HH:MM:SS", UTC)
+//   - cfurl_cache_blob_data      response_object: archived NSHTTPURLResponse
+//   - cfurl_cache_receiver_data  the body. If isDataOnFS is 1, receiver_data
+//                                holds a file name inside `fsCachedData/`
+//                                next to Cache.db instead of the bytes.
+// Large media almost always lives in fsCachedData. The database is read from
+// a temp snapshot (Cache.db plus its -wal/-shm) so a running Safari is never
+// blocked and uncommitted WAL pages are still seen.
+
+const CACHE_DB: &str = "Cache.db";
+const FS_CACHED_DATA: &str = "fsCachedData";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SafariCacheEntry {
+    pub entry_id: i64,
+    pub url: String,
+    /// UTC, as stored: "YYYY-MM-DD HH:MM:SS"
+    pub time_stamp: Option<String>,
+    /// Body size in bytes (0 if the body is missing)
+    pub size: u64,
+    /// "inline" (bytes in the database) or "fs_cached_data"
+    pub storage: &'static str,
+    /// fsCachedData file holding the body, when `storage` is "fs_cached_data"
+    pub data_path: Option<String>,
+    /// Content-Type found in the archived response, if any
+    pub content_type: Option<String>,
+    /// MIME type detected from the body's magic bytes
+    pub sniffed_mime: Option<&'static str>,
+    /// "video", "image" or "audio" when the body is recognizable media
+    pub category: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SafariEntryList {
+    pub db_path: String,
+    pub entries: Vec<SafariCacheEntry>,
+    pub errors: Vec<String>,
+}
+
+/// Where a body lives.
+enum Body {
+    Inline(Vec<u8>),
+    File(PathBuf),
+    Missing,
+}
+
+/// Default Cache.db locations on macOS: Safari (sandboxed and legacy paths).
+pub fn default_safari_paths() -> Vec<String> {
+    #[cfg_attr(not(target_os = "macos"), allow(unused_mut))]
+    let mut paths = Vec::new();
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(home) = std::env::var("HOME") {
+            let home = PathBuf::from(home);
+            for dir in [
+                "Library/Containers/com.apple.Safari/Data/Library/Caches/com.apple.Safari",
+                "Library/Caches/com.apple.Safari",
+            ] {
+                paths.push(home.join(dir).join(CACHE_DB).to_string_lossy().to_string());
+            }
+        }
+    }
+    paths
+}
+
+/// Accept either the Cache.db file or the folder containing it.
+fn resolve_db_path(path: &str) -> Result<PathBuf, String> {
+    let p = Path::new(path);
+    let db = if p.is_dir() { p.join(CACHE_DB) } else { p.to_path_buf() };
+    if !db.is_file() {
+        return Err(format!("Safari Cache.db not found: {}", db.display()));
+    }
+    Ok(db)
+}
+
+/// Copy Cache.db and its WAL/shared-memory files into a private temp folder
+/// and open the copy read-only.
+fn open_snapshot(db: &Path) -> Result<(Connection, PathBuf), String> {
+    let snapshot_dir = std::env::temp_dir().join(format!(
+        "cachephoenix-safari-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+    ));
+    std::fs::create_dir_all(&snapshot_dir)
+        .map_err(|e| format!("Failed to create dir {}: {}", snapshot_dir.display(), e))?;
+    let copy = snapshot_dir.join(CACHE_DB);
+    let data = crate::read_with_lock_retry(&db.to_string_lossy())
+        .map_err(|e| crate::format_read_error(&db.to_string_lossy(), &e))?;
+    std::fs::write(&copy, data).map_err(|e| format!("Failed to write {}: {}", copy.display(), e))?;
+    for suffix in ["-wal", "-shm"] {
+        let side = PathBuf::from(format!("{}{}", db.display(), suffix));
+        if side.is_file() {
+            if let Err(e) = std::fs::copy(&side, snapshot_dir.join(format!("{}{}", CACHE_DB, suffix))) {
+                eprintln!("[safari] Failed to copy {}: {}", side.display(), e);
+            }
+        }
+    }
+    let conn = Connection::open_with_flags(&copy, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", db.display(), e))?;
+    Ok((conn, snapshot_dir))
+}
+
+/// Pull a Content-Type value out of an archived NSHTTPURLResponse. The
+/// archive is a binary plist whose strings are stored verbatim, so the first
+/// "type/subtype" token for a media or common document type is the header value.
+fn declared_content_type(response_object: &[u8]) -> Option<String> {
+    const PREFIXES: &[&[u8]] = &[b"video/", b"audio/", b"image/", b"application/", b"text/"];
+    for start in 0..response_object.len() {
+        let rest = &response_object[start..];
+        if !PREFIXES.iter().any(|p| rest.starts_with(p)) {
+            continue;
+        }
+        let end = rest
+            .iter()
+            .position(|&b| !(b.is_ascii_alphanumeric() || b"/.+-_".contains(&b)))
+            .unwrap_or(rest.len());
+        let value = &rest[..end];
+        if value.len() > value.iter().position(|&b| b == b'/').unwrap_or(0) + 1 {
+            return Some(String::from_utf8_lossy(value).to_lowercase());
+        }
+    }
+    None
+}
+
+fn body_location(db: &Path, on_fs: bool, receiver_data: Option<Vec<u8>>) -> Body {
+    match receiver_data {
+        None => Body::Missing,
+        Some(data) if on_fs => {
+            let name = String::from_utf8_lossy(&data).trim_end_matches('\0').trim().to_string();
+            if name.is_empty() || name.contains(['/', '\\']) {
+                return Body::Missing;
+            }
+            let dir = db.parent().unwrap_or(Path::new("."));
+            Body::File(dir.join(FS_CACHED_DATA).join(name))
+        }
+        Some(data) => Body::Inline(data),
+    }
+}
+
+fn read_body_start(body: &Body) -> Option<Vec<u8>> {
+    match body {
+        Body::Inline(data) => Some(data[..data.len().min(64)].to_vec()),
+        Body::File(path) => crate::read_file_header(path.to_string_lossy().to_string(), 64).ok(),
+        Body::Missing => None,
+    }
+}
+
+fn list_entries(db: &Path) -> Result<SafariEntryList, String> {
+    let (conn, snapshot_dir) = open_snapshot(db)?;
+    let result = query_entries(&conn, db);
+    drop(conn);
+    let _ = std::fs::remove_dir_all(&snapshot_dir);
+    result
+}
+
+fn query_entries(conn: &Connection, db: &Path) -> Result<SafariEntryList, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.entry_ID, r.request_key, r.time_stamp, d.isDataOnFS, d.receiver_data, b.response_object
+             FROM cfurl_cache_response r
+             LEFT JOIN cfurl_cache_receiver_data d ON d.entry_ID = r.entry_ID
+             LEFT JOIN cfurl_cache_blob_data b ON b.entry_ID = r.entry_ID
+             ORDER BY r.entry_ID",
+        )
+        .map_err(|e| format!("Failed to query {}: {}", db.display(), e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<Vec<u8>>>(4)?,
+                row.get::<_, Option<Vec<u8>>>(5)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to query {}: {}", db.display(), e))?;
+
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for row in rows {
+        let (entry_id, url, time_stamp, on_fs, receiver_data, response_object) = match row {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(format!("Failed to read row: {}", e));
+                continue;
+            }
+        };
+        let body = body_location(db, on_fs.unwrap_or(0) != 0, receiver_data);
+        let (storage, size, data_path) = match &body {
+            Body::Inline(data) => ("inline", data.len() as u64, None),
+            Body::File(path) => {
+                let size = match std::fs::metadata(path) {
+                    Ok(m) => m.len(),
+                    Err(e) => {
+                        errors.push(format!("Entry {}: {}: {}", entry_id, path.display(), e));
+                        0
+                    }
+                };
+                ("fs_cached_data", size, Some(path.to_string_lossy().to_string()))
+            }
+            Body::Missing => ("inline", 0, None),
+        };
+        let sniffed = read_body_start(&body).and_then(|d| crate::quick_scan::sniff(&d));
+        entries.push(SafariCacheEntry {
+            entry_id,
+            url: url.unwrap_or_default(),
+            time_stamp,
+            size,
+            storage,
+            data_path,
+            content_type: response_object.as_deref().and_then(declared_content_type),
+            sniffed_mime: sniffed.map(|s| s.0),
+            category: sniffed.map(|s| s.1),
+        });
+    }
+    Ok(SafariEntryList {
+        db_path: db.to_string_lossy().to_string(),
+        entries,
+        errors,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Default Safari Cache.db paths for this OS (empty outside macOS).
+#[tauri::command]
+pub fn get_default_safari_paths() -> Vec<String> {
+    default_safari_paths()
+}
+
+/// List every entry in a Safari Cache.db (`path` is the file or its folder).
+#[tauri::command(async)]
+pub fn list_safari_entries(path: String) -> Result<SafariEntryList, String> {
+    let db = resolve_db_path(&path)?;
+    let list = list_entries(&db)?;
+    println!(
+        "[safari] {} entries in {} ({} errors)",
+        list.entries.len(),
+        list.db_path,
+        list.errors.len()
+    );
+    Ok(list)
+}
+
+/// Write one entry's body to `dst`. Returns bytes written. Requires a
+/// `consent` token covering `dst`.
+#[tauri::command(async)]
+pub fn extract_safari_entry(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    path: String,
+    entry_id: i64,
+    dst: String,
+    consent: String,
+) -> Result<u64, String> {
+    let dst = state.check(&consent, &dst)?;
+    let db = resolve_db_path(&path)?;
+    let (conn, snapshot_dir) = open_snapshot(&db)?;
+    let row = conn
+        .query_row(
+            "SELECT isDataOnFS, receiver_data FROM cfurl_cache_receiver_data WHERE entry_ID = ?1",
+            [entry_id],
+            |row| Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<Vec<u8>>>(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to query {}: {}", db.display(), e));
+    drop(conn);
+    let _ = std::fs::remove_dir_all(&snapshot_dir);
+
+    let (on_fs, receiver_data) = row?.ok_or_else(|| format!("No body stored for entry {}", entry_id))?;
+    match body_location(&db, on_fs.unwrap_or(0) != 0, receiver_data) {
+        Body::Inline(data) => crate::output_file::write_output(&dst, &data),
+        Body::File(src) => {
+            let src = src.to_string_lossy();
+            let data = crate::read_with_lock_retry(&src).map_err(|e| crate::format_read_error(&src, &e))?;
+            crate::output_file::write_output(&dst, &data)
+        }
+        Body::Missing => Err(format!("No body stored for entry {}", entry_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_inline_and_fs_cached_entries() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-safari-unit-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join(FS_CACHED_DATA)).unwrap();
+        let db = dir.join(CACHE_DB);
+        let conn = Connection::open(&db).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE cfurl_cache_response(entry_ID INTEGER PRIMARY KEY, request_key TEXT, time_stamp TEXT);
+             CREATE TABLE cfurl_cache_blob_data(entry_ID INTEGER, response_object BLOB);
+             CREATE TABLE cfurl_cache_receiver_data(entry_ID INTEGER, isDataOnFS INTEGER, receiver_data BLOB);
+             INSERT INTO cfurl_cache_response VALUES (1, 'https://example.com/a.png', '2024-01-17 10:00:00');
+             INSERT INTO cfurl_cache_response VALUES (2, 'https://example.com/b.mp4', '2024-01-17 10:00:01');
+             INSERT INTO cfurl_cache_blob_data VALUES (2, X'5C436F6E74656E742D547970655900766964656F2F6D7034');
+             INSERT INTO cfurl_cache_receiver_data VALUES (1, 0, X'89504E470D0A1A0A');
+             INSERT INTO cfurl_cache_receiver_data VALUES (2, 1, CAST('ABC-123' AS BLOB));",
+        )
+        .unwrap();
+        drop(conn);
+        let mp4 = b"\0\0\0\x14ftypisom\0\0\x02\0isom";
+        std::fs::write(dir.join(FS_CACHED_DATA).join("ABC-123"), mp4).unwrap();
+
+        let list = list_entries(&db).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(list.errors.is_empty(), "{:?}", list.errors);
+        assert_eq!(list.entries.len(), 2);
+        let png = &list.entries[0];
+        assert_eq!((png.storage, png.size, png.category), ("inline", 8, Some("image")));
+        let video = &list.entries[1];
+        assert_eq!(video.storage, "fs_cached_data");
+        assert_eq!(video.size, mp4.len() as u64);
+        assert_eq!(video.content_type.as_deref(), Some("video/mp4"));
+        assert_eq!(video.sniffed_mime, Some("video/mp4"));
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// ─── Saved originals ────────────────────────────────────────────────────────
+//
+// An attachment the user once saved from Discord ("Save media", or opened and
+// downloaded) may still be sitting in their Downloads folder in full, while
+// the cache only holds part of it. `find_saved_originals` looks for files
+// named like recovered attachments in the Downloads folder and in any
+// download folder named in Discord's settings.json, so the UI can point at
+// the original instead of a partial reconstruction.
+
+/// Folder levels searched below each root (Downloads/<sub>/<file>).
+const MAX_DEPTH: usize = 2;
+/// Stop listing a root after this many entries.
+const MAX_ENTRIES_PER_ROOT: usize = 100_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedOriginal {
+    /// The attachment filename that matched
+    pub filename: String,
+    pub path: String,
+    pub size: u64,
+    /// Unix seconds
+    pub modified_at: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedOriginalsResult {
+    pub matches: Vec<SavedOriginal>,
+    pub searched_dirs: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Match key for a file name: lowercased, with a browser-style duplicate
+/// suffix removed ("clip (2).mp4" → "clip.mp4").
+fn match_key(name: &str) -> String {
+    let lower = name.to_lowercase();
+    let (stem, ext) = match lower.rfind('.') {
+        Some(dot) if dot > 0 => (&lower[..dot], &lower[dot..]),
+        _ => (lower.as_str(), ""),
+    };
+    if let Some(open) = stem.rfind(" (") {
+        let inner = &stem[open + 2..];
+        if inner.len() > 1 && inner.ends_with(')') && inner[..inner.len() - 1].chars().all(|c| c.is_ascii_digit()) {
+            return format!("{}{}", &stem[..open], ext);
+        }
+    }
+    lower
+}
+
+/// The user's Downloads folder (XDG_DOWNLOAD_DIR on Linux when set).
+fn downloads_dir() -> Option<PathBuf> {
+    let home = std::env::var("USERPROFILE").or_else(|_| std::env::var("HOME")).ok()?;
+    let home = PathBuf::from(home);
+    if cfg!(target_os = "linux") {
+        if let Ok(dirs) = std::fs::read_to_string(home.join(".config/user-dirs.dirs")) {
+            for line in dirs.lines() {
+                if let Some(value) = line.strip_prefix("XDG_DOWNLOAD_DIR=") {
+                    let value = value.trim().trim_matches('"').replace("$HOME", &home.to_string_lossy());
+                    return Some(PathBuf::from(value));
+                }
+            }
+        }
+    }
+    Some(home.join("Downloads"))
+}
+
+/// Folders named in Discord's settings.json under any "download" key.
+/// The key isn't documented, so every string value whose key mentions
+/// downloads and that names an existing folder is taken.
+fn discord_download_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let settings_roots: Vec<PathBuf> = crate::cache::get_default_cache_paths()
+        .iter()
+        .filter(|p| p.to_lowercase().contains("discord"))
+        .filter_map(|p| {
+            // <root>/discord*/Cache/Cache_Data → <root>/discord*
+            Path::new(p).ancestors().find(|a| {
+                a.file_name().is_some_and(|n| n.to_string_lossy().to_lowercase().starts_with("discord"))
+            })
+            .map(Path::to_path_buf)
+        })
+        .collect();
+    for root in settings_roots {
+        let Ok(text) = std::fs::read_to_string(root.join("settings.json")) else {
+            continue;
+        };
+        let Ok(serde_json::Value::Object(settings)) = serde_json::from_str::<serde_json::Value>(&text) else {
+            continue;
+        };
+        for (key, value) in settings {
+            if let (true, Some(dir)) = (key.to_lowercase().contains("download"), value.as_str()) {
+                let dir = PathBuf::from(dir);
+                if dir.is_dir() && !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+    dirs
+}
+
+fn search(
+    dir: &Path,
+    depth: usize,
+    wanted: &HashMap<String, String>,
+    seen: &mut usize,
+    matches: &mut Vec<SavedOriginal>,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        *seen += 1;
+        if *seen > MAX_ENTRIES_PER_ROOT {
+            return;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            if depth < MAX_DEPTH {
+                search(&entry.path(), depth + 1, wanted, seen, matches);
+            }
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(filename) = wanted.get(&match_key(&name)) {
+            matches.push(SavedOriginal {
+                filename: filename.clone(),
+                path: entry.path().to_string_lossy().to_string(),
+                size: meta.len(),
+                modified_at: meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs_f64())
+                    .unwrap_or(0.0),
+            });
+        }
+    }
+}
+
+/// Look for already-downloaded copies of `filenames` in the Downloads folder,
+/// Discord's configured download folder, and `extra_dirs`.
+#[tauri::command(async)]
+pub fn find_saved_originals(
+    filenames: Vec<String>,
+    extra_dirs: Option<Vec<String>>,
+) -> Result<SavedOriginalsResult, String> {
+    let mut roots: Vec<PathBuf> = downloads_dir().into_iter().collect();
+    roots.extend(discord_download_dirs());
+    roots.extend(
+        extra_dirs
+            .unwrap_or_default()
+            .iter()
+            .map(|d| PathBuf::from(crate::path_display::expand_path(d))),
+    );
+    Ok(find_in(roots, &filenames))
+}
+
+/// Search `roots` for copies of `filenames`.
+fn find_in(mut roots: Vec<PathBuf>, filenames: &[String]) -> SavedOriginalsResult {
+    let wanted: HashMap<String, String> = filenames
+        .iter()
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| (match_key(f), f.clone()))
+        .collect();
+    roots.sort();
+    roots.dedup();
+
+    let mut result = SavedOriginalsResult { matches: Vec::new(), searched_dirs: Vec::new(), errors: Vec::new() };
+    if wanted.is_empty() {
+        return result;
+    }
+    for root in roots {
+        if !root.is_dir() {
+            continue;
+        }
+        let mut seen = 0usize;
+        search(&root, 0, &wanted, &mut seen, &mut result.matches);
+        if seen > MAX_ENTRIES_PER_ROOT {
+            result.errors.push(format!("Stopped after {} entries in {}", MAX_ENTRIES_PER_ROOT, root.display()));
+        }
+        result.searched_dirs.push(root.to_string_lossy().to_string());
+    }
+    println!(
+        "[saved_media] {} of {} filenames found in {} folders",
+        result.matches.len(),
+        wanted.len(),
+        result.searched_dirs.len()
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_renamed_duplicates() {
+        assert_eq!(match_key("Clip (2).MP4"), "clip.mp4");
+        assert_eq!(match_key("scores (final).png"), "scores (final).png");
+
+        let dir = std::env::temp_dir().join(format!("cachephoenix-saved-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("discord")).unwrap();
+        std::fs::write(dir.join("discord").join("clip (1).mp4"), b"full original").unwrap();
+        std::fs::write(dir.join("other.mp4"), b"x").unwrap();
+
+        let result = find_in(vec![dir.clone()], &["clip.mp4".to_string()]);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!((result.matches[0].filename.as_str(), result.matches[0].size), ("clip.mp4", 13));
+        assert_eq!(result.searched_dirs, [dir.to_string_lossy()]);
+    }
+}
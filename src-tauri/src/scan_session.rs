@@ -0,0 +1,332 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+// ─── Scan sessions and result grouping ──────────────────────────────────────
+//
+// The results view shows entries as trees (by attachment, by host, by day,
+// by type). Building those in JS means pulling every entry of every cache
+// folder into webview memory first. Instead, `open_scan_session` reads the
+// folders once into a catalog held here — one row per logical entry: a
+// blockfile index entry, or the `_0`/`_1`/`_s` files of one Simple Cache key
+// — and `group_entries` returns a two-level summary of it with counts and
+// sizes per node. Leaves carry entry ids only; `session_entries` fetches the
+// rows of a leaf when it is expanded.
+//
+//   attachment  Discord channel → attachment (CDN and media-proxy copies of
+//               one upload share a leaf); everything else under "Other"
+//   host        URL host → MIME type
+//   day         day of the response (or file) time, UTC → type category
+//   type        type category → MIME type
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogEntry {
+    pub id: usize,
+    pub cache_dir: String,
+    pub url: String,
+    /// Lowercase MIME type, parameters stripped
+    pub content_type: Option<String>,
+    pub size: u64,
+    /// Unix seconds: response time for blockfile entries, newest file mtime
+    /// for Simple Cache entries
+    pub time: Option<f64>,
+    /// Simple Cache file the body is read from; None for blockfile entries
+    pub body_file: Option<String>,
+    /// Discord CDN identity shared by every copy of one object
+    pub group_key: Option<String>,
+    pub channel_id: Option<String>,
+    pub original_filename: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub session: String,
+    pub dirs: Vec<String>,
+    pub entries: usize,
+    pub total_size: u64,
+    /// Folders that could not be read, with the reason
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupNode {
+    pub key: String,
+    pub label: String,
+    pub count: usize,
+    pub total_size: u64,
+    /// Newest entry time in the group, Unix seconds
+    pub latest: Option<f64>,
+    pub children: Vec<GroupNode>,
+    /// Leaf nodes only: ids for `session_entries`
+    pub entry_ids: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EntryGrouping {
+    pub session: String,
+    pub strategy: String,
+    pub count: usize,
+    pub total_size: u64,
+    pub groups: Vec<GroupNode>,
+}
+
+const STRATEGIES: &[&str] = &["attachment", "host", "day", "type"];
+
+/// Catalogs of open sessions by id.
+static SESSIONS: Mutex<Option<HashMap<String, Vec<CatalogEntry>>>> = Mutex::new(None);
+
+fn with_sessions<T>(f: impl FnOnce(&mut HashMap<String, Vec<CatalogEntry>>) -> T) -> T {
+    let mut sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    f(sessions.get_or_insert_with(HashMap::new))
+}
+
+/// Catalog rows of one cache folder, ids not yet assigned.
+fn read_dir_entries(dir: &str) -> Result<Vec<CatalogEntry>, String> {
+    let path = Path::new(dir);
+    if crate::cache::is_blockfile_dir(path) {
+        let index = crate::blockfile_index::parse_index_internal(path, None, None)?;
+        return Ok(index
+            .entries
+            .into_iter()
+            .map(|e| CatalogEntry {
+                id: 0,
+                cache_dir: dir.to_string(),
+                content_type: e.content_type.as_deref().map(crate::mime_category::essence),
+                size: e.body_size,
+                time: e.response_time.or(e.creation_time),
+                body_file: None,
+                group_key: e.group_key,
+                channel_id: e.cdn.and_then(|c| c.channel_id),
+                original_filename: e.original_filename,
+                url: e.url,
+            })
+            .collect());
+    }
+    let files = crate::cache::list_cache_files(dir)?;
+    Ok(crate::entry::group_entries(&files)
+        .into_iter()
+        .map(|g| {
+            let cdn = crate::discord_cdn::match_response(&g.url, None);
+            CatalogEntry {
+                id: 0,
+                cache_dir: dir.to_string(),
+                content_type: g.content_type,
+                size: g.body_size,
+                time: (g.modified_at > 0.0).then_some(g.modified_at),
+                body_file: g.body_file,
+                group_key: cdn.as_ref().map(|c| c.group_key.clone()),
+                channel_id: cdn.as_ref().and_then(|c| c.channel_id.clone()),
+                original_filename: cdn.and_then(|c| c.original_filename),
+                url: g.url,
+            }
+        })
+        .collect())
+}
+
+/// "YYYY-MM-DD" (UTC) of a Unix time.
+fn day_of(secs: f64) -> String {
+    // Howard Hinnant's civil_from_days.
+    let z = (secs / 86400.0).floor() as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn host_of(url: &str) -> String {
+    let url = crate::cache_key::effective_url(url);
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or("").to_lowercase()
+}
+
+fn category_of(entry: &CatalogEntry) -> &'static str {
+    crate::mime_category::classify(entry.content_type.as_deref(), None).category
+}
+
+/// (key, label) of an entry at both levels of `strategy`.
+fn levels(strategy: &str, entry: &CatalogEntry) -> [(String, String); 2] {
+    let mime = entry.content_type.clone().unwrap_or_else(|| "unknown".to_string());
+    let by_mime = (mime.clone(), mime);
+    match strategy {
+        "attachment" => match (&entry.channel_id, &entry.group_key) {
+            (Some(channel), Some(group)) => {
+                let name = entry.original_filename.clone().unwrap_or_else(|| group.clone());
+                [(channel.clone(), format!("Channel {}", channel)), (group.clone(), name)]
+            }
+            _ => [("other".to_string(), "Other".to_string()), (entry.url.clone(), entry.url.clone())],
+        },
+        "host" => {
+            let host = host_of(&entry.url);
+            [(host.clone(), host), by_mime]
+        }
+        "day" => {
+            let day = entry.time.map(day_of).unwrap_or_else(|| "unknown".to_string());
+            let label = if day == "unknown" { "Unknown date".to_string() } else { day.clone() };
+            let category = category_of(entry).to_string();
+            [(day, label), (category.clone(), category)]
+        }
+        _ => {
+            let category = category_of(entry).to_string();
+            [(category.clone(), category), by_mime]
+        }
+    }
+}
+
+fn add_to(node: &mut GroupNode, entry: &CatalogEntry) {
+    node.count += 1;
+    node.total_size += entry.size;
+    if entry.time > node.latest {
+        node.latest = entry.time;
+    }
+}
+
+fn new_node(key: String, label: String) -> GroupNode {
+    GroupNode { key, label, count: 0, total_size: 0, latest: None, children: Vec::new(), entry_ids: Vec::new() }
+}
+
+/// Days newest first; everything else largest first.
+fn sort_nodes(nodes: &mut [GroupNode], strategy: &str) {
+    if strategy == "day" {
+        nodes.sort_by(|a, b| b.key.cmp(&a.key));
+    } else {
+        nodes.sort_by(|a, b| b.total_size.cmp(&a.total_size).then_with(|| a.key.cmp(&b.key)));
+    }
+}
+
+fn group(catalog: &[CatalogEntry], strategy: &str) -> Vec<GroupNode> {
+    let mut top: Vec<GroupNode> = Vec::new();
+    let mut top_at: HashMap<String, usize> = HashMap::new();
+    let mut child_at: HashMap<(usize, String), usize> = HashMap::new();
+    for entry in catalog {
+        let [(key, label), (child_key, child_label)] = levels(strategy, entry);
+        let t = *top_at.entry(key.clone()).or_insert_with(|| {
+            top.push(new_node(key, label));
+            top.len() - 1
+        });
+        let c = *child_at.entry((t, child_key.clone())).or_insert_with(|| {
+            top[t].children.push(new_node(child_key, child_label));
+            top[t].children.len() - 1
+        });
+        add_to(&mut top[t], entry);
+        let leaf = &mut top[t].children[c];
+        add_to(leaf, entry);
+        leaf.entry_ids.push(entry.id);
+    }
+    for node in &mut top {
+        sort_nodes(&mut node.children, if strategy == "day" { "" } else { strategy });
+    }
+    sort_nodes(&mut top, strategy);
+    top
+}
+
+/// Read `dirs` into the catalog of `session`, replacing any earlier one.
+#[tauri::command(async)]
+pub fn open_scan_session(session: String, dirs: Vec<String>) -> SessionSummary {
+    let _job = crate::crash::job(format!("cataloguing session {}", session));
+    let mut catalog: Vec<CatalogEntry> = Vec::new();
+    let mut errors = Vec::new();
+    let dirs: Vec<String> = dirs.iter().map(|d| crate::path_display::expand_path(d)).collect();
+    for dir in &dirs {
+        match read_dir_entries(dir) {
+            Ok(entries) => catalog.extend(entries),
+            Err(e) => errors.push(format!("{}: {}", dir, e)),
+        }
+    }
+    for (id, entry) in catalog.iter_mut().enumerate() {
+        entry.id = id;
+    }
+    let summary = SessionSummary {
+        session: session.clone(),
+        dirs,
+        entries: catalog.len(),
+        total_size: catalog.iter().map(|e| e.size).sum(),
+        errors,
+    };
+    println!(
+        "[session] {}: {} entries ({} bytes) from {} folders, {} errors",
+        session,
+        summary.entries,
+        summary.total_size,
+        summary.dirs.len(),
+        summary.errors.len()
+    );
+    with_sessions(|sessions| sessions.insert(session, catalog));
+    summary
+}
+
+/// Group the catalog of `session` by "attachment", "host", "day" or "type".
+#[tauri::command]
+pub fn group_entries(session: String, strategy: String) -> Result<EntryGrouping, String> {
+    if !STRATEGIES.contains(&strategy.as_str()) {
+        return Err(format!("Unknown grouping \"{}\" (expected one of {})", strategy, STRATEGIES.join(", ")));
+    }
+    with_sessions(|sessions| {
+        let catalog = sessions.get(&session).ok_or_else(|| format!("No open scan session {}", session))?;
+        Ok(EntryGrouping {
+            count: catalog.len(),
+            total_size: catalog.iter().map(|e| e.size).sum(),
+            groups: group(catalog, &strategy),
+            session,
+            strategy,
+        })
+    })
+}
+
+/// Catalog rows of `session` with the given ids, in the order asked for.
+#[tauri::command]
+pub fn session_entries(session: String, ids: Vec<usize>) -> Result<Vec<CatalogEntry>, String> {
+    with_sessions(|sessions| {
+        let catalog = sessions.get(&session).ok_or_else(|| format!("No open scan session {}", session))?;
+        Ok(ids.iter().filter_map(|&id| catalog.get(id).cloned()).collect())
+    })
+}
+
+/// Drop the catalog of `session`.
+#[tauri::command]
+pub fn close_scan_session(session: String) {
+    with_sessions(|sessions| sessions.remove(&session));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_a_session_by_attachment_and_type() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-session-{}", std::process::id()));
+        let cache = crate::fixture::FixtureCache::create(&dir).unwrap();
+        let video = ["HTTP/1.1 200 OK", "Content-Type: video/mp4"];
+        cache.add_entry("https://cdn.discordapp.com/attachments/11/22/clip.mp4?ex=1", &video, b"0123456789").unwrap();
+        cache.add_entry("https://media.discordapp.net/attachments/11/22/clip.mp4", &video, b"01234").unwrap();
+        cache.add_entry("https://example.com/a.png", &["HTTP/1.1 200 OK", "Content-Type: image/png"], b"png").unwrap();
+
+        let summary = open_scan_session("s1".into(), vec![dir.to_string_lossy().to_string()]);
+        let by_attachment = group_entries("s1".into(), "attachment".into()).unwrap();
+        let by_type = group_entries("s1".into(), "type".into()).unwrap();
+        let by_day = group_entries("s1".into(), "day".into()).unwrap();
+        let leaf_ids = by_attachment.groups[0].children[0].entry_ids.clone();
+        let rows = session_entries("s1".into(), leaf_ids.clone()).unwrap();
+        close_scan_session("s1".into());
+        let closed = group_entries("s1".into(), "host".into());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!((summary.entries, summary.total_size), (3, 18));
+        assert_eq!(by_attachment.groups[0].label, "Channel 11");
+        assert_eq!(by_attachment.groups[0].children.len(), 1);
+        assert_eq!((by_attachment.groups[0].count, leaf_ids.len()), (2, 2));
+        assert_eq!(rows[0].original_filename.as_deref(), Some("clip.mp4"));
+        assert_eq!(by_type.groups[0].key, "video");
+        assert_eq!(by_type.groups[0].children[0].key, "video/mp4");
+        assert_eq!(by_day.groups.len(), 1);
+        assert!(closed.is_err());
+        assert!(group_entries("s1".into(), "size".into()).is_err());
+        assert_eq!(day_of(1_709_251_200.0), "2024-03-01");
+        assert_eq!(host_of("1/0/_dk_https://a.com https://a.com https://Cdn.Example.com/x"), "cdn.example.com");
+    }
+}
@@ -0,0 +1,279 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::fixture::FixtureCache;
+
+// ---------------------------------------------------------------------------
+// Self-test
+// ---------------------------------------------------------------------------
+//
+// Builds a tiny synthetic cache in the temp directory and runs it through the
+// same steps a real recovery uses: scan, header parsing, body extraction,
+// sparse reassembly and output writing. Directory and file names are
+// deliberately non-ASCII so path-encoding bugs (Windows code pages, NFC/NFD on
+// macOS) show up here instead of on a user's real cache.
+
+/// Non-ASCII profile folder the fixture cache lives under.
+const PROFILE_DIR: &str = "Prüfung テスト 测试";
+/// Non-ASCII output folder recovered files are written to.
+const OUTPUT_DIR: &str = "Ausgabe 出力";
+const ENTRY_URL: &str = "1/0/https://cdn.discordapp.com/attachments/1/2/%C3%BCberraschung.mp4";
+const SPARSE_URL: &str = "1/0/https://media.discordapp.net/attachments/1/3/clip.mp4";
+/// Entry whose body is in `_1`, with an empty stream 1 in `_0`.
+const LARGE_URL: &str = "1/0/https://cdn.discordapp.com/attachments/1/4/large.mp4";
+/// Last-used time written into the fixture index (2024-01-17, µs since 1601).
+const INDEX_LAST_USED: i64 = 13_350_000_000_000_000;
+/// Content-Disposition filename the fixture entry advertises.
+const EXPECTED_FILENAME: &str = "Überraschung 🎉.mp4";
+
+#[derive(Debug, Serialize)]
+pub struct StageResult {
+    pub name: &'static str,
+    /// "pass", "fail" or "skip"
+    pub status: &'static str,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub stages: Vec<StageResult>,
+}
+
+pub(crate) struct Runner {
+    pub(crate) stages: Vec<StageResult>,
+    failed: bool,
+}
+
+impl Runner {
+    pub(crate) fn new() -> Self {
+        Runner { stages: Vec::new(), failed: false }
+    }
+
+    /// Run a stage, or skip it if an earlier pipeline stage already failed.
+    pub(crate) fn stage(&mut self, name: &'static str, f: impl FnOnce() -> Result<String, String>) {
+        if self.failed {
+            self.stages.push(StageResult {
+                name,
+                status: "skip",
+                detail: "Skipped after an earlier failure".to_string(),
+                duration_ms: 0,
+            });
+            return;
+        }
+        self.always(name, f);
+        self.failed = self.stages.last().is_some_and(|s| s.status == "fail");
+    }
+
+    /// Run a stage regardless of earlier failures.
+    pub(crate) fn always(&mut self, name: &'static str, f: impl FnOnce() -> Result<String, String>) {
+        let started = Instant::now();
+        let (status, detail) = match f() {
+            Ok(detail) => ("pass", detail),
+            Err(e) => ("fail", e),
+        };
+        eprintln!("[self-test] {}: {} — {}", name, status, detail);
+        self.stages.push(StageResult {
+            name,
+            status,
+            detail,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+}
+
+/// A small but structurally valid MP4: ftyp + moov + mdat.
+fn sample_mp4() -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&20u32.to_be_bytes());
+    out.extend_from_slice(b"ftypisom\0\0\x02\0isom");
+    out.extend_from_slice(&8u32.to_be_bytes());
+    out.extend_from_slice(b"moov");
+    let payload: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    out.extend_from_slice(&(8 + payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(b"mdat");
+    out.extend_from_slice(&payload);
+    out
+}
+
+fn expect_eq(what: &str, got: &[u8], want: &[u8]) -> Result<String, String> {
+    if got == want {
+        Ok(format!("{} matches ({} bytes)", what, got.len()))
+    } else {
+        Err(format!("{} differs: got {} bytes, expected {}", what, got.len(), want.len()))
+    }
+}
+
+/// Run every filesystem stage against a fixture cache created under `root`.
+fn run_pipeline(runner: &mut Runner, root: &Path) {
+    let cache_dir = root.join(PROFILE_DIR).join("Cache").join("Cache_Data");
+    let output_dir = root.join(OUTPUT_DIR);
+    let body = sample_mp4();
+    let (first, second) = body.split_at(body.len() / 2);
+
+    let mut entry_path = String::new();
+    let mut sparse_path = String::new();
+    let mut large_path = String::new();
+
+    runner.stage("fixture", || {
+        let cache = FixtureCache::create(&cache_dir)?;
+        entry_path = cache.add_entry(
+            ENTRY_URL,
+            &[
+                "HTTP/1.1 200",
+                "content-type: video/mp4",
+                "content-disposition: attachment; filename=\"surprise.mp4\"; \
+                 filename*=UTF-8''%C3%9Cberraschung%20%F0%9F%8E%89.mp4",
+            ],
+            &body,
+        )?;
+        // Ranges stored out of order, as Chromium does after seeking.
+        sparse_path = cache.add_sparse_entry(SPARSE_URL, &[(first.len() as u64, second), (0, first)])?;
+        large_path = cache.add_entry(LARGE_URL, &["HTTP/1.1 200", "content-type: video/mp4"], b"")?;
+        cache.add_stream2_entry(LARGE_URL, &body)?;
+        cache.add_index(&[
+            (ENTRY_URL, INDEX_LAST_USED, body.len() as u64),
+            (SPARSE_URL, INDEX_LAST_USED, body.len() as u64),
+            (LARGE_URL, INDEX_LAST_USED, body.len() as u64),
+        ])?;
+        Ok(format!("Wrote 3 entries to {}", cache.dir().display()))
+    });
+
+    runner.stage("scan", || {
+        let files = crate::cache::list_cache_files(&cache_dir.to_string_lossy())?;
+        if files.len() != 4 {
+            return Err(format!("Expected 4 cache files, found {}", files.len()));
+        }
+        if files.iter().any(|f| f.last_used.is_none()) {
+            return Err("the-real-index metadata missing from listing".to_string());
+        }
+        Ok("Found 4 cache files with index metadata".to_string())
+    });
+
+    runner.stage("content_type", || {
+        let ct = crate::simple_cache_content_type(&entry_path)?;
+        if ct == "video/mp4" {
+            Ok(ct)
+        } else {
+            Err(format!("Expected video/mp4, got {}", ct))
+        }
+    });
+
+    let mut file_name = String::new();
+    runner.stage("original_filename", || {
+        let headers = crate::entry::load_entry(&entry_path, false)?.headers;
+        let (name, source) = crate::output_naming::choose_name(
+            crate::output_naming::NamingPolicy::ContentDisposition,
+            &headers,
+            None,
+            "recovered",
+        );
+        if source != "content_disposition" {
+            return Err(format!("No filename found in Content-Disposition, fell back to {:?}", name));
+        }
+        if name != EXPECTED_FILENAME {
+            return Err(format!("Expected {:?}, got {:?}", EXPECTED_FILENAME, name));
+        }
+        file_name = name;
+        Ok(file_name.clone())
+    });
+
+    runner.stage("extract", || {
+        let got = crate::read_cache_body(&entry_path)?;
+        expect_eq("Stream 1 body", &got, &body)
+    });
+
+    runner.stage("sparse", || {
+        let got = crate::read_cache_body(&sparse_path)?;
+        expect_eq("Reassembled sparse body", &got, &body)
+    });
+
+    runner.stage("entry", || {
+        let got = crate::entry::load_entry(&large_path, true)?;
+        expect_eq("Body from _1 behind an empty stream 1", &got.body, &body)
+    });
+
+    runner.stage("write_output", || {
+        let dst = output_dir.join(&file_name);
+        let dst = dst.to_string_lossy();
+        crate::copy_body(entry_path.clone(), dst.to_string(), None)?;
+        let got = std::fs::read(dst.as_ref()).map_err(|e| format!("Failed to read back {}: {}", dst, e))?;
+        expect_eq("Written file", &got, &body)
+    });
+}
+
+/// Run each sidecar with `-version` to confirm it exists and can execute.
+fn check_sidecars() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Cannot determine exe path: {}", e))?;
+    let exe_dir = exe.parent().ok_or_else(|| "Cannot determine exe directory".to_string())?;
+    let names: &[&str] = if cfg!(windows) {
+        &["ffmpeg.exe", "ffprobe.exe"]
+    } else {
+        &["ffmpeg", "ffprobe"]
+    };
+    let mut versions = Vec::new();
+    for name in names {
+        let path = exe_dir.join(name);
+        let output = std::process::Command::new(&path)
+            .arg("-version")
+            .output()
+            .map_err(|e| format!("Cannot run {}: {}", path.display(), e))?;
+        if !output.status.success() {
+            return Err(format!("{} -version exited with {}", name, output.status));
+        }
+        let first_line = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or("")
+            .to_string();
+        versions.push(first_line);
+    }
+    Ok(versions.join("; "))
+}
+
+/// Verify the install end to end on a synthetic cache: disk access with
+/// non-ASCII paths, every parsing stage, output writing and the ffmpeg/ffprobe
+/// sidecars. Nothing outside the temp directory is touched.
+#[tauri::command(async)]
+pub fn self_test() -> SelfTestReport {
+    let mut runner = Runner::new();
+    let root: PathBuf = std::env::temp_dir().join(format!("cachephoenix-self-test-{}", std::process::id()));
+
+    runner.stage("workspace", || {
+        std::fs::create_dir_all(&root)
+            .map_err(|e| format!("Failed to create dir {}: {}", root.display(), e))?;
+        Ok(root.display().to_string())
+    });
+    run_pipeline(&mut runner, &root);
+
+    runner.always("sidecars", check_sidecars);
+    runner.always("cleanup", || {
+        if !root.exists() {
+            return Ok("Nothing to clean up".to_string());
+        }
+        std::fs::remove_dir_all(&root)
+            .map(|_| "Removed temp files".to_string())
+            .map_err(|e| format!("Failed to remove {}: {}", root.display(), e))
+    });
+
+    let passed = runner.stages.iter().all(|s| s.status == "pass");
+    SelfTestReport { passed, stages: runner.stages }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipeline_passes_on_fixture() {
+        let root = std::env::temp_dir().join(format!("cachephoenix-self-test-unit-{}", std::process::id()));
+        let mut runner = Runner::new();
+        run_pipeline(&mut runner, &root);
+        let _ = std::fs::remove_dir_all(&root);
+        for stage in &runner.stages {
+            assert_eq!(stage.status, "pass", "{}: {}", stage.name, stage.detail);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+
+// ─── Sidecars started by the backend ────────────────────────────────────────
+//
+// ffmpeg and ffprobe are mostly run by the frontend through the shell plugin.
+// Work whose result is small but whose input is large (a thumbnail from a
+// video) is run from here instead, so only the result crosses IPC. Runs are
+// registered with the stall watchdog the same way the frontend's are: output
+// counts as progress, and a skip or kill of the operation kills the process.
+
+/// Stderr kept per run; ffmpeg's banner and progress lines beyond this are
+/// dropped.
+const MAX_STDERR: usize = 16 * 1024;
+/// How often a run checks whether the user skipped it.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct SidecarOutput {
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: String,
+}
+
+impl SidecarOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Run the bundled sidecar `name` ("ffmpeg", "ffprobe") with `args`, watched
+/// as a `job` on `path`, and collect its output.
+pub async fn run(
+    app: &tauri::AppHandle,
+    name: &str,
+    args: &[String],
+    job: &str,
+    path: &str,
+) -> Result<SidecarOutput, String> {
+    run_with(app, name, args, job, path, |_| {}).await
+}
+
+/// `run`, also handing each piece of stdout to `on_stdout` as it arrives
+/// (pieces are not split at line ends).
+pub async fn run_with(
+    app: &tauri::AppHandle,
+    name: &str,
+    args: &[String],
+    job: &str,
+    path: &str,
+    mut on_stdout: impl FnMut(&[u8]) + Send,
+) -> Result<SidecarOutput, String> {
+    let command = app.shell().sidecar(name).map_err(|e| format!("{} sidecar not found: {}", name, e))?;
+    let (mut events, child) =
+        command.args(args).set_raw_out(true).spawn().map_err(|e| format!("Failed to start {}: {}", name, e))?;
+    let watch = Arc::new(crate::watchdog::Watch::sidecar(job, path, child.pid()));
+
+    // A skipped run is killed from a poller thread; the event loop below then
+    // sees it terminate.
+    let child: Arc<Mutex<Option<CommandChild>>> = Arc::new(Mutex::new(Some(child)));
+    let done = Arc::new(AtomicBool::new(false));
+    {
+        let (watch, child, done) = (watch.clone(), child.clone(), done.clone());
+        std::thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                if watch.abandoned() {
+                    if let Some(child) = child.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                        let _ = child.kill();
+                    }
+                    return;
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+    }
+
+    let mut output = SidecarOutput { code: None, stdout: Vec::new(), stderr: String::new() };
+    while let Some(event) = events.recv().await {
+        match event {
+            CommandEvent::Stdout(bytes) => {
+                watch.advance(bytes.len() as u64);
+                on_stdout(&bytes);
+                output.stdout.extend_from_slice(&bytes);
+            }
+            CommandEvent::Stderr(bytes) => {
+                watch.advance(bytes.len() as u64);
+                if output.stderr.len() < MAX_STDERR {
+                    output.stderr.push_str(&String::from_utf8_lossy(&bytes));
+                }
+            }
+            CommandEvent::Error(message) => output.stderr.push_str(&message),
+            CommandEvent::Terminated(payload) => {
+                output.code = payload.code;
+                break;
+            }
+            _ => {}
+        }
+    }
+    done.store(true, Ordering::Relaxed);
+    if watch.abandoned() {
+        return Err(format!("Stalled {} run on {} was skipped", name, path));
+    }
+    Ok(output)
+}
@@ -0,0 +1,418 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// ─── Archived cache snapshots ───────────────────────────────────────────────
+//
+// Users often zip up `Cache_Data` before Discord prunes it. A `.zip`, `.tar`,
+// `.tar.gz` or `.tgz` given to `validate_cache_path` / `list_cache_files` is
+// extracted once into the temp workspace (`cachephoenix-snapshots/<key>`,
+// keyed by the archive's path, size and mtime) and the cache folder inside it
+// is listed like any other. Every later step — sparse reassembly, MP4
+// reconstruction, audits — then works on real files, so nothing downstream
+// needs to know the entries came from an archive. File mtimes are restored
+// from the archive, since unindexed-entry detection compares them with
+// the-real-index.
+//
+// The cache may sit a few folders deep (`backup/discord/Cache/Cache_Data`);
+// the first folder that classifies as a cache is used. A copy without
+// `index-dir` or `the-real-index` is still listed, just without last-used
+// times or eviction risk. Extraction refuses entries that would land
+// outside the snapshot folder and skips links and devices, and gives up once
+// the archive unpacks to more than `MAX_EXTRACTED_BYTES` — a real cache is a
+// fraction of that, a ZIP bomb is not.
+//
+// Extracting a large archive takes a while, so only a scan does it
+// (`list_cache_files` runs off the UI thread). `validate_cache_path` merely
+// reports an archive that hasn't been extracted yet.
+
+const ARCHIVE_SUFFIXES: &[&str] = &[".zip", ".tar", ".tar.gz", ".tgz"];
+const SNAPSHOT_DIR: &str = "cachephoenix-snapshots";
+/// Written last; holds the cache folder's path relative to the snapshot.
+const COMPLETE_MARKER: &str = ".cachephoenix-complete";
+/// How many folders deep the cache is looked for inside an archive.
+const CACHE_SEARCH_DEPTH: usize = 6;
+const TAR_BLOCK: usize = 512;
+/// Most bytes one archive may unpack to.
+const MAX_EXTRACTED_BYTES: u64 = 16 << 30;
+/// Largest GNU long-name or pax record read into memory.
+const MAX_TAR_RECORD: u64 = 1 << 20;
+
+/// One extraction at a time, so two scans of the same archive share it.
+static EXTRACTING: Mutex<()> = Mutex::new(());
+
+/// `path` is an archive file this module can open.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    ARCHIVE_SUFFIXES.iter().any(|s| name.ends_with(s)) && path.is_file()
+}
+
+/// Snapshot folder for `archive`; a changed archive gets a new one.
+fn snapshot_dir(archive: &Path) -> Result<PathBuf, String> {
+    use std::hash::{Hash, Hasher};
+    let meta = std::fs::metadata(archive).map_err(|e| crate::format_read_error(&archive.to_string_lossy(), &e))?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    std::fs::canonicalize(archive).unwrap_or_else(|_| archive.to_path_buf()).hash(&mut hasher);
+    meta.len().hash(&mut hasher);
+    meta.modified().ok().hash(&mut hasher);
+    Ok(std::env::temp_dir().join(SNAPSHOT_DIR).join(format!("{:016x}", hasher.finish())))
+}
+
+/// Archive member name → relative path; None for absolute or `..` names.
+fn safe_relative(name: &str) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in Path::new(&name.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!out.as_os_str().is_empty()).then_some(out)
+}
+
+/// Take `size` bytes from what is left of the extraction budget.
+fn spend(budget: &mut u64, size: u64) -> Result<(), String> {
+    *budget = budget.checked_sub(size).ok_or_else(too_large)?;
+    Ok(())
+}
+
+fn too_large() -> String {
+    let gib = MAX_EXTRACTED_BYTES >> 30;
+    format!("Archive unpacks to more than {} GiB; extract it yourself and add the folder", gib)
+}
+
+/// Copy exactly `size` bytes of `reader` into a new file at `target`.
+fn write_member(reader: &mut dyn Read, target: &Path, size: u64, modified: Option<SystemTime>) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let mut file = File::create(target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    let copied = std::io::copy(&mut reader.take(size), &mut file)
+        .map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+    if copied < size {
+        return Err(format!("Archive ends inside {} ({} of {} bytes)", target.display(), copied, size));
+    }
+    if let Some(time) = modified {
+        let _ = file.set_modified(time);
+    }
+    Ok(())
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    era * 146097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719468
+}
+
+/// ZIP timestamps are local DOS times without a zone; they are taken as UTC,
+/// which is close enough for ordering files against the index.
+fn zip_time(time: zip::DateTime) -> Option<SystemTime> {
+    let days = days_from_civil(time.year() as i64, time.month() as i64, time.day() as i64);
+    let secs = days * 86400 + time.hour() as i64 * 3600 + time.minute() as i64 * 60 + time.second() as i64;
+    u64::try_from(secs).ok().map(|s| UNIX_EPOCH + Duration::from_secs(s))
+}
+
+fn extract_zip(archive: &Path, dest: &Path, mut budget: u64) -> Result<usize, String> {
+    let file = File::open(archive).map_err(|e| crate::format_read_error(&archive.to_string_lossy(), &e))?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file))
+        .map_err(|e| format!("Not a readable ZIP archive {}: {}", archive.display(), e))?;
+    let mut files = 0;
+    for i in 0..zip.len() {
+        let mut member = zip.by_index(i).map_err(|e| format!("Failed to read ZIP entry {}: {}", i, e))?;
+        let Some(rel) = member.enclosed_name() else {
+            eprintln!("[snapshot] Skipping unsafe ZIP entry {}", member.name());
+            continue;
+        };
+        if member.is_dir() {
+            let _ = std::fs::create_dir_all(dest.join(rel));
+            continue;
+        }
+        if member.is_symlink() {
+            continue;
+        }
+        let (size, modified) = (member.size(), member.last_modified().and_then(zip_time));
+        spend(&mut budget, size)?;
+        write_member(&mut member, &dest.join(rel), size, modified)?;
+        files += 1;
+    }
+    Ok(files)
+}
+
+/// NUL-terminated header field as text.
+fn tar_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+/// Octal header number, or GNU base-256 when the top bit is set.
+fn tar_number(field: &[u8]) -> Option<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return field[1..].iter().try_fold(0u64, |n, &b| n.checked_mul(256)?.checked_add(b as u64));
+    }
+    let text = tar_str(field);
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+/// The `path` record of a pax extended header.
+fn pax_path(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    text.lines().find_map(|record| {
+        let (_, pair) = record.split_once(' ')?;
+        pair.strip_prefix("path=").map(str::to_string)
+    })
+}
+
+fn skip(reader: &mut dyn Read, n: u64) -> Result<(), String> {
+    std::io::copy(&mut reader.take(n), &mut std::io::sink()).map_err(|e| format!("Failed to read archive: {}", e))?;
+    Ok(())
+}
+
+/// Fill `block`; false at a clean end of the stream.
+fn read_block(reader: &mut dyn Read, block: &mut [u8; TAR_BLOCK]) -> Result<bool, String> {
+    let mut filled = 0;
+    while filled < TAR_BLOCK {
+        match reader.read(&mut block[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err("Archive ends inside a tar header".to_string()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(format!("Failed to read archive: {}", e)),
+        }
+    }
+    Ok(true)
+}
+
+/// Extract a ustar/GNU/pax stream: regular files and folders only.
+fn extract_tar(reader: &mut dyn Read, dest: &Path, mut budget: u64) -> Result<usize, String> {
+    let mut header = [0u8; TAR_BLOCK];
+    let mut long_name: Option<String> = None;
+    let mut files = 0;
+    while read_block(reader, &mut header)? {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        // A base-256 size can be near u64::MAX: no member may be larger than
+        // what is left of the budget, checked before any arithmetic on it.
+        let size = tar_number(&header[124..136]).ok_or("Bad size in tar header")?;
+        if size > budget {
+            return Err(too_large());
+        }
+        let padded = size.checked_next_multiple_of(TAR_BLOCK as u64).ok_or("Bad size in tar header")?;
+        let padding = padded - size;
+        let kind = header[156];
+        if kind == b'L' || kind == b'x' {
+            if size > MAX_TAR_RECORD {
+                return Err(format!("Tar long-name or pax record of {} bytes is too large", size));
+            }
+            let mut data = Vec::new();
+            reader.take(size).read_to_end(&mut data).map_err(|e| format!("Failed to read archive: {}", e))?;
+            skip(reader, padding)?;
+            long_name = if kind == b'L' { Some(tar_str(&data)) } else { pax_path(&data).or(long_name) };
+            continue;
+        }
+        let name = long_name.take().unwrap_or_else(|| {
+            let name = tar_str(&header[0..100]);
+            let prefix = if &header[257..262] == b"ustar" { tar_str(&header[345..500]) } else { String::new() };
+            if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+        });
+        let rel = safe_relative(&name);
+        match (kind, rel) {
+            (b'0' | b'7' | 0, Some(rel)) => {
+                let modified =
+                    tar_number(&header[136..148]).and_then(|s| UNIX_EPOCH.checked_add(Duration::from_secs(s)));
+                spend(&mut budget, size)?;
+                write_member(reader, &dest.join(rel), size, modified)?;
+                skip(reader, padding)?;
+                files += 1;
+            }
+            (b'5', Some(rel)) => {
+                let _ = std::fs::create_dir_all(dest.join(rel));
+                skip(reader, padded)?;
+            }
+            (_, rel) => {
+                if rel.is_none() {
+                    eprintln!("[snapshot] Skipping unsafe tar entry {}", name);
+                }
+                skip(reader, padded)?;
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn extract(archive: &Path, dest: &Path) -> Result<usize, String> {
+    let name = archive.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if name.ends_with(".zip") {
+        return extract_zip(archive, dest, MAX_EXTRACTED_BYTES);
+    }
+    let file = File::open(archive).map_err(|e| crate::format_read_error(&archive.to_string_lossy(), &e))?;
+    let mut reader: Box<dyn Read> = if name.ends_with(".tar") {
+        Box::new(BufReader::new(file))
+    } else {
+        Box::new(flate2::read::MultiGzDecoder::new(BufReader::new(file)))
+    };
+    extract_tar(&mut reader, dest, MAX_EXTRACTED_BYTES)
+}
+
+/// First folder under `dir`, breadth by name, that classifies as a cache.
+fn find_cache_root(dir: &Path, depth: usize) -> Option<PathBuf> {
+    if crate::cache::classify_dir(dir).kind != "unknown" {
+        return Some(dir.to_path_buf());
+    }
+    if depth == 0 {
+        return None;
+    }
+    let mut children: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.path())
+        .collect();
+    children.sort();
+    children.into_iter().find_map(|child| find_cache_root(&child, depth - 1))
+}
+
+/// The cache folder of an already extracted `archive`; None until a scan
+/// has extracted it.
+pub fn extracted(archive: &Path) -> Option<PathBuf> {
+    let dir = snapshot_dir(archive).ok()?;
+    let rel = std::fs::read_to_string(dir.join(COMPLETE_MARKER)).ok()?;
+    Some(dir.join(rel.trim()))
+}
+
+/// Extract `archive` (once) and return the cache folder inside the snapshot.
+pub fn open(archive: &Path) -> Result<PathBuf, String> {
+    let _guard = EXTRACTING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(root) = extracted(archive) {
+        return Ok(root);
+    }
+    let dir = snapshot_dir(archive)?;
+
+    let _job = crate::crash::job(format!("extracting {}", archive.display()));
+    let started = std::time::Instant::now();
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let files = match extract(archive, &dir) {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+    };
+    let root = find_cache_root(&dir, CACHE_SEARCH_DEPTH).unwrap_or_else(|| dir.clone());
+    let rel = root.strip_prefix(&dir).unwrap_or(Path::new("")).to_string_lossy().to_string();
+    std::fs::write(dir.join(COMPLETE_MARKER), &rel).map_err(|e| format!("Failed to write snapshot marker: {}", e))?;
+    println!(
+        "[snapshot] Extracted {} files from {} in {} ms; cache folder: {}",
+        files,
+        archive.display(),
+        started.elapsed().as_millis(),
+        root.display()
+    );
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ustar member: header, data, padding.
+    fn tar_member(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = [0u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[136..147].copy_from_slice(b"14530000000");
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        let mut out = header.to_vec();
+        out.extend_from_slice(data);
+        out.resize(out.len().next_multiple_of(TAR_BLOCK), 0);
+        out
+    }
+
+    #[test]
+    fn lists_a_cache_inside_a_tarball_and_a_zip() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-snapshot-unit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let entry = crate::fixture::simple_entry(key, &["HTTP/1.1 200 OK"], b"body bytes");
+        let name = "0123456789abcdef_0";
+
+        let mut tar = tar_member(&format!("backup/discord/Cache/Cache_Data/{}", name), &entry);
+        tar.extend(tar_member("../escape.txt", b"no"));
+        tar.extend([0u8; 2 * TAR_BLOCK]);
+        let tgz = dir.join("cache.tar.gz");
+        let mut gz = flate2::write::GzEncoder::new(File::create(&tgz).unwrap(), flate2::Compression::fast());
+        std::io::Write::write_all(&mut gz, &tar).unwrap();
+        gz.finish().unwrap();
+
+        let zip_path = dir.join("Cache_Data.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.start_file(format!("Cache_Data/{}", name), zip::write::SimpleFileOptions::default()).unwrap();
+        std::io::Write::write_all(&mut zip, &entry).unwrap();
+        zip.finish().unwrap();
+
+        let from_tar = crate::cache::list_cache_files(&tgz.to_string_lossy()).unwrap();
+        let before_scan = crate::cache::validate_cache_path(&zip_path.to_string_lossy()).unwrap();
+        let from_zip = crate::cache::list_cache_files(&zip_path.to_string_lossy()).unwrap();
+        let info = crate::cache::validate_cache_path(&zip_path.to_string_lossy()).unwrap();
+        let over_budget = extract_tar(&mut &tar[..], &dir.join("small"), entry.len() as u64 - 1);
+        let snapshots = [snapshot_dir(&tgz).unwrap(), snapshot_dir(&zip_path).unwrap()];
+        let escaped = snapshots[0].parent().unwrap().join("escape.txt").exists();
+        let mtime = std::fs::metadata(&from_tar[0].path).unwrap().modified().unwrap();
+        let body = crate::read_with_lock_retry(&from_zip[0].path).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        snapshots.iter().for_each(|s| drop(std::fs::remove_dir_all(s)));
+
+        assert_eq!(from_tar.len(), 1);
+        assert!(from_tar[0].path.contains("Cache_Data"));
+        assert_eq!(mtime, UNIX_EPOCH + Duration::from_secs(0o14530000000));
+        assert!(!escaped);
+        assert_eq!((before_scan.kind, before_scan.file_count, before_scan.from_archive), ("snapshot", 0, true));
+        assert!(over_budget.unwrap_err().contains("more than"));
+        assert_eq!((info.exists, info.kind, info.file_count, info.from_archive), (true, "http_cache", 1, true));
+        assert_eq!(body, entry);
+        assert_eq!(days_from_civil(2024, 3, 1), 19783);
+    }
+
+    #[test]
+    fn rejects_huge_base_256_tar_fields() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-snapshot-huge-{}", std::process::id()));
+        let huge = |kind: u8| {
+            let mut tar = tar_member("big", b"");
+            tar[124..128].copy_from_slice(&[0x80, 0, 0, 0]);
+            tar[128..136].fill(0xff);
+            tar[156] = kind;
+            tar
+        };
+        let mut long_name = tar_member("././@LongLink", b"");
+        long_name[124..135].copy_from_slice(format!("{:011o}", MAX_TAR_RECORD + 1).as_bytes());
+        long_name[156] = b'L';
+        let mut far_future = tar_member("late.txt", b"ok");
+        far_future[136..140].copy_from_slice(&[0x80, 0, 0, 0]);
+        far_future[140..148].fill(0xff);
+        far_future.extend([0u8; 2 * TAR_BLOCK]);
+
+        let results = [huge(b'0'), huge(b'5'), huge(b'x'), long_name]
+            .map(|tar| extract_tar(&mut &tar[..], &dir, MAX_EXTRACTED_BYTES));
+        let unbudgeted = extract_tar(&mut &huge(b'0')[..], &dir, u64::MAX);
+        let late = extract_tar(&mut &far_future[..], &dir, MAX_EXTRACTED_BYTES);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(results[..3].iter().all(|r| r.as_ref().unwrap_err().contains("more than")), "{:?}", results);
+        assert!(results[3].as_ref().unwrap_err().contains("too large"));
+        assert_eq!(unbudgeted, Err("Bad size in tar header".to_string()));
+        assert_eq!(late, Ok(1));
+    }
+}
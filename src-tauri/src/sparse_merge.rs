@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use crate::SparseOverlaps;
+
+// ─── Sparse entries spread over several `_s` files ──────────────────────────
+//
+// One URL's range data can end up in more than one sparse entry: the same
+// attachment under different isolation prefixes, or an entry doomed and
+// re-created while ranges kept arriving. Each `_s` file on its own is then
+// an incomplete body. Files are grouped by their key (URL, isolation prefix
+// stripped), and the ranges of a group are placed oldest file first, so
+// where ranges overlap the most recently written data wins — the same rule
+// `assemble_sparse_ranges` applies within one file.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SparseGroup {
+    /// URL the files belong to
+    pub key: String,
+    /// The group's `_s` files, oldest first
+    pub files: Vec<String>,
+    /// Present byte ranges of the merged body, as [start, end)
+    pub ranges: Vec<[u64; 2]>,
+    /// Size of the merged body (including zero-filled gaps)
+    pub size: u64,
+    pub covered_bytes: u64,
+    pub overlaps: SparseOverlaps,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// `_s` files grouped by URL, each group oldest first; groups in order of
+/// their first file in `paths`. Files whose key can't be read are left out.
+pub fn group_by_key(paths: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for path in paths.iter().filter(|p| crate::is_simple_cache_sparse(p)) {
+        let Some(key) = crate::read_simple_cache_key(path) else {
+            continue;
+        };
+        let url = crate::cache_key::effective_url(&key).to_string();
+        if !groups.contains_key(&url) {
+            order.push(url.clone());
+        }
+        groups.entry(url).or_default().push(path.clone());
+    }
+    order
+        .into_iter()
+        .map(|url| {
+            let mut files = groups.remove(&url).unwrap_or_default();
+            files.sort_by_key(|f| modified(f));
+            (url, files)
+        })
+        .collect()
+}
+
+/// Merge the ranges of `files` (oldest first) into one body.
+pub fn merge_files(key: &str, files: &[String]) -> Result<(Vec<u8>, SparseGroup), String> {
+    let mut raw = Vec::with_capacity(files.len());
+    for path in files {
+        let _permit = crate::io_limit::acquire_for(path);
+        raw.push(crate::read_with_lock_retry(path).map_err(|e| crate::format_read_error(path, &e))?);
+    }
+    let mut chunks = Vec::new();
+    for (path, data) in files.iter().zip(&raw) {
+        let (_, ranges) = crate::parse_sparse_ranges(data, path)?;
+        chunks.extend(ranges);
+    }
+    let file_size = raw.iter().map(|data| data.len() as u64).sum();
+    let (body, overlaps) = crate::assemble_sparse_ranges(&chunks, file_size);
+    let ranges = crate::compare::normalize(chunks.iter().map(|(off, d)| [*off, off + d.len() as u64]).collect());
+    let group = SparseGroup {
+        key: key.to_string(),
+        files: files.to_vec(),
+        covered_bytes: ranges.iter().map(|r| r[1] - r[0]).sum(),
+        ranges,
+        size: body.len() as u64,
+        overlaps,
+    };
+    Ok((body, group))
+}
+
+/// Group `_s` files by URL and report each group's merged range set.
+/// Only groups of two or more files are returned.
+#[tauri::command(async)]
+pub fn group_sparse_entries(paths: Vec<String>) -> Vec<SparseGroup> {
+    let _job = crate::crash::job(format!("grouping {} sparse files", paths.len()));
+    group_by_key(&paths)
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .filter_map(|(key, files)| match merge_files(&key, &files) {
+            Ok((_, group)) => Some(group),
+            Err(e) => {
+                eprintln!("[sparse] Cannot merge {} files of {}: {}", files.len(), key, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Reassemble one body from several `_s` files of the same URL and write it
+/// to `output`. Newer files win where ranges overlap. Requires a `consent`
+/// token covering `output`.
+#[tauri::command(async)]
+pub fn merge_sparse_entries(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    paths: Vec<String>,
+    output: String,
+    consent: String,
+) -> Result<SparseGroup, String> {
+    let output = state.check(&consent, &output)?;
+    let _job = crate::crash::job(format!("merging {} sparse files into {}", paths.len(), output));
+    let mut groups = group_by_key(&paths);
+    if groups.len() != 1 {
+        return Err(format!("Expected sparse files of one URL, found {} distinct keys", groups.len()));
+    }
+    let (key, files) = groups.remove(0);
+    let (body, group) = merge_files(&key, &files)?;
+    crate::output_file::write_output(&output, &body)?;
+    println!(
+        "[sparse] Merged {} files of {}: {} of {} bytes present, {} overlapping range(s)",
+        files.len(),
+        key,
+        group.covered_bytes,
+        group.size,
+        group.overlaps.ranges
+    );
+    Ok(group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureCache;
+
+    #[test]
+    fn merges_ranges_of_one_url_newest_first() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-sparse-merge-{}", std::process::id()));
+        let cache = FixtureCache::create(&dir).unwrap();
+        let url = "https://cdn.discordapp.com/attachments/1/2/clip.mp4";
+        let old = cache.add_sparse_entry(&format!("1/0/{}", url), &[(0, &[1u8; 100]), (100, &[1u8; 50])]).unwrap();
+        let new = cache.add_sparse_entry(&format!("_dk_s_ 1/0/{}", url), &[(120, &[2u8; 80])]).unwrap();
+        let other = cache.add_sparse_entry("1/0/https://cdn.discordapp.com/other.mp4", &[(0, b"x")]).unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(60);
+        std::fs::File::options().append(true).open(&new).unwrap().set_modified(later).unwrap();
+
+        let groups = group_sparse_entries(vec![new.clone(), other, old.clone()]);
+        let (body, _) = merge_files(url, &groups[0].files).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files, vec![old, new]);
+        assert_eq!(groups[0].ranges, vec![[0, 200]]);
+        assert_eq!((groups[0].overlaps.ranges, groups[0].overlaps.bytes), (1, 30));
+        assert_eq!((body[119], body[120], body[199]), (1, 2, 2));
+    }
+}
@@ -0,0 +1,170 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::events::Coalescer;
+
+// ─── Sparse entry watching ─────────────────────────────────────────────────
+//
+// While Discord streams a clip, its `_s` file grows one range at a time.
+// `watch_sparse_entry` polls the file and emits a `SparseCoverage` event each
+// time its range table changes, so the UI can show the download progressing
+// and the user can wait until the clip is fully cached before recovering it.
+// The watch ends once the file has not changed for `stable_secs`, when it is
+// removed (evicted), on `stop_watch_sparse_entry`, or after MAX_WATCH.
+
+/// Event name carrying a `SparseCoverage` payload.
+pub const SPARSE_COVERAGE_EVENT: &str = "sparse-coverage-changed";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(750);
+/// Default quiet period after which the entry is considered settled.
+const DEFAULT_STABLE_SECS: u64 = 30;
+const MAX_WATCH: Duration = Duration::from_secs(60 * 60);
+
+/// Paths with a running watch; removing a path stops its watch.
+#[derive(Default)]
+pub struct SparseWatches(Mutex<HashSet<String>>);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SparseCoverage {
+    pub path: String,
+    pub file_size: u64,
+    /// Bytes of the resource present in the file
+    pub covered_bytes: u64,
+    /// End of the furthest range (the resource is at least this long)
+    pub extent: u64,
+    /// Present ranges as [start, end), merged
+    pub ranges: Vec<[u64; 2]>,
+    /// Missing ranges below `extent`
+    pub gaps: Vec<[u64; 2]>,
+    /// Set on the final event: "stable", "removed", "stopped" or "timeout"
+    pub finished: Option<&'static str>,
+}
+
+/// Current range table of a sparse `_s` file.
+pub fn sparse_coverage(path: &str) -> Result<SparseCoverage, String> {
+    let raw = crate::read_with_lock_retry(path).map_err(|e| crate::format_read_error(path, &e))?;
+    let (_, chunks) = crate::parse_sparse_ranges(&raw, path)?;
+    let ranges = crate::compare::normalize(
+        chunks
+            .iter()
+            .map(|(off, d)| [*off, off + d.len() as u64])
+            .collect(),
+    );
+    let mut gaps = Vec::new();
+    let mut cursor = 0u64;
+    for r in &ranges {
+        if r[0] > cursor {
+            gaps.push([cursor, r[0]]);
+        }
+        cursor = r[1];
+    }
+    Ok(SparseCoverage {
+        path: path.to_string(),
+        file_size: raw.len() as u64,
+        covered_bytes: ranges.iter().map(|r| r[1] - r[0]).sum(),
+        extent: cursor,
+        ranges,
+        gaps,
+        finished: None,
+    })
+}
+
+/// (size, mtime) used to skip re-parsing an unchanged file.
+fn stamp(path: &str) -> Option<(u64, Option<SystemTime>)> {
+    std::fs::metadata(path).ok().map(|m| (m.len(), m.modified().ok()))
+}
+
+/// Watch a sparse `_s` file until it stops changing, emitting
+/// `sparse-coverage-changed` whenever its ranges change. Returns the final
+/// coverage (also emitted, with `finished` set).
+#[tauri::command(async)]
+pub fn watch_sparse_entry(
+    app: tauri::AppHandle,
+    watches: tauri::State<'_, SparseWatches>,
+    path: String,
+    stable_secs: Option<u64>,
+) -> Result<SparseCoverage, String> {
+    if !crate::is_simple_cache_sparse(&path) {
+        return Err(format!("Not a sparse cache file: {}", path));
+    }
+    let mut current = sparse_coverage(&path)?;
+    if !watches.0.lock().unwrap_or_else(|e| e.into_inner()).insert(path.clone()) {
+        return Err(format!("Already watching {}", path));
+    }
+    let stable_for = Duration::from_secs(stable_secs.unwrap_or(DEFAULT_STABLE_SECS));
+    let mut events = Coalescer::for_app(app, SPARSE_COVERAGE_EVENT);
+    events.push(current.clone());
+
+    let started = Instant::now();
+    let mut last_change = Instant::now();
+    let mut last_stamp = stamp(&path);
+    let finished = loop {
+        std::thread::sleep(POLL_INTERVAL);
+        if !watches.0.lock().unwrap_or_else(|e| e.into_inner()).contains(&path) {
+            break "stopped";
+        }
+        if started.elapsed() >= MAX_WATCH {
+            break "timeout";
+        }
+        let now_stamp = stamp(&path);
+        if now_stamp.is_none() {
+            break "removed";
+        }
+        if now_stamp != last_stamp {
+            last_stamp = now_stamp;
+            match sparse_coverage(&path) {
+                Ok(next) if next != current => {
+                    current = next;
+                    last_change = Instant::now();
+                    events.push(current.clone());
+                }
+                Ok(_) => {}
+                // Mid-write reads can see a torn range header; try again next poll.
+                Err(e) => eprintln!("[sparse_watch] {}", e),
+            }
+        }
+        if last_change.elapsed() >= stable_for {
+            break "stable";
+        }
+    };
+    watches.0.lock().unwrap_or_else(|e| e.into_inner()).remove(&path);
+
+    current.finished = Some(finished);
+    events.push(current.clone());
+    println!(
+        "[sparse_watch] {} finished ({}): {} of {} bytes in {} ranges",
+        path,
+        finished,
+        current.covered_bytes,
+        current.extent,
+        current.ranges.len()
+    );
+    Ok(current)
+}
+
+/// Stop a running `watch_sparse_entry` for `path`.
+#[tauri::command]
+pub fn stop_watch_sparse_entry(watches: tauri::State<'_, SparseWatches>, path: String) -> bool {
+    watches.0.lock().unwrap_or_else(|e| e.into_inner()).remove(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_gaps_between_ranges() {
+        let dir = std::env::temp_dir().join(format!("cachephoenix-sparse-watch-{}", std::process::id()));
+        let path = crate::fixture::FixtureCache::create(&dir)
+            .unwrap()
+            .add_sparse_entry("https://cdn.example/clip.mp4", &[(300, &[2u8; 50]), (0, &[1u8; 100])])
+            .unwrap();
+        let coverage = sparse_coverage(&path).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(coverage.ranges, vec![[0, 100], [300, 350]]);
+        assert_eq!(coverage.gaps, vec![[100, 300]]);
+        assert_eq!((coverage.covered_bytes, coverage.extent), (150, 350));
+    }
+}
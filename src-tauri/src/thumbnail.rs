@@ -0,0 +1,114 @@
+use serde::Serialize;
+
+// ─── Video thumbnails ───────────────────────────────────────────────────────
+//
+// The scan results grid shows a frame of every video candidate. The frame is
+// grabbed by the ffmpeg sidecar run from here and written as a small PNG
+// (at most THUMB_WIDTH wide), so the frontend only ever reads the thumbnail,
+// never the video. A truncated clip often ends before the requested
+// timestamp; the first frame is used then. Thumbnails are scratch files: the
+// output must be a `dccr_*` PNG in the temp directory, since an earlier
+// frame at that path is removed before each attempt.
+
+const THUMB_WIDTH: u32 = 480;
+const DEFAULT_TIMESTAMP: &str = "00:00:01";
+
+#[derive(Debug, Serialize)]
+pub struct Thumbnail {
+    pub path: String,
+    /// Where the frame was taken: the requested timestamp, or "0" when the
+    /// video is shorter than that
+    pub timestamp: String,
+    pub bytes: u64,
+}
+
+/// A position ffmpeg's `-ss` accepts: seconds ("1.5") or [[HH:]MM:]SS[.fff].
+fn valid_timestamp(timestamp: &str) -> bool {
+    let parts: Vec<&str> = timestamp.split(':').collect();
+    parts.len() <= 3
+        && parts.iter().enumerate().all(|(i, part)| {
+            let (whole, fraction) = part.split_once('.').unwrap_or((part, ""));
+            !whole.is_empty()
+                && whole.bytes().all(|b| b.is_ascii_digit())
+                && fraction.bytes().all(|b| b.is_ascii_digit())
+                && (fraction.is_empty() || i == parts.len() - 1)
+        })
+}
+
+fn thumbnail_args(path: &str, out_png: &str, timestamp: &str) -> Vec<String> {
+    let scale = format!("scale='min({},iw)':-2", THUMB_WIDTH);
+    [
+        "-y", "-v", "error",
+        "-fflags", "+genpts+discardcorrupt",
+        "-analyzeduration", "100M",
+        "-probesize", "100M",
+        "-err_detect", "ignore_err",
+        "-ss", timestamp,
+        "-i", path,
+        "-frames:v", "1",
+        "-update", "1",
+        "-vf", &scale,
+        out_png,
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect()
+}
+
+/// Grab one frame of the video at `path` into `out_png`, scaled to at most
+/// 480 px wide. `timestamp` is seconds or HH:MM:SS (default 1 s); a video
+/// shorter than that gives its first frame instead. `out_png` must be a
+/// scratch file in the temp directory.
+#[tauri::command]
+pub async fn generate_thumbnail(
+    app: tauri::AppHandle,
+    path: String,
+    out_png: String,
+    timestamp: Option<String>,
+) -> Result<Thumbnail, String> {
+    let requested = timestamp.unwrap_or_else(|| DEFAULT_TIMESTAMP.to_string());
+    if !valid_timestamp(&requested) {
+        return Err(format!("Invalid timestamp \"{}\" (expected seconds or HH:MM:SS)", requested));
+    }
+    if !out_png.to_ascii_lowercase().ends_with(".png") || !crate::consent::is_scratch(&out_png) {
+        return Err(format!("Thumbnail output must be a temporary .png file: {}", out_png));
+    }
+    let job = format!("thumbnail of {}", path);
+    let attempts = if requested.trim_start_matches(['0', ':', '.']).is_empty() {
+        vec!["0"]
+    } else {
+        vec![requested.as_str(), "0"]
+    };
+    let mut error = String::new();
+    for timestamp in attempts {
+        // A frame from an earlier run must not pass for this one's.
+        let _ = std::fs::remove_file(&out_png);
+        let args = thumbnail_args(&path, &out_png, timestamp);
+        let output = crate::sidecar::run(&app, "ffmpeg", &args, &job, &path).await?;
+        let bytes = std::fs::metadata(&out_png).map(|m| m.len()).unwrap_or(0);
+        if output.success() && bytes > 0 {
+            println!("[thumbnail] {} at {} -> {} ({} bytes)", path, timestamp, out_png, bytes);
+            return Ok(Thumbnail { path: out_png, timestamp: timestamp.to_string(), bytes });
+        }
+        error = output.stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("no frame decoded").to_string();
+    }
+    Err(format!("Could not grab a frame from {}: {}", path, error.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ffmpeg_positions_only() {
+        for ok in ["1", "0.5", "00:00:01", "1:02:03.250", "90.04"] {
+            assert!(valid_timestamp(ok), "{}", ok);
+        }
+        for bad in ["", "-1", "1.5:00", "00:00:01:00", "1e3", "-i", "00::01"] {
+            assert!(!valid_timestamp(bad), "{}", bad);
+        }
+        let args = thumbnail_args("in.mp4", "out.png", "2");
+        assert_eq!(args[args.iter().position(|a| a == "-ss").unwrap() + 1], "2");
+        assert_eq!(args.last().map(String::as_str), Some("out.png"));
+    }
+}
@@ -0,0 +1,186 @@
+use serde::Serialize;
+use std::path::Path;
+
+// Targeted lookup of a Discord user's avatars and banners.
+//
+// Discord fetches profile images from stable CDN paths keyed by user ID:
+//   /avatars/{user_id}/{hash}.{ext}
+//   /banners/{user_id}/{hash}.{ext}
+//   /guilds/{guild_id}/users/{user_id}/avatars|banners/{hash}.{ext}
+// Every time a user changes their avatar the hash changes, so each distinct
+// hash still sitting in a cache is an older version of the picture. Only URL
+// keys are read here; bodies are recovered through the normal pipeline.
+
+const PROFILE_KINDS: &[&str] = &["avatar", "banner", "guild_member_avatar", "guild_member_banner"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserMediaHit {
+    /// "avatar", "banner", "guild_member_avatar" or "guild_member_banner"
+    pub kind: &'static str,
+    pub url: String,
+    /// Asset hash without extension; one hash = one version of the image
+    pub asset_hash: String,
+    /// `a_`-prefixed hashes are animated (GIF) assets
+    pub animated: bool,
+    /// Set for per-server profile images
+    pub guild_id: Option<String>,
+    /// Cache directory the entry was found in
+    pub source: String,
+    /// Files holding the entry's data
+    pub files: Vec<String>,
+    /// When the entry was cached (unix seconds), if known
+    pub cached_at: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserMediaResult {
+    pub user_id: String,
+    /// Newest first
+    pub hits: Vec<UserMediaHit>,
+    /// Distinct asset hashes across all hits
+    pub versions: usize,
+    pub searched_sources: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Return the hit if `key` is a profile image belonging to `user_id`.
+fn match_key(key: &str, user_id: &str) -> Option<UserMediaHit> {
+    let m = crate::discord_cdn::match_response(key, None)?;
+    let kind = *PROFILE_KINDS.iter().find(|k| **k == m.kind)?;
+    // Group keys are "<kind>/<id>/<hash>" or "<kind>/<guild>/<user>/<hash>".
+    let parts: Vec<&str> = m.group_key.split('/').collect();
+    let (guild_id, owner, hash) = match parts.as_slice() {
+        [_, owner, hash] => (None, *owner, *hash),
+        [_, guild, owner, hash] => (Some(guild.to_string()), *owner, *hash),
+        _ => return None,
+    };
+    if owner != user_id {
+        return None;
+    }
+    Some(UserMediaHit {
+        kind,
+        url: key.rsplit(' ').next().unwrap_or(key).to_string(),
+        asset_hash: hash.to_string(),
+        animated: hash.starts_with("a_"),
+        guild_id,
+        source: String::new(),
+        files: Vec::new(),
+        cached_at: None,
+    })
+}
+
+/// Search a blockfile cache via its index.
+fn search_blockfile(dir: &str, user_id: &str, hits: &mut Vec<UserMediaHit>) -> Result<(), String> {
+    let listing = crate::blockfile_index::list_blockfile_entries(dir.to_string())?;
+    for entry in listing.entries {
+        let Some(mut hit) = match_key(&entry.url, user_id) else {
+            continue;
+        };
+        hit.source = dir.to_string();
+        hit.cached_at = entry.creation_time;
+        hit.files = entry
+            .streams
+            .iter()
+            .filter(|s| s.stream_index == 1)
+            .map(|s| Path::new(dir).join(&s.slot.file).to_string_lossy().to_string())
+            .collect();
+        hits.push(hit);
+    }
+    Ok(())
+}
+
+/// Search a Simple Cache directory by reading each entry's key.
+fn search_simple_cache(dir: &str, user_id: &str, hits: &mut Vec<UserMediaHit>) -> Result<(), String> {
+    let files = crate::cache::list_cache_files(dir)?;
+    for file in files.iter().filter(|f| f.name.ends_with("_0")) {
+        let Some(key) = crate::read_simple_cache_key(&file.path) else {
+            continue;
+        };
+        let Some(mut hit) = match_key(&key, user_id) else {
+            continue;
+        };
+        let stem = &file.name[..file.name.len() - 2];
+        hit.source = dir.to_string();
+        hit.cached_at = Some(file.modified_at);
+        hit.files = files
+            .iter()
+            .filter(|f| f.name.len() == file.name.len() && f.name.starts_with(stem))
+            .map(|f| f.path.clone())
+            .collect();
+        hits.push(hit);
+    }
+    Ok(())
+}
+
+/// Find every cached avatar/banner version for a Discord user ID.
+/// Searches `dirs`, or all known cache locations when omitted.
+#[tauri::command(async)]
+pub fn find_user_media(user_id: String, dirs: Option<Vec<String>>) -> Result<UserMediaResult, String> {
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() || !user_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Not a Discord user ID: {}", user_id));
+    }
+    let dirs = dirs.unwrap_or_else(crate::cache::get_default_cache_paths);
+
+    let mut hits = Vec::new();
+    let mut errors = Vec::new();
+    let mut searched_sources = Vec::new();
+    for dir in dirs {
+        let path = Path::new(&dir);
+        if !path.is_dir() {
+            continue;
+        }
+        // GPUCache is blockfile-shaped and Code Cache Simple Cache-shaped; skip both.
+        let Some(scan_path) = crate::cache::classify_dir(path).scan_dir else {
+            continue;
+        };
+        let scan_dir = scan_path.to_string_lossy();
+        let result = if crate::cache::is_blockfile_dir(&scan_path) {
+            search_blockfile(&scan_dir, &user_id, &mut hits)
+        } else {
+            search_simple_cache(&scan_dir, &user_id, &mut hits)
+        };
+        if let Err(e) = result {
+            errors.push(format!("{}: {}", dir, e));
+        }
+        searched_sources.push(dir);
+    }
+
+    hits.sort_by(|a, b| {
+        b.cached_at
+            .unwrap_or(0.0)
+            .total_cmp(&a.cached_at.unwrap_or(0.0))
+    });
+    let mut hashes: Vec<&str> = hits.iter().map(|h| h.asset_hash.as_str()).collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    let versions = hashes.len();
+
+    println!(
+        "[user_media] {} hits ({} versions) for user {} across {} sources",
+        hits.len(),
+        versions,
+        user_id,
+        searched_sources.len()
+    );
+    Ok(UserMediaResult { user_id, hits, versions, searched_sources, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_only_the_requested_user() {
+        let hit = match_key("https://cdn.discordapp.com/avatars/42/a_deadbeef.gif?size=128", "42").unwrap();
+        assert_eq!(hit.kind, "avatar");
+        assert_eq!(hit.asset_hash, "a_deadbeef");
+        assert!(hit.animated);
+        assert!(match_key("https://cdn.discordapp.com/avatars/43/a_deadbeef.gif", "42").is_none());
+
+        let guild = match_key("_dk_https://discord.com https://cdn.discordapp.com/guilds/7/users/42/banners/cafe.png", "42").unwrap();
+        assert_eq!(guild.kind, "guild_member_banner");
+        assert_eq!(guild.guild_id.as_deref(), Some("7"));
+        assert_eq!(guild.url, "https://cdn.discordapp.com/guilds/7/users/42/banners/cafe.png");
+    }
+}
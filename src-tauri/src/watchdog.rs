@@ -0,0 +1,393 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// ─── Stall watchdog ─────────────────────────────────────────────────────────
+//
+// A read from a network share that dropped, or an ffmpeg run stuck on a
+// broken stream, never errors — it just never returns, and the job waiting
+// on it hangs with it. Long operations register here and report bytes as
+// they go: file reads of WATCHED_READ_MIN bytes and more through
+// `read_file` (smaller ones are read directly — a thread per cache file
+// would cost more than the reads), sidecars through `watch_sidecar` and
+// `report_operation_progress` from the frontend, or `sidecar::run` for the
+// ones the backend starts. A monitor thread looks for operations that
+// haven't advanced for the stall timeout and emits `operation-stalled` with
+// what they're blocked on. The user can then skip one (its caller gets an
+// error and moves on; a blocked read's thread is left behind) or force-kill
+// it (sidecars only; the process and its children are killed). Only child
+// processes of the app are watched or killed, whatever pid the frontend
+// reports.
+
+pub const STALL_EVENT: &str = "operation-stalled";
+/// Seconds without progress before an operation counts as stalled.
+pub const DEFAULT_STALL_TIMEOUT_SECS: u64 = 60;
+/// Reads are done in chunks of this size so a slow one still shows progress.
+const READ_CHUNK: usize = 4 * 1024 * 1024;
+/// How often a watched read checks whether it has been skipped.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Files smaller than this are read without a watch.
+const WATCHED_READ_MIN: u64 = 16 * 1024 * 1024;
+
+static STALL_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_STALL_TIMEOUT_SECS);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+fn now_ms() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
+#[derive(Debug)]
+struct WatchState {
+    bytes: AtomicU64,
+    last_progress_ms: AtomicU64,
+    abandoned: AtomicBool,
+}
+
+impl WatchState {
+    fn advance(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.last_progress_ms.store(now_ms(), Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct Operation {
+    job: Option<String>,
+    /// "io" or "sidecar"
+    kind: &'static str,
+    path: String,
+    pid: Option<u32>,
+    started_ms: u64,
+    state: Arc<WatchState>,
+}
+
+static OPERATIONS: Mutex<Option<HashMap<u64, Operation>>> = Mutex::new(None);
+
+fn with_operations<T>(f: impl FnOnce(&mut HashMap<u64, Operation>) -> T) -> T {
+    let mut operations = OPERATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    f(operations.get_or_insert_with(HashMap::new))
+}
+
+fn register(job: Option<String>, kind: &'static str, path: &str, pid: Option<u32>) -> (u64, Arc<WatchState>) {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let now = now_ms();
+    let state = Arc::new(WatchState {
+        bytes: AtomicU64::new(0),
+        last_progress_ms: AtomicU64::new(now),
+        abandoned: AtomicBool::new(false),
+    });
+    let operation = Operation { job, kind, path: path.to_string(), pid, started_ms: now, state: state.clone() };
+    with_operations(|ops| ops.insert(id, operation));
+    (id, state)
+}
+
+/// A registered operation; unregisters on drop.
+pub(crate) struct Watch {
+    id: u64,
+    state: Arc<WatchState>,
+}
+
+impl Watch {
+    /// Watch an in-process operation on `path`, under the thread's current job.
+    pub(crate) fn io(path: &str) -> Self {
+        let (id, state) = register(crate::crash::current_job(), "io", path, None);
+        Watch { id, state }
+    }
+
+    /// Watch a sidecar the backend spawned.
+    pub(crate) fn sidecar(job: &str, path: &str, pid: u32) -> Self {
+        let (id, state) = register(Some(job.to_string()), "sidecar", path, Some(pid));
+        Watch { id, state }
+    }
+
+    /// `bytes` more output or data seen.
+    pub(crate) fn advance(&self, bytes: u64) {
+        self.state.advance(bytes);
+    }
+
+    /// The user chose to skip or kill this operation.
+    pub(crate) fn abandoned(&self) -> bool {
+        self.state.abandoned.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        with_operations(|ops| ops.remove(&self.id));
+    }
+}
+
+/// `std::fs::read`, watched when the file is large. The read runs on its own
+/// thread so a skip can return to the caller even while the read itself is
+/// stuck in the kernel.
+pub(crate) fn read_file(path: &str) -> std::io::Result<Vec<u8>> {
+    if std::fs::metadata(path)?.len() < WATCHED_READ_MIN {
+        return std::fs::read(path);
+    }
+    let watch = Watch::io(path);
+    let (tx, rx) = mpsc::channel();
+    let state = watch.state.clone();
+    let owned = path.to_string();
+    std::thread::spawn(move || {
+        let _ = tx.send(read_chunked(&owned, &state));
+    });
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(result) => return result,
+            Err(RecvTimeoutError::Timeout) if watch.abandoned() => {
+                eprintln!("[watchdog] Skipped stalled read of {}", path);
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("Skipped stalled read of {}", path),
+                ));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(std::io::Error::other(format!("Read of {} ended without a result", path)));
+            }
+        }
+    }
+}
+
+fn read_chunked(path: &str, state: &WatchState) -> std::io::Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+    let mut data = Vec::with_capacity(size);
+    loop {
+        let read = (&mut file).take(READ_CHUNK as u64).read_to_end(&mut data)?;
+        if read == 0 || state.abandoned.load(Ordering::Relaxed) {
+            return Ok(data);
+        }
+        state.advance(read as u64);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StalledOperation {
+    pub id: u64,
+    pub job: Option<String>,
+    /// "io" or "sidecar"
+    pub kind: &'static str,
+    /// The file being read, or the file a sidecar is working on
+    pub path: String,
+    pub pid: Option<u32>,
+    /// Bytes read, or for a sidecar bytes of output seen, so far
+    pub bytes: u64,
+    pub stalled_for_secs: u64,
+    pub running_for_secs: u64,
+}
+
+/// Operations that haven't advanced for `timeout` and haven't been skipped.
+fn stalled(timeout: Duration) -> Vec<StalledOperation> {
+    let now = now_ms();
+    let timeout = timeout.as_millis() as u64;
+    let mut found: Vec<StalledOperation> = with_operations(|ops| {
+        ops.iter()
+            .filter(|(_, op)| !op.state.abandoned.load(Ordering::Relaxed))
+            .filter_map(|(&id, op)| {
+                let idle = now.saturating_sub(op.state.last_progress_ms.load(Ordering::Relaxed));
+                (idle >= timeout).then(|| StalledOperation {
+                    id,
+                    job: op.job.clone(),
+                    kind: op.kind,
+                    path: op.path.clone(),
+                    pid: op.pid,
+                    bytes: op.state.bytes.load(Ordering::Relaxed),
+                    stalled_for_secs: idle / 1000,
+                    running_for_secs: now.saturating_sub(op.started_ms) / 1000,
+                })
+            })
+            .collect()
+    });
+    found.sort_by_key(|op| op.id);
+    found
+}
+
+/// Start the monitor thread. Call once, from `setup`. The event is sent
+/// whenever the set of stalled operations changes, including to empty.
+pub fn start(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut events = crate::events::Coalescer::for_app(app, STALL_EVENT);
+        let mut reported: Vec<u64> = Vec::new();
+        loop {
+            std::thread::sleep(Duration::from_secs(1));
+            // Held-back changes, and crash reports, go out once the rate allows.
+            events.tick();
+            crate::crash::tick_events();
+            let now_stalled = stalled(Duration::from_secs(STALL_TIMEOUT_SECS.load(Ordering::Relaxed)));
+            let ids: Vec<u64> = now_stalled.iter().map(|op| op.id).collect();
+            if ids == reported {
+                continue;
+            }
+            for op in now_stalled.iter().filter(|op| !reported.contains(&op.id)) {
+                eprintln!(
+                    "[watchdog] {} on {} stalled for {}s ({} bytes so far)",
+                    op.kind, op.path, op.stalled_for_secs, op.bytes
+                );
+            }
+            reported = ids;
+            events.push(now_stalled);
+        }
+    });
+}
+
+fn abandon(id: u64) -> Result<Option<u32>, String> {
+    with_operations(|ops| {
+        let op = ops.get(&id).ok_or_else(|| format!("No running operation {}", id))?;
+        op.state.abandoned.store(true, Ordering::Relaxed);
+        Ok(op.pid)
+    })
+}
+
+/// Parent of process `pid`, if it is still running.
+#[cfg(windows)]
+fn parent_pid(pid: u32) -> Option<u32> {
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command"])
+        .arg(format!("(Get-CimInstance Win32_Process -Filter 'ProcessId={}').ParentProcessId", pid))
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(windows))]
+fn parent_pid(pid: u32) -> Option<u32> {
+    let output = std::process::Command::new("ps").args(["-o", "ppid=", "-p", &pid.to_string()]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Whether `pid` is a process this app started.
+fn is_own_child(pid: u32) -> bool {
+    parent_pid(pid) == Some(std::process::id())
+}
+
+/// `pid` and its descendants, collected before anything is killed: once a
+/// parent dies its children are re-parented and can't be found any more.
+#[cfg(not(windows))]
+fn process_tree(pid: u32) -> Vec<u32> {
+    let mut tree = vec![pid];
+    let mut next = 0;
+    while let Some(&parent) = tree.get(next) {
+        if let Ok(output) = std::process::Command::new("pgrep").args(["-P", &parent.to_string()]).output() {
+            tree.extend(String::from_utf8_lossy(&output.stdout).lines().filter_map(|l| l.trim().parse::<u32>().ok()));
+        }
+        next += 1;
+    }
+    tree
+}
+
+fn kill_process(pid: u32) -> Result<(), String> {
+    if !is_own_child(pid) {
+        return Err(format!("Process {} was not started by CachePhoenix", pid));
+    }
+    #[cfg(windows)]
+    let status = std::process::Command::new("taskkill").args(["/PID", &pid.to_string(), "/T", "/F"]).status();
+    #[cfg(not(windows))]
+    let status = std::process::Command::new("kill")
+        .arg("-9")
+        .args(process_tree(pid).iter().map(|p| p.to_string()))
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("Failed to kill process {}: exit status {}", pid, status)),
+        Err(e) => Err(format!("Failed to kill process {}: {}", pid, e)),
+    }
+}
+
+/// Watch a sidecar the frontend spawned; returns the id to report progress
+/// and end the watch with. `pid` must be a child process of the app.
+#[tauri::command]
+pub fn watch_sidecar(job: String, pid: u32, path: String) -> Result<u64, String> {
+    if !is_own_child(pid) {
+        return Err(format!("Process {} was not started by CachePhoenix", pid));
+    }
+    Ok(register(Some(job), "sidecar", &path, Some(pid)).0)
+}
+
+/// `bytes` is the running total; the operation counts as progressing only
+/// when it grows.
+#[tauri::command]
+pub fn report_operation_progress(id: u64, bytes: u64) {
+    with_operations(|ops| {
+        if let Some(op) = ops.get(&id) {
+            if op.state.bytes.fetch_max(bytes, Ordering::Relaxed) < bytes {
+                op.state.last_progress_ms.store(now_ms(), Ordering::Relaxed);
+            }
+        }
+    })
+}
+
+#[tauri::command]
+pub fn end_operation_watch(id: u64) {
+    with_operations(|ops| ops.remove(&id));
+}
+
+#[tauri::command]
+pub fn list_stalled_operations() -> Vec<StalledOperation> {
+    stalled(Duration::from_secs(STALL_TIMEOUT_SECS.load(Ordering::Relaxed)))
+}
+
+/// Change the stall timeout; returns the value now in effect.
+#[tauri::command]
+pub fn set_stall_timeout(seconds: u64) -> u64 {
+    let seconds = seconds.clamp(5, 3600);
+    STALL_TIMEOUT_SECS.store(seconds, Ordering::Relaxed);
+    seconds
+}
+
+/// Stop waiting for an operation. A read's caller gets an error and moves
+/// on; for a sidecar the frontend stops waiting, but the process keeps running.
+#[tauri::command]
+pub fn skip_operation(id: u64) -> Result<(), String> {
+    abandon(id).map(|_| ())
+}
+
+/// Kill a sidecar's process tree. In-process reads can't be killed, so for
+/// those this is the same as a skip. A pid that is no longer the app's child
+/// (the sidecar exited and the pid was reused) is left alone.
+#[tauri::command]
+pub fn kill_operation(id: u64) -> Result<(), String> {
+    match abandon(id)? {
+        Some(pid) => {
+            eprintln!("[watchdog] Killing stalled process {}", pid);
+            kill_process(pid)
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_stalls_until_skipped() {
+        let path = std::env::temp_dir().join(format!("cachephoenix-watchdog-{}", std::process::id()));
+        std::fs::write(&path, vec![7u8; WATCHED_READ_MIN as usize + 10]).unwrap();
+        let data = read_file(&path.to_string_lossy());
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(data.unwrap().len(), WATCHED_READ_MIN as usize + 10);
+        assert!(watch_sidecar("ffmpeg".into(), 1, "x".into()).is_err());
+        assert!(kill_process(1).is_err());
+
+        let watch = Watch::io("/mnt/share/f_000123");
+        watch.state.advance(512);
+        let id = watch.id;
+        std::thread::sleep(Duration::from_millis(20));
+        let found = stalled(Duration::from_millis(10));
+        let op = found.iter().find(|op| op.id == id).unwrap();
+        assert_eq!((op.kind, op.path.as_str(), op.bytes), ("io", "/mnt/share/f_000123", 512));
+        assert!(stalled(Duration::from_secs(60)).iter().all(|op| op.id != id));
+
+        skip_operation(id).unwrap();
+        assert!(watch.abandoned());
+        assert!(stalled(Duration::from_millis(10)).iter().all(|op| op.id != id));
+        drop(watch);
+        assert!(skip_operation(id).is_err());
+    }
+}
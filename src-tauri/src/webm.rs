@@ -0,0 +1,530 @@
+use serde::Serialize;
+
+use crate::chunk_layout;
+use crate::reconstruction::{self, GapPolicy, ReconstructionResult};
+use crate::reconstruction_plan::{GapWalk, Step};
+
+// ─── WebM / Matroska reconstruction ─────────────────────────────────────────
+//
+// Matroska is EBML: every element is a variable-length ID, a variable-length
+// data size (all ones = unknown, used by live muxers such as MediaRecorder)
+// and the data. A WebM file is
+//   EBML header
+//   Segment
+//     SeekHead, Info, Tracks, [Cues]     positions relative to Segment data
+//     Cluster*                            Timecode + SimpleBlock/BlockGroup*
+//     [Cues], [Tags]
+// Chunked WebM is assembled like chunked MP4 — header file first, then the
+// rest in download order with missing chunks handled by the gap policy — and
+// then repaired: a final Cluster cut off mid-block is trimmed to its last
+// whole block, the Segment size is made to match, and Cues that can no longer
+// point at the right Clusters are overwritten with a Void element. Trimming
+// blocks off the final Cluster leaves it where it was, so that alone keeps
+// the Cues; dropping it whole or closing gaps moves what they point at.
+
+const EBML_HEADER: u32 = 0x1A45_DFA3;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const INFO: u32 = 0x1549_A966;
+const TRACKS: u32 = 0x1654_AE6B;
+const CUES: u32 = 0x1C53_BB6B;
+const CLUSTER: u32 = 0x1F43_B675;
+const TAGS: u32 = 0x1254_C367;
+const CHAPTERS: u32 = 0x1043_A770;
+const ATTACHMENTS: u32 = 0x1941_A469;
+const TIMECODE: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const VOID: u32 = 0xEC;
+/// Segment children; an unknown-size Cluster ends where one of these starts.
+const TOP_LEVEL: &[u32] = &[SEEK_HEAD, INFO, TRACKS, CUES, CLUSTER, TAGS, CHAPTERS, ATTACHMENTS];
+
+/// One EBML element header.
+#[derive(Debug, Clone, Copy)]
+struct Element {
+    id: u32,
+    offset: usize,
+    /// Bytes of the ID
+    id_len: usize,
+    /// Bytes of the size field
+    size_len: usize,
+    /// None for unknown size
+    size: Option<u64>,
+}
+
+impl Element {
+    fn data_start(&self) -> usize {
+        self.offset + self.id_len + self.size_len
+    }
+
+    /// End of the element's data; None for unknown size or past `usize`.
+    fn end(&self) -> Option<usize> {
+        self.data_start().checked_add(usize::try_from(self.size?).ok()?)
+    }
+}
+
+/// Element ID at `pos`, marker bits kept: (id, length).
+fn read_id(data: &[u8], pos: usize) -> Option<(u32, usize)> {
+    let len = data.get(pos)?.leading_zeros() as usize + 1;
+    if len > 4 {
+        return None;
+    }
+    let bytes = data.get(pos..pos + len)?;
+    Some((bytes.iter().fold(0u32, |acc, &b| acc << 8 | b as u32), len))
+}
+
+/// Data size at `pos`, marker bits removed: (size, length); all ones is None.
+fn read_size(data: &[u8], pos: usize) -> Option<(Option<u64>, usize)> {
+    let first = *data.get(pos)?;
+    if first == 0 {
+        return None;
+    }
+    let len = first.leading_zeros() as usize + 1;
+    let bytes = data.get(pos..pos + len)?;
+    let value = bytes[1..].iter().fold((first as u64) & (0xFF >> len), |acc, &b| acc << 8 | b as u64);
+    let all_ones = (1u64 << (7 * len)) - 1;
+    Some(((value != all_ones).then_some(value), len))
+}
+
+/// `value` as a size field of exactly `len` bytes; None if it doesn't fit.
+fn encode_size(value: u64, len: usize) -> Option<Vec<u8>> {
+    if !(1..=8).contains(&len) || value >= (1u64 << (7 * len)) - 1 {
+        return None;
+    }
+    let mut out = value.to_be_bytes()[8 - len..].to_vec();
+    out[0] |= 0x80 >> (len - 1);
+    Some(out)
+}
+
+fn read_element(data: &[u8], pos: usize) -> Option<Element> {
+    let (id, id_len) = read_id(data, pos)?;
+    let (size, size_len) = read_size(data, pos + id_len)?;
+    Some(Element { id, offset: pos, id_len, size_len, size })
+}
+
+/// Big-endian unsigned integer element data.
+fn read_uint(data: &[u8], el: &Element) -> Option<u64> {
+    let bytes = data.get(el.data_start()..el.end()?)?;
+    (bytes.len() <= 8).then(|| bytes.iter().fold(0u64, |acc, &b| acc << 8 | b as u64))
+}
+
+/// Next Cluster ID at or after `from`, for resyncing past zero-filled gaps.
+fn next_cluster(data: &[u8], from: usize) -> Option<usize> {
+    let id = CLUSTER.to_be_bytes();
+    data.get(from..)?.windows(4).position(|w| w == id).map(|at| from + at)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterInfo {
+    pub offset: u64,
+    pub timecode: Option<u64>,
+    pub blocks: usize,
+    /// Every child element parsed; false for damaged or cut-off Clusters
+    pub complete: bool,
+    /// Runs past the end of the data
+    pub cut_off: bool,
+    /// End of the Cluster's last whole child element
+    pub valid_end: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebmLayout {
+    pub segment_offset: u64,
+    /// Cues and SeekHead positions are relative to this
+    pub segment_data_start: u64,
+    /// None for unknown size
+    pub segment_size: Option<u64>,
+    pub has_tracks: bool,
+    pub cues_offset: Option<u64>,
+    pub clusters: Vec<ClusterInfo>,
+    /// Runs of unparseable bytes skipped to reach the next Cluster
+    pub resyncs: usize,
+}
+
+/// Walk a Cluster's children. An unknown-size Cluster ends at the next
+/// top-level element or the end of the data. Also returns where the next
+/// element starts, if that is known.
+fn parse_cluster(data: &[u8], el: &Element) -> (ClusterInfo, Option<usize>) {
+    let declared_end = el.end();
+    let limit = declared_end.unwrap_or(data.len()).min(data.len());
+    let mut info = ClusterInfo {
+        offset: el.offset as u64,
+        timecode: None,
+        blocks: 0,
+        complete: false,
+        cut_off: declared_end.is_none_or(|end| end > data.len()),
+        valid_end: el.data_start() as u64,
+    };
+    let mut pos = el.data_start();
+    while pos < limit {
+        let Some(child) = read_element(data, pos) else {
+            break;
+        };
+        if el.size.is_none() && TOP_LEVEL.contains(&child.id) {
+            break;
+        }
+        let Some(end) = child.end().filter(|&end| end <= limit) else {
+            break;
+        };
+        match child.id {
+            TIMECODE => info.timecode = read_uint(data, &child),
+            SIMPLE_BLOCK | BLOCK_GROUP => info.blocks += 1,
+            _ => {}
+        }
+        pos = end;
+        info.valid_end = end as u64;
+    }
+    let at_top_level = el.size.is_none() && read_id(data, pos).is_some_and(|(id, _)| TOP_LEVEL.contains(&id));
+    if el.size.is_none() {
+        // Without a size the Cluster is only cut off if its walk hit the end.
+        info.cut_off = !at_top_level && pos < data.len() && read_element(data, pos).is_some();
+    }
+    info.complete = at_top_level || (pos == limit && !info.cut_off);
+    let next = match el.size {
+        None => at_top_level.then_some(pos),
+        Some(_) => declared_end.filter(|&end| end <= data.len()),
+    };
+    (info, next)
+}
+
+/// Locate the Segment, its Cues and every Cluster. None if `data` doesn't
+/// start with an EBML header followed by a Segment.
+pub fn parse_layout(data: &[u8]) -> Option<WebmLayout> {
+    let header = read_element(data, 0).filter(|el| el.id == EBML_HEADER)?;
+    let segment = read_element(data, header.end()?).filter(|el| el.id == SEGMENT)?;
+    let mut layout = WebmLayout {
+        segment_offset: segment.offset as u64,
+        segment_data_start: segment.data_start() as u64,
+        segment_size: segment.size,
+        has_tracks: false,
+        cues_offset: None,
+        clusters: Vec::new(),
+        resyncs: 0,
+    };
+    let segment_end = segment.end().unwrap_or(data.len()).min(data.len());
+    let mut pos = segment.data_start();
+    while pos < segment_end {
+        let Some(el) = read_element(data, pos).filter(|el| el.id == CLUSTER || el.size.is_some()) else {
+            match next_cluster(data, pos + 1) {
+                Some(next) => {
+                    layout.resyncs += 1;
+                    pos = next;
+                    continue;
+                }
+                None => break,
+            }
+        };
+        match el.id {
+            CLUSTER => {
+                let (cluster, next) = parse_cluster(data, &el);
+                let cut_off = cluster.cut_off;
+                layout.clusters.push(cluster);
+                if cut_off {
+                    break;
+                }
+                match next.or_else(|| next_cluster(data, pos + 1).inspect(|_| layout.resyncs += 1)) {
+                    Some(next) => pos = next,
+                    None => break,
+                }
+                continue;
+            }
+            TRACKS => layout.has_tracks = true,
+            CUES => layout.cues_offset = Some(el.offset as u64),
+            _ => {}
+        }
+        match el.end() {
+            Some(end) if end <= data.len() => pos = end,
+            _ => break,
+        }
+    }
+    Some(layout)
+}
+
+/// The final Cluster, cut off by the end of the data, trimmed to whole blocks.
+#[derive(Debug, Clone, Serialize)]
+pub struct TruncatedCluster {
+    pub offset: u64,
+    pub kept_blocks: usize,
+    pub dropped_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebmReconstruction {
+    #[serde(flatten)]
+    pub result: ReconstructionResult,
+    pub clusters: usize,
+    pub truncated_cluster: Option<TruncatedCluster>,
+    /// "kept", "voided" (they would point at the wrong Clusters) or "missing"
+    pub cues: &'static str,
+}
+
+/// Rewrite the size field of the element at `offset` in place, keeping its
+/// width; unknown size when the value doesn't fit.
+fn patch_size(data: &mut [u8], offset: usize, value: u64) {
+    let Some(el) = read_element(data, offset) else {
+        return;
+    };
+    let width = el.size_len;
+    let encoded = encode_size(value, width).unwrap_or_else(|| {
+        let mut all_ones = vec![0xFF; width];
+        all_ones[0] = 0xFF >> (width - 1);
+        all_ones
+    });
+    let at = offset + el.id_len;
+    data[at..at + width].copy_from_slice(&encoded);
+}
+
+/// Overwrite the element at `offset` with a Void element of the same length.
+fn void_element(data: &mut [u8], offset: usize) -> bool {
+    let Some(end) = read_element(data, offset).and_then(|el| el.end()).filter(|&end| end <= data.len()) else {
+        return false;
+    };
+    let Some(size) = (end - offset).checked_sub(9).and_then(|len| encode_size(len as u64, 8)) else {
+        return false;
+    };
+    data[offset] = VOID as u8;
+    data[offset + 1..offset + 9].copy_from_slice(&size);
+    true
+}
+
+/// Trim a cut-off final Cluster, fix the Segment size and void stale Cues.
+fn repair(data: &mut Vec<u8>, result: &ReconstructionResult) -> Result<WebmReconstruction, String> {
+    let layout = parse_layout(data).ok_or("No EBML Segment found in the assembled data")?;
+    println!(
+        "[webm] Segment at {}: {} clusters, tracks: {}, cues: {:?}, resynced {} time(s)",
+        layout.segment_offset,
+        layout.clusters.len(),
+        layout.has_tracks,
+        layout.cues_offset,
+        layout.resyncs
+    );
+
+    let mut truncated_cluster = None;
+    let mut clusters = layout.clusters.len();
+    if let Some(last) = layout.clusters.last().filter(|c| c.cut_off) {
+        let offset = last.offset as usize;
+        let keep_to = if last.blocks > 0 { last.valid_end as usize } else { offset };
+        let dropped = (data.len() - keep_to) as u64;
+        println!(
+            "[webm] Final cluster at {} is cut off: keeping {} whole block(s), dropping {} bytes",
+            offset, last.blocks, dropped
+        );
+        data.truncate(keep_to);
+        if last.blocks > 0 {
+            let el = read_element(data, offset).ok_or("Cluster header unreadable")?;
+            if el.size.is_some() {
+                patch_size(data, offset, (keep_to - el.data_start()) as u64);
+            }
+        } else {
+            clusters -= 1;
+        }
+        truncated_cluster = Some(TruncatedCluster {
+            offset: last.offset,
+            kept_blocks: last.blocks,
+            dropped_bytes: dropped,
+        });
+    }
+
+    let segment_len = (data.len() as u64).saturating_sub(layout.segment_data_start);
+    if layout.segment_size.is_some_and(|size| size != segment_len) {
+        patch_size(data, layout.segment_offset as usize, segment_len);
+    }
+
+    let dropped_cluster = truncated_cluster.as_ref().is_some_and(|t| t.kept_blocks == 0);
+    let moved = (result.gaps_found > 0 && !result.gap_policy.pads()) || result.truncated || dropped_cluster;
+    let cues = match layout.cues_offset {
+        None => "missing",
+        Some(offset) if moved && (offset as usize) < data.len() && void_element(data, offset as usize) => {
+            println!("[webm] Voided Cues at {}: cue points no longer match the clusters", offset);
+            "voided"
+        }
+        Some(_) => "kept",
+    };
+
+    Ok(WebmReconstruction { result: result.clone(), clusters, truncated_cluster, cues })
+}
+
+/// Assemble `header_path` and `chunk_paths` into `output` and repair the result.
+pub(crate) fn reconstruct(
+    header_path: &str,
+    chunk_paths: &[String],
+    output: &str,
+    gap_policy: GapPolicy,
+) -> Result<WebmReconstruction, String> {
+    let mut result = ReconstructionResult::new(gap_policy);
+    let mut data = crate::read_cache_body(header_path)?;
+    if !data.starts_with(&EBML_HEADER.to_be_bytes()) {
+        return Err(format!("Not a WebM/Matroska file (no EBML header): {}", header_path));
+    }
+
+    let mut chunks = chunk_paths.to_vec();
+    let ordering = reconstruction::order_chunks(&mut chunks);
+    result.ordering = Some(ordering);
+    println!("[webm] Ordered {} chunks by {:?}", chunks.len(), ordering);
+
+    // Most common chunk size, for padding missing chunks
+    let mut size_counts: std::collections::HashMap<u64, usize> = std::collections::HashMap::new();
+    for path in &chunks {
+        if let Ok(meta) = std::fs::metadata(path) {
+            *size_counts.entry(meta.len()).or_insert(0) += 1;
+        }
+    }
+    let full_chunk_size = size_counts
+        .into_iter()
+        .max_by_key(|&(size, count)| (count, size))
+        .map_or(data.len() as u64, |(size, _)| size);
+
+    let header_hex = crate::parse_cache_hex(header_path);
+    let placements = chunk_layout::hex_placements(&chunks, header_hex, None);
+    let mut walk = GapWalk::new(gap_policy);
+    // Bytes left out under CloseGaps, so gap offsets stay in the original layout.
+    let mut dropped = 0u64;
+    for ((path, chunk), placement) in reconstruction::read_ahead(chunks, crate::read_cache_body).zip(placements) {
+        let chunk = chunk?;
+        if crate::is_standalone_file_header(&chunk) || crate::is_transport_stream_entry(&path, &chunk) {
+            println!("[webm] Skipping standalone file in chunk list: {}", path);
+            continue;
+        }
+        let step = walk.step(placement);
+        if let Step::Write { gap: Some(gap) } | Step::Stop { gap } = &step {
+            let missing = gap.end - gap.start;
+            let size = missing * full_chunk_size;
+            let names = gap.clone().filter_map(|slot| chunk_layout::slot_file_name(slot, header_hex, None)).collect();
+            result.record_gap_range(data.len() as u64 + dropped, size, names);
+            match gap_policy {
+                GapPolicy::PadZeros => {
+                    println!("[webm] Gap: {} missing chunk(s) before {} ({} bytes zero-fill)", missing, path, size);
+                    data.resize(data.len() + size as usize, 0);
+                }
+                GapPolicy::TruncateAtGap => {
+                    println!("[webm] Gap: {} missing chunk(s) before {} — truncating here", missing, path);
+                }
+                GapPolicy::CloseGaps => {
+                    println!("[webm] Gap: {} missing chunk(s) before {} — dropped, not padded", missing, path);
+                    dropped += size;
+                }
+            }
+        }
+        if !matches!(step, Step::Write { .. }) {
+            result.truncated = true;
+            break;
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    let mut report = repair(&mut data, &result)?;
+    crate::output_file::write_output(output, &data)?;
+    report.result.finish(&data);
+    println!("[webm] Written {} bytes ({} clusters) to {}", data.len(), report.clusters, output);
+    Ok(report)
+}
+
+/// Reconstruct a chunked WebM/Matroska file. The first path is the file with
+/// the EBML header; `gap_policy` controls missing chunks (defaults to zero
+/// padding, which keeps Cues offsets valid). Needs a `consent` token covering
+/// `output` unless it is a temp scratch file.
+#[tauri::command(async)]
+pub fn reconstruct_chunked_webm(
+    state: tauri::State<'_, crate::consent::ConsentState>,
+    header_path: String,
+    chunk_paths: Vec<String>,
+    output: String,
+    gap_policy: Option<GapPolicy>,
+    consent: Option<String>,
+) -> Result<WebmReconstruction, String> {
+    let output = state.check_output(consent.as_deref(), &output)?;
+    let _job = crate::crash::job(format!("reconstructing {}", output));
+    if let Some(parent) = std::path::Path::new(&output).parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {}", e))?;
+    }
+    reconstruct(&header_path, &chunk_paths, &output, gap_policy.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn el(id: u32, payload: &[u8]) -> Vec<u8> {
+        let id_bytes = id.to_be_bytes();
+        let skip = id_bytes.iter().take_while(|&&b| b == 0).count();
+        let mut out = id_bytes[skip..].to_vec();
+        let width = if [SEGMENT, CLUSTER, CUES].contains(&id) { 8 } else { 1 };
+        out.extend(encode_size(payload.len() as u64, width).unwrap());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn cluster(timecode: u8) -> Vec<u8> {
+        let mut payload = el(TIMECODE, &[timecode]);
+        payload.extend(el(SIMPLE_BLOCK, &[0x81, 0, 0, 0x80, 1, 2, 3, 4, 5, 6]));
+        payload.extend(el(SIMPLE_BLOCK, &[0x81, 0, 20, 0x80, 7, 8, 9, 10, 11, 12]));
+        el(CLUSTER, &payload)
+    }
+
+    fn sample_file() -> Vec<u8> {
+        let mut body = el(INFO, &[0x2A, 0xD7, 0xB1, 0x83, 0x0F, 0x42, 0x40]);
+        body.extend(el(TRACKS, &el(0xAE, &[0xD7, 0x81, 0x01])));
+        body.extend(el(CUES, &el(0xBB, &[0xB3, 0x81, 0x00])));
+        for timecode in [0, 40, 80] {
+            body.extend(cluster(timecode));
+        }
+        let mut file = el(EBML_HEADER, &[0x42, 0x82, 0x84, b'w', b'e', b'b', b'm']);
+        file.extend(el(SEGMENT, &body));
+        file
+    }
+
+    /// Write `parts` as numbered cache files and reconstruct them.
+    fn reconstruct_files(name: &str, parts: &[(u32, &[u8])], policy: GapPolicy) -> (WebmReconstruction, Vec<u8>) {
+        let root = std::env::temp_dir().join(format!("cachephoenix-webm-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let paths: Vec<String> = parts
+            .iter()
+            .map(|(hex, bytes)| {
+                let path = root.join(format!("f_{:06x}", hex));
+                std::fs::write(&path, bytes).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let output = root.join("out.webm").to_string_lossy().to_string();
+        let report = reconstruct(&paths[0], &paths[1..], &output, policy);
+        let written = std::fs::read(&output);
+        let _ = std::fs::remove_dir_all(&root);
+        (report.unwrap(), written.unwrap())
+    }
+
+    #[test]
+    fn trims_cut_off_cluster_and_keeps_cues() {
+        let file = sample_file();
+        let layout = parse_layout(&file).unwrap();
+        assert_eq!(layout.clusters.len(), 3);
+        assert!(layout.has_tracks && layout.cues_offset.is_some());
+        assert_eq!(layout.clusters[2].timecode, Some(80));
+
+        // The last chunk lost the end of the final block.
+        let split = file.len() / 2;
+        let cut = &file[..file.len() - 4];
+        let parts: &[(u32, &[u8])] = &[(1, &cut[..split]), (2, &cut[split..])];
+        let (report, written) = reconstruct_files("trim", parts, GapPolicy::PadZeros);
+        let truncated = report.truncated_cluster.unwrap();
+        assert_eq!((report.clusters, truncated.kept_blocks, report.cues), (3, 1, "kept"));
+        let repaired = parse_layout(&written).unwrap();
+        assert!(repaired.clusters.iter().all(|c| c.complete));
+        assert_eq!(repaired.clusters[2].blocks, 1);
+        assert_eq!(repaired.cues_offset, layout.cues_offset);
+    }
+
+    #[test]
+    fn voids_cues_once_a_gap_moves_the_clusters() {
+        let file = sample_file();
+        let split = file.len() / 2;
+        let parts: &[(u32, &[u8])] = &[(1, &file[..split]), (3, &file[split..])];
+
+        let (padded, _) = reconstruct_files("pad", parts, GapPolicy::PadZeros);
+        assert_eq!((padded.result.gaps_found, padded.cues), (1, "kept"));
+        assert_eq!(padded.result.gap_ranges[0].missing_chunks, ["f_000002"]);
+
+        let (dropped, written) = reconstruct_files("drop", parts, GapPolicy::CloseGaps);
+        assert_eq!((dropped.result.gaps_found, dropped.cues), (1, "voided"));
+        assert_eq!(parse_layout(&written).unwrap().cues_offset, None);
+    }
+}
@@ -1,7 +1,64 @@
-import { Toaster } from "sonner";
+import { useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Toaster, toast } from "sonner";
 import { AppLayout } from "@/components/layout/AppLayout";
+import { abandonSidecarRun } from "@/lib/ffmpeg";
+import type { CrashReport, StalledOperation } from "@/types";
 
 export function App() {
+  // A backend panic leaves pending commands unresolved; at least say so.
+  useEffect(() => {
+    const unlisten = listen<CrashReport[]>("backend-crashed", ({ payload }) => {
+      for (const report of payload) {
+        toast.error(`Backend crashed during ${report.job ?? "an operation"}`, {
+          description: report.report_path
+            ? `${report.message}\nReport saved to ${report.report_path}`
+            : report.message,
+          duration: Infinity,
+        });
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // The watchdog re-sends the full stalled list whenever it changes; keep one
+  // toast per stalled operation and drop the ones that recovered.
+  useEffect(() => {
+    let shown = new Set<number>();
+    const act = (op: StalledOperation, command: "skip_operation" | "kill_operation") => {
+      invoke(command, { id: op.id })
+        .then(() => abandonSidecarRun(op.id))
+        .catch((err) => toast.error(String(err)));
+    };
+    const unlisten = listen<StalledOperation[]>("operation-stalled", ({ payload }) => {
+      const current = new Set(payload.map((op) => op.id));
+      for (const id of shown) {
+        if (!current.has(id)) toast.dismiss(`stalled-${id}`);
+      }
+      for (const op of payload) {
+        if (shown.has(op.id)) continue;
+        const what = op.kind === "sidecar" ? `ffmpeg (PID ${op.pid})` : "A file read";
+        toast.warning(`${what} stalled during ${op.job ?? "an operation"}`, {
+          id: `stalled-${op.id}`,
+          description: `No progress for ${op.stalled_for_secs}s on ${op.path}`,
+          duration: Infinity,
+          action: { label: "Skip", onClick: () => act(op, "skip_operation") },
+          cancel:
+            op.kind === "sidecar"
+              ? { label: "Force kill", onClick: () => act(op, "kill_operation") }
+              : undefined,
+        });
+      }
+      shown = current;
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   return (
     <>
       <AppLayout />
@@ -1,6 +1,7 @@
 import { useEffect, useState, useCallback } from 'react';
 import { useAppStore } from '@/store';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-dialog';
 import { formatBytes } from '@/lib/utils';
 import { 
@@ -11,27 +12,35 @@ import {
   ChevronRight,
   X
 } from 'lucide-react';
-import type { CachePathInfo } from '@/types';
-import { isMacOS, probeFullDiskAccess, testPathAccess, type FdaProbeResult, type PathAccessResult } from '@/lib/permissions';
+import type { CachePathInfo, CarveProgress, CarveReport, DriveScanProgress, DriveScanResult } from '@/types';
+import {
+  isMacOS, probeFullDiskAccess, requestWriteConsent, revokeWriteConsent, testPathAccess,
+  type FdaProbeResult, type PathAccessResult,
+} from '@/lib/permissions';
 import FullDiskAccessDialog from './FullDiskAccessDialog';
 
 const CUSTOM_PATHS_KEY = 'customCachePaths';
 
-function getStoredCustomPaths(): string[] {
+/**
+ * Custom paths live in the backend's registry (config folder). Older builds
+ * kept them in localStorage; those are moved over once.
+ */
+async function getStoredCustomPaths(): Promise<string[]> {
   try {
-    const stored = localStorage.getItem(CUSTOM_PATHS_KEY);
-    if (stored) {
-      const parsed = JSON.parse(stored);
-      if (Array.isArray(parsed)) return parsed;
+    const legacy = localStorage.getItem(CUSTOM_PATHS_KEY);
+    if (legacy) {
+      const parsed = JSON.parse(legacy);
+      if (Array.isArray(parsed)) {
+        for (const path of parsed) {
+          await invoke('add_custom_cache_path', { path });
+        }
+      }
+      localStorage.removeItem(CUSTOM_PATHS_KEY);
     }
   } catch {
-    // Corrupted data — ignore
+    // Corrupted or unavailable legacy data — ignore
   }
-  return [];
-}
-
-function saveCustomPaths(paths: string[]) {
-  localStorage.setItem(CUSTOM_PATHS_KEY, JSON.stringify(paths));
+  return invoke<string[]>('list_custom_cache_paths');
 }
 
 export default function CacheFolderSelect() {
@@ -50,6 +59,9 @@ export default function CacheFolderSelect() {
   const [showFdaDialog, setShowFdaDialog] = useState(false);
   const [fdaProbe, setFdaProbe] = useState<FdaProbeResult | null>(null);
   const [pathAccessResult, setPathAccessResult] = useState<PathAccessResult | null>(null);
+  const [pastedPath, setPastedPath] = useState('');
+  const [driveScan, setDriveScan] = useState<DriveScanProgress | null>(null);
+  const [carving, setCarving] = useState<CarveProgress | null>(null);
 
   useEffect(() => {
     const detectPaths = async () => {
@@ -72,8 +84,8 @@ export default function CacheFolderSelect() {
         const autoDetected = new Set(validatedPaths.map(p => p.path));
         setAutoDetectedPaths(autoDetected);
 
-        // 2. Load custom paths from localStorage and merge
-        const storedCustom = getStoredCustomPaths();
+        // 2. Load custom paths from the registry and merge
+        const storedCustom = await getStoredCustomPaths();
         const customValidated: CachePathInfo[] = [];
 
         for (const customPath of storedCustom) {
@@ -102,6 +114,16 @@ export default function CacheFolderSelect() {
     }
   }, [setCachePaths, cachePaths.length]);
 
+  // The backend expands `~/...` and `%APPDATA%\...`; info.path is always absolute.
+  const addCustomPath = async (path: string) => {
+    const info = await invoke<CachePathInfo>('validate_cache_path', { path });
+    // Add to store if not exists
+    if (!cachePaths.find(p => p.path === info.path)) {
+      setCachePaths([...cachePaths, info]);
+      await invoke('add_custom_cache_path', { path: info.path });
+    }
+  };
+
   const handleAddCustomFolder = async () => {
     try {
       const selected = await open({
@@ -111,28 +133,94 @@ export default function CacheFolderSelect() {
       });
 
       if (selected && typeof selected === 'string') {
-        const info = await invoke<CachePathInfo>('validate_cache_path', { path: selected });
-        // Add to store if not exists
-        if (!cachePaths.find(p => p.path === info.path)) {
-          setCachePaths([...cachePaths, info]);
-          // Persist to localStorage
-          const stored = getStoredCustomPaths();
-          if (!stored.includes(info.path)) {
-            saveCustomPaths([...stored, info.path]);
-          }
-        }
+        await addCustomPath(selected);
       }
     } catch (e) {
       console.error('Failed to add custom folder', e);
     }
   };
 
+  // A zipped or tarred copy of a cache; the backend extracts it once and scans the copy.
+  const handleAddArchive = async () => {
+    try {
+      const selected = await open({
+        directory: false,
+        multiple: false,
+        title: 'Select Cache Archive',
+        filters: [{ name: 'Cache archive', extensions: ['zip', 'tar', 'gz', 'tgz'] }],
+      });
+      if (selected && typeof selected === 'string') {
+        await addCustomPath(selected);
+      }
+    } catch (e) {
+      console.error('Failed to add cache archive', e);
+    }
+  };
+
+  // Another machine's disk: walk it for caches instead of guessing paths.
+  const handleSearchDrive = async () => {
+    const root = await open({ directory: true, multiple: false, title: 'Select Drive or Folder to Search' });
+    if (!root || typeof root !== 'string') return;
+    setDriveScan({ root, dirs_visited: 0, caches_found: 0, current: root });
+    const unlisten = await listen<DriveScanProgress>('drive-scan-progress', ({ payload }) => setDriveScan(payload));
+    try {
+      const result = await invoke<DriveScanResult>('scan_drive_for_caches', { root });
+      const known = new Set(cachePaths.map(p => p.path));
+      const found = result.caches.filter(c => !known.has(c.path));
+      setCachePaths([...cachePaths, ...found]);
+      for (const info of found) {
+        await invoke('add_custom_cache_path', { path: info.path });
+      }
+    } catch (err) {
+      console.error('Drive search failed', err);
+    } finally {
+      unlisten();
+      setDriveScan(null);
+    }
+  };
+
+  // A wiped cache: carve entries out of a raw disk image into a folder, then scan that folder.
+  const handleCarveImage = async () => {
+    const image = await open({ directory: false, multiple: false, title: 'Select Raw Disk Image' });
+    if (!image || typeof image !== 'string') return;
+    const outputDir = await open({ directory: true, multiple: false, title: 'Select Folder for Carved Entries' });
+    if (!outputDir || typeof outputDir !== 'string') return;
+    const consent = await requestWriteConsent(outputDir, 'Write the entries carved from the disk image into this folder.')
+      .catch(() => null);
+    if (!consent) return;
+    setCarving({ image, bytes_scanned: 0, total_bytes: 0, found: 0 });
+    const unlisten = await listen<CarveProgress>('carve-progress', ({ payload }) => setCarving(payload));
+    try {
+      const report = await invoke<CarveReport>('carve_disk_image', { image, outputDir, consent });
+      if (report.items.some(item => item.kind !== 'mp4')) {
+        await addCustomPath(report.output_dir);
+      }
+    } catch (err) {
+      console.error('Carving failed', err);
+    } finally {
+      unlisten();
+      setCarving(null);
+      await revokeWriteConsent(consent);
+    }
+  };
+
+  const handleAddPastedPath = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!pastedPath.trim()) return;
+    try {
+      await addCustomPath(pastedPath.trim());
+      setPastedPath('');
+    } catch (err) {
+      console.error('Failed to add pasted path', err);
+    }
+  };
+
   const handleRemoveCustomFolder = (e: React.MouseEvent, path: string) => {
     e.stopPropagation(); // Don't toggle selection
     removeFromCachePaths(path);
-    // Remove from localStorage
-    const stored = getStoredCustomPaths();
-    saveCustomPaths(stored.filter(p => p !== path));
+    invoke('remove_custom_cache_path', { path }).catch((err) =>
+      console.error('Failed to remove custom folder', err),
+    );
   };
 
   const isCustomPath = (path: string) => !autoDetectedPaths.has(path);
@@ -181,7 +269,7 @@ export default function CacheFolderSelect() {
     if (isMacOS()) {
       try {
         const files = await invoke<Array<{name: string; path: string; size: number}>>('list_cache_files', { dir: selectedPaths[0] });
-        const sparseFile = files.find(f => f.name.endsWith('_s') && f.size > 1000);
+        const sparseFile = files.find(f => f.name.toLowerCase().endsWith('_s') && f.size > 1000);
         const streamFile = files.find(f => f.name.endsWith('_0'));
         if (sparseFile) {
           const diagS = await invoke('diagnose_file_read', { path: sparseFile.path });
@@ -282,8 +370,22 @@ export default function CacheFolderSelect() {
                             </span>
                           </div>
                           <p className="text-xs font-mono text-text-muted mt-1 truncate max-w-[200px]" title={pathInfo.path}>
-                            {pathInfo.path}
+                            {pathInfo.display_path}
                           </p>
+                          {pathInfo.kind_label && (
+                            <p className="text-xs text-text-muted mt-1" title={pathInfo.resolved_path ?? undefined}>
+                              {pathInfo.kind_label}
+                            </p>
+                          )}
+                          {pathInfo.discord_build && (
+                            <p
+                              className="text-xs text-text-muted mt-1"
+                              title={Object.entries(pathInfo.discord_build.modules).map(([m, v]) => `${m} ${v}`).join("\n") || undefined}
+                            >
+                              Build: {pathInfo.discord_build.channel} {pathInfo.discord_build.host_version ?? "unknown version"}
+                              {pathInfo.discord_build.host_updates_skipped && " (host updates skipped)"}
+                            </p>
+                          )}
                         </div>
                       </div>
                       
@@ -332,6 +434,57 @@ export default function CacheFolderSelect() {
               </button>
             </div>
           )}
+          {!loading && (
+            <form onSubmit={handleAddPastedPath} className="mt-4 flex gap-2">
+              <input
+                type="text"
+                value={pastedPath}
+                onChange={(e) => setPastedPath(e.target.value)}
+                placeholder="Or paste a folder or .zip/.tar.gz path (~/... or %APPDATA%\...)"
+                className="flex-1 rounded-md border border-border-subtle bg-surface-3 px-3 py-1.5 text-sm font-mono text-text-primary placeholder:text-text-muted focus:border-phoenix focus:outline-none focus:ring-1 focus:ring-phoenix"
+              />
+              <button
+                type="submit"
+                disabled={!pastedPath.trim()}
+                className="rounded-md bg-surface-3 px-3 py-1.5 text-sm font-medium text-text-primary hover:bg-surface-2 disabled:opacity-50"
+              >
+                Add
+              </button>
+              <button
+                type="button"
+                onClick={handleSearchDrive}
+                disabled={driveScan !== null}
+                className="rounded-md bg-surface-3 px-3 py-1.5 text-sm font-medium text-text-primary hover:bg-surface-2 disabled:opacity-50"
+              >
+                Search a Drive…
+              </button>
+              <button
+                type="button"
+                onClick={handleAddArchive}
+                className="rounded-md bg-surface-3 px-3 py-1.5 text-sm font-medium text-text-primary hover:bg-surface-2"
+              >
+                Open Archive…
+              </button>
+              <button
+                type="button"
+                onClick={handleCarveImage}
+                disabled={carving !== null}
+                className="rounded-md bg-surface-3 px-3 py-1.5 text-sm font-medium text-text-primary hover:bg-surface-2 disabled:opacity-50"
+              >
+                Carve Disk Image…
+              </button>
+            </form>
+          )}
+          {driveScan && (
+            <p className="mt-2 text-xs text-text-muted truncate" title={driveScan.current}>
+              Searching {driveScan.root}: {driveScan.dirs_visited} folders, {driveScan.caches_found} caches found
+            </p>
+          )}
+          {carving && (
+            <p className="mt-2 text-xs text-text-muted truncate" title={carving.image}>
+              Carving {carving.image}: {formatBytes(carving.bytes_scanned)} of {formatBytes(carving.total_bytes)}, {carving.found} found
+            </p>
+          )}
         </div>
       </div>
 
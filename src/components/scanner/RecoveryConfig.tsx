@@ -1,5 +1,6 @@
-import { useEffect } from 'react';
+import { useEffect, useState } from 'react';
 import { useAppStore } from '@/store';
+import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
 import { homeDir, join } from '@tauri-apps/api/path';
 import { formatBytes } from '@/lib/utils';
@@ -13,7 +14,7 @@ import {
   ToggleLeft,
   ToggleRight
 } from 'lucide-react';
-import type { RecoveryOptions } from '@/types';
+import type { ExportTargetInfo, RecoveryOptions } from '@/types';
 
 export default function RecoveryConfig() {
   const { 
@@ -23,6 +24,12 @@ export default function RecoveryConfig() {
     setScannerStep, 
     setIsRecovering 
   } = useAppStore();
+  const [exportTargets, setExportTargets] = useState<ExportTargetInfo[]>([]);
+  useEffect(() => {
+    invoke<ExportTargetInfo[]>('list_export_targets')
+      .then(setExportTargets)
+      .catch((e) => console.error('Failed to list export targets', e));
+  }, []);
   useEffect(() => {
     const setDefaultOutput = async () => {
       if (!recoveryOptions.outputFolder) {
@@ -61,7 +68,7 @@ export default function RecoveryConfig() {
   };
 
   const toggleOption = (key: keyof RecoveryOptions) => {
-    if (key === 'outputFolder') return;
+    if (key === 'outputFolder' || key === 'exportTarget' || key === 'zipPassphrase') return;
     setRecoveryOptions({
       ...recoveryOptions,
       [key]: !recoveryOptions[key]
@@ -110,6 +117,50 @@ export default function RecoveryConfig() {
               </div>
             </div>
             
+            <div className="space-y-2">
+              <label className="text-sm text-text-muted">Also send each file to:</label>
+              <select
+                value={exportTargets.some(t => t.id === recoveryOptions.exportTarget) ? recoveryOptions.exportTarget : ''}
+                onChange={(e) => setRecoveryOptions({ exportTarget: e.target.value })}
+                className="w-full bg-surface-3/50 border border-surface-3 rounded-lg px-3 py-2 text-sm text-text-primary"
+              >
+                <option value="">No device or share</option>
+                {exportTargets.map(target => (
+                  <option key={target.id} value={target.id}>{target.label}</option>
+                ))}
+              </select>
+              <input
+                type="text"
+                value={exportTargets.some(t => t.id === recoveryOptions.exportTarget) ? '' : recoveryOptions.exportTarget}
+                onChange={(e) => setRecoveryOptions({ exportTarget: e.target.value.trim() })}
+                placeholder={'or a NAS: smb:\\\\nas\\share or sftp:user@nas/folder'}
+                className="w-full bg-surface-3/50 border border-surface-3 rounded-lg px-3 py-2 text-sm font-mono text-text-primary"
+              />
+              {recoveryOptions.exportTarget && (
+                <OptionToggle
+                  label="Keep a copy in the output folder"
+                  description="Off: each file is removed locally once it arrived; subtitles and concatenation are skipped"
+                  checked={recoveryOptions.keepLocalCopy}
+                  onChange={() => toggleOption('keepLocalCopy')}
+                />
+              )}
+              <OptionToggle
+                label="Also pack into a ZIP"
+                description="Bundle the recovered files and mapping.csv into CachePhoenix_recovered.zip in the output folder"
+                checked={recoveryOptions.packZip}
+                onChange={() => toggleOption('packZip')}
+              />
+              {recoveryOptions.packZip && (
+                <input
+                  type="password"
+                  value={recoveryOptions.zipPassphrase}
+                  onChange={(e) => setRecoveryOptions({ zipPassphrase: e.target.value })}
+                  placeholder="Passphrase (optional, AES-256; opens in 7-Zip, WinZip or Keka)"
+                  className="w-full bg-surface-3/50 border border-surface-3 rounded-lg px-3 py-2 text-sm text-text-primary"
+                />
+              )}
+            </div>
+
             <div className="pt-4 border-t border-surface-2">
                <div className="flex items-center justify-between text-sm text-text-muted">
                  <span>Free Space Required:</span>
@@ -188,6 +239,55 @@ export default function RecoveryConfig() {
               onChange={() => toggleOption('generateThumbnails')}
             />
 
+            <OptionToggle
+              label="Extract subtitles"
+              description="Save embedded caption tracks (mov_text, WebVTT) as .srt/.vtt text files next to each video"
+              checked={recoveryOptions.extractTranscripts}
+              onChange={() => toggleOption('extractTranscripts')}
+            />
+
+            <OptionToggle
+              label="Decode compressed responses"
+              description="Decompress bodies the server sent gzip, Brotli or zstd encoded; turn off to keep the bytes exactly as cached"
+              checked={recoveryOptions.decodeContentEncoding}
+              onChange={() => toggleOption('decodeContentEncoding')}
+            />
+
+            <OptionToggle
+              label="Use original file names"
+              description="Name files after the server's Content-Disposition or the URL (e.g. holiday.mp4) instead of Video 1, Video 2, ..."
+              checked={recoveryOptions.useOriginalNames}
+              onChange={() => toggleOption('useOriginalNames')}
+            />
+
+            <OptionToggle
+              label="Save audio of damaged videos"
+              description="When a reconstructed video is missing picture data, also save its sound as an .m4a"
+              checked={recoveryOptions.salvageAudio}
+              onChange={() => toggleOption('salvageAudio')}
+            />
+
+            <OptionToggle
+              label="Fill missing parts of images with grey"
+              description="When a JPEG's end never reached the cache, write the missing area as grey so every viewer shows the full picture size"
+              checked={recoveryOptions.padJpegGray}
+              onChange={() => toggleOption('padJpegGray')}
+            />
+
+            <OptionToggle
+              label="Check that videos and audio play"
+              description="Probe every recovered video and audio file with ffprobe and list the ones with no playable stream"
+              checked={recoveryOptions.verifyPlayback}
+              onChange={() => toggleOption('verifyPlayback')}
+            />
+
+            <OptionToggle
+              label="Convert HLS streams to MP4"
+              description="Remux reassembled .ts segment streams into .mp4 without re-encoding; off keeps the raw .ts"
+              checked={recoveryOptions.remuxHlsToMp4}
+              onChange={() => toggleOption('remuxHlsToMp4')}
+            />
+
             <OptionToggle
               label="Concatenate selected videos"
               description="Merge all selected videos into a single file in chronological order"
@@ -1,13 +1,15 @@
-import { useEffect, useRef } from 'react';
+import { useEffect, useRef, useState } from 'react';
 import { useAppStore } from '@/store';
-import { recoverResources } from '@/lib/recovery';
+import { cancelRecovery, recoverResources } from '@/lib/recovery';
 import { invoke } from '@tauri-apps/api/core';
 import { 
   CheckCircle, 
   AlertCircle, 
+  AlertTriangle,
   FolderOpen, 
   RefreshCw,
-  Terminal
+  Terminal,
+  XCircle
 } from 'lucide-react';
 
 export default function RecoveryProgress() {
@@ -25,6 +27,7 @@ export default function RecoveryProgress() {
   } = useAppStore();
 
   const logContainerRef = useRef<HTMLDivElement>(null);
+  const [cancelling, setCancelling] = useState(false);
 
   useEffect(() => {
     if (logContainerRef.current) {
@@ -76,6 +79,8 @@ export default function RecoveryProgress() {
 
   const isComplete = recoveryProgress?.phase === 'complete';
   const hasErrors = (recoveryProgress?.errors.length || 0) > 0;
+  const hasPartial = (recoveryProgress?.partial.length || 0) > 0;
+  const hasUnplayable = (recoveryProgress?.unplayable.length || 0) > 0;
   const percent = recoveryProgress?.total 
     ? Math.round((recoveryProgress.current / recoveryProgress.total) * 100) 
     : 0;
@@ -92,6 +97,8 @@ export default function RecoveryProgress() {
             <p className="text-text-muted">
               Successfully recovered {recoveryProgress?.current} files.
               {hasErrors && <span className="text-error ml-2">({recoveryProgress?.errors.length} errors)</span>}
+              {hasPartial && <span className="text-warning ml-2">({recoveryProgress?.partial.length} incomplete)</span>}
+              {hasUnplayable && <span className="text-warning ml-2">({recoveryProgress?.unplayable.length} won't play)</span>}
             </p>
           </div>
         ) : (
@@ -117,6 +124,14 @@ export default function RecoveryProgress() {
               <p className="text-sm font-mono text-text-muted truncate h-6">
                 {recoveryProgress?.currentFile || 'Preparing...'}
               </p>
+              <button
+                onClick={() => { setCancelling(true); cancelRecovery(); }}
+                disabled={cancelling}
+                className="mt-2 inline-flex items-center gap-1.5 rounded-md border border-surface-3 px-3 py-1.5 text-xs text-text-muted hover:text-error hover:border-error/50 transition-colors disabled:opacity-50"
+              >
+                <XCircle className="w-3.5 h-3.5" />
+                {cancelling ? 'Cancelling...' : 'Cancel'}
+              </button>
             </div>
           </div>
         )}
@@ -159,6 +174,38 @@ export default function RecoveryProgress() {
             </div>
           </div>
         )}
+
+        {hasPartial && (
+          <div className="bg-surface-1/50 border border-warning/20 rounded-xl overflow-hidden">
+            <div className="bg-warning/20 px-4 py-3 flex items-center space-x-2 text-warning font-medium">
+              <AlertTriangle className="w-5 h-5" />
+              <span>{recoveryProgress?.partial.length} Partially Recovered</span>
+            </div>
+            <div className="max-h-32 overflow-y-auto p-4 space-y-1 bg-black/20">
+              {recoveryProgress?.partial.map((name, i) => (
+                <div key={i} className="text-xs text-warning/80 font-mono border-l-2 border-warning/20 pl-3">
+                  {name}
+                </div>
+              ))}
+            </div>
+          </div>
+        )}
+
+        {hasUnplayable && (
+          <div className="bg-surface-1/50 border border-warning/20 rounded-xl overflow-hidden">
+            <div className="bg-warning/20 px-4 py-3 flex items-center space-x-2 text-warning font-medium">
+              <AlertTriangle className="w-5 h-5" />
+              <span>{recoveryProgress?.unplayable.length} Not Playable</span>
+            </div>
+            <div className="max-h-32 overflow-y-auto p-4 space-y-1 bg-black/20">
+              {recoveryProgress?.unplayable.map((name, i) => (
+                <div key={i} className="text-xs text-warning/80 font-mono border-l-2 border-warning/20 pl-3">
+                  {name}
+                </div>
+              ))}
+            </div>
+          </div>
+        )}
       </div>
 
       <div className="py-3 flex justify-center space-x-4 shrink-0">
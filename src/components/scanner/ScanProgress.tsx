@@ -1,12 +1,14 @@
 import { useEffect } from 'react';
 import { useAppStore } from '@/store';
 import { startScan, cancelCurrentScan, isCurrentlyScanning } from '@/lib/scanService';
-import { XCircle } from 'lucide-react';
+import { formatBytes } from '@/lib/utils';
+import { Flame, XCircle } from 'lucide-react';
 
 export default function ScanProgress() {
   const { 
     selectedPaths, 
     scanProgress, 
+    hotItems,
     setIsScanning, 
     setScannerStep 
   } = useAppStore();
@@ -79,6 +81,25 @@ export default function ScanProgress() {
         </p>
       </div>
 
+      {hotItems.length > 0 && (
+        <div className="w-full max-w-xl rounded-lg border border-phoenix/30 bg-surface-2 p-4 space-y-2">
+          <div className="flex items-center text-sm font-medium text-white">
+            <Flame className="w-4 h-4 mr-2 text-phoenix" />
+            {hotItems.length.toLocaleString()} likely complete video{hotItems.length === 1 ? '' : 's'} found so far
+          </div>
+          <ul className="max-h-40 overflow-y-auto space-y-1 text-xs font-mono text-text-muted">
+            {hotItems.map((item, i) => (
+              <li key={`${i}-${item.url}`} className="flex justify-between gap-4">
+                <span className="truncate" title={item.url}>
+                  {item.original_filename || item.url.split(/[?#]/)[0].split('/').pop() || item.url}
+                </span>
+                <span className="shrink-0">{formatBytes(item.body_size)}</span>
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
+
       <button
         onClick={handleCancel}
         className="mt-8 flex items-center px-6 py-2 rounded-full border border-error/30 text-error hover:bg-error/10 hover:border-error transition-all duration-200 group"
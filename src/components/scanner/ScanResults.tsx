@@ -1,7 +1,7 @@
-import { useMemo, useCallback, useRef, memo, useState, useLayoutEffect } from 'react';
+import { useMemo, useCallback, useRef, memo, useState, useLayoutEffect, useEffect } from 'react';
 import { useVirtualizer } from '@tanstack/react-virtual';
 import { useAppStore } from '@/store';
-import { formatBytes, formatDuration } from '@/lib/utils';
+import { formatBytes, formatDuration, generateId, isVoiceMessage } from '@/lib/utils';
 
 import {
   Search,
@@ -20,9 +20,23 @@ import {
   Globe,
   Link,
   Bug,
+  Mic,
+  Layers,
 } from 'lucide-react';
-import type { CacheResource, FilterCategory, MediaCategory } from '@/types';
+import type {
+  CacheResource,
+  CatalogEntry,
+  EntryGrouping,
+  FilterCategory,
+  GroupingStrategy,
+  GroupNode,
+  MediaCategory,
+  ReconstructionPlan,
+} from '@/types';
 import { cancelCurrentScan } from '@/lib/scanService';
+import { requestWriteConsent } from '@/lib/permissions';
+import { planReconstruction } from '@/lib/recovery';
+import { closeScanSession, groupEntries, openScanSession, sessionEntries } from '@/lib/scanner';
 import { save } from '@tauri-apps/plugin-dialog';
 import { invoke } from '@tauri-apps/api/core';
 
@@ -63,6 +77,7 @@ const CATEGORY_TO_MEDIA: Record<FilterCategory, MediaCategory | null> = {
   images: 'image',
   videos: 'video',
   audio: 'audio',
+  voice: 'audio',
   other: 'other',
 };
 
@@ -71,11 +86,85 @@ const CATEGORIES: { id: FilterCategory; label: string; icon: React.ElementType }
   { id: 'images', label: 'Images', icon: ImageIcon },
   { id: 'videos', label: 'Videos', icon: Film },
   { id: 'audio', label: 'Audio', icon: Music },
+  { id: 'voice', label: 'Voice', icon: Mic },
   { id: 'other', label: 'Other', icon: File },
 ];
 
 
 
+const LAYOUT_LABELS: Record<ReconstructionPlan['layout'], string> = {
+  fragmented: 'Fragmented MP4 (fragments reordered)',
+  moov_at_end: 'Streaming layout (moov after mdat)',
+  moov_at_front: 'moov at front (chunks concatenated)',
+  no_moov: 'No moov found (chunks concatenated)',
+};
+
+/** Dry run of a chunked MP4 reconstruction: what would be written, without writing it. */
+function ReconstructionPlanSection({ resource }: { resource: CacheResource }) {
+  const [plan, setPlan] = useState<ReconstructionPlan | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [loading, setLoading] = useState(false);
+
+  const runPlan = useCallback(async () => {
+    setLoading(true);
+    setError(null);
+    try {
+      setPlan(await planReconstruction(resource.files[0].path, resource.files.slice(1).map((f) => f.path)));
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setLoading(false);
+    }
+  }, [resource]);
+
+  const skipped = plan ? plan.chunks.filter((c) => !c.written) : [];
+  const missing = plan ? plan.gaps.reduce((sum, g) => sum + g.missing_chunks, 0) : 0;
+
+  return (
+    <div className="space-y-2">
+      <div className="flex items-center justify-between">
+        <span className="text-text-muted block text-xs uppercase tracking-wider">Reconstruction Plan</span>
+        <button
+          onClick={runPlan}
+          disabled={loading}
+          className="px-3 py-1 rounded-lg text-xs font-medium border border-surface-3 text-text-secondary hover:text-text-primary hover:border-text-muted disabled:opacity-50"
+        >
+          {loading ? 'Planning…' : plan ? 'Plan Again' : 'Plan Reconstruction'}
+        </button>
+      </div>
+      {error && <div className="text-xs text-red-400 break-all">{error}</div>}
+      {plan && (
+        <div className="rounded-lg border border-surface-2 bg-black/20 p-2 space-y-1 text-xs">
+          <div>{LAYOUT_LABELS[plan.layout]}</div>
+          <div className="font-mono">
+            {formatBytes(plan.expected_size)} expected
+            {plan.truncated ? ' (stops at the first gap)' : ''}
+          </div>
+          {plan.gaps.length > 0 && (
+            <div className="text-amber-400">
+              {missing} missing chunk{missing === 1 ? '' : 's'} in {plan.gaps.length} gap{plan.gaps.length === 1 ? '' : 's'}
+            </div>
+          )}
+          {skipped.length > 0 && (
+            <div className="text-text-muted">
+              {skipped.length} chunk{skipped.length === 1 ? '' : 's'} left out
+              ({skipped.map((c) => c.role.replace('_', ' ')).filter((r, i, all) => all.indexOf(r) === i).join(', ')})
+            </div>
+          )}
+          {plan.layout_check && (plan.layout_check.moved > 0 || plan.layout_check.contradicted > 0) && (
+            <div className="text-text-muted">
+              Sample table check: {plan.layout_check.moved} re-placed, {plan.layout_check.contradicted} match nowhere
+            </div>
+          )}
+          {plan.notes.map((note, i) => (
+            <div key={i} className="text-text-muted">{note}</div>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
+
 interface ResultCardProps {
   resource: CacheResource;
   isPreview: boolean;
@@ -217,6 +306,122 @@ const ResultCard = memo(function ResultCard({
 
 
 
+const GROUPINGS: { id: GroupingStrategy; label: string }[] = [
+  { id: 'attachment', label: 'Attachment' },
+  { id: 'host', label: 'Host' },
+  { id: 'day', label: 'Day' },
+  { id: 'type', label: 'Type' },
+];
+
+/** One node of the grouped tree; a leaf loads its rows when first opened. */
+function GroupRow({ node, session, depth }: { node: GroupNode; session: string; depth: number }) {
+  const [open, setOpen] = useState(false);
+  const [rows, setRows] = useState<CatalogEntry[] | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const isLeaf = node.children.length === 0;
+
+  const toggle = useCallback(async () => {
+    setOpen((o) => !o);
+    if (isLeaf && rows === null) {
+      try {
+        setRows(await sessionEntries(session, node.entry_ids));
+      } catch (err) {
+        setError(String(err));
+      }
+    }
+  }, [isLeaf, rows, session, node.entry_ids]);
+
+  return (
+    <div>
+      <button
+        onClick={toggle}
+        className="w-full flex items-center space-x-2 py-1.5 pr-2 rounded hover:bg-surface-2 text-left text-sm"
+        style={{ paddingLeft: `${depth * 1.25 + 0.5}rem` }}
+      >
+        <ChevronRight className={`w-4 h-4 shrink-0 text-text-muted transition-transform ${open ? 'rotate-90' : ''}`} />
+        <span className="flex-1 truncate" title={node.label}>{node.label}</span>
+        <span className="text-xs text-text-muted font-mono">{node.count}</span>
+        <span className="text-xs text-text-muted font-mono w-20 text-right">{formatBytes(node.total_size)}</span>
+        <span className="text-xs text-text-muted w-40 text-right">
+          {node.latest != null ? formatDateTime(node.latest) : ''}
+        </span>
+      </button>
+      {open && node.children.map((child) => (
+        <GroupRow key={child.key} node={child} session={session} depth={depth + 1} />
+      ))}
+      {open && error && <div className="text-xs text-red-400 break-all pl-10">{error}</div>}
+      {open && rows && rows.map((row) => (
+        <div
+          key={row.id}
+          className="flex items-center space-x-2 py-0.5 pr-2 text-xs text-text-muted"
+          style={{ paddingLeft: `${(depth + 1) * 1.25 + 1.5}rem` }}
+        >
+          <span className="flex-1 truncate font-mono" title={row.url}>{row.url}</span>
+          <span className="font-mono w-20 text-right">{formatBytes(row.size)}</span>
+        </div>
+      ))}
+    </div>
+  );
+}
+
+/**
+ * The scanned folders as a tree (by attachment, host, day or type). The tree
+ * is built by the backend from its own catalog of the folders, so it works
+ * on every entry there, not only the media the grid shows.
+ */
+function GroupedResults({ dirs, strategy }: { dirs: string[]; strategy: GroupingStrategy }) {
+  const [session, setSession] = useState<string | null>(null);
+  const [grouping, setGrouping] = useState<EntryGrouping | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    const id = generateId();
+    let cancelled = false;
+    openScanSession(id, dirs)
+      .then((summary) => {
+        if (cancelled) return;
+        setSession(id);
+        if (summary.errors.length > 0) setError(summary.errors.join('\n'));
+      })
+      .catch((err) => !cancelled && setError(String(err)));
+    return () => {
+      cancelled = true;
+      closeScanSession(id).catch(() => {});
+    };
+  }, [dirs]);
+
+  useEffect(() => {
+    if (!session) return;
+    let cancelled = false;
+    groupEntries(session, strategy)
+      .then((g) => !cancelled && setGrouping(g))
+      .catch((err) => !cancelled && setError(String(err)));
+    return () => {
+      cancelled = true;
+    };
+  }, [session, strategy]);
+
+  return (
+    <div className="py-4 space-y-2">
+      {error && <div className="text-xs text-red-400 whitespace-pre-wrap break-all">{error}</div>}
+      {!grouping ? (
+        <div className="text-sm text-text-muted">Grouping entries…</div>
+      ) : (
+        <>
+          <div className="text-sm text-text-muted">
+            {grouping.count} entries, {formatBytes(grouping.total_size)}
+          </div>
+          <div className="rounded-xl border border-surface-2 bg-surface-1 p-2">
+            {grouping.groups.map((node) => (
+              <GroupRow key={`${grouping.strategy}:${node.key}`} node={node} session={grouping.session} depth={0} />
+            ))}
+          </div>
+        </>
+      )}
+    </div>
+  );
+}
+
 function useColumnCount(containerRef: React.RefObject<HTMLDivElement | null>) {
   const [columns, setColumns] = useState(4);
 
@@ -259,10 +464,16 @@ export default function ScanResults() {
   const sortOrder = useAppStore((s) => s.sortOrder);
   const setSortOrder = useAppStore((s) => s.setSortOrder);
   const resetScanner = useAppStore((s) => s.resetScanner);
+  const selectedPaths = useAppStore((s) => s.selectedPaths);
+  const [groupBy, setGroupBy] = useState<GroupingStrategy | null>(null);
 
   const scanDebugData = useAppStore((s) => s.scanDebugData);
+  const persistUrlKeys = useAppStore((s) => s.settings.persistUrlKeys);
 
   const handleExportDebug = useCallback(async () => {
+    // With URL persistence off, keep sizes/types/timings but drop anything
+    // that reads like browsing history (URLs, headers, Discord IDs).
+    const redact = !persistUrlKeys;
     const debugPayload = {
       exportedAt: new Date().toISOString(),
       resourceCount: resources.length,
@@ -274,20 +485,21 @@ export default function ScanResults() {
         totalSize: r.totalSize,
         fileCount: r.files.length,
         files: r.files,
-        indexUrl: r.indexUrl,
+        indexUrl: redact ? undefined : r.indexUrl,
         indexContentType: r.indexContentType,
         indexHttpStatus: r.indexHttpStatus,
-        indexHeaders: r.indexHeaders,
+        indexHeaders: redact ? undefined : r.indexHeaders,
         indexIsSparse: r.indexIsSparse,
         indexChildCount: r.indexChildCount,
+        indexMissingChunks: r.indexMissingChunks,
         indexRequestTime: r.indexRequestTime,
         indexResponseTime: r.indexResponseTime,
         indexOriginalFilename: r.indexOriginalFilename,
-        discordInfo: r.discordInfo,
+        discordInfo: redact ? undefined : r.discordInfo,
         videoInfo: r.videoInfo,
         modifiedAt: r.modifiedAt,
       })),
-      scanDebugData,
+      scanDebugData: redact ? null : scanDebugData,
     };
 
     try {
@@ -296,12 +508,13 @@ export default function ScanResults() {
         filters: [{ name: 'JSON', extensions: ['json'] }],
       });
       if (filePath) {
-        await invoke('write_file_bytes', { path: filePath, data: Array.from(new TextEncoder().encode(JSON.stringify(debugPayload, null, 2))) });
+        const consent = await requestWriteConsent(filePath, 'Save scan debug data to this file.');
+        await invoke('write_file_bytes', { path: filePath, data: Array.from(new TextEncoder().encode(JSON.stringify(debugPayload, null, 2))), consent });
       }
     } catch (err) {
       console.error('Failed to export debug data:', err);
     }
-  }, [resources, scanDebugData]);
+  }, [resources, scanDebugData, persistUrlKeys]);
   const scrollContainerRef = useRef<HTMLDivElement>(null);
   const columns = useColumnCount(scrollContainerRef);
 
@@ -325,7 +538,8 @@ export default function ScanResults() {
     const lowerSearch = searchQuery.toLowerCase();
 
     const filtered = resources.filter(r => {
-      const matchesCategory = filterCategory === 'all' || r.mediaCategory === mediaMatch;
+      const matchesCategory = filterCategory === 'all' ||
+        (filterCategory === 'voice' ? isVoiceMessage(r) : r.mediaCategory === mediaMatch);
       const matchesSearch = !searchQuery || r.displayName.toLowerCase().includes(lowerSearch);
       return matchesCategory && matchesSearch;
     });
@@ -453,6 +667,21 @@ export default function ScanResults() {
               <span>{sortOrder === 'newest' ? 'Newest' : 'Oldest'}</span>
             </button>
 
+            <div className="flex items-center space-x-1.5 px-3 py-2 rounded-lg bg-surface-2 border border-surface-3 text-sm text-text-muted">
+              <Layers className="w-3.5 h-3.5" />
+              <select
+                value={groupBy ?? ''}
+                onChange={(e) => setGroupBy((e.target.value || null) as GroupingStrategy | null)}
+                className="bg-transparent font-medium focus:outline-none"
+                title="Group all cache entries of the scanned folders"
+              >
+                <option value="">No grouping</option>
+                {GROUPINGS.map((g) => (
+                  <option key={g.id} value={g.id}>By {g.label.toLowerCase()}</option>
+                ))}
+              </select>
+            </div>
+
             <div className="relative flex-1 max-w-md">
               <Search className="absolute left-3 top-1/2 transform -translate-y-1/2 text-text-muted w-4 h-4" />
               <input
@@ -472,7 +701,9 @@ export default function ScanResults() {
         ref={scrollContainerRef}
         className="flex-1 min-h-0 overflow-y-auto"
       >
-        {filteredAndSortedResources.length === 0 ? (
+        {groupBy ? (
+          <GroupedResults dirs={selectedPaths} strategy={groupBy} />
+        ) : filteredAndSortedResources.length === 0 ? (
           <div className="flex flex-col items-center justify-center py-20 text-text-muted space-y-4">
             <Search className="w-16 h-16 opacity-20" />
             <p className="text-lg font-medium">No results found matching your criteria</p>
@@ -580,6 +811,32 @@ export default function ScanResults() {
                   <span className="font-mono">{previewResource.files.length}</span>
                 </div>
                 )}
+                {previewResource.corruptKeyFiles && previewResource.corruptKeyFiles.length > 0 && (
+                  <div className="space-y-1 col-span-2">
+                    <span className="text-text-muted block text-xs uppercase tracking-wider">Cache Header</span>
+                    <span className="text-xs text-amber-400">
+                      Key hash mismatch in {previewResource.corruptKeyFiles.length} file{previewResource.corruptKeyFiles.length === 1 ? "" : "s"} — header is damaged, output may contain stray bytes
+                    </span>
+                  </div>
+                )}
+                {previewResource.crcMismatchFiles && previewResource.crcMismatchFiles.length > 0 && (
+                  <div className="space-y-1 col-span-2">
+                    <span className="text-text-muted block text-xs uppercase tracking-wider">Checksum</span>
+                    <span className="text-xs text-amber-400">
+                      CRC32 mismatch in {previewResource.crcMismatchFiles.length} file{previewResource.crcMismatchFiles.length === 1 ? "" : "s"} — part of the body was damaged after caching
+                    </span>
+                  </div>
+                )}
+                {previewResource.keyVerified !== undefined && (
+                  <div className="space-y-1 col-span-2">
+                    <span className="text-text-muted block text-xs uppercase tracking-wider">Key SHA-256</span>
+                    {previewResource.keyVerified ? (
+                      <span className="text-xs text-success">Verified — key and end-of-entry record match</span>
+                    ) : (
+                      <span className="text-xs text-amber-400">Mismatch — entry may be partially overwritten</span>
+                    )}
+                  </div>
+                )}
                 {previewResource.modifiedAt ? (
                   <div className="space-y-1 col-span-2">
                     <span className="text-text-muted block text-xs uppercase tracking-wider">Modified</span>
@@ -637,6 +894,7 @@ export default function ScanResults() {
                         <span className="text-text-muted block text-xs uppercase tracking-wider">Storage</span>
                         <span className="font-mono text-xs">
                           Sparse (Range-Request) — {previewResource.indexChildCount ?? 0} chunks
+                          {previewResource.indexMissingChunks ? `, ${previewResource.indexMissingChunks} evicted` : ""}
                         </span>
                       </div>
                     )}
@@ -680,6 +938,13 @@ export default function ScanResults() {
                         <span className="font-mono text-xs break-all">{previewResource.discordInfo.filename}</span>
                       </div>
                     )}
+                    {previewResource.savedOriginal && (
+                      <div className="space-y-1">
+                        <span className="text-text-muted block text-xs uppercase tracking-wider">Full Copy On Disk</span>
+                        <span className="font-mono text-xs break-all">{previewResource.savedOriginal.path}</span>
+                        <span className="text-xs text-text-muted block">{formatBytes(previewResource.savedOriginal.size)}</span>
+                      </div>
+                    )}
                     {previewResource.discordInfo.uploadedAt != null && (
                       <div className="space-y-1">
                         <span className="text-text-muted block text-xs uppercase tracking-wider">
@@ -726,10 +991,28 @@ export default function ScanResults() {
                         <span className="font-mono text-xs">Media Proxy (resized/transcoded)</span>
                       </div>
                     )}
+                    {previewResource.discordInfo.variant && (
+                      <div className="space-y-1">
+                        <span className="text-text-muted block text-xs uppercase tracking-wider">Variant</span>
+                        <span className="font-mono text-xs">
+                          {[
+                            previewResource.discordInfo.variant.width != null && previewResource.discordInfo.variant.height != null
+                              ? `${previewResource.discordInfo.variant.width}×${previewResource.discordInfo.variant.height}`
+                              : null,
+                            previewResource.discordInfo.variant.size != null ? `${previewResource.discordInfo.variant.size}px` : null,
+                            previewResource.discordInfo.variant.format,
+                            previewResource.discordInfo.variant.quality,
+                          ].filter(Boolean).join(" · ")}
+                        </span>
+                      </div>
+                    )}
                   </div>
                 </div>
               )}
 
+              {previewResource.resourceType === 'mp4_chunked' && !previewResource.indexUrl && previewResource.files.length > 1 && (
+                <ReconstructionPlanSection key={previewResource.id} resource={previewResource} />
+              )}
 
               {previewResource.files.length > 0 && (
                 <div className="space-y-2">
@@ -0,0 +1,104 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { ShieldCheck } from "lucide-react";
+import { useAppStore } from "@/store";
+import type { AuditReport } from "@/types";
+import { formatBytes } from "@/lib/utils";
+
+const COUNTS: { key: "healthy" | "truncated" | "corrupt" | "locked" | "unreadable"; label: string }[] = [
+  { key: "healthy", label: "Healthy" },
+  { key: "truncated", label: "Truncated" },
+  { key: "corrupt", label: "Corrupt" },
+  { key: "locked", label: "Locked" },
+  { key: "unreadable", label: "Unreadable" },
+];
+
+/** Settings section that runs audit_cache over a detected cache folder. */
+export function CacheAuditSection() {
+  const { cachePaths } = useAppStore();
+  const folders = cachePaths.filter((p) => p.exists);
+  const [dir, setDir] = useState("");
+  const [report, setReport] = useState<AuditReport | null>(null);
+  const [running, setRunning] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const selected = dir || folders[0]?.path || "";
+
+  const run = async (details: boolean) => {
+    setRunning(true);
+    try {
+      setReport(await invoke<AuditReport>("audit_cache", { dir: selected, details }));
+      setError(null);
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setRunning(false);
+    }
+  };
+
+  const inputClass =
+    "rounded-md border border-border-subtle bg-surface-3 px-3 py-1.5 text-sm text-text-primary placeholder:text-text-muted focus:border-phoenix focus:outline-none focus:ring-1 focus:ring-phoenix";
+  const problems = report?.files.filter((f) => f.status !== "healthy" && f.status !== "skipped") ?? [];
+
+  return (
+    <section className="rounded-xl border border-border-subtle bg-surface-2 p-6 shadow-sm">
+      <div className="flex items-center gap-3 mb-6 pb-4 border-b border-border-subtle">
+        <div className="rounded-lg bg-surface-3 p-2">
+          <ShieldCheck className="h-5 w-5 text-text-primary" />
+        </div>
+        <h2 className="text-lg font-semibold text-text-primary">Cache Integrity Audit</h2>
+      </div>
+
+      <div className="space-y-4">
+        <p className="text-xs text-text-muted">
+          Check the header, end-of-file records and checksums of every file in a cache folder — a quick way to see how much of a damaged disk is still intact.
+        </p>
+
+        <div className="flex items-center gap-2">
+          <select className={`${inputClass} flex-1`} value={selected} onChange={(e) => setDir(e.target.value)}>
+            {folders.length === 0 && <option value="">No cache folders detected</option>}
+            {folders.map((p) => <option key={p.path} value={p.path}>{p.client_name} — {p.display_path}</option>)}
+          </select>
+          <button
+            onClick={() => run(false)}
+            disabled={!selected || running}
+            className="shrink-0 rounded-md bg-phoenix px-4 py-2 text-sm font-semibold text-white hover:bg-phoenix-dark transition-colors disabled:opacity-50"
+          >
+            {running ? "Auditing..." : "Audit"}
+          </button>
+        </div>
+
+        {report && (
+          <div className="space-y-2 text-sm text-text-primary">
+            <div className="flex flex-wrap gap-x-4 gap-y-1">
+              {COUNTS.map(({ key, label }) => (
+                <span key={key}>{label}: <span className="font-semibold">{report[key]}</span></span>
+              ))}
+            </div>
+            <p className="text-xs text-text-muted">
+              {report.total} files, {formatBytes(report.bytes_read)} read in {(report.elapsed_ms / 1000).toFixed(1)} s
+              {report.skipped > 0 && ` · ${report.skipped} blockfile files not checked`}
+            </p>
+            {report.files.length === 0 && report.total > report.healthy + report.skipped && (
+              <button onClick={() => run(true)} disabled={running} className="text-xs text-phoenix hover:underline">
+                Show affected files
+              </button>
+            )}
+            {problems.length > 0 && (
+              <ul className="max-h-48 overflow-y-auto space-y-1 text-xs">
+                {problems.map((f) => (
+                  <li key={f.path} className="rounded-md bg-surface-3 px-3 py-1.5">
+                    <span className="font-mono">{f.path.split(/[\\/]/).pop()}</span>
+                    <span className="text-amber-400"> {f.status}</span>
+                    {f.problems.length > 0 && <span className="text-text-muted"> — {f.problems.join("; ")}</span>}
+                  </li>
+                ))}
+              </ul>
+            )}
+          </div>
+        )}
+        {error && <p className="text-xs text-amber-400">{error}</p>}
+      </div>
+    </section>
+  );
+}
@@ -0,0 +1,117 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Filter, Plus, X } from "lucide-react";
+import type { IgnoreRule } from "@/types";
+import { formatBytes } from "@/lib/utils";
+
+const EMPTY_DRAFT = { source: "", pattern: "", smallerThanKb: "", olderThanDays: "", kind: "" };
+
+function describeRule(rule: IgnoreRule): string {
+  const parts: string[] = [];
+  if (rule.pattern) parts.push(`named ${rule.pattern}`);
+  if (rule.kind) parts.push(rule.kind.replace("_", " "));
+  if (rule.smaller_than != null) parts.push(`under ${formatBytes(rule.smaller_than)}`);
+  if (rule.larger_than != null) parts.push(`over ${formatBytes(rule.larger_than)}`);
+  if (rule.older_than_days != null) parts.push(`older than ${rule.older_than_days} days`);
+  return `Skip files ${parts.join(", ")}${rule.source ? ` in ${rule.source}` : ""}`;
+}
+
+/** Settings section for the scan ignore rules kept by the backend (ignore_rules.json). */
+export function IgnoreRulesSection() {
+  const [rules, setRules] = useState<IgnoreRule[]>([]);
+  const [draft, setDraft] = useState(EMPTY_DRAFT);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<IgnoreRule[]>("list_ignore_rules").then(setRules).catch((err) => setError(String(err)));
+  }, []);
+
+  const save = async (next: IgnoreRule[]) => {
+    try {
+      setRules(await invoke<IgnoreRule[]>("set_ignore_rules", { rules: next }));
+      setError(null);
+      return true;
+    } catch (err) {
+      setError(String(err));
+      return false;
+    }
+  };
+
+  const addRule = async () => {
+    const number = (s: string) => (s.trim() === "" ? null : Number(s));
+    const kb = number(draft.smallerThanKb);
+    const rule: IgnoreRule = {
+      source: draft.source.trim() || null,
+      pattern: draft.pattern.trim() || null,
+      smaller_than: kb == null ? null : Math.round(kb * 1024),
+      larger_than: null,
+      older_than_days: number(draft.olderThanDays),
+      kind: (draft.kind || null) as IgnoreRule["kind"],
+    };
+    if (await save([...rules, rule])) setDraft(EMPTY_DRAFT);
+  };
+
+  const inputClass =
+    "rounded-md border border-border-subtle bg-surface-3 px-3 py-1.5 text-sm text-text-primary placeholder:text-text-muted focus:border-phoenix focus:outline-none focus:ring-1 focus:ring-phoenix";
+
+  return (
+    <section className="rounded-xl border border-border-subtle bg-surface-2 p-6 shadow-sm">
+      <div className="flex items-center gap-3 mb-6 pb-4 border-b border-border-subtle">
+        <div className="rounded-lg bg-surface-3 p-2">
+          <Filter className="h-5 w-5 text-text-primary" />
+        </div>
+        <h2 className="text-lg font-semibold text-text-primary">Scan Ignore Rules</h2>
+      </div>
+
+      <div className="space-y-4">
+        <p className="text-xs text-text-muted">
+          Files matching every condition of a rule are skipped while cache folders are listed. Leave the folder empty to apply a rule to all sources.
+        </p>
+
+        {rules.length > 0 && (
+          <ul className="space-y-2">
+            {rules.map((rule, i) => (
+              <li key={i} className="flex items-center justify-between gap-3 rounded-md bg-surface-3 px-3 py-2 text-sm text-text-primary">
+                <span className="truncate">{describeRule(rule)}</span>
+                <button
+                  onClick={() => save(rules.filter((_, j) => j !== i))}
+                  className="shrink-0 text-text-muted hover:text-phoenix transition-colors"
+                  title="Remove rule"
+                >
+                  <X className="h-4 w-4" />
+                </button>
+              </li>
+            ))}
+          </ul>
+        )}
+
+        <div className="grid grid-cols-2 gap-2 sm:grid-cols-5">
+          <input className={inputClass} placeholder="Name, e.g. *_0" value={draft.pattern}
+            onChange={(e) => setDraft({ ...draft, pattern: e.target.value })} />
+          <input className={inputClass} placeholder="Under (KB)" type="number" min={0} value={draft.smallerThanKb}
+            onChange={(e) => setDraft({ ...draft, smallerThanKb: e.target.value })} />
+          <input className={inputClass} placeholder="Older than (days)" type="number" min={1} value={draft.olderThanDays}
+            onChange={(e) => setDraft({ ...draft, olderThanDays: e.target.value })} />
+          <select className={inputClass} value={draft.kind} onChange={(e) => setDraft({ ...draft, kind: e.target.value })}>
+            <option value="">Any file kind</option>
+            <option value="simple_cache">Simple Cache _0/_1</option>
+            <option value="sparse">Sparse _s</option>
+            <option value="blockfile">Blockfile f_</option>
+          </select>
+          <input className={inputClass} placeholder="Only in folder" value={draft.source}
+            onChange={(e) => setDraft({ ...draft, source: e.target.value })} />
+        </div>
+        <div className="flex items-center justify-between gap-3">
+          <span className="text-xs text-amber-400">{error}</span>
+          <button
+            onClick={addRule}
+            className="shrink-0 inline-flex items-center gap-1 rounded-md bg-phoenix px-4 py-2 text-sm font-semibold text-white hover:bg-phoenix-dark transition-colors"
+          >
+            <Plus className="h-4 w-4" />
+            Add rule
+          </button>
+        </div>
+      </div>
+    </section>
+  );
+}
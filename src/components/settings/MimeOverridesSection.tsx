@@ -0,0 +1,86 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Shapes, Plus, X } from "lucide-react";
+import type { MimeOverride } from "@/types";
+
+const CATEGORIES: MimeOverride["category"][] = ["video", "image", "audio", "document", "other"];
+
+/** Settings section for MIME type → category overrides kept by the backend (mime_overrides.json). */
+export function MimeOverridesSection() {
+  const [overrides, setOverrides] = useState<MimeOverride[]>([]);
+  const [draft, setDraft] = useState<MimeOverride>({ pattern: "", category: "video" });
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<MimeOverride[]>("list_mime_overrides").then(setOverrides).catch((err) => setError(String(err)));
+  }, []);
+
+  const save = async (next: MimeOverride[]) => {
+    try {
+      setOverrides(await invoke<MimeOverride[]>("set_mime_overrides", { overrides: next }));
+      setError(null);
+      return true;
+    } catch (err) {
+      setError(String(err));
+      return false;
+    }
+  };
+
+  const addOverride = async () => {
+    if (await save([...overrides, draft])) setDraft({ pattern: "", category: draft.category });
+  };
+
+  const inputClass =
+    "rounded-md border border-border-subtle bg-surface-3 px-3 py-1.5 text-sm text-text-primary placeholder:text-text-muted focus:border-phoenix focus:outline-none focus:ring-1 focus:ring-phoenix";
+
+  return (
+    <section className="rounded-xl border border-border-subtle bg-surface-2 p-6 shadow-sm">
+      <div className="flex items-center gap-3 mb-6 pb-4 border-b border-border-subtle">
+        <div className="rounded-lg bg-surface-3 p-2">
+          <Shapes className="h-5 w-5 text-text-primary" />
+        </div>
+        <h2 className="text-lg font-semibold text-text-primary">Content Type Categories</h2>
+      </div>
+
+      <div className="space-y-4">
+        <p className="text-xs text-text-muted">
+          Treat a Content-Type as a different category, e.g. a site that serves clips as text/plain. End a type with "/" to cover all of it ("model/").
+        </p>
+
+        {overrides.length > 0 && (
+          <ul className="space-y-2">
+            {overrides.map((o, i) => (
+              <li key={i} className="flex items-center justify-between gap-3 rounded-md bg-surface-3 px-3 py-2 text-sm text-text-primary">
+                <span className="truncate">{o.pattern} → {o.category}</span>
+                <button
+                  onClick={() => save(overrides.filter((_, j) => j !== i))}
+                  className="shrink-0 text-text-muted hover:text-phoenix transition-colors"
+                  title="Remove override"
+                >
+                  <X className="h-4 w-4" />
+                </button>
+              </li>
+            ))}
+          </ul>
+        )}
+
+        <div className="flex items-center gap-2">
+          <input className={`${inputClass} flex-1`} placeholder="MIME type, e.g. application/x-mpegurl" value={draft.pattern}
+            onChange={(e) => setDraft({ ...draft, pattern: e.target.value })} />
+          <select className={inputClass} value={draft.category}
+            onChange={(e) => setDraft({ ...draft, category: e.target.value as MimeOverride["category"] })}>
+            {CATEGORIES.map((c) => <option key={c} value={c}>{c}</option>)}
+          </select>
+          <button
+            onClick={addOverride}
+            className="shrink-0 inline-flex items-center gap-1 rounded-md bg-phoenix px-4 py-2 text-sm font-semibold text-white hover:bg-phoenix-dark transition-colors"
+          >
+            <Plus className="h-4 w-4" />
+            Add
+          </button>
+        </div>
+        {error && <p className="text-xs text-amber-400">{error}</p>}
+      </div>
+    </section>
+  );
+}
@@ -9,15 +9,24 @@ import {
   HelpCircle,
   Github,
   Cpu,
-  FileText
+  FileText,
+  Shield,
+  Trash2
 } from "lucide-react";
 import { open } from "@tauri-apps/plugin-dialog";
-import { cn } from "@/lib/utils";
+import { invoke } from "@tauri-apps/api/core";
+import { cn, formatBytes } from "@/lib/utils";
+import { forgetEverything } from "@/lib/privacy";
+import { IgnoreRulesSection } from "@/components/settings/IgnoreRulesSection";
+import { MimeOverridesSection } from "@/components/settings/MimeOverridesSection";
+import { CacheAuditSection } from "@/components/settings/CacheAuditSection";
 
 export function SettingsPage() {
   const settings = useAppStore((state) => state.settings);
   const setSettings = useAppStore((state) => state.setSettings);
+  const busy = useAppStore((state) => state.isScanning || state.isRecovering);
   const [appVersion, setAppVersion] = useState("");
+  const [forgetStatus, setForgetStatus] = useState<string | null>(null);
 
   useEffect(() => {
     getVersion().then(setAppVersion).catch(() => setAppVersion("unknown"));
@@ -39,6 +48,21 @@ export function SettingsPage() {
     }
   };
 
+  const handleForgetEverything = async () => {
+    setForgetStatus("Clearing…");
+    try {
+      const report = await forgetEverything();
+      setForgetStatus(
+        `Removed ${report.entries_removed} temp item${report.entries_removed === 1 ? "" : "s"} (${formatBytes(report.bytes_removed)}), thumbnails and saved paths` +
+          (report.entries_kept > 0 ? `; ${report.entries_kept} still in use were kept` : "") +
+          (report.errors.length > 0 ? ` — ${report.errors.length} could not be removed` : ""),
+      );
+    } catch (error) {
+      console.error("Failed to forget everything:", error);
+      setForgetStatus("Failed to clear stored data");
+    }
+  };
+
   return (
     <div className="p-8 max-w-3xl mx-auto space-y-8 animate-fade-in pb-20">
       <div className="space-y-2">
@@ -76,6 +100,30 @@ export function SettingsPage() {
               />
             </div>
 
+            <div className="flex flex-col gap-3 sm:flex-row sm:items-center sm:justify-between">
+              <div className="space-y-0.5">
+                <label className="text-sm font-medium text-text-primary flex items-center gap-2">
+                  <FileText className="h-4 w-4 text-text-muted" />
+                  Max Open Files
+                </label>
+                <p className="text-xs text-text-muted">
+                  Cache files read at the same time. Lower this if scans fail with "too many open files" (1-256)
+                </p>
+              </div>
+              <input
+                type="number"
+                min={1}
+                max={256}
+                value={settings.ioConcurrency}
+                onChange={(e) => {
+                  const ioConcurrency = Math.max(1, Math.min(256, parseInt(e.target.value) || 32));
+                  setSettings({ ioConcurrency });
+                  invoke("set_io_concurrency", { limit: ioConcurrency }).catch(console.error);
+                }}
+                className="w-20 rounded-md border border-border-subtle bg-surface-3 px-3 py-1.5 text-sm text-text-primary focus:border-phoenix focus:outline-none focus:ring-1 focus:ring-phoenix"
+              />
+            </div>
+
             <div className="flex flex-col gap-3">
               <div className="space-y-0.5">
                 <label className="text-sm font-medium text-text-primary flex items-center gap-2">
@@ -168,6 +216,135 @@ export function SettingsPage() {
           </div>
         </section>
 
+        <IgnoreRulesSection />
+
+        <MimeOverridesSection />
+
+        <CacheAuditSection />
+
+        <section className="rounded-xl border border-border-subtle bg-surface-2 p-6 shadow-sm">
+          <div className="flex items-center gap-3 mb-6 pb-4 border-b border-border-subtle">
+            <div className="rounded-lg bg-surface-3 p-2">
+              <Shield className="h-5 w-5 text-text-primary" />
+            </div>
+            <h2 className="text-lg font-semibold text-text-primary">Privacy</h2>
+          </div>
+
+          <div className="space-y-6">
+            <div className="flex items-center justify-between">
+              <div className="space-y-0.5">
+                <label className="text-sm font-medium text-text-primary">Save source URLs</label>
+                <p className="text-xs text-text-muted">
+                  When off, URLs, request headers and Discord IDs are left out of exported debug data, mapping.csv and partial-export notes
+                </p>
+              </div>
+              <button
+                onClick={() => setSettings({ persistUrlKeys: !settings.persistUrlKeys })}
+                className={cn(
+                  "relative inline-flex h-6 w-11 items-center rounded-full transition-colors focus:outline-none focus:ring-2 focus:ring-phoenix focus:ring-offset-2 focus:ring-offset-surface-1",
+                  settings.persistUrlKeys ? "bg-phoenix" : "bg-surface-3"
+                )}
+              >
+                <span
+                  className={cn(
+                    "inline-block h-4 w-4 transform rounded-full bg-white transition-transform",
+                    settings.persistUrlKeys ? "translate-x-6" : "translate-x-1"
+                  )}
+                />
+              </button>
+            </div>
+
+            <div className="flex items-center justify-between">
+              <div className="space-y-0.5">
+                <label className="text-sm font-medium text-text-primary">Look for saved originals</label>
+                <p className="text-xs text-text-muted">
+                  After a scan, check Downloads and Discord's download folder for full copies of recovered attachments
+                </p>
+              </div>
+              <button
+                onClick={() => setSettings({ checkSavedOriginals: !settings.checkSavedOriginals })}
+                className={cn(
+                  "relative inline-flex h-6 w-11 items-center rounded-full transition-colors focus:outline-none focus:ring-2 focus:ring-phoenix focus:ring-offset-2 focus:ring-offset-surface-1",
+                  settings.checkSavedOriginals ? "bg-phoenix" : "bg-surface-3"
+                )}
+              >
+                <span
+                  className={cn(
+                    "inline-block h-4 w-4 transform rounded-full bg-white transition-transform",
+                    settings.checkSavedOriginals ? "translate-x-6" : "translate-x-1"
+                  )}
+                />
+              </button>
+            </div>
+
+            <div className="flex items-center justify-between">
+              <div className="space-y-0.5">
+                <label className="text-sm font-medium text-text-primary">Verify checksums</label>
+                <p className="text-xs text-text-muted">
+                  After a scan, check the CRC32 Chromium stored for each cached body to flag damaged entries (reads every cache file)
+                </p>
+              </div>
+              <button
+                onClick={() => setSettings({ verifyCrc32: !settings.verifyCrc32 })}
+                className={cn(
+                  "relative inline-flex h-6 w-11 items-center rounded-full transition-colors focus:outline-none focus:ring-2 focus:ring-phoenix focus:ring-offset-2 focus:ring-offset-surface-1",
+                  settings.verifyCrc32 ? "bg-phoenix" : "bg-surface-3"
+                )}
+              >
+                <span
+                  className={cn(
+                    "inline-block h-4 w-4 transform rounded-full bg-white transition-transform",
+                    settings.verifyCrc32 ? "translate-x-6" : "translate-x-1"
+                  )}
+                />
+              </button>
+            </div>
+
+            <div className="flex items-center justify-between">
+              <div className="space-y-0.5">
+                <label className="text-sm font-medium text-text-primary">Recover deleted entries</label>
+                <p className="text-xs text-text-muted">
+                  After a scan, also pick up files the browser was deleting (todelete_), files dropped from the index and orphaned blockfile entries
+                </p>
+              </div>
+              <button
+                onClick={() => setSettings({ recoverDeletedEntries: !settings.recoverDeletedEntries })}
+                className={cn(
+                  "relative inline-flex h-6 w-11 items-center rounded-full transition-colors focus:outline-none focus:ring-2 focus:ring-phoenix focus:ring-offset-2 focus:ring-offset-surface-1",
+                  settings.recoverDeletedEntries ? "bg-phoenix" : "bg-surface-3"
+                )}
+              >
+                <span
+                  className={cn(
+                    "inline-block h-4 w-4 transform rounded-full bg-white transition-transform",
+                    settings.recoverDeletedEntries ? "translate-x-6" : "translate-x-1"
+                  )}
+                />
+              </button>
+            </div>
+
+            <div className="flex flex-col gap-3 sm:flex-row sm:items-center sm:justify-between">
+              <div className="space-y-0.5">
+                <label className="text-sm font-medium text-text-primary flex items-center gap-2">
+                  <Trash2 className="h-4 w-4 text-text-muted" />
+                  Forget everything
+                </label>
+                <p className="text-xs text-text-muted">
+                  {forgetStatus ?? "Delete temp files, cached thumbnails, saved cache paths and current scan results. Recovered files are kept."}
+                </p>
+              </div>
+              <button
+                onClick={handleForgetEverything}
+                disabled={busy}
+                title={busy ? "Wait for the current scan or recovery to finish" : undefined}
+                className="shrink-0 disabled:opacity-50 disabled:pointer-events-none rounded-md border border-border-subtle bg-surface-3 px-4 py-2 text-sm font-semibold text-text-primary hover:border-phoenix hover:text-phoenix transition-colors"
+              >
+                Forget
+              </button>
+            </div>
+          </div>
+        </section>
+
         <section className="rounded-xl border border-border-subtle bg-surface-2 p-6 shadow-sm">
           <div className="flex items-center gap-3 mb-6 pb-4 border-b border-border-subtle">
             <div className="rounded-lg bg-surface-3 p-2">
@@ -186,7 +363,7 @@ export function SettingsPage() {
             </div>
 
             <p className="text-sm text-text-muted leading-relaxed">
-              A powerful tool that recovers media files from Chromium-based browser caches — Discord, Chrome, Brave, Edge, Opera, and custom folders.
+              A powerful tool that recovers media files from Chromium-based browser caches — Discord, Slack, Teams, WhatsApp, Signal, Spotify, Chrome, Brave, Edge, Opera, Vivaldi, Arc, Yandex, Chromium, and custom folders.
             </p>
 
             <div className="pt-4 flex gap-4 border-t border-border-subtle">
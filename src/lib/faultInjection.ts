@@ -0,0 +1,29 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { FaultRule } from "@/types";
+
+/**
+ * Dev-only failure injection. Makes the backend's file reading commands fail
+ * like a broken cache would, so error and retry flows can be tried without
+ * one. From the devtools console:
+ *
+ *   __faults.set([{ command: "read_sparse_cache_file", fault: "eacces", times: 2 }])
+ *   __faults.clear()
+ */
+export async function setFaults(rules: Partial<FaultRule>[]): Promise<number> {
+  const full: FaultRule[] = rules.map((r) => ({
+    command: r.command ?? null,
+    path_contains: r.path_contains ?? null,
+    fault: r.fault ?? "eperm",
+    delay_ms: r.delay_ms ?? null,
+    times: r.times ?? null,
+  }));
+  return invoke("set_fault_injection", { rules: full });
+}
+
+export async function clearFaults(): Promise<number> {
+  return invoke("set_fault_injection", { rules: [] });
+}
+
+export function installFaultInjection(): void {
+  (window as unknown as Record<string, unknown>).__faults = { set: setFaults, clear: clearFaults };
+}
@@ -7,6 +7,7 @@ export interface FFprobeInfo {
     format_name?: string;
   };
   streams: Array<{
+    index?: number;
     codec_type: string;
     codec_name: string;
     width?: number;
@@ -15,20 +16,108 @@ export interface FFprobeInfo {
     sample_rate?: string;
     channels?: number;
     profile?: string;
+    tags?: { language?: string; title?: string };
   }>;
 }
 
+type SidecarOutput = { stdout: string; stderr: string; code: number };
+
+/** Sidecar runs the backend watchdog is tracking, by watch id. */
+const watchedRuns = new Map<number, (err: Error) => void>();
+
+/**
+ * Stop waiting for a watched sidecar run the user skipped or killed; its
+ * caller gets an error. Does nothing for ids that aren't sidecar runs.
+ */
+export function abandonSidecarRun(watchId: number) {
+  watchedRuns.get(watchId)?.(new Error("Stalled ffmpeg run was skipped"));
+}
+
+/**
+ * `command.execute()`, but registered with the backend watchdog: output seen
+ * counts as progress, so a run that goes silent is reported as stalled.
+ */
+async function execWatched(
+  command: Command<string>,
+  name: string,
+  path: string,
+): Promise<SidecarOutput> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  const stdout: string[] = [];
+  const stderr: string[] = [];
+  let watchId: number | null = null;
+  let seen = 0;
+  let lastReport = 0;
+  let finished = false;
+
+  const progress = (line: string) => {
+    seen += line.length;
+    const now = Date.now();
+    if (watchId !== null && now - lastReport > 1000) {
+      lastReport = now;
+      invoke("report_operation_progress", { id: watchId, bytes: seen }).catch(() => {});
+    }
+  };
+  command.stdout.on("data", (line) => {
+    stdout.push(line);
+    progress(line);
+  });
+  command.stderr.on("data", (line) => {
+    stderr.push(line);
+    progress(line);
+  });
+
+  return new Promise((resolve, reject) => {
+    const finish = () => {
+      finished = true;
+      if (watchId !== null) {
+        watchedRuns.delete(watchId);
+        invoke("end_operation_watch", { id: watchId }).catch(() => {});
+      }
+    };
+    command.on("close", (data) => {
+      if (finished) return;
+      finish();
+      resolve({ stdout: stdout.join("\n"), stderr: stderr.join("\n"), code: data.code ?? -1 });
+    });
+    command.on("error", (err) => {
+      if (finished) return;
+      finish();
+      reject(new Error(err));
+    });
+    command
+      .spawn()
+      .then(async (child) => {
+        const id = await invoke<number>("watch_sidecar", { job: name, pid: child.pid, path }).catch((err) => {
+          console.warn(`[${name}] not watched for stalls: ${err}`);
+          return null;
+        });
+        if (id === null) return;
+        if (finished) {
+          invoke("end_operation_watch", { id }).catch(() => {});
+          return;
+        }
+        watchId = id;
+        watchedRuns.set(id, (err) => {
+          if (finished) return;
+          finish();
+          reject(err);
+        });
+      })
+      .catch((err) => {
+        if (finished) return;
+        finished = true;
+        reject(err instanceof Error ? err : new Error(String(err)));
+      });
+  });
+}
+
 async function runSidecar(
   name: string,
   args: string[],
-): Promise<{ stdout: string; stderr: string; code: number }> {
+): Promise<SidecarOutput> {
   const command = Command.sidecar(`binaries/${name}`, args);
-  const output = await command.execute();
-  return {
-    stdout: output.stdout,
-    stderr: output.stderr,
-    code: output.code ?? -1,
-  };
+  return execWatched(command, name, args[args.length - 1] ?? "");
 }
 
 /** Quick health check: can we execute the ffmpeg sidecar at all? */
@@ -87,7 +176,7 @@ export async function ffmpegReEncode(
     command.stdout.on("data", (line) => onProgress(line));
   }
 
-  const output = await command.execute();
+  const output = await execWatched(command, "ffmpeg", inputPath);
   return output.code === 0;
 }
 
@@ -95,33 +184,67 @@ export async function ffmpegReEncode(
  * Remux (stream-copy) an MP4 without re-encoding.
  * Used for chunked MP4 reconstructions where the raw data must be preserved as-is.
  * Re-encoding would cause ffmpeg to silently truncate at the first corrupted/zero-filled gap.
+ * Runs in the backend (remux_mp4, with faststart), which owns the ffmpeg process
+ * and only replaces the output once the remux succeeded. Outputs outside the temp scratch
+ * files need a write `consent` token.
  */
 export async function ffmpegRemux(
   inputPath: string,
   outputPath: string,
-  onProgress?: (line: string) => void,
+  consent?: string,
 ): Promise<boolean> {
-  const command = Command.sidecar("binaries/ffmpeg", [
-    "-y",
-    "-fflags", "+genpts+discardcorrupt+igndts",
-    "-analyzeduration", "100M",
-    "-probesize", "100M",
-    "-err_detect", "ignore_err",
-    "-i", inputPath,
-    "-c", "copy",
-    "-movflags", "+faststart",
-    "-max_muxing_queue_size", "1024",
-    outputPath,
-  ]);
-
-  if (onProgress) {
-    command.on("close", (_data) => {});
-    command.stderr.on("data", (line) => onProgress(line));
-    command.stdout.on("data", (line) => onProgress(line));
+  const { invoke } = await import("@tauri-apps/api/core");
+  try {
+    await invoke("remux_mp4", { input: inputPath, output: outputPath, faststart: true, consent: consent ?? null });
+    return true;
+  } catch (err) {
+    console.warn(`[ffmpeg remux] ${err}`);
+    return false;
   }
+}
 
-  const output = await command.execute();
-  return output.code === 0;
+/** Text subtitle codecs ffmpeg can write out as .srt; WebVTT keeps its own format. */
+const TEXT_SUBTITLE_CODECS = new Set(["mov_text", "subrip", "srt", "ass", "ssa", "text", "webvtt"]);
+
+/**
+ * Write each embedded text subtitle/caption track of a video to a sidecar file
+ * next to it: `name.srt`, or `name.<lang>.srt` / `name.<n>.vtt` when there are
+ * several. Bitmap subtitles (PGS, DVD) can't become text and are reported as skipped.
+ */
+export async function ffmpegExtractSubtitles(
+  videoPath: string,
+): Promise<{ written: string[]; skipped: string[] }> {
+  const info = await ffprobe(videoPath);
+  const tracks = info.streams.filter((s) => s.codec_type === "subtitle");
+  const base = videoPath.replace(/\.[^./\\]+$/, "");
+  const written: string[] = [];
+  const skipped: string[] = [];
+
+  for (let n = 0; n < tracks.length; n++) {
+    const track = tracks[n];
+    if (!TEXT_SUBTITLE_CODECS.has(track.codec_name)) {
+      skipped.push(`track ${n} (${track.codec_name}, not text)`);
+      continue;
+    }
+    const vtt = track.codec_name === "webvtt";
+    const label = track.tags?.language && track.tags.language !== "und" ? track.tags.language : String(n);
+    const outPath = `${base}${tracks.length > 1 ? `.${label}` : ""}.${vtt ? "vtt" : "srt"}`;
+    const result = await runSidecar("ffmpeg", [
+      "-y",
+      "-v", "error",
+      "-i", videoPath,
+      "-map", `0:s:${n}`,
+      "-c:s", vtt ? "webvtt" : "srt",
+      outPath,
+    ]);
+    if (result.code === 0) {
+      written.push(outPath);
+    } else {
+      console.warn(`[ffmpeg subtitles] track ${n} of ${videoPath} failed: ${result.stderr.slice(0, 500)}`);
+      skipped.push(`track ${n} (${track.codec_name}, ffmpeg failed)`);
+    }
+  }
+  return { written, skipped };
 }
 
 export async function ffmpegValidate(
@@ -189,6 +312,7 @@ export async function convertGifToMp4(
 export async function ffmpegConcat(
   inputPaths: string[],
   outputPath: string,
+  consent: string,
   onProgress?: (line: string) => void,
 ): Promise<boolean> {
   const listContent = inputPaths.map((p) => `file '${p.replace(/'/g, "'\\''")}'`).join("\n");
@@ -199,6 +323,7 @@ export async function ffmpegConcat(
   await invoke("write_file_bytes", {
     path: tempListPath,
     data: Array.from(encoder.encode(listContent)),
+    consent,
   });
 
   const command = Command.sidecar("binaries/ffmpeg", [
@@ -222,7 +347,7 @@ export async function ffmpegConcat(
     command.stderr.on("data", (line) => onProgress(line));
   }
 
-  const output = await command.execute();
+  const output = await execWatched(command, "ffmpeg", outputPath);
 
   try {
     const { remove } = await import("@tauri-apps/plugin-fs");
@@ -259,6 +384,6 @@ export async function ffmpegRawExtract(
     command.stderr.on("data", (line) => onProgress(line));
   }
 
-  const output = await command.execute();
+  const output = await execWatched(command, "ffmpeg", inputPath);
   return output.code === 0;
 }
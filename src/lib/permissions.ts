@@ -13,6 +13,7 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
+import type { WriteConsent } from "@/types";
 
 let _isMac: boolean | null = null;
 
@@ -137,3 +138,20 @@ export async function requestFullDiskAccess(): Promise<void> {
     console.warn("[permissions] Failed to open FDA settings:", err);
   }
 }
+
+/**
+ * Ask the backend for a write-consent token covering `scope`.
+ *
+ * The backend shows a native confirmation dialog; every command that writes,
+ * rewrites or deletes files outside the scanner's temp scratch files rejects
+ * calls without a live token for the target path. Throws if the user declines.
+ */
+export async function requestWriteConsent(scope: string, reason: string): Promise<string> {
+  const consent: WriteConsent = await invoke("request_write_consent", { scope, reason });
+  return consent.token;
+}
+
+/** Drop a consent token once the writes it covered are done. */
+export async function revokeWriteConsent(token: string): Promise<void> {
+  await invoke("revoke_write_consent", { token }).catch(() => {});
+}
@@ -0,0 +1,30 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { PurgeReport } from "@/types";
+import { clearThumbnailCache } from "@/lib/thumbnailCache";
+import { useAppStore } from "@/store";
+
+/**
+ * "Forget everything": remove every trace CachePhoenix keeps between runs.
+ *
+ * - temp workspace (Safari snapshots, staging copies, thumbnail/remux scratch files)
+ * - the IndexedDB thumbnail cache
+ * - stored custom cache paths (backend registry)
+ * - in-memory scan results and debug data
+ *
+ * Recovered output folders are never touched.
+ */
+export async function forgetEverything(): Promise<PurgeReport> {
+  const report: PurgeReport = await invoke("purge_temp_workspace");
+  const customPaths: string[] = await invoke("list_custom_cache_paths");
+  for (const path of customPaths) {
+    await invoke("remove_custom_cache_path", { path });
+  }
+  await clearThumbnailCache();
+  try {
+    localStorage.clear();
+  } catch {
+    // Storage unavailable — nothing persisted
+  }
+  useAppStore.getState().resetScanner();
+  return report;
+}
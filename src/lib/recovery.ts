@@ -1,28 +1,134 @@
 ﻿import { invoke } from "@tauri-apps/api/core";
-import type { CacheResource, RecoveryOptions, RecoveryProgress } from "@/types";
+import type { ArchiveExportResult, AudioSalvage, CacheResource, Confidence, DashReconstruction, ExportFileResult, GapPolicy, GapRange, HlsReconstruction, ImageRepairReport, JobCleanup, LegacyRepairReport, MappingReport, MediaProbe, PartialExport, ReconstructionPlan, ReconstructionResult, RecoveredEntry, RecoveryOptions, RecoveryProgress, SampleCoverage, SparseGroup, WebmReconstruction } from "@/types";
 import { getFileExtension } from "@/lib/utils";
+import { useAppStore } from "@/store";
+import { requestWriteConsent, revokeWriteConsent } from "@/lib/permissions";
 import {
   ffmpegReEncode,
   ffmpegRemux,
   ffmpegConcat,
   generateThumbnail,
   convertGifToMp4,
+  ffmpegExtractSubtitles,
 } from "@/lib/ffmpeg";
 
+// Set by cancelRecovery(); checked between resources.
+let cancelRequested = false;
+let currentJobId: string | null = null;
+
+/**
+ * Stop the running recovery: the resource in progress fails at its next
+ * backend write and its files are removed; remaining resources are skipped.
+ */
+export async function cancelRecovery(): Promise<void> {
+  cancelRequested = true;
+  if (currentJobId) await invoke("cancel_output_job", { jobId: currentJobId }).catch(() => {});
+}
+
 export async function recoverResources(
   resources: CacheResource[],
   options: RecoveryOptions,
   onProgress: (progress: RecoveryProgress) => void,
 ): Promise<void> {
   const total = resources.length;
+  cancelRequested = false;
   const log: string[] = [];
   const errors: string[] = [];
+  // Files that were written but only partly arrived (e.g. truncated images)
+  const partial: string[] = [];
+  const unplayable: string[] = [];
+  // Outputs kept in the output folder, for the ZIP
+  const kept: string[] = [];
 
   const addLog = (msg: string) => {
     log.push(msg);
     notifyProgress();
   };
 
+  // Flag MP4s whose moov references more sample data than was recovered —
+  // they will stop playing early even though reconstruction "succeeded".
+  const reportCoverage = (coverage: SampleCoverage | null, name: string) => {
+    if (!coverage || coverage.missing_samples === 0) return;
+    const mb = (n: number) => (n / 1024 / 1024).toFixed(2);
+    addLog(
+      `  ⚠ Incomplete: ${coverage.shortfall_percent.toFixed(1)}% of media missing ` +
+      `(${mb(coverage.recovered_bytes)} of ${mb(coverage.expected_bytes)} MB); ` +
+      `plays to about ${coverage.playable_percent.toFixed(0)}%`,
+    );
+    const trackNames: Record<string, string> = { vide: "video", soun: "audio" };
+    for (const track of coverage.tracks) {
+      addLog(
+        `    ${trackNames[track.handler] ?? track.handler}: ${track.present_percent.toFixed(1)}% of ` +
+        `${track.total_samples} samples present`,
+      );
+    }
+    console.warn(`[recovery] ${name}: ${coverage.missing_samples}/${coverage.total_samples} samples missing`);
+  };
+
+  // So the user can tell which reconstructions are worth opening first.
+  const reportConfidence = (confidence: Confidence | null) => {
+    if (!confidence) return;
+    addLog(`  Confidence: ${confidence.score.toFixed(0)}% (${confidence.rating})` +
+      (confidence.reasons.length > 0 ? ` — ${confidence.reasons.join(", ")}` : ""));
+  };
+
+  // Which cache files were missing and where the video will glitch.
+  const reportGaps = (ranges: GapRange[]) => {
+    const clock = (secs: number) =>
+      `${Math.floor(secs / 60)}:${Math.floor(secs % 60).toString().padStart(2, "0")}`;
+    for (const range of ranges) {
+      const chunks = range.missing_chunks.length > 0 ? range.missing_chunks.join(", ") : "unknown chunks";
+      const time = range.start_secs !== null && range.end_secs !== null
+        ? ` — glitches around ${clock(range.start_secs)}–${clock(range.end_secs)}`
+        : "";
+      addLog(
+        `  Missing ${(range.bytes / 1024 / 1024).toFixed(2)} MB at ${(range.offset / 1024 / 1024).toFixed(2)} MB ` +
+        `(${chunks})${time}` + (range.reason ? ` — ${range.reason}` : ""),
+      );
+    }
+  };
+
+  // Video samples lost but audio there: keep the sound as an .m4a next to it.
+  const salvageAudio = async (coverage: SampleCoverage | null, path: string, track: (path: string) => Promise<unknown>) => {
+    const video = coverage?.tracks.find((t) => t.handler === "vide");
+    const audio = coverage?.tracks.find((t) => t.handler === "soun");
+    if (!options.salvageAudio || !video || !audio || video.present_samples === video.total_samples) return;
+    if (audio.present_percent <= video.present_percent) return;
+    const m4aPath = path.replace(/\.[^.]+$/, "_audio.m4a");
+    await track(m4aPath);
+    try {
+      const salvage: AudioSalvage = await invoke("salvage_audio", { path, output: m4aPath, consent });
+      addLog(
+        `  Saved audio: ${salvage.duration_secs.toFixed(1)}s` +
+        (salvage.dropped_samples > 0 ? ` (${salvage.dropped_secs.toFixed(1)}s lost)` : "") +
+        ` -> ${m4aPath}`,
+      );
+    } catch (err) {
+      addLog(`  Audio salvage failed: ${err instanceof Error ? err.message : String(err)}`);
+    }
+  };
+
+  // Send a finished job's files on to the export target (NAS, phone).
+  const sendToTarget = async (paths: string[]) => {
+    try {
+      const results: ExportFileResult[] = await invoke("export_to_target", {
+        target: options.exportTarget,
+        paths,
+        removeSources: !options.keepLocalCopy,
+        consent,
+      });
+      for (const result of results) {
+        if (result.ok) addLog(`  Sent ${result.source} (${(result.bytes / 1024 / 1024).toFixed(2)} MB)`);
+        else errors.push(`Could not send ${result.source}: ${result.error}`);
+      }
+    } catch (err) {
+      errors.push(`Could not send to ${options.exportTarget}: ${err instanceof Error ? err.message : String(err)}`);
+    }
+  };
+  // Without a local copy the output folder only stages files, so the steps
+  // that read them again after the loop are skipped.
+  const stagingOnly = options.exportTarget !== "" && !options.keepLocalCopy;
+
   let lastProgressUpdate = 0;
   const THROTTLE_MS = 150;
 
@@ -32,7 +138,7 @@ export async function recoverResources(
     lastProgressUpdate = now;
     onProgress({
       current: 0, total, currentFile: "",
-      phase: "copying", log: [...log], errors: [...errors],
+      phase: "copying", log: [...log], errors: [...errors], partial: [...partial], unplayable: [...unplayable],
     });
   };
 
@@ -40,17 +146,79 @@ export async function recoverResources(
     lastProgressUpdate = 0;
     onProgress({
       current: 0, total, currentFile: "",
-      phase: "copying", log: [...log], errors: [...errors],
+      phase: "copying", log: [...log], errors: [...errors], partial: [...partial], unplayable: [...unplayable],
     });
   };
 
+  // One confirmation for the whole run; every in-place write below stays
+  // inside the output folder.
+  const consent = await requestWriteConsent(
+    options.outputFolder,
+    `Recover ${total} file${total === 1 ? "" : "s"} into this folder.`,
+  );
+
+  // PNG/JPEG/GIF/WebP/AVIF: cut trailing garbage and close truncated files
+  // (baseline JPEGs at their last whole MCU, GIFs at their last whole frame),
+  // noting images that only partly arrived.
+  const repairImage = async (path: string, name: string) => {
+    const repair: ImageRepairReport = await invoke("repair_image", { path, consent, padGray: options.padJpegGray });
+    if (repair.action === "repaired") {
+      addLog(`  Repaired ${repair.format}: ${repair.details.join("; ")}`);
+    }
+    if (repair.frames !== null && repair.frames > 1) {
+      addLog(`  Animated GIF, ${repair.frames} frames`);
+    }
+    if (!repair.complete) {
+      const decoded = repair.mcus !== null && repair.total_mcus
+        ? `, ${((repair.mcus / repair.total_mcus) * 100).toFixed(0)}% of it decoded`
+        : "";
+      addLog(`  ⚠ Image is incomplete (${repair.format}${decoded}); the missing part will show ` +
+        (repair.gray_padded ? "grey" : "blank"));
+      partial.push(name);
+    }
+  };
+
+  // Reconstructed video/audio only counts as recovered if ffprobe finds a
+  // stream in it that plays.
+  const verifyPlayback = async (path: string, name: string) => {
+    try {
+      const probe: MediaProbe = await invoke("probe_media", { path });
+      const streams = probe.streams
+        .filter((s) => s.kind === "video" || s.kind === "audio")
+        .map((s) => s.kind === "video" && s.width && s.height
+          ? `${s.codec ?? "?"} ${s.width}x${s.height}`
+          : `${s.codec ?? "?"}${s.sample_rate ? ` ${(s.sample_rate / 1000).toFixed(1)} kHz` : ""}`);
+      const duration = probe.duration_secs !== null ? `, ${probe.duration_secs.toFixed(1)}s` : "";
+      addLog(`  Probe: ${streams.length > 0 ? streams.join(", ") : "no audio/video streams"}${duration}`);
+      if (!probe.playable) {
+        addLog(`  ⚠ Does not play${probe.errors.length > 0 ? `: ${probe.errors[0]}` : ""}`);
+        unplayable.push(name);
+      }
+    } catch (err) {
+      addLog(`  Could not probe ${path}: ${err instanceof Error ? err.message : String(err)}`);
+    }
+  };
+
   await invoke("write_file_bytes", {
     path: options.outputFolder + "/.cachephoenix_marker",
     data: Array.from(new TextEncoder().encode("CachePhoenix output")),
+    consent,
+  });
+  await invoke("begin_export_mapping", {
+    folder: options.outputFolder,
+    includeUrls: useAppStore.getState().settings.persistUrlKeys,
   });
 
   for (let i = 0; i < resources.length; i++) {
     const resource = resources[i];
+    if (cancelRequested) {
+      addLog(`Recovery cancelled; ${resources.length - i} file(s) not recovered`);
+      break;
+    }
+    if (resource.resourceType === "encrypted_data") {
+      addLog(`Skipping ${resource.displayName}: encrypted by the app, not recoverable`);
+      continue;
+    }
     const ext = getFileExtension(resource.resourceType);
     const baseName = `${resource.displayName.replace(/[^a-zA-Z0-9_-]/g, "_")}`;
 
@@ -63,17 +231,127 @@ export async function recoverResources(
       subFolder = `${options.outputFolder}/${typeFolder}`;
     }
 
-    const outputPath = `${subFolder}/${baseName}${ext}`;
+    let outputPath = `${subFolder}/${baseName}${ext}`;
 
     onProgress({
       current: i + 1, total,
       currentFile: resource.displayName,
       phase: "copying",
-      log: [...log], errors: [...errors],
+      log: [...log], errors: [...errors], partial: [...partial], unplayable: [...unplayable],
+    });
+
+    // Everything this resource writes is tracked so a failure or cancel
+    // doesn't leave half a recovery behind.
+    const jobId = `recover-${resource.id}`;
+    currentJobId = jobId;
+    await invoke("begin_output_job", {
+      jobId,
+      folder: options.outputFolder,
+      source: resource.files[0]?.path ?? null,
+      url: resource.indexUrl ?? null,
+      consent,
     });
+    const trackOutput = (path: string) => invoke("track_job_output", { jobId, path }).catch(() => {});
+    let succeeded = false;
+
+    // Multi-chunk images (range-split PNG/JPEG, or sparse index entries)
+    const isChunkedImage = resource.mediaCategory === "image" &&
+      (resource.files.length > 1 || (!!resource.indexIsSparse && (resource.indexChildCount ?? 0) > 1));
+
+    // todelete_ files and orphaned blockfile entries aren't normal cache files.
+    const deleted = resource.deletedEntry?.source !== "unindexed" ? resource.deletedEntry : undefined;
 
     try {
-      if (resource.resourceType === "mp4_complete" ||
+      if (deleted) {
+        addLog(`Recovering deleted ${resource.displayName}...`);
+        await invoke("recover_deleted_entry", {
+          path: deleted.path,
+          entryAddress: deleted.entry_address,
+          dst: outputPath,
+          decodeContent: options.decodeContentEncoding,
+          consent,
+        });
+        if (deleted.body_reused) {
+          addLog(`  ⚠ Part of this entry's storage was reused; the file may hold another resource's data`);
+        }
+        addLog(`  -> ${outputPath}`);
+      } else if (isChunkedImage) {
+        const chunkCount = resource.indexIsSparse ? resource.indexChildCount : resource.files.length;
+        addLog(`Reassembling ${resource.displayName} (${chunkCount} chunks)...`);
+        if (resource.indexIsSparse && resource.indexUrl && resource.cacheDir) {
+          await invoke("reconstruct_from_index", {
+            dir: resource.cacheDir,
+            url: resource.indexUrl,
+            output: outputPath,
+            consent,
+          });
+        } else {
+          await invoke("concat_files", {
+            paths: resource.files.map((f) => f.path),
+            output: outputPath,
+            decodeContent: options.decodeContentEncoding,
+            consent,
+          });
+        }
+        if (resource.resourceType === "png" || resource.resourceType === "jpeg" || resource.resourceType === "gif" ||
+            resource.resourceType === "webp" || resource.resourceType === "avif") {
+          await repairImage(outputPath, resource.displayName);
+        }
+        addLog(`  -> ${outputPath}`);
+      } else if (resource.resourceType === "mp4_fragment" && resource.files.length > 1) {
+        // DASH/fMP4 init segment + media segments (or byte ranges) grouped by the scanner
+        addLog(`Reassembling fragmented ${resource.displayName} (${resource.files.length} files)...`);
+        const result: DashReconstruction = await invoke("reconstruct_dash", {
+          sources: resource.files.map((f) => ({ path: f.path, url: resource.fileUrls?.[f.path] ?? null })),
+          output: outputPath,
+          consent,
+        });
+        addLog(`  ${result.fragments} fragments joined (${(result.bytes_written / 1024 / 1024).toFixed(2)} MB)`);
+        if (result.range_gaps > 0) {
+          addLog(`  ⚠ ${result.range_gaps} byte range(s) missing between cached requests`);
+        }
+        if (result.missing_fragments > 0) {
+          addLog(`  ⚠ ${result.missing_fragments} fragment(s) missing — playback will skip over them`);
+        }
+        if (result.absolute_offsets > 0) {
+          addLog(`  ⚠ ${result.absolute_offsets} fragment(s) use absolute offsets and may not play`);
+        }
+        reportConfidence(result.confidence);
+        addLog(`  -> ${outputPath}`);
+      } else if (resource.resourceType === "mpeg_ts" && resource.files.length > 1) {
+        // HLS .ts segments (and cached .m3u8 playlists) grouped by the scanner
+        addLog(`Reassembling ${resource.displayName}...`);
+        const result: HlsReconstruction = await invoke("reconstruct_hls", {
+          sources: resource.files.map((f) => ({ path: f.path, url: resource.fileUrls?.[f.path] ?? null })),
+          output: outputPath,
+          consent,
+        });
+        addLog(`  ${result.segments} segments joined by ${result.segment_order.replace("_", " ")} (${(result.bytes_written / 1024 / 1024).toFixed(2)} MB)`);
+        if (result.missing_segments > 0) {
+          addLog(`  ⚠ ${result.missing_segments} segment(s) missing — playback will skip over them`);
+        }
+        if (result.encrypted_segments > 0) {
+          addLog(`  ⚠ ${result.encrypted_segments} encrypted segment(s) skipped`);
+        }
+        reportConfidence(result.confidence);
+        if (options.remuxHlsToMp4) {
+          const mp4Path = outputPath.replace(/\.ts$/i, ".mp4");
+          await trackOutput(mp4Path);
+          addLog(`  Remuxing to MP4...`);
+          const { remove } = await import("@tauri-apps/plugin-fs");
+          if (await ffmpegRemux(outputPath, mp4Path, consent)) {
+            await remove(outputPath).catch(() => {});
+            outputPath = mp4Path;
+          } else {
+            await remove(mp4Path).catch(() => {});
+            addLog(`  Remux failed, keeping the .ts stream`);
+          }
+        }
+        addLog(`  -> ${outputPath}`);
+        if (options.generateThumbnails) {
+          await generateThumbnail(outputPath, outputPath.replace(/\.[^.]+$/, "_thumb.jpg"));
+        }
+      } else if (resource.resourceType === "mp4_complete" ||
           // ── Images ──────────────────────────────────────────────────────────
           resource.resourceType === "png" ||
           resource.resourceType === "jpeg" ||
@@ -90,6 +368,7 @@ export async function recoverResources(
           resource.resourceType === "mpeg_ts" ||
           resource.resourceType === "wmv" ||
           resource.resourceType === "mov" ||
+          resource.resourceType === "ogv" ||
           // ── New resource types (Simple Cache / fragment detection) ─────────
           resource.resourceType === "mp4_header_only" ||
           resource.resourceType === "mp4_fragment" ||
@@ -107,27 +386,101 @@ export async function recoverResources(
           resource.resourceType === "m4a") {
         addLog(`Copying ${resource.displayName}...`);
         // _s files are Chromium Simple Cache sparse files (HTTP 206 range data on macOS).
-        // They must be reassembled via copy_sparse_file rather than copied raw.
-        const isSparseSource = /[0-9a-f]{16}_s$/.test(resource.files[0].path);
-        if (isSparseSource) {
-          await invoke("copy_sparse_file", {
-            src: resource.files[0].path,
-            dst: outputPath,
+        // They are reassembled rather than copied raw; if ranges are missing the
+        // output gets a .cachephoenix-part.json sidecar so mergePartialExport can
+        // fill it in later.
+        const isSparseSource = /[0-9a-f]{16}_s$/i.test(resource.files[0].path);
+        if (resource.sparseGroupFiles && resource.sparseGroupFiles.length > 1) {
+          // Ranges of this URL are split over several _s entries; newest data wins.
+          const group: SparseGroup = await invoke("merge_sparse_entries", {
+            paths: resource.sparseGroupFiles,
+            output: outputPath,
+            consent,
+          });
+          addLog(`  Merged ${group.files.length} sparse entries (${(group.covered_bytes / 1024 / 1024).toFixed(2)} of ${(group.size / 1024 / 1024).toFixed(2)} MB present)`);
+        } else if (isSparseSource) {
+          const part: PartialExport = await invoke("export_partial", {
+            sources: [resource.files[0].path],
+            output: outputPath,
+            includeUrl: useAppStore.getState().settings.persistUrlKeys,
+            consent,
           });
+          if (!part.complete) {
+            const missing = part.missing.reduce((sum, [start, end]) => sum + end - start, 0);
+            addLog(
+              `  ⚠ Partial: ${(missing / 1024 / 1024).toFixed(2)} MB in ${part.missing.length} range(s) missing` +
+              (part.unreadable.length > 0 ? `, ${part.unreadable.length} file(s) locked or unreadable` : "") +
+              ` — retry later to fill in`,
+            );
+          }
+        } else if (/[0-9a-f]{16}_[01]$/i.test(resource.files[0].path)) {
+          // Simple Cache _0/_1: large bodies live in _1 with an empty stream 1
+          // in _0, so extract the entry as a whole rather than one file.
+          if (options.useOriginalNames) {
+            const recovered: RecoveredEntry = await invoke("recover_entry", {
+              src: resource.files[0].path,
+              outputDir: subFolder,
+              naming: "content_disposition",
+              fallbackName: baseName,
+              extension: ext,
+              decodeContent: options.decodeContentEncoding,
+              consent,
+            });
+            outputPath = recovered.path;
+          } else {
+            await invoke("copy_entry", {
+              src: resource.files[0].path,
+              dst: outputPath,
+              decodeContent: options.decodeContentEncoding,
+              consent,
+            });
+          }
         } else {
           await invoke("copy_file", {
             src: resource.files[0].path,
             dst: outputPath,
+            decodeContent: options.decodeContentEncoding,
+            consent,
           });
         }
         addLog(`  -> ${outputPath}`);
 
+        if (resource.resourceType === "png" || resource.resourceType === "jpeg" || resource.resourceType === "gif" ||
+            resource.resourceType === "webp" || resource.resourceType === "avif") {
+          await repairImage(outputPath, resource.displayName);
+        }
+
+        if (resource.resourceType === "mp4_complete" || resource.resourceType === "mp4_header_only") {
+          try {
+            reportCoverage(await invoke<SampleCoverage | null>("check_mp4_coverage", { path: outputPath }), resource.displayName);
+          } catch { /* coverage is advisory */ }
+        }
+
+        // Legacy containers: drop corrupt Ogg pages / rebuild the AVI idx1 index /
+        // trim MP3s to whole frames before ffmpeg reads the file.
+        if (resource.resourceType === "avi" || resource.resourceType === "ogv" ||
+            resource.resourceType === "ogg" || resource.resourceType === "opus" ||
+            resource.resourceType === "mp3") {
+          const repair: LegacyRepairReport = await invoke("repair_legacy_media", { path: outputPath, consent });
+          if (repair.action === "repaired") {
+            addLog(`  Repaired ${repair.container}: ${repair.details.join("; ")}`);
+          }
+          if (repair.duration_secs !== null && repair.codec === "opus") {
+            addLog(`  Opus audio, ${repair.duration_secs.toFixed(1)}s` +
+              (repair.missing_pages > 0 ? ` (${repair.missing_pages} page(s) lost)` : ""));
+          }
+          if (repair.container === "mp3" && repair.duration_secs !== null) {
+            addLog(`  ${repair.units} MPEG audio frames, ${repair.duration_secs.toFixed(1)}s`);
+          }
+        }
+
         // Re-mux ALL recovered videos through ffmpeg with error tolerance.
         // Cache-extracted video data (both sparse _s and regular _0) often has
         // structural issues (truncated mdat, wrong box sizes, partial downloads)
         // that ffmpeg's error-tolerance flags can repair for playback.
         if (resource.mediaCategory === "video") {
           const remuxedPath = outputPath.replace(/\.([^.]+)$/, "_remuxed.$1");
+          await trackOutput(remuxedPath);
           addLog(`  Re-encoding video for playability...`);
           const remuxOk = await ffmpegReEncode(outputPath, remuxedPath);
           if (remuxOk) {
@@ -138,7 +491,7 @@ export async function recoverResources(
               addLog(`  Re-encode successful`);
               // Defensive: strip duplicate moov boxes that can occur if ffmpeg's
               // +faststart pass is interrupted or the process runs twice.
-              const moovCount: number = await invoke("fix_mp4_moov", { path: outputPath });
+              const moovCount: number = await invoke("fix_mp4_moov", { path: outputPath, consent });
               if (moovCount > 1) {
                 addLog(`  Fixed ${moovCount} moov boxes (stripped duplicates)`);
               }
@@ -155,6 +508,7 @@ export async function recoverResources(
 
         if (resource.resourceType === "gif" && options.convertGifToMp4) {
           const mp4Path = outputPath.replace(/\.gif$/i, "_converted.mp4");
+          await trackOutput(mp4Path);
           addLog(`  Converting GIF to MP4...`);
           const success = await convertGifToMp4(outputPath, mp4Path);
           if (success) addLog(`  -> ${mp4Path}`);
@@ -170,7 +524,7 @@ export async function recoverResources(
           current: i + 1, total,
           currentFile: resource.displayName,
           phase: "reconstructing",
-          log: [...log], errors: [...errors],
+          log: [...log], errors: [...errors], partial: [...partial], unplayable: [...unplayable],
         });
 
         if (resource.indexUrl && resource.cacheDir) {
@@ -182,31 +536,49 @@ export async function recoverResources(
             ? `Reconstructing from index (sparse, ${resource.indexChildCount ?? '?'} children)`
             : `Reconstructing from index (single file)`;
           addLog(`${label}: ${resource.displayName}...`);
-          const totalBytes: number = await invoke("reconstruct_from_index", {
+          const result: ReconstructionResult = await invoke("reconstruct_from_index", {
             dir: resource.cacheDir,
             url: resource.indexUrl,
             output: outputPath,
+            consent,
           });
-          addLog(`  Index reconstruction: ${(totalBytes / 1024 / 1024).toFixed(2)} MB`);
+          addLog(`  Index reconstruction: ${(result.bytes_written / 1024 / 1024).toFixed(2)} MB`);
+          reportGaps(result.gap_ranges);
+          reportCoverage(result.coverage, resource.displayName);
+          reportConfidence(result.confidence);
+          await salvageAudio(result.coverage, outputPath, trackOutput);
         } else {
           // Legacy heuristic reconstruction — no index data available
           addLog(`Reconstructing chunked MP4: ${resource.displayName} (${resource.files.length} chunks)...`);
           const headerPath = resource.files[0].path;
           const chunkPaths = resource.files.slice(1).map((f) => f.path);
-          const totalBytes: number = await invoke("reconstruct_chunked_mp4", {
+          const result: ReconstructionResult = await invoke("reconstruct_chunked_mp4", {
             headerPath,
             chunkPaths,
             output: outputPath,
+            consent,
           });
-          addLog(`  Raw reconstruction: ${(totalBytes / 1024 / 1024).toFixed(2)} MB`);
+          addLog(`  Raw reconstruction: ${(result.bytes_written / 1024 / 1024).toFixed(2)} MB`);
+          if (result.ordering === "mtime" || result.ordering === "input") {
+            addLog(`  Chunks ordered by ${result.ordering === "mtime" ? "file modification time" : "file list"} (no hex sequence or Content-Range)`);
+          }
+          const layout = result.layout_check;
+          if (layout && (layout.moved > 0 || layout.contradicted > 0)) {
+            addLog(`  Sample table check: ${layout.moved} chunk(s) re-placed, ${layout.contradicted} match nowhere`);
+          }
+          reportGaps(result.gap_ranges);
+          reportCoverage(result.coverage, resource.displayName);
+          reportConfidence(result.confidence);
+          await salvageAudio(result.coverage, outputPath, trackOutput);
         }
 
         // Remux (stream-copy) reconstructed MP4 through ffmpeg to fix container structure.
         // IMPORTANT: use remux (-c copy) NOT re-encode for chunked files.
         // Re-encoding causes ffmpeg to silently truncate at zero-filled gap regions.
         const remuxedPath = outputPath.replace(/\.([^.]+)$/, "_remuxed.$1");
+        await trackOutput(remuxedPath);
         addLog(`  Re-muxing for playability...`);
-        const remuxOk = await ffmpegRemux(outputPath, remuxedPath);
+        const remuxOk = await ffmpegRemux(outputPath, remuxedPath, consent);
         if (remuxOk) {
           try {
             const { remove, rename } = await import("@tauri-apps/plugin-fs");
@@ -215,7 +587,7 @@ export async function recoverResources(
             addLog(`  Re-mux successful`);
             // Defensive: strip duplicate moov boxes that can occur if ffmpeg's
             // +faststart pass is interrupted or the process runs twice.
-            const moovCount: number = await invoke("fix_mp4_moov", { path: outputPath });
+            const moovCount: number = await invoke("fix_mp4_moov", { path: outputPath, consent });
             if (moovCount > 1) {
               addLog(`  Fixed ${moovCount} moov boxes (stripped duplicates)`);
             }
@@ -238,13 +610,33 @@ export async function recoverResources(
         const rawPath = outputPath.replace(/\.webm$/i, "_raw.webm");
 
         const chunkPaths = resource.files.map((f) => f.path);
-        await invoke("concat_files", { paths: chunkPaths, output: rawPath });
+        if (chunkPaths.length >= 2) {
+          // EBML-aware reassembly: trims a cut-off final cluster and voids stale Cues
+          const result: WebmReconstruction = await invoke("reconstruct_chunked_webm", {
+            headerPath: chunkPaths[0],
+            chunkPaths: chunkPaths.slice(1),
+            output: rawPath,
+            consent,
+          });
+          addLog(`  Raw reconstruction: ${(result.bytes_written / 1024 / 1024).toFixed(2)} MB, ${result.clusters} clusters`);
+          if (result.truncated_cluster) {
+            addLog(`  Final cluster was cut off: kept ${result.truncated_cluster.kept_blocks} whole block(s)`);
+          }
+        } else {
+          await invoke("concat_files", {
+            paths: chunkPaths,
+            output: rawPath,
+            decodeContent: options.decodeContentEncoding,
+            consent,
+          });
+        }
 
         const mp4OutputPath = options.convertWebmToMp4
           ? outputPath.replace(/\.webm$/i, ".mp4")
           : outputPath;
 
         let webmSuccess = false;
+        await trackOutput(mp4OutputPath);
         try {
           webmSuccess = await ffmpegReEncode(rawPath, mp4OutputPath, (line) => {
             if (line.includes("frame=") || line.includes("time=")) {
@@ -267,10 +659,16 @@ export async function recoverResources(
 
         if (webmSuccess) {
           addLog(`  -> ${mp4OutputPath}`);
+          outputPath = mp4OutputPath;
         } else {
           errors.push(`Failed to recover WebM: ${resource.displayName}`);
         }
       }
+      const recoveredMedia = resource.mediaCategory === "video" || resource.mediaCategory === "audio";
+      if (recoveredMedia && options.verifyPlayback && !errors.some((e) => e.includes(resource.displayName))) {
+        await verifyPlayback(outputPath, resource.displayName);
+      }
+      succeeded = true;
     } catch (err) {
       const msg = err instanceof Error ? err.message : String(err);
       const src = resource.files[0]?.path ?? 'unknown';
@@ -287,13 +685,25 @@ export async function recoverResources(
         addLog(`    → Fix: System Settings > Privacy & Security > Full Disk Access > enable CachePhoenix`);
       }
     }
+    currentJobId = null;
+    const cleanup = await invoke<JobCleanup>("end_output_job", {
+      jobId,
+      succeeded: succeeded && !cancelRequested,
+      consent,
+    });
+    if (cleanup.removed.length > 0) {
+      addLog(`  Removed ${cleanup.removed.length} incomplete output(s) of ${resource.displayName}`);
+    }
+    if (options.exportTarget && cleanup.kept.length > 0) {
+      await sendToTarget(cleanup.kept);
+    }
+    if (!stagingOnly) kept.push(...cleanup.kept);
 
     flushProgress();
   }
 
-  // ── Concatenate selected videos (optional) ───────────────────────────────
-  if (options.concatenateVideos) {
-    // Collect all successfully recovered video output paths
+  // Output paths of all successfully recovered videos
+  const recoveredVideos = () => {
     const videoPaths: { path: string; modifiedAt: number }[] = [];
     for (const resource of resources) {
       if (resource.mediaCategory !== "video") continue;
@@ -313,6 +723,31 @@ export async function recoverResources(
         });
       }
     }
+    return videoPaths;
+  };
+
+  // ── Extract subtitle tracks (optional) ───────────────────────────────────
+  if (options.extractTranscripts && !stagingOnly && !cancelRequested) {
+    const videos = recoveredVideos();
+    addLog(`Extracting subtitles from ${videos.length} video${videos.length === 1 ? "" : "s"}...`);
+    let trackCount = 0;
+    for (const video of videos) {
+      try {
+        const { written, skipped } = await ffmpegExtractSubtitles(video.path);
+        trackCount += written.length;
+        for (const path of written) addLog(`  -> ${path}`);
+        if (skipped.length > 0) addLog(`  Skipped in ${video.path}: ${skipped.join(", ")}`);
+      } catch (err) {
+        addLog(`  Could not read tracks of ${video.path}: ${err instanceof Error ? err.message : String(err)}`);
+      }
+    }
+    addLog(trackCount > 0 ? `  ${trackCount} subtitle track(s) saved` : "  No text subtitle tracks found");
+    flushProgress();
+  }
+
+  // ── Concatenate selected videos (optional) ───────────────────────────────
+  if (options.concatenateVideos && !stagingOnly && !cancelRequested) {
+    const videoPaths = recoveredVideos();
 
     if (videoPaths.length >= 2) {
       // Sort by timestamp (chronological order)
@@ -328,12 +763,13 @@ export async function recoverResources(
         current: total, total,
         currentFile: "Concatenating videos...",
         phase: "copying",
-        log: [...log], errors: [...errors],
+        log: [...log], errors: [...errors], partial: [...partial], unplayable: [...unplayable],
       });
 
       const concatOk = await ffmpegConcat(
         videoPaths.map((v) => v.path),
         concatOutput,
+        consent,
         (line) => {
           if (line.includes("frame=") || line.includes("time=")) {
             const logIdx = log.findIndex((l) => l.startsWith("  ffmpeg concat: "));
@@ -360,10 +796,79 @@ export async function recoverResources(
     }
   }
 
+  // mapping.csv: where every recovered file came from, plus name collisions and duplicates.
+  try {
+    const mapping = await invoke<MappingReport>("finish_export_mapping", { folder: options.outputFolder, consent });
+    if (mapping.path) addLog(`Wrote ${mapping.path} (${mapping.rows} file${mapping.rows === 1 ? "" : "s"})`);
+    if (mapping.path && kept.length > 0) kept.push(mapping.path);
+    for (const collision of mapping.collisions) {
+      addLog(`  ⚠ ${collision.output_paths.length} files named like ${collision.name} come from different cache entries`);
+    }
+    for (const group of mapping.duplicates) {
+      addLog(`  ${group.length} outputs have identical contents: ${group.join(", ")}`);
+    }
+  } catch (err) {
+    addLog(`Could not write mapping.csv: ${err instanceof Error ? err.message : String(err)}`);
+  }
+
+  // ── Pack everything into one ZIP (optional) ──────────────────────────────
+  if (options.packZip && kept.length > 0 && !cancelRequested) {
+    const zipPath = `${options.outputFolder}/CachePhoenix_recovered.zip`;
+    addLog(`Packing ${kept.length} file${kept.length === 1 ? "" : "s"} into ${zipPath}...`);
+    try {
+      const archive: ArchiveExportResult = await invoke("export_zip", {
+        paths: kept,
+        output: zipPath,
+        passphrase: options.zipPassphrase || null,
+        consent,
+      });
+      addLog(
+        `  ${archive.files} file(s), ${(archive.bytes_written / 1024 / 1024).toFixed(2)} MB` +
+        (archive.encryption === "aes256" ? ", AES-256 encrypted" : ""),
+      );
+      for (const error of archive.errors) errors.push(`Left out of the ZIP: ${error}`);
+    } catch (err) {
+      errors.push(`Could not create the ZIP: ${err instanceof Error ? err.message : String(err)}`);
+    }
+  }
+
+  await revokeWriteConsent(consent);
+
   onProgress({
     current: total, total,
     currentFile: "",
     phase: "complete",
-    log: [...log], errors: [...errors],
+    log: [...log], errors: [...errors], partial: [...partial], unplayable: [...unplayable],
   });
 }
+
+/**
+ * What reconstructing a chunked MP4 from `headerPath` and `chunkPaths` would
+ * do — chunk roles and order, gaps, output size — without writing anything.
+ */
+export async function planReconstruction(
+  headerPath: string,
+  chunkPaths: string[],
+  gapPolicy?: GapPolicy,
+): Promise<ReconstructionPlan> {
+  return invoke<ReconstructionPlan>("plan_reconstruction", { headerPath, chunkPaths, gapPolicy: gapPolicy ?? null });
+}
+
+/**
+ * Fill the missing ranges of a partial export (one with a .cachephoenix-part.json
+ * next to it) from its original cache files plus any new `sources`.
+ */
+export async function mergePartialExport(outputPath: string, sources: string[] = []): Promise<PartialExport> {
+  const folder = outputPath.replace(/[\\/][^\\/]*$/, "");
+  const consent = await requestWriteConsent(folder, "Fill in the missing parts of a partially recovered file.");
+  try {
+    return await invoke<PartialExport>("merge_partial", {
+      output: outputPath,
+      sources,
+      includeUrl: useAppStore.getState().settings.persistUrlKeys,
+      consent,
+    });
+  } finally {
+    await revokeWriteConsent(consent);
+  }
+}
@@ -1,6 +1,11 @@
-import { scanCacheFolder } from "@/lib/scanner";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { mimeToFileType, scanCacheFolder } from "@/lib/scanner";
+import { generateId, getMediaCategory } from "@/lib/utils";
 import { useAppStore } from "@/store";
-import type { CacheResource, ScanDebugData } from "@/types";
+import type {
+  CacheResource, CrcReport, DeletedEntryScan, HotItem, KeyHashCheck, SparseGroup, ScanDebugData, SavedOriginalsResult,
+} from "@/types";
 
 // Module-level scan state — survives component mount/unmount
 let currentScanAbortController: AbortController | null = null;
@@ -18,6 +23,151 @@ export function cancelCurrentScan(): void {
   isScanRunning = false;
 }
 
+/**
+ * Point resources at full copies the user already downloaded, matched by the
+ * attachment's original filename. Failures only cost the hint.
+ */
+async function attachSavedOriginals(resources: CacheResource[]): Promise<void> {
+  const nameOf = (r: CacheResource) => r.indexOriginalFilename || r.discordInfo?.filename;
+  const filenames = [...new Set(resources.map(nameOf).filter((n): n is string => !!n))];
+  if (filenames.length === 0) return;
+  try {
+    const result = await invoke<SavedOriginalsResult>("find_saved_originals", { filenames });
+    const byName = new Map(result.matches.map(m => [m.filename, m]));
+    for (const resource of resources) {
+      const name = nameOf(resource);
+      const match = name ? byName.get(name) : undefined;
+      if (match) resource.savedOriginal = match;
+    }
+  } catch (err) {
+    console.warn("[CachePhoenix] Saved-original lookup failed:", err);
+  }
+}
+
+/**
+ * Flag resources whose Simple Cache files have a header key_hash that doesn't
+ * match the stored key. The backend already refuses to derive body offsets
+ * from such headers; this surfaces it in the UI. Also records whether the
+ * key SHA-256 stored before EOF0 matched (`keyVerified`).
+ */
+async function attachKeyHashChecks(resources: CacheResource[]): Promise<void> {
+  const SIMPLE_CACHE_FILE = /^[0-9a-f]{16}_[01s]$/i;
+  const paths = [...new Set(resources.flatMap(r => r.files.filter(f => SIMPLE_CACHE_FILE.test(f.name)).map(f => f.path)))];
+  if (paths.length === 0) return;
+  try {
+    const checks = await invoke<KeyHashCheck[]>("verify_key_hashes", { paths });
+    const corrupt = new Set(checks.filter(c => c.status === "mismatch").map(c => c.path));
+    const keyVerified = new Map(checks.filter(c => c.key_verified !== null).map(c => [c.path, c.key_verified]));
+    for (const resource of resources) {
+      const bad = resource.files.filter(f => corrupt.has(f.path)).map(f => f.path);
+      if (bad.length > 0) resource.corruptKeyFiles = bad;
+      const verified = resource.files.filter(f => keyVerified.has(f.path)).map(f => keyVerified.get(f.path));
+      if (verified.length > 0) resource.keyVerified = verified.every(Boolean);
+    }
+    const shaMismatches = [...keyVerified.values()].filter(v => v === false).length;
+    if (corrupt.size > 0) console.warn(`[CachePhoenix] ${corrupt.size} Simple Cache files have a key_hash mismatch`);
+    if (shaMismatches > 0) console.warn(`[CachePhoenix] ${shaMismatches} Simple Cache entries have a key SHA-256 mismatch`);
+  } catch (err) {
+    console.warn("[CachePhoenix] key_hash verification failed:", err);
+  }
+}
+
+/**
+ * Flag resources whose Simple Cache streams or sparse ranges fail their stored
+ * CRC32 — the body was torn or overwritten after the browser wrote it.
+ */
+async function attachCrcChecks(resources: CacheResource[]): Promise<void> {
+  const SIMPLE_CACHE_FILE = /^[0-9a-f]{16}_[01s]$/i;
+  const paths = [...new Set(resources.flatMap(r => r.files.filter(f => SIMPLE_CACHE_FILE.test(f.name)).map(f => f.path)))];
+  if (paths.length === 0) return;
+  try {
+    const report = await invoke<CrcReport>("verify_crc32", { paths });
+    const mismatched = new Set(report.files.filter(c => c.status === "mismatch").map(c => c.path));
+    for (const resource of resources) {
+      const bad = resource.files.filter(f => mismatched.has(f.path)).map(f => f.path);
+      if (bad.length > 0) resource.crcMismatchFiles = bad;
+    }
+    console.log(
+      `[CachePhoenix] CRC32: ${(report.bytes_checked / 1024 / 1024).toFixed(1)} MB in ${report.elapsed_ms} ms ` +
+      `(${report.hardware ? "hardware" : "software"}), ${mismatched.size} mismatched`,
+    );
+  } catch (err) {
+    console.warn("[CachePhoenix] CRC32 verification failed:", err);
+  }
+}
+
+/**
+ * Add the entries the browser deleted or is deleting: todelete_ files and
+ * orphaned blockfile entries become resources of their own, and resources
+ * whose files the-real-index no longer lists are marked as deleted.
+ */
+async function attachDeletedEntries(resources: CacheResource[], paths: string[]): Promise<void> {
+  for (const dir of paths) {
+    try {
+      const scan = await invoke<DeletedEntryScan>("scan_deleted_entries", { dir });
+      let added = 0;
+      for (const entry of scan.entries) {
+        if (entry.source === "unindexed") {
+          const resource = resources.find(r => r.files.some(f => f.path === entry.path));
+          if (resource) resource.deletedEntry = entry;
+          continue;
+        }
+        const resourceType = (entry.mime_type && mimeToFileType(entry.mime_type)) || "unknown_data";
+        const category = getMediaCategory(resourceType);
+        const label = category === "image" ? "Image" : category === "video" ? "Video" : category === "audio" ? "Audio" : "File";
+        const name = entry.path.split(/[\\/]/).pop() ?? entry.path;
+        added++;
+        resources.push({
+          id: generateId(),
+          resourceType,
+          mediaCategory: category,
+          files: [{ name, path: entry.path, size: entry.body_size, modified_at: entry.time ?? undefined }],
+          totalSize: entry.body_size,
+          displayName: `Deleted ${label} ${added}`,
+          modifiedAt: entry.time ?? undefined,
+          selected: false,
+          cacheDir: scan.dir,
+          deletedEntry: entry,
+        });
+      }
+      if (scan.errors.length > 0) console.warn(`[CachePhoenix] deleted-entry scan of ${dir}:`, scan.errors);
+      console.log(`[CachePhoenix] ${added} deleted entries recovered from ${scan.dir}`);
+    } catch (err) {
+      console.warn(`[CachePhoenix] deleted-entry scan of ${dir} failed:`, err);
+    }
+  }
+}
+
+/**
+ * Fold resources whose _s files hold ranges of the same URL into one: the
+ * resource with the most data keeps all the files (sparseGroupFiles) and
+ * recovery merges them; the others are dropped from the results.
+ */
+async function mergeSparseGroups(resources: CacheResource[]): Promise<void> {
+  const SPARSE_FILE = /^[0-9a-f]{16}_s$/i;
+  const paths = resources.flatMap(r => r.files.filter(f => SPARSE_FILE.test(f.name)).map(f => f.path));
+  if (paths.length < 2) return;
+  try {
+    const groups = await invoke<SparseGroup[]>("group_sparse_entries", { paths });
+    const dropped = new Set<CacheResource>();
+    for (const group of groups) {
+      const members = resources.filter(r => r.files.some(f => group.files.includes(f.path)));
+      if (members.length < 2) continue;
+      const primary = members.reduce((a, b) => (b.totalSize > a.totalSize ? b : a));
+      primary.sparseGroupFiles = group.files;
+      primary.totalSize = group.size;
+      for (const m of members) if (m !== primary) dropped.add(m);
+    }
+    if (dropped.size > 0) {
+      console.log(`[CachePhoenix] Merged ${dropped.size} sparse resources into ${groups.length} multi-file entries`);
+      const kept = resources.filter(r => !dropped.has(r));
+      resources.splice(0, resources.length, ...kept);
+    }
+  } catch (err) {
+    console.warn("[CachePhoenix] sparse grouping failed:", err);
+  }
+}
+
 export async function startScan(selectedPaths: string[]): Promise<void> {
   // Cancel any existing scan first
   cancelCurrentScan();
@@ -29,6 +179,12 @@ export async function startScan(selectedPaths: string[]): Promise<void> {
   const allResources: CacheResource[] = [];
   let lastDebugData: ScanDebugData | null = null;
 
+  // Likely videos found early, so an urgent clip shows before the scan ends.
+  useAppStore.getState().clearHotItems();
+  const unlistenHot = await listen<HotItem[]>("scan-hot-items", ({ payload }) => {
+    if (!abortController.signal.aborted) useAppStore.getState().addHotItems(payload);
+  });
+
   try {
     for (const path of selectedPaths) {
       if (abortController.signal.aborted) break;
@@ -67,6 +223,17 @@ export async function startScan(selectedPaths: string[]): Promise<void> {
           console.log(`[CachePhoenix][DEBUG] Sample resource WITHOUT metadata:`, { id: s.id, displayName: s.displayName, resourceType: s.resourceType, files: s.files?.length, indexUrl: s.indexUrl, indexContentType: s.indexContentType });
         }
       }
+      await mergeSparseGroups(allResources);
+      await attachKeyHashChecks(allResources);
+      if (useAppStore.getState().settings.verifyCrc32) {
+        await attachCrcChecks(allResources);
+      }
+      if (useAppStore.getState().settings.checkSavedOriginals) {
+        await attachSavedOriginals(allResources);
+      }
+      if (useAppStore.getState().settings.recoverDeletedEntries) {
+        await attachDeletedEntries(allResources, selectedPaths);
+      }
       useAppStore.getState().setScanDebugData(lastDebugData);
       useAppStore.getState().setResources(allResources);
       useAppStore.getState().setIsScanning(false);
@@ -78,6 +245,7 @@ export async function startScan(selectedPaths: string[]): Promise<void> {
       useAppStore.getState().setIsScanning(false);
     }
   } finally {
+    unlistenHot();
     if (currentScanAbortController === abortController) {
       currentScanAbortController = null;
       isScanRunning = false;
@@ -1,7 +1,7 @@
 ﻿import { invoke } from "@tauri-apps/api/core";
-import type { CacheFileEntry, CacheResource, FileType, VideoInfo, BlockfileIndexResult, BlockfileCacheEntry, ScanDebugData, ChunkAssociationDebug } from "@/types";
-import { generateId, getMediaCategory, parseDiscordUrl } from "@/lib/utils";
-import { generateThumbnail as ffmpegThumbnail, ffmpegHealthCheck, ffmpegRemux } from "@/lib/ffmpeg";
+import type { CacheFileEntry, CacheResource, FileType, VideoInfo, BlockfileIndexResult, BlockfileCacheEntry, ContentTypeEntry, ScanDebugData, ChunkAssociationDebug, EncryptedCheck, MimeClass, CacheEntryGroup, CatalogEntry, EntryGrouping, GroupingStrategy, SessionSummary } from "@/types";
+import { discordInfoFromCdn, generateId, getMediaCategory } from "@/lib/utils";
+import { ffmpegHealthCheck, ffmpegRemux } from "@/lib/ffmpeg";
 import { tempDir } from "@tauri-apps/api/path";
 import { getCachedThumbnail, setCachedThumbnail } from "@/lib/thumbnailCache";
 
@@ -14,6 +14,7 @@ import { getCachedThumbnail, setCachedThumbnail } from "@/lib/thumbnailCache";
 //
 // Special cases handled below:
 //   RIFF container  -> check bytes [8..12] to distinguish WAV / AVI / WEBP
+//   Ogg container   -> first packet "\x80theora" means OGV video, else audio
 //   ISO BMFF (ftyp) -> box-size at [0..4], type at [4..8]; major brand at [8..12]
 //   MPEG-TS         -> sync byte 0x47 must repeat at +188
 //   MP3 sync frame  -> 0xFF 0xE? or 0xFF 0xF? with valid layer/version bits
@@ -60,10 +61,15 @@ function isAdtsAac(h: Uint8Array): boolean {
   return h[0] === 0xff && (h[1] & 0xf0) === 0xf0 && (h[1] & 0x06) !== 0x00; // layer ≠ 11 means AAC
 }
 
-// MPEG-TS: 0x47 sync byte repeats at intervals of 188, 192, or 204 bytes.
+// MPEG-TS: 0x47 sync byte repeats at intervals of 188 or 204 bytes; M2TS
+// puts a 4-byte timestamp before each 188-byte packet (192-byte stride).
 function isMpegTs(h: Uint8Array): boolean {
   if (h.length < 188 + 1) return false;
-  return h[0] === 0x47 && h[188] === 0x47;
+  return h[0] === 0x47 && (h[188] === 0x47 || h[204] === 0x47);
+}
+
+function isM2ts(h: Uint8Array): boolean {
+  return h.length > 196 && h[4] === 0x47 && h[196] === 0x47;
 }
 
 export function detectFileType(header: Uint8Array): FileType | null {
@@ -93,6 +99,14 @@ export function detectFileType(header: Uint8Array): FileType | null {
       return "riff_unknown";
     }
 
+    // ── Ogg disambiguation: Theora BOS packet => OGV ────────────────────────
+    if (sig.type === "ogg") {
+      const packetStart = header.length > 26 ? 27 + header[26] : -1;
+      const theora = [0x80, 0x74, 0x68, 0x65, 0x6f, 0x72, 0x61]; // "\x80theora"
+      if (packetStart > 0 && theora.every((b, i) => header[packetStart + i] === b)) return "ogv";
+      return "ogg";
+    }
+
     // ── MPEG-TS second-sync check ────────────────────────────────────────────
     if (sig.type === "mpeg_ts_candidate") {
       return isMpegTs(header) ? "mpeg_ts" : null;
@@ -112,8 +126,14 @@ export function detectFileType(header: Uint8Array): FileType | null {
     const boxType = String.fromCharCode(header[4], header[5], header[6], header[7]);
     if (boxType === "ftyp" && boxSize >= 8 && boxSize <= 512) {
       const brand = String.fromCharCode(header[8], header[9], header[10], header[11]).trim();
-      // AVIF / HEIF brands
+      // AVIF / HEIF brands. Many AVIFs have the generic HEIF major brand
+      // (mif1/msf1) and only list avif among the compatible brands.
+      const compatible: string[] = [];
+      for (let i = 16; i + 4 <= Math.min(boxSize, header.length); i += 4) {
+        compatible.push(String.fromCharCode(header[i], header[i + 1], header[i + 2], header[i + 3]));
+      }
       if (brand === "avif" || brand === "avis") return "avif";
+      if ((brand === "mif1" || brand === "msf1") && compatible.some((b) => b === "avif" || b === "avis")) return "avif";
       if (brand === "heic" || brand === "hevc" || brand === "mif1" || brand === "msf1") return "heic";
       // M4A / audio-only MPEG-4
       if (brand === "M4A " || brand === "m4a " || brand === "M4B " || brand === "M4P ") return "m4a";
@@ -135,6 +155,9 @@ export function detectFileType(header: Uint8Array): FileType | null {
     }
   }
 
+  // ── M2TS (timestamp-prefixed transport stream packets) ─────────────────
+  if (isM2ts(header)) return "mpeg_ts";
+
   // ── WebM/MKV Cluster continuation (Element ID 0x1F43B675) ──────────────
   if (header.length >= 4 &&
       header[0] === 0x1f && header[1] === 0x43 && header[2] === 0xb6 && header[3] === 0x75) {
@@ -392,7 +415,7 @@ async function generateVideoDataUrl(
   const tmp = await tempDir();
   const uid = Date.now();
   const cleanPath = `${tmp}dccr_clean_${uid}.bin`;
-  const isSparse = /[0-9a-f]{16}_s$/.test(filePath);
+  const isSparse = /[0-9a-f]{16}_s$/i.test(filePath);
 
   // Step 1: Copy source file to a clean temp file
   try {
@@ -406,19 +429,13 @@ async function generateVideoDataUrl(
     return undefined;
   }
 
-  // Step 2: Generate thumbnail via ffmpeg sidecar
-  const thumbPath = `${tmp}dccr_thumb_${uid}.jpg`;
+  // Step 2: Grab a frame via the backend's ffmpeg sidecar (it falls back to the
+  // first frame when the clip is shorter than the timestamp)
+  const thumbPath = `${tmp}dccr_thumb_${uid}.png`;
   try {
-    let ok = await ffmpegThumbnail(cleanPath, thumbPath, "00:00:00.500");
-    if (!ok) {
-      ok = await ffmpegThumbnail(cleanPath, thumbPath, "00:00:00");
-    }
-    if (!ok) {
-      try { const { remove } = await import("@tauri-apps/plugin-fs"); await remove(cleanPath); } catch { /* ignore */ }
-      return undefined;
-    }
+    await invoke("generate_thumbnail", { path: cleanPath, outPng: thumbPath, timestamp: "00:00:00.500" });
   } catch (err) {
-    console.warn(`[thumbnail] STEP 2 FAILED (ffmpeg sidecar) for ${filePath}:`, err);
+    console.warn(`[thumbnail] STEP 2 FAILED (generate_thumbnail) for ${filePath}:`, err);
     try { const { remove } = await import("@tauri-apps/plugin-fs"); await remove(cleanPath); } catch { /* ignore */ }
     return undefined;
   }
@@ -433,7 +450,7 @@ async function generateVideoDataUrl(
       await remove(thumbPath);
       await remove(cleanPath);
     } catch { /* ignore */ }
-    return `data:image/jpeg;base64,${b64}`;
+    return `data:image/png;base64,${b64}`;
   } catch (err) {
     console.warn(`[thumbnail] STEP 3 FAILED (read thumbnail) for ${filePath}:`, err);
     return undefined;
@@ -441,7 +458,7 @@ async function generateVideoDataUrl(
 }
 
 function parseCacheHex(name: string): number | null {
-  if (name.length === 8 && name.startsWith("f_")) {
+  if (name.length === 8 && name.toLowerCase().startsWith("f_")) {
     const num = parseInt(name.slice(2), 16);
     return Number.isNaN(num) ? null : num;
   }
@@ -450,8 +467,9 @@ function parseCacheHex(name: string): number | null {
 
 /** Extract the 16-hex-char hash from a Simple Cache filename (e.g. "170e8695a0c85bd4_0" → "170e8695a0c85bd4"). */
 function parseSimpleCacheHash(name: string): { hash: string; stream: string } | null {
-  const match = name.match(/^([0-9a-f]{16})_([01s])$/);
-  return match ? { hash: match[1], stream: match[2] } : null;
+  // Case-insensitive: caches copied across filesystems may be upper-cased.
+  const match = name.match(/^([0-9a-f]{16})_([01s])$/i);
+  return match ? { hash: match[1].toLowerCase(), stream: match[2].toLowerCase() } : null;
 }
 
 async function generateChunkedVideoDataUrl(
@@ -474,8 +492,14 @@ async function generateChunkedVideoDataUrl(
         chunkPaths,
         output: rawPath,
       });
+    } else if (resource.resourceType === "webm_mkv" && allPaths.length >= 2) {
+      await invoke("reconstruct_chunked_webm", {
+        headerPath: allPaths[0],
+        chunkPaths: allPaths.slice(1),
+        output: rawPath,
+      });
     } else {
-      // Generic concatenation for WebM, audio, or single-file MP4 fragments
+      // Generic concatenation for audio or single-file MP4 fragments
       await invoke("concat_files", {
         paths: allPaths,
         output: rawPath,
@@ -619,6 +643,9 @@ const CONTENT_TYPE_MAP: Record<string, FileType> = {
   "video/x-msvideo": "avi",
   "video/quicktime": "mov",
   "video/mp2t": "mpeg_ts",
+  "video/iso.segment": "mp4_fragment",
+  "audio/iso.segment": "mp4_fragment",
+  "video/ogg": "ogv",
   // Audio
   "audio/mpeg": "mp3",
   "audio/mp3": "mp3",
@@ -644,20 +671,10 @@ const CONTENT_TYPE_MAP: Record<string, FileType> = {
   "image/heif": "heic",
 };
 
-function mimeToFileType(mime: string): FileType | null {
+export function mimeToFileType(mime: string): FileType | null {
   return CONTENT_TYPE_MAP[mime] ?? null;
 }
 
-/**
- * Strip the Chromium cache key prefix (e.g. `1/0/`, `0/0/`) from URLs.
- * Chromium's GenerateCacheKey prepends `<has_credentials>/<partition>/` to URLs.
- * This makes `new URL(...)` fail because `1/0/https://...` isn't a valid URL.
- */
-function stripCacheKeyPrefix(rawUrl: string): string {
-  const match = rawUrl.match(/^\d+\/\d+\/(https?:\/\/.+)$/);
-  return match ? match[1] : rawUrl;
-}
-
 export async function scanCacheFolder(
   folderPath: string,
   onProgress?: (progress: ScanProgress) => void,
@@ -693,17 +710,50 @@ export async function scanCacheFolder(
   const mp4HeaderFiles: Array<{ file: CacheFileEntry; data: Uint8Array }> = [];
   const standaloneFiles: Array<{ file: CacheFileEntry; fileType: FileType }> = [];
   const dataChunkFiles: CacheFileEntry[] = [];
+  // Signal Desktop caches attachments already encrypted; their bodies are
+  // checked for ciphertext before anything tries to group them.
+  const isSignalCache = /[\\/]signal[\\/]/i.test(folderPath);
+  const opaqueSimpleFiles: CacheFileEntry[] = [];
   // ── Phase 1: Partition files into Simple Cache pairs and Blockfile files ──
+  // Simple Cache files are grouped by the URL key stored in each of them
+  // (group_cache_entries); file-name hashes are the fallback.
   const simpleCacheMap = new Map<string, { file0?: CacheFileEntry; file1?: CacheFileEntry; fileS?: CacheFileEntry }>();
   const blockfileFiles: CacheFileEntry[] = [];
+  const fileByPath = new Map(files.map((f) => [f.path, f]));
+  const groupedPaths = new Set<string>();
+  // Simple Cache file path → effective URL of its entry
+  const urlByPath = new Map<string, string>();
+  // Cached HLS playlists (.m3u8), by URL; used to order .ts segments
+  const playlistUrlByPath = new Map<string, string>();
+  try {
+    const groups = await invoke<CacheEntryGroup[]>("group_cache_entries", { dir: folderPath });
+    const pick = (path: string | null) => {
+      const file = path ? fileByPath.get(path) : undefined;
+      if (file) groupedPaths.add(file.path);
+      return file;
+    };
+    for (const group of groups) {
+      // _s file = HTTP 206 range response data
+      simpleCacheMap.set(group.key, { file0: pick(group.stream0_file), file1: pick(group.stream2_file), fileS: pick(group.sparse_file) });
+      for (const path of [group.stream0_file, group.stream2_file, group.sparse_file]) {
+        if (path) urlByPath.set(path, group.url);
+      }
+      if (group.stream0_file && /\.m3u8$/i.test(group.url.split(/[?#]/)[0])) {
+        playlistUrlByPath.set(group.stream0_file, group.url);
+      }
+    }
+  } catch (err) {
+    console.warn(`[CachePhoenix] group_cache_entries failed, pairing files by name:`, err);
+  }
 
   for (const file of files) {
+    if (groupedPaths.has(file.path)) continue;
     const parsed = parseSimpleCacheHash(file.name);
     if (parsed) {
       const entry = simpleCacheMap.get(parsed.hash) || {};
       if (parsed.stream === "0") entry.file0 = file;
       else if (parsed.stream === "1") entry.file1 = file;
-      else if (parsed.stream === "s") entry.fileS = file; // sparse file (_s) — HTTP 206 range response data
+      else if (parsed.stream === "s") entry.fileS = file;
       simpleCacheMap.set(parsed.hash, entry);
     } else {
       blockfileFiles.push(file);
@@ -721,6 +771,38 @@ export async function scanCacheFolder(
   // On macOS/Linux, Discord serves videos via HTTP 206 range requests. The actual video
   // bytes live in the _s (sparse) file, NOT in the _0 body (which may be empty or just a
   // tiny stub). We MUST check _s first when it exists.
+  // Entries whose type hinges on the Content-Type in their _0 headers.
+  const awaitingContentType: Array<{ file0: CacheFileEntry; fileS?: CacheFileEntry }> = [];
+  // Strategy 2 up to the Content-Type fallback: false when the magic bytes say nothing.
+  const detectBody = async (file0: CacheFileEntry): Promise<boolean> => {
+    const headerBytes: number[] = await invoke("read_file_header", { path: file0.path, size: 256 });
+    const header = new Uint8Array(headerBytes);
+    const fileType = detectFileType(header);
+
+    // MP4 with ftyp detected — check for complete moov+mdat
+    if (fileType && (fileType === "mp4_complete" || fileType === "mp4_header_only" || fileType === "mp4_chunked")) {
+      const fullBytes: number[] = await invoke("read_file_bytes", { path: file0.path });
+      const fullData = new Uint8Array(fullBytes);
+      const boxes = parseMP4Boxes(fullData);
+      const hasMoov = boxes.some((b) => b.boxType === "moov");
+      const hasMdat = boxes.some((b) => b.boxType === "mdat");
+      if (hasMoov && hasMdat) {
+        standaloneFiles.push({ file: file0, fileType: "mp4_complete" });
+      } else {
+        // Incomplete MP4 in Simple Cache — still standalone (not blockfile-chunked)
+        standaloneFiles.push({ file: file0, fileType: "mp4_header_only" });
+      }
+      return true;
+    }
+
+    // Non-MP4 identified by magic bytes
+    if (fileType) {
+      standaloneFiles.push({ file: file0, fileType });
+      return true;
+    }
+    return false;
+  };
+
   let detectIdx = 0;
   for (const [, { file0, file1: _file1, fileS }] of simpleCacheMap) {
     if (!file0 && !fileS) continue;
@@ -768,42 +850,10 @@ export async function scanCacheFolder(
               });
             }
             sparseHandled = true;
-          } else {
-            // Magic bytes didn't match — try Content-Type from _0 headers to identify sparse data
-            if (file0) {
-              try {
-                const contentType: string = await invoke("read_file_content_type", { path: file0.path });
-                const ct = contentType.toLowerCase().split(";")[0].trim();
-                const ctFileType = mimeToFileType(ct);
-                if (ctFileType) {
-                  const sparseSize: number = await invoke("get_sparse_cache_size", { path: fileS.path });
-                  if (ctFileType === "mp4_complete" || ctFileType.startsWith("mp4")) {
-                    // MP4 identified by Content-Type — read sparse for moov/mdat check
-                    const sparseBytes: number[] = await invoke("read_sparse_cache_file", { path: fileS.path });
-                    const sparseData = new Uint8Array(sparseBytes);
-                    const boxes = parseMP4Boxes(sparseData);
-                    const hasMoov = boxes.some((b) => b.boxType === "moov");
-                    const hasMdat = boxes.some((b) => b.boxType === "mdat");
-                    standaloneFiles.push({
-                      file: { ...fileS, size: sparseData.length },
-                      fileType: (hasMoov && hasMdat) ? "mp4_complete" : "mp4_header_only",
-                    });
-                  } else {
-                    let ctSparseSize = sparseSize;
-                    // Same sanity check: if sparse computed size is suspicious, read full data
-                    if (ctSparseSize <= 0 || ctSparseSize < fileS.size) {
-                      const fallbackBytes: number[] = await invoke("read_sparse_cache_file", { path: fileS.path });
-                      ctSparseSize = fallbackBytes.length;
-                    }
-                    standaloneFiles.push({
-                      file: { ...fileS, size: ctSparseSize > 0 ? ctSparseSize : fileS.size },
-                      fileType: ctFileType,
-                    });
-                  }
-                  sparseHandled = true;
-                }
-              } catch (err) { console.warn(`[scan] Content-Type fallback failed for ${file0.path}:`, err); }
-            }
+          } else if (file0) {
+            // Magic bytes didn't match — the Content-Type from _0 headers decides, after the loop
+            awaitingContentType.push({ file0, fileS });
+            sparseHandled = true;
           }
         }
       } catch (err) { console.warn(`[scan-debug] Sparse detection FAILED for ${fileS?.path}:`, err); onProgress?.({ phase: "detecting", current: detectIdx, total, currentFile: `${fileS?.name} (sparse read failed)` }); }
@@ -813,39 +863,54 @@ export async function scanCacheFolder(
 
     // ── Strategy 2: Try _0 body (works when there's no _s, or _s was empty/unrecognized) ──
     if (!file0) continue;
+    if (!(await detectBody(file0))) awaitingContentType.push({ file0 });
+  }
 
-    const headerBytes: number[] = await invoke("read_file_header", { path: file0.path, size: 256 });
-    const header = new Uint8Array(headerBytes);
-    let fileType = detectFileType(header);
-
-    // MP4 with ftyp detected — check for complete moov+mdat
-    if (fileType && (fileType === "mp4_complete" || fileType === "mp4_header_only" || fileType === "mp4_chunked")) {
-      const fullBytes: number[] = await invoke("read_file_bytes", { path: file0.path });
-      const fullData = new Uint8Array(fullBytes);
-      const boxes = parseMP4Boxes(fullData);
-      const hasMoov = boxes.some((b) => b.boxType === "moov");
-      const hasMdat = boxes.some((b) => b.boxType === "mdat");
-      if (hasMoov && hasMdat) {
-        standaloneFiles.push({ file: file0, fileType: "mp4_complete" });
-      } else {
-        // Incomplete MP4 in Simple Cache — still standalone (not blockfile-chunked)
-        standaloneFiles.push({ file: file0, fileType: "mp4_header_only" });
-      }
-      continue;
-    }
+  // ── Phase 2a-ct: Content-Type fallback for files the magic bytes didn't identify ──
+  // One batched call instead of one per file; a file without the header gets an error entry.
+  let contentTypes: Record<string, ContentTypeEntry> = {};
+  if (awaitingContentType.length > 0) {
+    try {
+      contentTypes = await invoke("read_content_types", { paths: awaitingContentType.map(({ file0 }) => file0.path) });
+    } catch (err) { console.warn(`[scan] read_content_types failed:`, err); }
+  }
+  for (const { file0, fileS } of awaitingContentType) {
+    const contentType = contentTypes[file0.path]?.content_type;
+    const ctFileType = contentType ? mimeToFileType(contentType.toLowerCase().split(";")[0].trim()) : null;
 
-    // Non-MP4 identified by magic bytes
-    if (fileType) {
-      standaloneFiles.push({ file: file0, fileType });
-      continue;
+    if (fileS && ctFileType) {
+      try {
+        if (ctFileType === "mp4_complete" || ctFileType.startsWith("mp4")) {
+          // MP4 identified by Content-Type — read sparse for moov/mdat check
+          const sparseBytes: number[] = await invoke("read_sparse_cache_file", { path: fileS.path });
+          const sparseData = new Uint8Array(sparseBytes);
+          const boxes = parseMP4Boxes(sparseData);
+          const hasMoov = boxes.some((b) => b.boxType === "moov");
+          const hasMdat = boxes.some((b) => b.boxType === "mdat");
+          standaloneFiles.push({
+            file: { ...fileS, size: sparseData.length },
+            fileType: (hasMoov && hasMdat) ? "mp4_complete" : "mp4_header_only",
+          });
+        } else {
+          let ctSparseSize: number = await invoke("get_sparse_cache_size", { path: fileS.path });
+          // Same sanity check: if sparse computed size is suspicious, read full data
+          if (ctSparseSize <= 0 || ctSparseSize < fileS.size) {
+            const fallbackBytes: number[] = await invoke("read_sparse_cache_file", { path: fileS.path });
+            ctSparseSize = fallbackBytes.length;
+          }
+          standaloneFiles.push({
+            file: { ...fileS, size: ctSparseSize > 0 ? ctSparseSize : fileS.size },
+            fileType: ctFileType,
+          });
+        }
+        continue;
+      } catch (err) { console.warn(`[scan] Content-Type fallback failed for ${file0.path}:`, err); }
     }
+    // The sparse data wasn't identified — fall back to the _0 body as Strategy 2 does
+    if (fileS && await detectBody(file0)) continue;
 
-    // Magic bytes failed — try Content-Type from _0 file's HTTP headers
-    try {
-      const contentType: string = await invoke("read_file_content_type", { path: file0.path });
-      const ct = contentType.toLowerCase().split(";")[0].trim();
-      const ctFileType = mimeToFileType(ct);
-      if (ctFileType) {
+    if (ctFileType) {
+      try {
         if (ctFileType === "mp4_complete" || ctFileType.startsWith("mp4")) {
           const fullBytes: number[] = await invoke("read_file_bytes", { path: file0.path });
           const fullData = new Uint8Array(fullBytes);
@@ -861,12 +926,11 @@ export async function scanCacheFolder(
           standaloneFiles.push({ file: file0, fileType: ctFileType });
         }
         continue;
-      }
-    } catch {
-      // No Content-Type header — skip
+      } catch (err) { console.warn(`[scan] Content-Type fallback failed for ${file0.path}:`, err); }
     }
 
     // Unidentified Simple Cache files are NOT added to dataChunkFiles (they are NOT blockfile chunks).
+    if (isSignalCache) opaqueSimpleFiles.push(file0);
   }
   // ── Phase 2b: Process Blockfile files (f_XXXXXX) ─────────────────────────
 
@@ -1087,7 +1151,7 @@ export async function scanCacheFolder(
         if (entry.is_sparse && entry.children.length > 0) {
           // Check URL extension
           try {
-            const urlObj = new URL(stripCacheKeyPrefix(entry.url));
+            const urlObj = new URL(entry.effective_url);
             const pathname = urlObj.pathname.toLowerCase();
             if (pathname.endsWith('.mp4') || pathname.endsWith('.m4v')) fileType = 'mp4_complete';
             else if (pathname.endsWith('.webm')) fileType = 'webm_mkv';
@@ -1096,6 +1160,7 @@ export async function scanCacheFolder(
             else if (pathname.endsWith('.png')) fileType = 'png';
             else if (pathname.endsWith('.jpg') || pathname.endsWith('.jpeg')) fileType = 'jpeg';
             else if (pathname.endsWith('.webp')) fileType = 'webp';
+            else if (pathname.endsWith('.avif')) fileType = 'avif';
           } catch { /* not a valid URL */ }
         }
         // If still no file type and we have no magic bytes to try, try reading
@@ -1134,7 +1199,7 @@ export async function scanCacheFolder(
       let displayName = entry.original_filename || "";
       if (!displayName) {
         try {
-          const urlObj = new URL(stripCacheKeyPrefix(entry.url));
+          const urlObj = new URL(entry.effective_url);
           const pathParts = urlObj.pathname.split("/").filter(Boolean);
           displayName = pathParts.length > 0 ? decodeURIComponent(pathParts[pathParts.length - 1]) : "";
         } catch {
@@ -1155,7 +1220,7 @@ export async function scanCacheFolder(
         files: resourceFiles,
         totalSize,
         displayName,
-        modifiedAt: entry.creation_time ?? (resourceFiles.length > 0 ? Math.max(...resourceFiles.map(f => f.modified_at || 0)) : 0),
+        modifiedAt: entry.last_used ?? entry.creation_time ?? (resourceFiles.length > 0 ? Math.max(...resourceFiles.map(f => f.modified_at || 0)) : 0),
         selected: false,
         indexUrl: entry.url,
         cacheDir: folderPath,
@@ -1164,10 +1229,11 @@ export async function scanCacheFolder(
         indexOriginalFilename: entry.original_filename ?? undefined,
         indexIsSparse: entry.is_sparse || undefined,
         indexChildCount: entry.children.length > 0 ? entry.children.length : undefined,
+        indexMissingChunks: entry.missing_children.length > 0 ? entry.missing_children.length : undefined,
         indexRequestTime: entry.request_time ?? undefined,
         indexResponseTime: entry.response_time ?? undefined,
         indexHeaders: entry.response_headers ?? undefined,
-        discordInfo: parseDiscordUrl(entry.url) ?? undefined,
+        discordInfo: entry.cdn ? discordInfoFromCdn(entry.cdn) : undefined,
       });
       recordChunkAssociations(resources[resources.length - 1], "index", {
         parentHeaderFile: resourceFiles[0]?.name,
@@ -1214,6 +1280,28 @@ export async function scanCacheFolder(
     }
   }
 
+  // ── Encrypted bodies (Signal) ────────────────────────────────────────────
+  // Ciphertext has no magic bytes, so it would land in dataChunkFiles and be
+  // stitched onto a nearby MP4 header as a "chunk". Pull it out instead and
+  // report it as not recoverable.
+  const encryptedFiles: CacheFileEntry[] = [];
+  if (isSignalCache && dataChunkFiles.length + opaqueSimpleFiles.length > 0) {
+    try {
+      const checks: EncryptedCheck[] = await invoke("check_encrypted_bodies", {
+        paths: [...dataChunkFiles, ...opaqueSimpleFiles].map(f => f.path),
+      });
+      const encryptedPaths = new Set(checks.filter(c => c.encrypted).map(c => c.path));
+      for (const file of [...dataChunkFiles, ...opaqueSimpleFiles]) {
+        if (encryptedPaths.has(file.path)) encryptedFiles.push(file);
+      }
+      for (let i = dataChunkFiles.length - 1; i >= 0; i--) {
+        if (encryptedPaths.has(dataChunkFiles[i].path)) dataChunkFiles.splice(i, 1);
+      }
+    } catch (err) {
+      console.warn(`[CachePhoenix] Encrypted body check failed:`, err);
+    }
+  }
+
   // ── Deduplicate MP4 header files ──────────────────────────────────────────
   // Chromium may cache the same video via different cache entries (e.g. range
   // requests), producing multiple f_XXXXXX files with identical ftyp+mdat
@@ -1365,16 +1453,17 @@ export async function scanCacheFolder(
         resource.indexOriginalFilename = entry.original_filename ?? undefined;
         resource.indexIsSparse = entry.is_sparse || undefined;
         resource.indexChildCount = entry.children.length > 0 ? entry.children.length : undefined;
+        resource.indexMissingChunks = entry.missing_children.length > 0 ? entry.missing_children.length : undefined;
         resource.indexRequestTime = entry.request_time ?? undefined;
         resource.indexResponseTime = entry.response_time ?? undefined;
         resource.indexHeaders = entry.response_headers ?? undefined;
-        resource.discordInfo = parseDiscordUrl(entry.url) ?? undefined;
+        resource.discordInfo = entry.cdn ? discordInfoFromCdn(entry.cdn) : undefined;
         // Try to get a better display name from index metadata
         if (entry.original_filename && (!resource.displayName || resource.displayName.startsWith('Video ') || resource.displayName.startsWith('Audio '))) {
           resource.displayName = entry.original_filename;
         } else if (entry.url && (!resource.displayName || resource.displayName.startsWith('Video ') || resource.displayName.startsWith('Audio '))) {
           try {
-            const urlObj = new URL(stripCacheKeyPrefix(entry.url));
+            const urlObj = new URL(entry.effective_url);
             const pathParts = urlObj.pathname.split('/').filter(Boolean);
             if (pathParts.length > 0) {
               const urlName = decodeURIComponent(pathParts[pathParts.length - 1]);
@@ -1441,25 +1530,25 @@ export async function scanCacheFolder(
   // ── Sequential grouping walk ──────────────────────────────────────────────
   // Media header types that start a new group
   const AUDIO_HEADER_TYPES = new Set<FileType>(["mp3", "ogg", "aac", "flac", "wav", "opus", "wma", "m4a"]);
-  const VIDEO_HEADER_TYPES = new Set<FileType>(["webm_mkv", "avi", "flv", "mpeg_ts", "mov"]);
+  const VIDEO_HEADER_TYPES = new Set<FileType>(["webm_mkv", "avi", "flv", "mpeg_ts", "mov", "ogv"]);
 
   // Content types that should NEVER be included in a video group, even if
   // their magic bytes were unrecognized (fileType === null).  Checked via
-  // the blockfile index metadata (indexEntryByFile).
-  const NON_MEDIA_CONTENT_TYPE_PREFIXES = [
-    "font/", "text/", "application/javascript", "application/json",
-    "application/xml", "application/wasm", "application/x-font",
-    "application/font", "application/octet-stream",
-  ];
+  // the blockfile index metadata (indexEntryByFile), classified by the
+  // backend's MIME table once per distinct type.
+  const mimeClasses = new Map<string, MimeClass>();
+  for (const entry of indexEntryByFile.values()) {
+    if (entry.content_type && !mimeClasses.has(entry.content_type)) {
+      mimeClasses.set(entry.content_type, await invoke<MimeClass>("classify_mime", { mime: entry.content_type }));
+    }
+  }
 
   /** Check whether the blockfile index says this file is a non-media type. */
   function isKnownNonMedia(fileName: string): boolean {
-    const entry = indexEntryByFile.get(fileName);
-    if (!entry?.content_type) return false;
-    const ct = entry.content_type.toLowerCase().split(";")[0].trim();
-    // Positive media check — if the index says it's a media type, never exclude
-    if (ct.startsWith("video/") || ct.startsWith("audio/") || ct.startsWith("image/")) return false;
-    return NON_MEDIA_CONTENT_TYPE_PREFIXES.some(prefix => ct.startsWith(prefix));
+    const ct = indexEntryByFile.get(fileName)?.content_type;
+    const mimeClass = ct ? mimeClasses.get(ct) : undefined;
+    if (!mimeClass || mimeClass.source === "unknown") return false;
+    return !["video", "audio", "image"].includes(mimeClass.category);
   }
 
   const CHUNK_BLOCK_SIZE = 1_048_576; // 1 MB — Chromium's default block size
@@ -1479,6 +1568,21 @@ export async function scanCacheFolder(
     return entry.fileType === "mp4_complete" && entry.file.size === CHUNK_BLOCK_SIZE;
   }
 
+  /**
+   * Large images are range-split like videos. A PNG/JPEG cut at exactly
+   * CHUNK_BLOCK_SIZE, or whose Content-Range total exceeds the file, is the
+   * first chunk of a bigger image: it starts a group like a media header, and
+   * recovery validates the reassembled image with repair_image.
+   */
+  const CHUNKED_IMAGE_TYPES = new Set<FileType>(["png", "jpeg"]);
+  function isSuspectChunkedImage(entry: { fileType: FileType | null; file: CacheFileEntry }): boolean {
+    if (entry.fileType === null || !CHUNKED_IMAGE_TYPES.has(entry.fileType)) return false;
+    if (entry.file.size === CHUNK_BLOCK_SIZE) return true;
+    const headers = indexEntryByFile.get(entry.file.name)?.response_headers;
+    const totalBytes = parseContentRangeTotal(headers?.["content-range"] || headers?.["Content-Range"]);
+    return totalBytes !== undefined && totalBytes > entry.file.size;
+  }
+
   function isContinuationChunk(ft: FileType | null): boolean {
     return ft === null || ft === "mp4_fragment" || ft === "webm_continuation" || ft === "media_data_chunk";
   }
@@ -1490,7 +1594,7 @@ export async function scanCacheFolder(
     if (claimed.has(i)) continue;
     const entry = blockfileEntries[i];
 
-    if (!isMediaHeader(entry.fileType) && !isSuspectChunkedMP4Complete(entry)) continue;
+    if (!isMediaHeader(entry.fileType) && !isSuspectChunkedMP4Complete(entry) && !isSuspectChunkedImage(entry)) continue;
 
     // Found a media header — collect continuation chunks
     claimed.add(i);
@@ -1644,7 +1748,7 @@ export async function scanCacheFolder(
         const next = blockfileEntries[j];
 
         // Stop at another media header or suspect chunked header
-        if (isMediaHeader(next.fileType) || isSuspectChunkedMP4Complete(next)) break;
+        if (isMediaHeader(next.fileType) || isSuspectChunkedMP4Complete(next) || isSuspectChunkedImage(next)) break;
 
         // Hex proximity gate (even with affinity, don't reach too far)
         if (next.hex - headerHex > MAX_HEX_GAP) break;
@@ -1684,7 +1788,7 @@ export async function scanCacheFolder(
         headerHex,
       });
     } else {
-      // ── Non-MP4 media header (WebM, audio, etc.): sequential chunk collection ──
+      // ── Non-MP4 media header (WebM, audio, large image): sequential chunk collection ──
       const groupFiles: CacheFileEntry[] = [entry.file];
       const headerHex = entry.hex;
       let chunkMethod: ChunkAssociationDebug["method"] = "hex-proximity";
@@ -1726,7 +1830,7 @@ export async function scanCacheFolder(
         const next = blockfileEntries[j];
 
         // Stop if we hit another media header or suspect chunked mp4
-        if (isMediaHeader(next.fileType) || isSuspectChunkedMP4Complete(next)) break;
+        if (isMediaHeader(next.fileType) || isSuspectChunkedMP4Complete(next) || isSuspectChunkedImage(next)) break;
 
         // Hex proximity gate — tightened from 500 to 50
         if (next.hex - headerHex > MAX_HEX_GAP) break;
@@ -1747,7 +1851,7 @@ export async function scanCacheFolder(
       resourceIdx++;
       const totalSize = groupFiles.reduce((sum, f) => sum + f.size, 0);
       const category = getMediaCategory(entry.fileType!);
-      const label = category === "video" ? "Video" : category === "audio" ? "Audio" : "File";
+      const label = category === "image" ? "Image" : category === "video" ? "Video" : category === "audio" ? "Audio" : "File";
       resources.push({
         id: generateId(),
         resourceType: entry.fileType!,
@@ -1815,7 +1919,123 @@ export async function scanCacheFolder(
     recordChunkAssociations(resources[resources.length - 1], "unclaimed");
   }
 
+  if (encryptedFiles.length > 0) {
+    resources.push({
+      id: generateId(),
+      resourceType: "encrypted_data",
+      mediaCategory: "other",
+      files: encryptedFiles,
+      totalSize: encryptedFiles.reduce((sum, f) => sum + f.size, 0),
+      displayName: `Encrypted blobs (${encryptedFiles.length}) — not recoverable`,
+      modifiedAt: Math.max(...encryptedFiles.map(f => f.modified_at || 0)),
+      selected: false,
+    });
+  }
+
+
+
+  // ── Post-processing: group fMP4/DASH segments per representation ─────────
+  // Streamed video is cached one request per entry: a small init segment
+  // (ftyp + moov) and many styp/sidx/moof/mdat media segments, or — for
+  // YouTube-style players — byte ranges (`&range=a-b`) of one fragmented file.
+  // Entries of one representation (same directory, same representation query
+  // parameters such as itag, same file name once segment numbers are removed)
+  // become one resource; the backend lays byte ranges out, picks the init
+  // segment and orders fragments by sequence number.
+  const dirOfUrl = (url: string | undefined) => {
+    if (!url) return null;
+    try {
+      const parsed = new URL(url);
+      return parsed.origin + parsed.pathname.replace(/[^/]*$/, "");
+    } catch {
+      return null;
+    }
+  };
+  const resourceUrl = (r: CacheResource) => r.indexUrl ?? urlByPath.get(r.files[0]?.path ?? "");
+  const urlDir = (r: CacheResource) => dirOfUrl(resourceUrl(r));
+  {
+    const REPRESENTATION_PARAMS = ["itag", "mime", "representation", "rep", "quality", "bitrate", "track", "lang"];
+    const SEGMENT_NAME_TOKEN = /^(init|seg|segment|chunk|frag|fragment|media)?\d*$/;
+    const isByteRange = (url: string) => /[?&](range|bytes)=\d/.test(url);
+    const representationKey = (url: string) => {
+      const dir = dirOfUrl(url);
+      if (!dir) return null;
+      const parsed = new URL(url);
+      const params = REPRESENTATION_PARAMS.flatMap((p) => parsed.searchParams.getAll(p).map((v) => `${p}=${v}`));
+      const stem = parsed.pathname.replace(/^.*\//, "").replace(/\.[^.]*$/, "");
+      const name = stem.toLowerCase().split(/[-_.]/).filter((t) => !SEGMENT_NAME_TOKEN.test(t)).join("_");
+      return `${dir}${name}?${params.join("&")}`;
+    };
+    const membersByKey = new Map<string, number[]>();
+    resources.forEach((r, i) => {
+      const url = resourceUrl(r);
+      if (!url || r.files.length !== 1) return;
+      const member = r.resourceType === "mp4_fragment" ||
+        ((r.resourceType === "mp4_complete" || r.resourceType === "mp4_header_only" || r.resourceType === "m4a") &&
+          (r.totalSize < 1024 * 1024 || isByteRange(url)));
+      const key = member ? representationKey(url) : null;
+      if (key) membersByKey.set(key, [...(membersByKey.get(key) ?? []), i]);
+    });
+    const toRemove = new Set<number>();
+    for (const [key, indices] of membersByKey) {
+      const members = indices.map((i) => resources[i]);
+      const fragments = members.filter((r) => r.resourceType === "mp4_fragment").length;
+      const byteRanges = members.every((r) => isByteRange(resourceUrl(r)!));
+      // Needs fragments and an init candidate, or a file split into byte ranges
+      if (members.length < 2 || (!byteRanges && (fragments === 0 || fragments === members.length))) continue;
+      const keep = members.find((r) => r.resourceType === "mp4_fragment") ?? members[0];
+      keep.resourceType = "mp4_fragment";
+      keep.mediaCategory = members.every((r) => r.mediaCategory === "audio") ? "audio" : "video";
+      keep.fileUrls = Object.fromEntries(members.map((r) => [r.files[0].path, resourceUrl(r)!]));
+      keep.files = members.flatMap((r) => r.files);
+      keep.totalSize = keep.files.reduce((sum, f) => sum + f.size, 0);
+      keep.modifiedAt = Math.max(...keep.files.map((f) => f.modified_at || 0));
+      keep.displayName = `Fragmented ${keep.mediaCategory} ${resourceIdx + 1} (${members.length} segments)`;
+      resourceIdx++;
+      members.filter((r) => r !== keep).forEach((r) => toRemove.add(resources.indexOf(r)));
+      console.log(`[CachePhoenix] Grouped ${members.length} DASH entries (${fragments} fragments${byteRanges ? ", byte ranges" : ""}) for ${key.slice(0, 80)}`);
+    }
+    for (const idx of Array.from(toRemove).sort((a, b) => b - a)) {
+      resources.splice(idx, 1);
+    }
+  }
 
+  // ── Post-processing: group HLS transport-stream segments ─────────────────
+  // HLS video is cached one .ts segment per entry. Segments whose URLs share a
+  // directory become one resource together with the .m3u8 playlists cached
+  // there; the backend orders them by the playlist's sequence numbers (or the
+  // segment file names) and joins them.
+  {
+    const segmentsByDir = new Map<string, number[]>();
+    resources.forEach((r, i) => {
+      const dir = r.resourceType === "mpeg_ts" && r.files.length === 1 ? urlDir(r) : null;
+      if (dir) segmentsByDir.set(dir, [...(segmentsByDir.get(dir) ?? []), i]);
+    });
+    const toRemove = new Set<number>();
+    for (const [dir, indices] of segmentsByDir) {
+      if (indices.length < 2) continue;
+      const playlists = Array.from(playlistUrlByPath)
+        .filter(([, url]) => dirOfUrl(url) === dir)
+        .flatMap(([path]) => fileByPath.get(path) ?? []);
+      const keep = resources[indices[0]];
+      keep.fileUrls = {};
+      for (const r of indices.map((i) => resources[i])) {
+        const url = r.indexUrl ?? urlByPath.get(r.files[0].path);
+        if (url) keep.fileUrls[r.files[0].path] = url;
+      }
+      for (const file of playlists) keep.fileUrls[file.path] = playlistUrlByPath.get(file.path)!;
+      keep.files = [...indices.flatMap((i) => resources[i].files), ...playlists];
+      keep.totalSize = keep.files.reduce((sum, f) => sum + f.size, 0);
+      keep.modifiedAt = Math.max(...keep.files.map((f) => f.modified_at || 0));
+      keep.displayName = `HLS video ${resourceIdx + 1} (${indices.length} segments)`;
+      resourceIdx++;
+      indices.slice(1).forEach((i) => toRemove.add(i));
+      console.log(`[CachePhoenix] Grouped ${indices.length} HLS segments with ${playlists.length} playlist(s) under ${dir.slice(0, 80)}`);
+    }
+    for (const idx of Array.from(toRemove).sort((a, b) => b - a)) {
+      resources.splice(idx, 1);
+    }
+  }
 
   // ── Post-processing: deduplicate resources with the same indexUrl ──────────
   // The index-first path creates authoritative resources, but edge cases
@@ -1958,3 +2178,29 @@ function assembleChunkedMP4(
     selected: false,
   };
 }
+
+// ─── Scan sessions ──────────────────────────────────────────────────────────
+//
+// The grouped results view asks the backend for its trees instead of
+// building them from every entry here: a session catalogs the scanned
+// folders once, `groupEntries` summarizes it, and a leaf's rows are only
+// fetched when it is opened.
+
+/** Catalog `dirs` under `session`, replacing any earlier catalog of that id. */
+export async function openScanSession(session: string, dirs: string[]): Promise<SessionSummary> {
+  return invoke<SessionSummary>("open_scan_session", { session, dirs });
+}
+
+/** Two-level summary of a session's entries by `strategy`. */
+export async function groupEntries(session: string, strategy: GroupingStrategy): Promise<EntryGrouping> {
+  return invoke<EntryGrouping>("group_entries", { session, strategy });
+}
+
+/** Catalog rows of a leaf's `entry_ids`. */
+export async function sessionEntries(session: string, ids: number[]): Promise<CatalogEntry[]> {
+  return invoke<CatalogEntry[]>("session_entries", { session, ids });
+}
+
+export async function closeScanSession(session: string): Promise<void> {
+  await invoke("close_scan_session", { session });
+}
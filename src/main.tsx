@@ -2,6 +2,11 @@ import React from "react";
 import ReactDOM from "react-dom/client";
 import { App } from "./App";
 import "./styles/globals.css";
+import { installFaultInjection } from "./lib/faultInjection";
+
+if (import.meta.env.DEV) {
+  installFaultInjection();
+}
 
 ReactDOM.createRoot(document.getElementById("root")!).render(
   <React.StrictMode>
@@ -5,12 +5,14 @@ import type {
   CachePathInfo,
   CacheResource,
   FilterCategory,
+  HotItem,
   RecoveryOptions,
   RecoveryProgress,
   ScanDebugData,
   ScannerStep,
   SortOrder,
 } from "@/types";
+import { isVoiceMessage } from "@/lib/utils";
 
 interface AppStore {
   currentPage: AppPage;
@@ -28,6 +30,10 @@ interface AppStore {
 
   scanProgress: { current: number; total: number; currentFile: string; phase: string };
   setScanProgress: (progress: { current: number; total: number; currentFile: string; phase: string }) => void;
+  /** Likely complete videos announced while the scan is still running */
+  hotItems: HotItem[];
+  addHotItems: (items: HotItem[]) => void;
+  clearHotItems: () => void;
 
   resources: CacheResource[];
   setResources: (resources: CacheResource[]) => void;
@@ -90,6 +96,9 @@ export const useAppStore = create<AppStore>((set) => ({
 
   scanProgress: { current: 0, total: 0, currentFile: "", phase: "" },
   setScanProgress: (progress) => set({ scanProgress: progress }),
+  hotItems: [],
+  addHotItems: (items) => set((state) => ({ hotItems: [...state.hotItems, ...items] })),
+  clearHotItems: () => set({ hotItems: [] }),
 
   resources: [],
   setResources: (resources) => set({ resources }),
@@ -111,6 +120,7 @@ export const useAppStore = create<AppStore>((set) => ({
         : category === "images" ? r.mediaCategory === "image"
         : category === "videos" ? r.mediaCategory === "video"
         : category === "audio" ? r.mediaCategory === "audio"
+        : category === "voice" ? isVoiceMessage(r)
         : r.mediaCategory === "other",
     })),
   })),
@@ -133,6 +143,17 @@ export const useAppStore = create<AppStore>((set) => ({
     generateThumbnails: true,
     convertGifToMp4: false,
     concatenateVideos: false,
+    extractTranscripts: false,
+    decodeContentEncoding: true,
+    useOriginalNames: true,
+    remuxHlsToMp4: true,
+    salvageAudio: true,
+    padJpegGray: false,
+    verifyPlayback: true,
+    exportTarget: "",
+    keepLocalCopy: true,
+    packZip: false,
+    zipPassphrase: "",
   },
   setRecoveryOptions: (options) => set((state) => ({
     recoveryOptions: { ...state.recoveryOptions, ...options },
@@ -143,10 +164,15 @@ export const useAppStore = create<AppStore>((set) => ({
 
   settings: {
     maxThreads: 4,
+    ioConcurrency: 32,
     outputNamingTemplate: "{type}_{index}",
     autoOpenOutput: true,
     defaultOutputFolder: "",
     theme: "dark",
+    persistUrlKeys: true,
+    checkSavedOriginals: false,
+    verifyCrc32: true,
+    recoverDeletedEntries: false,
   },
   setSettings: (settings) => set((state) => ({
     settings: { ...state.settings, ...settings },
@@ -164,6 +190,7 @@ export const useAppStore = create<AppStore>((set) => ({
     isScanning: false,
     isRecovering: false,
     scanProgress: { current: 0, total: 0, currentFile: "", phase: "" },
+    hotItems: [],
     recoveryProgress: null,
     resources: [],
     selectedPaths: [],
@@ -21,6 +21,7 @@ export type FileType =
   | "mov"
   | "mp4_fragment"
   | "webm_continuation"
+  | "ogv"
   // ── Audio ───────────────────────────────────────────────────────
   | "mp3"
   | "aac"
@@ -33,7 +34,8 @@ export type FileType =
   // ── Fallback ────────────────────────────────────────────────────
   | "unknown_data"
   | "riff_unknown"
-  | "media_data_chunk";
+  | "media_data_chunk"
+  | "encrypted_data";
 
 export type MediaCategory = "image" | "video" | "audio" | "other";
 
@@ -42,14 +44,43 @@ export interface CacheFileEntry {
   path: string;
   size: number;
   modified_at?: number;
+  /** Last time the browser used the entry (unix seconds), from the-real-index */
+  last_used?: number | null;
+  /** Size of the whole entry according to the-real-index */
+  index_size?: number | null;
+  /** Likelihood the entry is evicted soon; set when the-real-index was readable */
+  eviction_risk?: EvictionRisk | null;
 }
 
 export interface CachePathInfo {
   path: string;
+  /** `path` with the home / AppData folder abbreviated (~/..., %APPDATA%\...) */
+  display_path: string;
   exists: boolean;
   file_count: number;
   total_size: number;
   client_name: string;
+  /** What the folder is; non-media caches (Code Cache, GPUCache, ...) are never scanned */
+  kind: "http_cache" | "profile_root" | "unknown" | "snapshot" | "code_cache" | "gpu_cache" | "dawn_cache" | "shader_cache" | "spotify_storage";
+  /** Explanation shown for anything other than an HTTP cache */
+  kind_label: string | null;
+  /** For a profile root: the HTTP cache folder that is actually scanned */
+  resolved_path: string | null;
+  /** Which Discord build wrote the cache, for Discord folders */
+  discord_build: DiscordBuild | null;
+  /** `path` is a ZIP or tar archive; `resolved_path` is its extracted cache folder */
+  from_archive: boolean;
+}
+
+export interface DiscordBuild {
+  channel: "stable" | "ptb" | "canary" | "development";
+  /** Newest installed host version, e.g. "1.0.9163" */
+  host_version: string | null;
+  /** Installed modules and their versions */
+  modules: Record<string, number>;
+  /** settings.json has SKIP_HOST_UPDATE set, so the host may be old */
+  host_updates_skipped: boolean;
+  user_data_dir: string;
 }
 
 export interface ScannedFile {
@@ -90,6 +121,8 @@ export interface CacheResource {
   indexIsSparse?: boolean;
   /** Number of sparse children (range-request chunks) */
   indexChildCount?: number;
+  /** Sparse children the parent lists but the cache has evicted */
+  indexMissingChunks?: number;
   /** HTTP request timestamp from cache metadata (unix seconds) */
   indexRequestTime?: number;
   /** HTTP response timestamp from cache metadata (unix seconds) */
@@ -98,6 +131,20 @@ export interface CacheResource {
   indexHeaders?: Record<string, string>;
   /** Parsed Discord-specific metadata from the source URL */
   discordInfo?: DiscordInfo;
+  /** A full copy of this attachment already on disk (Downloads etc.) */
+  savedOriginal?: SavedOriginal;
+  /** Simple Cache files of this resource whose header key_hash doesn't match the key */
+  corruptKeyFiles?: string[];
+  /** Key SHA-256 before EOF0 checked: true if every `_0` file matched, false if any didn't */
+  keyVerified?: boolean;
+  /** Simple Cache files of this resource whose stream or range CRC32 doesn't match */
+  crcMismatchFiles?: string[];
+  /** All _s files holding ranges of this URL, oldest first; set when there is more than one */
+  sparseGroupFiles?: string[];
+  /** Set when the browser deleted (or is deleting) the entry; recovered via recover_deleted_entry */
+  deletedEntry?: DeletedEntry;
+  /** Request URL per file path, for resources merged from several entries (HLS segments) */
+  fileUrls?: Record<string, string>;
 }
 
 export interface VideoInfo {
@@ -118,6 +165,28 @@ export interface RecoveryOptions {
   generateThumbnails: boolean;
   convertGifToMp4: boolean;
   concatenateVideos: boolean;
+  /** After recovery, save embedded subtitle/caption tracks as .srt/.vtt next to each video */
+  extractTranscripts: boolean;
+  /** Undo gzip/br/zstd/deflate Content-Encoding when copying cached bodies */
+  decodeContentEncoding: boolean;
+  /** Name Simple Cache outputs from Content-Disposition or the URL instead of the scan label */
+  useOriginalNames: boolean;
+  /** Stream-copy reassembled HLS transport streams into .mp4 via ffmpeg */
+  remuxHlsToMp4: boolean;
+  /** Also save the audio track as .m4a when a reconstructed video is missing samples */
+  salvageAudio: boolean;
+  /** Fill the missing part of a truncated JPEG with grey instead of leaving it to the viewer */
+  padJpegGray: boolean;
+  /** ffprobe every recovered video/audio file and flag the ones that don't play */
+  verifyPlayback: boolean;
+  /** Export target id ("mtp:...", "smb:...", "sftp:...") each recovered file is sent to; "" keeps files local */
+  exportTarget: string;
+  /** Keep the local copy of files sent to the export target */
+  keepLocalCopy: boolean;
+  /** After recovery, also pack the recovered files into one ZIP in the output folder */
+  packZip: boolean;
+  /** AES-256 passphrase for that ZIP; "" leaves it unencrypted. Never stored */
+  zipPassphrase: string;
 }
 
 export interface RecoveryProgress {
@@ -127,21 +196,35 @@ export interface RecoveryProgress {
   phase: "copying" | "reconstructing" | "encoding" | "validating" | "complete";
   log: string[];
   errors: string[];
+  /** Display names of files that were recovered but are incomplete */
+  partial: string[];
+  /** Display names of video/audio files ffprobe found no playable stream in */
+  unplayable: string[];
 }
 
 export interface AppSettings {
   maxThreads: number;
+  /** File operations the backend runs at once (set_io_concurrency) */
+  ioConcurrency: number;
   outputNamingTemplate: string;
   autoOpenOutput: boolean;
   defaultOutputFolder: string;
   theme: "dark" | "light";
+  /** When false, URLs and request headers are left out of anything written to disk */
+  persistUrlKeys: boolean;
+  /** After a scan, look in Downloads for full copies of recovered attachments */
+  checkSavedOriginals: boolean;
+  /** After a scan, verify the stored CRC32 of every Simple Cache stream */
+  verifyCrc32: boolean;
+  /** After a scan, look for todelete_ files, unindexed files and orphaned blockfile entries */
+  recoverDeletedEntries: boolean;
 }
 
 export type AppPage = "scanner" | "settings";
 
 export type ScannerStep = "select" | "scanning" | "results" | "recovery" | "complete";
 
-export type FilterCategory = "all" | "images" | "videos" | "audio" | "other";
+export type FilterCategory = "all" | "images" | "videos" | "audio" | "voice" | "other";
 
 export type SortOrder = "newest" | "oldest";
 
@@ -170,6 +253,37 @@ export interface DiscordInfo {
   isMediaProxy: boolean;
   /** The full URL without HMAC signature */
   cleanUrl?: string;
+  /** Resize/format query params of a media proxy URL */
+  variant?: CdnVariant;
+}
+
+export interface CdnVariant {
+  width: number | null;
+  height: number | null;
+  size: number | null;
+  format: string | null;
+  quality: string | null;
+}
+
+/** A Discord CDN URL matched against the backend's endpoint table (discord_cdn.rs) */
+export interface CdnMatch {
+  kind: string;
+  host: string;
+  is_media_proxy: boolean;
+  group_key: string;
+  /** Content-Disposition filename, else the one in the URL */
+  original_filename: string | null;
+  /** `ex` / `is` in Unix seconds, `hm` as stored */
+  signed: { expires_at: number | null; issued_at: number | null; hm: string | null } | null;
+  channel_id: string | null;
+  resource_id: string | null;
+  /** Unix seconds, decoded from the snowflake IDs */
+  created_at: number | null;
+  channel_created_at: number | null;
+  variant: CdnVariant | null;
+  clean_url: string;
+  header_hits: string[];
+  header_misses: string[];
 }
 
 // ── Blockfile Index Parser Types ─────────────────────────────────────────────
@@ -186,10 +300,36 @@ export interface BlockfileSparseChild {
   child_id: number;
   offset_bytes: number;
   data_ref: BlockfileDataRef;
+  /** Body ranges [start, end) this child actually holds, from its block bitmap */
+  ranges: [number, number][];
+}
+
+export interface EvictionRisk {
+  /** 0.0 (safe) .. 1.0 (next in line for eviction) */
+  score: number;
+  level: "low" | "medium" | "high";
+  lru_list: "no_use" | "low_use" | "high_use" | "simple";
+}
+
+/** Header of one data_N block file */
+export interface BlockfileDataFile {
+  name: string;
+  version: number;
+  /** Block size in bytes: 36 (rankings), 256, 1024 or 4096 */
+  entry_size: number;
+  num_entries: number;
+  max_entries: number;
+  /** Next file in the chain once this one is full (e.g. "data_4") */
+  next_file: string | null;
 }
 
 export interface BlockfileCacheEntry {
+  /** Cache key as stored, possibly with an isolation prefix */
   url: string;
+  /** The resource URL, isolation prefix stripped */
+  effective_url: string;
+  /** Top frame and frame site of a double-keyed key; empty otherwise */
+  isolation_sites: string[];
   content_type: string | null;
   content_length: number | null;
   original_filename: string | null;
@@ -204,16 +344,43 @@ export interface BlockfileCacheEntry {
   body_size: number;
   is_sparse: boolean;
   children: BlockfileSparseChild[];
+  /** Present body ranges [start, end) across all sparse children */
+  sparse_ranges: [number, number][];
+  /** Children listed in the parent's bitmap but evicted from the index */
+  missing_children: number[];
+  /** Declared size of streams 0-3 (headers, body, extra, sparse/metadata) */
+  stream_sizes: [number, number, number, number];
+  /** Times the entry was reused; selects its LRU list for eviction */
+  reuse_count: number;
+  /** Last use and last write from the entry's rankings node (unix seconds) */
+  last_used: number | null;
+  last_modified: number | null;
+  /** LRU list the entry is linked into */
+  lru_list: "no_use" | "low_use" | "high_use" | null;
+  /** Position in the order eviction trims the lists (0 = first to go) */
+  eviction_order: number | null;
+  /** Likelihood this entry is evicted soon */
+  eviction_risk: EvictionRisk | null;
+  /** Host/signature-independent identity for known Discord CDN URLs */
+  group_key: string | null;
+  /** Parsed Discord CDN URL, for known endpoints */
+  cdn: CdnMatch | null;
 }
 
 export interface BlockfileIndexResult {
   entries: BlockfileCacheEntry[];
   entry_count: number;
   version: number;
+  /** Total bytes stored in the cache according to the index header */
+  num_bytes: number;
+  /** Block files found, including chained data_4+ files */
+  block_files: BlockfileDataFile[];
   errors: string[];
   /** Sparse child-to-parent linking diagnostics */
   sparse_linking_stats: {
     total_children_linked: number;
+    /** Children left over from an earlier download of the same URL (signature mismatch) */
+    stale_children: number;
     orphaned_groups: number;
     orphaned_children_total: number;
     unmatched_parents: number;
@@ -224,6 +391,270 @@ export interface BlockfileIndexResult {
   };
 }
 
+/** Payload item of the "scan-hot-items" event, emitted while the scan is still running */
+export interface HotItem {
+  url: string;
+  content_type: string;
+  body_size: number;
+  original_filename: string | null;
+  group_key: string | null;
+  /** 0..1; how sure the backend is that this is a complete, recoverable video */
+  confidence: number;
+}
+
+/** Payload of the "index-parse-progress" event (at most 10/s, latest state only) */
+export interface IndexProgress {
+  phase: "entries" | "linking";
+  current: number;
+  total: number;
+}
+
+// ── Reconstruction Types ─────────────────────────────────────────────────────
+
+/**
+ * How missing chunks are handled during reconstruction. "close_gaps" does not
+ * rewrite a progressive MP4's moov, so its playback breaks after the first gap.
+ */
+export type GapPolicy = "pad_zeros" | "truncate_at_gap" | "close_gaps";
+
+/** Returned by reconstruct_chunked_mp4 and reconstruct_from_index */
+export interface ReconstructionResult {
+  bytes_written: number;
+  gap_policy: GapPolicy;
+  gaps_found: number;
+  gap_bytes: number;
+  truncated: boolean;
+  /** moov sample bytes vs. bytes present; null for non-MP4 output */
+  coverage: SampleCoverage | null;
+  /** What ordered the chunk files; null for index/sparse reconstructions */
+  ordering: "hex_sequence" | "content_range" | "mtime" | "input" | null;
+  /** Chunk placement checked against the moov's sample table; null without one */
+  layout_check: LayoutCheck | null;
+  /** Missing ranges, where the reconstructor knows them (chunked MP4 only) */
+  gap_ranges: GapRange[];
+  /** How likely the output is to be worth opening */
+  confidence: Confidence | null;
+}
+
+/** A missing range of a reconstruction and roughly where playback glitches */
+export interface GapRange {
+  /** Offset in the original upload's layout */
+  offset: number;
+  bytes: number;
+  /** Cache files that would have held it, e.g. "f_00002a" */
+  missing_chunks: string[];
+  /** Playback time the range covers, from the moov's sample tables */
+  start_secs: number | null;
+  end_secs: number | null;
+  /** Why the range is missing, when a chunk was there but left out */
+  reason: string | null;
+}
+
+/** 0–100 score of a reconstruction and the factors that cost it points */
+export interface Confidence {
+  score: number;
+  rating: "high" | "medium" | "low";
+  /** Share of the media present */
+  filled_percent: number;
+  gaps: number;
+  /** Share of checkable chunks the moov's sample table agrees with */
+  layout_agreement: number | null;
+  /** Share of media chunks at the full chunk size */
+  chunk_consistency: number | null;
+  /** Worst first */
+  reasons: string[];
+}
+
+export interface LayoutCheck {
+  confirmed: number;
+  /** Moved to the slot where their video samples line up */
+  moved: number;
+  /** Matched neither their hex slot nor any free slot */
+  contradicted: number;
+  unverifiable: number;
+}
+
+/** One chunk of a plan_reconstruction result */
+export interface PlannedChunk {
+  path: string;
+  bytes: number;
+  hex: number | null;
+  role: "header" | "media" | "tail" | "duplicate_tail" | "foreign";
+  /** Chunk-sized slot after the header (moov-at-end layout) */
+  slot: number | null;
+  /** Slot from a hex number or the sample table, not just after the previous chunk */
+  anchored: boolean;
+  /** Ends up in the output */
+  written: boolean;
+}
+
+/** Returned by plan_reconstruction: what reconstruct_chunked_mp4 would write */
+export interface ReconstructionPlan {
+  layout: "fragmented" | "moov_at_end" | "moov_at_front" | "no_moov";
+  gap_policy: GapPolicy;
+  ordering: ReconstructionResult["ordering"];
+  full_chunk_size: number;
+  mdat_declared_size: number | null;
+  /** Header first, then the chunks in write order */
+  chunks: PlannedChunk[];
+  gaps: { before: string; missing_chunks: number; bytes: number }[];
+  layout_check: LayoutCheck | null;
+  moov_bytes: number | null;
+  truncated: boolean;
+  expected_size: number;
+  notes: string[];
+}
+
+/** Returned by reconstruct_chunked_webm */
+export interface WebmReconstruction extends ReconstructionResult {
+  clusters: number;
+  /** The final cluster was cut off mid-block and trimmed to whole blocks */
+  truncated_cluster: { offset: number; kept_blocks: number; dropped_bytes: number } | null;
+  /** "voided" when cue points would no longer match the clusters */
+  cues: "kept" | "voided" | "missing";
+}
+
+/** Returned by reconstruct_fragmented_mp4 */
+export interface Fmp4Reconstruction extends ReconstructionResult {
+  fragments: number;
+  /** Sequence numbers absent between the first and last fragment */
+  missing_fragments: number;
+  /** Re-fetched fragments; one copy is kept */
+  duplicate_fragments: number;
+  /** Fragments of another rendition (tracks the init segment lacks) */
+  foreign_fragments: number;
+  cut_off_fragments: number;
+  /** Fragments addressing data by absolute offset; may not play */
+  absolute_offsets: number;
+}
+
+/** Returned by salvage_audio */
+export interface AudioSalvage {
+  /** Sample entry type, e.g. "mp4a" */
+  codec: string;
+  samples: number;
+  /** Audio samples missing from the source, left out */
+  dropped_samples: number;
+  duration_secs: number;
+  dropped_secs: number;
+  bytes_written: number;
+}
+
+/** Returned by reconstruct_dash */
+export interface DashReconstruction extends Fmp4Reconstruction {
+  /** Entries placed by byte range (range=a-b URLs, Content-Range) rather than read as whole segments */
+  range_entries: number;
+  /** Holes between byte ranges */
+  range_gaps: number;
+}
+
+/** Returned by reconstruct_hls */
+export interface HlsReconstruction extends ReconstructionResult {
+  /** Segments written */
+  segments: number;
+  /** Sequence numbers absent between the first and last segment */
+  missing_segments: number;
+  /** Re-fetched segments; one copy is kept */
+  duplicate_segments: number;
+  /** Segments the playlist marks as encrypted; skipped */
+  encrypted_segments: number;
+  cut_off_segments: number;
+  /** Files that were neither a transport stream nor a playlist */
+  unrecognised_files: number;
+  playlists: number;
+  segment_order: "playlist" | "url_number" | "timestamp" | "input";
+}
+
+/** Returned with reconstructions and by check_mp4_coverage */
+export interface SampleCoverage {
+  expected_bytes: number;
+  recovered_bytes: number;
+  total_samples: number;
+  missing_samples: number;
+  /** Missing share of the sample bytes, 0–100 */
+  shortfall_percent: number;
+  /** Share of the video track before its first missing sample, 0–100 */
+  playable_percent: number;
+  tracks: TrackCoverage[];
+}
+
+export interface TrackCoverage {
+  /** hdlr type: "vide", "soun", ... */
+  handler: string;
+  total_samples: number;
+  present_samples: number;
+  /** Samples ending past the end of the file */
+  outside_file: number;
+  /** Samples inside the file but all zeros (padded gaps) */
+  zero_filled: number;
+  present_percent: number;
+  playable_percent: number;
+}
+
+/** Returned by repair_legacy_media (Ogg page checks, AVI idx1 rebuild, MP3 frame trimming) */
+export interface LegacyRepairReport {
+  container: "ogg" | "avi" | "mp3" | "unknown";
+  codec: string | null;
+  action: "none" | "repaired" | "unsupported";
+  bytes_before: number;
+  bytes_after: number;
+  /** Ogg pages kept / AVI movi chunks / MP3 frames kept */
+  units: number;
+  bad_pages: number;
+  /** Ogg pages missing according to page sequence numbers */
+  missing_pages: number;
+  /** From the last granule position (Opus, Vorbis) or the MP3 frames kept */
+  duration_secs: number | null;
+  dropped_bytes: number;
+  index_entries: number;
+  details: string[];
+}
+
+/** Returned by repair_image / repair_png (PNG chunk walk, JPEG marker walk, GIF frames, WebP RIFF size, AVIF boxes) */
+export interface ImageRepairReport {
+  format: "png" | "jpeg" | "gif" | "webp" | "avif" | "unknown";
+  /** IEND / EOI present: the whole image arrived */
+  complete: boolean;
+  action: "none" | "repaired" | "unsupported";
+  bytes_before: number;
+  bytes_after: number;
+  /** PNG chunks with a CRC mismatch (kept) */
+  bad_chunks: number;
+  /** Truncated baseline JPEG: MCUs that decoded whole, out of the image's total */
+  mcus: number | null;
+  total_mcus: number | null;
+  /** The missing MCUs were written as grey */
+  gray_padded: boolean;
+  /** GIF: whole frames kept */
+  frames: number | null;
+  details: string[];
+}
+
+/** One stream of a probe_media result */
+export interface StreamInfo {
+  index: number;
+  kind: string;
+  codec: string | null;
+  width: number | null;
+  height: number | null;
+  frame_rate: number | null;
+  sample_rate: number | null;
+  channels: number | null;
+  duration_secs: number | null;
+}
+
+/** Returned by probe_media (ffprobe sidecar) */
+export interface MediaProbe {
+  path: string;
+  format: string | null;
+  duration_secs: number | null;
+  bit_rate: number | null;
+  streams: StreamInfo[];
+  errors: string[];
+  /** A decodable audio or video stream with a usable size and duration was found */
+  playable: boolean;
+}
+
 // ── Debug / Metadata Dump Types ──────────────────────────────────────────────
 
 /** Tracks why a chunk was associated with a particular resource during scanning. */
@@ -286,3 +717,511 @@ export interface ScanDebugData {
     resourcesWithMetadata: number;
   };
 }
+
+/** Returned by request_write_consent; pass `token` as `consent` to write commands */
+export interface WriteConsent {
+  token: string;
+  /** Normalized folder (or file) the token covers */
+  scope: string;
+  idle_ttl_secs: number;
+}
+
+/** Returned by purge_temp_workspace */
+export interface PurgeReport {
+  entries_removed: number;
+  bytes_removed: number;
+  /** Recent entries left alone because a backend job was running */
+  entries_kept: number;
+  errors: string[];
+}
+
+/** Payload of the "sparse-coverage-changed" event emitted by watch_sparse_entry */
+export interface SparseCoverage {
+  path: string;
+  file_size: number;
+  covered_bytes: number;
+  /** End of the furthest cached range */
+  extent: number;
+  ranges: [number, number][];
+  gaps: [number, number][];
+  /** Set on the final event */
+  finished: "stable" | "removed" | "stopped" | "timeout" | null;
+}
+
+/** Returned by check_encrypted_bodies */
+export interface EncryptedCheck {
+  path: string;
+  /** Shannon entropy of the body sample, bits per byte */
+  entropy: number;
+  chi_square: number;
+  encrypted: boolean;
+}
+
+/** Item of the "backend-crashed" event emitted by the panic hook (batched) */
+export interface CrashReport {
+  message: string;
+  /** "file:line" of the panic */
+  location: string | null;
+  /** The job the backend was running, if known */
+  job: string | null;
+  /** Where the full report (with backtrace) was written */
+  report_path: string | null;
+}
+
+/** Rate limit of one backend event type (list_event_policies / set_event_policy) */
+export interface EventPolicy {
+  max_per_sec: number;
+  /** "latest" keeps the newest item; "batch" sends every item, in arrays. Fixed per event */
+  mode: { kind: "latest" } | { kind: "batch"; max_batch: number };
+}
+
+export interface EventPolicyEntry {
+  event: string;
+  policy: EventPolicy;
+  overridden: boolean;
+}
+
+/** A file on disk matching a recovered attachment's name (find_saved_originals) */
+export interface SavedOriginal {
+  filename: string;
+  path: string;
+  size: number;
+  /** Unix seconds */
+  modified_at: number;
+}
+
+/** Returned by find_saved_originals */
+export interface SavedOriginalsResult {
+  matches: SavedOriginal[];
+  searched_dirs: string[];
+  errors: string[];
+}
+
+/** Returned by scan_drive_for_caches */
+export interface DriveScanResult {
+  root: string;
+  /** HTTP caches found anywhere under root */
+  caches: CachePathInfo[];
+  dirs_visited: number;
+  unreadable: string[];
+}
+
+/** Payload of the "drive-scan-progress" event */
+export interface DriveScanProgress {
+  root: string;
+  dirs_visited: number;
+  caches_found: number;
+  current: string;
+}
+
+/** A backend failure-injection rule (debug builds only). */
+export interface FaultRule {
+  /** Command to match, e.g. "read_sparse_cache_file"; null matches all */
+  command: string | null;
+  /** Substring of the path to match; null matches all */
+  path_contains: string | null;
+  fault: "eperm" | "eacces" | "slow" | "corrupt";
+  /** Delay for "slow" (default 2000 ms) */
+  delay_ms: number | null;
+  /** Fire this many times, then stop; null fires forever */
+  times: number | null;
+}
+
+/** Returned by verify_key_hashes: header key_hash checked against the stored key */
+export interface KeyHashCheck {
+  path: string;
+  status: "ok" | "repaired" | "mismatch" | "not_simple_cache" | "unreadable";
+  stored_hash: number | null;
+  computed_hash: number | null;
+  key_length: number | null;
+  /** Key matched the SHA-256 stored before EOF0; null if the file carries none */
+  key_verified: boolean | null;
+}
+
+/** Returned by export_partial / merge_partial */
+export interface PartialExport {
+  output: string;
+  bytes_written: number;
+  /** Bytes of real data in the output */
+  present_bytes: number;
+  /** Bytes this call added */
+  bytes_added: number;
+  /** Missing byte ranges as [start, end) */
+  missing: [number, number][];
+  /** Source files that could not be read (locked, permission denied) */
+  unreadable: string[];
+  complete: boolean;
+  /** The .cachephoenix-part.json sidecar, if the output is still incomplete */
+  meta_path: string | null;
+}
+
+/** One file's result from verify_crc32 */
+export interface CrcCheck {
+  path: string;
+  status: "ok" | "mismatch" | "unchecked" | "not_simple_cache" | "unreadable";
+  streams_checked: number;
+  /** Streams/ranges whose CRC didn't match, e.g. "stream 1", "range @ 1048576" */
+  failed: string[];
+  bytes_checked: number;
+}
+
+/** Returned by verify_crc32 */
+export interface CrcReport {
+  /** The CPU has the instructions the hardware CRC path uses */
+  hardware: boolean;
+  files: CrcCheck[];
+  bytes_checked: number;
+  elapsed_ms: number;
+}
+
+/** Returned by read_entry: one Simple Cache entry assembled from its _0/_1/_s files */
+export interface EntryBody {
+  files: string[];
+  /** Status line and headers from stream 0 */
+  headers: string[];
+  content_type: string | null;
+  body_source: "stream1" | "stream2" | "sparse" | null;
+  body: number[];
+}
+
+/** A scan ignore rule (list_ignore_rules / set_ignore_rules); a file matching every set condition is skipped */
+export interface IgnoreRule {
+  /** Only apply inside this folder; null applies to every source */
+  source: string | null;
+  /** File-name glob with * and ?, e.g. "*_0" */
+  pattern: string | null;
+  smaller_than: number | null;
+  larger_than: number | null;
+  older_than_days: number | null;
+  kind: "simple_cache" | "sparse" | "blockfile" | null;
+}
+
+/** Returned by group_sparse_entries / merge_sparse_entries */
+export interface SparseGroup {
+  key: string;
+  /** The group's _s files, oldest first */
+  files: string[];
+  /** Present byte ranges of the merged body as [start, end) */
+  ranges: [number, number][];
+  size: number;
+  covered_bytes: number;
+  overlaps: { ranges: number; bytes: number; conflicting_bytes: number };
+}
+
+/** Stream 0 of a Simple Cache entry, parsed (read_entry_headers) */
+export interface ResponseInfo {
+  /** "pickle" for a serialized HttpResponseInfo, "raw" for a bare header block */
+  format: "pickle" | "raw";
+  version: number | null;
+  flags: number;
+  extra_flags: number;
+  truncated: boolean;
+  was_fetched_via_spdy: boolean;
+  was_alpn_negotiated: boolean;
+  was_fetched_via_proxy: boolean;
+  status_line: string | null;
+  status_code: number | null;
+  /** In stored order, duplicates kept */
+  headers: { name: string; value: string }[];
+  /** Unix seconds */
+  request_time: number | null;
+  response_time: number | null;
+  original_response_time: number | null;
+  certificate: {
+    chain_length: number;
+    leaf_sha256: string;
+    leaf_bytes: number;
+    cert_status: number | null;
+  } | null;
+}
+
+/** Media category of a MIME type (classify_mime) */
+export interface MimeClass {
+  /** The type the category was taken from, parameters stripped */
+  mime: string | null;
+  category: "video" | "image" | "audio" | "document" | "other";
+  source: "override" | "mime" | "sniffed" | "unknown";
+}
+
+/** User-set category for a MIME type or top-level type ("model/") */
+export interface MimeOverride {
+  pattern: string;
+  category: MimeClass["category"];
+}
+
+/** Files removed by end_output_job / clean_job_outputs */
+export interface JobCleanup {
+  job_id: string;
+  removed: string[];
+  /** "path: error" for files that could not be removed */
+  failed: string[];
+  /** Files a successful job finished writing (for export_to_target) */
+  kept: string[];
+}
+
+/** A device or share recovered files can be sent to (list_export_targets) */
+export interface ExportTargetInfo {
+  /** Passed back to export_to_target */
+  id: string;
+  label: string;
+  kind: "local" | "mtp" | "smb" | "sftp";
+}
+
+/** Result of sending one file with export_to_target */
+export interface ExportFileResult {
+  source: string;
+  ok: boolean;
+  bytes: number;
+  error: string | null;
+}
+
+/** Returned by export_zip */
+export interface ArchiveExportResult {
+  output: string;
+  files: number;
+  /** Total size of the source files */
+  bytes_in: number;
+  /** Size of the finished archive */
+  bytes_written: number;
+  encryption: "none" | "aes256";
+  /** Sources that could not be added, with the reason */
+  errors: string[];
+}
+
+/** One Simple Cache entry: the _0/_1/_s files sharing a key (group_cache_entries) */
+export interface CacheEntryGroup {
+  /** Stored key; "#<hash>" when no file's key was readable */
+  key: string;
+  url: string;
+  /** Top frame and frame site of a double-keyed key; empty otherwise */
+  isolation_sites: string[];
+  hash: string;
+  stream0_file: string | null;
+  stream2_file: string | null;
+  sparse_file: string | null;
+  total_size: number;
+  modified_at: number;
+  content_type: string | null;
+  http_status: number | null;
+  content_length: number | null;
+  /** Content-Disposition filename */
+  original_filename: string | null;
+  /** Where the body is */
+  body_source: "stream1" | "stream2" | "sparse" | null;
+  body_file: string | null;
+  /** Upper bound for sparse bodies */
+  body_size: number;
+}
+
+/** Where recover_entry takes the output name from; each falls through to the next */
+export type NamingPolicy = "content_disposition" | "url" | "fallback";
+
+export interface RecoveredEntry {
+  path: string;
+  name: string;
+  name_source: NamingPolicy;
+  bytes: number;
+}
+
+/** One file's result from audit_cache */
+export interface AuditFile {
+  path: string;
+  status: "healthy" | "truncated" | "corrupt" | "locked" | "unreadable" | "skipped";
+  /** What failed, e.g. "EOF0 record missing", "CRC mismatch in stream 1" */
+  problems: string[];
+  size: number;
+}
+
+/** Returned by audit_cache; `files` is empty unless details were requested */
+export interface AuditReport {
+  dir: string;
+  total: number;
+  healthy: number;
+  truncated: number;
+  corrupt: number;
+  locked: number;
+  unreadable: number;
+  skipped: number;
+  bytes_read: number;
+  elapsed_ms: number;
+  files: AuditFile[];
+}
+
+/** Outputs from different cache entries that share a name (ignoring " (2)" suffixes) */
+export interface NameCollision {
+  name: string;
+  output_paths: string[];
+  cache_paths: string[];
+}
+
+/** Returned by finish_export_mapping */
+/** Per-file outcome of read_content_types; exactly one field is set. */
+export interface ContentTypeEntry {
+  /** Lowercase MIME type without parameters */
+  content_type: string | null;
+  error: string | null;
+}
+
+export interface MappingReport {
+  /** Where mapping.csv was written; null if there was nothing to write */
+  path: string | null;
+  rows: number;
+  added: number;
+  dropped: number;
+  collisions: NameCollision[];
+  /** Outputs with identical contents, grouped; relative to the folder */
+  duplicates: string[][];
+}
+
+// ── Deleted entries (scan_deleted_entries) ────────────────────────────────
+
+export interface DeletedEntry {
+  source: "todelete_file" | "unindexed" | "orphaned_entry";
+  /** The cache file; for an orphaned entry, the data_N file holding its EntryStore */
+  path: string;
+  /** Orphaned blockfile entries only, e.g. "0xA0010003" */
+  entry_address: string | null;
+  url: string | null;
+  /** Simple Cache files: the stream file it was */
+  stream_file: "_0" | "_1" | "_s" | null;
+  /** Orphaned entries only */
+  state: "normal" | "evicted" | "doomed" | null;
+  body_size: number;
+  /** Sniffed from the body, else the stored Content-Type */
+  mime_type: string | null;
+  /** Unix seconds */
+  time: number | null;
+  /** The body's storage now belongs to a live entry; the data may be another resource's */
+  body_reused: boolean;
+}
+
+export interface DeletedEntryScan {
+  dir: string;
+  entries: DeletedEntry[];
+  errors: string[];
+}
+
+// ── Disk-image carving (carve_disk_image) ─────────────────────────────────
+
+export interface CarvedItem {
+  kind: "simple_entry" | "stream2_entry" | "sparse_entry" | "mp4";
+  /** Byte offset in the image */
+  offset: number;
+  length: number;
+  url: string | null;
+  output: string;
+}
+
+/** Payload of the `carve-progress` event */
+export interface CarveProgress {
+  image: string;
+  bytes_scanned: number;
+  total_bytes: number;
+  found: number;
+}
+
+export interface CarveReport {
+  image: string;
+  /** Reads as a Simple Cache folder; add it as a cache path to scan the carved entries */
+  output_dir: string;
+  bytes_scanned: number;
+  items: CarvedItem[];
+  /** Entries whose key was already carved (another copy of the same file) */
+  duplicates: number;
+  errors: string[];
+  elapsed_ms: number;
+}
+
+// ── Scan sessions (open_scan_session / group_entries) ─────────────────────
+
+export interface CatalogEntry {
+  id: number;
+  cache_dir: string;
+  url: string;
+  /** Lowercase MIME type, parameters stripped */
+  content_type: string | null;
+  size: number;
+  /** Unix seconds: response time (blockfile) or newest file mtime (Simple Cache) */
+  time: number | null;
+  /** Simple Cache file the body is read from; null for blockfile entries */
+  body_file: string | null;
+  /** Discord CDN identity shared by every copy of one object */
+  group_key: string | null;
+  channel_id: string | null;
+  original_filename: string | null;
+}
+
+export interface SessionSummary {
+  session: string;
+  dirs: string[];
+  entries: number;
+  total_size: number;
+  errors: string[];
+}
+
+export type GroupingStrategy = "attachment" | "host" | "day" | "type";
+
+export interface GroupNode {
+  key: string;
+  label: string;
+  count: number;
+  total_size: number;
+  /** Newest entry time in the group, unix seconds */
+  latest: number | null;
+  children: GroupNode[];
+  /** Leaf nodes only; fetch the rows with session_entries */
+  entry_ids: number[];
+}
+
+export interface EntryGrouping {
+  session: string;
+  strategy: GroupingStrategy;
+  count: number;
+  total_size: number;
+  groups: GroupNode[];
+}
+
+// ── Stall watchdog (operation-stalled) ──
+
+/** An operation that hasn't made progress for the stall timeout */
+export interface StalledOperation {
+  id: number;
+  job: string | null;
+  kind: "io" | "sidecar";
+  /** The file being read, or the file a sidecar is working on */
+  path: string;
+  pid: number | null;
+  bytes: number;
+  stalled_for_secs: number;
+  running_for_secs: number;
+}
+
+// ── Forward compatibility (compat_fingerprints) ──
+
+/** Structural fields of one file with an unrecognised layout; never content */
+export interface CompatFingerprint {
+  file_size: number;
+  fields: Record<string, number>;
+}
+
+export interface CompatObservation {
+  format: "simple_entry" | "simple_index" | "blockfile_index";
+  /** What was unexpected, e.g. "header version 6" */
+  anomaly: string;
+  count: number;
+  samples: CompatFingerprint[];
+}
+
+// ── Moov rebuild from a reference file (rebuild_moov) ──
+
+export interface MoovRebuild {
+  codec: "h264" | "h265";
+  samples: number;
+  sync_samples: number;
+  /** mdat bytes that weren't video: interleaved audio, zero-filled gaps, damage */
+  skipped_bytes: number;
+  /** Handlers of reference tracks left out, e.g. "soun" */
+  dropped_tracks: string[];
+  duration_secs: number;
+  bytes_written: number;
+}